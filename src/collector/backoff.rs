@@ -0,0 +1,382 @@
+//! Backing off from failing repositories.
+//!
+//! This is a private module. Its types are used by the RRDP and rsync
+//! collectors. [`Backoff`] is re-exported so the recorded failures can be
+//! reported outside of a validation run.
+
+use std::{fs, io};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use chrono::{DateTime, TimeZone, Utc};
+use log::{debug, info, warn};
+use rand::Rng;
+use crate::config::Config;
+use crate::metrics::{FailingRepository, FailureReason};
+use crate::utils::sync::Mutex;
+
+
+//------------ Backoff -------------------------------------------------------
+
+/// Tracking of failing repositories across validation runs.
+///
+/// For every repository whose last update failed, the number of
+/// consecutive failures, the time of the first of them, and the reason of
+/// the last one is kept. If backoff is enabled, a failing repository is not
+/// tried again before a delay has passed that doubles with every further
+/// failure up to a maximum. A random jitter of up to half the delay is
+/// subtracted so that repositories that failed together aren’t all retried
+/// together. This applies to updates during validation runs as well as to
+/// the heartbeat and repository probes between them.
+///
+/// Repositories that have been failing for longer than a configured time
+/// are considered dead. They are logged when they die and reported in the
+/// metrics.
+///
+/// Repositories are identified by a URI given as a string. For RRDP, this
+/// is the rpkiNotify URI, for rsync the URI of the module.
+///
+/// The state is kept in the file `backoff` in the cache directory. Each
+/// line contains the number of failures, the Unix time of the first
+/// failure, the Unix time before which the repository is not tried again,
+/// the code of the failure reason, and the URI, separated by white space.
+/// Lines without a reason are accepted, too.
+#[derive(Debug)]
+pub struct Backoff {
+    /// The path of the file to keep the state in.
+    path: PathBuf,
+
+    /// The delay after the first failure if backoff is enabled.
+    initial: Option<Duration>,
+
+    /// The maximum delay.
+    max: Duration,
+
+    /// The time a repository needs to fail to be considered dead.
+    dead_age: Duration,
+
+    /// The state of the failing repositories.
+    repositories: Mutex<HashMap<String, FailureState>>,
+}
+
+impl Backoff {
+    /// The name of the file in the cache directory holding the state.
+    const FILE_NAME: &'static str = "backoff";
+
+    /// Creates a new value, loading the state from the last run.
+    ///
+    /// If the state cannot be loaded or the config asks for a fresh start,
+    /// starts out without any failing repositories.
+    pub fn new(config: &Config) -> Self {
+        let path = config.cache_dir.join(Self::FILE_NAME);
+        let repositories = if config.fresh {
+            HashMap::new()
+        }
+        else {
+            Self::load(&path)
+        };
+        Backoff {
+            path,
+            initial: config.repository_backoff,
+            max: config.repository_backoff_max,
+            dead_age: config.dead_repository_age,
+            repositories: Mutex::new(repositories),
+        }
+    }
+
+    /// Loads the state from the file at `path`.
+    fn load(path: &PathBuf) -> HashMap<String, FailureState> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) => {
+                if err.kind() != io::ErrorKind::NotFound {
+                    warn!(
+                        "Failed to read repository backoff state {}: {}",
+                        path.display(), err
+                    );
+                }
+                return HashMap::new()
+            }
+        };
+        let mut res = HashMap::new();
+        for line in content.lines() {
+            match FailureState::parse_line(line) {
+                Some((uri, state)) => {
+                    res.insert(uri.into(), state);
+                }
+                None => {
+                    warn!(
+                        "Ignoring invalid line '{}' in repository backoff \
+                         state {}.",
+                        line, path.display()
+                    );
+                }
+            }
+        }
+        res
+    }
+
+    /// Saves the state for the next run.
+    ///
+    /// As the state is informational only, errors are logged but otherwise
+    /// ignored.
+    pub fn save(&self) {
+        let mut content = String::new();
+        for (uri, state) in self.repositories.lock().iter() {
+            content.push_str(&state.to_line(uri));
+        }
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+        if let Err(err) = fs::write(&tmp_path, content).and_then(|_| {
+            fs::rename(&tmp_path, &self.path)
+        }) {
+            warn!(
+                "Failed to write repository backoff state {}: {}",
+                self.path.display(), err
+            );
+        }
+    }
+
+    /// Returns whether the repository should not be tried at this time.
+    pub fn is_backing_off(&self, uri: &str) -> bool {
+        if self.initial.is_none() {
+            return false
+        }
+        let repositories = self.repositories.lock();
+        let state = match repositories.get(uri) {
+            Some(state) => state,
+            None => return false,
+        };
+        if state.next_attempt <= Utc::now().timestamp() {
+            return false
+        }
+        debug!(
+            "{}: Skipping update after {} failures until {}.",
+            uri, state.failures,
+            timestamp(state.next_attempt).map(|time| {
+                time.to_rfc3339()
+            }).unwrap_or_default()
+        );
+        true
+    }
+
+    /// Allows the repository to be tried again right away.
+    ///
+    /// This is used when an update of the repository was explicitly
+    /// requested. The recorded failures are kept so that a repository
+    /// that is still failing isn’t considered to have recovered.
+    pub fn retry_now(&self, uri: &str) {
+        if let Some(state) = self.repositories.lock().get_mut(uri) {
+            state.next_attempt = 0;
+        }
+    }
+
+    /// Records a successful update of a repository.
+    pub fn success(&self, uri: &str) {
+        let state = match self.repositories.lock().remove(uri) {
+            Some(state) => state,
+            None => return,
+        };
+        if state.is_dead(Utc::now().timestamp(), self.dead_age) {
+            info!(
+                "{}: Repository is available again after {} failures.",
+                uri, state.failures
+            );
+        }
+    }
+
+    /// Records a failed update of a repository.
+    pub fn failure(&self, uri: &str, reason: FailureReason) {
+        let now = Utc::now().timestamp();
+        let mut repositories = self.repositories.lock();
+        let state = repositories.entry(uri.into()).or_insert(
+            FailureState {
+                failures: 0, since: now, next_attempt: now, reason
+            }
+        );
+        let was_dead = state.is_dead(now, self.dead_age);
+        state.failures = state.failures.saturating_add(1);
+        state.reason = reason;
+        if let Some(delay) = self.delay(state.failures) {
+            state.next_attempt = now.saturating_add(
+                i64::try_from(delay.as_secs()).unwrap_or(i64::MAX)
+            );
+        }
+        if !was_dead && state.is_dead(now, self.dead_age) {
+            warn!(
+                "{}: Repository has been failing since {}. \
+                 Considering it dead.",
+                uri,
+                timestamp(state.since).map(|time| {
+                    time.to_rfc3339()
+                }).unwrap_or_default()
+            );
+        }
+    }
+
+    /// Returns the delay after the given number of consecutive failures.
+    ///
+    /// Returns `None` if backoff is disabled.
+    fn delay(&self, failures: u32) -> Option<Duration> {
+        let delay = self.initial?.saturating_mul(
+            2u32.saturating_pow(failures.saturating_sub(1))
+        ).min(self.max);
+        if delay.is_zero() {
+            return Some(delay)
+        }
+        Some(rand::thread_rng().gen_range(delay / 2..=delay))
+    }
+
+    /// Returns the repositories whose last update failed.
+    ///
+    /// The list is ordered by URI.
+    pub fn failing(&self) -> Vec<FailingRepository> {
+        let now = Utc::now().timestamp();
+        let mut res: Vec<_> = self.repositories.lock().iter().filter_map(
+            |(uri, state)| {
+                Some(FailingRepository {
+                    uri: uri.clone(),
+                    since: timestamp(state.since)?,
+                    failures: state.failures,
+                    reason: state.reason,
+                    dead: state.is_dead(now, self.dead_age),
+                })
+            }
+        ).collect();
+        res.sort_by(|left, right| left.uri.cmp(&right.uri));
+        res
+    }
+}
+
+
+//------------ FailureState --------------------------------------------------
+
+/// The state of a failing repository.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct FailureState {
+    /// The number of consecutive failed updates.
+    failures: u32,
+
+    /// Unix timestamp of the first of these failures.
+    since: i64,
+
+    /// Unix timestamp before which no further update is attempted.
+    next_attempt: i64,
+
+    /// The reason the last update failed.
+    reason: FailureReason,
+}
+
+impl FailureState {
+    /// Parses a line of the state file into the URI and the state.
+    fn parse_line(line: &str) -> Option<(&str, Self)> {
+        let mut items = line.split_whitespace();
+        let failures = items.next()?.parse().ok()?;
+        let since = items.next()?.parse().ok()?;
+        let next_attempt = items.next()?.parse().ok()?;
+        let (reason, uri) = match (items.next()?, items.next()) {
+            (uri, None) => (FailureReason::Unknown, uri),
+            (reason, Some(uri)) => (FailureReason::from_code(reason)?, uri),
+        };
+        if items.next().is_some() {
+            return None
+        }
+        Some((uri, FailureState { failures, since, next_attempt, reason }))
+    }
+
+    /// Returns the line of the state file for this state.
+    fn to_line(self, uri: &str) -> String {
+        format!(
+            "{} {} {} {} {}\n",
+            self.failures, self.since, self.next_attempt, self.reason.code(),
+            uri
+        )
+    }
+
+    /// Returns whether the repository is to be considered dead.
+    fn is_dead(self, now: i64, dead_age: Duration) -> bool {
+        now.saturating_sub(self.since)
+            >= i64::try_from(dead_age.as_secs()).unwrap_or(i64::MAX)
+    }
+}
+
+
+//------------ Helper Functions ----------------------------------------------
+
+/// Converts a Unix timestamp into a date-time.
+fn timestamp(ts: i64) -> Option<DateTime<Utc>> {
+    Utc.timestamp_opt(ts, 0).single()
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn failure_state_lines() {
+        let state = FailureState {
+            failures: 3, since: 1000, next_attempt: 2000,
+            reason: FailureReason::Timeout,
+        };
+        let line = state.to_line("https://rrdp.example.net/notify.xml");
+        assert_eq!(
+            FailureState::parse_line(line.trim_end()),
+            Some(("https://rrdp.example.net/notify.xml", state))
+        );
+        assert_eq!(
+            FailureState::parse_line("3 1000 2000 uri"),
+            Some(("uri", FailureState {
+                failures: 3, since: 1000, next_attempt: 2000,
+                reason: FailureReason::Unknown,
+            }))
+        );
+        assert_eq!(FailureState::parse_line("3 1000 2000"), None);
+        assert_eq!(FailureState::parse_line("3 1000 x uri"), None);
+        assert_eq!(FailureState::parse_line("3 1000 2000 foo uri"), None);
+    }
+
+    #[test]
+    fn backoff() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default_with_paths(
+            Default::default(), dir.path().into()
+        );
+        config.repository_backoff = Some(Duration::from_secs(600));
+        config.repository_backoff_max = Duration::from_secs(1800);
+        config.dead_repository_age = Duration::from_secs(0);
+        let uri = "rsync://rpki.example.net/repo/";
+
+        let backoff = Backoff::new(&config);
+        assert!(!backoff.is_backing_off(uri));
+        backoff.failure(uri, FailureReason::Connection);
+        assert!(backoff.is_backing_off(uri));
+        for _ in 0..10 {
+            backoff.failure(uri, FailureReason::Rsync);
+        }
+        for failures in 1..40 {
+            let delay = backoff.delay(failures).unwrap().as_secs();
+            let max = (600u64 << (failures - 1).min(20)).min(1800);
+            assert!((max / 2..=max).contains(&delay));
+        }
+        let failing = backoff.failing();
+        assert_eq!(failing.len(), 1);
+        assert_eq!(failing[0].failures, 11);
+        assert_eq!(failing[0].reason, FailureReason::Rsync);
+        assert!(failing[0].dead);
+
+        // The state survives a restart.
+        backoff.save();
+        let backoff = Backoff::new(&config);
+        assert!(backoff.is_backing_off(uri));
+        backoff.retry_now(uri);
+        assert!(!backoff.is_backing_off(uri));
+        assert_eq!(backoff.failing()[0].failures, 11);
+        backoff.success(uri);
+        assert!(!backoff.is_backing_off(uri));
+        assert!(backoff.failing().is_empty());
+    }
+}
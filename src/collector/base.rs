@@ -2,18 +2,31 @@
 //!
 //! This is a private module. It’s types are re-exported by the parent.
 
-use std::collections::HashSet;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::path::Path;
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 use bytes::Bytes;
-use log::info;
+use chrono::Utc;
+use log::{error, info};
 use rpki::repository::tal::TalUri;
 use rpki::uri;
-use crate::config::{Config, FallbackPolicy};
+use crate::config::{Config, FallbackPolicy, TransportPolicy};
 use crate::error::{Failed, Fatal, RunFailed};
-use crate::metrics::Metrics;
+use crate::metrics::{
+    CacheMetrics, CleanupPreview, Metrics, RepositoryProbe, UpdatePreview
+};
 use crate::engine::CaCert;
+use crate::utils::sync::{HostLimit, Mutex};
 use super::{rrdp, rsync};
+use super::backoff::Backoff;
+use super::cache::Cache;
+use super::checkpoint::Checkpoint;
+use super::fetchlog::FetchLog;
+use super::schedule::Schedule;
 
 
 //------------ Collector -----------------------------------------------------
@@ -42,6 +55,36 @@ pub struct Collector {
 
     /// The policy for falling back from RRDP to rsync.
     rrdp_fallback: FallbackPolicy,
+
+    /// Per-repository overrides of the transport to use.
+    ///
+    /// The keys are rpkiNotify URIs or host names.
+    transport_overrides: HashMap<String, TransportPolicy>,
+
+    /// The tracker for failing repositories.
+    ///
+    /// This is `None` for an offline collector.
+    backoff: Option<Arc<Backoff>>,
+
+    /// The accounting for the size of the collected data.
+    ///
+    /// This is `None` for an offline collector.
+    cache: Option<Cache>,
+
+    /// The schedule for spreading repository updates.
+    ///
+    /// This is `None` if all repositories are updated in every run.
+    schedule: Option<Schedule>,
+
+    /// The progress of the current validation run.
+    ///
+    /// This is `None` if no checkpoints are kept.
+    checkpoint: Option<Checkpoint>,
+
+    /// The log of fetch attempts.
+    ///
+    /// This is `None` if no fetch log is configured.
+    fetch_log: Option<FetchLog>,
 }
 
 impl Collector {
@@ -62,15 +105,78 @@ impl Collector {
     /// Takes all necessary information from `config`.
     pub fn new(
         config: &Config,
+    ) -> Result<Self, Failed> {
+        Self::init(config)?;
+        let host_limit = config.max_host_connections.map(|limit| {
+            Arc::new(HostLimit::new(limit))
+        });
+        let backoff = Arc::new(Backoff::new(config));
+        Ok(Collector {
+            rrdp: rrdp::Collector::new(
+                config, host_limit.clone(), Some(backoff.clone())
+            )?,
+            rsync: rsync::Collector::new(
+                config, host_limit, Some(backoff.clone())
+            )?,
+            rrdp_fallback: config.rrdp_fallback,
+            transport_overrides: config.transport_overrides.clone(),
+            backoff: Some(backoff),
+            cache: Some(Cache::new(config)),
+            schedule: Schedule::new(config),
+            checkpoint: Checkpoint::new(config),
+            fetch_log: FetchLog::new(config),
+        })
+    }
+
+    /// Creates a new collector that doesn’t access the network.
+    ///
+    /// RRDP will be disabled and rsync will never actually be run. Instead,
+    /// the data present in the rsync working directory is used as is.
+    pub fn new_offline(
+        config: &Config,
     ) -> Result<Self, Failed> {
         Self::init(config)?;
         Ok(Collector {
-            rrdp: rrdp::Collector::new(config)?,
-            rsync: rsync::Collector::new(config)?,
+            rrdp: None,
+            rsync: Some(rsync::Collector::new_offline(config)?),
             rrdp_fallback: config.rrdp_fallback,
+            transport_overrides: HashMap::new(),
+            backoff: None,
+            cache: None,
+            schedule: None,
+            checkpoint: None,
+            fetch_log: None,
         })
     }
 
+    /// Returns the transport policy for the given CA.
+    ///
+    /// An override for the rpkiNotify URI takes precedence over one for
+    /// the host of that URI which in turn takes precedence over one for
+    /// the host of the CA repository’s rsync URI.
+    fn transport_policy(&self, ca: &CaCert) -> TransportPolicy {
+        if let Some(policy) = ca.rpki_notify().and_then(|rpki_notify| {
+            self.notify_policy(rpki_notify)
+        }) {
+            return policy
+        }
+        self.transport_overrides.get(
+            ca.ca_repository().authority()
+        ).copied().unwrap_or(TransportPolicy::RrdpFirst)
+    }
+
+    /// Returns the transport policy override for an rpkiNotify URI.
+    ///
+    /// Returns `None` if there is no override for either the URI itself or
+    /// its host.
+    fn notify_policy(
+        &self, rpki_notify: &uri::Https
+    ) -> Option<TransportPolicy> {
+        self.transport_overrides.get(rpki_notify.as_str()).or_else(|| {
+            self.transport_overrides.get(rpki_notify.authority())
+        }).copied()
+    }
+
     /// Ignites the collector.
     ///
     /// This needs to be done after a possible fork as the collector may spawn
@@ -92,9 +198,104 @@ impl Collector {
         Ok(())
     }
 
+    /// Checks whether any of the known RRDP repositories has changed.
+    ///
+    /// Repositories that are configured to only use rsync are skipped.
+    /// Returns the rpkiNotify URI of the first repository that was found
+    /// to have a new serial or `None` if there are no changes or RRDP is
+    /// disabled.
+    fn heartbeat(&self) -> Option<uri::Https> {
+        self.rrdp.as_ref()?.heartbeat(|rpki_notify| {
+            self.notify_policy(rpki_notify) != Some(TransportPolicy::Rsync)
+        })
+    }
+
+    /// Probes whether the locally known repositories are reachable.
+    ///
+    /// Returns the results for all RRDP repositories and rsync servers
+    /// present locally. Each probe may take at most `timeout`.
+    pub fn probe(
+        &self, timeout: Duration
+    ) -> Result<Vec<RepositoryProbe>, Fatal> {
+        let mut res = Vec::new();
+        if let Some(rrdp) = self.rrdp.as_ref() {
+            res.extend(rrdp.probe(timeout)?);
+        }
+        if let Some(rsync) = self.rsync.as_ref() {
+            res.extend(rsync.probe(timeout)?);
+        }
+        Ok(res)
+    }
+
+    /// Determines what updating the locally known repositories would do.
+    ///
+    /// Returns the previews for all RRDP repositories and rsync modules
+    /// present locally, ordered by URI.
+    pub fn preview(&self) -> Result<Vec<UpdatePreview>, Fatal> {
+        let mut res = Vec::new();
+        if let Some(rrdp) = self.rrdp.as_ref() {
+            res.extend(rrdp.preview()?);
+        }
+        if let Some(rsync) = self.rsync.as_ref() {
+            res.extend(rsync.preview()?);
+        }
+        res.sort_by(|left, right| left.uri.cmp(&right.uri));
+        Ok(res)
+    }
+
+    /// Returns information about the locally known RRDP repositories.
+    ///
+    /// Returns an empty list if RRDP is disabled.
+    pub fn rrdp_info(
+        &self
+    ) -> Result<Vec<rrdp::RrdpRepositoryInfo>, Fatal> {
+        match self.rrdp.as_ref() {
+            Some(rrdp) => rrdp.repository_info(self.rrdp_fallback),
+            None => Ok(Vec::new())
+        }
+    }
+
+    /// Returns the local copy of a repository.
+    ///
+    /// The repository is not updated. If `rpki_notify` is given, the copy
+    /// held by the RRDP collector is used, otherwise the rsync working
+    /// directory. Returns `Ok(None)` if the respective transport is
+    /// disabled or, for RRDP, if there is no usable local copy.
+    pub fn local_repository(
+        &self, rpki_notify: Option<&uri::Https>
+    ) -> Result<Option<LocalRepository<'_>>, Fatal> {
+        match rpki_notify {
+            Some(rpki_notify) => {
+                let rrdp = match self.rrdp.as_ref() {
+                    Some(rrdp) => rrdp,
+                    None => return Ok(None)
+                };
+                Ok(rrdp.read_local(rpki_notify)?.map(|repository| {
+                    LocalRepository(LocalInner::Rrdp(repository))
+                }))
+            }
+            None => {
+                Ok(self.rsync.as_ref().map(|rsync| {
+                    LocalRepository(LocalInner::Rsync(rsync))
+                }))
+            }
+        }
+    }
+
     /// Starts a new validation run using this collector.
     pub fn start(&self) -> Run {
-        Run::new(self)
+        Run::new(self, None)
+    }
+
+    /// Starts a validation run that only updates a single repository.
+    ///
+    /// Any backoff for the repository is lifted so that it is tried even
+    /// if it has failed recently.
+    pub fn start_refresh(&self, target: &RefreshTarget) -> Run<'_> {
+        if let Some(backoff) = self.backoff.as_ref() {
+            backoff.retry_now(&target.key());
+        }
+        Run::new(self, Some(target.clone()))
     }
 
     /// Dumps the content of the collector and store owned by the engine.
@@ -107,6 +308,55 @@ impl Collector {
         }
         Ok(())
     }
+
+    /// Imports rsync modules from the given directory.
+    ///
+    /// See [`rsync::Collector::import`] for the expected layout. Returns
+    /// the number of imported modules. Fails if rsync is disabled.
+    pub fn import_rsync(&self, source: &Path) -> Result<usize, Failed> {
+        match self.rsync.as_ref() {
+            Some(rsync) => rsync.import(source),
+            None => {
+                error!("Cannot import rsync data: rsync is disabled.");
+                Err(Failed)
+            }
+        }
+    }
+}
+
+
+
+//------------ Heartbeat -----------------------------------------------------
+
+/// A check of the known RRDP repositories for changes.
+///
+/// A heartbeat only fetches the notification files of the RRDP
+/// repositories that have been seen before. It honours the transport
+/// overrides, the repository backoff, and the limit of concurrent
+/// connections per host of the collector it was created from. Since it
+/// keeps a reference to that collector, it can be moved to a separate
+/// thread and run alongside other work.
+#[derive(Clone, Debug)]
+pub struct Heartbeat {
+    /// The collector to check.
+    collector: Arc<Collector>,
+}
+
+impl Heartbeat {
+    /// Creates a new heartbeat for the given collector.
+    pub fn new(collector: Arc<Collector>) -> Self {
+        Heartbeat { collector }
+    }
+
+    /// Checks whether any of the known RRDP repositories has changed.
+    ///
+    /// Returns the rpkiNotify URI of the first repository that was found
+    /// to have a new serial or `None` if there are no changes. Failures
+    /// are logged and otherwise ignored as they will be dealt with during
+    /// the next validation run.
+    pub fn check(&self) -> Option<uri::Https> {
+        self.collector.heartbeat()
+    }
 }
 
 
@@ -131,15 +381,77 @@ pub struct Run<'a> {
 
     /// The runner for RRDP if this transport is enabled.
     rrdp: Option<rrdp::Run<'a>>,
+
+    /// The size of the collected data after cleanup.
+    cache: Mutex<Option<CacheMetrics>>,
+
+    /// The only repository to update if this is a refresh run.
+    target: Option<RefreshTarget>,
+
+    /// The Unix time the run was started.
+    started: i64,
+
+    /// The repositories already updated by an interrupted run.
+    ///
+    /// These are not updated again in this run.
+    resumed: HashSet<String>,
 }
 
 impl<'a> Run<'a> {
     /// Creates a new validation run for the given collector.
-    fn new(collector: &'a Collector) -> Self {
+    fn new(collector: &'a Collector, target: Option<RefreshTarget>) -> Self {
+        let started = Utc::now().timestamp();
+        let resumed = match collector.checkpoint.as_ref() {
+            Some(checkpoint) if target.is_none() => {
+                checkpoint.start(started)
+            }
+            _ => HashSet::new(),
+        };
         Run {
             collector,
             rsync: collector.rsync.as_ref().map(|rsync| rsync.start()),
             rrdp: collector.rrdp.as_ref().map(|rrdp| rrdp.start()),
+            cache: Default::default(),
+            target,
+            started,
+            resumed,
+        }
+    }
+
+    /// Returns whether the run only updates a single repository.
+    pub fn is_refresh(&self) -> bool {
+        self.target.is_some()
+    }
+
+    /// Returns the URI identifying the repository of the CA.
+    ///
+    /// This is the rpkiNotify URI for RRDP and the module URI for rsync.
+    fn repository_key(ca: &CaCert) -> Cow<str> {
+        match ca.rpki_notify() {
+            Some(uri) => Cow::Borrowed(uri.as_str()),
+            None => ca.ca_repository().canonical_module(),
+        }
+    }
+
+    /// Returns whether the repository of the CA may be updated.
+    ///
+    /// In a refresh run, this is only the target repository. Otherwise,
+    /// repositories already updated by a resumed run are skipped and, if
+    /// updates are spread, only repositories that are due are updated.
+    fn is_included(&self, ca: &CaCert) -> bool {
+        if let Some(target) = self.target.as_ref() {
+            return target.matches(ca)
+        }
+        if !self.resumed.is_empty()
+            && self.resumed.contains(Self::repository_key(ca).as_ref())
+        {
+            return false
+        }
+        match self.collector.schedule.as_ref() {
+            Some(schedule) => {
+                schedule.is_due(&Self::repository_key(ca), self.started)
+            }
+            None => true
         }
     }
 
@@ -156,6 +468,23 @@ impl<'a> Run<'a> {
         if let Some(rsync) = self.rsync {
             rsync.done(metrics)
         }
+        if let Some(fetch_log) = self.collector.fetch_log.as_ref() {
+            fetch_log.write(metrics);
+        }
+        if let Some(checkpoint) = self.collector.checkpoint.as_ref() {
+            if self.target.is_none() {
+                checkpoint.finish();
+            }
+        }
+        if let Some(backoff) = self.collector.backoff.as_ref() {
+            metrics.failing_repositories = backoff.failing();
+            let dead = metrics.dead_repositories().count();
+            if dead > 0 {
+                info!("{} repositories are considered dead.", dead);
+            }
+            backoff.save();
+        }
+        metrics.cache = self.cache.into_inner();
     }
 
     /// Loads the trust anchor certificate at the given URI.
@@ -167,7 +496,12 @@ impl<'a> Run<'a> {
     /// Trust anchor certificates referenced by a rsync URI will cause that
     /// module to be updated once, whereas those referenced via HTTPS URIs
     /// will be newly downloaded upon each call.
+    ///
+    /// A refresh run never loads trust anchor certificates.
     pub fn load_ta(&self, uri: &TalUri) -> Option<Bytes> {
+        if self.is_refresh() {
+            return None
+        }
         match *uri {
             TalUri::Rsync(ref uri) => {
                 self.rsync.as_ref().and_then(|rsync| {
@@ -188,8 +522,66 @@ impl<'a> Run<'a> {
     ///
     /// If no updated version of the repository is available, returns
     /// `Ok(None)`.
+    ///
+    /// Which transports are tried in what order is determined by the
+    /// transport policy for the CA’s repository.
+    ///
+    /// In a refresh run, returns `Ok(None)` for all CAs whose repository
+    /// isn’t the one to be refreshed. Likewise, if updates are spread,
+    /// returns `Ok(None)` for CAs whose repository isn’t due yet, and if
+    /// an interrupted run is resumed, for CAs whose repository it has
+    /// already updated.
     pub fn repository<'s>(
         &'s self, ca: &'s CaCert
+    ) -> Result<Option<Repository<'s>>, RunFailed> {
+        if !self.is_included(ca) {
+            return Ok(None)
+        }
+        let res = self.load_repository(ca)?;
+        if let Some(checkpoint) = self.collector.checkpoint.as_ref() {
+            if self.target.is_none()
+                && res.as_ref().is_some_and(Repository::is_current)
+            {
+                checkpoint.updated(&Self::repository_key(ca));
+            }
+        }
+        Ok(res)
+    }
+
+    /// Accesses the repository for the CA using its transport policy.
+    fn load_repository<'s>(
+        &'s self, ca: &'s CaCert
+    ) -> Result<Option<Repository<'s>>, RunFailed> {
+        match self.collector.transport_policy(ca) {
+            TransportPolicy::RrdpFirst => self.rrdp_repository(ca, true),
+            TransportPolicy::Rrdp => self.rrdp_repository(ca, false),
+            TransportPolicy::Rsync => Ok(self.rsync_repository(ca)),
+            TransportPolicy::RsyncFirst => {
+                let repo = match self.rsync_repository(ca) {
+                    Some(repo) if repo.is_current() => return Ok(Some(repo)),
+                    repo => repo,
+                };
+                if ca.rpki_notify().is_some() && self.rrdp.is_some() {
+                    info!(
+                        "{}: rsync update failed, trying RRDP.",
+                        ca.ca_repository()
+                    );
+                    if let Some(rrdp) = self.rrdp_repository(ca, false)? {
+                        return Ok(Some(rrdp))
+                    }
+                }
+                Ok(repo)
+            }
+        }
+    }
+
+    /// Accesses the repository for the provided CA via RRDP.
+    ///
+    /// If the CA doesn’t support RRDP, RRDP is disabled, or the RRDP
+    /// update failed and the fallback policy allows it, rsync is used
+    /// instead if `fallback` is `true`.
+    fn rrdp_repository<'s>(
+        &'s self, ca: &'s CaCert, fallback: bool,
     ) -> Result<Option<Repository<'s>>, RunFailed> {
         // See if we should and can use RRDP
         if let Some(rrdp_uri) = ca.rpki_notify() {
@@ -229,6 +621,9 @@ impl<'a> Run<'a> {
                     }
                 }
 
+                if !fallback {
+                    return Ok(None)
+                }
                 if first && self.rsync.is_some() {
                     info!("RRDP {}: Falling back to rsync.", rrdp_uri);
                 }
@@ -236,17 +631,64 @@ impl<'a> Run<'a> {
         }
 
         // Well, okay, then. How about rsync?
-        if let Some(ref rsync) = self.rsync {
-            rsync.load_module(ca.ca_repository());
-            return Ok(Some(Repository::rsync(rsync)))
+        if fallback {
+            Ok(self.rsync_repository(ca))
+        }
+        else {
+            Ok(None)
         }
+    }
+
+    /// Accesses the repository for the provided CA via rsync.
+    ///
+    /// Returns `None` if rsync is disabled.
+    fn rsync_repository<'s>(
+        &'s self, ca: &'s CaCert
+    ) -> Option<Repository<'s>> {
+        let rsync = self.rsync.as_ref()?;
+        rsync.load_module(ca.ca_repository());
+        Some(Repository::rsync(
+            rsync, rsync.is_current(ca.ca_repository())
+        ))
+    }
 
-        // All is lost.
-        Ok(None)
+    /// Returns whether accessing the repository for the CA would block.
+    ///
+    /// This is the case if the CA’s repository is accessed via rsync
+    /// first, the module hasn’t been updated yet, and the maximum number
+    /// of rsync processes are already running. Repositories that only fall
+    /// back to rsync after RRDP failed are never considered busy.
+    ///
+    /// The result is only a hint that allows postponing the CA in favour of
+    /// others instead of waiting.
+    pub fn is_busy(&self, ca: &CaCert) -> bool {
+        if !self.is_included(ca) {
+            return false
+        }
+        let rsync = match self.rsync.as_ref() {
+            Some(rsync) => rsync,
+            None => return false,
+        };
+        let rsync_first = match self.collector.transport_policy(ca) {
+            TransportPolicy::Rsync | TransportPolicy::RsyncFirst => true,
+            TransportPolicy::Rrdp => false,
+            TransportPolicy::RrdpFirst => {
+                ca.rpki_notify().is_none() || self.rrdp.is_none()
+            }
+        };
+        rsync_first && rsync.is_busy(ca.ca_repository())
     }
 
     /// Returns whether the repository for the PRKI CA has been updated.
     pub fn was_updated(&self, ca: &CaCert) -> bool {
+        if matches!(
+            self.collector.transport_policy(ca),
+            TransportPolicy::Rsync | TransportPolicy::RsyncFirst
+        ) {
+            if let Some(ref rsync) = self.rsync {
+                return rsync.was_updated(ca.ca_repository());
+            }
+        }
         if let Some(rrdp_uri) = ca.rpki_notify() {
             if let Some(ref rrdp) = self.rrdp {
                 return rrdp.was_updated(rrdp_uri);
@@ -261,13 +703,41 @@ impl<'a> Run<'a> {
     /// Cleans the collector.
     ///
     /// Any RRDP repository or rsync module not included in `retain` will
-    /// be deleted.
+    /// be deleted. Afterwards, the size of the remaining data is determined
+    /// and, if it exceeds the configured limit, the least recently
+    /// validated repositories are deleted, too.
+    ///
+    /// If `retain` was created via [`Cleanup::preview`], nothing is
+    /// deleted. Instead, everything that would be deleted is recorded in
+    /// `retain`.
     pub fn cleanup(&self, retain: &mut Cleanup) -> Result<(), Failed> {
         if let Some(rsync) = self.rsync.as_ref() {
-            rsync.cleanup(&mut retain.rsync)?;
+            rsync.cleanup(&mut retain.rsync, retain.preview.as_mut())?;
         }
         if let Some(rrdp) = self.rrdp.as_ref() {
-            rrdp.cleanup(&mut retain.rrdp)?;
+            rrdp.cleanup(&mut retain.rrdp, retain.preview.as_mut())?;
+        }
+        if let Some(cache) = self.collector.cache.as_ref() {
+            let mut entries = Vec::new();
+            if let Some(rsync) = self.rsync.as_ref() {
+                entries.extend(rsync.cache_entries()?);
+            }
+            if let Some(rrdp) = self.rrdp.as_ref() {
+                entries.extend(rrdp.cache_entries()?);
+            }
+            match retain.preview.as_mut() {
+                Some(preview) => {
+                    // Repositories deleted for being unused don’t count.
+                    let removed: HashSet<_> = preview.iter().map(|item| {
+                        item.uri.clone()
+                    }).collect();
+                    entries.retain(|entry| !removed.contains(&entry.uri));
+                    preview.extend(cache.preview(entries));
+                }
+                None => {
+                    *self.cache.lock() = Some(cache.update(entries)?);
+                }
+            }
         }
         Ok(())
     }
@@ -292,6 +762,9 @@ enum RepoInner<'a> {
     Rsync {
         /// The rsync runner.
         rsync: &'a rsync::Run<'a>,
+
+        /// Did the last update of the rsync module succeed?
+        current: bool,
     }
 }
 
@@ -302,9 +775,9 @@ impl<'a> Repository<'a> {
     }
 
     /// Creates an rsync repository.
-    fn rsync(rsync: &'a rsync::Run<'a>) -> Self {
+    fn rsync(rsync: &'a rsync::Run<'a>, current: bool) -> Self {
         Repository(
-            RepoInner::Rsync { rsync }
+            RepoInner::Rsync { rsync, current }
         )
     }
 
@@ -313,6 +786,18 @@ impl<'a> Repository<'a> {
         matches!(self.0, RepoInner::Rrdp { .. })
     }
 
+    /// Returns whether the repository is known to be current.
+    ///
+    /// An RRDP repository is only used if it was updated successfully and
+    /// is therefore always current. An rsync repository is current if the
+    /// last update of its module succeeded.
+    pub fn is_current(&self) -> bool {
+        match self.0 {
+            RepoInner::Rrdp { .. } => true,
+            RepoInner::Rsync { current, .. } => current,
+        }
+    }
+
     /// Loads an object from the repository.
     ///
     /// If the object is unavailable for some reason, logs diagnostic
@@ -324,7 +809,7 @@ impl<'a> Repository<'a> {
             RepoInner::Rrdp { ref repository } => {
                 repository.load_object(uri)
             }
-            RepoInner::Rsync { rsync } => {
+            RepoInner::Rsync { rsync, .. } => {
                 Ok(rsync.load_file(uri))
             }
         }
@@ -332,9 +817,48 @@ impl<'a> Repository<'a> {
 }
 
 
+//------------ LocalRepository -----------------------------------------------
+
+/// Access to the local copy of a repository outside of a validation run.
+///
+/// A value can be acquired via [`Collector::local_repository`].
+#[derive(Debug)]
+pub struct LocalRepository<'a>(LocalInner<'a>);
+
+#[derive(Debug)]
+enum LocalInner<'a> {
+    /// The local copy of an RRDP repository.
+    Rrdp(rrdp::ReadRepository),
+
+    /// The rsync working directory.
+    Rsync(&'a rsync::Collector),
+}
+
+impl<'a> LocalRepository<'a> {
+    /// Loads an object from the local copy.
+    ///
+    /// Returns `Ok(None)` if the object isn’t present.
+    pub fn load_object(
+        &self, uri: &uri::Rsync
+    ) -> Result<Option<Bytes>, Fatal> {
+        match self.0 {
+            LocalInner::Rrdp(ref repository) => {
+                repository.load_object(uri).map_err(|_| Fatal)
+            }
+            LocalInner::Rsync(rsync) => rsync.load_local(uri),
+        }
+    }
+}
+
+
 //------------ Cleanup -------------------------------------------------------
 
 /// A builder-style type for data retained during cleanup.
+///
+/// A value created via [`preview`][Self::preview] turns cleanup into a
+/// dry run: nothing is deleted and what would have been deleted is
+/// collected instead. It can be retrieved via
+/// [`into_preview`][Self::into_preview] afterwards.
 #[derive(Clone, Debug, Default)]
 pub struct Cleanup {
     /// The set of rsync modules to retain.
@@ -342,6 +866,9 @@ pub struct Cleanup {
 
     /// The set of RRDP repositories to retain.
     rrdp: HashSet<uri::Https>,
+
+    /// The data that would be deleted if this is a dry run.
+    preview: Option<Vec<CleanupPreview>>,
 }
 
 impl Cleanup {
@@ -350,6 +877,23 @@ impl Cleanup {
         Default::default()
     }
 
+    /// Creates a new cleanup object for a dry run.
+    pub fn preview() -> Self {
+        Cleanup {
+            preview: Some(Vec::new()),
+            .. Default::default()
+        }
+    }
+
+    /// Returns the data a dry run found would be deleted.
+    ///
+    /// The list is ordered by URI. It is empty if this isn’t a dry run.
+    pub fn into_preview(self) -> Vec<CleanupPreview> {
+        let mut res = self.preview.unwrap_or_default();
+        res.sort_by(|left, right| left.uri.cmp(&right.uri));
+        res
+    }
+
     /// Registers an RRDP repository to be retained in cleanup.
     pub fn add_rrdp_repository(&mut self, rpki_notify: &uri::Https) {
         self.rrdp.insert(rpki_notify.clone());
@@ -361,3 +905,71 @@ impl Cleanup {
     }
 }
 
+
+//------------ RefreshTarget -------------------------------------------------
+
+/// The repository to be updated by a refresh run.
+///
+/// An RRDP repository is given by its rpkiNotify URI. For rsync, any URI
+/// within the module can be given and the whole module is updated.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RefreshTarget {
+    /// An RRDP repository.
+    Rrdp(uri::Https),
+
+    /// An rsync module.
+    Rsync(uri::Rsync),
+}
+
+impl RefreshTarget {
+    /// Returns the key identifying the repository.
+    ///
+    /// This is the rpkiNotify URI for RRDP and the canonical module URI
+    /// for rsync as used for tracking failing repositories.
+    pub fn key(&self) -> Cow<'_, str> {
+        match self {
+            RefreshTarget::Rrdp(uri) => Cow::Borrowed(uri.as_str()),
+            RefreshTarget::Rsync(uri) => uri.canonical_module(),
+        }
+    }
+
+    /// Returns whether the CA’s repository is the target.
+    fn matches(&self, ca: &CaCert) -> bool {
+        match self {
+            RefreshTarget::Rrdp(uri) => ca.rpki_notify() == Some(uri),
+            RefreshTarget::Rsync(uri) => {
+                ca.ca_repository().canonical_module()
+                    == uri.canonical_module()
+            }
+        }
+    }
+}
+
+
+//--- FromStr
+
+impl FromStr for RefreshTarget {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(uri) = uri::Https::from_str(s) {
+            Ok(RefreshTarget::Rrdp(uri))
+        }
+        else if let Ok(uri) = uri::Rsync::from_str(s) {
+            Ok(RefreshTarget::Rsync(uri))
+        }
+        else {
+            Err("expected an rpkiNotify or rsync URI")
+        }
+    }
+}
+
+
+//--- Display
+
+impl fmt::Display for RefreshTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.key())
+    }
+}
+
@@ -0,0 +1,305 @@
+//! Accounting for and limiting the size of the collected data.
+//!
+//! This is a private module. Its types are used by the RRDP and rsync
+//! collectors and are not re-exported.
+
+use std::{cmp, fs, io};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use chrono::Utc;
+use log::{info, warn};
+use crate::config::Config;
+use crate::error::Failed;
+use crate::metrics::{
+    CacheMetrics, CachedRepository, CleanupPreview, CleanupReason,
+};
+use crate::utils::fatal;
+
+
+//------------ Cache ---------------------------------------------------------
+
+/// Tracking of the on-disk size of the collected data.
+///
+/// After the collector has been cleaned up, the RRDP and rsync collectors
+/// report the size of each of their repositories. If the total exceeds the
+/// configured limit, repositories are removed until it doesn’t anymore.
+/// The least recently validated repositories are removed first and among
+/// those validated at the same time the largest ones. A removed repository
+/// is simply fetched anew when it is needed again.
+///
+/// In order to know when a repository was last validated, the time is kept
+/// in the file `cache-usage` in the cache directory. Each line contains
+/// the Unix time and the URI of the repository, separated by white space.
+#[derive(Debug)]
+pub struct Cache {
+    /// The path of the file to keep the usage times in.
+    path: PathBuf,
+
+    /// The maximum size of the collected data.
+    limit: Option<u64>,
+}
+
+impl Cache {
+    /// The name of the file in the cache directory holding the usage times.
+    const FILE_NAME: &'static str = "cache-usage";
+
+    /// Creates a new value from the configuration.
+    pub fn new(config: &Config) -> Self {
+        Cache {
+            path: config.cache_dir.join(Self::FILE_NAME),
+            limit: config.max_cache_size,
+        }
+    }
+
+    /// Accounts for the given repositories and evicts some if necessary.
+    ///
+    /// Returns the metrics describing the remaining and evicted
+    /// repositories.
+    pub fn update(
+        &self, entries: Vec<CacheEntry>
+    ) -> Result<CacheMetrics, Failed> {
+        let (entries, evicted) = self.select(entries);
+        let evicted = evicted.into_iter().map(|entry| {
+            info!(
+                "Removing {} ({} bytes) to stay within the cache \
+                 size limit.",
+                entry.uri, entry.size
+            );
+            fatal::remove_all(&entry.path)?;
+            Ok(entry.into_metrics())
+        }).collect::<Result<Vec<_>, Failed>>()?;
+
+        self.save(&entries);
+        let mut repositories: Vec<_> = entries.into_iter().map(|item| {
+            item.1.into_metrics()
+        }).collect();
+        repositories.sort_by(|left, right| left.uri.cmp(&right.uri));
+        Ok(CacheMetrics { repositories, evicted })
+    }
+
+    /// Returns the repositories that would be evicted.
+    ///
+    /// Nothing is removed and the usage times are left untouched.
+    pub fn preview(&self, entries: Vec<CacheEntry>) -> Vec<CleanupPreview> {
+        self.select(entries).1.into_iter().map(|entry| {
+            CleanupPreview {
+                uri: entry.uri,
+                rrdp: entry.rrdp,
+                size: entry.size,
+                reason: CleanupReason::CacheLimit,
+            }
+        }).collect()
+    }
+
+    /// Splits the entries into those to keep and those to evict.
+    ///
+    /// The entries to keep are returned together with their last usage
+    /// time.
+    fn select(
+        &self, entries: Vec<CacheEntry>
+    ) -> (Vec<(i64, CacheEntry)>, Vec<CacheEntry>) {
+        let now = Utc::now().timestamp();
+        let usage = self.load();
+        let mut entries: Vec<_> = entries.into_iter().map(|entry| {
+            let last_used = if entry.used {
+                now
+            }
+            else {
+                usage.get(&entry.uri).copied().unwrap_or(0)
+            };
+            (last_used, entry)
+        }).collect();
+
+        let limit = match self.limit {
+            Some(limit) => limit,
+            None => return (entries, Vec::new())
+        };
+        let mut total: u64 = entries.iter().map(|item| item.1.size).sum();
+        if total <= limit {
+            return (entries, Vec::new())
+        }
+        entries.sort_by_key(|(last_used, entry)| {
+            (*last_used, cmp::Reverse(entry.size))
+        });
+        let mut remaining = Vec::new();
+        let mut evicted = Vec::new();
+        for (last_used, entry) in entries {
+            if total <= limit {
+                remaining.push((last_used, entry));
+                continue
+            }
+            total = total.saturating_sub(entry.size);
+            evicted.push(entry);
+        }
+        (remaining, evicted)
+    }
+
+    /// Loads the usage times from the file.
+    fn load(&self) -> HashMap<String, i64> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(err) => {
+                if err.kind() != io::ErrorKind::NotFound {
+                    warn!(
+                        "Failed to read cache usage file {}: {}",
+                        self.path.display(), err
+                    );
+                }
+                return HashMap::new()
+            }
+        };
+        content.lines().filter_map(|line| {
+            let (time, uri) = line.split_once(' ')?;
+            Some((uri.into(), time.parse().ok()?))
+        }).collect()
+    }
+
+    /// Saves the usage times of the remaining repositories.
+    ///
+    /// As the usage times are informational only, errors are logged but
+    /// otherwise ignored.
+    fn save(&self, entries: &[(i64, CacheEntry)]) {
+        let mut content = String::new();
+        for (last_used, entry) in entries {
+            content.push_str(&format!("{} {}\n", last_used, entry.uri));
+        }
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+        if let Err(err) = fs::write(&tmp_path, content).and_then(|_| {
+            fs::rename(&tmp_path, &self.path)
+        }) {
+            warn!(
+                "Failed to write cache usage file {}: {}",
+                self.path.display(), err
+            );
+        }
+    }
+}
+
+
+//------------ CacheEntry ----------------------------------------------------
+
+/// A repository present in the collected data.
+#[derive(Clone, Debug)]
+pub struct CacheEntry {
+    /// The URI identifying the repository.
+    ///
+    /// For RRDP, this is the rpkiNotify URI. For rsync, this is the URI
+    /// of the module.
+    pub uri: String,
+
+    /// The path of the file or directory holding the repository.
+    pub path: PathBuf,
+
+    /// Whether this is an RRDP repository rather than an rsync module.
+    pub rrdp: bool,
+
+    /// The on-disk size of the repository in bytes.
+    pub size: u64,
+
+    /// Whether the repository was used during the current validation run.
+    pub used: bool,
+}
+
+impl CacheEntry {
+    /// Converts the entry into its metrics.
+    fn into_metrics(self) -> CachedRepository {
+        CachedRepository { uri: self.uri, rrdp: self.rrdp, size: self.size }
+    }
+}
+
+
+//------------ dir_size ------------------------------------------------------
+
+/// Returns the total size of all files underneath a directory.
+///
+/// Symbolic links are not followed.
+pub fn dir_size(path: &Path) -> Result<u64, io::Error> {
+    let mut res = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            res += dir_size(&entry.path())?;
+        }
+        else if file_type.is_file() {
+            res += entry.metadata()?.len();
+        }
+    }
+    Ok(res)
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(
+        dir: &tempfile::TempDir, name: &str, size: u64, used: bool
+    ) -> CacheEntry {
+        let path = dir.path().join(name);
+        fs::write(&path, vec![0u8; size as usize]).unwrap();
+        CacheEntry {
+            uri: format!("rsync://example.net/{}/", name),
+            path, rrdp: false, size, used,
+        }
+    }
+
+    #[test]
+    fn update() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default_with_paths(
+            Default::default(), dir.path().into()
+        );
+        config.max_cache_size = Some(250);
+        let cache = Cache::new(&config);
+
+        // Everything fits.
+        let metrics = cache.update(vec![
+            entry(&dir, "a", 100, false),
+            entry(&dir, "b", 100, true),
+        ]).unwrap();
+        assert_eq!(metrics.repositories.len(), 2);
+        assert!(metrics.evicted.is_empty());
+        assert_eq!(metrics.size(false), 200);
+
+        // The entries not used in this run go first, then the largest.
+        let metrics = cache.update(vec![
+            entry(&dir, "a", 100, false),
+            entry(&dir, "b", 100, true),
+            entry(&dir, "c", 10, true),
+            entry(&dir, "d", 60, true),
+        ]).unwrap();
+        let evicted: Vec<_> = metrics.evicted.iter().map(|item| {
+            item.uri.as_str()
+        }).collect();
+        assert_eq!(evicted, ["rsync://example.net/a/"]);
+        assert!(!dir.path().join("a").exists());
+
+        // A preview doesn’t remove anything.
+        let preview = cache.preview(vec![
+            entry(&dir, "b", 100, true),
+            entry(&dir, "c", 10, true),
+            entry(&dir, "d", 160, true),
+        ]);
+        assert_eq!(preview.len(), 1);
+        assert_eq!(preview[0].uri, "rsync://example.net/d/");
+        assert_eq!(preview[0].reason, CleanupReason::CacheLimit);
+        assert!(dir.path().join("d").exists());
+
+        let metrics = cache.update(vec![
+            entry(&dir, "b", 100, true),
+            entry(&dir, "c", 10, true),
+            entry(&dir, "d", 160, true),
+        ]).unwrap();
+        let evicted: Vec<_> = metrics.evicted.iter().map(|item| {
+            item.uri.as_str()
+        }).collect();
+        assert_eq!(evicted, ["rsync://example.net/d/"]);
+        assert_eq!(metrics.size(false), 110);
+        assert_eq!(metrics.evicted_size(), 160);
+    }
+}
@@ -0,0 +1,251 @@
+//! Resuming the repository updates of interrupted validation runs.
+//!
+//! Only the progress of updating repositories is checkpointed. Validation
+//! itself always starts over, producing the complete data set anew.
+//!
+//! This is a private module. Its types are used by the base collector only.
+
+use std::{fs, io};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use log::{info, warn};
+use crate::config::Config;
+use crate::utils::sync::Mutex;
+
+
+//------------ Checkpoint ----------------------------------------------------
+
+/// The progress of the current validation run.
+///
+/// While a validation run is going on, the repositories that have been
+/// updated successfully are recorded and periodically saved to the file
+/// `checkpoint` in the cache directory. The file is removed when the run
+/// finishes. If it is still present when the next run starts and that
+/// run is started within the refresh interval of the interrupted run, the
+/// interrupted run is resumed: the repositories it has already updated are
+/// not updated again and the publication points in them are validated
+/// from the store instead.
+///
+/// Repositories are identified by a URI given as a string. For RRDP, this
+/// is the rpkiNotify URI, for rsync the URI of the module.
+///
+/// The first line of the file contains the Unix time the run was started,
+/// each following line the URI of an updated repository.
+#[derive(Debug)]
+pub struct Checkpoint {
+    /// The path of the file to keep the checkpoint in.
+    path: PathBuf,
+
+    /// The minimum time between saving the checkpoint.
+    interval: Duration,
+
+    /// The maximum age of a run that can be resumed in seconds.
+    max_age: i64,
+
+    /// The progress of the current run.
+    state: Mutex<State>,
+}
+
+/// The progress of a validation run.
+#[derive(Debug, Default)]
+struct State {
+    /// The Unix time the run was started.
+    ///
+    /// This is `None` if there currently is no run.
+    started: Option<i64>,
+
+    /// The repositories updated during the run.
+    updated: HashSet<String>,
+
+    /// When the checkpoint was last saved.
+    saved: Option<Instant>,
+}
+
+impl Checkpoint {
+    /// The name of the file in the cache directory holding the checkpoint.
+    const FILE_NAME: &'static str = "checkpoint";
+
+    /// Creates a new value if checkpoints are configured.
+    ///
+    /// Loads the checkpoint of an interrupted run unless the config asks
+    /// for a fresh start.
+    pub fn new(config: &Config) -> Option<Self> {
+        let interval = config.checkpoint_interval?;
+        let path = config.cache_dir.join(Self::FILE_NAME);
+        let state = if config.fresh {
+            State::default()
+        }
+        else {
+            Self::load(&path)
+        };
+        Some(Checkpoint {
+            path,
+            interval,
+            max_age: i64::try_from(
+                config.refresh.as_secs()
+            ).unwrap_or(i64::MAX),
+            state: Mutex::new(state),
+        })
+    }
+
+    /// Loads the checkpoint from the file at `path`.
+    fn load(path: &PathBuf) -> State {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) => {
+                if err.kind() != io::ErrorKind::NotFound {
+                    warn!(
+                        "Failed to read checkpoint {}: {}",
+                        path.display(), err
+                    );
+                }
+                return State::default()
+            }
+        };
+        let mut lines = content.lines();
+        let started = match lines.next().and_then(|line| {
+            line.trim().parse().ok()
+        }) {
+            Some(started) => started,
+            None => {
+                warn!("Ignoring invalid checkpoint {}.", path.display());
+                return State::default()
+            }
+        };
+        State {
+            started: Some(started),
+            updated: lines.filter_map(|line| {
+                let line = line.trim();
+                (!line.is_empty()).then(|| line.into())
+            }).collect(),
+            saved: None,
+        }
+    }
+
+    /// Starts a validation run at the Unix time `now`.
+    ///
+    /// If an interrupted run can be resumed, returns the repositories it
+    /// has already updated. Otherwise starts a new run and returns an
+    /// empty set.
+    pub fn start(&self, now: i64) -> HashSet<String> {
+        let mut state = self.state.lock();
+        if let Some(started) = state.started {
+            if now.saturating_sub(started) < self.max_age {
+                if !state.updated.is_empty() {
+                    info!(
+                        "Resuming interrupted validation run. Skipping \
+                         {} repositories already updated.",
+                        state.updated.len()
+                    );
+                }
+                return state.updated.clone()
+            }
+        }
+        *state = State {
+            started: Some(now),
+            .. Default::default()
+        };
+        HashSet::new()
+    }
+
+    /// Records that the repository has been updated successfully.
+    ///
+    /// Saves the checkpoint if the interval has passed since it was last
+    /// saved.
+    pub fn updated(&self, uri: &str) {
+        let mut state = self.state.lock();
+        if state.started.is_none() || state.updated.contains(uri) {
+            return
+        }
+        state.updated.insert(uri.into());
+        if state.saved.is_some_and(|saved| saved.elapsed() < self.interval) {
+            return
+        }
+        self.save(&state);
+        state.saved = Some(Instant::now());
+    }
+
+    /// Finishes the current run and removes the checkpoint.
+    pub fn finish(&self) {
+        *self.state.lock() = State::default();
+        if let Err(err) = fs::remove_file(&self.path) {
+            if err.kind() != io::ErrorKind::NotFound {
+                warn!(
+                    "Failed to remove checkpoint {}: {}",
+                    self.path.display(), err
+                );
+            }
+        }
+    }
+
+    /// Saves the checkpoint.
+    ///
+    /// As the checkpoint is an optimization only, errors are logged but
+    /// otherwise ignored.
+    fn save(&self, state: &State) {
+        let started = match state.started {
+            Some(started) => started,
+            None => return,
+        };
+        let mut content = format!("{}\n", started);
+        for uri in &state.updated {
+            content.push_str(uri);
+            content.push('\n');
+        }
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+        if let Err(err) = fs::write(&tmp_path, content).and_then(|_| {
+            fs::rename(&tmp_path, &self.path)
+        }) {
+            warn!(
+                "Failed to write checkpoint {}: {}",
+                self.path.display(), err
+            );
+        }
+    }
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn resume() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default_with_paths(
+            Default::default(), dir.path().into()
+        );
+        config.refresh = Duration::from_secs(600);
+        config.checkpoint_interval = Some(Duration::ZERO);
+
+        let checkpoint = Checkpoint::new(&config).unwrap();
+        assert!(checkpoint.start(1000).is_empty());
+        checkpoint.updated("https://rrdp.example.net/notification.xml");
+        checkpoint.updated("rsync://rsync.example.net/repo/");
+
+        // An interrupted run is resumed within the refresh interval.
+        let checkpoint = Checkpoint::new(&config).unwrap();
+        let resumed = checkpoint.start(1100);
+        assert_eq!(resumed.len(), 2);
+        assert!(resumed.contains("rsync://rsync.example.net/repo/"));
+
+        // But not after it.
+        let checkpoint = Checkpoint::new(&config).unwrap();
+        assert!(checkpoint.start(1600).is_empty());
+
+        // A finished run leaves nothing to resume.
+        let checkpoint = Checkpoint::new(&config).unwrap();
+        assert_eq!(checkpoint.start(1100).len(), 2);
+        checkpoint.finish();
+        let checkpoint = Checkpoint::new(&config).unwrap();
+        assert!(checkpoint.start(1100).is_empty());
+
+        config.checkpoint_interval = None;
+        assert!(Checkpoint::new(&config).is_none());
+    }
+}
@@ -0,0 +1,241 @@
+//! Logging fetch attempts in machine-readable form.
+//!
+//! This is a private module. Its types are used by the base collector only.
+
+use std::fmt::Write;
+use std::fs;
+use std::io::Write as _;
+use std::path::PathBuf;
+use log::warn;
+use crate::config::Config;
+use crate::metrics::{Metrics, RrdpRepositoryMetrics, RsyncModuleMetrics};
+use crate::collector::HttpStatus;
+use crate::utils::json::json_str;
+
+
+//------------ FetchLog ------------------------------------------------------
+
+/// A log of all attempts to fetch a repository.
+///
+/// At the end of each validation run, one line is appended to the log file
+/// for every RRDP repository and rsync module the collector tried to
+/// update. Each line is a JSON object with the following members:
+///
+/// * `time`: the start time of the validation run,
+/// * `runId`: the identifier of the validation run,
+/// * `uri`: the rpkiNotify URI or the URI of the rsync module,
+/// * `transport`: either `"rrdp"` or `"rsync"`,
+/// * `outcome`: one of `"success"`, `"unchanged"`, `"skipped"`, or
+///   `"failure"`,
+/// * `status`: the HTTP status or rsync exit code,
+/// * `bytes`: the number of bytes received,
+/// * `duration`: the duration of the attempt in seconds,
+/// * `reason`: the code of the failure reason for failed attempts,
+/// * `error`: a description of the error for failed attempts, and
+/// * `mirror`: the URI of the mirror an rsync module was fetched from.
+///
+/// Members whose value isn’t known are `null`.
+#[derive(Debug)]
+pub struct FetchLog {
+    /// The path of the log file.
+    path: PathBuf,
+}
+
+impl FetchLog {
+    /// Creates a new value if a fetch log is configured.
+    pub fn new(config: &Config) -> Option<Self> {
+        config.fetch_log.as_ref().map(|path| {
+            FetchLog { path: path.clone() }
+        })
+    }
+
+    /// Appends the fetch attempts of a validation run to the log.
+    ///
+    /// As the log is informational only, errors are logged but otherwise
+    /// ignored.
+    pub fn write(&self, metrics: &Metrics) {
+        let mut content = String::new();
+        for item in &metrics.rrdp {
+            rrdp_line(&mut content, metrics, item);
+        }
+        for item in &metrics.rsync {
+            rsync_line(&mut content, metrics, item);
+        }
+        if content.is_empty() {
+            return
+        }
+        if let Err(err) = fs::OpenOptions::new().create(true).append(
+            true
+        ).open(&self.path).and_then(|mut file| {
+            file.write_all(content.as_bytes())
+        }) {
+            warn!(
+                "Failed to write fetch log {}: {}",
+                self.path.display(), err
+            );
+        }
+    }
+}
+
+
+//------------ Helper Functions ----------------------------------------------
+
+/// Appends the log line for an RRDP repository.
+fn rrdp_line(
+    target: &mut String, metrics: &Metrics, item: &RrdpRepositoryMetrics
+) {
+    let status = item.status();
+    let (outcome, error) = if matches!(status, HttpStatus::Skipped) {
+        ("skipped", None)
+    }
+    else if let Some(limit) = item.limit_violation {
+        ("failure", Some(format!("{} limit exceeded", limit.code())))
+    }
+    else if item.head_skipped || status.is_not_modified() || (
+        status.is_success() && item.payload_status.is_none()
+    ) {
+        ("unchanged", None)
+    }
+    else if status.is_success() {
+        ("success", None)
+    }
+    else {
+        ("failure", Some(match status {
+            HttpStatus::Response(code) => format!("HTTP status {}", code),
+            HttpStatus::Rejected => String::from("response rejected"),
+            _ => String::from("request failed"),
+        }))
+    };
+    line(
+        target, metrics, item.notify_uri.as_str(), "rrdp", outcome,
+        Some(i64::from(status.into_i16())), Some(item.bytes),
+        item.duration.as_ref().ok().map(|duration| duration.as_secs_f64()),
+        error.as_ref().map(|_| item.failure_reason().code()),
+        error.as_deref(), None
+    )
+}
+
+/// Appends the log line for an rsync module.
+fn rsync_line(
+    target: &mut String, metrics: &Metrics, item: &RsyncModuleMetrics
+) {
+    let (outcome, error) = match item.status {
+        Ok(status) if status.success() => ("success", None),
+        Ok(status) => {
+            ("failure", Some(format!("rsync exited with {}", status)))
+        }
+        Err(ref err) => ("failure", Some(err.to_string())),
+    };
+    line(
+        target, metrics, item.module.as_str(), "rsync", outcome,
+        item.status.as_ref().ok().and_then(|status| {
+            status.code()
+        }).map(i64::from),
+        item.stats.map(|stats| stats.bytes_received),
+        item.duration.as_ref().ok().map(|duration| duration.as_secs_f64()),
+        error.as_ref().map(|_| item.failure_reason().code()),
+        error.as_deref(), item.mirror.as_deref(),
+    )
+}
+
+/// Appends a single log line.
+#[allow(clippy::too_many_arguments)]
+fn line(
+    target: &mut String,
+    metrics: &Metrics,
+    uri: &str,
+    transport: &str,
+    outcome: &str,
+    status: Option<i64>,
+    bytes: Option<u64>,
+    duration: Option<f64>,
+    reason: Option<&str>,
+    error: Option<&str>,
+    mirror: Option<&str>,
+) {
+    fn opt(value: Option<impl std::fmt::Display>) -> String {
+        match value {
+            Some(value) => value.to_string(),
+            None => String::from("null"),
+        }
+    }
+
+    fn opt_str(value: Option<impl std::fmt::Display>) -> String {
+        match value {
+            Some(value) => format!("\"{}\"", json_str(value)),
+            None => String::from("null"),
+        }
+    }
+
+    let _ = writeln!(
+        target,
+        "{{\"time\":\"{}\",\"runId\":{},\"uri\":\"{}\",\
+         \"transport\":\"{}\",\"outcome\":\"{}\",\"status\":{},\
+         \"bytes\":{},\"duration\":{},\"reason\":{},\"error\":{},\
+         \"mirror\":{}}}",
+        metrics.time.to_rfc3339(),
+        opt_str(metrics.run_id.as_ref()),
+        json_str(uri), transport, outcome,
+        opt(status), opt(bytes),
+        opt(duration.map(|duration| format!("{:.3}", duration))),
+        opt_str(reason), opt_str(error), opt_str(mirror),
+    );
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io;
+    use std::str::FromStr;
+    use std::time::Duration;
+    use reqwest::StatusCode;
+    use rpki::uri;
+
+    #[test]
+    fn lines() {
+        let mut metrics = Metrics::new();
+        let mut rrdp = RrdpRepositoryMetrics::new(
+            uri::Https::from_str("https://rrdp.example.net/notify.xml")
+                .unwrap()
+        );
+        rrdp.notify_status = HttpStatus::Response(StatusCode::NOT_FOUND);
+        rrdp.duration = Ok(Duration::from_millis(1500));
+        metrics.rrdp.push(rrdp);
+        metrics.rsync.push(RsyncModuleMetrics {
+            module: uri::Rsync::from_str(
+                "rsync://rsync.example.net/repo/"
+            ).unwrap(),
+            status: Err(io::Error::new(io::ErrorKind::TimedOut, "timeout")),
+            duration: Ok(Duration::from_secs(10)),
+            points: 1,
+            address_family: None,
+            stats: None,
+            mirror: None,
+        });
+
+        let mut content = String::new();
+        rrdp_line(&mut content, &metrics, &metrics.rrdp[0]);
+        rsync_line(&mut content, &metrics, &metrics.rsync[0]);
+        let time = metrics.time.to_rfc3339();
+        assert_eq!(
+            content,
+            format!(
+                "{{\"time\":\"{time}\",\"runId\":null,\
+                 \"uri\":\"https://rrdp.example.net/notify.xml\",\
+                 \"transport\":\"rrdp\",\"outcome\":\"failure\",\
+                 \"status\":404,\"bytes\":0,\"duration\":1.500,\
+                 \"reason\":\"http-status\",\
+                 \"error\":\"HTTP status 404 Not Found\",\"mirror\":null}}\n\
+                 {{\"time\":\"{time}\",\"runId\":null,\
+                 \"uri\":\"rsync://rsync.example.net/repo/\",\
+                 \"transport\":\"rsync\",\"outcome\":\"failure\",\
+                 \"status\":null,\"bytes\":null,\"duration\":10.000,\
+                 \"reason\":\"timeout\",\"error\":\"timeout\",\
+                 \"mirror\":null}}\n",
+            )
+        );
+    }
+}
@@ -24,10 +24,21 @@
 //  mirroring the structure of the base module, i.e., they also have
 //  `Collector`, `Run`, and `Repository` types.
 //
-pub use self::base::{Collector, Cleanup, Run, Repository};
-pub use self::rrdp::{HttpStatus, SnapshotReason};
+pub use self::backoff::Backoff;
+pub use self::base::{
+    Collector, Cleanup, Heartbeat, LocalRepository, RefreshTarget, Run,
+    Repository
+};
+pub use self::rrdp::{
+    HttpStatus, RrdpLimit, RrdpRepositoryInfo, SnapshotReason
+};
 
+mod backoff;
 mod base;
+mod cache;
+mod checkpoint;
+mod fetchlog;
 mod rrdp;
 mod rsync;
+mod schedule;
 
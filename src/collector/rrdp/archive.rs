@@ -78,6 +78,11 @@ impl RrdpArchive {
     pub fn path(&self) -> &Arc<PathBuf> {
         &self.path
     }
+
+    /// Returns the current size of the archive file in bytes.
+    pub fn size(&self) -> u64 {
+        self.archive.size()
+    }
 }
 
 impl RrdpArchive {
@@ -380,19 +385,28 @@ pub struct RepositoryState {
 
     /// Information of the deltas since in the last notificiation.
     pub delta_state: HashMap<u64, rrdp::Hash>,
+
+    /// Unix timestamp in seconds of the last failed update if any.
+    ///
+    /// This is kept across delta updates but reset when the repository is
+    /// replaced from a snapshot.
+    pub failed_ts: Option<i64>,
 }
 
 impl RepositoryState {
     /// The current version of the data.
     ///
-    /// This is 1 since version 0 was in the main branch for quite some time.
-    const VERSION: u8 = 1;
+    /// This is 2 since version 0 was in the main branch for quite some time
+    /// and version 1 didn’t have the time of the last failed update.
+    const VERSION: u8 = 2;
 
     /// Reads the state from an IO reader.
+    ///
+    /// Version 1 of the data is still accepted.
     fn parse(reader: &mut impl io::Read) -> Result<Self, io::Error> {
         // Version number.
         let version = u8::parse(reader)?;
-        if version != Self::VERSION {
+        if version != 1 && version != Self::VERSION {
             return Err(io::Error::new(
                 io::ErrorKind::Other,
                 format!("unexpected version {}", version)
@@ -408,6 +422,12 @@ impl RepositoryState {
             last_modified_ts: Parse::parse(reader)?,
             etag: Parse::parse(reader)?,
             delta_state: Parse::parse(reader)?,
+            failed_ts: if version == 1 {
+                None
+            }
+            else {
+                Parse::parse(reader)?
+            },
         })
     }
 
@@ -422,6 +442,7 @@ impl RepositoryState {
         self.last_modified_ts.compose(writer)?;
         self.etag.compose(writer)?;
         self.delta_state.compose(writer)?;
+        self.failed_ts.compose(writer)?;
         Ok(())
     }
 
@@ -441,6 +462,14 @@ impl RepositoryState {
         Utc.timestamp_opt(self.best_before_ts, 0).single()
     }
 
+    /// Returns the time of the last failed update as a proper timestamp.
+    ///
+    /// Returns `None` if no update has failed or if the time cannot be
+    /// converted into a timestamp.
+    pub fn failed(&self) -> Option<DateTime<Utc>> {
+        self.failed_ts.and_then(|ts| Utc.timestamp_opt(ts, 0).single())
+    }
+
     /// Sets the update time to now.
     pub fn touch(&mut self, fallback: FallbackTime) {
         self.updated_ts = Utc::now().timestamp();
@@ -554,11 +583,40 @@ mod test {
                 (18, rrdp::Hash::from_data(b"123")),
                 (19, rrdp::Hash::from_data(b"332")),
             ].iter().cloned().collect(),
+            failed_ts: Some(1234),
         };
         let mut buf = Vec::new();
         state.compose(&mut buf).unwrap();
         let parsed = RepositoryState::parse(&mut buf.as_slice()).unwrap();
         assert_eq!(state, parsed);
     }
+
+    #[test]
+    fn parse_repository_state_v1() {
+        let mut state = RepositoryState {
+            rpki_notify: uri::Https::from_str(
+                "https://foo.bar/baz"
+            ).unwrap(),
+            session: Uuid::from_u128(0xa1a2a3a4b1b2c1c2d1d2d3d4d5d6d7d8u128),
+            serial: 12,
+            updated_ts: 1000,
+            best_before_ts: 2000,
+            last_modified_ts: None,
+            etag: None,
+            delta_state: HashMap::new(),
+            failed_ts: Some(1500),
+        };
+        let mut buf = Vec::new();
+        state.compose(&mut buf).unwrap();
+
+        // Version 1 is version 2 without the trailing failed_ts.
+        let mut failed = Vec::new();
+        state.failed_ts.compose(&mut failed).unwrap();
+        buf[0] = 1;
+        buf.truncate(buf.len() - failed.len());
+        state.failed_ts = None;
+        let parsed = RepositoryState::parse(&mut buf.as_slice()).unwrap();
+        assert_eq!(state, parsed);
+    }
 }
 
@@ -1,22 +1,33 @@
-use std::{cmp, fs, io};
+use std::{cmp, fs, io, thread};
 use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Weak};
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime};
 use bytes::Bytes;
+use chrono::{DateTime, Utc};
 use log::{debug, error, info, warn};
 use rpki::uri;
 use rpki::crypto::DigestAlgorithm;
 use rpki::rrdp::{DeltaInfo, DeltaListError, NotificationFile};
 use tempfile::NamedTempFile;
-use crate::config::Config;
+use uuid::Uuid;
+use crate::collector::backoff::Backoff;
+use crate::collector::cache::{CacheEntry, dir_size};
+use crate::config::{
+    Config, DeltaOverflowPolicy, FallbackPolicy, RrdpDeltaOverride,
+    RrdpLimits
+};
 use crate::error::{Fatal, RunFailed};
-use crate::metrics::{Metrics, RrdpRepositoryMetrics};
+use crate::metrics::{
+    CleanupPreview, CleanupReason, Metrics, PreviewChange, RepositoryProbe,
+    RrdpRepositoryMetrics, UpdatePreview,
+};
 use crate::utils::fatal;
 use crate::utils::archive::{ArchiveError, OpenError};
 use crate::utils::dump::DumpRegistry;
 use crate::utils::json::JsonBuilder;
-use crate::utils::sync::{Mutex, RwLock};
+use crate::utils::sync::{HostLimit, Mutex, RwLock};
 use crate::utils::uri::UriExt;
 use super::archive::{FallbackTime, RrdpArchive, RepositoryState};
 use super::http::{HttpClient, HttpStatus};
@@ -25,8 +36,25 @@ use super::update::{
 };
 
 
+//------------ Special Directories -------------------------------------------
+
+/// The name of the directory for temporary files.
+const TMP_DIR: &str = "tmp";
+
+/// The name of the directory for partially downloaded snapshot files.
+const PARTIAL_DIR: &str = "partial";
+
+/// Returns whether a directory is not an authority directory.
+fn is_special_dir(name: &OsStr) -> bool {
+    name == TMP_DIR || name == PARTIAL_DIR
+}
+
+
 //------------ Collector -----------------------------------------------------
 
+/// The number of repositories checked concurrently by a heartbeat.
+const HEARTBEAT_THREADS: usize = 4;
+
 /// The local copy of RPKI repositories synchronized via RRDP.
 #[derive(Debug)]
 pub struct Collector {
@@ -38,6 +66,16 @@ pub struct Collector {
 
     /// Various configuration options.
     config: RrdpConfig,
+
+    /// The limit of concurrent updates per host if there is one.
+    ///
+    /// This is shared with the rsync collector.
+    host_limit: Option<Arc<HostLimit>>,
+
+    /// The tracker for failing repositories if there is one.
+    ///
+    /// This is shared with the rsync collector.
+    backoff: Option<Arc<Backoff>>,
 }
 
 impl Collector {
@@ -78,7 +116,11 @@ impl Collector {
     /// Creates a new RRDP collector.
     ///
     /// Returns `Ok(None)` if RRDP was disabled.
-    pub fn new(config: &Config) -> Result<Option<Self>, Fatal> {
+    pub fn new(
+        config: &Config,
+        host_limit: Option<Arc<HostLimit>>,
+        backoff: Option<Arc<Backoff>>,
+    ) -> Result<Option<Self>, Fatal> {
         if config.disable_rrdp {
             return Ok(None)
         }
@@ -86,6 +128,8 @@ impl Collector {
             working_dir: Self::create_working_dir(config)?,
             http: HttpClient::new(config)?,
             config: config.into(),
+            host_limit,
+            backoff,
         }))
     }
 
@@ -99,7 +143,7 @@ impl Collector {
     pub fn sanitize(&self) -> Result<(), Fatal> {
         for entry in fatal::read_dir(&self.working_dir)? {
             let entry = entry?;
-            if !entry.is_dir() || entry.file_name() == "tmp" {
+            if !entry.is_dir() || is_special_dir(entry.file_name()) {
                 continue;
             }
             for entry in fatal::read_dir(entry.path())? {
@@ -146,6 +190,238 @@ impl Collector {
         Run::new(self)
     }
 
+    /// Checks whether any of the locally known repositories has changed.
+    ///
+    /// Fetches the notification files of all repositories present in the
+    /// working directory for which `select` returns `true` and compares
+    /// their session and serial with the state of the local copy. Returns
+    /// the rpkiNotify URI of the first repository found to have changed or
+    /// `None` if there are no changes.
+    ///
+    /// Only the notification files are requested and conditional requests
+    /// are used where possible, so this is cheap enough to be done
+    /// frequently. Up to [`HEARTBEAT_THREADS`] repositories are checked
+    /// concurrently, observing the limit of connections per host.
+    /// Repositories that are currently backed off are skipped. Failures,
+    /// including those to read the working directory, are ignored as they
+    /// will be dealt with during the next validation run.
+    pub fn heartbeat(
+        &self, select: impl Fn(&uri::Https) -> bool + Sync
+    ) -> Option<uri::Https> {
+        let states = self.load_states().ok()?;
+        let queue = Mutex::new(states.into_iter().filter(|state| {
+            select(&state.rpki_notify)
+            && !self.backoff.as_ref().is_some_and(|backoff| {
+                backoff.is_backing_off(state.rpki_notify.as_str())
+            })
+        }));
+        let changed = Mutex::new(None);
+        thread::scope(|scope| {
+            for _ in 0..HEARTBEAT_THREADS {
+                scope.spawn(|| {
+                    while changed.lock().is_none() {
+                        let state = match queue.lock().next() {
+                            Some(state) => state,
+                            None => break,
+                        };
+                        if self.heartbeat_check(&state) {
+                            *changed.lock() = Some(state.rpki_notify);
+                        }
+                    }
+                });
+            }
+        });
+        changed.into_inner()
+    }
+
+    /// Checks whether a single repository has changed.
+    fn heartbeat_check(&self, state: &RepositoryState) -> bool {
+        let _permit = self.host_limit.as_ref().map(|limit| {
+            limit.acquire(state.rpki_notify.authority())
+        });
+        let mut status = HttpStatus::Error;
+        let notify = match Notification::get(
+            &self.http, &state.rpki_notify, Some(state),
+            &mut status, &mut None, self.config.max_delta_list_len,
+        ) {
+            Ok(Some(notify)) => notify,
+            Ok(None) | Err(_) => return false,
+        };
+        if notify.content().session_id() != state.session
+            || notify.content().serial() != state.serial
+        {
+            debug!(
+                "RRDP {}: Heartbeat detected new serial {}.",
+                state.rpki_notify, notify.content().serial()
+            );
+            true
+        }
+        else {
+            false
+        }
+    }
+
+    /// Probes whether the locally known repositories are reachable.
+    ///
+    /// Sends a HEAD request for the notification file of each repository
+    /// present in the working directory. Each request may take at most
+    /// `timeout`. Repositories that are currently backed off are skipped.
+    pub fn probe(
+        &self, timeout: Duration
+    ) -> Result<Vec<RepositoryProbe>, Fatal> {
+        Ok(self.load_states()?.into_iter().filter(|state| {
+            !self.backoff.as_ref().is_some_and(|backoff| {
+                backoff.is_backing_off(state.rpki_notify.as_str())
+            })
+        }).map(|state| {
+            let time = Utc::now();
+            let start = Instant::now();
+            let reachable = self.http.probe(&state.rpki_notify, timeout);
+            RepositoryProbe {
+                uri: state.rpki_notify.to_string(),
+                time,
+                duration: start.elapsed(),
+                reachable,
+            }
+        }).collect())
+    }
+
+    /// Determines what updating the locally known repositories would do.
+    ///
+    /// Fetches the notification files of all repositories present in the
+    /// working directory and compares them with the state of the local
+    /// copy. No snapshot or delta files are downloaded and the local copy
+    /// is left untouched.
+    pub fn preview(&self) -> Result<Vec<UpdatePreview>, Fatal> {
+        Ok(self.load_states()?.into_iter().map(|state| {
+            let mut status = HttpStatus::Error;
+            let change = match Notification::get(
+                &self.http, &state.rpki_notify, Some(&state),
+                &mut status, &mut None, self.config.max_delta_list_len,
+            ) {
+                Ok(Some(notify)) => {
+                    match self.plan_update(
+                        &state.rpki_notify, &notify, &state
+                    ) {
+                        Ok([]) => PreviewChange::Unchanged,
+                        Ok(deltas) => PreviewChange::Deltas {
+                            count: deltas.len(),
+                            serial: deltas[deltas.len() - 1].serial(),
+                        },
+                        Err(reason) => PreviewChange::Snapshot {
+                            reason,
+                            serial: notify.content().serial(),
+                        },
+                    }
+                }
+                Ok(None) => PreviewChange::Unchanged,
+                Err(_) => PreviewChange::Failed,
+            };
+            UpdatePreview { uri: state.rpki_notify.to_string(), change }
+        }).collect())
+    }
+
+    /// Returns information about all repositories present in the working dir.
+    ///
+    /// The `fallback` policy is used to determine whether rsync would be
+    /// used for a repository should updating it fail.
+    pub fn repository_info(
+        &self, fallback: FallbackPolicy
+    ) -> Result<Vec<RrdpRepositoryInfo>, Fatal> {
+        let mut res: Vec<_> = self.load_sized_states()?.into_iter().map(
+            |(state, size, _)| RrdpRepositoryInfo {
+                fallback: {
+                    state.is_expired()
+                    && matches!(fallback, FallbackPolicy::Stale)
+                },
+                updated: state.updated(),
+                failed: state.failed(),
+                best_before: state.best_before(),
+                size,
+                session: state.session,
+                serial: state.serial,
+                rpki_notify: state.rpki_notify,
+            }
+        ).collect();
+        res.sort_by(|left, right| {
+            left.rpki_notify.as_str().cmp(right.rpki_notify.as_str())
+        });
+        Ok(res)
+    }
+
+    /// Loads the state of all repositories present in the working dir.
+    ///
+    /// Repositories with unreadable state and, if configured, dubious
+    /// rpkiNotify URIs are skipped.
+    fn load_states(&self) -> Result<Vec<RepositoryState>, Fatal> {
+        Ok(
+            self.load_sized_states()?.into_iter().map(|(state, _, _)| {
+                state
+            }).collect()
+        )
+    }
+
+    /// Loads the state, file size, and path of all repositories.
+    ///
+    /// The same repositories as with [`load_states`][Self::load_states]
+    /// are skipped.
+    fn load_sized_states(
+        &self
+    ) -> Result<Vec<(RepositoryState, u64, PathBuf)>, Fatal> {
+        let mut res = Vec::new();
+        for entry in fatal::read_dir(&self.working_dir)? {
+            let entry = entry?;
+            if !entry.is_dir() || is_special_dir(entry.file_name()) {
+                continue;
+            }
+            for entry in fatal::read_dir(entry.path())? {
+                let entry = entry?;
+                if !entry.is_file() {
+                    continue;
+                }
+                let size = entry.len();
+                let path = entry.into_path();
+                let state = match RrdpArchive::open(
+                    path.clone().into()
+                ).and_then(|archive| archive.load_state()) {
+                    Ok(state) => state,
+                    Err(err) if err.should_retry() => continue,
+                    Err(_) => return Err(Fatal),
+                };
+                if self.config.filter_dubious
+                    && state.rpki_notify.has_dubious_authority()
+                {
+                    continue;
+                }
+                res.push((state, size, path));
+            }
+        }
+        Ok(res)
+    }
+
+    /// Opens the local copy of a repository for reading.
+    ///
+    /// The repository is not updated. Returns `Ok(None)` if there is no
+    /// local copy or it cannot be used.
+    pub fn read_local(
+        &self, rpki_notify: &uri::Https
+    ) -> Result<Option<ReadRepository>, Fatal> {
+        let mut path = self.working_dir.join(
+            rpki_notify.canonical_authority().as_ref()
+        );
+        let mut file_name = Self::repository_file_name(rpki_notify);
+        file_name.push_str(".bin");
+        path.push(file_name);
+        if !path.exists() {
+            return Ok(None)
+        }
+        match RrdpArchive::open(path.into()) {
+            Ok(archive) => Ok(Some(ReadRepository { archive })),
+            Err(err) if err.should_retry() => Ok(None),
+            Err(_) => Err(Fatal),
+        }
+    }
+
     #[allow(clippy::mutable_key_type)]
     pub fn dump(&self, dir: &Path) -> Result<(), Fatal> {
         let dir = dir.join("rrdp");
@@ -154,7 +430,7 @@ impl Collector {
         let mut states = HashMap::new();
         for entry in fatal::read_dir(&self.working_dir)? {
             let entry = entry?;
-            if !entry.is_dir() || entry.file_name() == "tmp" {
+            if !entry.is_dir() || is_special_dir(entry.file_name()) {
                 continue;
             }
             for entry in fatal::read_dir(entry.path())? {
@@ -267,24 +543,49 @@ impl Collector {
             return Err(Fatal)
         }
 
+        let mut dir = Self::repository_file_name(rpki_notify);
+        dir.push_str(".bin");
+        path.push(&dir);
+        Ok(path)
+    }
+
+    /// Returns the path for a partially downloaded snapshot of a repository.
+    pub(super) fn partial_path(
+        &self, rpki_notify: &uri::Https
+    ) -> Result<PathBuf, Fatal> {
+        let mut path = self.working_dir.join(PARTIAL_DIR);
+        if let Err(err) = fs::create_dir_all(&path) {
+            error!(
+                "Failed to create RRDP partial download directory {}: {}",
+                path.display(), err
+            );
+            return Err(Fatal)
+        }
+        path.push(Self::repository_file_name(rpki_notify));
+        Ok(path)
+    }
+
+    /// Returns the base of the file names used for a repository.
+    ///
+    /// This is the hex representation of the SHA-256 hash of the
+    /// rpkiNotify URI.
+    fn repository_file_name(rpki_notify: &uri::Https) -> String {
         let alg = DigestAlgorithm::sha256();
-        let mut dir = String::with_capacity(
+        let mut res = String::with_capacity(
               alg.digest_len()
               + 4 // ".bin"
         );
         crate::utils::str::append_hex(
             alg.digest(rpki_notify.as_slice()).as_ref(),
-            &mut dir
+            &mut res
         );
-        dir.push_str(".bin");
-        path.push(&dir);
-        Ok(path)
+        res
     }
 
     fn temp_file(
         &self
     ) -> Result<(fs::File, Arc<PathBuf>), Fatal> {
-        let base = self.working_dir.join("tmp");
+        let base = self.working_dir.join(TMP_DIR);
         if let Err(err) = fs::create_dir_all(&base) {
             error!(
                 "Failed to create RRDP temporary directory {}: {}",
@@ -312,6 +613,28 @@ impl Collector {
         Ok((file, path.into()))
     }
 
+    /// Creates an anonymous temporary file.
+    ///
+    /// The file is created in the RRDP temporary directory and is deleted
+    /// once it is dropped.
+    pub(super) fn anonymous_temp_file(&self) -> Result<fs::File, Fatal> {
+        let base = self.working_dir.join(TMP_DIR);
+        if let Err(err) = fs::create_dir_all(&base) {
+            error!(
+                "Failed to create RRDP temporary directory {}: {}",
+                base.display(), err
+            );
+            return Err(Fatal)
+        }
+        tempfile::tempfile_in(&base).map_err(|err| {
+            error!(
+                "Failed to create temporary RRDP file in {}: {}",
+                base.display(), err
+            );
+            Fatal
+        })
+    }
+
     pub(super) fn http(&self) -> &HttpClient {
         &self.http
     }
@@ -319,6 +642,107 @@ impl Collector {
     pub(super) fn config(&self) -> &RrdpConfig {
         &self.config
     }
+
+    /// Determines the deltas needed to update a repository.
+    ///
+    /// Returns the deltas to apply to the local copy described by `state`
+    /// in order to reach the state announced by `notify`. The slice is
+    /// empty if the local copy is up-to-date. If a snapshot is needed
+    /// instead, returns the reason why.
+    fn plan_update<'b>(
+        &self,
+        rpki_notify: &uri::Https,
+        notify: &'b Notification,
+        state: &RepositoryState,
+    ) -> Result<&'b [DeltaInfo], SnapshotReason> {
+        if let Err(err) = notify.content().delta_status() {
+            match err {
+                DeltaListError::Oversized => {
+                    info!(
+                        "RRDP {}: Overly large delta set in notification file",
+                        rpki_notify
+                    );
+                    return Err(SnapshotReason::LargeDeltaSet);
+                }
+            }
+        }
+        notify.check_deltas(state)?;
+        self.calc_deltas(rpki_notify, notify.content(), state)
+    }
+
+    /// Calculates the slice of deltas to follow for updating.
+    ///
+    /// Returns an empty slice if no update is necessary.
+    /// Returns a non-empty slice of the sequence of deltas to be applied.
+    /// If there are too many deltas and the delta policy for the repository
+    /// allows partial updates, this slice only contains the first deltas.
+    fn calc_deltas<'b>(
+        &self,
+        rpki_notify: &uri::Https,
+        notify: &'b NotificationFile,
+        state: &RepositoryState
+    ) -> Result<&'b [DeltaInfo], SnapshotReason> {
+        if notify.session_id() != state.session {
+            debug!("New session. Need to get snapshot.");
+            return Err(SnapshotReason::NewSession)
+        }
+        debug!("{}: Serials: us {}, them {}.",
+            rpki_notify, state.serial, notify.serial()
+        );
+        if notify.serial() == state.serial {
+            return Ok(&[]);
+        }
+
+        // If there is no last delta (remember, we have a different
+        // serial than the notification file) or if the last delta’s
+        // serial differs from that noted in the notification file,
+        // bail out.
+        if notify.deltas().last().map(|delta| delta.serial())
+            != Some(notify.serial())
+        {
+            debug!("Last delta serial differs from current serial.");
+            return Err(SnapshotReason::BadDeltaSet)
+        }
+
+        let mut deltas = notify.deltas();
+        let serial = match state.serial.checked_add(1) {
+            Some(serial) => serial,
+            None => return Err(SnapshotReason::LargeSerial)
+        };
+        loop {
+            let first = match deltas.first() {
+                Some(first) => first,
+                None => {
+                    debug!("Ran out of deltas.");
+                    return Err(SnapshotReason::BadDeltaSet)
+                }
+            };
+            match first.serial().cmp(&serial) {
+                cmp::Ordering::Greater => {
+                    debug!("First delta is too new ({})", first.serial());
+                    return Err(SnapshotReason::OutdatedLocal)
+                }
+                cmp::Ordering::Equal => break,
+                cmp::Ordering::Less => deltas = &deltas[1..]
+            }
+        }
+
+        let (max_count, overflow) = self.config.delta_policy(
+            rpki_notify
+        );
+        if deltas.len() > max_count {
+            debug!(
+                "RRDP: {}: Too many delta steps required ({})",
+                rpki_notify, deltas.len()
+            );
+            if overflow == DeltaOverflowPolicy::Partial && max_count > 0 {
+                return Ok(&deltas[..max_count])
+            }
+            return Err(SnapshotReason::TooManyDeltas)
+        }
+
+        Ok(deltas)
+    }
 }
 
 
@@ -360,7 +784,7 @@ impl<'a> Run<'a> {
     /// This just downloads the file. It is not cached since that is done
     /// by the store anyway.
     pub fn load_ta(&self, uri: &uri::Https) -> Option<Bytes> {
-        let mut response = match self.collector.http.response(uri, false) {
+        let mut response = match self.collector.http.response(uri, None, false) {
             Ok(response) => response,
             Err(_) => return None,
         };
@@ -434,6 +858,10 @@ impl<'a> Run<'a> {
             (LoadResult::Unavailable, metrics)
         }
         else {
+            // Wait until the server has capacity for us.
+            let _permit = self.collector.host_limit.as_ref().map(|limit| {
+                limit.acquire(rpki_notify.authority())
+            });
             RepositoryUpdate::new(
                 self.collector, rpki_notify
             )?.try_update()?
@@ -453,10 +881,16 @@ impl<'a> Run<'a> {
         Ok((res, true))
     }
 
+    /// Cleans the collector only keeping the repositories in `retain`.
+    ///
+    /// If `preview` is some list, nothing is deleted. Instead, all
+    /// repositories and stray data that would be deleted are added to the
+    /// list. Temporary data is not included.
     #[allow(clippy::mutable_key_type)]
     pub fn cleanup(
         &self,
-        retain: &mut HashSet<uri::Https>
+        retain: &mut HashSet<uri::Https>,
+        preview: Option<&mut Vec<CleanupPreview>>,
     ) -> Result<(), Fatal> {
         // Add all the RRDP repositories we’ve tried during this run to be
         // kept.
@@ -464,6 +898,10 @@ impl<'a> Run<'a> {
             retain.insert(uri.clone());
         }
 
+        if let Some(preview) = preview {
+            return self.preview_cleanup(retain, preview)
+        }
+
         for entry in fatal::read_dir(&self.collector.working_dir)? {
             let entry = entry?;
             if entry.is_file() {
@@ -477,9 +915,12 @@ impl<'a> Run<'a> {
                 }
             }
             else if entry.is_dir() {
-                if entry.file_name() == "tmp" {
+                if entry.file_name() == TMP_DIR {
                     self.cleanup_tmp(entry.path())?
                 }
+                else if entry.file_name() == PARTIAL_DIR {
+                    self.cleanup_partial(entry.path(), retain)?
+                }
                 else {
                     self.cleanup_authority(entry.path(), retain)?;
                 }
@@ -489,6 +930,84 @@ impl<'a> Run<'a> {
         Ok(())
     }
 
+    /// Adds what cleaning up would delete to `preview`.
+    #[allow(clippy::mutable_key_type)]
+    fn preview_cleanup(
+        &self,
+        retain: &HashSet<uri::Https>,
+        preview: &mut Vec<CleanupPreview>,
+    ) -> Result<(), Fatal> {
+        for entry in fatal::read_dir(&self.collector.working_dir)? {
+            let entry = entry?;
+            if entry.is_file() {
+                preview.push(Self::preview_stray(&entry)?);
+            }
+            else if entry.is_dir() && !is_special_dir(entry.file_name()) {
+                for entry in fatal::read_dir(entry.path())? {
+                    let entry = entry?;
+                    if !entry.is_file() {
+                        preview.push(Self::preview_stray(&entry)?);
+                        continue
+                    }
+                    let state = match RrdpArchive::open(
+                        entry.path().to_path_buf().into()
+                    ).and_then(|archive| archive.load_state()) {
+                        Ok(state) => state,
+                        Err(err) if err.should_retry() => continue,
+                        Err(_) => return Err(Fatal),
+                    };
+                    if !retain.contains(&state.rpki_notify) {
+                        preview.push(CleanupPreview {
+                            uri: state.rpki_notify.to_string(),
+                            rrdp: true,
+                            size: entry.len(),
+                            reason: CleanupReason::Unused,
+                        });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates the preview item for removing stray data.
+    fn preview_stray(
+        entry: &fatal::DirEntry
+    ) -> Result<CleanupPreview, Fatal> {
+        let size = if entry.is_dir() {
+            dir_size(entry.path()).map_err(|err| {
+                error!(
+                    "Fatal: failed to determine size of {}: {}",
+                    entry.path().display(), err
+                );
+                Fatal
+            })?
+        }
+        else {
+            entry.len()
+        };
+        Ok(CleanupPreview {
+            uri: entry.path().display().to_string(),
+            rrdp: true,
+            size,
+            reason: CleanupReason::Stray,
+        })
+    }
+
+    /// Returns the cache entries for all repositories in the working dir.
+    pub fn cache_entries(&self) -> Result<Vec<CacheEntry>, Fatal> {
+        let updated = self.updated.read();
+        Ok(self.collector.load_sized_states()?.into_iter().map(
+            |(state, size, path)| CacheEntry {
+                used: updated.contains_key(&state.rpki_notify),
+                uri: state.rpki_notify.to_string(),
+                path,
+                rrdp: true,
+                size,
+            }
+        ).collect())
+    }
+
     /// Cleans up an authority directory.
     pub fn cleanup_tmp(
         &self,
@@ -516,6 +1035,30 @@ impl<'a> Run<'a> {
         Ok(())
     }
 
+    /// Cleans up the directory of partially downloaded snapshots.
+    ///
+    /// Keeps only the files of repositories that are to be retained.
+    #[allow(clippy::mutable_key_type)]
+    pub fn cleanup_partial(
+        &self,
+        path: &Path,
+        retain: &HashSet<uri::Https>
+    ) -> Result<(), Fatal> {
+        let keep: HashSet<_> = retain.iter().map(|uri| {
+            Collector::repository_file_name(uri)
+        }).collect();
+        for entry in fatal::read_dir(path)? {
+            let entry = entry?;
+            if entry.is_file() && entry.file_name().to_str().map(|name| {
+                keep.contains(name)
+            }).unwrap_or(false) {
+                continue
+            }
+            fatal::remove_all(entry.path())?;
+        }
+        Ok(())
+    }
+
     /// Cleans up an authority directory.
     #[allow(clippy::mutable_key_type)]
     pub fn cleanup_authority(
@@ -581,6 +1124,40 @@ impl<'a> Run<'a> {
 }
 
 
+//------------ RrdpRepositoryInfo --------------------------------------------
+
+/// Information about the local copy of an RRDP repository.
+///
+/// This is what the collector knows about a repository between validation
+/// runs. A list of these is returned by [`Collector::repository_info`].
+#[derive(Clone, Debug)]
+pub struct RrdpRepositoryInfo {
+    /// The rpkiNotify URI of the repository.
+    pub rpki_notify: uri::Https,
+
+    /// The session ID of the local copy.
+    pub session: Uuid,
+
+    /// The serial number of the local copy.
+    pub serial: u64,
+
+    /// The time of the last successful update.
+    pub updated: Option<DateTime<Utc>>,
+
+    /// The time of the last failed update.
+    pub failed: Option<DateTime<Utc>>,
+
+    /// The time after which the local copy is considered outdated.
+    pub best_before: Option<DateTime<Utc>>,
+
+    /// Will rsync be used should the next update fail?
+    pub fallback: bool,
+
+    /// The size of the repository file on disk in bytes.
+    pub size: u64,
+}
+
+
 //------------ RrdpConfig ----------------------------------------------------
 
 /// The configuration of the RRDP collector.
@@ -598,11 +1175,37 @@ pub struct RrdpConfig {
     /// The maximum allowed size for published objects.
     pub max_object_size: Option<u64>,
 
+    /// The maximum allowed on-disk size of a repository.
+    pub max_repository_size: Option<u64>,
+
+    /// The maximum number of objects in a snapshot or delta file.
+    pub max_objects: Option<u64>,
+
+    /// The maximum decoded size of all objects in a snapshot or delta file.
+    pub max_decoded_size: Option<u64>,
+
+    /// Per-repository overrides for the content limits.
+    pub limit_overrides: Arc<HashMap<uri::Https, RrdpLimits>>,
+
     /// The maximum number of deltas we process before using a snapshot.
     pub max_delta_count: usize,
 
+    /// What to do if there are more deltas than allowed.
+    pub delta_overflow: DeltaOverflowPolicy,
+
+    /// Per-repository overrides for the delta policy.
+    pub delta_overrides: Arc<HashMap<uri::Https, RrdpDeltaOverride>>,
+
     /// The maximum length of the delta list in a notification file.
     pub max_delta_list_len: usize,
+
+    /// The chunk size for ranged snapshot downloads.
+    ///
+    /// If this is `None`, snapshots are downloaded in a single request.
+    pub snapshot_chunk_size: Option<u64>,
+
+    /// The number of parallel requests for ranged snapshot downloads.
+    pub snapshot_parallelism: usize,
 }
 
 impl<'a> From<&'a Config> for RrdpConfig {
@@ -611,8 +1214,68 @@ impl<'a> From<&'a Config> for RrdpConfig {
             filter_dubious: !config.allow_dubious_hosts,
             fallback_time: FallbackTime::from_config(config),
             max_object_size: config.max_object_size,
+            max_repository_size: config.max_repository_size,
+            max_objects: config.rrdp_max_objects.map(|value| value as u64),
+            max_decoded_size: config.rrdp_max_decoded_size,
+            limit_overrides: Arc::new(config.rrdp_limit_overrides.clone()),
             max_delta_count: config.rrdp_max_delta_count,
+            delta_overflow: config.rrdp_delta_overflow,
+            delta_overrides: Arc::new(config.rrdp_delta_overrides.clone()),
             max_delta_list_len: config.rrdp_max_delta_list_len,
+            snapshot_chunk_size: config.rrdp_snapshot_chunk_size,
+            snapshot_parallelism: config.rrdp_snapshot_parallelism,
+        }
+    }
+}
+
+impl RrdpConfig {
+    /// Returns the delta policy for the given repository.
+    ///
+    /// Returns the maximum number of deltas to apply and what to do if
+    /// there are more.
+    fn delta_policy(
+        &self, rpki_notify: &uri::Https
+    ) -> (usize, DeltaOverflowPolicy) {
+        match self.delta_overrides.get(rpki_notify) {
+            Some(spec) => {
+                (
+                    spec.max_delta_count,
+                    spec.overflow.unwrap_or(self.delta_overflow)
+                )
+            }
+            None => (self.max_delta_count, self.delta_overflow)
+        }
+    }
+
+    /// Returns the content limits for the given repository.
+    ///
+    /// Limits that are not overridden for the repository are taken from
+    /// the global configuration. Limits that are `None` in the returned
+    /// value are not enforced.
+    pub fn limits(&self, rpki_notify: &uri::Https) -> RrdpLimits {
+        fn pick(
+            value: Option<u64>, global: Option<u64>
+        ) -> Option<u64> {
+            match value {
+                Some(0) => None,
+                Some(value) => Some(value),
+                None => global,
+            }
+        }
+
+        let spec = self.limit_overrides.get(rpki_notify).copied()
+            .unwrap_or_default();
+        RrdpLimits {
+            max_object_size: pick(
+                spec.max_object_size, self.max_object_size
+            ),
+            max_repository_size: pick(
+                spec.max_repository_size, self.max_repository_size
+            ),
+            max_objects: pick(spec.max_objects, self.max_objects),
+            max_decoded_size: pick(
+                spec.max_decoded_size, self.max_decoded_size
+            ),
         }
     }
 }
@@ -764,7 +1427,34 @@ impl<'a> RepositoryUpdate<'a> {
             current.1.best_before()
         );
 
-        let is_updated = self.update(current)?;
+        let has_current = current.is_some();
+        let backoff = self.collector.backoff.as_deref();
+        let is_updated = if backoff.is_some_and(|backoff| {
+            backoff.is_backing_off(self.rpki_notify.as_str())
+        }) {
+            // The repository failed recently. Skip it until it is due
+            // again.
+            self.metrics.notify_status = HttpStatus::Skipped;
+            false
+        }
+        else {
+            let is_updated = self.update(current)?;
+            if !is_updated && has_current {
+                self.record_failure()?;
+            }
+            if let Some(backoff) = backoff {
+                if is_updated {
+                    backoff.success(self.rpki_notify.as_str())
+                }
+                else {
+                    backoff.failure(
+                        self.rpki_notify.as_str(),
+                        self.metrics.failure_reason()
+                    )
+                }
+            }
+            is_updated
+        };
 
         self.metrics.duration = SystemTime::now().duration_since(start_time);
 
@@ -799,13 +1489,32 @@ impl<'a> RepositoryUpdate<'a> {
         &mut self,
         current: Option<(RrdpArchive, RepositoryState)>,
     ) -> Result<bool, RunFailed> {
+        if let Some((_, state)) = current.as_ref() {
+            if let Some(status) = self.collector.http.head_unchanged(
+                self.rpki_notify, state.etag.as_ref(), state.last_modified()
+            ) {
+                debug!(
+                    "RRDP {}: HEAD request shows no change.", self.rpki_notify
+                );
+                self.metrics.notify_status = status;
+                self.metrics.head_skipped = true;
+                self.not_modified(current)?;
+                return Ok(true)
+            }
+        }
+
+        self.metrics.requests += 1;
         let notify = match Notification::get(
             &self.collector.http, self.rpki_notify,
             current.as_ref().map(|x| &x.1),
             &mut self.metrics.notify_status,
+            &mut self.metrics.address_family,
             self.collector.config.max_delta_list_len,
         ) {
-            Ok(Some(notify)) => notify,
+            Ok(Some(notify)) => {
+                self.metrics.bytes += notify.size();
+                notify
+            }
             Ok(None) => {
                 self.not_modified(current)?;
                 return Ok(true)
@@ -832,6 +1541,29 @@ impl<'a> RepositoryUpdate<'a> {
         self.snapshot_update(&notify)
     }
 
+    /// Records the time of a failed update in the repository state.
+    ///
+    /// Since this is for information only, a broken state is ignored and
+    /// left for the next update to deal with.
+    fn record_failure(&self) -> Result<(), RunFailed> {
+        let mut archive = match RrdpArchive::try_open(self.path.clone()) {
+            Ok(Some(archive)) => archive,
+            Ok(None) => return Ok(()),
+            Err(err) if err.should_retry() => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        let mut state = match archive.load_state() {
+            Ok(state) => state,
+            Err(err) if err.should_retry() => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        state.failed_ts = Some(Utc::now().timestamp());
+        match archive.update_state(&state) {
+            Err(err) if !err.should_retry() => Err(err),
+            _ => Ok(())
+        }
+    }
+
     /// Handle the case of a Not Modified response.
     fn not_modified(
         &mut self,
@@ -859,7 +1591,7 @@ impl<'a> RepositoryUpdate<'a> {
     ) -> Result<bool, RunFailed> {
         debug!("RRDP {}: updating from snapshot.", self.rpki_notify);
         let (file, path) = self.collector.temp_file()?;
-        let mut archive = RrdpArchive::create_with_file(file, path.clone())?;
+        let mut archive = RrdpArchive::create_with_file(file, path)?;
         if let Err(err) = SnapshotUpdate::new(
             self.collector, &mut archive, notify, &mut self.metrics
         ).try_update() {
@@ -872,14 +1604,25 @@ impl<'a> RepositoryUpdate<'a> {
                     "RRDP {}: failed to process snapshot file {}: {}",
                     self.rpki_notify, notify.content().snapshot().uri(), err
                 );
+                if let Some(limit) = err.limit() {
+                    self.metrics.limit_violation = Some(limit);
+                }
                 return Ok(false)
             }
         }
         
+        self.install_archive(archive)?;
+        debug!("RRDP {}: snapshot update completed.", self.rpki_notify);
+        Ok(true)
+    }
+
+    /// Replaces the repository’s archive with the temporary `archive`.
+    fn install_archive(&self, archive: RrdpArchive) -> Result<(), RunFailed> {
         // XXX There is a possible issue here: Someone could unlink the
         //     temp file and replace it with something new and we will now
         //     copy that to the final location.
 
+        let path = archive.path().clone();
         if let Err(err) = fs::remove_file(self.path.as_ref()) {
             if !matches!(err.kind(), io::ErrorKind::NotFound) {
                 error!(
@@ -898,9 +1641,43 @@ impl<'a> RepositoryUpdate<'a> {
             );
             return Err(RunFailed::fatal())
         }
+        Ok(())
+    }
 
-        debug!("RRDP {}: snapshot update completed.", self.rpki_notify);
-        Ok(true)
+    /// Creates a working copy of the repository’s archive.
+    ///
+    /// The copy lives in a temporary file and can be moved into place via
+    /// [`install_archive`][Self::install_archive] once it has been updated
+    /// successfully or dropped via
+    /// [`discard_archive`][Self::discard_archive] otherwise.
+    fn copy_archive(&self) -> Result<RrdpArchive, RunFailed> {
+        let (file, path) = self.collector.temp_file()?;
+        drop(file);
+        if let Err(err) = fs::copy(self.path.as_ref(), path.as_ref()) {
+            error!(
+                "Fatal: Failed to copy RRDP repository file {} to {}: {}",
+                self.path.display(), path.display(), err
+            );
+            return Err(RunFailed::fatal())
+        }
+        match RrdpArchive::try_open(path.clone())? {
+            Some(archive) => Ok(archive),
+            None => {
+                error!(
+                    "Fatal: Temporary RRDP repository file {} disappeared.",
+                    path.display()
+                );
+                Err(RunFailed::fatal())
+            }
+        }
+    }
+
+    /// Drops a working copy of the repository’s archive.
+    fn discard_archive(&self, archive: RrdpArchive) {
+        let path = archive.path().clone();
+        drop(archive);
+        // Whatever is left over will be removed during cleanup.
+        let _ = fs::remove_file(path.as_ref());
     }
 
     /// Performs a delta update of the RRDP repository.
@@ -909,34 +1686,28 @@ impl<'a> RepositoryUpdate<'a> {
     /// anything at all if the repository is up-to-date. Returns whether the
     /// update succeeded. If `Ok(Some(reason))` is returned, a snapshot update
     /// should be tried next because of the reason given.
+    ///
+    /// Deltas are applied to a working copy of the archive which only
+    /// replaces the current archive once all deltas have been applied
+    /// successfully. If the update fails, the repository is left in its
+    /// previous state.
     fn delta_update(
         &mut self,
         notify: &Notification,
         mut archive: RrdpArchive,
         state: RepositoryState,
     ) -> Result<Option<SnapshotReason>, RunFailed> {
-        if let Err(err) = notify.content().delta_status() {
-            match err {
-                DeltaListError::Oversized => {
-                    info!(
-                        "RRDP {}: Overly large delta set in notification file",
-                        self.rpki_notify
-                    );
-                    return Ok(Some(SnapshotReason::LargeDeltaSet));
-                }
-            }
-        }
-
-        if let Err(reason) = notify.check_deltas(&state) {
-            return Ok(Some(reason))
-        }
-
-        let deltas = match self.calc_deltas(notify.content(), &state) {
+        let deltas = match self.collector.plan_update(
+            self.rpki_notify, notify, &state
+        ) {
             Ok(deltas) => deltas,
             Err(reason) => return Ok(Some(reason)),
         };
 
-        if !deltas.is_empty() {
+        let is_copy = !deltas.is_empty();
+        if is_copy {
+            drop(archive);
+            archive = self.copy_archive()?;
             let count = deltas.len();
             for (i, info) in deltas.iter().enumerate() {
                 debug!(
@@ -944,7 +1715,7 @@ impl<'a> RepositoryUpdate<'a> {
                     self.rpki_notify, i + 1, count
                 );
                 if let Err(err) = DeltaUpdate::new(
-                    self.collector, &mut archive,
+                    self.collector, &mut archive, self.rpki_notify,
                     notify.content().session_id(),
                     info, &mut self.metrics
                 ).try_update() {
@@ -952,17 +1723,39 @@ impl<'a> RepositoryUpdate<'a> {
                         "RRDP {}: failed to process delta: {}",
                         self.rpki_notify, err,
                     );
+                    if let Some(limit) = err.limit() {
+                        self.metrics.limit_violation = Some(limit);
+                    }
+                    self.discard_archive(archive);
                     return Ok(Some(SnapshotReason::ConflictingDelta))
                 }
+                self.metrics.deltas += 1;
             }
         }
 
-        // We are up-to-date now, so we can replace the state file with one
-        // reflecting the notification we’ve got originally. This will update
-        // the etag and last-modified data.
-        if let Err(err) = archive.update_state(
-            &notify.to_repository_state(self.collector.config.fallback_time)
-        ) {
+        // If we only applied some of the deltas, the state needs to reflect
+        // the last delta we have applied. Otherwise we are up-to-date now,
+        // so we can replace the state file with one reflecting the
+        // notification we’ve got originally. This will update the etag and
+        // last-modified data.
+        let fallback = self.collector.config.fallback_time;
+        let mut new_state = match deltas.last() {
+            Some(last) if last.serial() != notify.content().serial() => {
+                info!(
+                    "RRDP {}: Partial update to serial {}. Remaining deltas \
+                     will be applied during the next update.",
+                    self.rpki_notify, last.serial()
+                );
+                self.metrics.serial = Some(last.serial());
+                notify.to_partial_repository_state(last.serial(), fallback)
+            }
+            _ => notify.to_repository_state(fallback)
+        };
+        new_state.failed_ts = state.failed_ts;
+        if let Err(err) = archive.update_state(&new_state) {
+            if is_copy {
+                self.discard_archive(archive);
+            }
             if err.should_retry() {
                 return Ok(Some(SnapshotReason::CorruptArchive))
             }
@@ -970,74 +1763,12 @@ impl<'a> RepositoryUpdate<'a> {
                 return Err(err)
             }
         }
+        if is_copy {
+            self.install_archive(archive)?;
+        }
 
         debug!("RRDP {}: Delta update completed.", self.rpki_notify);
         Ok(None)
     }
-
-    /// Calculates the slice of deltas to follow for updating.
-    ///
-    /// Returns an empty slice if no update is necessary.
-    /// Returns a non-empty slice of the sequence of deltas to be applied.
-    fn calc_deltas<'b>(
-        &self,
-        notify: &'b NotificationFile,
-        state: &RepositoryState
-    ) -> Result<&'b [DeltaInfo], SnapshotReason> {
-        if notify.session_id() != state.session {
-            debug!("New session. Need to get snapshot.");
-            return Err(SnapshotReason::NewSession)
-        }
-        debug!("{}: Serials: us {}, them {}.",
-            self.rpki_notify, state.serial, notify.serial()
-        );
-        if notify.serial() == state.serial {
-            return Ok(&[]);
-        }
-
-        // If there is no last delta (remember, we have a different
-        // serial than the notification file) or if the last delta’s
-        // serial differs from that noted in the notification file,
-        // bail out.
-        if notify.deltas().last().map(|delta| delta.serial())
-            != Some(notify.serial())
-        {
-            debug!("Last delta serial differs from current serial.");
-            return Err(SnapshotReason::BadDeltaSet)
-        }
-
-        let mut deltas = notify.deltas();
-        let serial = match state.serial.checked_add(1) {
-            Some(serial) => serial,
-            None => return Err(SnapshotReason::LargeSerial)
-        };
-        loop {
-            let first = match deltas.first() {
-                Some(first) => first,
-                None => {
-                    debug!("Ran out of deltas.");
-                    return Err(SnapshotReason::BadDeltaSet)
-                }
-            };
-            match first.serial().cmp(&serial) {
-                cmp::Ordering::Greater => {
-                    debug!("First delta is too new ({})", first.serial());
-                    return Err(SnapshotReason::OutdatedLocal)
-                }
-                cmp::Ordering::Equal => break,
-                cmp::Ordering::Less => deltas = &deltas[1..]
-            }
-        }
-
-        if deltas.len() > self.collector.config.max_delta_count {
-            debug!(
-                "RRDP: {}: Too many delta steps required ({})",
-                self.rpki_notify, deltas.len()
-            );
-            return Err(SnapshotReason::TooManyDeltas)
-        }
-
-        Ok(deltas)
-    }
 }
 
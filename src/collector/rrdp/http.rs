@@ -1,17 +1,25 @@
-use std::{fs, io};
+use std::{fs, io, thread};
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::Entry;
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
 use bytes::Bytes;
 use chrono::{DateTime, Utc};
-use log::{error, warn};
+use log::{debug, error, warn};
 use reqwest::{header, redirect};
-use reqwest::{Certificate, Proxy, StatusCode};
+use reqwest::{Certificate, NoProxy, Proxy, StatusCode};
 use reqwest::blocking::{Client, ClientBuilder, RequestBuilder, Response};
 use rpki::uri;
-use crate::config::Config;
+use crate::config::{Config, RrdpOverride, RrdpTlsOverride};
 use crate::error::Fatal;
 use crate::utils::date::{format_http_date, parse_http_date};
+use crate::utils::net::AddressFamily;
+use crate::utils::ratelimit::{RateLimit, TokenBucket};
+use crate::utils::sync::Mutex;
+use crate::utils::tls::create_client_config;
+use crate::utils::uri::UriRewrite;
 
 
 //------------ HttpClient ----------------------------------------------------
@@ -25,17 +33,140 @@ pub struct HttpClient {
     /// that is not ideal but 
     client: Result<Client, Option<ClientBuilder>>,
 
+    /// Additional clients for connect timeouts and TLS trust settings.
+    ///
+    /// Reqwest only allows setting the connect timeout and the trusted
+    /// certificates for a client, so we need a separate client for each
+    /// combination of a connect timeout used in the per-repository overrides
+    /// and a host with its own TLS trust settings. The clients are ignited
+    /// together with the main client.
+    extra_clients: HashMap<
+        ClientKey, Result<Client, Option<ClientBuilder>>
+    >,
+
+    /// The hosts with their own TLS trust settings.
+    tls_hosts: HashSet<String>,
+
     /// The base directory for storing copies of responses if that is enabled.
     response_dir: Option<PathBuf>,
 
+    /// The local directory to read responses from instead if enabled.
+    local_dir: Option<PathBuf>,
+
     /// The timeout for requests.
     timeout: Option<Duration>,
+
+    /// The number of times a failed request is retried.
+    retries: usize,
+
+    /// The time to wait before the first retry.
+    retry_backoff: Duration,
+
+    /// Whether to probe notification files with a HEAD request first.
+    head_probe: bool,
+
+    /// Per-repository overrides for timeouts and retries.
+    overrides: HashMap<String, RrdpOverride>,
+
+    /// The rules for rewriting URIs before requesting them.
+    rewrite: UriRewrite,
+
+    /// The bandwidth limit for each repository in bytes per second.
+    bandwidth_limit: Option<u64>,
+
+    /// Per-repository overrides of the bandwidth limit.
+    bandwidth_overrides: HashMap<uri::Https, u64>,
+
+    /// The token buckets of the limited repositories.
+    buckets: Mutex<HashMap<uri::Https, Arc<TokenBucket>>>,
 }
 
 impl HttpClient {
     /// Creates a new, not-yet-ignited client based on the config.
     pub fn new(config: &Config) -> Result<Self, Fatal> {
+        let headers = Self::create_headers(config)?;
+        let proxy_password = match config.rrdp_proxy_password.as_ref() {
+            Some(secret) => Some(
+                secret.load_string(
+                    "RRDP proxy password"
+                ).map_err(|_| Fatal)?
+            ),
+            None => None,
+        };
+        let proxy_password = proxy_password.as_deref();
+        let mut timeouts = HashSet::new();
+        timeouts.insert(None);
+        timeouts.extend(
+            config.rrdp_overrides.values().filter_map(|value| {
+                value.connect_timeout.map(Some)
+            })
+        );
+        let mut hosts = vec![(None, None)];
+        hosts.extend(config.rrdp_tls_overrides.iter().map(|(host, tls)| {
+            (Some(host.clone()), Some(tls))
+        }));
+        let mut extra_clients = HashMap::new();
+        for &connect_timeout in &timeouts {
+            for (tls_host, tls) in &hosts {
+                if connect_timeout.is_none() && tls_host.is_none() {
+                    continue
+                }
+                let key = ClientKey {
+                    connect_timeout, tls_host: tls_host.clone()
+                };
+                if let Entry::Vacant(entry) = extra_clients.entry(key) {
+                    entry.insert(Err(Some(Self::create_builder(
+                        config, &headers, proxy_password,
+                        connect_timeout.or(config.rrdp_connect_timeout),
+                        tls_host.as_deref().zip(*tls),
+                    )?)));
+                }
+            }
+        }
+        if let Some(dir) = config.rrdp_local_dir.as_ref() {
+            warn!(
+                "Reading RRDP data from local directory {} instead of \
+                 fetching it. This is intended for testing only.",
+                dir.display()
+            );
+        }
+        Ok(HttpClient {
+            client: Err(Some(
+                Self::create_builder(
+                    config, &headers, proxy_password,
+                    config.rrdp_connect_timeout, None
+                )?
+            )),
+            extra_clients,
+            tls_hosts: config.rrdp_tls_overrides.keys().cloned().collect(),
+            response_dir: config.rrdp_keep_responses.clone(),
+            local_dir: config.rrdp_local_dir.clone(),
+            timeout: config.rrdp_timeout,
+            retries: config.rrdp_retries,
+            retry_backoff: config.rrdp_retry_backoff,
+            head_probe: config.rrdp_head_probe,
+            overrides: config.rrdp_overrides.clone(),
+            rewrite: UriRewrite::new(&config.uri_rewrites),
+            bandwidth_limit: config.rrdp_bandwidth_limit,
+            bandwidth_overrides: config.rrdp_bandwidth_overrides.clone(),
+            buckets: Default::default(),
+        })
+    }
 
+    /// Creates a client builder based on the config.
+    ///
+    /// The default headers are taken from `headers` and the proxy password
+    /// from `proxy_password` so they only need to be created once. The
+    /// connect timeout is taken from `connect_timeout` rather than the
+    /// config. If `tls` is given, the client uses the TLS trust settings
+    /// for the given host instead of the default trusted certificates.
+    fn create_builder(
+        config: &Config,
+        headers: &header::HeaderMap,
+        proxy_password: Option<&str>,
+        connect_timeout: Option<Duration>,
+        tls: Option<(&str, &RrdpTlsOverride)>,
+    ) -> Result<ClientBuilder, Fatal> {
         // Deal with the reqwest’s TLS features by defining a creator
         // function for the two cases.
         #[cfg(not(feature = "native-tls"))]
@@ -50,39 +181,186 @@ impl HttpClient {
 
         let mut builder = create_builder();
         builder = builder.user_agent(&config.rrdp_user_agent);
+        builder = builder.default_headers(headers.clone());
         builder = builder.tcp_keepalive(config.rrdp_tcp_keepalive);
         builder = builder.timeout(None); // Set per request.
+        let compress = !config.rrdp_disable_compression;
+        builder = builder.gzip(compress).deflate(compress).brotli(compress);
         builder = builder.redirect(
             redirect::Policy::custom(Self::redirect_policy)
         );
-        if let Some(timeout) = config.rrdp_connect_timeout {
+        if let Some(timeout) = connect_timeout {
             builder = builder.connect_timeout(timeout);
         }
         if let Some(addr) = config.rrdp_local_addr {
             builder = builder.local_address(addr)
         }
-        for path in &config.rrdp_root_certs {
-            builder = builder.add_root_certificate(
-                Self::load_cert(path)?
+        if let Some((host, tls)) = tls {
+            builder = builder.use_preconfigured_tls(
+                create_client_config(
+                    &format!("RRDP host {}", host),
+                    tls.ca_bundle.as_deref(), &tls.pinned_certs,
+                ).map_err(|_| Fatal)?
             );
         }
+        else {
+            for path in &config.rrdp_root_certs {
+                builder = builder.add_root_certificate(
+                    Self::load_cert(path)?
+                );
+            }
+        }
+        let no_proxy = NoProxy::from_string(&config.rrdp_no_proxy.join(","));
+        if let Some(proxy) = config.rrdp_http_proxy.as_ref() {
+            builder = builder.proxy(Self::create_proxy(
+                config, proxy_password, proxy, "rrdp-http-proxy", &no_proxy,
+                Proxy::http
+            )?);
+        }
+        if let Some(proxy) = config.rrdp_https_proxy.as_ref() {
+            builder = builder.proxy(Self::create_proxy(
+                config, proxy_password, proxy, "rrdp-https-proxy",
+                &no_proxy, Proxy::https
+            )?);
+        }
         for proxy in &config.rrdp_proxies {
-            let proxy = match Proxy::all(proxy) {
-                Ok(proxy) => proxy,
-                Err(err) => {
+            builder = builder.proxy(Self::create_proxy(
+                config, proxy_password, proxy, "rrdp-proxy", &no_proxy,
+                Proxy::all
+            )?);
+        }
+        Ok(builder)
+    }
+
+    /// Creates the additional headers from the config.
+    ///
+    /// Each header in `rrdp_headers` must be given as its name and value
+    /// separated by a colon. White space around the value is ignored.
+    /// Giving credentials this way is deprecated, so a warning is logged
+    /// for headers that look like they carry one. The values of the headers
+    /// in `rrdp_header_secrets` are loaded from their secrets. Both kinds
+    /// of credentials are marked as sensitive.
+    fn create_headers(
+        config: &Config
+    ) -> Result<header::HeaderMap, Fatal> {
+        let mut res = header::HeaderMap::new();
+        for item in &config.rrdp_headers {
+            let (name, value) = match item.split_once(':') {
+                Some(pair) => pair,
+                None => {
                     error!(
-                        "Invalid rrdp-proxy '{}': {}", proxy, err
+                        "Invalid rrdp-header '{}': expected 'name: value'.",
+                        item
                     );
                     return Err(Fatal)
                 }
             };
-            builder = builder.proxy(proxy);
+            let name = match header::HeaderName::from_bytes(
+                name.trim().as_bytes()
+            ) {
+                Ok(name) => name,
+                Err(err) => {
+                    error!("Invalid rrdp-header '{}': {}", item, err);
+                    return Err(Fatal)
+                }
+            };
+            let mut value = match header::HeaderValue::from_str(
+                value.trim()
+            ) {
+                Ok(value) => value,
+                Err(err) => {
+                    error!("Invalid rrdp-header '{}': {}", item, err);
+                    return Err(Fatal)
+                }
+            };
+            if is_credential_header(&name) {
+                warn!(
+                    "Giving credentials such as the RRDP header '{}' via \
+                     rrdp-header is deprecated. Please use \
+                     rrdp-header-secret instead.",
+                    name
+                );
+                value.set_sensitive(true);
+            }
+            res.append(name, value);
         }
-        Ok(HttpClient {
-            client: Err(Some(builder)),
-            response_dir: config.rrdp_keep_responses.clone(),
-            timeout: config.rrdp_timeout,
-        })
+        for (name, secret) in &config.rrdp_header_secrets {
+            let what = format!("value of RRDP header '{}'", name);
+            let value = secret.load_string(&what).map_err(|_| Fatal)?;
+            let name = match header::HeaderName::from_bytes(name.as_bytes()) {
+                Ok(name) => name,
+                Err(err) => {
+                    error!("Invalid RRDP header name '{}': {}", name, err);
+                    return Err(Fatal)
+                }
+            };
+            let mut value = match header::HeaderValue::from_str(
+                value.trim()
+            ) {
+                Ok(value) => value,
+                Err(err) => {
+                    error!("Invalid {}: {}", what, err);
+                    return Err(Fatal)
+                }
+            };
+            value.set_sensitive(true);
+            res.append(name, value);
+        }
+        Ok(res)
+    }
+
+    /// Creates a proxy from its URI.
+    ///
+    /// The proxy is created via `create` and applies the authentication
+    /// and no-proxy settings from `config` with the password taken from
+    /// `proxy_password`. Since SOCKS5 proxies don’t use
+    /// HTTP authentication, the credentials are added to their URI
+    /// instead. The `what` argument names the option for error messages.
+    fn create_proxy(
+        config: &Config,
+        proxy_password: Option<&str>,
+        proxy: &str,
+        what: &str,
+        no_proxy: &Option<NoProxy>,
+        create: fn(String) -> Result<Proxy, reqwest::Error>,
+    ) -> Result<Proxy, Fatal> {
+        let socks = proxy.starts_with("socks5");
+        let mut uri = proxy.to_string();
+        if let (true, Some(username)) = (
+            socks, config.rrdp_proxy_username.as_ref()
+        ) {
+            let mut url = match reqwest::Url::parse(proxy) {
+                Ok(url) => url,
+                Err(err) => {
+                    error!("Invalid {} '{}': {}", what, proxy, err);
+                    return Err(Fatal)
+                }
+            };
+            if url.set_username(username).is_err()
+                || url.set_password(proxy_password).is_err()
+            {
+                error!(
+                    "Invalid {} '{}': cannot add credentials", what, proxy
+                );
+                return Err(Fatal)
+            }
+            uri = url.into();
+        }
+        let mut res = match create(uri) {
+            Ok(proxy) => proxy,
+            Err(err) => {
+                error!("Invalid {} '{}': {}", what, proxy, err);
+                return Err(Fatal)
+            }
+        };
+        if !socks {
+            if let Some(username) = config.rrdp_proxy_username.as_ref() {
+                res = res.basic_auth(
+                    username, proxy_password.unwrap_or("")
+                );
+            }
+        }
+        Ok(res.no_proxy(no_proxy.clone()))
     }
 
     /// Ignites the client.
@@ -91,7 +369,18 @@ impl HttpClient {
     /// be called after any potential fork on Unix systems because it spawns
     /// threads.
     pub fn ignite(&mut self) -> Result<(), Fatal> {
-        let builder = match self.client.as_mut() {
+        Self::ignite_client(&mut self.client)?;
+        for client in self.extra_clients.values_mut() {
+            Self::ignite_client(client)?;
+        }
+        Ok(())
+    }
+
+    /// Ignites a single client.
+    fn ignite_client(
+        client: &mut Result<Client, Option<ClientBuilder>>
+    ) -> Result<(), Fatal> {
+        let builder = match client.as_mut() {
             Ok(_) => return Ok(()),
             Err(builder) => match builder.take() {
                 Some(builder) => builder,
@@ -101,14 +390,14 @@ impl HttpClient {
                 }
             }
         };
-        let client = match builder.build() {
+        let built = match builder.build() {
             Ok(client) => client,
             Err(err) => {
                 error!("Failed to initialize HTTP client: {}.", err);
                 return Err(Fatal)
             }
         };
-        self.client = Ok(client);
+        *client = Ok(built);
         Ok(())
     }
 
@@ -150,20 +439,356 @@ impl HttpClient {
         self.client.as_ref().expect("HTTP client has not been ignited")
     }
 
+    /// Returns the client to use for the given URI and override.
+    ///
+    /// The client is selected based on the connect timeout of the override
+    /// and the TLS trust settings for the host of the URI.
+    fn override_client(
+        &self, uri: &uri::Https, value: Option<&RrdpOverride>
+    ) -> &Client {
+        let key = ClientKey {
+            connect_timeout: value.and_then(|value| value.connect_timeout),
+            tls_host: {
+                self.tls_hosts.get(uri.authority()).cloned()
+            },
+        };
+        if let Some(Ok(client)) = self.extra_clients.get(&key) {
+            return client
+        }
+        self.client()
+    }
+
+    /// Returns the timeout and retry override for a repository.
+    ///
+    /// Overrides for the rpkiNotify URI take precedence over those for its
+    /// host.
+    fn repository_override(
+        &self, rpki_notify: Option<&uri::Https>
+    ) -> Option<&RrdpOverride> {
+        let rpki_notify = rpki_notify?;
+        self.overrides.get(rpki_notify.as_str()).or_else(|| {
+            self.overrides.get(rpki_notify.authority())
+        })
+    }
+
+    /// Creates a GET request for the given URI.
+    ///
+    /// Applies the URI rewrite rules and uses the client for the override.
+    fn get(
+        &self, uri: &uri::Https, value: Option<&RrdpOverride>
+    ) -> RequestBuilder {
+        let target = self.rewrite.rewrite(uri.as_str());
+        if target != uri.as_str() {
+            debug!("RRDP {}: requesting from {}.", uri, target);
+        }
+        self.override_client(uri, value).get(target.as_ref())
+    }
+
+    /// Returns the response from the local directory if that is enabled.
+    ///
+    /// The URI rewrite rules are applied first. The path of the file is
+    /// constructed from the components of the rewritten URI starting with
+    /// the authority in the same way as for kept responses. URIs with
+    /// components that could lead outside the directory are treated as
+    /// not found.
+    fn local_response(
+        &self, uri: &uri::Https, limit: RateLimit
+    ) -> Option<HttpResponse> {
+        let dir = self.local_dir.as_ref()?;
+        let target = self.rewrite.rewrite(uri.as_str());
+        let target = target.strip_prefix("https://").unwrap_or(&target);
+        if target.split('/').any(|item| item == "..") {
+            debug!("RRDP {}: not a valid local path.", uri);
+            return Some(HttpResponse::local(None, limit))
+        }
+        Some(HttpResponse::local(Some(&dir.join(target)), limit))
+    }
+
+    /// Sends a request, retrying if necessary.
+    ///
+    /// Applies the request timeout and, if the request fails with a
+    /// connection error, a timeout, or a server error status, retries it
+    /// according to the configuration or the override given.
+    fn send(
+        &self,
+        uri: &uri::Https,
+        mut request: RequestBuilder,
+        value: Option<&RrdpOverride>,
+    ) -> Result<Response, reqwest::Error> {
+        let timeout = value.and_then(|value| value.timeout).or(self.timeout);
+        let retries = value.and_then(|value| value.retries).unwrap_or(
+            self.retries
+        );
+        let mut backoff = value.and_then(|value| value.retry_backoff)
+            .unwrap_or(self.retry_backoff);
+        if let Some(timeout) = timeout {
+            request = request.timeout(timeout);
+        }
+        let mut attempt = 0;
+        loop {
+            let next = if attempt < retries {
+                request.try_clone()
+            }
+            else {
+                None
+            };
+            let err = match request.send().and_then(|response| {
+                response.error_for_status()
+            }) {
+                Ok(response) => return Ok(response),
+                Err(err) => err,
+            };
+            let retry = err.is_connect() || err.is_timeout()
+                || err.status().map(|status| {
+                    status.is_server_error()
+                }).unwrap_or(false);
+            match next {
+                Some(next) if retry => {
+                    attempt += 1;
+                    debug!(
+                        "RRDP {}: {}. Retrying in {}s ({}/{}).",
+                        uri, err, backoff.as_secs(), attempt, retries
+                    );
+                    thread::sleep(backoff);
+                    backoff = backoff.saturating_mul(2);
+                    request = next;
+                }
+                _ => return Err(err)
+            }
+        }
+    }
+
+    /// Checks whether the server for the given URI is reachable.
+    ///
+    /// Sends an HTTP HEAD request for the URI, applying the URI rewrite
+    /// rules, and returns whether a successful response was received
+    /// within `timeout`.
+    pub fn probe(&self, uri: &uri::Https, timeout: Duration) -> bool {
+        if let Some(response) = self.local_response(
+            uri, RateLimit::default()
+        ) {
+            return response.status().is_success()
+        }
+        let target = self.rewrite.rewrite(uri.as_str());
+        let request = self.override_client(uri, None).head(target.as_ref());
+        match request.timeout(timeout).send() {
+            Ok(response) => response.status().is_success(),
+            Err(err) => {
+                debug!("RRDP {}: probe failed: {}", uri, err);
+                false
+            }
+        }
+    }
+
+    /// Checks via a HEAD request whether a notification file is unchanged.
+    ///
+    /// The file is considered unchanged if the server responds with the
+    /// same ETag as given in `etag` or, if there is no ETag, with the same
+    /// Last-Modified date as given in `last_modified`. In this case, the
+    /// status of the response is returned. If probing is disabled, there
+    /// is nothing to compare with, or the file may have changed, returns
+    /// `None` and the file should be requested as usual.
+    pub fn head_unchanged(
+        &self,
+        uri: &uri::Https,
+        etag: Option<&Bytes>,
+        last_modified: Option<DateTime<Utc>>,
+    ) -> Option<HttpStatus> {
+        if !self.head_probe || (etag.is_none() && last_modified.is_none())
+            || self.local_dir.is_some()
+        {
+            return None
+        }
+        let value = self.repository_override(Some(uri));
+        let target = self.rewrite.rewrite(uri.as_str());
+        let mut request = self.override_client(uri, value).head(
+            target.as_ref()
+        );
+        if let Some(timeout) = value.and_then(|value| {
+            value.timeout
+        }).or(self.timeout) {
+            request = request.timeout(timeout);
+        }
+        let response = match request.send() {
+            Ok(response) => response,
+            Err(err) => {
+                debug!("RRDP {}: HEAD request failed: {}", uri, err);
+                return None
+            }
+        };
+        if response.status() != StatusCode::OK {
+            return None
+        }
+        let unchanged = match etag {
+            Some(etag) => {
+                header_etag(response.headers()).as_ref() == Some(etag)
+            }
+            None => {
+                header_last_modified(response.headers()) == last_modified
+            }
+        };
+        if unchanged {
+            Some(response.status().into())
+        }
+        else {
+            None
+        }
+    }
+
+    /// Returns the rate limit for transfers of the given repository.
+    ///
+    /// All transfers for the same repository share the same limit.
+    pub fn rate_limit(&self, rpki_notify: &uri::Https) -> RateLimit {
+        let mut res = RateLimit::default();
+        let rate = match self.bandwidth_overrides.get(rpki_notify) {
+            Some(0) => return res,
+            Some(rate) => *rate,
+            None => match self.bandwidth_limit {
+                Some(rate) => rate,
+                None => return res,
+            }
+        };
+        res.push(
+            self.buckets.lock().entry(rpki_notify.clone()).or_insert_with(|| {
+                Arc::new(TokenBucket::new(rate))
+            }).clone()
+        );
+        res
+    }
+
+    /// Returns the length of the resource if it can be requested in ranges.
+    ///
+    /// Sends an HTTP HEAD request for the URI and returns the content
+    /// length if the server indicates support for byte ranges. Returns
+    /// `None` if the request failed or ranges are not supported.
+    pub fn ranged_length(
+        &self, uri: &uri::Https, rpki_notify: &uri::Https,
+    ) -> Option<u64> {
+        if self.local_dir.is_some() {
+            return None
+        }
+        let value = self.repository_override(Some(rpki_notify));
+        let target = self.rewrite.rewrite(uri.as_str());
+        // Ranges refer to the encoded content, so we must not allow any
+        // compression here.
+        let mut request = self.override_client(uri, value).head(
+            target.as_ref()
+        ).header(header::ACCEPT_ENCODING, "identity");
+        if let Some(timeout) = value.and_then(|value| {
+            value.timeout
+        }).or(self.timeout) {
+            request = request.timeout(timeout);
+        }
+        let response = match request.send() {
+            Ok(response) => response,
+            Err(err) => {
+                debug!("RRDP {}: HEAD request failed: {}", uri, err);
+                return None
+            }
+        };
+        if !response.status().is_success() {
+            return None
+        }
+        let headers = response.headers();
+        if headers.get(header::ACCEPT_RANGES)?.as_bytes() != b"bytes" {
+            return None
+        }
+        headers.get(header::CONTENT_LENGTH)?.to_str().ok()?.parse().ok()
+    }
+
+    /// Performs an HTTP GET request for a byte range of the given URI.
+    ///
+    /// The range starts at `start` and ends right before `end`. The caller
+    /// needs to check that the response does indeed contain the range.
+    ///
+    /// Responses to range requests are never kept and the caller is
+    /// responsible for applying the rate limit.
+    pub fn range_response(
+        &self,
+        uri: &uri::Https,
+        rpki_notify: &uri::Https,
+        start: u64,
+        end: u64,
+    ) -> Result<Response, reqwest::Error> {
+        let value = self.repository_override(Some(rpki_notify));
+        let request = self.get(uri, value).header(
+            header::RANGE, format!("bytes={}-{}", start, end - 1)
+        ).header(
+            header::ACCEPT_ENCODING, "identity"
+        );
+        self.send(uri, request, value)
+    }
+
+    /// Performs an HTTP GET request for the remainder of the given URI.
+    ///
+    /// Requests the content starting at byte `start` through to the end.
+    /// The caller needs to check that the response does indeed contain
+    /// this range via [`HttpResponse::range_start`].
+    ///
+    /// The rate limit of the repository is applied when reading the
+    /// response. The response is never kept.
+    pub fn resume_response(
+        &self,
+        uri: &uri::Https,
+        rpki_notify: &uri::Https,
+        start: u64,
+    ) -> Result<HttpResponse, reqwest::Error> {
+        if let Some(response) = self.local_response(
+            uri, self.rate_limit(rpki_notify)
+        ) {
+            return Ok(response)
+        }
+        let value = self.repository_override(Some(rpki_notify));
+        let request = self.get(uri, value).header(
+            header::RANGE, format!("bytes={}-", start)
+        ).header(
+            header::ACCEPT_ENCODING, "identity"
+        );
+        self.send(uri, request, value).map(|response| {
+            HttpResponse::create(
+                response, uri, &None, self.rate_limit(rpki_notify), false
+            )
+        })
+    }
+
+    /// Returns whether responses are kept.
+    pub fn keeps_responses(&self) -> bool {
+        self.response_dir.is_some()
+    }
+
     /// Performs an HTTP GET request for the given URI.
     ///
     /// If keeping responses is enabled, the response is written to a file
     /// corresponding to the URI. If the resource behind the URI changes over
     /// time and this change should be tracked, set `multi` to `true` to
     /// include the current time in the file name.
+    ///
+    /// If the URI matches one of the configured rewrite rules, the request
+    /// is sent to the rewritten URI instead.
+    ///
+    /// If `rpki_notify` is given, the rate limit of that repository is
+    /// applied when reading the response.
     pub fn response(
         &self,
         uri: &uri::Https,
+        rpki_notify: Option<&uri::Https>,
         multi: bool,
     ) -> Result<HttpResponse, reqwest::Error> {
-        self._response(uri, self.client().get(uri.as_str()), multi)
+        let limit = match rpki_notify {
+            Some(rpki_notify) => self.rate_limit(rpki_notify),
+            None => RateLimit::default(),
+        };
+        if let Some(response) = self.local_response(uri, limit.clone()) {
+            return Ok(response)
+        }
+        let value = self.repository_override(rpki_notify);
+        self._response(uri, self.get(uri, value), value, limit, multi)
     }
 
+    /// Performs a conditional HTTP GET request for a notification file.
+    ///
+    /// The rate limit for the repository is applied when reading the
+    /// response.
     pub fn conditional_response(
         &self,
         uri: &uri::Https,
@@ -171,7 +796,13 @@ impl HttpClient {
         last_modified: Option<DateTime<Utc>>,
         multi: bool,
     ) -> Result<HttpResponse, reqwest::Error> {
-        let mut request = self.client().get(uri.as_str());
+        if let Some(response) = self.local_response(
+            uri, self.rate_limit(uri)
+        ) {
+            return Ok(response)
+        }
+        let value = self.repository_override(Some(uri));
+        let mut request = self.get(uri, value);
         if let Some(etag) = etag {
             request = request.header(
                 header::IF_NONE_MATCH, etag.as_ref()
@@ -183,23 +814,22 @@ impl HttpClient {
                 format_http_date(last_modified)
             );
         }
-        self._response(uri, request, multi)
+        self._response(uri, request, value, self.rate_limit(uri), multi)
     }
 
     /// Creates a response from a request builder.
     fn _response(
         &self,
         uri: &uri::Https,
-        mut request: RequestBuilder,
+        request: RequestBuilder,
+        value: Option<&RrdpOverride>,
+        limit: RateLimit,
         multi: bool
     ) -> Result<HttpResponse, reqwest::Error> {
-        if let Some(timeout) = self.timeout {
-            request = request.timeout(timeout);
-        }
-        request.send().and_then(|response| {
-            response.error_for_status()
-        }).map(|response| {
-            HttpResponse::create(response, uri, &self.response_dir, multi)
+        self.send(uri, request, value).map(|response| {
+            HttpResponse::create(
+                response, uri, &self.response_dir, limit, multi
+            )
         })
     }
 
@@ -285,15 +915,37 @@ impl HttpClient {
 }
 
 
+//------------ ClientKey -----------------------------------------------------
+
+/// The key for selecting one of the additional HTTP clients.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct ClientKey {
+    /// The connect timeout from a per-repository override.
+    connect_timeout: Option<Duration>,
+
+    /// The host whose TLS trust settings the client uses.
+    tls_host: Option<String>,
+}
+
+
 //------------ HttpResponse --------------------------------------------------
 
 /// Wraps a reqwest response for added features.
+///
+/// If RRDP data is read from a local directory, the response wraps a file
+/// instead.
 pub struct HttpResponse {
-    /// The wrapped reqwest response.
-    response: Response,
+    /// The wrapped reqwest response or local file.
+    response: ResponseSource,
 
     /// A file to also store read data into.
     file: Option<fs::File>,
+
+    /// The rate limit to apply when reading.
+    limit: RateLimit,
+
+    /// The number of bytes read from the response so far.
+    bytes_read: u64,
 }
 
 impl HttpResponse {
@@ -305,20 +957,78 @@ impl HttpResponse {
     /// `false` the last component will be the file name. If `multi` is
     /// `true` the last component will be a directory, too, and the file name
     /// will be the ISO timestamp of the current time.
+    ///
+    /// Reading the response will be limited by `limit`.
     pub fn create(
         response: Response,
         uri: &uri::Https,
         response_dir: &Option<PathBuf>,
+        limit: RateLimit,
         multi: bool
     ) -> Self {
         HttpResponse {
-            response,
+            response: ResponseSource::Remote(response),
             file: response_dir.as_ref().and_then(|base| {
                 Self::open_file(base, uri, multi)
-            })
+            }),
+            limit,
+            bytes_read: 0,
         }
     }
 
+    /// Creates a new response for the local file at `path`.
+    ///
+    /// If the file exists, the response has a status of 200 OK and its
+    /// content. If it doesn’t or `path` is `None`, the status is 404 Not
+    /// Found. Any other error results in 500 Internal Server Error. In the
+    /// latter two cases, the response is empty.
+    fn local(path: Option<&Path>, limit: RateLimit) -> Self {
+        let path = match path {
+            Some(path) => path,
+            None => {
+                return HttpResponse {
+                    response: ResponseSource::Local {
+                        status: StatusCode::NOT_FOUND, len: None, file: None
+                    },
+                    file: None, limit, bytes_read: 0,
+                }
+            }
+        };
+        let response = match fs::File::open(path) {
+            Ok(file) => {
+                ResponseSource::Local {
+                    status: StatusCode::OK,
+                    len: file.metadata().ok().map(|meta| meta.len()),
+                    file: Some(file),
+                }
+            }
+            Err(err) => {
+                debug!("Failed to open {}: {}", path.display(), err);
+                ResponseSource::Local {
+                    status: if err.kind() == io::ErrorKind::NotFound {
+                        StatusCode::NOT_FOUND
+                    }
+                    else {
+                        StatusCode::INTERNAL_SERVER_ERROR
+                    },
+                    len: None,
+                    file: None,
+                }
+            }
+        };
+        HttpResponse { response, file: None, limit, bytes_read: 0 }
+    }
+
+    /// Returns the number of bytes read from the response so far.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Returns the address family of the connection used for the response.
+    pub fn address_family(&self) -> Option<AddressFamily> {
+        self.response.remote()?.remote_addr().as_ref().map(AddressFamily::of)
+    }
+
     /// Opens the file mirroring file.
     ///
     /// See [`create`][Self::create] for the rules.
@@ -367,7 +1077,37 @@ impl HttpResponse {
 
     /// Returns the value of the content length header if present.
     pub fn content_length(&self) -> Option<u64> {
-        self.response.content_length()
+        match self.response {
+            ResponseSource::Remote(ref response) => response.content_length(),
+            ResponseSource::Local { len, .. } => len,
+        }
+    }
+
+    /// Returns whether the content could later be requested in ranges.
+    ///
+    /// This is the case if the server indicates support for byte ranges
+    /// and the content has not been transparently decompressed, in which
+    /// case the content length would have been removed.
+    pub fn accepts_ranges(&self) -> bool {
+        let headers = match self.response.remote() {
+            Some(response) => response.headers(),
+            None => return false,
+        };
+        headers.get(header::ACCEPT_RANGES).map(|value| {
+            value.as_bytes() == b"bytes"
+        }).unwrap_or(false)
+        && headers.contains_key(header::CONTENT_LENGTH)
+    }
+
+    /// Returns the start of the range given in the Content-Range header.
+    ///
+    /// Returns `None` if the header is missing or malformed.
+    pub fn range_start(&self) -> Option<u64> {
+        self.response.remote()?.headers().get(header::CONTENT_RANGE)?
+            .to_str().ok()?
+            .strip_prefix("bytes ")?
+            .split_once('-')?.0
+            .parse().ok()
     }
 
     /// Copies the full content of the response to the given writer.
@@ -382,7 +1122,10 @@ impl HttpResponse {
 
     /// Returns the status code of the response.
     pub fn status(&self) -> StatusCode {
-        self.response.status()
+        match self.response {
+            ResponseSource::Remote(ref response) => response.status(),
+            ResponseSource::Local { status, .. } => status,
+        }
     }
 
     /// Returns the value of the ETag header if present.
@@ -396,14 +1139,7 @@ impl HttpResponse {
     /// The method returns a `Bytes` value as there is a good chance the
     /// tag is short enough to be be inlined.
     pub fn etag(&self) -> Option<Bytes> {
-        let mut etags = self.response.headers()
-            .get_all(header::ETAG)
-            .into_iter();
-        let etag = etags.next()?;
-        if etags.next().is_some() {
-            return None
-        }
-        Self::parse_etag(etag.as_bytes())
+        header_etag(self.response.remote()?.headers())
     }
 
     /// Parses the ETag value.
@@ -440,18 +1176,95 @@ impl HttpResponse {
     /// The method quietly returns `None` if the content of a header is
     /// malformed or if there is more than one occurence of the header.
     pub fn last_modified(&self) -> Option<DateTime<Utc>> {
-        let mut iter = self.response.headers()
-            .get_all(header::LAST_MODIFIED)
-            .into_iter();
-        let value = iter.next()?;
-        if iter.next().is_some() {
-            return None
+        header_last_modified(self.response.remote()?.headers())
+    }
+}
+
+
+//------------ ResponseSource ------------------------------------------------
+
+/// Where the content of an [`HttpResponse`] comes from.
+enum ResponseSource {
+    /// A response received from an HTTP server.
+    Remote(Response),
+
+    /// A file in the local directory.
+    Local {
+        /// The status code to pretend.
+        status: StatusCode,
+
+        /// The size of the file if known.
+        len: Option<u64>,
+
+        /// The file if it could be opened.
+        file: Option<fs::File>,
+    }
+}
+
+impl ResponseSource {
+    /// Returns the reqwest response if this is one.
+    fn remote(&self) -> Option<&Response> {
+        match self {
+            ResponseSource::Remote(response) => Some(response),
+            ResponseSource::Local { .. } => None,
+        }
+    }
+}
+
+impl io::Read for ResponseSource {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        match self {
+            ResponseSource::Remote(response) => response.read(buf),
+            ResponseSource::Local { file: Some(file), .. } => file.read(buf),
+            ResponseSource::Local { file: None, .. } => Ok(0),
         }
-        parse_http_date(value.to_str().ok()?)
     }
 }
 
 
+//------------ Helper Functions ----------------------------------------------
+
+/// Returns whether a header is likely to carry a credential.
+fn is_credential_header(name: &header::HeaderName) -> bool {
+    if *name == header::AUTHORIZATION
+        || *name == header::PROXY_AUTHORIZATION
+        || *name == header::COOKIE
+    {
+        return true
+    }
+    let name = name.as_str();
+    ["auth", "key", "password", "secret", "token"].iter().any(|part| {
+        name.contains(part)
+    })
+}
+
+/// Returns the value of the ETag header in a header map.
+///
+/// See [`HttpResponse::etag`] for details.
+fn header_etag(headers: &header::HeaderMap) -> Option<Bytes> {
+    let mut etags = headers.get_all(header::ETAG).into_iter();
+    let etag = etags.next()?;
+    if etags.next().is_some() {
+        return None
+    }
+    HttpResponse::parse_etag(etag.as_bytes())
+}
+
+/// Returns the value of the Last-Modified header in a header map.
+///
+/// See [`HttpResponse::last_modified`] for details.
+fn header_last_modified(
+    headers: &header::HeaderMap
+) -> Option<DateTime<Utc>> {
+    let mut iter = headers.get_all(header::LAST_MODIFIED).into_iter();
+    let value = iter.next()?;
+    if iter.next().is_some() {
+        return None
+    }
+    parse_http_date(value.to_str().ok()?)
+}
+
+
 //--- Read
 
 impl io::Read for HttpResponse {
@@ -460,6 +1273,8 @@ impl io::Read for HttpResponse {
         if let Some(file) = self.file.as_mut() {
             file.write_all(&buf[..res])?;
         }
+        self.limit.take(res);
+        self.bytes_read += res as u64;
         Ok(res)
     }
 }
@@ -476,6 +1291,9 @@ pub enum HttpStatus {
     /// The repository URI was rejected.
     Rejected,
 
+    /// The update was skipped because the repository failed recently.
+    Skipped,
+
     /// An error happened.
     Error
 }
@@ -485,6 +1303,7 @@ impl HttpStatus {
         match self {
             HttpStatus::Response(code) => code.as_u16() as i16,
             HttpStatus::Rejected => -2,
+            HttpStatus::Skipped => -3,
             HttpStatus::Error => -1,
         }
     }
@@ -510,3 +1329,64 @@ impl From<StatusCode> for HttpStatus {
     }
 }
 
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn credential_headers() {
+        for name in ["authorization", "cookie", "x-api-key", "x-token"] {
+            assert!(is_credential_header(
+                &header::HeaderName::from_static(name)
+            ));
+        }
+        for name in ["x-site", "accept-language"] {
+            assert!(!is_credential_header(
+                &header::HeaderName::from_static(name)
+            ));
+        }
+    }
+
+    #[test]
+    fn local_response() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("rrdp.example.net")).unwrap();
+        fs::write(
+            dir.path().join("rrdp.example.net/notification.xml"), b"foo"
+        ).unwrap();
+        let mut config = Config::default_with_paths(
+            Default::default(), dir.path().into()
+        );
+        config.rrdp_local_dir = Some(dir.path().into());
+        let client = HttpClient::new(&config).unwrap();
+
+        let mut response = client.response(
+            &uri::Https::from_str(
+                "https://rrdp.example.net/notification.xml"
+            ).unwrap(),
+            None, false
+        ).unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.content_length(), Some(3));
+        assert!(!response.accepts_ranges());
+        let mut content = Vec::new();
+        response.copy_to(&mut content).unwrap();
+        assert_eq!(content, b"foo");
+        assert_eq!(response.bytes_read(), 3);
+
+        for uri in [
+            "https://rrdp.example.net/missing.xml",
+            "https://rrdp.example.net/../rrdp.example.net/notification.xml",
+        ] {
+            let response = client.response(
+                &uri::Https::from_str(uri).unwrap(), None, false
+            ).unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        }
+    }
+}
@@ -1,8 +1,10 @@
 #![allow(dead_code)]
 
-pub use self::base::{Collector, LoadResult, ReadRepository, Run};
+pub use self::base::{
+    Collector, LoadResult, ReadRepository, RrdpRepositoryInfo, Run
+};
 pub use self::http::HttpStatus;
-pub use self::update::SnapshotReason;
+pub use self::update::{RrdpLimit, SnapshotReason};
 
 mod archive;
 mod base;
@@ -1,19 +1,25 @@
 
-use std::{error, fmt, io};
+use std::{cmp, error, fmt, fs, io, thread};
 use std::collections::HashSet;
-use std::io::Read;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use bytes::Bytes;
 use chrono::{DateTime, Utc};
-use log::{error, warn};
+use log::{debug, error, warn};
 use reqwest::StatusCode;
 use ring::digest;
 use ring::constant_time::verify_slices_are_equal;
 use rpki::{rrdp, uri};
 use rpki::rrdp::{DeltaInfo, NotificationFile, ProcessDelta, ProcessSnapshot};
 use uuid::Uuid;
+use crate::config::RrdpLimits;
 use crate::error::{Failed, RunFailed};
 use crate::metrics::RrdpRepositoryMetrics;
 use crate::utils::archive::{ArchiveError, PublishError};
+use crate::utils::net::AddressFamily;
+use crate::utils::ratelimit::{LimitedRead, RateLimit};
 use super::archive::{AccessError, FallbackTime, RepositoryState, RrdpArchive};
 use super::base::Collector;
 use super::http::{HttpClient, HttpResponse, HttpStatus};
@@ -34,13 +40,18 @@ pub struct Notification {
 
     /// The Last-Modified value if provided,
     last_modified: Option<DateTime<Utc>>,
+
+    /// The size of the notification file in bytes.
+    size: u64,
 }
 
 impl Notification {
     /// Requests, parses, and returns the given RRDP notification file.
     ///
     /// The value referred to by `status` will be updated to the received
-    /// status code or `HttpStatus::Error` if the request failed.
+    /// status code or `HttpStatus::Error` if the request failed. The value
+    /// referred to by `family` will be updated to the address family of the
+    /// connection if a response was received.
     ///
     /// Returns the notification file on success. Returns `Ok(None)` if a
     /// response was received successfully but indicated that the
@@ -50,6 +61,7 @@ impl Notification {
         uri: &uri::Https,
         state: Option<&RepositoryState>,
         status: &mut HttpStatus,
+        family: &mut Option<AddressFamily>,
         delta_list_limit: usize,
     ) -> Result<Option<Self>, Failed> {
         let response = match http.conditional_response(
@@ -60,6 +72,7 @@ impl Notification {
         ) {
             Ok(response) => {
                 *status = response.status().into();
+                *family = response.address_family();
                 response
             }
             Err(err) => {
@@ -91,12 +104,12 @@ impl Notification {
     ///
     /// Assumes that the response status was 200 OK.
     fn from_response(
-        uri: uri::Https, response: HttpResponse, delta_list_limit: usize
+        uri: uri::Https, mut response: HttpResponse, delta_list_limit: usize
     ) -> Result<Self, Failed> {
         let etag = response.etag();
         let last_modified = response.last_modified();
         let mut content = NotificationFile::parse_limited(
-            io::BufReader::new(response), delta_list_limit
+            io::BufReader::new(&mut response), delta_list_limit
         ).map_err(|err| {
             warn!("RRDP {}: {}", uri, err);
             Failed
@@ -108,7 +121,15 @@ impl Notification {
             return Err(Failed)
         }
         content.sort_deltas();
-        Ok(Notification { uri, content, etag, last_modified })
+        Ok(Notification {
+            uri, content, etag, last_modified,
+            size: response.bytes_read(),
+        })
+    }
+
+    /// Returns the size of the notification file in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
     }
 
     /// Returns a reference to the content of the notification file.
@@ -131,6 +152,24 @@ impl Notification {
             delta_state: self.content.deltas().iter().map(|delta| {
                 (delta.serial(), delta.hash())
             }).collect(),
+            failed_ts: None,
+        }
+    }
+
+    /// Creates repository state for a partial update from this notification.
+    ///
+    /// The state reflects a repository that has only been updated up to
+    /// and including the delta with the serial number `serial`. Because
+    /// the notification file will be needed again for the remaining
+    /// deltas, the Etag and Last-Modified values are not retained.
+    pub fn to_partial_repository_state(
+        &self, serial: u64, fallback: FallbackTime,
+    ) -> RepositoryState {
+        RepositoryState {
+            serial,
+            last_modified_ts: None,
+            etag: None,
+            .. self.to_repository_state(fallback)
         }
     }
 
@@ -172,6 +211,12 @@ pub struct SnapshotUpdate<'a> {
 
     /// The metrics for the update.
     metrics: &'a mut RrdpRepositoryMetrics,
+
+    /// The content limits for the repository.
+    limits: RrdpLimits,
+
+    /// The content processed so far.
+    content: ContentCount,
 }
 
 impl<'a> SnapshotUpdate<'a> {
@@ -181,29 +226,132 @@ impl<'a> SnapshotUpdate<'a> {
         notify: &'a Notification,
         metrics: &'a mut RrdpRepositoryMetrics,
     ) -> Self {
-        SnapshotUpdate { collector, archive, notify, metrics }
+        SnapshotUpdate {
+            collector, archive, notify, metrics,
+            limits: collector.config().limits(&notify.uri),
+            content: Default::default(),
+        }
     }
 
     pub fn try_update(mut self) -> Result<(), SnapshotError> {
-        let response = match self.collector.http().response(
-            self.notify.content.snapshot().uri(), false
-        ) {
+        if let Some(file) = self.ranged_download()? {
+            return self.update_from(file)
+        }
+
+        let mut partial = self.open_partial()?;
+        let offset = partial.as_ref().map(|partial| partial.len).unwrap_or(0);
+        let uri = self.notify.content.snapshot().uri();
+        self.metrics.requests += 1;
+        let response = if offset > 0 {
+            self.collector.http().resume_response(
+                uri, &self.notify.uri, offset
+            )
+        }
+        else {
+            self.collector.http().response(
+                uri, Some(&self.notify.uri), false
+            )
+        };
+        let mut response = match response {
             Ok(response) => {
                 self.metrics.payload_status = Some(response.status().into());
-                if response.status() != StatusCode::OK {
-                    return Err(response.status().into())
-                }
-                else {
-                    response
-                }
+                response
             }
             Err(err) => {
                 self.metrics.payload_status = Some(HttpStatus::Error);
                 return Err(err.into())
             }
         };
+        let resumed = offset > 0
+            && response.status() == StatusCode::PARTIAL_CONTENT
+            && response.range_start() == Some(offset);
+        if resumed {
+            debug!(
+                "RRDP {}: resuming snapshot download at byte {}.",
+                self.notify.uri, offset
+            );
+        }
+        else if response.status() != StatusCode::OK {
+            if matches!(
+                response.status(),
+                StatusCode::PARTIAL_CONTENT
+                    | StatusCode::RANGE_NOT_SATISFIABLE
+            ) {
+                // The server doesn’t like our range. Start over next time.
+                if let Some(partial) = partial.take() {
+                    partial.remove();
+                }
+            }
+            return Err(response.status().into())
+        }
+        else if !response.accepts_ranges() {
+            // We wouldn’t be able to resume, so don’t bother storing.
+            if let Some(partial) = partial.take() {
+                partial.remove();
+            }
+        }
+        else if let Some(partial) = partial.as_mut() {
+            partial.reset()?;
+        }
+
+        let prefix = match (resumed, partial.as_ref()) {
+            (true, Some(partial)) => Some(partial.reader()?),
+            _ => None,
+        };
+        let mut tee = TeeRead::new(&mut response, partial.as_mut());
+        let res = match prefix {
+            Some(prefix) => self.update_from(prefix.chain(&mut tee)),
+            None => self.update_from(&mut tee),
+        };
+        let read_failed = tee.read_failed;
+        self.metrics.bytes += response.bytes_read();
+        if let Some(partial) = partial {
+            if res.is_err() && read_failed && partial.len > 0 {
+                debug!(
+                    "RRDP {}: keeping {} bytes of interrupted snapshot \
+                     download.",
+                    self.notify.uri, partial.len
+                );
+            }
+            else {
+                partial.remove();
+            }
+        }
+        res
+    }
 
-        let mut reader = io::BufReader::new(HashRead::new(response));
+    /// Opens the file for a partial download of the snapshot.
+    ///
+    /// Returns `Ok(None)` if partial downloads cannot be used, either
+    /// because responses are kept or because the file cannot be opened.
+    fn open_partial(
+        &self
+    ) -> Result<Option<PartialSnapshot>, SnapshotError> {
+        if self.collector.http().keeps_responses() {
+            return Ok(None)
+        }
+        let path = self.collector.partial_path(
+            &self.notify.uri
+        ).map_err(|_| RunFailed::fatal())?;
+        match PartialSnapshot::open(
+            path.clone(), self.notify.content.snapshot().hash()
+        ) {
+            Ok(partial) => Ok(Some(partial)),
+            Err(err) => {
+                warn!(
+                    "RRDP {}: cannot open partial snapshot file {}: {}",
+                    self.notify.uri, path.display(), err
+                );
+                Ok(None)
+            }
+        }
+    }
+
+    /// Updates the repository from the snapshot data read from `source`.
+    fn update_from(
+        &mut self, source: impl io::Read
+    ) -> Result<(), SnapshotError> {
+        let mut reader = io::BufReader::new(HashRead::new(source));
         self.process(&mut reader)?;
         let hash = reader.into_inner().into_hash();
         if verify_slices_are_equal(
@@ -219,6 +367,153 @@ impl<'a> SnapshotUpdate<'a> {
         )?;
         Ok(())
     }
+
+    /// Downloads the snapshot file in parallel chunks if enabled.
+    ///
+    /// If ranged downloads are enabled and the server supports them for a
+    /// snapshot file larger than one chunk, the chunks are requested in
+    /// parallel and written straight into a temporary file which is
+    /// returned, rewound to its start. Otherwise returns `Ok(None)` and the
+    /// snapshot should be downloaded in a single request.
+    ///
+    /// The chunks are not kept in memory but written to their place in the
+    /// file as they are received. The hash of the snapshot is checked while
+    /// processing the file afterwards.
+    fn ranged_download(
+        &mut self
+    ) -> Result<Option<fs::File>, SnapshotError> {
+        let config = self.collector.config();
+        let chunk_size = match config.snapshot_chunk_size {
+            Some(size) => size,
+            None => return Ok(None)
+        };
+        let http = self.collector.http();
+        if http.keeps_responses() {
+            return Ok(None)
+        }
+        let uri = self.notify.content.snapshot().uri();
+        let len = match http.ranged_length(uri, &self.notify.uri) {
+            Some(len) if len > chunk_size => len,
+            _ => return Ok(None)
+        };
+        let count = (len + chunk_size - 1) / chunk_size;
+        let workers = cmp::min(
+            config.snapshot_parallelism.max(1) as u64, count
+        );
+        debug!(
+            "RRDP {}: downloading snapshot in {} chunks.",
+            self.notify.uri, count
+        );
+
+        let mut file = self.collector.anonymous_temp_file().map_err(|_| {
+            RunFailed::fatal()
+        })?;
+        let limit = http.rate_limit(&self.notify.uri);
+        let next = AtomicU64::new(0);
+        let (tx, rx) = mpsc::sync_channel(workers as usize);
+        let res = thread::scope(|scope| {
+            for _ in 0..workers {
+                let tx = tx.clone();
+                let next = &next;
+                let limit = &limit;
+                let rpki_notify = &self.notify.uri;
+                let file = &file;
+                scope.spawn(move || {
+                    loop {
+                        let idx = next.fetch_add(1, Ordering::Relaxed);
+                        if idx >= count {
+                            break
+                        }
+                        let start = idx * chunk_size;
+                        let end = cmp::min(start + chunk_size, len);
+                        let res = Self::fetch_range(
+                            http, uri, rpki_notify, limit, file, start, end
+                        );
+                        let failed = res.is_err();
+                        if tx.send(res).is_err() || failed {
+                            break
+                        }
+                    }
+                });
+            }
+            drop(tx);
+
+            // Dropping the receiver when returning early makes the
+            // workers stop after their current request.
+            for res in rx {
+                self.metrics.requests += 1;
+                self.metrics.bytes += res?;
+            }
+            Ok(())
+        });
+        match res {
+            Ok(()) => {
+                self.metrics.payload_status = Some(
+                    StatusCode::PARTIAL_CONTENT.into()
+                );
+            }
+            Err(SnapshotError::HttpStatus(status)) => {
+                self.metrics.payload_status = Some(status.into());
+                return Err(SnapshotError::HttpStatus(status))
+            }
+            Err(err) => {
+                self.metrics.payload_status = Some(HttpStatus::Error);
+                return Err(err)
+            }
+        }
+        file.rewind()?;
+        Ok(Some(file))
+    }
+
+    /// Fetches the given byte range of a file into the same range of `file`.
+    ///
+    /// Returns the number of bytes received.
+    fn fetch_range(
+        http: &HttpClient,
+        uri: &uri::Https,
+        rpki_notify: &uri::Https,
+        limit: &RateLimit,
+        file: &fs::File,
+        start: u64,
+        end: u64,
+    ) -> Result<u64, SnapshotError> {
+        let response = http.range_response(uri, rpki_notify, start, end)?;
+        if response.status() != StatusCode::PARTIAL_CONTENT {
+            return Err(response.status().into())
+        }
+        let mut response = LimitedRead::new(response, limit.clone());
+        let mut buf = vec![0; RANGE_BUF_SIZE];
+        let mut pos = start;
+        loop {
+            let read = match response.read(&mut buf) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {
+                    continue
+                }
+                Err(err) => return Err(err.into())
+            };
+            if pos + read as u64 > end {
+                return Err(SnapshotError::Rrdp(
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "long range response"
+                    ).into()
+                ))
+            }
+            write_all_at(file, &buf[..read], pos)?;
+            pos += read as u64;
+        }
+        if pos != end {
+            return Err(SnapshotError::Rrdp(
+                io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "short range response"
+                ).into()
+            ))
+        }
+        Ok(end - start)
+    }
 }
 
 impl<'a> ProcessSnapshot for SnapshotUpdate<'a> {
@@ -250,8 +545,9 @@ impl<'a> ProcessSnapshot for SnapshotUpdate<'a> {
         data: &mut rrdp::ObjectReader,
     ) -> Result<(), Self::Err> {
         let content = RrdpDataRead::new(
-            data, &uri, self.collector.config().max_object_size,
+            data, &uri, self.limits.max_object_size,
         ).read_all()?;
+        self.content.add(content.len(), &self.limits)?;
         self.archive.publish_object(&uri, &content).map_err(|err| match err {
             PublishError::AlreadyExists => {
                 SnapshotError::DuplicateObject(uri.clone())
@@ -271,7 +567,13 @@ impl<'a> ProcessSnapshot for SnapshotUpdate<'a> {
                 );
                 SnapshotError::RunFailed(RunFailed::fatal())
             }
-        })
+        })?;
+        if let Some(limit) = self.limits.max_repository_size {
+            if self.archive.size() > limit {
+                return Err(SnapshotError::LargeRepository(limit))
+            }
+        }
+        Ok(())
     }
 }
 
@@ -291,6 +593,9 @@ pub struct DeltaUpdate<'a> {
     /// The archive the repository is stored in.
     archive: &'a mut RrdpArchive,
 
+    /// The rpkiNotify URI of the repository.
+    rpki_notify: &'a uri::Https,
+
     /// The session ID of the RRDP session.
     session_id: Uuid,
 
@@ -304,6 +609,12 @@ pub struct DeltaUpdate<'a> {
     ///
     /// This is so we can error out if a URI was touched more than once.
     seen: HashSet<uri::Rsync>,
+
+    /// The content limits for the repository.
+    limits: RrdpLimits,
+
+    /// The content processed so far.
+    content: ContentCount,
 }
 
 impl<'a> DeltaUpdate<'a> {
@@ -311,19 +622,23 @@ impl<'a> DeltaUpdate<'a> {
     pub fn new(
         collector: &'a Collector,
         archive: &'a mut RrdpArchive,
+        rpki_notify: &'a uri::Https,
         session_id: Uuid,
         info: &'a DeltaInfo,
         metrics: &'a mut RrdpRepositoryMetrics,
     ) -> Self {
         DeltaUpdate {
-            collector, archive, session_id, info, metrics,
+            collector, archive, rpki_notify, session_id, info, metrics,
             seen: Default::default(),
+            limits: collector.config().limits(rpki_notify),
+            content: Default::default(),
         }
     }
 
     pub fn try_update(mut self) -> Result<(), DeltaError> {
-        let response = match self.collector.http().response(
-            self.info.uri(), false
+        self.metrics.requests += 1;
+        let mut response = match self.collector.http().response(
+            self.info.uri(), Some(self.rpki_notify), false
         ) {
             Ok(response) => {
                 self.metrics.payload_status = Some(response.status().into());
@@ -340,9 +655,11 @@ impl<'a> DeltaUpdate<'a> {
             }
         };
 
-        let mut reader = io::BufReader::new(HashRead::new(response));
-        self.process(&mut reader)?;
+        let mut reader = io::BufReader::new(HashRead::new(&mut response));
+        let res = self.process(&mut reader);
         let hash = reader.into_inner().into_hash();
+        self.metrics.bytes += response.bytes_read();
+        res?;
         if verify_slices_are_equal(
             hash.as_ref(),
             self.info.hash().as_ref()
@@ -384,8 +701,9 @@ impl<'a> ProcessDelta for DeltaUpdate<'a> {
             return Err(DeltaError::ObjectRepeated { uri })
         }
         let content = RrdpDataRead::new(
-            data, &uri, self.collector.config().max_object_size
+            data, &uri, self.limits.max_object_size
         ).read_all()?;
+        self.content.add(content.len(), &self.limits)?;
         match hash {
             Some(hash) => {
                 self.archive.update_object(
@@ -414,7 +732,13 @@ impl<'a> ProcessDelta for DeltaUpdate<'a> {
                     }
                 })
             }
+        }?;
+        if let Some(limit) = self.limits.max_repository_size {
+            if self.archive.size() > limit {
+                return Err(DeltaError::LargeRepository(limit))
+            }
         }
+        Ok(())
     }
 
     fn withdraw(
@@ -425,6 +749,7 @@ impl<'a> ProcessDelta for DeltaUpdate<'a> {
         if !self.seen.insert(uri.clone()) {
             return Err(DeltaError::ObjectRepeated { uri })
         }
+        self.content.add(0, &self.limits)?;
         self.archive.delete_object(&uri, hash).map_err(|err| match err {
             AccessError::NotFound => {
                 DeltaError::MissingObject { uri: uri.clone() }
@@ -438,6 +763,178 @@ impl<'a> ProcessDelta for DeltaUpdate<'a> {
 }
 
 
+//------------ write_all_at --------------------------------------------------
+
+/// The size of the buffer used when receiving a byte range.
+const RANGE_BUF_SIZE: usize = 64 * 1024;
+
+/// Writes all of `buf` into `file` starting at `offset`.
+///
+/// This doesn’t use the file’s cursor, so multiple threads can write to
+/// different parts of the file at the same time.
+#[cfg(unix)]
+fn write_all_at(file: &fs::File, buf: &[u8], offset: u64) -> io::Result<()> {
+    std::os::unix::fs::FileExt::write_all_at(file, buf, offset)
+}
+
+#[cfg(windows)]
+fn write_all_at(
+    file: &fs::File, mut buf: &[u8], mut offset: u64
+) -> io::Result<()> {
+    use std::os::windows::fs::FileExt;
+
+    while !buf.is_empty() {
+        let written = file.seek_write(buf, offset)?;
+        if written == 0 {
+            return Err(io::ErrorKind::WriteZero.into())
+        }
+        buf = &buf[written..];
+        offset += written as u64;
+    }
+    Ok(())
+}
+
+
+//------------ PartialSnapshot -----------------------------------------------
+
+/// The stored beginning of an interrupted snapshot download.
+///
+/// The file starts with the hash of the complete snapshot file. This is
+/// followed by the data received so far.
+struct PartialSnapshot {
+    /// The path of the file.
+    path: PathBuf,
+
+    /// The file, positioned at its end.
+    file: fs::File,
+
+    /// The hash of the complete snapshot file.
+    hash: rrdp::Hash,
+
+    /// The number of bytes of snapshot data in the file.
+    len: u64,
+}
+
+impl PartialSnapshot {
+    /// The length of the hash at the start of the file.
+    const HASH_LEN: u64 = 32;
+
+    /// Opens the file for a snapshot with the given hash.
+    ///
+    /// If the file already exists and is for the same snapshot, its data
+    /// can be used. Otherwise it is emptied.
+    fn open(path: PathBuf, hash: rrdp::Hash) -> Result<Self, io::Error> {
+        let mut file = fs::OpenOptions::new()
+            .read(true).write(true).create(true).truncate(false)
+            .open(&path)?;
+        let len = file.metadata()?.len();
+        let mut stored = [0u8; Self::HASH_LEN as usize];
+        let mut res = if len >= Self::HASH_LEN
+            && file.read_exact(&mut stored).is_ok()
+            && stored == hash.as_ref()
+        {
+            PartialSnapshot {
+                path, file, hash, len: len - Self::HASH_LEN
+            }
+        }
+        else {
+            PartialSnapshot { path, file, hash, len: 0 }
+        };
+        if res.len > 0 {
+            res.file.seek(SeekFrom::End(0))?;
+        }
+        else {
+            res.reset()?;
+        }
+        Ok(res)
+    }
+
+    /// Removes all stored data.
+    fn reset(&mut self) -> Result<(), io::Error> {
+        self.file.set_len(0)?;
+        self.file.rewind()?;
+        self.file.write_all(self.hash.as_ref())?;
+        self.len = 0;
+        Ok(())
+    }
+
+    /// Returns a reader for the stored data.
+    fn reader(&self) -> Result<impl io::Read, io::Error> {
+        let mut file = fs::File::open(&self.path)?;
+        file.seek(SeekFrom::Start(Self::HASH_LEN))?;
+        Ok(file.take(self.len))
+    }
+
+    /// Appends data to the file.
+    fn append(&mut self, data: &[u8]) -> Result<(), io::Error> {
+        self.file.write_all(data)?;
+        self.len += data.len() as u64;
+        Ok(())
+    }
+
+    /// Removes the file.
+    fn remove(self) {
+        drop(self.file);
+        if let Err(err) = fs::remove_file(&self.path) {
+            if err.kind() != io::ErrorKind::NotFound {
+                warn!(
+                    "Failed to delete partial snapshot file {}: {}",
+                    self.path.display(), err
+                );
+            }
+        }
+    }
+}
+
+
+//------------ TeeRead -------------------------------------------------------
+
+/// A reader wrapper that stores all read data in a partial snapshot.
+struct TeeRead<'a, R> {
+    /// The wrapped reader.
+    reader: R,
+
+    /// The partial snapshot to store data in.
+    ///
+    /// This becomes `None` if storing fails.
+    partial: Option<&'a mut PartialSnapshot>,
+
+    /// Did reading from the wrapped reader fail?
+    read_failed: bool,
+}
+
+impl<'a, R> TeeRead<'a, R> {
+    /// Creates a new tee reader.
+    fn new(reader: R, partial: Option<&'a mut PartialSnapshot>) -> Self {
+        TeeRead { reader, partial, read_failed: false }
+    }
+}
+
+impl<'a, R: io::Read> io::Read for TeeRead<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        let res = match self.reader.read(buf) {
+            Ok(res) => res,
+            Err(err) => {
+                if err.kind() != io::ErrorKind::Interrupted {
+                    self.read_failed = true;
+                }
+                return Err(err)
+            }
+        };
+        if let Some(partial) = self.partial.as_mut() {
+            if let Err(err) = partial.append(&buf[..res]) {
+                warn!(
+                    "Failed to write partial snapshot file {}: {}",
+                    partial.path.display(), err
+                );
+                self.partial = None;
+            }
+        }
+        Ok(res)
+    }
+}
+
+
 //------------ HashRead ------------------------------------------------------
 
 /// A reader wrapper that calculates the SHA-256 hash of all read data.
@@ -586,6 +1083,44 @@ impl<'a, R: io::Read> io::Read for RrdpDataRead<'a, R> {
 }
 
 
+//------------ ContentCount --------------------------------------------------
+
+/// The amount of content processed from a snapshot or delta file.
+#[derive(Clone, Copy, Debug, Default)]
+struct ContentCount {
+    /// The number of elements processed.
+    objects: u64,
+
+    /// The accumulated size of the decoded objects.
+    decoded_size: u64,
+}
+
+impl ContentCount {
+    /// Adds an element with an object of size `len`.
+    ///
+    /// Returns an error if this exceeds one of the limits.
+    fn add(
+        &mut self, len: usize, limits: &RrdpLimits
+    ) -> Result<(), RrdpDataReadError> {
+        self.objects += 1;
+        self.decoded_size = self.decoded_size.saturating_add(
+            u64::try_from(len).unwrap_or(u64::MAX)
+        );
+        if let Some(limit) = limits.max_objects {
+            if self.objects > limit {
+                return Err(RrdpDataReadError::TooManyObjects(limit))
+            }
+        }
+        if let Some(limit) = limits.max_decoded_size {
+            if self.decoded_size > limit {
+                return Err(RrdpDataReadError::LargeDecodedSize(limit))
+            }
+        }
+        Ok(())
+    }
+}
+
+
 //------------ SnapshotReason ------------------------------------------------
 
 /// The reason why a snapshot was used.
@@ -643,18 +1178,57 @@ impl SnapshotReason {
 }
 
 
+//------------ RrdpLimit -----------------------------------------------------
+
+/// A content limit that was exceeded by an RRDP update.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RrdpLimit {
+    /// An object was larger than allowed.
+    ObjectSize,
+
+    /// The repository grew larger than allowed.
+    RepositorySize,
+
+    /// A snapshot or delta file contained too many objects.
+    ObjectCount,
+
+    /// The objects of a snapshot or delta file were too large in total.
+    DecodedSize,
+}
+
+impl RrdpLimit {
+    /// All limits.
+    pub const ALL: [Self; 4] = [
+        RrdpLimit::ObjectSize, RrdpLimit::RepositorySize,
+        RrdpLimit::ObjectCount, RrdpLimit::DecodedSize,
+    ];
+
+    /// Returns a shorthand code for the limit.
+    pub fn code(self) -> &'static str {
+        match self {
+            RrdpLimit::ObjectSize => "object-size",
+            RrdpLimit::RepositorySize => "repository-size",
+            RrdpLimit::ObjectCount => "objects",
+            RrdpLimit::DecodedSize => "decoded-size",
+        }
+    }
+}
+
+
 //============ Errors ========================================================
 
 //------------ RrdpDataReadError ---------------------------------------------
 
 /// An error happened while reading object data.
 ///
-/// This covers both the case where the maximum allowed file size was
-/// exhausted as well as where reading data failed. Neither of them is fatal,
+/// This covers both the case where one of the content limits was
+/// exceeded as well as where reading data failed. Neither of them is fatal,
 /// so we need to process them separately.
 #[derive(Debug)]
 enum RrdpDataReadError {
     LargeObject(uri::Rsync),
+    TooManyObjects(u64),
+    LargeDecodedSize(u64),
     Read(io::Error),
 }
 
@@ -682,9 +1256,29 @@ pub enum SnapshotError {
     DuplicateObject(uri::Rsync),
     HashMismatch,
     LargeObject(uri::Rsync),
+    LargeRepository(u64),
+    TooManyObjects(u64),
+    LargeDecodedSize(u64),
     RunFailed(RunFailed),
 }
 
+impl SnapshotError {
+    /// Returns the content limit that was exceeded if any.
+    pub fn limit(&self) -> Option<RrdpLimit> {
+        match *self {
+            SnapshotError::LargeObject(_) => Some(RrdpLimit::ObjectSize),
+            SnapshotError::LargeRepository(_) => {
+                Some(RrdpLimit::RepositorySize)
+            }
+            SnapshotError::TooManyObjects(_) => Some(RrdpLimit::ObjectCount),
+            SnapshotError::LargeDecodedSize(_) => {
+                Some(RrdpLimit::DecodedSize)
+            }
+            _ => None
+        }
+    }
+}
+
 impl From<reqwest::Error> for SnapshotError {
     fn from(err: reqwest::Error) -> Self {
         SnapshotError::Http(err)
@@ -721,6 +1315,12 @@ impl From<RrdpDataReadError> for SnapshotError {
             RrdpDataReadError::LargeObject(uri) => {
                 SnapshotError::LargeObject(uri)
             }
+            RrdpDataReadError::TooManyObjects(limit) => {
+                SnapshotError::TooManyObjects(limit)
+            }
+            RrdpDataReadError::LargeDecodedSize(limit) => {
+                SnapshotError::LargeDecodedSize(limit)
+            }
             RrdpDataReadError::Read(err) => {
                 SnapshotError::Rrdp(err.into())
             }
@@ -761,6 +1361,18 @@ impl fmt::Display for SnapshotError {
             SnapshotError::LargeObject(ref uri) => {
                 write!(f, "object exceeds size limit: {}", uri)
             }
+            SnapshotError::LargeRepository(limit) => {
+                write!(f, "repository exceeds size limit of {} bytes", limit)
+            }
+            SnapshotError::TooManyObjects(limit) => {
+                write!(f, "snapshot exceeds limit of {} objects", limit)
+            }
+            SnapshotError::LargeDecodedSize(limit) => {
+                write!(
+                    f, "snapshot content exceeds size limit of {} bytes",
+                    limit
+                )
+            }
             SnapshotError::RunFailed(_) => Ok(()),
         }
     }
@@ -803,9 +1415,27 @@ pub enum DeltaError {
     },
     DeltaHashMismatch,
     LargeObject(uri::Rsync),
+    LargeRepository(u64),
+    TooManyObjects(u64),
+    LargeDecodedSize(u64),
     Archive(ArchiveError),
 }
 
+impl DeltaError {
+    /// Returns the content limit that was exceeded if any.
+    pub fn limit(&self) -> Option<RrdpLimit> {
+        match *self {
+            DeltaError::LargeObject(_) => Some(RrdpLimit::ObjectSize),
+            DeltaError::LargeRepository(_) => {
+                Some(RrdpLimit::RepositorySize)
+            }
+            DeltaError::TooManyObjects(_) => Some(RrdpLimit::ObjectCount),
+            DeltaError::LargeDecodedSize(_) => Some(RrdpLimit::DecodedSize),
+            _ => None
+        }
+    }
+}
+
 impl From<reqwest::Error> for DeltaError {
     fn from(err: reqwest::Error) -> Self {
         DeltaError::Http(err)
@@ -836,6 +1466,12 @@ impl From<RrdpDataReadError> for DeltaError {
             RrdpDataReadError::LargeObject(uri) => {
                 DeltaError::LargeObject(uri)
             }
+            RrdpDataReadError::TooManyObjects(limit) => {
+                DeltaError::TooManyObjects(limit)
+            }
+            RrdpDataReadError::LargeDecodedSize(limit) => {
+                DeltaError::LargeDecodedSize(limit)
+            }
             RrdpDataReadError::Read(err) => {
                 DeltaError::Rrdp(err.into())
             }
@@ -894,6 +1530,18 @@ impl fmt::Display for DeltaError {
             DeltaError::LargeObject(ref uri) => {
                 write!(f, "object exceeds size limit: {}", uri)
             }
+            DeltaError::LargeRepository(limit) => {
+                write!(f, "repository exceeds size limit of {} bytes", limit)
+            }
+            DeltaError::TooManyObjects(limit) => {
+                write!(f, "delta exceeds limit of {} objects", limit)
+            }
+            DeltaError::LargeDecodedSize(limit) => {
+                write!(
+                    f, "delta content exceeds size limit of {} bytes",
+                    limit
+                )
+            }
             DeltaError::DeltaHashMismatch => {
                 write!(f, "delta file hash value mismatch")
             }
@@ -906,3 +1554,52 @@ impl fmt::Display for DeltaError {
 
 impl error::Error for DeltaError { }
 
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A reader that fails after returning its data.
+    struct FailingRead<'a>(&'a [u8]);
+
+    impl<'a> io::Read for FailingRead<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+            if self.0.is_empty() {
+                return Err(io::ErrorKind::ConnectionReset.into())
+            }
+            self.0.read(buf)
+        }
+    }
+
+    #[test]
+    fn partial_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("partial");
+        let hash = rrdp::Hash::from([1u8; 32]);
+
+        let mut partial = PartialSnapshot::open(path.clone(), hash).unwrap();
+        assert_eq!(partial.len, 0);
+        let mut tee = TeeRead::new(FailingRead(b"foobar"), Some(&mut partial));
+        let mut data = Vec::new();
+        assert!(tee.read_to_end(&mut data).is_err());
+        assert!(tee.read_failed);
+        assert_eq!(data, b"foobar");
+        drop(partial);
+
+        let partial = PartialSnapshot::open(path.clone(), hash).unwrap();
+        assert_eq!(partial.len, 6);
+        let mut data = Vec::new();
+        partial.reader().unwrap().read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"foobar");
+        drop(partial);
+
+        let other = rrdp::Hash::from([2u8; 32]);
+        let partial = PartialSnapshot::open(path.clone(), other).unwrap();
+        assert_eq!(partial.len, 0);
+        partial.remove();
+        assert!(!path.exists());
+    }
+}
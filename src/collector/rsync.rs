@@ -11,29 +11,39 @@
 //! updated. When access to a module that has not yet been updated is
 //! requested, we spawn rsync and block until it returns. If during that time
 //! another thread requests access to the same module, that thread is blocked,
-//! too.
+//! too. All publication points within a module thus share a single update.
+//! How many points used each module is recorded in the module’s metrics.
 
-use std::{fmt, fs, io, ops};
+use std::{cmp, env, fmt, fs, io, ops};
 use std::borrow::{Borrow, Cow};
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::process::{Command as StdCommand, ExitStatus, Stdio};
 use std::str::FromStr;
 use std::sync::Arc;
-use std::time::{Duration, SystemTime};
+use std::net::TcpStream;
+use std::time::{Duration, Instant, SystemTime};
 use bytes::Bytes;
+use chrono::Utc;
 use futures::{FutureExt, TryFutureExt};
-use futures::future::Either;
+use futures::future::{self, Either};
 use log::{debug, error, info, warn};
 use rpki::uri;
 use tokio::io::AsyncBufReadExt;
 use tokio::process::Command as AsyncCommand;
-use crate::config::Config;
+use crate::config::{Config, RsyncOverride};
 use crate::error::{Failed, Fatal};
-use crate::metrics::{Metrics, RsyncModuleMetrics};
+use crate::metrics::{
+    CleanupPreview, CleanupReason, Metrics, PreviewChange, RepositoryProbe,
+    RsyncCapabilities, RsyncModuleMetrics, RsyncStats, UpdatePreview,
+};
 use crate::utils::fatal;
-use crate::utils::sync::{Mutex, RwLock};
-use crate::utils::uri::UriExt;
+use crate::utils::net::{self, AddressFamily, DnsCache};
+use crate::utils::sync::{HostLimit, Mutex, RwLock, Semaphore};
+use crate::utils::uri::SshUri;
+use crate::utils::uri::{UriExt, UriRewrite};
+use super::backoff::Backoff;
+use super::cache::{CacheEntry, dir_size};
 
 
 //------------ Collector -----------------------------------------------------
@@ -52,6 +62,19 @@ pub struct Collector {
 
     /// Whether to filter dubious authorities in rsync URIs.
     filter_dubious: bool,
+
+    /// The limit of concurrent updates per host if there is one.
+    ///
+    /// This is shared with the RRDP collector.
+    host_limit: Option<Arc<HostLimit>>,
+
+    /// The limit of concurrently running rsync processes if there is one.
+    parallel: Option<Semaphore>,
+
+    /// The tracker for failing repositories if there is one.
+    ///
+    /// This is shared with the RRDP collector.
+    backoff: Option<Arc<Backoff>>,
 }
  
 
@@ -93,7 +116,11 @@ impl Collector {
     /// Creates a new rsync collector.
     ///
     /// If use of rsync is disabled via the config, returns `Ok(None)`.
-    pub fn new(config: &Config) -> Result<Option<Self>, Failed> {
+    pub fn new(
+        config: &Config,
+        host_limit: Option<Arc<HostLimit>>,
+        backoff: Option<Arc<Backoff>>,
+    ) -> Result<Option<Self>, Failed> {
         if config.disable_rsync {
             Ok(None)
         }
@@ -103,11 +130,30 @@ impl Collector {
                     Self::create_working_dir(config)?
                 ),
                 command: Some(RsyncCommand::new(config)?),
-                filter_dubious: !config.allow_dubious_hosts
+                filter_dubious: !config.allow_dubious_hosts,
+                host_limit,
+                parallel: config.rsync_max_parallel.map(Semaphore::new),
+                backoff,
             }))
         }
     }
 
+    /// Creates a new rsync collector that never runs rsync.
+    ///
+    /// The data present in the working directory will be used as is.
+    pub fn new_offline(config: &Config) -> Result<Self, Failed> {
+        Ok(Collector {
+            working_dir: WorkingDir::new(
+                Self::create_working_dir(config)?
+            ),
+            command: None,
+            filter_dubious: !config.allow_dubious_hosts,
+            host_limit: None,
+            parallel: None,
+            backoff: None,
+        })
+    }
+
     /// Prepares the collector for use in a validation run.
     pub fn ignite(&mut self) -> Result<(), Failed> {
         // We don’t need to do anything. But just in case we later will,
@@ -127,6 +173,136 @@ impl Collector {
         Run::new(self)
     }
 
+    /// Probes whether the locally known rsync servers are reachable.
+    ///
+    /// Tries to establish a TCP connection to each server that has modules
+    /// present in the working directory and closes it again right away.
+    /// Each attempt may take at most `timeout`. Servers all of whose modules
+    /// are currently backed off are skipped. If actually running rsync
+    /// has been disabled, nothing is probed.
+    pub fn probe(
+        &self, timeout: Duration
+    ) -> Result<Vec<RepositoryProbe>, Fatal> {
+        let command = match self.command.as_ref() {
+            Some(command) => command,
+            None => return Ok(Vec::new())
+        };
+        let mut res = Vec::new();
+        for entry in fatal::read_dir(&self.working_dir.base)? {
+            let entry = entry?;
+            if !entry.is_dir() {
+                continue
+            }
+            let uri = match entry.file_name().to_str() {
+                Some(authority) => format!("rsync://{}/", authority),
+                None => continue
+            };
+            if self.server_backing_off(entry.path(), &uri)? {
+                continue
+            }
+            let time = Utc::now();
+            let start = Instant::now();
+            let reachable = command.probe(&uri, timeout);
+            res.push(RepositoryProbe {
+                uri, time, duration: start.elapsed(), reachable
+            });
+        }
+        Ok(res)
+    }
+
+    /// Returns whether all modules of a server are currently backed off.
+    ///
+    /// The modules are taken from the server’s directory at `path`. The
+    /// `uri` is the rsync URI of the server itself.
+    fn server_backing_off(
+        &self, path: &Path, uri: &str
+    ) -> Result<bool, Fatal> {
+        let backoff = match self.backoff.as_ref() {
+            Some(backoff) => backoff,
+            None => return Ok(false)
+        };
+        let mut any = false;
+        for entry in fatal::read_dir(path)? {
+            let entry = entry?;
+            if !entry.is_dir() {
+                continue
+            }
+            let module = match entry.file_name().to_str() {
+                Some(module) => format!("{}{}/", uri, module),
+                None => continue
+            };
+            if !backoff.is_backing_off(&module) {
+                return Ok(false)
+            }
+            any = true;
+        }
+        Ok(any)
+    }
+
+    /// Determines what updating the locally known modules would do.
+    ///
+    /// Runs rsync in dry-run mode for each module present in the working
+    /// directory. If actually running rsync has been disabled, nothing is
+    /// previewed.
+    pub fn preview(&self) -> Result<Vec<UpdatePreview>, Fatal> {
+        let command = match self.command.as_ref() {
+            Some(command) => command,
+            None => return Ok(Vec::new())
+        };
+        let mut res = Vec::new();
+        for host in fatal::read_dir(&self.working_dir.base)? {
+            let host = host?;
+            if !host.is_dir() {
+                continue
+            }
+            let authority = match host.file_name().to_str() {
+                Some(authority) => authority.to_string(),
+                None => continue
+            };
+            for module in fatal::read_dir(host.path())? {
+                let module = module?;
+                if !module.is_dir() {
+                    continue
+                }
+                let uri = match module.file_name().to_str().and_then(|name| {
+                    uri::Rsync::from_string(
+                        format!("rsync://{}/{}/", authority, name)
+                    ).ok()
+                }) {
+                    Some(uri) => uri,
+                    None => continue
+                };
+                let module = Module::from_uri(&uri);
+                let change = command.preview(
+                    &module, &self.working_dir.module_path(&module)
+                );
+                res.push(UpdatePreview { uri: uri.to_string(), change });
+            }
+        }
+        Ok(res)
+    }
+
+    /// Loads a file from the working directory.
+    ///
+    /// The module of the file is not updated. Returns `Ok(None)` if the
+    /// file isn’t present.
+    pub fn load_local(
+        &self, uri: &uri::Rsync
+    ) -> Result<Option<Bytes>, Fatal> {
+        let path = self.working_dir.uri_path(uri);
+        match fs::read(&path) {
+            Ok(data) => Ok(Some(data.into())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => {
+                error!(
+                    "Fatal: failed to read file {}: {}",
+                    path.display(), err
+                );
+                Err(Fatal)
+            }
+        }
+    }
+
     /// Dumps the content of the rsync collector.
     pub fn dump(&self, dir: &Path) -> Result<(), Failed> {
         let target = dir.join("rsync");
@@ -146,6 +322,67 @@ impl Collector {
         Ok(())
     }
 
+    /// Imports rsync modules from a directory.
+    ///
+    /// The directory `source` needs to contain a directory for each rsync
+    /// server named after its authority which in turn contains a directory
+    /// for each module. Entries starting with a dot or that don’t result in
+    /// a valid rsync URI are skipped. Modules already present in the
+    /// working directory are left untouched since their data is likely
+    /// fresher.
+    ///
+    /// Returns the number of imported modules.
+    pub fn import(&self, source: &Path) -> Result<usize, Failed> {
+        let mut res = 0;
+        for host in fatal::read_dir(source)? {
+            let host = host?;
+            if !host.is_dir() {
+                continue
+            }
+            let authority = match host.file_name().to_str() {
+                Some(name) if !name.starts_with('.') => name,
+                _ => continue
+            };
+            for module in fatal::read_dir(host.path())? {
+                let module = module?;
+                if !module.is_dir() {
+                    continue
+                }
+                let uri = match module.file_name().to_str().and_then(|name| {
+                    uri::Rsync::from_string(
+                        format!("rsync://{}/{}/", authority, name)
+                    ).ok()
+                }) {
+                    Some(uri) => uri,
+                    None => {
+                        debug!(
+                            "Skipping {}: not an rsync module.",
+                            module.path().display()
+                        );
+                        continue
+                    }
+                };
+                let target = self.working_dir.module_path(
+                    &Module::from_uri(&uri)
+                );
+                if target.exists() {
+                    debug!("Skipping {}: already present.", uri);
+                    continue
+                }
+                if let Err(err) = fatal::copy_existing_dir_all(
+                    module.path(), &target
+                ) {
+                    // Don’t leave a partial module behind.
+                    let _ = fs::remove_dir_all(&target);
+                    return Err(err)
+                }
+                info!("Imported rsync module {}.", uri);
+                res += 1;
+            }
+        }
+        Ok(res)
+    }
+
     /// Recursively copies the content of `source` to `target`.
     fn dump_dir(source: &Path, target: &Path) -> Result<(), Failed> {
         let read_dir = match fs::read_dir(source) {
@@ -217,6 +454,9 @@ pub struct Run<'a> {
     /// The set of modules that have been updated already.
     updated: RwLock<HashSet<OwnedModule>>,
 
+    /// The set of modules whose update has failed.
+    failed: RwLock<HashSet<OwnedModule>>,
+
     /// The modules that are currently being updated.
     ///
     /// The value in the map is a mutex that is used to synchronize competing
@@ -226,6 +466,9 @@ pub struct Run<'a> {
 
     /// The metrics for updated rsync modules.
     metrics: Mutex<Vec<RsyncModuleMetrics>>,
+
+    /// The number of publication points that used each module.
+    points: Mutex<HashMap<OwnedModule, usize>>,
 }
 
 
@@ -235,8 +478,10 @@ impl<'a> Run<'a> {
         Run {
             collector,
             updated: Default::default(),
+            failed: Default::default(),
             running: Default::default(),
             metrics: Default::default(),
+            points: Default::default(),
         }
     }
 
@@ -248,6 +493,29 @@ impl<'a> Run<'a> {
         self.updated.read().contains(Module::from_uri(uri).as_ref())
     }
 
+    /// Returns whether updating the module for the given URI would block.
+    ///
+    /// This is the case if the module hasn’t been updated yet and the
+    /// maximum number of rsync processes are already running. The result is
+    /// only a hint since other threads may start or finish processes at any
+    /// time.
+    pub fn is_busy(&self, uri: &uri::Rsync) -> bool {
+        self.collector.command.is_some()
+        && self.collector.parallel.as_ref().is_some_and(|parallel| {
+            parallel.is_exhausted()
+        })
+        && !self.was_updated(uri)
+    }
+
+    /// Returns whether the module for the given URI is known to be current.
+    ///
+    /// This is the case if the module has not been updated yet or if its
+    /// last update succeeded. If there is no rsync command because the
+    /// collector is used offline, all modules are considered current.
+    pub fn is_current(&self, uri: &uri::Rsync) -> bool {
+        !self.failed.read().contains(Module::from_uri(uri).as_ref())
+    }
+
     /// Tries to update the module for the given URI.
     ///
     /// If the module has not yet been updated, may block until an update
@@ -260,6 +528,11 @@ impl<'a> Run<'a> {
         };
         let module = Module::from_uri(uri);
 
+        // Count the use of the module.
+        *self.points.lock().entry(
+            module.clone().into_owned()
+        ).or_default() += 1;
+
         // If it is already up-to-date, return.
         if self.updated.read().contains(module.as_ref()) {
             return
@@ -285,14 +558,44 @@ impl<'a> Run<'a> {
             warn!(
                 "{}: Dubious host name. Skipping update.",
                 module
-            )
+            );
+            self.failed.write().insert(module.clone().into_owned());
+        }
+        else if self.collector.backoff.as_ref().is_some_and(|backoff| {
+            backoff.is_backing_off(module.as_str())
+        }) {
+            // The module failed recently. Skip it until it is due again.
+            self.failed.write().insert(module.clone().into_owned());
         }
         else {
-            // Run the actual update.
+            // Wait until the server has capacity for us, then run the
+            // actual update.
+            let _permit = self.collector.host_limit.as_ref().map(|limit| {
+                limit.acquire(uri.authority())
+            });
+            let _process = self.collector.parallel.as_ref().map(|limit| {
+                limit.acquire()
+            });
             let metrics = command.update(
                 module.as_ref(),
                 &self.collector.working_dir.module_path(module.as_ref())
             );
+            let success = matches!(
+                metrics.status, Ok(status) if status.success()
+            );
+            if !success {
+                self.failed.write().insert(module.clone().into_owned());
+            }
+            if let Some(backoff) = self.collector.backoff.as_ref() {
+                if success {
+                    backoff.success(module.as_str())
+                }
+                else {
+                    backoff.failure(
+                        module.as_str(), metrics.failure_reason()
+                    )
+                }
+            }
 
             // Insert into updated map and metrics.
             self.metrics.lock().push(metrics);
@@ -346,10 +649,17 @@ impl<'a> Run<'a> {
     }
 
     /// Cleans the collector only keeping the modules included in `retain`.
+    ///
+    /// If `preview` is some list, nothing is deleted. Instead, everything
+    /// that would be deleted is added to the list.
     //
     //  This currently is super agressive, deleting everyting that it doesn’t
     //  like.
-    pub fn cleanup(&self, retain: &mut ModuleSet) -> Result<(), Failed> {
+    pub fn cleanup(
+        &self,
+        retain: &mut ModuleSet,
+        mut preview: Option<&mut Vec<CleanupPreview>>,
+    ) -> Result<(), Failed> {
         if self.collector.command.is_none() {
             return Ok(())
         }
@@ -363,14 +673,16 @@ impl<'a> Run<'a> {
             &self.collector.working_dir.base
         )? {
             let entry = entry?;
-            let keep = match entry.file_name().to_str() {
-                Some(name) => {
-                    match retain.authorities.get(name) {
-                        Some(modules) => self.cleanup_host(&entry, modules)?,
-                        None => false,
-                    }
-                }
-                None => false
+            let modules = entry.file_name().to_str().and_then(|name| {
+                retain.authorities.get(name)
+            });
+            if let Some(preview) = preview.as_deref_mut() {
+                Self::preview_host(&entry, modules, preview)?;
+                continue
+            }
+            let keep = match modules {
+                Some(modules) => self.cleanup_host(&entry, modules)?,
+                None => false,
             };
 
             if !keep {
@@ -381,6 +693,71 @@ impl<'a> Run<'a> {
         Ok(())
     }
 
+    /// Adds what cleaning up a host directory would remove to `preview`.
+    ///
+    /// The modules to retain for the host are given via `retain`. If this
+    /// is `None`, all modules would be removed.
+    fn preview_host(
+        entry: &fatal::DirEntry,
+        retain: Option<&HashSet<String>>,
+        preview: &mut Vec<CleanupPreview>,
+    ) -> Result<(), Failed> {
+        let authority = match entry.file_name().to_str() {
+            Some(authority) if entry.is_dir() => authority,
+            _ => {
+                preview.push(Self::preview_item(entry, None)?);
+                return Ok(())
+            }
+        };
+        for entry in fatal::read_dir(entry.path())? {
+            let entry = entry?;
+            let name = match entry.file_name().to_str() {
+                Some(name) if entry.is_dir() => name,
+                _ => {
+                    preview.push(Self::preview_item(&entry, None)?);
+                    continue
+                }
+            };
+            if retain.is_some_and(|retain| retain.contains(name)) {
+                continue
+            }
+            preview.push(Self::preview_item(
+                &entry, Some(format!("rsync://{}/{}/", authority, name))
+            )?);
+        }
+        Ok(())
+    }
+
+    /// Creates the preview item for removing a directory entry.
+    ///
+    /// If `uri` is `None`, the entry is stray data.
+    fn preview_item(
+        entry: &fatal::DirEntry, uri: Option<String>,
+    ) -> Result<CleanupPreview, Failed> {
+        let size = if entry.is_dir() {
+            dir_size(entry.path()).map_err(|err| {
+                error!(
+                    "Failed to determine size of {}: {}",
+                    entry.path().display(), err
+                );
+                Failed
+            })?
+        }
+        else {
+            entry.len()
+        };
+        Ok(match uri {
+            Some(uri) => CleanupPreview {
+                uri, rrdp: false, size, reason: CleanupReason::Unused
+            },
+            None => CleanupPreview {
+                uri: entry.path().display().to_string(),
+                rrdp: false, size,
+                reason: CleanupReason::Stray,
+            }
+        })
+    }
+
     /// Removes all modules from the directory that are not in `retain`.
     ///
     /// Returns whether the host directory should be kept or can be deleted,
@@ -415,6 +792,60 @@ impl<'a> Run<'a> {
         Ok(keep_host)
     }
 
+    /// Returns the cache entries for all modules in the working dir.
+    ///
+    /// If actually running rsync has been disabled, the modules can’t be
+    /// fetched again, so no entries are returned.
+    pub fn cache_entries(&self) -> Result<Vec<CacheEntry>, Failed> {
+        if self.collector.command.is_none() {
+            return Ok(Vec::new())
+        }
+        let updated = self.updated.read();
+        let mut res = Vec::new();
+        for host in fatal::read_dir(&self.collector.working_dir.base)? {
+            let host = host?;
+            if !host.is_dir() {
+                continue
+            }
+            let authority = match host.file_name().to_str() {
+                Some(authority) => authority.to_string(),
+                None => continue
+            };
+            for module in fatal::read_dir(host.path())? {
+                let module = module?;
+                if !module.is_dir() {
+                    continue
+                }
+                let uri = match module.file_name().to_str().and_then(|name| {
+                    uri::Rsync::from_string(
+                        format!("rsync://{}/{}/", authority, name)
+                    ).ok()
+                }) {
+                    Some(uri) => uri,
+                    None => continue
+                };
+                let size = match dir_size(module.path()) {
+                    Ok(size) => size,
+                    Err(err) => {
+                        error!(
+                            "Failed to determine size of {}: {}",
+                            module.path().display(), err
+                        );
+                        return Err(Failed)
+                    }
+                };
+                res.push(CacheEntry {
+                    used: updated.contains(Module::from_uri(&uri).as_ref()),
+                    uri: uri.to_string(),
+                    path: module.into_path(),
+                    rrdp: false,
+                    size,
+                });
+            }
+        }
+        Ok(res)
+    }
+
     /// Finishes the validation run.
     ///
     /// Updates `metrics` with the collector run’s metrics.
@@ -422,13 +853,57 @@ impl<'a> Run<'a> {
     /// If you are not interested in the metrics, you can simple drop the
     /// value, instead.
     pub fn done(self, metrics: &mut Metrics) {
+        let points = self.points.into_inner();
         metrics.rsync = self.metrics.into_inner();
+        metrics.rsync_capabilities = self.collector.command.as_ref().map(
+            |command| command.capabilities.clone()
+        );
+        for item in &mut metrics.rsync {
+            if let Some(count) = points.get(
+                Module::from_uri(&item.module).as_ref()
+            ) {
+                item.points = *count;
+            }
+        }
     }
 }
 
 
 //------------ RsyncCommand --------------------------------------------------
 
+/// How often to check the size of a module while rsync is running.
+const SIZE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The connect timeout for SSH if none is given in the override.
+///
+/// This is the same as the connect timeout we give to rsync by default.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to keep resolved rsync server addresses.
+const DNS_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// The timeout for connecting to a server to select an address family.
+///
+/// This is kept short since rsync will still try all addresses itself if
+/// the connection fails.
+const FAMILY_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long to keep the address family of a server.
+///
+/// This should cover a typical validation run so that a server is only
+/// connected to once per run no matter how many modules it has.
+const SERVER_CACHE_TTL: Duration = Duration::from_secs(600);
+
+/// The default port of an rsync server.
+const DEFAULT_PORT: u16 = 873;
+
+/// The long options whose support is determined when probing rsync.
+const PROBED_OPTIONS: &[&str] = &[
+    "--bwlimit", "--compress", "--contimeout", "--delete-excluded",
+    "--dry-run", "--exclude", "--include", "--ipv4", "--ipv6", "--max-size",
+    "--no-motd", "--stats",
+];
+
 /// The command to run rsync.
 #[derive(Debug)]
 struct RsyncCommand {
@@ -440,78 +915,445 @@ struct RsyncCommand {
     /// We will always add a few more when actually running.
     args: Vec<String>,
 
+    /// The filter arguments limiting the files transferred.
+    ///
+    /// These are added after any per-module arguments so that filter rules
+    /// given there take precedence.
+    filter: Vec<String>,
+
     /// The rsync timeout.
     timeout: Option<Duration>,
+
+    /// The bandwidth limit in KiB per second.
+    bandwidth_limit: Option<u64>,
+
+    /// Per-module overrides of the timeouts, limit, and arguments.
+    ///
+    /// The keys are canonical module URIs.
+    overrides: HashMap<String, RsyncOverride>,
+
+    /// The rules for rewriting module URIs before accessing them.
+    rewrite: UriRewrite,
+
+    /// The maximum size of a module on disk.
+    max_module_size: Option<u64>,
+
+    /// The cache of resolved server addresses.
+    dns: DnsCache,
+
+    /// The outcome of connecting to servers.
+    ///
+    /// The keys are the lower-case authorities of the rewritten URIs, the
+    /// values the time of the last probe or connection attempt and the
+    /// address family used if it succeeded.
+    servers: Mutex<HashMap<String, (Instant, Option<AddressFamily>)>>,
+
+    /// Should we tell rsync which address family to use?
+    ///
+    /// This is only done if we use the default arguments and rsync doesn’t
+    /// connect through a proxy.
+    select_family: bool,
+
+    /// The capabilities of the rsync binary.
+    capabilities: RsyncCapabilities,
 }
 
 impl RsyncCommand {
     /// Creates a new rsync command from the config.
     pub fn new(config: &Config) -> Result<Self, Failed> {
         let command = config.rsync_command.clone();
-        let output = match StdCommand::new(&command).arg("-h").output() {
-            Ok(output) => output,
-            Err(err) => {
-                error!(
-                    "Failed to run rsync: {}",
-                    err
-                );
-                return Err(Failed)
-            }
-        };
-        if !output.status.success() {
-            error!(
-                "Running rsync failed with output: \n{}",
-                String::from_utf8_lossy(&output.stderr)
-            );
-            return Err(Failed);
-        }
+        let capabilities = Self::probe_capabilities(&command)?;
+        info!(
+            "Using rsync version {} supporting {}.",
+            capabilities.version.as_deref().unwrap_or("unknown"),
+            capabilities.options.join(" ")
+        );
         let args = match config.rsync_args {
             Some(ref args) => args.clone(),
             None => {
                 let mut args = Vec::new();
-                args.push("--no-motd".into());
-                args.push("-z".into());
-                let has_contimeout =
-                   output.stdout.windows(12)
-                   .any(|window| window == b"--contimeout");
-                if has_contimeout {
+                if capabilities.supports("--no-motd") {
+                    args.push("--no-motd".into());
+                }
+                if capabilities.supports("--compress") {
+                    args.push("-z".into());
+                }
+                if capabilities.supports("--contimeout") {
                     args.push("--contimeout=10".into());
                 }
+                if capabilities.supports("--stats") {
+                    args.push("--stats".into());
+                }
                 if let Some(max_size) = config.max_object_size {
-                    args.push(format!("--max-size={}", max_size));
+                    if capabilities.supports("--max-size") {
+                        args.push(format!("--max-size={}", max_size));
+                    }
+                    else {
+                        warn!(
+                            "rsync doesn’t support --max-size. Object size \
+                             will not be limited for rsync."
+                        );
+                    }
                 }
                 args
             }
         };
+        let filter = if
+            capabilities.supports("--include")
+            && capabilities.supports("--exclude")
+        {
+            let mut filter = Self::filter_args(config);
+            if !capabilities.supports("--delete-excluded") {
+                filter.retain(|arg| arg != "--delete-excluded");
+            }
+            filter
+        }
+        else {
+            if !config.rsync_include.is_empty()
+                || !config.rsync_exclude.is_empty()
+            {
+                warn!(
+                    "rsync doesn’t support filter rules. Ignoring the \
+                     rsync-include and rsync-exclude options."
+                );
+            }
+            Vec::new()
+        };
+        let bandwidth_limit = if capabilities.supports("--bwlimit") {
+            config.rsync_bandwidth_limit.map(|limit| {
+                cmp::max(limit / 1024, 1)
+            })
+        }
+        else {
+            if config.rsync_bandwidth_limit.is_some() {
+                warn!(
+                    "rsync doesn’t support --bwlimit. Ignoring the \
+                     rsync-bandwidth-limit option."
+                );
+            }
+            None
+        };
         Ok(RsyncCommand {
             command,
             args,
+            filter,
             timeout: config.rsync_timeout,
+            bandwidth_limit,
+            overrides: config.rsync_overrides.clone(),
+            rewrite: UriRewrite::new(&config.uri_rewrites),
+            max_module_size: config.max_repository_size,
+            dns: DnsCache::new(DNS_CACHE_TTL),
+            servers: Default::default(),
+            select_family: {
+                config.rsync_args.is_none()
+                && env::var_os("RSYNC_PROXY").is_none()
+                && capabilities.supports("--ipv4")
+                && capabilities.supports("--ipv6")
+            },
+            capabilities,
         })
     }
 
+    /// Determines the version and supported options of the rsync binary.
+    ///
+    /// The options are taken from the output of `rsync -h`. Some builds
+    /// exit with an error status after printing their help, so the output
+    /// is used regardless of the status as long as it mentions any of the
+    /// options we are interested in. The version is taken from the first
+    /// line of the output of `rsync --version` if that can be run.
+    fn probe_capabilities(
+        command: &str
+    ) -> Result<RsyncCapabilities, Failed> {
+        let output = match StdCommand::new(command).arg("-h").output() {
+            Ok(output) => output,
+            Err(err) => {
+                error!(
+                    "Failed to run rsync: {}",
+                    err
+                );
+                return Err(Failed)
+            }
+        };
+        let mut help = output.stdout;
+        help.extend_from_slice(&output.stderr);
+        let options: Vec<_> = PROBED_OPTIONS.iter().copied().filter(|option| {
+            has_option(&help, option)
+        }).collect();
+        if !output.status.success() && options.is_empty() {
+            error!(
+                "Running rsync failed with output: \n{}",
+                String::from_utf8_lossy(&help)
+            );
+            return Err(Failed);
+        }
+        let version = StdCommand::new(command).arg("--version").output().ok()
+            .and_then(|output| {
+                parse_version(&String::from_utf8_lossy(&output.stdout))
+            });
+        Ok(RsyncCapabilities { version, options })
+    }
+
+    /// Returns the filter arguments for the configured patterns.
+    ///
+    /// Excluded files are never transferred. If there are include
+    /// patterns, only files matching one of them are transferred.
+    /// Directories are always included so that rsync descends into them.
+    /// Files that are filtered out are deleted from the local copy.
+    fn filter_args(config: &Config) -> Vec<String> {
+        let mut res: Vec<_> = config.rsync_exclude.iter().map(|pattern| {
+            format!("--exclude={}", pattern)
+        }).collect();
+        if !config.rsync_include.is_empty() {
+            res.push("--include=*/".into());
+            res.extend(config.rsync_include.iter().map(|pattern| {
+                format!("--include={}", pattern)
+            }));
+            res.push("--exclude=*".into());
+        }
+        if !res.is_empty() {
+            res.push("--delete-excluded".into());
+        }
+        res
+    }
+
+    /// Tries to connect to the rsync server given by `uri`.
+    ///
+    /// The URI is rewritten first if necessary. Only the authority part of
+    /// the URI is used. If it doesn’t contain a port, the default rsync port
+    /// is used. If the server has addresses of both families, connections
+    /// are attempted in parallel as described in RFC 8305.
+    fn connect(
+        &self, uri: &str, timeout: Duration
+    ) -> Result<TcpStream, io::Error> {
+        let uri = self.rewrite.rewrite(uri);
+        let (host, port) = Self::split_authority(Self::authority(&uri))?;
+        net::connect(&self.dns.resolve(host, port)?, timeout)
+    }
+
+    /// Returns the authority portion of an rsync URI.
+    fn authority(uri: &str) -> &str {
+        let authority = uri.strip_prefix("rsync://").unwrap_or(uri);
+        match authority.split_once('/') {
+            Some((authority, _)) => authority,
+            None => authority,
+        }
+    }
+
+    /// Splits an authority into host and port.
+    ///
+    /// If the authority doesn’t contain a port, the default rsync port is
+    /// used.
+    fn split_authority(authority: &str) -> Result<(&str, u16), io::Error> {
+        match authority.rsplit_once(':') {
+            Some((host, port)) if !port.contains(']') => {
+                let port = u16::from_str(port).map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "invalid port")
+                })?;
+                Ok((host, port))
+            }
+            _ => Ok((authority, DEFAULT_PORT)),
+        }
+    }
+
+    /// Returns the lower-case authority of a URI after rewriting it.
+    fn server_key(&self, uri: &str) -> String {
+        Self::authority(&self.rewrite.rewrite(uri)).to_ascii_lowercase()
+    }
+
+    /// Returns the address family rsync should use for the given URI.
+    ///
+    /// The URI `from` is either the URI of the module `source` itself or
+    /// that of one of its mirrors. The family is taken from the last probe
+    /// of the server. If there is no recent probe, a connection is raced
+    /// across address families with a short timeout and its outcome is
+    /// cached for the server, so a server with many modules is only
+    /// connected to once per validation run. No family is selected for
+    /// modules fetched via SSH.
+    fn address_family(
+        &self,
+        source: &Module,
+        from: &str,
+        value: Option<&RsyncOverride>,
+    ) -> Option<AddressFamily> {
+        if !self.select_family {
+            return None
+        }
+        if from == source.as_str()
+            && value.is_some_and(|value| value.ssh.is_some())
+        {
+            return None
+        }
+        let key = self.server_key(from);
+        if let Some((time, family)) = self.servers.lock().get(&key) {
+            if time.elapsed() < SERVER_CACHE_TTL {
+                return *family
+            }
+        }
+        let family = match self.connect(
+            from, FAMILY_CONNECT_TIMEOUT
+        ).and_then(|sock| sock.peer_addr()) {
+            Ok(addr) => Some(AddressFamily::of(&addr)),
+            Err(err) => {
+                debug!("rsync {}: failed to connect to server: {}", from, err);
+                None
+            }
+        };
+        self.servers.lock().insert(key, (Instant::now(), family));
+        family
+    }
+
+    /// Probes whether the rsync server given by `uri` is reachable.
+    ///
+    /// The outcome is remembered and the address family of a successful
+    /// connection used for later runs of rsync against the server.
+    fn probe(&self, uri: &str, timeout: Duration) -> bool {
+        let key = self.server_key(uri);
+        let family = match self.connect(
+            uri, timeout
+        ).and_then(|sock| sock.peer_addr()) {
+            Ok(addr) => Some(AddressFamily::of(&addr)),
+            Err(err) => {
+                debug!("rsync {}: probe failed: {}", uri, err);
+                None
+            }
+        };
+        self.servers.lock().insert(key, (Instant::now(), family));
+        family.is_some()
+    }
+
     /// Updates a module by running rsync.
+    ///
+    /// If updating from the module’s own URI fails and mirrors are
+    /// configured for the module, they are tried in order until one of
+    /// them succeeds.
     pub fn update(
         &self,
         source: &Module,
         destination: &Path
     ) -> RsyncModuleMetrics {
         let start = SystemTime::now();
-        let status = self.command(
-            source, destination
-        ).and_then(|cmd| self.run(source, cmd));
+        let value = self.overrides.get(source.as_str());
+        let mut address_family = self.address_family(
+            source, source.as_str(), value
+        );
+        let mut stats = None;
+        let mut status = self.transfer(
+            source, source.as_str(), destination, address_family, value,
+            &mut stats
+        );
+        let mut mirror = None;
+        let mirrors = value.map(|value| value.mirrors.as_slice());
+        for item in mirrors.unwrap_or_default() {
+            if matches!(status, Ok(status) if status.success()) {
+                break
+            }
+            info!("{}: update failed, trying mirror {}.", source, item);
+            address_family = self.address_family(source, item, value);
+            stats = None;
+            status = self.transfer(
+                source, item, destination, address_family, value, &mut stats
+            );
+            if matches!(status, Ok(status) if status.success()) {
+                mirror = Some(item.clone());
+            }
+        }
+        if let Some(limit) = self.max_module_size {
+            if matches!(dir_size(destination), Ok(size) if size > limit) {
+                warn!(
+                    "{}: module exceeds size limit of {} bytes. \
+                     Removing local copy.",
+                    source, limit
+                );
+                if let Err(err) = fs::remove_dir_all(destination) {
+                    error!(
+                        "Failed to remove rsync module directory {}: {}",
+                        destination.display(), err
+                    );
+                }
+                status = Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "module exceeds size limit"
+                ));
+            }
+        }
         RsyncModuleMetrics {
             module: source.to_uri(),
             status,
             duration: SystemTime::now().duration_since(start),
+            points: 0,
+            address_family,
+            stats,
+            mirror,
+        }
+    }
+
+    /// Determines what updating a module would do.
+    ///
+    /// Runs rsync in dry-run mode with statistics enabled and returns the
+    /// number of files and bytes that would be transferred.
+    pub fn preview(
+        &self,
+        source: &Module,
+        destination: &Path
+    ) -> PreviewChange {
+        let value = self.overrides.get(source.as_str());
+        let address_family = self.address_family(
+            source, source.as_str(), value
+        );
+        let mut stats = None;
+        let status = self.command(
+            source, source.as_str(), destination, address_family, value
+        ).and_then(|mut cmd| {
+            cmd.arg("--dry-run").arg("--stats");
+            self.run(source, destination, cmd, value, &mut stats)
+        });
+        match (status, stats) {
+            (Ok(status), Some(stats)) if status.success() => {
+                if stats.files_transferred == 0 {
+                    PreviewChange::Unchanged
+                }
+                else {
+                    PreviewChange::Files {
+                        count: stats.files_transferred,
+                        size: stats.transferred_size,
+                    }
+                }
+            }
+            _ => PreviewChange::Failed
         }
     }
 
+    /// Transfers the module from the given URI.
+    ///
+    /// The URI `from` is either the URI of the module `source` itself or
+    /// that of one of its mirrors.
+    fn transfer(
+        &self,
+        source: &Module,
+        from: &str,
+        destination: &Path,
+        address_family: Option<AddressFamily>,
+        value: Option<&RsyncOverride>,
+        stats: &mut Option<RsyncStats>,
+    ) -> Result<ExitStatus, io::Error> {
+        self.command(
+            source, from, destination, address_family, value
+        ).and_then(|cmd| {
+            self.run(source, destination, cmd, value, stats)
+        })
+    }
+
     /// Actually runs the rsync command.
+    ///
+    /// Transfer statistics found in the output of rsync are stored in
+    /// `stats`.
     fn run(
         &self,
         source: &Module,
-        mut command: AsyncCommand
+        destination: &Path,
+        mut command: AsyncCommand,
+        value: Option<&RsyncOverride>,
+        stats: &mut Option<RsyncStats>,
     ) -> Result<ExitStatus, io::Error> {
         // Because we can’t have a timeout on a child process with just std,
         // we resort to Tokio here: We fire up a current-thread runtime and
@@ -532,20 +1374,33 @@ impl RsyncCommand {
                 tokio::io::BufReader::new
             );
             let mut stdout = Vec::new();
+            let timeout = match value.and_then(|value| value.timeout) {
+                Some(timeout) if timeout.is_zero() => None,
+                Some(timeout) => Some(timeout),
+                None => self.timeout,
+            };
+            let wait = match timeout {
+                None => Either::Left(child.wait().map(Ok)),
+                Some(timeout) => {
+                    Either::Right(
+                        tokio::time::timeout(
+                            timeout, child.wait()
+                        ).map_err(|_| {
+                            io::Error::new(
+                                io::ErrorKind::TimedOut,
+                                "rsync process reached time out"
+                            )
+                        })
+                    )
+                }
+            };
             let res = tokio::try_join!(
-                match self.timeout {
-                    None => Either::Left(child.wait().map(Ok)),
-                    Some(timeout) => {
-                        Either::Right(
-                            tokio::time::timeout(
-                                timeout, child.wait()
-                            ).map_err(|_| {
-                                io::Error::new(
-                                    io::ErrorKind::TimedOut,
-                                    "rsync process reached time out"
-                                )
-                            })
-                        )
+                async {
+                    tokio::select! {
+                        res = wait => res,
+                        err = Self::watch_size(
+                            destination, self.max_module_size
+                        ) => Err(err),
                     }
                 },
                 async {
@@ -585,7 +1440,12 @@ impl RsyncCommand {
             };
             if !stdout.is_empty() {
                 String::from_utf8_lossy(&stdout).lines().for_each(|l| {
-                    info!("{}: {}", source, l);
+                    if parse_stats_line(stats, l) {
+                        debug!("{}: {}", source, l);
+                    }
+                    else if !l.is_empty() {
+                        info!("{}: {}", source, l);
+                    }
                 })
             }
             if let Err(ref err) = status {
@@ -595,13 +1455,45 @@ impl RsyncCommand {
         })
     }
 
+    /// Watches the size of a module while rsync is running.
+    ///
+    /// Resolves into an error once the module directory has grown beyond
+    /// `limit`. Never resolves if there is no limit.
+    async fn watch_size(destination: &Path, limit: Option<u64>) -> io::Error {
+        let limit = match limit {
+            Some(limit) => limit,
+            None => return future::pending().await,
+        };
+        loop {
+            tokio::time::sleep(SIZE_CHECK_INTERVAL).await;
+            if matches!(dir_size(destination), Ok(size) if size > limit) {
+                return io::Error::new(
+                    io::ErrorKind::Other,
+                    "module exceeds size limit"
+                )
+            }
+        }
+    }
+
     /// Creates the rsync command.
+    ///
+    /// If there is an override for the module, its settings replace the
+    /// connect timeout and bandwidth limit and its extra arguments are
+    /// added.
     fn command(
         &self,
         source: &Module,
-        destination: &Path
+        from: &str,
+        destination: &Path,
+        address_family: Option<AddressFamily>,
+        value: Option<&RsyncOverride>,
     ) -> Result<AsyncCommand, io::Error> {
-        info!("rsyncing from {}.", source);
+        if from == source.as_str() {
+            info!("rsyncing from {}.", source);
+        }
+        else {
+            info!("rsyncing {} from mirror {}.", source, from);
+        }
         fs::create_dir_all(destination)?;
         let destination = match Self::format_destination(destination) {
             Ok(some) => some,
@@ -616,18 +1508,89 @@ impl RsyncCommand {
                 ));
             }
         };
+        let connect_timeout = value.and_then(|value| value.connect_timeout);
+        let connect_timeout = connect_timeout.filter(|_| {
+            self.capabilities.supports("--contimeout")
+        });
+        let ssh = if from == source.as_str() {
+            value.and_then(|value| value.ssh.as_ref().map(|ssh| (ssh, value)))
+        }
+        else {
+            None
+        };
         let mut cmd = AsyncCommand::new(&self.command);
         for item in &self.args {
+            if (
+                connect_timeout.is_some() || ssh.is_some()
+            ) && item.starts_with("--contimeout") {
+                continue
+            }
             cmd.arg(item);
         }
+        if let Some((ssh, value)) = ssh {
+            cmd.arg("-e").arg(Self::ssh_command(ssh, value));
+        }
+        else if let Some(timeout) = connect_timeout {
+            cmd.arg(format!("--contimeout={}", timeout.as_secs()));
+        }
+        let bandwidth_limit = match value.and_then(|v| v.bandwidth_limit) {
+            Some(0) => None,
+            Some(_) if !self.capabilities.supports("--bwlimit") => None,
+            Some(limit) => Some(cmp::max(limit / 1024, 1)),
+            None => self.bandwidth_limit,
+        };
+        if let Some(limit) = bandwidth_limit {
+            cmd.arg(format!("--bwlimit={}", limit));
+        }
+        if let Some(value) = value {
+            cmd.args(&value.extra_args);
+        }
+        cmd.args(&self.filter);
+        match address_family {
+            Some(AddressFamily::Ipv4) => { cmd.arg("-4"); }
+            Some(AddressFamily::Ipv6) => { cmd.arg("-6"); }
+            None => { }
+        }
+        let source_uri = match ssh {
+            Some((ssh, _)) => Cow::Owned(ssh.rsync_source()),
+            None => self.rewrite.rewrite(from),
+        };
         cmd.arg("-rtO")
            .arg("--delete")
-           .arg(source.to_string())
+           .arg(source_uri.as_ref())
            .arg(destination);
         debug!("{}: Running command {:?}", source, cmd);
         Ok(cmd)
     }
 
+    /// Returns the remote shell command for fetching a module via SSH.
+    ///
+    /// SSH is run in batch mode so it fails rather than asks for a
+    /// password. The override’s connect timeout is used for SSH since the
+    /// `--contimeout` option only applies to the rsync daemon protocol.
+    fn ssh_command(ssh: &SshUri, value: &RsyncOverride) -> String {
+        let timeout = value.connect_timeout.unwrap_or(CONNECT_TIMEOUT);
+        let mut res = format!(
+            "ssh -o BatchMode=yes -o ConnectTimeout={}",
+            cmp::max(timeout.as_secs(), 1)
+        );
+        if ssh.has_port() {
+            res.push_str(&format!(" -p {}", ssh.port()));
+        }
+        if let Some(path) = value.ssh_identity_file.as_ref() {
+            res.push_str(
+                &format!(" -i {}", shell_quote(&path.display().to_string()))
+            );
+        }
+        if let Some(path) = value.ssh_known_hosts.as_ref() {
+            res.push_str(&format!(
+                " -o UserKnownHostsFile={} -o StrictHostKeyChecking=yes",
+                shell_quote(&path.display().to_string())
+            ));
+        }
+        res
+    }
+
     /// Formats the destination path for inclusion in the command.
     #[cfg(not(windows))]
     #[allow(clippy::unnecessary_wraps)]
@@ -726,6 +1689,111 @@ impl RsyncCommand {
 }
 
 
+//------------ has_option and parse_version ---------------------------------
+
+/// Returns whether the help text of rsync mentions the given long option.
+///
+/// The option must not be followed by a character that could continue the
+/// option name so that, e.g., `--include` isn’t found in `--include-from`.
+fn has_option(help: &[u8], option: &str) -> bool {
+    let option = option.as_bytes();
+    help.windows(option.len()).enumerate().any(|(pos, window)| {
+        window == option && !matches!(
+            help.get(pos + option.len()),
+            Some(ch) if ch.is_ascii_alphanumeric() || *ch == b'-'
+        )
+    })
+}
+
+/// Parses the version from the output of `rsync --version`.
+///
+/// This is the word following the first occurrence of “version” on the
+/// first line.
+fn parse_version(output: &str) -> Option<String> {
+    let mut words = output.lines().next()?.split_whitespace();
+    words.find(|word| *word == "version")?;
+    words.next().map(Into::into)
+}
+
+
+//------------ shell_quote ---------------------------------------------------
+
+/// Quotes a string for use as a single word in a shell command line.
+///
+/// The remote shell command given to rsync is split into words by rsync
+/// itself which understands single quotes the same way a shell does.
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+
+//------------ parse_stats_line ----------------------------------------------
+
+/// The beginnings of lines in the statistics output of rsync.
+const STATS_PREFIXES: &[&str] = &[
+    "Number of ", "Total ", "Literal data:", "Matched data:", "File list ",
+    "sent ", "total size is ",
+];
+
+/// Parses a line of rsync output into the transfer statistics.
+///
+/// Returns whether the line was part of the statistics output. Lines that
+/// are but don’t contain anything we are interested in are skipped.
+fn parse_stats_line(stats: &mut Option<RsyncStats>, line: &str) -> bool {
+    if !STATS_PREFIXES.iter().any(|prefix| line.starts_with(prefix)) {
+        return false
+    }
+    let stats = stats.get_or_insert_with(Default::default);
+    if let Some(value) = line.strip_prefix(
+        "Number of regular files transferred:"
+    ).or_else(|| line.strip_prefix("Number of files transferred:")) {
+        if let Some(value) = parse_stats_number(value) {
+            stats.files_transferred = value
+        }
+    }
+    else if let Some(value) = line.strip_prefix("Total file size:") {
+        if let Some(value) = parse_stats_number(value) {
+            stats.total_size = value
+        }
+    }
+    else if let Some(value) = line.strip_prefix(
+        "Total transferred file size:"
+    ) {
+        if let Some(value) = parse_stats_number(value) {
+            stats.transferred_size = value
+        }
+    }
+    else if let Some(value) = line.strip_prefix("Total bytes sent:") {
+        if let Some(value) = parse_stats_number(value) {
+            stats.bytes_sent = value
+        }
+    }
+    else if let Some(value) = line.strip_prefix("Total bytes received:") {
+        if let Some(value) = parse_stats_number(value) {
+            stats.bytes_received = value
+        }
+    }
+    else if let Some(value) = line.strip_prefix("total size is ") {
+        if let Some((_, value)) = value.split_once("speedup is ") {
+            if let Ok(value) = f64::from_str(&value.trim().replace(',', "")) {
+                stats.speedup = value
+            }
+        }
+    }
+    true
+}
+
+/// Parses a number in the statistics output of rsync.
+///
+/// Newer versions of rsync group digits with commas and may append a unit
+/// after white space.
+fn parse_stats_number(value: &str) -> Option<u64> {
+    u64::from_str(
+        &value.split_whitespace().next()?.replace(',', "")
+    ).ok()
+}
+
+
 //------------ WorkingDir ----------------------------------------------------
 
 /// The working directory of the rsync collector.
@@ -794,6 +1862,11 @@ impl Module {
     pub fn to_uri(&self) -> uri::Rsync {
         uri::Rsync::from_str(&self.0).unwrap()
     }
+
+    /// Returns the module as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
 }
 
 
@@ -901,3 +1974,120 @@ impl ModuleSet {
     }
 }
 
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_stats() {
+        let output = "\
+            Number of files: 1,234 (reg: 1,000, dir: 234)\n\
+            Number of created files: 2\n\
+            Number of deleted files: 1\n\
+            Number of regular files transferred: 5\n\
+            Total file size: 12,345,678 bytes\n\
+            Total transferred file size: 4,321 bytes\n\
+            Literal data: 4,321 bytes\n\
+            Matched data: 0 bytes\n\
+            File list size: 0\n\
+            File list generation time: 0.001 seconds\n\
+            File list transfer time: 0.000 seconds\n\
+            Total bytes sent: 123\n\
+            Total bytes received: 45,678\n\
+            \n\
+            sent 123 bytes  received 45,678 bytes  9,160.20 bytes/sec\n\
+            total size is 12,345,678  speedup is 269.55\n\
+        ";
+        let mut stats = None;
+        for line in output.lines() {
+            if !line.is_empty() {
+                assert!(parse_stats_line(&mut stats, line));
+            }
+        }
+        assert_eq!(
+            stats,
+            Some(RsyncStats {
+                files_transferred: 5,
+                total_size: 12_345_678,
+                transferred_size: 4_321,
+                bytes_sent: 123,
+                bytes_received: 45_678,
+                speedup: 269.55,
+            })
+        );
+
+        let mut stats = None;
+        assert!(!parse_stats_line(&mut stats, "Welcome to the server"));
+        assert!(stats.is_none());
+    }
+
+    #[test]
+    fn filter_args() {
+        let mut config = Config::default_with_paths(
+            Default::default(), Default::default()
+        );
+        config.rsync_include = vec!["*.cer".into(), "*.roa".into()];
+        config.rsync_exclude = vec!["*.tar.gz".into()];
+        assert_eq!(
+            RsyncCommand::filter_args(&config),
+            [
+                "--exclude=*.tar.gz", "--include=*/", "--include=*.cer",
+                "--include=*.roa", "--exclude=*", "--delete-excluded",
+            ]
+        );
+
+        config.rsync_include = Vec::new();
+        config.rsync_exclude = Vec::new();
+        assert!(RsyncCommand::filter_args(&config).is_empty());
+    }
+
+    #[test]
+    fn probe_output() {
+        let help = b"  -z, --compress              compress file data\n\
+            --include=PATTERN       don't exclude matching files\n\
+            --include-from=FILE     read include patterns from FILE\n\
+            --contimeout=SECONDS    set daemon connection timeout\n";
+        assert!(has_option(help, "--compress"));
+        assert!(has_option(help, "--include"));
+        assert!(has_option(help, "--contimeout"));
+        assert!(!has_option(help, "--include-from-x"));
+        assert!(!has_option(help, "--exclude"));
+        assert!(!has_option(b"--statsx", "--stats"));
+
+        assert_eq!(
+            parse_version(
+                "rsync  version 3.2.7  protocol version 31\nCopyright\n"
+            ).as_deref(),
+            Some("3.2.7")
+        );
+        assert_eq!(parse_version("rsync 1.0\n"), None);
+    }
+
+    #[test]
+    fn ssh_command() {
+        let ssh = SshUri::from_str("ssh://repo.example.net/srv/rpki/").unwrap();
+        let mut value = RsyncOverride::default();
+        assert_eq!(
+            RsyncCommand::ssh_command(&ssh, &value),
+            "ssh -o BatchMode=yes -o ConnectTimeout=10"
+        );
+
+        let ssh = SshUri::from_str(
+            "ssh://repo.example.net:2222/srv/rpki/"
+        ).unwrap();
+        value.connect_timeout = Some(Duration::from_secs(5));
+        value.ssh_identity_file = Some("/etc/routinator/it's.key".into());
+        value.ssh_known_hosts = Some("/etc/routinator/known_hosts".into());
+        assert_eq!(
+            RsyncCommand::ssh_command(&ssh, &value),
+            "ssh -o BatchMode=yes -o ConnectTimeout=5 -p 2222 \
+             -i '/etc/routinator/it'\\''s.key' \
+             -o UserKnownHostsFile='/etc/routinator/known_hosts' \
+             -o StrictHostKeyChecking=yes"
+        );
+    }
+}
@@ -0,0 +1,112 @@
+//! Spreading repository updates over the refresh interval.
+//!
+//! This is a private module. Its types are used by the base collector only.
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use crate::config::Config;
+use crate::utils::sync::Mutex;
+
+
+//------------ Schedule ------------------------------------------------------
+
+/// The schedule for updating repositories when spreading updates.
+///
+/// Normally, all repositories are updated during every validation run. If
+/// updates are spread, validation runs happen more often but each
+/// repository is only updated once per refresh interval. The time within
+/// the interval is determined by a hash of the repository’s URI, so that
+/// updates are distributed evenly over the interval and each repository
+/// is updated at about the same time in each interval.
+///
+/// Repositories are identified by a URI given as a string. For RRDP, this
+/// is the rpkiNotify URI, for rsync the URI of the module.
+///
+/// The time of the last update is only kept in memory. Thus, every
+/// repository is updated during the first validation run.
+#[derive(Debug)]
+pub struct Schedule {
+    /// The length of the refresh interval in seconds.
+    period: i64,
+
+    /// The start time of the validation run of the last update.
+    ///
+    /// The times are Unix timestamps.
+    updated: Mutex<HashMap<String, i64>>,
+}
+
+impl Schedule {
+    /// Creates a new schedule if spreading updates is configured.
+    pub fn new(config: &Config) -> Option<Self> {
+        config.fetch_spread?;
+        Some(Schedule {
+            period: i64::try_from(
+                config.refresh.as_secs()
+            ).unwrap_or(i64::MAX).max(1),
+            updated: Default::default(),
+        })
+    }
+
+    /// Returns whether the repository should be updated.
+    ///
+    /// The validation run performing the update was started at the Unix
+    /// time `run_start`. A repository is due if it hasn’t been updated
+    /// since its start time in the current interval. Once a repository is
+    /// found due, it stays due for the rest of the run.
+    pub fn is_due(&self, uri: &str, run_start: i64) -> bool {
+        let slot_start = run_start - (
+            run_start - self.offset(uri)
+        ).rem_euclid(self.period);
+        let mut updated = self.updated.lock();
+        match updated.get(uri) {
+            Some(&last) if last == run_start => true,
+            Some(&last) if last >= slot_start => false,
+            _ => {
+                updated.insert(uri.into(), run_start);
+                true
+            }
+        }
+    }
+
+    /// Returns the offset of the repository’s update within the interval.
+    fn offset(&self, uri: &str) -> i64 {
+        let mut hasher = DefaultHasher::new();
+        uri.hash(&mut hasher);
+        (hasher.finish() % (self.period as u64)) as i64
+    }
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn is_due() {
+        let mut config = Config::default_with_paths(
+            Default::default(), Default::default()
+        );
+        config.refresh = Duration::from_secs(600);
+        config.fetch_spread = Some(10);
+        let schedule = Schedule::new(&config).unwrap();
+        let uri = "https://rrdp.example.net/notification.xml";
+        let offset = schedule.offset(uri);
+
+        // New repositories are always due and stay due during the run.
+        assert!(schedule.is_due(uri, 6000 + offset + 10));
+        assert!(schedule.is_due(uri, 6000 + offset + 10));
+
+        // Not due again until the offset is reached in the next interval.
+        assert!(!schedule.is_due(uri, 6000 + offset + 300));
+        assert!(!schedule.is_due(uri, 6600 + offset - 1));
+        assert!(schedule.is_due(uri, 6600 + offset + 1));
+        assert!(!schedule.is_due(uri, 6600 + offset + 2));
+
+        config.fetch_spread = None;
+        assert!(Schedule::new(&config).is_none());
+    }
+}
@@ -14,16 +14,22 @@ use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::thread::available_parallelism;
 use std::time::Duration;
+use chrono::{DateTime, Utc};
 use clap::{
     Command, Args, ArgAction, ArgMatches, FromArgMatches, Parser,
     crate_version,
 };
 use dirs::home_dir;
 use log::{LevelFilter, error, warn};
+use rpki::uri;
+use rpki::resources::{Asn, Prefix};
 #[cfg(unix)] use syslog::Facility;
 use toml_edit as toml;
 use crate::tals;
 use crate::error::Failed;
+use crate::utils::secret::Secret;
+use crate::utils::tls::CertFingerprint;
+use crate::utils::uri::{SshUri, UriRewrite};
 
 
 //------------ Defaults for Some Values --------------------------------------
@@ -61,12 +67,24 @@ const DEFAULT_RRDP_FALLBACK: FallbackPolicy = FallbackPolicy::Stale;
 /// The default for the RRDP fallback time.
 const DEFAULT_RRDP_FALLBACK_TIME: Duration = Duration::from_secs(3600);
 
+/// The default for the maximum delay between attempts at a failing repository.
+const DEFAULT_REPOSITORY_BACKOFF_MAX: Duration = Duration::from_secs(86400);
+
+/// The default time a repository needs to fail before it is considered dead.
+const DEFAULT_DEAD_REPOSITORY_AGE: Duration = Duration::from_secs(604800);
+
 /// The default for the maximum number of deltas.
 const DEFAULT_RRDP_MAX_DELTA_COUNT: usize = 100;
 
 /// The default for the maximum number of deltas parsed.
 const DEFAULT_RRDP_MAX_DELTA_LIST_LEN: usize = 500;
 
+/// The default number of parallel requests for ranged snapshot downloads.
+const DEFAULT_RRDP_SNAPSHOT_PARALLELISM: usize = 4;
+
+/// The default time before retrying a failed RRDP request.
+const DEFAULT_RRDP_RETRY_BACKOFF: Duration = Duration::from_secs(5);
+
 /// The default RRDP HTTP User Agent header value to send.
 const DEFAULT_RRDP_USER_AGENT: &str = concat!("Routinator/", crate_version!());
 
@@ -77,6 +95,16 @@ const DEFAULT_RTR_TCP_KEEPALIVE: Option<Duration>
 /// The default stale policy.
 const DEFAULT_STALE_POLICY: FilterPolicy = FilterPolicy::Reject;
 
+/// The default policy for files listed on a manifest but not available.
+const DEFAULT_MISSING_FILES_POLICY: FilterPolicy = FilterPolicy::Reject;
+
+/// The default policy for files not matching their manifest hash.
+const DEFAULT_HASH_MISMATCH_POLICY: FilterPolicy = FilterPolicy::Reject;
+
+/// The default resource validation algorithm.
+const DEFAULT_RESOURCE_VALIDATION: ResourceValidation
+    = ResourceValidation::Reconsidered;
+
 /// The default unsafe-vrps policy.
 const DEFAULT_UNSAFE_VRPS_POLICY: FilterPolicy = FilterPolicy::Accept;
 
@@ -89,6 +117,14 @@ const DEFAULT_MAX_OBJECT_SIZE: u64 = 20_000_000;
 /// The default maximum CA depth.
 const DEFAULT_MAX_CA_DEPTH: usize = 32;
 
+/// The default file name patterns rsync should transfer.
+///
+/// These are the file name extensions of the RPKI signed objects.
+const DEFAULT_RSYNC_INCLUDE: &[&str] = &[
+    "*.asa", "*.cer", "*.crl", "*.gbr", "*.mft", "*.roa", "*.sig", "*.spl",
+    "*.tak",
+];
+
 /// The default syslog facility.
 #[cfg(unix)]
 const DEFAULT_SYSLOG_FACILITY: Facility = Facility::LOG_DAEMON;
@@ -134,6 +170,9 @@ pub struct Config {
     /// file there.
     pub config_file: PathBuf,
 
+    /// The profile providing defaults for deployment size.
+    pub profile: ConfigProfile,
+
     /// Path to the directory that contains the repository cache.
     pub cache_dir: PathBuf,
 
@@ -166,6 +205,54 @@ pub struct Config {
     /// objects should be rejected, this is the default policy.
     pub stale: FilterPolicy,
 
+    /// The grace period for stale objects.
+    ///
+    /// If this is some, manifests and CRLs that became stale less than
+    /// this long ago are accepted with a warning even if `stale` is set to
+    /// reject.
+    pub stale_grace: Option<Duration>,
+
+    /// The tolerated clock skew.
+    ///
+    /// If this is some, objects with a notBefore or thisUpdate time less
+    /// than this far in the future are accepted.
+    pub clock_skew: Option<Duration>,
+
+    /// The time to validate against instead of the current time.
+    ///
+    /// This allows replaying archived repository data.
+    pub validation_time: Option<DateTime<Utc>>,
+
+    /// How should we deal with files missing from a publication point?
+    ///
+    /// These are files listed on an updated manifest that the collector
+    /// was unable to provide. With the default policy of reject, the update
+    /// is not used and the previously stored version of the publication
+    /// point is used instead. Otherwise, the update is used without the
+    /// missing files.
+    pub missing_files: FilterPolicy,
+
+    /// How should we deal with files that don’t match their manifest hash?
+    ///
+    /// With the default policy of reject, the update is not used and the
+    /// previously stored version of the publication point is used instead.
+    /// Otherwise, the update is used without the offending files.
+    pub hash_mismatch: FilterPolicy,
+
+    /// Which algorithm should be used for validating resources?
+    ///
+    /// This only affects certificates using the RFC 8360 certificate
+    /// policy. With the default reconsidered algorithm, resources of these
+    /// certificates exceeding those of their issuer are dropped. With the
+    /// strict algorithm, such certificates are rejected.
+    pub resource_validation: ResourceValidation,
+
+    /// Per-TAL overrides for the manifest handling policies and the
+    /// resource validation algorithm.
+    ///
+    /// The keys are TAL names.
+    pub tal_policies: HashMap<String, TalPolicy>,
+
     /// How should we deal with unsafe VRPs?
     ///
     /// Unsafe VRPs have their prefix intersect with a prefix held by a
@@ -179,6 +266,11 @@ pub struct Config {
     /// How to deal with unknown RPKI object types.
     pub unknown_objects: FilterPolicy,
 
+    /// The local policy rules for route origins of valid ROAs.
+    ///
+    /// The keys are the names of the rules.
+    pub roa_policy: HashMap<String, RoaPolicyRule>,
+
     /// The maximum length of IPv4 prefixes included in the VRP set.
     pub limit_v4_len: Option<u8>,
 
@@ -188,6 +280,12 @@ pub struct Config {
     /// Allow dubious host names.
     pub allow_dubious_hosts: bool,
 
+    /// The maximum number of concurrent updates per host.
+    ///
+    /// This limits both RRDP updates and rsync processes targeting the
+    /// same server. If this is None, there is no limit.
+    pub max_host_connections: Option<usize>,
+
     /// Should we wipe the cache before starting?
     ///
     /// (This option is only available on command line.)
@@ -206,11 +304,36 @@ pub struct Config {
     /// arguments.
     pub rsync_args: Option<Vec<String>>,
 
+    /// File name patterns of the files rsync should transfer.
+    ///
+    /// If this is empty, all files not excluded are transferred.
+    pub rsync_include: Vec<String>,
+
+    /// File name patterns of the files rsync should never transfer.
+    pub rsync_exclude: Vec<String>,
+
     /// Timeout for rsync commands.
     ///
     /// If this is None, no timeout is set.
     pub rsync_timeout: Option<Duration>,
 
+    /// The bandwidth limit for rsync in bytes per second.
+    ///
+    /// If this is None, rsync is not limited.
+    pub rsync_bandwidth_limit: Option<u64>,
+
+    /// The maximum number of rsync processes running at the same time.
+    ///
+    /// If this is None, there is no limit other than the number of
+    /// validation threads.
+    pub rsync_max_parallel: Option<usize>,
+
+    /// Per-module overrides for rsync settings.
+    ///
+    /// The keys are rsync module URIs in canonical form, i.e., with a
+    /// lower case host name and a trailing slash.
+    pub rsync_overrides: HashMap<String, RsyncOverride>,
+
     /// Whether to disable RRDP.
     pub disable_rrdp: bool,
 
@@ -220,15 +343,61 @@ pub struct Config {
     /// Time since last update of an RRDP repository before fallback to rsync.
     pub rrdp_fallback_time: Duration,
 
+    /// Per-repository overrides of the transport to use.
+    ///
+    /// The keys are either rpkiNotify URIs or host names.
+    pub transport_overrides: HashMap<String, TransportPolicy>,
+
+    /// The initial delay before retrying a failing repository.
+    ///
+    /// If this is `Some(_)`, a repository whose update failed is not tried
+    /// again before this time has passed. The delay doubles with every
+    /// further consecutive failure. If it is `None`, failing repositories
+    /// are tried during every validation run.
+    pub repository_backoff: Option<Duration>,
+
+    /// The maximum delay before retrying a failing repository.
+    pub repository_backoff_max: Duration,
+
+    /// The time a repository needs to keep failing to be considered dead.
+    pub dead_repository_age: Duration,
+
     /// The maxmimm number of deltas we allow before using snapshot.
     pub rrdp_max_delta_count: usize,
 
+    /// What to do if there are more deltas than allowed.
+    pub rrdp_delta_overflow: DeltaOverflowPolicy,
+
+    /// Per-repository overrides of the delta policy.
+    ///
+    /// The keys are the rpkiNotify URIs of the repositories.
+    pub rrdp_delta_overrides: HashMap<uri::Https, RrdpDeltaOverride>,
+
     /// The maximum allowd length of the delta list in an RRDP notification.
     ///
     /// If this number is exceeded, the delta list will be discarded and
     /// the snapshot will be used.
     pub rrdp_max_delta_list_len: usize,
 
+    /// The chunk size in bytes for ranged RRDP snapshot downloads.
+    ///
+    /// If this is None, snapshots are downloaded in a single request.
+    pub rrdp_snapshot_chunk_size: Option<u64>,
+
+    /// The number of parallel requests for ranged snapshot downloads.
+    pub rrdp_snapshot_parallelism: usize,
+
+    /// The bandwidth limit for each RRDP repository in bytes per second.
+    ///
+    /// If this is None, RRDP repositories are not limited.
+    pub rrdp_bandwidth_limit: Option<u64>,
+
+    /// Per-repository overrides of the RRDP bandwidth limit.
+    ///
+    /// The keys are the rpkiNotify URIs of the repositories. A value of
+    /// zero means that the repository is not limited.
+    pub rrdp_bandwidth_overrides: HashMap<uri::Https, u64>,
+
     /// RRDP timeout in seconds.
     ///
     /// If this is None, no timeout is set.
@@ -237,6 +406,38 @@ pub struct Config {
     /// Optional RRDP connect timeout.
     pub rrdp_connect_timeout: Option<Duration>,
 
+    /// The number of times a failed RRDP request is retried.
+    pub rrdp_retries: usize,
+
+    /// The time to wait before retrying a failed RRDP request.
+    ///
+    /// The time is doubled for each further retry.
+    pub rrdp_retry_backoff: Duration,
+
+    /// Whether to probe RRDP notification files with a HEAD request.
+    ///
+    /// If this is `true` and we have an ETag or Last-Modified value for
+    /// a repository, we first send a HEAD request for its notification
+    /// file and skip the repository if these values haven’t changed.
+    pub rrdp_head_probe: bool,
+
+    /// Whether to disable compression of RRDP responses.
+    ///
+    /// Unless this is `true`, RRDP requests ask for gzip, deflate, or
+    /// Brotli compressed responses which are decompressed transparently.
+    pub rrdp_disable_compression: bool,
+
+    /// Per-repository overrides for RRDP timeouts and retries.
+    ///
+    /// The keys are either rpkiNotify URIs or host names.
+    pub rrdp_overrides: HashMap<String, RrdpOverride>,
+
+    /// Per-host TLS trust settings for RRDP.
+    ///
+    /// The keys are host names. Connections to these hosts don’t use the
+    /// system trust store or `rrdp_root_certs`.
+    pub rrdp_tls_overrides: HashMap<String, RrdpTlsOverride>,
+
     /// Optional TCP keepalive duration for RRDP connections.
     pub rrdp_tcp_keepalive: Option<Duration>,
 
@@ -251,18 +452,133 @@ pub struct Config {
     /// RRDP HTTP proxies.
     pub rrdp_proxies: Vec<String>,
 
+    /// RRDP proxy used only for plain HTTP requests.
+    pub rrdp_http_proxy: Option<String>,
+
+    /// RRDP proxy used only for HTTPS requests.
+    pub rrdp_https_proxy: Option<String>,
+
+    /// The user name for authenticating with the RRDP proxies.
+    pub rrdp_proxy_username: Option<String>,
+
+    /// The secret providing the password for the RRDP proxies.
+    pub rrdp_proxy_password: Option<Secret>,
+
+    /// Hosts, addresses, and networks to be accessed without proxy.
+    pub rrdp_no_proxy: Vec<String>,
+
     /// RRDP HTTP User Agent.
     pub rrdp_user_agent: String,
 
+    /// Additional HTTP headers for RRDP requests.
+    ///
+    /// Each header is given as a string with the header name and value
+    /// separated by a colon.
+    pub rrdp_headers: Vec<String>,
+
+    /// Additional HTTP headers for RRDP requests with secret values.
+    ///
+    /// Each item is the header name and the secret providing its value.
+    pub rrdp_header_secrets: Vec<(String, Secret)>,
+
     /// Should we keep RRDP responses and if so where?
     pub rrdp_keep_responses: Option<PathBuf>,
 
+    /// Read RRDP data from this local directory instead of fetching it.
+    ///
+    /// This is intended for test and lab environments only.
+    pub rrdp_local_dir: Option<PathBuf>,
+
+    /// Append a JSON line for every fetch attempt to this file.
+    pub fetch_log: Option<PathBuf>,
+
+    /// Write a report of the objects rejected by a run to this file.
+    pub rejection_report: Option<PathBuf>,
+
+    /// Re-publish the validated data as a local mirror in this directory.
+    pub mirror_dir: Option<PathBuf>,
+
+    /// The URI under which the mirror’s `https` directory is served.
+    ///
+    /// If this is `None`, the mirror doesn’t include RRDP repositories.
+    pub mirror_https_uri: Option<uri::Https>,
+
+    /// Whether to cross-check the collector and store after each run.
+    ///
+    /// If this is `true`, the collector’s local copy of every stored
+    /// publication point is compared with what the store has accepted and
+    /// the differences are included in the metrics.
+    pub crosscheck: bool,
+
+    /// Whether to collect a detailed report of redundant route origins.
+    ///
+    /// Route origins published by more than one ROA and route origins
+    /// shadowed by another route origin are always counted. If this is
+    /// `true`, they are also listed individually in the metrics.
+    pub roa_overlap_report: bool,
+
+    /// Rules for rewriting rsync and RRDP URIs before accessing them.
+    ///
+    /// Each rule consists of a URI prefix and its replacement. The first
+    /// rule with a matching prefix is applied.
+    pub uri_rewrites: Vec<(String, String)>,
+
     /// Optional size limit for objects.
     pub max_object_size: Option<u64>,
 
+    /// Optional on-disk size limit for RRDP repositories and rsync modules.
+    pub max_repository_size: Option<u64>,
+
+    /// Optional on-disk size limit for all collected data.
+    ///
+    /// If the data collected via RRDP and rsync exceeds this size after a
+    /// validation run, the least recently validated repositories are
+    /// removed.
+    pub max_cache_size: Option<u64>,
+
+    /// Optional limit for the number of objects in an RRDP file.
+    ///
+    /// This applies to each snapshot or delta file separately.
+    pub rrdp_max_objects: Option<usize>,
+
+    /// Optional limit for the decoded size of all objects in an RRDP file.
+    ///
+    /// This applies to each snapshot or delta file separately.
+    pub rrdp_max_decoded_size: Option<u64>,
+
+    /// Per-repository overrides of the RRDP content limits.
+    ///
+    /// The keys are the rpkiNotify URIs of the repositories.
+    pub rrdp_limit_overrides: HashMap<uri::Https, RrdpLimits>,
+
     /// Maxium length of the CA chain.
     pub max_ca_depth: usize,
 
+    /// Maximum number of child CAs of a single CA.
+    ///
+    /// If this is `None`, the number is not limited.
+    pub max_ca_children: Option<usize>,
+
+    /// Maximum number of objects on the manifest of a publication point.
+    ///
+    /// If this is `None`, the number is not limited.
+    pub max_point_objects: Option<usize>,
+
+    /// Maximum time for processing the subtree below a CA.
+    ///
+    /// If this is `None`, the time is not limited.
+    pub max_ca_time: Option<Duration>,
+
+    /// Maximum number of signature verifications kept in the cache.
+    ///
+    /// If this is `None`, verifications are not cached.
+    pub signature_cache_size: Option<usize>,
+
+    /// Maximum number of decoded objects kept in the cache.
+    ///
+    /// If this is `None`, decoded objects are not cached.
+    pub object_cache_size: Option<usize>,
+
     /// Whether to process BGPsec router keys.
     pub enable_bgpsec: bool,
 
@@ -277,12 +593,93 @@ pub struct Config {
     /// deleted.
     pub dirty_repository: bool,
 
+    /// Whether to use the store in read-only mode.
+    ///
+    /// If this is `true`, validation is performed using only the data
+    /// present in the store. The collector is not used and the store is
+    /// never modified.
+    pub store_readonly: bool,
+
+    /// Whether the store is shared with other Routinator processes.
+    ///
+    /// If this is `true`, access to the store is coordinated with other
+    /// processes using the same repository directory through file locks.
+    pub store_shared: bool,
+
+    /// Whether to validate frozen data.
+    ///
+    /// If this is `true`, the store is used in read-only mode and a hash
+    /// of the stored data is recorded with every validation run, so that
+    /// the outcome of a run can be attributed to an exact input state.
+    pub freeze: bool,
+
+    /// The maximum age of stored data used when a repository is unavailable.
+    ///
+    /// If this is `Some(_)`, stored data of a publication point that hasn’t
+    /// been confirmed to be current for longer than this duration is not
+    /// used anymore. If it is `None`, stored data is used for as long as
+    /// it is valid.
+    pub stored_max_age: Option<Duration>,
+
+    /// The age after which stored data is considered stale.
+    ///
+    /// If this is `Some(_)`, stored data of a publication point that hasn’t
+    /// been confirmed to be current for longer than this duration is
+    /// still used but reported as stale in the metrics.
+    pub stored_stale_age: Option<Duration>,
+
+    /// The interval for saving the progress of a validation run.
+    ///
+    /// If this is some, the repositories updated during a validation run
+    /// are recorded in a checkpoint file at most this often. If the run is
+    /// interrupted, the next run resumes it and doesn’t update these
+    /// repositories again. If this is `None`, no checkpoints are kept.
+    pub checkpoint_interval: Option<Duration>,
+
     /// Number of threads used during validation.
     pub validation_threads: usize,
 
+    /// The share of the validation threads reserved for individual TALs.
+    ///
+    /// The keys are TAL names, the values percentages of the validation
+    /// threads. TALs without a share use the remaining threads.
+    pub tal_shares: HashMap<String, u8>,
+
+    /// The priority of individual TALs when sharing validation threads.
+    ///
+    /// The keys are TAL names. Tasks of TALs with a higher priority are
+    /// processed first. TALs without a priority have a priority of 0.
+    pub tal_priorities: HashMap<String, i32>,
+
     /// The refresh interval for repository validation.
     pub refresh: Duration,
 
+    /// The number of validation runs to spread repository updates over.
+    ///
+    /// If this is some, validation runs happen this many times per
+    /// refresh interval but each repository is only updated once per
+    /// interval. If this is `None`, all repositories are updated in every
+    /// validation run.
+    pub fetch_spread: Option<u32>,
+
+    /// The interval for checking RRDP notification files between runs.
+    ///
+    /// If this is some, the notification files of all known RRDP
+    /// repositories are fetched in this interval while waiting for the next
+    /// validation run. If any of them has changed, the next run is started
+    /// early. If this is `None`, no such checks are done.
+    pub rrdp_heartbeat: Option<Duration>,
+
+    /// The interval for probing repository reachability between runs.
+    pub repository_probe: Option<Duration>,
+
+    /// The minimum time between publishing two new serials.
+    ///
+    /// Validation results arriving earlier are held back and only the
+    /// latest of them is published once the interval has passed. If this
+    /// is `None`, results are published right away.
+    pub min_serial_interval: Option<Duration>,
+
     /// The RTR retry inverval to be announced to a client.
     pub retry: Duration,
 
@@ -292,6 +689,11 @@ pub struct Config {
     /// How many diffs to keep in the history.
     pub history_size: usize,
 
+    /// How many payload snapshots to keep in the history.
+    ///
+    /// If this is zero, only the current snapshot is kept.
+    pub snapshot_history: usize,
+
     /// Addresses to listen on for RTR TCP transport connections.
     pub rtr_listen: Vec<SocketAddr>,
 
@@ -315,18 +717,46 @@ pub struct Config {
     /// Should we publish detailed RTR client statistics?
     pub rtr_client_metrics: bool,
 
-    /// Path to the RTR TLS private key.
-    pub rtr_tls_key: Option<PathBuf>,
+    /// The address of an upstream RTR server to receive payload from.
+    ///
+    /// If this is present, the server does not perform validation itself
+    /// but only serves the payload received from this server.
+    pub rtr_upstream: Option<String>,
+
+    /// A PEM file with the CA certificates for the upstream RTR server.
+    ///
+    /// If this or `rtr_upstream_pinned_certs` is present, the connection
+    /// to the upstream server uses TLS.
+    pub rtr_upstream_ca_bundle: Option<PathBuf>,
+
+    /// The acceptable fingerprints of the upstream RTR server certificate.
+    pub rtr_upstream_pinned_certs: Vec<CertFingerprint>,
+
+    /// The RTR TLS private key.
+    pub rtr_tls_key: Option<Secret>,
 
     /// Path to the RTR TLS server certificate.
     pub rtr_tls_cert: Option<PathBuf>,
 
-    /// Path to the HTTP TLS private key.
-    pub http_tls_key: Option<PathBuf>,
+    /// The HTTP TLS private key.
+    pub http_tls_key: Option<Secret>,
 
     /// Path to the HTTP TLS server certificate.
     pub http_tls_cert: Option<PathBuf>,
 
+    /// The token required for requesting a repository refresh via HTTP.
+    ///
+    /// If this is `None`, the refresh endpoint is disabled.
+    pub http_refresh_token: Option<Secret>,
+
+    /// The token required for enabling or disabling TALs via HTTP.
+    ///
+    /// If this is `None`, the TAL endpoints are disabled.
+    pub http_tal_token: Option<Secret>,
+
+    /// Append a JSON line for every request to an administrative endpoint.
+    pub http_audit_log: Option<PathBuf>,
+
     /// The log levels to be logged.
     pub log_level: LevelFilter,
 
@@ -396,7 +826,8 @@ impl Config {
     ) -> Result<Self, Failed> {
         let mut res = Self::create_base_config(
             Self::path_value_of(matches, "config", cur_dir)
-                .as_ref().map(AsRef::as_ref)
+                .as_ref().map(AsRef::as_ref),
+            matches.get_one::<ConfigProfile>("profile").copied(),
         )?;
 
         res.apply_arg_matches(matches, cur_dir)?;
@@ -429,6 +860,11 @@ impl Config {
         // log_target - Goes first so we can move things out of args later.
         self.apply_log_matches(&args, cur_dir)?;
 
+        // profile - Its defaults were applied by the base config already.
+        if let Some(profile) = args.profile {
+            self.profile = profile
+        }
+
         // cache_dir
         if let Some(dir) = args.repository_dir {
             self.cache_dir = cur_dir.join(dir)
@@ -474,6 +910,46 @@ impl Config {
             self.stale = value
         }
 
+        // stale_grace
+        if let Some(value) = args.stale_grace {
+            self.stale_grace = if value == 0 {
+                None
+            }
+            else {
+                Some(Duration::from_secs(value))
+            }
+        }
+
+        // clock_skew
+        if let Some(value) = args.clock_skew {
+            self.clock_skew = if value == 0 {
+                None
+            }
+            else {
+                Some(Duration::from_secs(value))
+            }
+        }
+
+        // validation_time
+        if let Some(value) = args.validation_time {
+            self.validation_time = Some(value)
+        }
+
+        // missing_files
+        if let Some(value) = args.missing_files {
+            self.missing_files = value
+        }
+
+        // hash_mismatch
+        if let Some(value) = args.hash_mismatch {
+            self.hash_mismatch = value
+        }
+
+        // resource_validation
+        if let Some(value) = args.resource_validation {
+            self.resource_validation = value
+        }
+
         // unsafe_vrps
         if let Some(value) = args.unsafe_vrps {
             self.unsafe_vrps = value
@@ -499,6 +975,16 @@ impl Config {
             self.allow_dubious_hosts = true
         }
 
+        // max_host_connections
+        if let Some(value) = args.max_host_connections {
+            self.max_host_connections = if value == 0 {
+                None
+            }
+            else {
+                Some(value)
+            };
+        }
+
         // fresh
         if args.fresh {
             self.fresh = true
@@ -514,6 +1000,16 @@ impl Config {
             self.rsync_command = value
         }
 
+        // rsync_include
+        if let Some(list) = args.rsync_include {
+            self.rsync_include = list
+        }
+
+        // rsync_exclude
+        if let Some(list) = args.rsync_exclude {
+            self.rsync_exclude = list
+        }
+
         // rsync_timeout
         if let Some(value) = args.rsync_timeout {
             self.rsync_timeout = if value == 0 {
@@ -524,6 +1020,26 @@ impl Config {
             };
         }
 
+        // rsync_bandwidth_limit
+        if let Some(value) = args.rsync_bandwidth_limit {
+            self.rsync_bandwidth_limit = if value == 0 {
+                None
+            }
+            else {
+                Some(value)
+            };
+        }
+
+        // rsync_max_parallel
+        if let Some(value) = args.rsync_max_parallel {
+            self.rsync_max_parallel = if value == 0 {
+                None
+            }
+            else {
+                Some(value)
+            };
+        }
+
         // disable_rrdp
         if args.disable_rrdp {
             self.disable_rrdp = true
@@ -539,16 +1055,110 @@ impl Config {
             self.rrdp_fallback_time = Duration::from_secs(value)
         }
 
+        // transport_overrides
+        if let Some(list) = args.transport_override {
+            let mut res = HashMap::new();
+            for item in list {
+                let (key, value) = match item.split_once('=') {
+                    Some(some) => some,
+                    None => {
+                        error!(
+                            "Invalid transport override '{}': \
+                             expected URI=POLICY or HOST=POLICY.",
+                            item
+                        );
+                        return Err(Failed)
+                    }
+                };
+                match TransportPolicy::from_str(value) {
+                    Ok(value) => {
+                        res.insert(key.into(), value);
+                    }
+                    Err(err) => {
+                        error!(
+                            "Invalid transport override '{}': {}.", item, err
+                        );
+                        return Err(Failed)
+                    }
+                }
+            }
+            self.transport_overrides = res;
+        }
+
+        // repository_backoff
+        if let Some(value) = args.repository_backoff {
+            self.repository_backoff = if value == 0 {
+                None
+            }
+            else {
+                Some(Duration::from_secs(value))
+            }
+        }
+
+        // repository_backoff_max
+        if let Some(value) = args.repository_backoff_max {
+            self.repository_backoff_max = Duration::from_secs(value)
+        }
+
+        // dead_repository_age
+        if let Some(value) = args.dead_repository_age {
+            self.dead_repository_age = Duration::from_secs(value)
+        }
+
         // rrdp_max_delta_count
         if let Some(value) = args.rrdp_max_delta_count {
             self.rrdp_max_delta_count = value
         }
 
+        // rrdp_delta_overflow
+        if let Some(value) = args.rrdp_delta_overflow {
+            self.rrdp_delta_overflow = value
+        }
+
+        // rrdp_delta_overrides
+        if let Some(list) = args.rrdp_delta_override {
+            self.rrdp_delta_overrides = Self::rrdp_overrides_from_args(
+                list, "RRDP delta override", "URI=COUNT[:POLICY]"
+            )?;
+        }
+
         // rrdp_max_delta_list_len
         if let Some(value) = args.rrdp_max_delta_list_len {
             self.rrdp_max_delta_list_len = value
         }
 
+        // rrdp_snapshot_chunk_size
+        if let Some(value) = args.rrdp_snapshot_chunk_size {
+            self.rrdp_snapshot_chunk_size = if value == 0 {
+                None
+            }
+            else {
+                Some(value)
+            }
+        }
+
+        // rrdp_snapshot_parallelism
+        if let Some(value) = args.rrdp_snapshot_parallelism {
+            self.rrdp_snapshot_parallelism = value
+        }
+
+        // rrdp_bandwidth_limit
+        if let Some(value) = args.rrdp_bandwidth_limit {
+            self.rrdp_bandwidth_limit = if value == 0 {
+                None
+            }
+            else {
+                Some(value)
+            };
+        }
+
+        // rrdp_bandwidth_overrides
+        if let Some(list) = args.rrdp_bandwidth_override {
+            self.rrdp_bandwidth_overrides = Self::rrdp_overrides_from_args(
+                list, "RRDP bandwidth override", "URI=BYTES"
+            )?;
+        }
+
         // rrdp_timeout
         if let Some(value) = args.rrdp_timeout {
             self.rrdp_timeout = if value == 0 {
@@ -564,6 +1174,26 @@ impl Config {
             self.rrdp_connect_timeout = Some(Duration::from_secs(value))
         }
 
+        // rrdp_retries
+        if let Some(value) = args.rrdp_retries {
+            self.rrdp_retries = value
+        }
+
+        // rrdp_retry_backoff
+        if let Some(value) = args.rrdp_retry_backoff {
+            self.rrdp_retry_backoff = Duration::from_secs(value)
+        }
+
+        // rrdp_head_probe
+        if args.rrdp_head_probe {
+            self.rrdp_head_probe = true
+        }
+
+        // rrdp_disable_compression
+        if args.rrdp_disable_compression {
+            self.rrdp_disable_compression = true
+        }
+
         // rrdp_tcp_keepalive
         if let Some(value) = args.rrdp_tcp_keepalive {
             self.rrdp_tcp_keepalive = if value == 0 {
@@ -591,33 +1221,243 @@ impl Config {
             self.rrdp_proxies = list
         }
 
-        // rrdp_keep_responses
-        if let Some(path) = args.rrdp_keep_responses {
-            self.rrdp_keep_responses = Some(path)
+        // rrdp_http_proxy
+        if let Some(proxy) = args.rrdp_http_proxy {
+            self.rrdp_http_proxy = Some(proxy)
         }
 
-        // max_object_size
-        if let Some(value) = args.max_object_size {
-            if value == 0 {
-                self.max_object_size = None
-            }
-            else {
-                self.max_object_size = Some(value)
-            }
+        // rrdp_https_proxy
+        if let Some(proxy) = args.rrdp_https_proxy {
+            self.rrdp_https_proxy = Some(proxy)
         }
 
-        // max_ca_depth
-        if let Some(value) = args.max_ca_depth {
-            self.max_ca_depth = value;
+        // rrdp_proxy_username
+        if let Some(username) = args.rrdp_proxy_username {
+            self.rrdp_proxy_username = Some(username)
         }
 
-        // enable_bgpsec
-        if args.enable_bgpsec {
+        // rrdp_no_proxy
+        if let Some(list) = args.rrdp_no_proxy {
+            self.rrdp_no_proxy = list
+        }
+
+        // rrdp_user_agent
+        if let Some(agent) = args.rrdp_user_agent {
+            self.rrdp_user_agent = agent
+        }
+
+        // rrdp_headers
+        if let Some(list) = args.rrdp_header {
+            self.rrdp_headers = list
+        }
+
+        // rrdp_header_secrets
+        if let Some(list) = args.rrdp_header_secret {
+            self.rrdp_header_secrets = list.into_iter().map(|item| {
+                let (name, secret) = match item.split_once('=') {
+                    Some(some) => some,
+                    None => {
+                        error!(
+                            "Invalid RRDP header secret '{}': \
+                             expected NAME=SECRET.", item
+                        );
+                        return Err(Failed)
+                    }
+                };
+                match Secret::from_str_in(secret, cur_dir) {
+                    Ok(secret) => Ok((name.trim().into(), secret)),
+                    Err(err) => {
+                        error!(
+                            "Invalid RRDP header secret '{}': {}.",
+                            item, err
+                        );
+                        Err(Failed)
+                    }
+                }
+            }).collect::<Result<_, _>>()?;
+        }
+
+        // rrdp_keep_responses
+        if let Some(path) = args.rrdp_keep_responses {
+            self.rrdp_keep_responses = Some(path)
+        }
+
+        // rrdp_local_dir
+        if let Some(path) = args.rrdp_local_dir {
+            self.rrdp_local_dir = Some(cur_dir.join(path))
+        }
+
+        // fetch_log
+        if let Some(path) = args.fetch_log {
+            self.fetch_log = Some(cur_dir.join(path))
+        }
+
+        // rejection_report
+        if let Some(path) = args.rejection_report {
+            self.rejection_report = Some(cur_dir.join(path))
+        }
+
+        // mirror_dir
+        if let Some(path) = args.mirror_dir {
+            self.mirror_dir = Some(cur_dir.join(path))
+        }
+
+        // mirror_https_uri
+        if let Some(uri) = args.mirror_https_uri {
+            self.mirror_https_uri = Some(uri)
+        }
+
+        // crosscheck
+        if args.crosscheck {
+            self.crosscheck = true
+        }
+
+        // roa_overlap_report
+        if args.roa_overlap_report {
+            self.roa_overlap_report = true
+        }
+
+        // uri_rewrites
+        if let Some(list) = args.uri_rewrite {
+            self.uri_rewrites = Vec::new();
+            for rule in list {
+                let (from, to) = match rule.split_once('=') {
+                    Some(some) => some,
+                    None => {
+                        error!(
+                            "Invalid URI rewrite rule '{}': \
+                             expected FROM=TO.",
+                            rule
+                        );
+                        return Err(Failed)
+                    }
+                };
+                if let Err(err) = UriRewrite::check_rule(from, to) {
+                    error!("Invalid URI rewrite rule '{}': {}.", rule, err);
+                    return Err(Failed)
+                }
+                self.uri_rewrites.push((from.into(), to.into()));
+            }
+        }
+
+        // max_object_size
+        if let Some(value) = args.max_object_size {
+            if value == 0 {
+                self.max_object_size = None
+            }
+            else {
+                self.max_object_size = Some(value)
+            }
+        }
+
+        // max_repository_size
+        if let Some(value) = args.max_repository_size {
+            if value == 0 {
+                self.max_repository_size = None
+            }
+            else {
+                self.max_repository_size = Some(value)
+            }
+        }
+
+        // max_cache_size
+        if let Some(value) = args.max_cache_size {
+            if value == 0 {
+                self.max_cache_size = None
+            }
+            else {
+                self.max_cache_size = Some(value)
+            }
+        }
+
+        // rrdp_max_objects
+        if let Some(value) = args.rrdp_max_objects {
+            self.rrdp_max_objects = if value == 0 {
+                None
+            }
+            else {
+                Some(value)
+            };
+        }
+
+        // rrdp_max_decoded_size
+        if let Some(value) = args.rrdp_max_decoded_size {
+            self.rrdp_max_decoded_size = if value == 0 {
+                None
+            }
+            else {
+                Some(value)
+            };
+        }
+
+        // rrdp_limit_overrides
+        if let Some(list) = args.rrdp_limit_override {
+            self.rrdp_limit_overrides = Self::rrdp_overrides_from_args(
+                list, "RRDP limit override", "URI=LIMIT:VALUE[,...]"
+            )?;
+        }
+
+        // max_ca_depth
+        if let Some(value) = args.max_ca_depth {
+            self.max_ca_depth = value;
+        }
+
+        // max_ca_children
+        if let Some(value) = args.max_ca_children {
+            self.max_ca_children = if value == 0 {
+                None
+            }
+            else {
+                Some(value)
+            }
+        }
+
+        // max_point_objects
+        if let Some(value) = args.max_point_objects {
+            self.max_point_objects = if value == 0 {
+                None
+            }
+            else {
+                Some(value)
+            }
+        }
+
+        // max_ca_time
+        if let Some(value) = args.max_ca_time {
+            self.max_ca_time = if value == 0 {
+                None
+            }
+            else {
+                Some(Duration::from_secs(value))
+            }
+        }
+
+        // signature_cache_size
+        if let Some(value) = args.signature_cache_size {
+            self.signature_cache_size = if value == 0 {
+                None
+            }
+            else {
+                Some(value)
+            }
+        }
+
+        // object_cache_size
+        if let Some(value) = args.object_cache_size {
+            self.object_cache_size = if value == 0 {
+                None
+            }
+            else {
+                Some(value)
+            }
+        }
+
+        // enable_bgpsec
+        if args.enable_bgpsec {
             self.enable_bgpsec = true
         }
 
         // enable_aspa
-        #[cfg(feature = "aspa")]
         if args.enable_aspa {
             self.enable_aspa = true
         }
@@ -627,11 +1467,112 @@ impl Config {
             self.dirty_repository = true
         }
 
+        // store_readonly
+        if args.store_readonly {
+            self.store_readonly = true
+        }
+
+        // store_shared
+        if args.store_shared {
+            self.store_shared = true
+        }
+
+        // freeze
+        if args.freeze {
+            self.freeze = true;
+            self.store_readonly = true;
+        }
+
+        // stored_max_age
+        if let Some(value) = args.stored_max_age {
+            self.stored_max_age = if value == 0 {
+                None
+            }
+            else {
+                Some(Duration::from_secs(value))
+            }
+        }
+
+        // stored_stale_age
+        if let Some(value) = args.stored_stale_age {
+            self.stored_stale_age = if value == 0 {
+                None
+            }
+            else {
+                Some(Duration::from_secs(value))
+            }
+        }
+
+        // checkpoint_interval
+        if let Some(value) = args.checkpoint_interval {
+            self.checkpoint_interval = if value == 0 {
+                None
+            }
+            else {
+                Some(Duration::from_secs(value))
+            }
+        }
+
         // validation_threads
         if let Some(value) = args.validation_threads {
             self.validation_threads = value
         }
 
+        // tal_shares
+        if let Some(list) = args.tal_share {
+            let mut res = HashMap::new();
+            for item in list {
+                let (tal, value) = match item.split_once('=') {
+                    Some(some) => some,
+                    None => {
+                        error!(
+                            "Invalid TAL share '{}': expected TAL=PERCENT.",
+                            item
+                        );
+                        return Err(Failed)
+                    }
+                };
+                match parse_tal_share(value) {
+                    Ok(value) => {
+                        res.insert(tal.into(), value);
+                    }
+                    Err(err) => {
+                        error!("Invalid TAL share '{}': {}.", item, err);
+                        return Err(Failed)
+                    }
+                }
+            }
+            if let Err(err) = check_tal_shares(&res) {
+                error!("Invalid TAL shares: {}.", err);
+                return Err(Failed)
+            }
+            self.tal_shares = res;
+        }
+
+        // tal_priorities
+        if let Some(list) = args.tal_priority {
+            let mut res = HashMap::new();
+            for item in list {
+                let value = item.split_once('=').and_then(|(tal, value)| {
+                    i32::from_str(value.trim()).ok().map(|value| (tal, value))
+                });
+                match value {
+                    Some((tal, value)) => {
+                        res.insert(tal.into(), value);
+                    }
+                    None => {
+                        error!(
+                            "Invalid TAL priority '{}': \
+                             expected TAL=PRIORITY.",
+                            item
+                        );
+                        return Err(Failed)
+                    }
+                }
+            }
+            self.tal_priorities = res;
+        }
+
         // log_level
         if args.verbose > 1 {
             self.log_level = LevelFilter::Debug
@@ -726,6 +1667,41 @@ impl Config {
             self.refresh = Duration::from_secs(value)
         }
 
+        // fetch_spread
+        if let Some(value) = args.fetch_spread {
+            self.fetch_spread = if value == 0 { None } else { Some(value) }
+        }
+
+        // rrdp_heartbeat
+        if let Some(value) = args.rrdp_heartbeat {
+            self.rrdp_heartbeat = if value == 0 {
+                None
+            }
+            else {
+                Some(Duration::from_secs(value))
+            }
+        }
+
+        // repository_probe
+        if let Some(value) = args.repository_probe {
+            self.repository_probe = if value == 0 {
+                None
+            }
+            else {
+                Some(Duration::from_secs(value))
+            }
+        }
+
+        // min_serial_interval
+        if let Some(value) = args.min_serial_interval {
+            self.min_serial_interval = if value == 0 {
+                None
+            }
+            else {
+                Some(Duration::from_secs(value))
+            }
+        }
+
         // retry
         if let Some(value) = args.retry {
             self.retry = Duration::from_secs(value)
@@ -741,6 +1717,11 @@ impl Config {
             self.history_size = value
         }
 
+        // snapshot_history
+        if let Some(value) = args.snapshot_history {
+            self.snapshot_history = value
+        }
+
         // rtr_listen
         if let Some(list) = args.rtr_listen {
             self.rtr_listen = list
@@ -781,9 +1762,26 @@ impl Config {
             self.rtr_client_metrics = true
         }
 
+        // rtr_upstream
+        if let Some(addr) = args.rtr_upstream {
+            self.rtr_upstream = Some(addr)
+        }
+
+        // rtr_upstream_ca_bundle
+        if let Some(path) = args.rtr_upstream_ca_bundle {
+            self.rtr_upstream_ca_bundle = Some(cur_dir.join(path))
+        }
+
+        // rtr_upstream_pinned_certs
+        if let Some(pins) = args.rtr_upstream_pinned_sha256 {
+            self.rtr_upstream_pinned_certs = pins
+        }
+
         // rtr_tls_key
-        if let Some(path) = args.rtr_tls_key {
-            self.rtr_tls_key = Some(cur_dir.join(path))
+        if let Some(secret) = args.rtr_tls_key {
+            self.rtr_tls_key = Some(
+                Self::secret_from_arg(&secret, cur_dir, "rtr-tls-key")?
+            )
         }
 
         // rtr_tls_cert
@@ -792,8 +1790,10 @@ impl Config {
         }
 
         // http_tls_key
-        if let Some(path) = args.http_tls_key {
-            self.http_tls_key = Some(cur_dir.join(path))
+        if let Some(secret) = args.http_tls_key {
+            self.http_tls_key = Some(
+                Self::secret_from_arg(&secret, cur_dir, "http-tls-key")?
+            )
         }
 
         // http_tls_cert
@@ -801,6 +1801,27 @@ impl Config {
             self.http_tls_cert = Some(cur_dir.join(path))
         }
 
+        // http_refresh_token
+        if let Some(secret) = args.http_refresh_token {
+            self.http_refresh_token = Some(
+                Self::secret_from_arg(
+                    &secret, cur_dir, "http-refresh-token"
+                )?
+            )
+        }
+
+        // http_tal_token
+        if let Some(secret) = args.http_tal_token {
+            self.http_tal_token = Some(
+                Self::secret_from_arg(&secret, cur_dir, "http-tal-token")?
+            )
+        }
+
+        // http_audit_log
+        if let Some(path) = args.http_audit_log {
+            self.http_audit_log = Some(cur_dir.join(path))
+        }
+
         // pid_file
         if let Some(pid_file) = args.pid_file {
             self.pid_file = Some(cur_dir.join(pid_file))
@@ -840,12 +1861,73 @@ impl Config {
         matches.get_one::<PathBuf>(key).map(|path| dir.join(path))
     }
 
+    /// Parses a secret given on the command line.
+    ///
+    /// Relative paths are expanded based on the given directory. The
+    /// `option` is used in error messages.
+    fn secret_from_arg(
+        value: &str, dir: &Path, option: &str
+    ) -> Result<Secret, Failed> {
+        Secret::from_str_in(value, dir).map_err(|err| {
+            error!("Invalid secret for --{}: {}.", option, err);
+            Failed
+        })
+    }
+
+    /// Parses a list of per-repository overrides from the command line.
+    ///
+    /// Each item in the list has the form `URI=VALUE` where the URI is the
+    /// rpkiNotify URI of an RRDP repository. The `what` and `expected`
+    /// strings are used in error messages.
+    fn rrdp_overrides_from_args<T>(
+        list: Vec<String>,
+        what: &str,
+        expected: &str,
+    ) -> Result<HashMap<uri::Https, T>, Failed>
+    where T: FromStr, T::Err: fmt::Display {
+        let mut res = HashMap::new();
+        for item in list {
+            let (rpki_notify, value) = match item.split_once('=') {
+                Some(some) => some,
+                None => {
+                    error!(
+                        "Invalid {} '{}': expected {}.",
+                        what, item, expected
+                    );
+                    return Err(Failed)
+                }
+            };
+            let rpki_notify = match uri::Https::from_str(rpki_notify) {
+                Ok(uri) => uri,
+                Err(err) => {
+                    error!("Invalid {} '{}': {}.", what, item, err);
+                    return Err(Failed)
+                }
+            };
+            let value = match T::from_str(value) {
+                Ok(value) => value,
+                Err(err) => {
+                    error!("Invalid {} '{}': {}.", what, item, err);
+                    return Err(Failed)
+                }
+            };
+            res.insert(rpki_notify, value);
+        }
+        Ok(res)
+    }
+
     /// Creates the correct base configuration for the given config file path.
     /// 
     /// If no config path is given, tries to read the default config in
     /// `$HOME/.routinator.conf`. If that doesn’t exist, creates a default
     /// config.
-    fn create_base_config(path: Option<&Path>) -> Result<Self, Failed> {
+    ///
+    /// If `profile` is given, it overrides the profile given in the config
+    /// file.
+    fn create_base_config(
+        path: Option<&Path>,
+        profile: Option<ConfigProfile>,
+    ) -> Result<Self, Failed> {
         let file = match path {
             Some(path) => {
                 match ConfigFile::read(path)? {
@@ -861,20 +1943,51 @@ impl Config {
                     Some(dir) => match ConfigFile::read(
                                             &dir.join(".routinator.conf"))? {
                         Some(file) => file,
-                        None => return Ok(Self::default()),
+                        None => {
+                            return Ok(Self::default_with_profile(
+                                profile.unwrap_or_default()
+                            ))
+                        }
+                    }
+                    None => {
+                        return Ok(Self::default_with_profile(
+                            profile.unwrap_or_default()
+                        ))
                     }
-                    None => return Ok(Self::default())
                 }
             }
         };
-        Self::from_config_file(file)
+        Self::from_config_file(file, profile)
+    }
+
+    /// Creates a default config using the defaults of the given profile.
+    fn default_with_profile(profile: ConfigProfile) -> Self {
+        Config {
+            profile,
+            rsync_timeout: Some(profile.rsync_timeout()),
+            rrdp_fallback_time: profile.rrdp_fallback_time(),
+            rrdp_max_delta_count: profile.rrdp_max_delta_count(),
+            rrdp_timeout: Some(profile.rrdp_timeout()),
+            validation_threads: profile.validation_threads(),
+            history_size: profile.history_size(),
+            ..Self::default()
+        }
     }
 
     /// Creates a base config from a config file.
-    fn from_config_file(mut file: ConfigFile) -> Result<Self, Failed> {
+    ///
+    /// If `profile` is given, it overrides the profile given in the config
+    /// file.
+    fn from_config_file(
+        mut file: ConfigFile,
+        profile: Option<ConfigProfile>,
+    ) -> Result<Self, Failed> {
         let log_target = Self::log_target_from_config_file(&mut file)?;
-        let res = Config {
+        let file_profile = file.take_from_str("profile")?;
+        let profile = profile.or(file_profile).unwrap_or_default();
+        let mut res = Config {
             config_file: file.path.clone(),
+            profile,
             cache_dir: file.take_mandatory_path("repository-dir")?,
             no_rir_tals: file.take_bool("no-rir-tals")?.unwrap_or(false),
             bundled_tals: {
@@ -889,18 +2002,51 @@ impl Config {
             stale: {
                 file.take_from_str("stale")?.unwrap_or(DEFAULT_STALE_POLICY)
             },
-            unsafe_vrps: {
-                file.take_from_str("unsafe-vrps")?
-                    .unwrap_or(DEFAULT_UNSAFE_VRPS_POLICY)
+            stale_grace: {
+                match file.take_u64("stale-grace")? {
+                    Some(0) | None => None,
+                    Some(value) => Some(Duration::from_secs(value)),
+                }
+            },
+            clock_skew: {
+                match file.take_u64("clock-skew")? {
+                    Some(0) | None => None,
+                    Some(value) => Some(Duration::from_secs(value)),
+                }
+            },
+            validation_time: file.take_from_str("validation-time")?,
+            missing_files: {
+                file.take_from_str("missing-files")?
+                    .unwrap_or(DEFAULT_MISSING_FILES_POLICY)
+            },
+            hash_mismatch: {
+                file.take_from_str("hash-mismatch")?
+                    .unwrap_or(DEFAULT_HASH_MISMATCH_POLICY)
+            },
+            resource_validation: {
+                file.take_from_str("resource-validation")?
+                    .unwrap_or(DEFAULT_RESOURCE_VALIDATION)
+            },
+            tal_policies: file.take_tal_policies("tal-policies")?,
+            unsafe_vrps: {
+                file.take_from_str("unsafe-vrps")?
+                    .unwrap_or(DEFAULT_UNSAFE_VRPS_POLICY)
             },
             unknown_objects: {
                 file.take_from_str("unknown-objects")?
                     .unwrap_or(DEFAULT_UNKNOWN_OBJECTS_POLICY)
             },
+            roa_policy: file.take_roa_policy("roa-policy")?,
             limit_v4_len: file.take_limited_u8("limit-v4-len", 32)?,
             limit_v6_len: file.take_limited_u8("limit-v6-len", 128)?,
             allow_dubious_hosts:
                 file.take_bool("allow-dubious-hosts")?.unwrap_or(false),
+            max_host_connections: {
+                match file.take_small_usize("max-host-connections")? {
+                    Some(0) | None => None,
+                    Some(value) => Some(value),
+                }
+            },
             fresh: false,
             disable_rsync: file.take_bool("disable-rsync")?.unwrap_or(false),
             rsync_command: {
@@ -908,13 +2054,34 @@ impl Config {
                     .unwrap_or_else(|| "rsync".into())
             },
             rsync_args: file.take_string_array("rsync-args")?,
+            rsync_include: {
+                file.take_string_array("rsync-include")?.unwrap_or_else(|| {
+                    Self::default_rsync_include()
+                })
+            },
+            rsync_exclude: {
+                file.take_string_array("rsync-exclude")?.unwrap_or_default()
+            },
             rsync_timeout: {
                 match file.take_u64("rsync-timeout")? {
                     Some(0) => None,
                     Some(value) => Some(Duration::from_secs(value)),
-                    None => Some(DEFAULT_RSYNC_TIMEOUT)
+                    None => Some(profile.rsync_timeout())
                 }
             },
+            rsync_bandwidth_limit: {
+                match file.take_u64("rsync-bandwidth-limit")? {
+                    Some(0) | None => None,
+                    Some(value) => Some(value),
+                }
+            },
+            rsync_max_parallel: {
+                match file.take_small_usize("rsync-max-parallel")? {
+                    Some(0) | None => None,
+                    Some(value) => Some(value),
+                }
+            },
+            rsync_overrides: file.take_rsync_overrides("rsync-overrides")?,
             disable_rrdp: file.take_bool("disable-rrdp")?.unwrap_or(false),
             rrdp_fallback: {
                 file.take_from_str("rrdp-fallback")?
@@ -923,26 +2090,89 @@ impl Config {
             rrdp_fallback_time: {
                 file.take_u64("rrdp-fallback-time")?
                 .map(Duration::from_secs)
-                .unwrap_or(DEFAULT_RRDP_FALLBACK_TIME)
+                .unwrap_or_else(|| profile.rrdp_fallback_time())
+            },
+            transport_overrides: {
+                file.take_transport_overrides("transport-overrides")?
+            },
+            repository_backoff: {
+                match file.take_u64("repository-backoff")? {
+                    Some(0) | None => None,
+                    Some(value) => Some(Duration::from_secs(value)),
+                }
+            },
+            repository_backoff_max: {
+                file.take_u64("repository-backoff-max")?
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_REPOSITORY_BACKOFF_MAX)
+            },
+            dead_repository_age: {
+                file.take_u64("dead-repository-age")?
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_DEAD_REPOSITORY_AGE)
             },
             rrdp_max_delta_count: {
                 file.take_usize("rrdp-max-delta-count")?
-                .unwrap_or(DEFAULT_RRDP_MAX_DELTA_COUNT)
+                .unwrap_or_else(|| profile.rrdp_max_delta_count())
+            },
+            rrdp_delta_overflow: {
+                file.take_from_str("rrdp-delta-overflow")?
+                .unwrap_or(DeltaOverflowPolicy::Snapshot)
+            },
+            rrdp_delta_overrides: {
+                file.take_rrdp_overrides("rrdp-delta-overrides")?
             },
             rrdp_max_delta_list_len: {
                 file.take_usize("rrdp-max-delta-list-len")?
                 .unwrap_or(DEFAULT_RRDP_MAX_DELTA_LIST_LEN)
             },
+            rrdp_snapshot_chunk_size: {
+                match file.take_u64("rrdp-snapshot-chunk-size")? {
+                    Some(0) | None => None,
+                    Some(value) => Some(value),
+                }
+            },
+            rrdp_snapshot_parallelism: {
+                file.take_small_usize("rrdp-snapshot-parallelism")?
+                .unwrap_or(DEFAULT_RRDP_SNAPSHOT_PARALLELISM)
+            },
+            rrdp_bandwidth_limit: {
+                match file.take_u64("rrdp-bandwidth-limit")? {
+                    Some(0) | None => None,
+                    Some(value) => Some(value),
+                }
+            },
+            rrdp_bandwidth_overrides: {
+                file.take_rrdp_overrides("rrdp-bandwidth-overrides")?
+            },
             rrdp_timeout: {
                 match file.take_u64("rrdp-timeout")? {
                     Some(0) => None,
                     Some(value) => Some(Duration::from_secs(value)),
-                    None => Some(DEFAULT_RRDP_TIMEOUT)
+                    None => Some(profile.rrdp_timeout())
                 }
             },
             rrdp_connect_timeout: {
                 file.take_u64("rrdp-connect-timeout")?.map(Duration::from_secs)
             },
+            rrdp_retries: {
+                file.take_small_usize("rrdp-retries")?.unwrap_or(0)
+            },
+            rrdp_retry_backoff: {
+                file.take_u64("rrdp-retry-backoff")?
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_RRDP_RETRY_BACKOFF)
+            },
+            rrdp_head_probe: {
+                file.take_bool("rrdp-head-probe")?.unwrap_or(false)
+            },
+            rrdp_disable_compression: {
+                file.take_bool("rrdp-disable-compression")?.unwrap_or(false)
+            },
+            rrdp_overrides: file.take_rrdp_request_overrides("rrdp-overrides")?,
+            rrdp_tls_overrides: {
+                file.take_rrdp_tls_overrides("rrdp-tls-overrides")?
+            },
             rrdp_tcp_keepalive: {
                 match file.take_u64("rrdp-tcp-keepalive")? {
                     Some(0) => None,
@@ -958,8 +2188,39 @@ impl Config {
             rrdp_proxies: {
                 file.take_string_array("rrdp-proxies")?.unwrap_or_default()
             },
-            rrdp_user_agent: DEFAULT_RRDP_USER_AGENT.to_string(),
+            rrdp_http_proxy: file.take_string("rrdp-http-proxy")?,
+            rrdp_https_proxy: file.take_string("rrdp-https-proxy")?,
+            rrdp_proxy_username: file.take_string("rrdp-proxy-username")?,
+            rrdp_proxy_password: file.take_secret("rrdp-proxy-password")?,
+            rrdp_no_proxy: {
+                file.take_string_array("rrdp-no-proxy")?.unwrap_or_default()
+            },
+            rrdp_user_agent: {
+                file.take_string("rrdp-user-agent")?.unwrap_or_else(|| {
+                    DEFAULT_RRDP_USER_AGENT.to_string()
+                })
+            },
+            rrdp_headers: {
+                file.take_string_array("rrdp-headers")?.unwrap_or_default()
+            },
+            rrdp_header_secrets: {
+                file.take_string_pairs("rrdp-header-secrets")?
+                .unwrap_or_default().into_iter().map(|(name, secret)| {
+                    file.secret_from_str(&secret, "rrdp-header-secrets")
+                    .map(|secret| (name, secret))
+                }).collect::<Result<_, _>>()?
+            },
             rrdp_keep_responses: file.take_path("rrdp-keep-responses")?,
+            rrdp_local_dir: file.take_path("rrdp-local-dir")?,
+            fetch_log: file.take_path("fetch-log")?,
+            rejection_report: file.take_path("rejection-report")?,
+            mirror_dir: file.take_path("mirror-dir")?,
+            mirror_https_uri: file.take_from_str("mirror-https-uri")?,
+            crosscheck: file.take_bool("crosscheck")?.unwrap_or(false),
+            roa_overlap_report: {
+                file.take_bool("roa-overlap-report")?.unwrap_or(false)
+            },
+            uri_rewrites: file.take_uri_rewrites("uri-rewrites")?,
             max_object_size: {
                 match file.take_u64("max-object-size")? {
                     Some(0) => None,
@@ -967,31 +2228,129 @@ impl Config {
                     None => Some(DEFAULT_MAX_OBJECT_SIZE),
                 }
             },
+            max_repository_size: {
+                match file.take_u64("max-repository-size")? {
+                    Some(0) | None => None,
+                    Some(value) => Some(value),
+                }
+            },
+            max_cache_size: {
+                match file.take_u64("max-cache-size")? {
+                    Some(0) | None => None,
+                    Some(value) => Some(value),
+                }
+            },
+            rrdp_max_objects: {
+                match file.take_usize("rrdp-max-objects")? {
+                    Some(0) | None => None,
+                    Some(value) => Some(value),
+                }
+            },
+            rrdp_max_decoded_size: {
+                match file.take_u64("rrdp-max-decoded-size")? {
+                    Some(0) | None => None,
+                    Some(value) => Some(value),
+                }
+            },
+            rrdp_limit_overrides: {
+                file.take_rrdp_overrides("rrdp-limit-overrides")?
+            },
             max_ca_depth: {
                 file.take_usize("max-ca-depth")?
                     .unwrap_or(DEFAULT_MAX_CA_DEPTH)
             },
+            max_ca_children: {
+                match file.take_usize("max-ca-children")? {
+                    Some(0) => None,
+                    value => value,
+                }
+            },
+            max_point_objects: {
+                match file.take_usize("max-point-objects")? {
+                    Some(0) => None,
+                    value => value,
+                }
+            },
+            max_ca_time: {
+                match file.take_u64("max-ca-time")? {
+                    Some(0) | None => None,
+                    Some(value) => Some(Duration::from_secs(value)),
+                }
+            },
+            signature_cache_size: {
+                match file.take_usize("signature-cache-size")? {
+                    Some(0) => None,
+                    value => value,
+                }
+            },
+            object_cache_size: {
+                match file.take_usize("object-cache-size")? {
+                    Some(0) => None,
+                    value => value,
+                }
+            },
             enable_bgpsec: file.take_bool("enable-bgpsec")?.unwrap_or(false),
-
-            #[cfg(feature = "aspa")]
             enable_aspa: file.take_bool("enable-aspa")?.unwrap_or(false),
-
-            #[cfg(not(feature = "aspa"))]
-            enable_aspa: false,
-
             dirty_repository: file.take_bool("dirty")?.unwrap_or(false),
+            store_readonly: {
+                file.take_bool("store-readonly")?.unwrap_or(false)
+            },
+            store_shared: file.take_bool("store-shared")?.unwrap_or(false),
+            freeze: file.take_bool("freeze")?.unwrap_or(false),
+            stored_max_age: {
+                match file.take_u64("stored-max-age")? {
+                    Some(0) | None => None,
+                    Some(value) => Some(Duration::from_secs(value)),
+                }
+            },
+            stored_stale_age: {
+                match file.take_u64("stored-stale-age")? {
+                    Some(0) | None => None,
+                    Some(value) => Some(Duration::from_secs(value)),
+                }
+            },
+            checkpoint_interval: {
+                match file.take_u64("checkpoint-interval")? {
+                    Some(0) | None => None,
+                    Some(value) => Some(Duration::from_secs(value)),
+                }
+            },
             validation_threads: {
                 file.take_small_usize(
                     "validation-threads"
-                )?.unwrap_or_else(|| {
-                    Config::default_validation_threads()
-                })
+                )?.unwrap_or_else(|| profile.validation_threads())
             },
+            tal_shares: file.take_tal_shares("tal-shares")?,
+            tal_priorities: file.take_tal_priorities("tal-priorities")?,
             refresh: {
                 Duration::from_secs(
                     file.take_u64("refresh")?.unwrap_or(DEFAULT_REFRESH)
                 )
             },
+            fetch_spread: {
+                match file.take_u32("fetch-spread")? {
+                    Some(0) | None => None,
+                    Some(value) => Some(value),
+                }
+            },
+            rrdp_heartbeat: {
+                match file.take_u64("rrdp-heartbeat")? {
+                    Some(0) | None => None,
+                    Some(value) => Some(Duration::from_secs(value)),
+                }
+            },
+            repository_probe: {
+                match file.take_u64("repository-probe")? {
+                    Some(0) | None => None,
+                    Some(value) => Some(Duration::from_secs(value)),
+                }
+            },
+            min_serial_interval: {
+                match file.take_u64("min-serial-interval")? {
+                    Some(0) | None => None,
+                    Some(value) => Some(Duration::from_secs(value)),
+                }
+            },
             retry: {
                 Duration::from_secs(
                     file.take_u64("retry")?.unwrap_or(DEFAULT_RETRY)
@@ -1004,7 +2363,10 @@ impl Config {
             },
             history_size: {
                 file.take_small_usize("history-size")?
-                    .unwrap_or(DEFAULT_HISTORY_SIZE)
+                    .unwrap_or_else(|| profile.history_size())
+            },
+            snapshot_history: {
+                file.take_small_usize("snapshot-history")?.unwrap_or(0)
             },
             rtr_listen: {
                 file.take_from_str_array("rtr-listen")?.unwrap_or_default()
@@ -1031,10 +2393,21 @@ impl Config {
             rtr_client_metrics: {
                 file.take_bool("rtr-client-metrics")?.unwrap_or(false)
             },
-            rtr_tls_key: file.take_path("rtr-tls-key")?,
+            rtr_upstream: file.take_string("rtr-upstream")?,
+            rtr_upstream_ca_bundle: {
+                file.take_path("rtr-upstream-ca-bundle")?
+            },
+            rtr_upstream_pinned_certs: {
+                file.take_from_str_array("rtr-upstream-pinned-sha256")?
+                    .unwrap_or_default()
+            },
+            rtr_tls_key: file.take_secret("rtr-tls-key")?,
             rtr_tls_cert: file.take_path("rtr-tls-cert")?,
-            http_tls_key: file.take_path("http-tls-key")?,
+            http_tls_key: file.take_secret("http-tls-key")?,
             http_tls_cert: file.take_path("http-tls-cert")?,
+            http_refresh_token: file.take_secret("http-refresh-token")?,
+            http_tal_token: file.take_secret("http-tal-token")?,
+            http_audit_log: file.take_path("http-audit-log")?,
             log_level: {
                 file.take_from_str("log-level")?.unwrap_or(LevelFilter::Warn)
             },
@@ -1053,6 +2426,9 @@ impl Config {
                 file.path.display()
             );
         }
+        if res.freeze {
+            res.store_readonly = true
+        }
        
         file.check_exhausted()?;
         Ok(res)
@@ -1154,6 +2530,7 @@ impl Config {
     ) -> Self {
         Self {
             config_file,
+            profile: ConfigProfile::Default,
             cache_dir,
             no_rir_tals: false,
             bundled_tals: Vec::new(),
@@ -1161,39 +2538,107 @@ impl Config {
             exceptions: Vec::new(),
             strict: DEFAULT_STRICT,
             stale: DEFAULT_STALE_POLICY,
+            stale_grace: None,
+            clock_skew: None,
+            validation_time: None,
+            missing_files: DEFAULT_MISSING_FILES_POLICY,
+            hash_mismatch: DEFAULT_HASH_MISMATCH_POLICY,
+            resource_validation: DEFAULT_RESOURCE_VALIDATION,
+            tal_policies: HashMap::new(),
             unsafe_vrps: DEFAULT_UNSAFE_VRPS_POLICY,
             unknown_objects: DEFAULT_UNKNOWN_OBJECTS_POLICY,
+            roa_policy: HashMap::new(),
             limit_v4_len: None,
             limit_v6_len: None,
             allow_dubious_hosts: false,
+            max_host_connections: None,
             fresh: false,
             disable_rsync: false,
             rsync_command: "rsync".into(),
             rsync_args: None,
+            rsync_include: Self::default_rsync_include(),
+            rsync_exclude: Vec::new(),
             rsync_timeout: Some(DEFAULT_RSYNC_TIMEOUT),
+            rsync_bandwidth_limit: None,
+            rsync_max_parallel: None,
+            rsync_overrides: HashMap::new(),
             disable_rrdp: false,
             rrdp_fallback: DEFAULT_RRDP_FALLBACK,
             rrdp_fallback_time: DEFAULT_RRDP_FALLBACK_TIME,
+            transport_overrides: HashMap::new(),
+            repository_backoff: None,
+            repository_backoff_max: DEFAULT_REPOSITORY_BACKOFF_MAX,
+            dead_repository_age: DEFAULT_DEAD_REPOSITORY_AGE,
             rrdp_max_delta_count: DEFAULT_RRDP_MAX_DELTA_COUNT,
+            rrdp_delta_overflow: DeltaOverflowPolicy::Snapshot,
+            rrdp_delta_overrides: HashMap::new(),
             rrdp_max_delta_list_len: DEFAULT_RRDP_MAX_DELTA_LIST_LEN,
+            rrdp_snapshot_chunk_size: None,
+            rrdp_snapshot_parallelism: DEFAULT_RRDP_SNAPSHOT_PARALLELISM,
+            rrdp_bandwidth_limit: None,
+            rrdp_bandwidth_overrides: HashMap::new(),
             rrdp_timeout: Some(DEFAULT_RRDP_TIMEOUT), 
             rrdp_connect_timeout: None,
+            rrdp_retries: 0,
+            rrdp_retry_backoff: DEFAULT_RRDP_RETRY_BACKOFF,
+            rrdp_head_probe: false,
+            rrdp_disable_compression: false,
+            rrdp_overrides: HashMap::new(),
+            rrdp_tls_overrides: HashMap::new(),
             rrdp_tcp_keepalive: Some(DEFAULT_RRDP_TCP_KEEPALIVE),
             rrdp_local_addr: None,
             rrdp_root_certs: Vec::new(),
             rrdp_proxies: Vec::new(),
+            rrdp_http_proxy: None,
+            rrdp_https_proxy: None,
+            rrdp_proxy_username: None,
+            rrdp_proxy_password: None,
+            rrdp_no_proxy: Vec::new(),
+            uri_rewrites: Vec::new(),
             rrdp_user_agent: DEFAULT_RRDP_USER_AGENT.to_string(),
+            rrdp_headers: Vec::new(),
+            rrdp_header_secrets: Vec::new(),
             rrdp_keep_responses: None,
+            rrdp_local_dir: None,
+            fetch_log: None,
+            rejection_report: None,
+            mirror_dir: None,
+            mirror_https_uri: None,
+            crosscheck: false,
+            roa_overlap_report: false,
             max_object_size: Some(DEFAULT_MAX_OBJECT_SIZE),
+            max_repository_size: None,
+            max_cache_size: None,
+            rrdp_max_objects: None,
+            rrdp_max_decoded_size: None,
+            rrdp_limit_overrides: HashMap::new(),
             max_ca_depth: DEFAULT_MAX_CA_DEPTH,
+            max_ca_children: None,
+            max_point_objects: None,
+            max_ca_time: None,
+            signature_cache_size: None,
+            object_cache_size: None,
             enable_bgpsec: false,
             enable_aspa: false,
             dirty_repository: DEFAULT_DIRTY_REPOSITORY,
+            store_readonly: false,
+            store_shared: false,
+            freeze: false,
+            stored_max_age: None,
+            stored_stale_age: None,
+            checkpoint_interval: None,
             validation_threads: Config::default_validation_threads(),
+            tal_shares: HashMap::new(),
+            tal_priorities: HashMap::new(),
             refresh: Duration::from_secs(DEFAULT_REFRESH),
+            fetch_spread: None,
+            rrdp_heartbeat: None,
+            repository_probe: None,
+            min_serial_interval: None,
             retry: Duration::from_secs(DEFAULT_RETRY),
             expire: Duration::from_secs(DEFAULT_EXPIRE),
             history_size: DEFAULT_HISTORY_SIZE,
+            snapshot_history: 0,
             rtr_listen: Vec::new(),
             rtr_tls_listen: Vec::new(),
             http_listen: Vec::new(),
@@ -1201,10 +2646,16 @@ impl Config {
             systemd_listen: false,
             rtr_tcp_keepalive: DEFAULT_RTR_TCP_KEEPALIVE,
             rtr_client_metrics: false,
+            rtr_upstream: None,
+            rtr_upstream_ca_bundle: None,
+            rtr_upstream_pinned_certs: Vec::new(),
             rtr_tls_key: None,
             rtr_tls_cert: None,
             http_tls_key: None,
             http_tls_cert: None,
+            http_refresh_token: None,
+            http_tal_token: None,
+            http_audit_log: None,
             log_level: LevelFilter::Warn,
             log_target: LogTarget::default(),
             pid_file: None,
@@ -1221,6 +2672,11 @@ impl Config {
         available_parallelism().map(|x| x.get()).unwrap_or(1)
     }
 
+    /// Returns the default value for the rsync include patterns.
+    fn default_rsync_include() -> Vec<String> {
+        DEFAULT_RSYNC_INCLUDE.iter().map(|s| String::from(*s)).collect()
+    }
+
     /// Alters paths so that they are relative to a possible chroot.
     pub fn adjust_chroot_paths(&mut self) -> Result<(), Failed> {
         if let Some(ref chroot) = self.chroot {
@@ -1308,6 +2764,7 @@ impl Config {
         }
 
         let mut res = toml::Table::new();
+        insert(&mut res, "profile", self.profile.to_string());
         insert(
             &mut res, "repository-dir", self.cache_dir.display().to_string()
         );
@@ -1327,10 +2784,49 @@ impl Config {
         );
         insert(&mut res, "strict", self.strict);
         insert(&mut res, "stale", format!("{}", self.stale));
+        if let Some(grace) = self.stale_grace {
+            insert_int(&mut res, "stale-grace", grace.as_secs());
+        }
+        if let Some(skew) = self.clock_skew {
+            insert_int(&mut res, "clock-skew", skew.as_secs());
+        }
+        if let Some(time) = self.validation_time {
+            insert(&mut res, "validation-time", time.to_rfc3339());
+        }
+        insert(
+            &mut res, "missing-files", format!("{}", self.missing_files)
+        );
+        insert(
+            &mut res, "hash-mismatch", format!("{}", self.hash_mismatch)
+        );
+        insert(
+            &mut res, "resource-validation",
+            format!("{}", self.resource_validation)
+        );
+        if !self.tal_policies.is_empty() {
+            insert(
+                &mut res, "tal-policies",
+                toml::Value::InlineTable(
+                    self.tal_policies.iter().map(|(key, value)| {
+                        (key.clone(), toml::Value::InlineTable(value.to_toml()))
+                    }).collect()
+                )
+            );
+        }
         insert(&mut res, "unsafe-vrps", format!("{}", self.unsafe_vrps));
         insert(
             &mut res, "unknown-objects", format!("{}", self.unknown_objects)
         );
+        if !self.roa_policy.is_empty() {
+            insert(
+                &mut res, "roa-policy",
+                toml::Value::InlineTable(
+                    self.roa_policy.iter().map(|(key, value)| {
+                        (key.clone(), toml::Value::InlineTable(value.to_toml()))
+                    }).collect()
+                )
+            );
+        }
         if let Some(value) = self.limit_v4_len {
             insert(&mut res, "limit-v4-len", i64::from(value));
         }
@@ -1338,6 +2834,10 @@ impl Config {
             insert(&mut res, "limit-v6-len", i64::from(value));
         }
         insert(&mut res, "allow-dubious-hosts", self.allow_dubious_hosts);
+        insert_int(
+            &mut res, "max-host-connections",
+            self.max_host_connections.unwrap_or(0)
+        );
         insert(&mut res, "disable-rsync", self.disable_rsync);
         insert(&mut res, "rsync-command", self.rsync_command.clone());
         if let Some(ref args) = self.rsync_args {
@@ -1348,6 +2848,22 @@ impl Config {
                 )
             );
         }
+        insert(
+            &mut res, "rsync-include",
+            toml::Value::Array(
+                self.rsync_include.iter().map(|s| {
+                    toml::Value::from(s.clone())
+                }).collect()
+            )
+        );
+        insert(
+            &mut res, "rsync-exclude",
+            toml::Value::Array(
+                self.rsync_exclude.iter().map(|s| {
+                    toml::Value::from(s.clone())
+                }).collect()
+            )
+        );
         insert_int(
             &mut res, "rsync-timeout",
             match self.rsync_timeout {
@@ -1355,6 +2871,24 @@ impl Config {
                 Some(value) => value.as_secs(),
             }
         );
+        insert_int(
+            &mut res, "rsync-bandwidth-limit",
+            self.rsync_bandwidth_limit.unwrap_or(0)
+        );
+        insert_int(
+            &mut res, "rsync-max-parallel",
+            self.rsync_max_parallel.unwrap_or(0)
+        );
+        if !self.rsync_overrides.is_empty() {
+            insert(
+                &mut res, "rsync-overrides",
+                toml::Value::InlineTable(
+                    self.rsync_overrides.iter().map(|(key, value)| {
+                        (key.clone(), toml::Value::InlineTable(value.to_toml()))
+                    }).collect()
+                )
+            );
+        }
         insert(&mut res, "disable-rrdp", self.disable_rrdp);
         insert(&mut res, "rrdp-fallback", self.rrdp_fallback.to_string());
         insert_int(
@@ -1363,9 +2897,25 @@ impl Config {
         insert_int(
             &mut res, "rrdp-max-delta-count", self.rrdp_max_delta_count
         );
+        insert(
+            &mut res, "rrdp-delta-overflow",
+            self.rrdp_delta_overflow.to_string()
+        );
         insert_int(
             &mut res, "rrdp-max-delta-list-len", self.rrdp_max_delta_list_len
         );
+        insert_int(
+            &mut res, "rrdp-snapshot-chunk-size",
+            self.rrdp_snapshot_chunk_size.unwrap_or(0)
+        );
+        insert_int(
+            &mut res, "rrdp-snapshot-parallelism",
+            self.rrdp_snapshot_parallelism
+        );
+        insert_int(
+            &mut res, "rrdp-bandwidth-limit",
+            self.rrdp_bandwidth_limit.unwrap_or(0)
+        );
         insert_int(
             &mut res, "rrdp-timeout",
             match self.rrdp_timeout {
@@ -1376,6 +2926,15 @@ impl Config {
         if let Some(timeout) = self.rrdp_connect_timeout {
             insert_int(&mut res, "rrdp-connect-timeout", timeout.as_secs());
         }
+        insert_int(&mut res, "rrdp-retries", self.rrdp_retries);
+        insert_int(
+            &mut res, "rrdp-retry-backoff", self.rrdp_retry_backoff.as_secs()
+        );
+        insert(&mut res, "rrdp-head-probe", self.rrdp_head_probe);
+        insert(
+            &mut res, "rrdp-disable-compression",
+            self.rrdp_disable_compression
+        );
         insert_int(
             &mut res, "rrdp-tcp-keepalive",
             match self.rrdp_tcp_keepalive {
@@ -1402,92 +2961,286 @@ impl Config {
                 }).collect()
             )
         );
-        if let Some(path) = self.rrdp_keep_responses.as_ref() {
-            insert(
-                &mut res,"rrdp-keep-responses", format!("{}", path.display())
-            );
+        if let Some(ref proxy) = self.rrdp_http_proxy {
+            insert(&mut res, "rrdp-http-proxy", proxy.clone());
+        }
+        if let Some(ref proxy) = self.rrdp_https_proxy {
+            insert(&mut res, "rrdp-https-proxy", proxy.clone());
+        }
+        if let Some(ref username) = self.rrdp_proxy_username {
+            insert(&mut res, "rrdp-proxy-username", username.clone());
+        }
+        if let Some(ref secret) = self.rrdp_proxy_password {
+            insert(&mut res, "rrdp-proxy-password", secret.to_string());
         }
-        insert_int(
-            &mut res, "max-object-size",
-            self.max_object_size.unwrap_or(0),
-        );
-        insert_int(&mut res, "max-ca-depth", self.max_ca_depth);
-        insert(&mut res, "enable-bgpsec", self.enable_bgpsec);
-        #[cfg(feature = "aspa")]
-        insert(&mut res, "enable-aspa", self.enable_aspa);
-        insert(&mut res, "dirty", self.dirty_repository);
-        insert_int(&mut res, "validation-threads", self.validation_threads);
-        insert_int(&mut res, "refresh", self.refresh.as_secs());
-        insert_int(&mut res, "retry", self.retry.as_secs());
-        insert_int(&mut res, "expire", self.expire.as_secs());
-        insert_int(&mut res, "history-size", self.history_size);
-        insert(
-            &mut res, "rtr-listen",
-            toml::Value::Array(
-                self.rtr_listen.iter().map(|a| {
-                    toml::Value::from(a.to_string())
-                }).collect()
-            )
-        );
-        insert(
-            &mut res, "rtr-tls-listen",
-            toml::Value::Array(
-                self.rtr_tls_listen.iter().map(|a| {
-                    toml::Value::from(a.to_string())
-                }).collect()
-            )
-        );
         insert(
-            &mut res, "http-listen",
+            &mut res, "rrdp-no-proxy",
             toml::Value::Array(
-                self.http_listen.iter().map(|a| {
-                    toml::Value::from(a.to_string())
+                self.rrdp_no_proxy.iter().map(|s| {
+                    toml::Value::from(s.clone())
                 }).collect()
             )
         );
+        if self.rrdp_user_agent != DEFAULT_RRDP_USER_AGENT {
+            insert(&mut res, "rrdp-user-agent", self.rrdp_user_agent.clone());
+        }
         insert(
-            &mut res, "http-tls-listen",
+            &mut res, "rrdp-headers",
             toml::Value::Array(
-                self.http_tls_listen.iter().map(|a| {
-                    toml::Value::from(a.to_string())
+                self.rrdp_headers.iter().map(|s| {
+                    toml::Value::from(s.clone())
                 }).collect()
             )
         );
-        insert(&mut res, "systemd-listen", self.systemd_listen);
-        insert_int(
-            &mut res, "rtr-tcp-keepalive",
-            match self.rtr_tcp_keepalive {
-                Some(value) => value.as_secs(),
-                None => 0,
-            }
-        );
-        insert( &mut res, "rtr-client-metrics", self.rtr_client_metrics);
-        if let Some(ref path) = self.rtr_tls_key {
-            insert(&mut res, "rtr-tls-key", path.display().to_string());
+        if !self.rrdp_header_secrets.is_empty() {
+            insert(
+                &mut res, "rrdp-header-secrets",
+                toml::Value::Array(
+                    self.rrdp_header_secrets.iter().map(|(name, secret)| {
+                        toml::Value::Array([
+                            toml::Value::from(name.clone()),
+                            toml::Value::from(secret.to_string()),
+                        ].into_iter().collect())
+                    }).collect()
+                )
+            );
         }
-        if let Some(ref path) = self.rtr_tls_cert {
-            insert(&mut res, "rtr-tls-cert", path.display().to_string());
+        if let Some(path) = self.rrdp_keep_responses.as_ref() {
+            insert(
+                &mut res,"rrdp-keep-responses", format!("{}", path.display())
+            );
         }
-        if let Some(ref path) = self.http_tls_key {
-            insert(&mut res, "http-tls-key", path.display().to_string());
+        if let Some(path) = self.rrdp_local_dir.as_ref() {
+            insert(
+                &mut res, "rrdp-local-dir", format!("{}", path.display())
+            );
         }
-        if let Some(ref path) = self.http_tls_cert {
-            insert(&mut res, "http-tls-cert", path.display().to_string());
+        if let Some(path) = self.fetch_log.as_ref() {
+            insert(
+                &mut res, "fetch-log", format!("{}", path.display())
+            );
         }
-        insert(&mut res, "log-level", self.log_level.to_string());
-        match self.log_target {
-            #[cfg(unix)]
-            LogTarget::Default(facility) => {
-                insert(&mut res, "log", "default");
-                insert(
-                    &mut res, "syslog-facility", facility_to_string(facility)
-                );
-            }
-            #[cfg(unix)]
-            LogTarget::Syslog(facility) => {
-                insert(&mut res, "log", "syslog");
-                insert(
-                    &mut res, "syslog-facility", facility_to_string(facility)
+        if let Some(path) = self.rejection_report.as_ref() {
+            insert(
+                &mut res, "rejection-report", format!("{}", path.display())
+            );
+        }
+        if let Some(path) = self.mirror_dir.as_ref() {
+            insert(
+                &mut res, "mirror-dir", format!("{}", path.display())
+            );
+        }
+        if let Some(uri) = self.mirror_https_uri.as_ref() {
+            insert(&mut res, "mirror-https-uri", uri.to_string());
+        }
+        insert(&mut res, "crosscheck", self.crosscheck);
+        insert(&mut res, "roa-overlap-report", self.roa_overlap_report);
+        insert_int(
+            &mut res, "max-object-size",
+            self.max_object_size.unwrap_or(0),
+        );
+        insert_int(
+            &mut res, "max-repository-size",
+            self.max_repository_size.unwrap_or(0),
+        );
+        insert_int(
+            &mut res, "max-cache-size",
+            self.max_cache_size.unwrap_or(0),
+        );
+        insert_int(
+            &mut res, "rrdp-max-objects",
+            self.rrdp_max_objects.unwrap_or(0),
+        );
+        insert_int(
+            &mut res, "rrdp-max-decoded-size",
+            self.rrdp_max_decoded_size.unwrap_or(0),
+        );
+        if !self.rrdp_limit_overrides.is_empty() {
+            insert(
+                &mut res, "rrdp-limit-overrides",
+                toml::Value::Array(
+                    self.rrdp_limit_overrides.iter().map(|(left, right)| {
+                        toml::Value::Array([
+                            toml::Value::from(left.to_string()),
+                            toml::Value::from(right.to_string()),
+                        ].into_iter().collect())
+                    }).collect()
+                )
+            );
+        }
+        insert_int(&mut res, "max-ca-depth", self.max_ca_depth);
+        insert_int(
+            &mut res, "max-ca-children", self.max_ca_children.unwrap_or(0)
+        );
+        insert_int(
+            &mut res, "max-point-objects",
+            self.max_point_objects.unwrap_or(0)
+        );
+        if let Some(time) = self.max_ca_time {
+            insert_int(&mut res, "max-ca-time", time.as_secs());
+        }
+        insert_int(
+            &mut res, "signature-cache-size",
+            self.signature_cache_size.unwrap_or(0)
+        );
+        insert_int(
+            &mut res, "object-cache-size",
+            self.object_cache_size.unwrap_or(0)
+        );
+        insert(&mut res, "enable-bgpsec", self.enable_bgpsec);
+        insert(&mut res, "enable-aspa", self.enable_aspa);
+        insert(&mut res, "dirty", self.dirty_repository);
+        insert(&mut res, "store-readonly", self.store_readonly);
+        insert(&mut res, "store-shared", self.store_shared);
+        insert(&mut res, "freeze", self.freeze);
+        if let Some(max_age) = self.stored_max_age {
+            insert_int(&mut res, "stored-max-age", max_age.as_secs());
+        }
+        if let Some(stale_age) = self.stored_stale_age {
+            insert_int(&mut res, "stored-stale-age", stale_age.as_secs());
+        }
+        if let Some(interval) = self.checkpoint_interval {
+            insert_int(&mut res, "checkpoint-interval", interval.as_secs());
+        }
+        insert_int(&mut res, "validation-threads", self.validation_threads);
+        if !self.tal_shares.is_empty() {
+            insert(
+                &mut res, "tal-shares",
+                toml::Value::Array(
+                    self.tal_shares.iter().map(|(left, right)| {
+                        toml::Value::Array([
+                            toml::Value::from(left.clone()),
+                            toml::Value::from(format!("{}%", right)),
+                        ].into_iter().collect())
+                    }).collect()
+                )
+            );
+        }
+        if !self.tal_priorities.is_empty() {
+            insert(
+                &mut res, "tal-priorities",
+                toml::Value::Array(
+                    self.tal_priorities.iter().map(|(left, right)| {
+                        toml::Value::Array([
+                            toml::Value::from(left.clone()),
+                            toml::Value::from(right.to_string()),
+                        ].into_iter().collect())
+                    }).collect()
+                )
+            );
+        }
+        insert_int(&mut res, "refresh", self.refresh.as_secs());
+        if let Some(spread) = self.fetch_spread {
+            insert_int(&mut res, "fetch-spread", spread);
+        }
+        if let Some(heartbeat) = self.rrdp_heartbeat {
+            insert_int(&mut res, "rrdp-heartbeat", heartbeat.as_secs());
+        }
+        if let Some(probe) = self.repository_probe {
+            insert_int(&mut res, "repository-probe", probe.as_secs());
+        }
+        if let Some(interval) = self.min_serial_interval {
+            insert_int(&mut res, "min-serial-interval", interval.as_secs());
+        }
+        insert_int(&mut res, "retry", self.retry.as_secs());
+        insert_int(&mut res, "expire", self.expire.as_secs());
+        insert_int(&mut res, "history-size", self.history_size);
+        insert_int(&mut res, "snapshot-history", self.snapshot_history);
+        insert(
+            &mut res, "rtr-listen",
+            toml::Value::Array(
+                self.rtr_listen.iter().map(|a| {
+                    toml::Value::from(a.to_string())
+                }).collect()
+            )
+        );
+        insert(
+            &mut res, "rtr-tls-listen",
+            toml::Value::Array(
+                self.rtr_tls_listen.iter().map(|a| {
+                    toml::Value::from(a.to_string())
+                }).collect()
+            )
+        );
+        insert(
+            &mut res, "http-listen",
+            toml::Value::Array(
+                self.http_listen.iter().map(|a| {
+                    toml::Value::from(a.to_string())
+                }).collect()
+            )
+        );
+        insert(
+            &mut res, "http-tls-listen",
+            toml::Value::Array(
+                self.http_tls_listen.iter().map(|a| {
+                    toml::Value::from(a.to_string())
+                }).collect()
+            )
+        );
+        insert(&mut res, "systemd-listen", self.systemd_listen);
+        insert_int(
+            &mut res, "rtr-tcp-keepalive",
+            match self.rtr_tcp_keepalive {
+                Some(value) => value.as_secs(),
+                None => 0,
+            }
+        );
+        insert( &mut res, "rtr-client-metrics", self.rtr_client_metrics);
+        if let Some(ref addr) = self.rtr_upstream {
+            insert(&mut res, "rtr-upstream", addr.clone());
+        }
+        if let Some(ref path) = self.rtr_upstream_ca_bundle {
+            insert(
+                &mut res, "rtr-upstream-ca-bundle",
+                path.display().to_string()
+            );
+        }
+        if !self.rtr_upstream_pinned_certs.is_empty() {
+            insert(
+                &mut res, "rtr-upstream-pinned-sha256",
+                self.rtr_upstream_pinned_certs.iter().map(|pin| {
+                    pin.to_string()
+                }).collect::<toml::Array>()
+            );
+        }
+        if let Some(ref secret) = self.rtr_tls_key {
+            insert(&mut res, "rtr-tls-key", secret.to_string());
+        }
+        if let Some(ref path) = self.rtr_tls_cert {
+            insert(&mut res, "rtr-tls-cert", path.display().to_string());
+        }
+        if let Some(ref secret) = self.http_tls_key {
+            insert(&mut res, "http-tls-key", secret.to_string());
+        }
+        if let Some(ref path) = self.http_tls_cert {
+            insert(&mut res, "http-tls-cert", path.display().to_string());
+        }
+        if let Some(ref secret) = self.http_refresh_token {
+            insert(&mut res, "http-refresh-token", secret.to_string());
+        }
+        if let Some(ref secret) = self.http_tal_token {
+            insert(&mut res, "http-tal-token", secret.to_string());
+        }
+        if let Some(path) = self.http_audit_log.as_ref() {
+            insert(
+                &mut res, "http-audit-log", format!("{}", path.display())
+            );
+        }
+        insert(&mut res, "log-level", self.log_level.to_string());
+        match self.log_target {
+            #[cfg(unix)]
+            LogTarget::Default(facility) => {
+                insert(&mut res, "log", "default");
+                insert(
+                    &mut res, "syslog-facility", facility_to_string(facility)
+                );
+            }
+            #[cfg(unix)]
+            LogTarget::Syslog(facility) => {
+                insert(&mut res, "log", "syslog");
+                insert(
+                    &mut res, "syslog-facility", facility_to_string(facility)
                 );
             }
             LogTarget::Stderr => {
@@ -1513,6 +3266,71 @@ impl Config {
         if let Some(ref group) = self.group {
             insert(&mut res, "group", group.clone());
         }
+        if let Some(backoff) = self.repository_backoff {
+            insert_int(&mut res, "repository-backoff", backoff.as_secs());
+        }
+        insert_int(
+            &mut res, "repository-backoff-max",
+            self.repository_backoff_max.as_secs()
+        );
+        insert_int(
+            &mut res, "dead-repository-age",
+            self.dead_repository_age.as_secs()
+        );
+        if !self.transport_overrides.is_empty() {
+            insert(
+                &mut res, "transport-overrides",
+                toml::Value::Array(
+                    self.transport_overrides.iter().map(|(left, right)| {
+                        toml::Value::Array([
+                            toml::Value::from(left.clone()),
+                            toml::Value::from(right.to_string()),
+                        ].into_iter().collect())
+                    }).collect()
+                )
+            );
+        }
+        if !self.rrdp_delta_overrides.is_empty() {
+            insert(
+                &mut res, "rrdp-delta-overrides",
+                toml::Value::Array(
+                    self.rrdp_delta_overrides.iter().map(|(left, right)| {
+                        toml::Value::Array([
+                            toml::Value::from(left.to_string()),
+                            toml::Value::from(right.to_string()),
+                        ].into_iter().collect())
+                    }).collect()
+                )
+            );
+        }
+        if !self.rrdp_bandwidth_overrides.is_empty() {
+            insert(
+                &mut res, "rrdp-bandwidth-overrides",
+                toml::Value::Array(
+                    self.rrdp_bandwidth_overrides.iter().map(
+                        |(left, right)| {
+                            toml::Value::Array([
+                                toml::Value::from(left.to_string()),
+                                toml::Value::from(right.to_string()),
+                            ].into_iter().collect())
+                        }
+                    ).collect()
+                )
+            );
+        }
+        if !self.uri_rewrites.is_empty() {
+            insert(
+                &mut res, "uri-rewrites",
+                toml::Value::Array(
+                    self.uri_rewrites.iter().map(|(left, right)| {
+                        toml::Value::Array([
+                            toml::Value::from(left.clone()),
+                            toml::Value::from(right.clone()),
+                        ].into_iter().collect())
+                    }).collect()
+                )
+            );
+        }
         if !self.tal_labels.is_empty() {
             insert(
                 &mut res, "tal-labels",
@@ -1526,6 +3344,26 @@ impl Config {
                 )
             );
         }
+        if !self.rrdp_overrides.is_empty() {
+            insert(
+                &mut res, "rrdp-overrides",
+                toml::Value::InlineTable(
+                    self.rrdp_overrides.iter().map(|(key, value)| {
+                        (key.clone(), toml::Value::InlineTable(value.to_toml()))
+                    }).collect()
+                )
+            );
+        }
+        if !self.rrdp_tls_overrides.is_empty() {
+            insert(
+                &mut res, "rrdp-tls-overrides",
+                toml::Value::InlineTable(
+                    self.rrdp_tls_overrides.iter().map(|(key, value)| {
+                        (key.clone(), toml::Value::InlineTable(value.to_toml()))
+                    }).collect()
+                )
+            );
+        }
         res
     }
 }
@@ -1562,6 +3400,109 @@ impl fmt::Display for Config {
 }
 
 
+//------------ ConfigProfile -------------------------------------------------
+
+/// A named set of defaults for a deployment size.
+///
+/// The profile provides the default values for a number of options that
+/// are typically tuned together, such as the number of validation
+/// threads, timeouts, the history size, and the RRDP delta policy. Values
+/// explicitly given in the config file or on the command line always take
+/// precedence.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ConfigProfile {
+    /// A small installation, such as a single router or a test system.
+    Small,
+
+    /// The default profile.
+    #[default]
+    Default,
+
+    /// An installation serving a large number of routers.
+    LargeFleet,
+}
+
+impl ConfigProfile {
+    /// Returns the default number of validation threads.
+    pub fn validation_threads(self) -> usize {
+        let available = Config::default_validation_threads();
+        match self {
+            ConfigProfile::Small => available.min(2),
+            ConfigProfile::Default => available,
+            ConfigProfile::LargeFleet => available,
+        }
+    }
+
+    /// Returns the default rsync timeout.
+    pub fn rsync_timeout(self) -> Duration {
+        match self {
+            ConfigProfile::Small => Duration::from_secs(600),
+            ConfigProfile::Default => DEFAULT_RSYNC_TIMEOUT,
+            ConfigProfile::LargeFleet => Duration::from_secs(120),
+        }
+    }
+
+    /// Returns the default RRDP timeout.
+    pub fn rrdp_timeout(self) -> Duration {
+        match self {
+            ConfigProfile::Small => Duration::from_secs(600),
+            ConfigProfile::Default => DEFAULT_RRDP_TIMEOUT,
+            ConfigProfile::LargeFleet => Duration::from_secs(120),
+        }
+    }
+
+    /// Returns the default RRDP fallback time.
+    pub fn rrdp_fallback_time(self) -> Duration {
+        match self {
+            ConfigProfile::Small => Duration::from_secs(7200),
+            ConfigProfile::Default => DEFAULT_RRDP_FALLBACK_TIME,
+            ConfigProfile::LargeFleet => Duration::from_secs(1800),
+        }
+    }
+
+    /// Returns the default maximum number of RRDP deltas.
+    pub fn rrdp_max_delta_count(self) -> usize {
+        match self {
+            ConfigProfile::Small => 50,
+            ConfigProfile::Default => DEFAULT_RRDP_MAX_DELTA_COUNT,
+            ConfigProfile::LargeFleet => 500,
+        }
+    }
+
+    /// Returns the default number of VRP diffs to keep.
+    pub fn history_size(self) -> usize {
+        match self {
+            ConfigProfile::Small => 5,
+            ConfigProfile::Default => DEFAULT_HISTORY_SIZE,
+            ConfigProfile::LargeFleet => 100,
+        }
+    }
+}
+
+impl FromStr for ConfigProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "small" => Ok(ConfigProfile::Small),
+            "default" => Ok(ConfigProfile::Default),
+            "large-fleet" => Ok(ConfigProfile::LargeFleet),
+            _ => Err(format!("invalid profile '{}'", s))
+        }
+    }
+}
+
+impl fmt::Display for ConfigProfile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            ConfigProfile::Small => "small",
+            ConfigProfile::Default => "default",
+            ConfigProfile::LargeFleet => "large-fleet",
+        })
+    }
+}
+
+
 //------------ LogTarget -----------------------------------------------------
 
 /// The target to log to.
@@ -1682,6 +3623,49 @@ impl fmt::Display for FilterPolicy {
 }
 
 
+//------------ ResourceValidation --------------------------------------------
+
+/// The algorithm for validating the resources of certificates.
+///
+/// The algorithms only differ for certificates which claim resources their
+/// issuer doesn’t have.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ResourceValidation {
+    /// Reject certificates that claim more resources than their issuer.
+    ///
+    /// This is the original algorithm of RFC 6487.
+    Strict,
+
+    /// Drop resources the issuer doesn’t have.
+    ///
+    /// This is the validation reconsidered algorithm of RFC 8360. It is
+    /// only applied to certificates using the certificate policy of RFC
+    /// 8360. All other certificates are still rejected.
+    Reconsidered,
+}
+
+impl FromStr for ResourceValidation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "strict" => Ok(ResourceValidation::Strict),
+            "reconsidered" => Ok(ResourceValidation::Reconsidered),
+            _ => Err(format!("invalid resource validation '{}'", s))
+        }
+    }
+}
+
+impl fmt::Display for ResourceValidation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            ResourceValidation::Strict => "strict",
+            ResourceValidation::Reconsidered => "reconsidered",
+        })
+    }
+}
+
+
 //------------ FallbackPolicy ------------------------------------------------
 
 /// The policy for fallback to rsync.
@@ -1729,15 +3713,501 @@ impl fmt::Display for FallbackPolicy {
 }
 
 
-//------------ GlobalArgs ----------------------------------------------------
+//------------ TransportPolicy -----------------------------------------------
 
-/// The global command line arguments.
+/// The policy for choosing the transport of a repository.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TransportPolicy {
+    /// Only ever use RRDP.
+    Rrdp,
+
+    /// Only ever use rsync, even if RRDP is available.
+    Rsync,
+
+    /// Try RRDP first and fall back to rsync per the fallback policy.
+    ///
+    /// This is the default behaviour.
+    RrdpFirst,
+
+    /// Try rsync first and only use RRDP if the rsync update fails.
+    RsyncFirst,
+}
+
+impl FromStr for TransportPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rrdp" => Ok(TransportPolicy::Rrdp),
+            "rsync" => Ok(TransportPolicy::Rsync),
+            "rrdp-first" => Ok(TransportPolicy::RrdpFirst),
+            "rsync-first" => Ok(TransportPolicy::RsyncFirst),
+            _ => Err(format!("invalid policy '{}'", s))
+        }
+    }
+}
+
+impl fmt::Display for TransportPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            TransportPolicy::Rrdp => "rrdp",
+            TransportPolicy::Rsync => "rsync",
+            TransportPolicy::RrdpFirst => "rrdp-first",
+            TransportPolicy::RsyncFirst => "rsync-first",
+        })
+    }
+}
+
+
+//------------ DeltaOverflowPolicy -------------------------------------------
+
+/// The policy for RRDP updates needing more deltas than allowed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DeltaOverflowPolicy {
+    /// Update from the snapshot instead.
+    Snapshot,
+
+    /// Apply only the allowed number of deltas.
+    ///
+    /// The remaining deltas are skipped and applied during later updates.
+    Partial,
+}
+
+impl FromStr for DeltaOverflowPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "snapshot" => Ok(DeltaOverflowPolicy::Snapshot),
+            "partial" => Ok(DeltaOverflowPolicy::Partial),
+            _ => Err(format!("invalid policy '{}'", s))
+        }
+    }
+}
+
+impl fmt::Display for DeltaOverflowPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            DeltaOverflowPolicy::Snapshot => "snapshot",
+            DeltaOverflowPolicy::Partial => "partial",
+        })
+    }
+}
+
+
+//------------ RrdpDeltaOverride ---------------------------------------------
+
+/// The delta policy for a specific RRDP repository.
+///
+/// In text form, this is the maximum number of deltas optionally followed
+/// by a colon and the overflow policy, e.g., `500:partial`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RrdpDeltaOverride {
+    /// The maximum number of deltas to apply.
+    pub max_delta_count: usize,
+
+    /// What to do if there are more deltas.
+    ///
+    /// If this is `None`, the global policy is used.
+    pub overflow: Option<DeltaOverflowPolicy>,
+}
+
+impl FromStr for RrdpDeltaOverride {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (count, overflow) = match s.split_once(':') {
+            Some((count, overflow)) => {
+                (count, Some(DeltaOverflowPolicy::from_str(overflow)?))
+            }
+            None => (s, None)
+        };
+        let max_delta_count = usize::from_str(count).map_err(|_| {
+            format!("invalid delta count '{}'", count)
+        })?;
+        Ok(RrdpDeltaOverride { max_delta_count, overflow })
+    }
+}
+
+impl fmt::Display for RrdpDeltaOverride {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.overflow {
+            Some(overflow) => {
+                write!(f, "{}:{}", self.max_delta_count, overflow)
+            }
+            None => write!(f, "{}", self.max_delta_count)
+        }
+    }
+}
+
+
+//------------ RrdpLimits ----------------------------------------------------
+
+/// Content limits for a specific RRDP repository.
+///
+/// Each value that is `None` falls back to the global setting. In text
+/// form, this is a comma-separated list of limits, each given as the
+/// limit’s name and its value separated by a colon. The names are
+/// `object-size`, `repository-size`, `objects`, and `decoded-size`. A value
+/// of 0 disables the limit for the repository, e.g.,
+/// `objects:500000,decoded-size:0`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RrdpLimits {
+    /// The maximum size of a single object.
+    pub max_object_size: Option<u64>,
+
+    /// The maximum on-disk size of the repository.
+    pub max_repository_size: Option<u64>,
+
+    /// The maximum number of objects in a snapshot or delta file.
+    pub max_objects: Option<u64>,
+
+    /// The maximum decoded size of all objects in a snapshot or delta file.
+    pub max_decoded_size: Option<u64>,
+}
+
+impl FromStr for RrdpLimits {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut res = RrdpLimits::default();
+        for item in s.split(',') {
+            let (name, value) = item.split_once(':').ok_or_else(|| {
+                format!("expected LIMIT:VALUE in '{}'", item)
+            })?;
+            let value = u64::from_str(value.trim()).map_err(|_| {
+                format!("invalid value in '{}'", item)
+            })?;
+            let target = match name.trim() {
+                "object-size" => &mut res.max_object_size,
+                "repository-size" => &mut res.max_repository_size,
+                "objects" => &mut res.max_objects,
+                "decoded-size" => &mut res.max_decoded_size,
+                _ => return Err(format!("unknown limit '{}'", name.trim()))
+            };
+            *target = Some(value);
+        }
+        Ok(res)
+    }
+}
+
+impl fmt::Display for RrdpLimits {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut first = true;
+        for (name, value) in [
+            ("object-size", self.max_object_size),
+            ("repository-size", self.max_repository_size),
+            ("objects", self.max_objects),
+            ("decoded-size", self.max_decoded_size),
+        ] {
+            if let Some(value) = value {
+                if !first {
+                    f.write_str(",")?;
+                }
+                write!(f, "{}:{}", name, value)?;
+                first = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+
+//------------ TalPolicy ----------------------------------------------------
+
+/// Manifest handling and resource validation policies for a specific TAL.
+///
+/// Each value that is `None` falls back to the global setting.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TalPolicy {
+    /// The policy for stale manifests and CRLs.
+    pub stale: Option<FilterPolicy>,
+
+    /// The policy for files missing from an updated publication point.
+    pub missing_files: Option<FilterPolicy>,
+
+    /// The policy for files not matching their manifest hash.
+    pub hash_mismatch: Option<FilterPolicy>,
+
+    /// The algorithm for validating resources.
+    pub resource_validation: Option<ResourceValidation>,
+}
+
+impl TalPolicy {
+    /// Returns an inline TOML table representing the policy.
+    fn to_toml(self) -> toml::InlineTable {
+        let mut res = toml::InlineTable::new();
+        if let Some(value) = self.stale {
+            res.insert("stale", toml::Value::from(value.to_string()));
+        }
+        if let Some(value) = self.missing_files {
+            res.insert("missing-files", toml::Value::from(value.to_string()));
+        }
+        if let Some(value) = self.hash_mismatch {
+            res.insert("hash-mismatch", toml::Value::from(value.to_string()));
+        }
+        if let Some(value) = self.resource_validation {
+            res.insert(
+                "resource-validation", toml::Value::from(value.to_string())
+            );
+        }
+        res
+    }
+}
+
+
+//------------ RoaPolicyRule -------------------------------------------------
+
+/// A local policy rule for route origins of valid ROAs.
+///
+/// A route origin matches the rule if it matches all the conditions given.
+/// A rule without any conditions doesn’t match anything.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RoaPolicyRule {
+    /// Match if max length exceeds the prefix length by more bits.
+    pub max_length_excess: Option<u8>,
+
+    /// Match if the prefix covers or is covered by any of these prefixes.
+    pub prefixes: Vec<Prefix>,
+
+    /// Match if the origin AS is any of these.
+    pub asns: Vec<Asn>,
+
+    /// What to do with matching route origins.
+    ///
+    /// With *reject*, matching route origins are dropped, with *warn* they
+    /// are kept but logged.
+    pub action: FilterPolicy,
+}
+
+impl RoaPolicyRule {
+    /// Returns an inline TOML table representing the rule.
+    fn to_toml(&self) -> toml::InlineTable {
+        let mut res = toml::InlineTable::new();
+        if let Some(value) = self.max_length_excess {
+            res.insert(
+                "max-length-excess", toml::Value::from(i64::from(value))
+            );
+        }
+        if !self.prefixes.is_empty() {
+            res.insert(
+                "prefixes",
+                toml::Value::Array(
+                    self.prefixes.iter().map(|prefix| {
+                        format!("{}/{}", prefix.addr(), prefix.len())
+                    }).collect()
+                )
+            );
+        }
+        if !self.asns.is_empty() {
+            res.insert(
+                "asns",
+                toml::Value::Array(
+                    self.asns.iter().map(ToString::to_string).collect()
+                )
+            );
+        }
+        res.insert("action", toml::Value::from(self.action.to_string()));
+        res
+    }
+}
+
+
+//------------ RrdpOverride --------------------------------------------------
+
+/// Timeout and retry settings for a specific RRDP repository.
+///
+/// Each value that is `None` falls back to the global setting.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RrdpOverride {
+    /// The connect timeout.
+    pub connect_timeout: Option<Duration>,
+
+    /// The timeout for each request.
+    pub timeout: Option<Duration>,
+
+    /// The number of times a failed request is retried.
+    pub retries: Option<usize>,
+
+    /// The time to wait before retrying a failed request.
+    pub retry_backoff: Option<Duration>,
+}
+
+impl RrdpOverride {
+    /// Returns an inline TOML table representing the override.
+    fn to_toml(self) -> toml::InlineTable {
+        let mut res = toml::InlineTable::new();
+        if let Some(value) = self.connect_timeout {
+            res.insert(
+                "connect-timeout", toml::Value::from(value.as_secs() as i64)
+            );
+        }
+        if let Some(value) = self.timeout {
+            res.insert("timeout", toml::Value::from(value.as_secs() as i64));
+        }
+        if let Some(value) = self.retries {
+            res.insert("retries", toml::Value::from(value as i64));
+        }
+        if let Some(value) = self.retry_backoff {
+            res.insert(
+                "retry-backoff", toml::Value::from(value.as_secs() as i64)
+            );
+        }
+        res
+    }
+}
+
+
+//------------ RsyncOverride -------------------------------------------------
+
+/// Settings for a specific rsync module.
+///
+/// Each value that is `None` falls back to the global setting.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RsyncOverride {
+    /// The timeout for the rsync process.
+    ///
+    /// A zero duration means no timeout at all.
+    pub timeout: Option<Duration>,
+
+    /// The timeout for connecting to the rsync server.
+    ///
+    /// This is passed to rsync via its `--contimeout` option.
+    pub connect_timeout: Option<Duration>,
+
+    /// The bandwidth limit in bytes per second.
+    ///
+    /// A value of zero means no limit.
+    pub bandwidth_limit: Option<u64>,
+
+    /// Additional arguments passed to rsync.
+    pub extra_args: Vec<String>,
+
+    /// Mirrors of the module.
+    ///
+    /// If updating the module from its own URI fails, these module URIs
+    /// are tried in order until one succeeds.
+    pub mirrors: Vec<String>,
+
+    /// The location to fetch the module from via SSH.
+    ///
+    /// If this is present, the module isn’t fetched from an rsync daemon
+    /// but rsync is run over SSH. Mirrors are still fetched from a daemon.
+    pub ssh: Option<SshUri>,
+
+    /// The SSH identity file to use when fetching via SSH.
+    pub ssh_identity_file: Option<PathBuf>,
+
+    /// The SSH known hosts file to use when fetching via SSH.
+    pub ssh_known_hosts: Option<PathBuf>,
+}
+
+impl RsyncOverride {
+    /// Returns an inline TOML table representing the override.
+    fn to_toml(&self) -> toml::InlineTable {
+        let mut res = toml::InlineTable::new();
+        if let Some(value) = self.timeout {
+            res.insert("timeout", toml::Value::from(value.as_secs() as i64));
+        }
+        if let Some(value) = self.connect_timeout {
+            res.insert(
+                "connect-timeout", toml::Value::from(value.as_secs() as i64)
+            );
+        }
+        if let Some(value) = self.bandwidth_limit {
+            res.insert("bandwidth-limit", toml::Value::from(value as i64));
+        }
+        if !self.extra_args.is_empty() {
+            res.insert(
+                "extra-args",
+                toml::Value::Array(
+                    self.extra_args.iter().map(|item| {
+                        toml::Value::from(item.clone())
+                    }).collect()
+                )
+            );
+        }
+        if !self.mirrors.is_empty() {
+            res.insert(
+                "mirrors",
+                toml::Value::Array(
+                    self.mirrors.iter().map(|item| {
+                        toml::Value::from(item.clone())
+                    }).collect()
+                )
+            );
+        }
+        if let Some(value) = self.ssh.as_ref() {
+            res.insert("ssh", toml::Value::from(value.to_string()));
+        }
+        if let Some(value) = self.ssh_identity_file.as_ref() {
+            res.insert(
+                "ssh-identity-file",
+                toml::Value::from(value.display().to_string())
+            );
+        }
+        if let Some(value) = self.ssh_known_hosts.as_ref() {
+            res.insert(
+                "ssh-known-hosts",
+                toml::Value::from(value.display().to_string())
+            );
+        }
+        res
+    }
+}
+
+
+//------------ RrdpTlsOverride -----------------------------------------------
+
+/// TLS trust settings for a specific RRDP host.
+///
+/// At least one of the two fields must be present.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RrdpTlsOverride {
+    /// A PEM file with the CA certificates to trust for the host.
+    pub ca_bundle: Option<PathBuf>,
+
+    /// The acceptable SHA-256 fingerprints of the server certificate.
+    pub pinned_certs: Vec<CertFingerprint>,
+}
+
+impl RrdpTlsOverride {
+    /// Returns an inline TOML table representing the override.
+    fn to_toml(&self) -> toml::InlineTable {
+        let mut res = toml::InlineTable::new();
+        if let Some(path) = self.ca_bundle.as_ref() {
+            res.insert(
+                "ca-bundle", toml::Value::from(format!("{}", path.display()))
+            );
+        }
+        if !self.pinned_certs.is_empty() {
+            res.insert(
+                "pinned-sha256",
+                toml::Value::Array(
+                    self.pinned_certs.iter().map(|item| {
+                        toml::Value::from(item.to_string())
+                    }).collect()
+                )
+            );
+        }
+        res
+    }
+}
+
+
+//------------ GlobalArgs ----------------------------------------------------
+
+/// The global command line arguments.
 #[derive(Clone, Debug, Parser)]
 struct GlobalArgs {
     /// Read base configuration from this file
     #[arg(short, long, value_name="PATH")]
     config: Option<PathBuf>,
 
+    /// Use defaults for a deployment size (small, default, large-fleet)
+    #[arg(long, value_name="PROFILE")]
+    profile: Option<ConfigProfile>,
+
     /// Sets the repository cache directory
     #[arg(short, long, value_name="PATH")]
     repository_dir: Option<PathBuf>,
@@ -1766,6 +4236,30 @@ struct GlobalArgs {
     #[arg(long, value_name = "POLICY")]
     stale: Option<FilterPolicy>,
 
+    /// Accept objects that became stale less than this long ago
+    #[arg(long, value_name = "SECONDS")]
+    stale_grace: Option<u64>,
+
+    /// Accept objects that become valid less than this far in the future
+    #[arg(long, value_name = "SECONDS")]
+    clock_skew: Option<u64>,
+
+    /// Validate as if it was the given time (RFC 3339)
+    #[arg(long, value_name = "TIME")]
+    validation_time: Option<DateTime<Utc>>,
+
+    /// The policy for files missing from an updated publication point
+    #[arg(long, value_name = "POLICY")]
+    missing_files: Option<FilterPolicy>,
+
+    /// The policy for files not matching their manifest hash
+    #[arg(long, value_name = "POLICY")]
+    hash_mismatch: Option<FilterPolicy>,
+
+    /// The algorithm for validating resources of RFC 8360 certificates
+    #[arg(long, value_name = "ALGORITHM")]
+    resource_validation: Option<ResourceValidation>,
+
     /// The policy for handling unsafe VRPs
     #[arg(long, value_name = "POLICY")]
     unsafe_vrps: Option<FilterPolicy>,
@@ -1794,6 +4288,10 @@ struct GlobalArgs {
     #[arg(long)]
     allow_dubious_hosts: bool,
 
+    /// Maximum number of concurrent updates per server (0 for no limit)
+    #[arg(long, value_name = "COUNT")]
+    max_host_connections: Option<usize>,
+
     /// Delete cached data, download everything again
     #[arg(long)]
     fresh: bool,
@@ -1806,10 +4304,26 @@ struct GlobalArgs {
     #[arg(long, value_name="COMMAND")]
     rsync_command: Option<String>,
 
+    /// File name pattern of files rsync should transfer
+    #[arg(long, value_name = "PATTERN")]
+    rsync_include: Option<Vec<String>>,
+
+    /// File name pattern of files rsync should not transfer
+    #[arg(long, value_name = "PATTERN")]
+    rsync_exclude: Option<Vec<String>>,
+
     /// Timeout for rsync commands (0 for none)
     #[arg(long, value_name = "SECONDS")]
     rsync_timeout: Option<u64>,
 
+    /// Limit rsync to this many bytes per second (0 for no limit)
+    #[arg(long, value_name = "BYTES")]
+    rsync_bandwidth_limit: Option<u64>,
+
+    /// Maximum number of parallel rsync processes (0 for no limit)
+    #[arg(long, value_name = "COUNT")]
+    rsync_max_parallel: Option<usize>,
+
     /// Disable RRDP and only use rsync
     #[arg(long)]
     disable_rrdp: bool,
@@ -1818,10 +4332,34 @@ struct GlobalArgs {
     #[arg(long, value_name = "COUNT")]
     rrdp_max_delta_count: Option<usize>,
 
+    /// What to do if there are too many RRDP deltas
+    #[arg(long, value_name = "POLICY")]
+    rrdp_delta_overflow: Option<DeltaOverflowPolicy>,
+
+    /// Override the RRDP delta policy for a repository
+    #[arg(long, value_name = "URI=COUNT[:POLICY]")]
+    rrdp_delta_override: Option<Vec<String>>,
+
     /// Maximum allowed length of the delta list in a RRDP notification file.
     #[arg(long, value_name = "LEN")]
     rrdp_max_delta_list_len: Option<usize>,
 
+    /// Download RRDP snapshots in chunks of this size (0 for off)
+    #[arg(long, value_name = "BYTES")]
+    rrdp_snapshot_chunk_size: Option<u64>,
+
+    /// Number of parallel requests for chunked snapshot downloads
+    #[arg(long, value_name = "COUNT")]
+    rrdp_snapshot_parallelism: Option<usize>,
+
+    /// Limit each RRDP repository to this many bytes per second
+    #[arg(long, value_name = "BYTES")]
+    rrdp_bandwidth_limit: Option<u64>,
+
+    /// Override the RRDP bandwidth limit for a repository
+    #[arg(long, value_name = "URI=BYTES")]
+    rrdp_bandwidth_override: Option<Vec<String>>,
+
     /// When to fall back to rsync if RRDP fails
     #[arg(long, value_name = "POLICY")]
     rrdp_fallback: Option<FallbackPolicy>,
@@ -1830,6 +4368,22 @@ struct GlobalArgs {
     #[arg(long, value_name = "SECONDS")]
     rrdp_fallback_time: Option<u64>,
 
+    /// Override the transport for a repository or host
+    #[arg(long, value_name = "URI=POLICY")]
+    transport_override: Option<Vec<String>>,
+
+    /// Initial delay before retrying a failing repository (0 for none)
+    #[arg(long, value_name = "SECONDS")]
+    repository_backoff: Option<u64>,
+
+    /// Maximum delay before retrying a failing repository
+    #[arg(long, value_name = "SECONDS")]
+    repository_backoff_max: Option<u64>,
+
+    /// Time a repository needs to keep failing to be considered dead
+    #[arg(long, value_name = "SECONDS")]
+    dead_repository_age: Option<u64>,
+
     /// Timeout of network operation for RRDP (0 for none)
     #[arg(long, value_name = "SECONDS")]
     rrdp_timeout: Option<u64>,
@@ -1838,13 +4392,29 @@ struct GlobalArgs {
     #[arg(long, value_name = "SECONDS")]
     rrdp_connect_timeout: Option<u64>,
 
-    /// TCP keepalive duration for RRDP connections (0 for none)
-    #[arg(value_name = "SECONDS")]
-    rrdp_tcp_keepalive: Option<u64>,
+    /// Number of times to retry failed RRDP requests
+    #[arg(long, value_name = "COUNT")]
+    rrdp_retries: Option<usize>,
 
-    /// Local address for outgoing RRDP connections
-    #[arg(long, value_name = "ADDR")]
-    rrdp_local_addr: Option<IpAddr>,
+    /// Time to wait before retrying a failed RRDP request
+    #[arg(long, value_name = "SECONDS")]
+    rrdp_retry_backoff: Option<u64>,
+
+    /// Skip RRDP repositories found unchanged via a HEAD request
+    #[arg(long)]
+    rrdp_head_probe: bool,
+
+    /// Don’t request compressed RRDP responses
+    #[arg(long)]
+    rrdp_disable_compression: bool,
+
+    /// TCP keepalive duration for RRDP connections (0 for none)
+    #[arg(value_name = "SECONDS")]
+    rrdp_tcp_keepalive: Option<u64>,
+
+    /// Local address for outgoing RRDP connections
+    #[arg(long, value_name = "ADDR")]
+    rrdp_local_addr: Option<IpAddr>,
 
     /// Path to trusted PEM certificate for RRDP HTTPS
     #[arg(long, value_name = "PATH")]
@@ -1854,24 +4424,123 @@ struct GlobalArgs {
     #[arg(long, value_name = "URI")]
     rrdp_proxy: Option<Vec<String>>,
 
+    /// Proxy server for plain HTTP RRDP requests only
+    #[arg(long, value_name = "URI")]
+    rrdp_http_proxy: Option<String>,
+
+    /// Proxy server for HTTPS RRDP requests only
+    #[arg(long, value_name = "URI")]
+    rrdp_https_proxy: Option<String>,
+
+    /// User name for authenticating with the RRDP proxy
+    #[arg(long, value_name = "NAME")]
+    rrdp_proxy_username: Option<String>,
+
+    /// Host, address, or network to access without RRDP proxy
+    #[arg(long, value_name = "HOST")]
+    rrdp_no_proxy: Option<Vec<String>>,
+
+    /// User-Agent header to use for RRDP requests
+    #[arg(long, value_name = "AGENT")]
+    rrdp_user_agent: Option<String>,
+
+    /// Additional HTTP header to send with RRDP requests
+    #[arg(long, value_name = "NAME:VALUE")]
+    rrdp_header: Option<Vec<String>>,
+
+    /// Additional HTTP header with a secret value for RRDP requests
+    #[arg(long, value_name = "NAME=SECRET")]
+    rrdp_header_secret: Option<Vec<String>>,
+
     /// Keep RRDP responses in the given directory
     #[arg(long, value_name = "PATH")]
     rrdp_keep_responses: Option<PathBuf>,
 
+    /// Read RRDP data from the given directory (for testing only)
+    #[arg(long, value_name = "PATH")]
+    rrdp_local_dir: Option<PathBuf>,
+
+    /// Append a JSON line for every fetch attempt to the given file
+    #[arg(long, value_name = "PATH")]
+    fetch_log: Option<PathBuf>,
+
+    /// Write a report of the objects rejected by each run to the given file
+    #[arg(long, value_name = "PATH")]
+    rejection_report: Option<PathBuf>,
+
+    /// Re-publish the validated data as a local mirror in the given directory
+    #[arg(long, value_name = "PATH")]
+    mirror_dir: Option<PathBuf>,
+
+    /// URI under which the mirror's https directory is served
+    #[arg(long, value_name = "URI")]
+    mirror_https_uri: Option<uri::Https>,
+
+    /// Compare collector and store after each validation run
+    #[arg(long)]
+    crosscheck: bool,
+
+    /// List duplicate and shadowed route origins individually
+    #[arg(long)]
+    roa_overlap_report: bool,
+
+    /// Rewrite URIs starting with FROM to start with TO instead
+    #[arg(long, value_name = "FROM=TO")]
+    uri_rewrite: Option<Vec<String>>,
+
     /// Maximum size of downloaded objects (0 for no limit)
     #[arg(long, value_name = "BYTES")]
     max_object_size: Option<u64>,
 
+    /// Maximum on-disk size of a repository (0 for no limit)
+    #[arg(long, value_name = "BYTES")]
+    max_repository_size: Option<u64>,
+
+    /// Maximum on-disk size of all collected data (0 for no limit)
+    #[arg(long, value_name = "BYTES")]
+    max_cache_size: Option<u64>,
+
+    /// Maximum number of objects in an RRDP file (0 for no limit)
+    #[arg(long, value_name = "COUNT")]
+    rrdp_max_objects: Option<usize>,
+
+    /// Maximum decoded size of all objects in an RRDP file (0 for no limit)
+    #[arg(long, value_name = "BYTES")]
+    rrdp_max_decoded_size: Option<u64>,
+
+    /// Override the RRDP content limits for a repository
+    #[arg(long, value_name = "URI=LIMIT:VALUE[,...]")]
+    rrdp_limit_override: Option<Vec<String>>,
+
     /// Maximum distance of a CA from a trust anchor
     #[arg(long, value_name = "COUNT")]
     max_ca_depth: Option<usize>,
 
+    /// Maximum number of child CAs of a single CA (0 for none)
+    #[arg(long, value_name = "COUNT")]
+    max_ca_children: Option<usize>,
+
+    /// Maximum number of objects of a publication point (0 for none)
+    #[arg(long, value_name = "COUNT")]
+    max_point_objects: Option<usize>,
+
+    /// Maximum time for processing the CAs below a CA (0 for none)
+    #[arg(long, value_name = "SECONDS")]
+    max_ca_time: Option<u64>,
+
+    /// Maximum number of cached signature verifications (0 for none)
+    #[arg(long, value_name = "COUNT")]
+    signature_cache_size: Option<usize>,
+
+    /// Maximum number of cached decoded objects (0 for none)
+    #[arg(long, value_name = "COUNT")]
+    object_cache_size: Option<usize>,
+
     /// Include BGPsec router keys in the data set
     #[arg(long)]
     enable_bgpsec: bool,
 
     /// Include ASPA in the data set
-    #[cfg(feature = "aspa")]
     #[arg(long)]
     enable_aspa: bool,
 
@@ -1879,10 +4548,42 @@ struct GlobalArgs {
     #[arg(long)]
     dirty_repository: bool,
 
+    /// Validate using only stored data and never modify the store
+    #[arg(long)]
+    store_readonly: bool,
+
+    /// Share the store with other Routinator processes
+    #[arg(long)]
+    store_shared: bool,
+
+    /// Validate only stored data and record a hash of it
+    #[arg(long)]
+    freeze: bool,
+
+    /// Maximum age of unconfirmed stored data (0 for none)
+    #[arg(long, value_name = "SECONDS")]
+    stored_max_age: Option<u64>,
+
+    /// Age after which unconfirmed stored data is stale (0 for none)
+    #[arg(long, value_name = "SECONDS")]
+    stored_stale_age: Option<u64>,
+
+    /// Interval for saving the progress of a run (0 for none)
+    #[arg(long, value_name = "SECONDS")]
+    checkpoint_interval: Option<u64>,
+
     /// Number of threads for validation
     #[arg(long, value_name = "COUNT")]
     validation_threads: Option<usize>,
 
+    /// Reserve a percentage of the validation threads for a TAL
+    #[arg(long, value_name = "TAL=PERCENT")]
+    tal_share: Option<Vec<String>>,
+
+    /// Set the priority of a TAL when sharing validation threads
+    #[arg(long, value_name = "TAL=PRIORITY")]
+    tal_priority: Option<Vec<String>>,
+
     /// Log more information, twice for even more
     #[arg(short, long, action = ArgAction::Count)]
     verbose: u8,
@@ -1916,6 +4617,22 @@ struct ServerArgs {
     #[arg(long, value_name = "SECONDS")]
     refresh: Option<u64>,
 
+    /// Spread repository updates over this many runs per refresh interval
+    #[arg(long, value_name = "COUNT")]
+    fetch_spread: Option<u32>,
+
+    /// Interval for checking RRDP notification files between runs
+    #[arg(long, value_name = "SECONDS")]
+    rrdp_heartbeat: Option<u64>,
+
+    /// Interval for probing repository reachability between runs
+    #[arg(long, value_name = "SECONDS")]
+    repository_probe: Option<u64>,
+
+    /// Minimum number of seconds between publishing new serials
+    #[arg(long, value_name = "SECONDS")]
+    min_serial_interval: Option<u64>,
+
     /// RTR retry interval in seconds [default 600]
     #[arg(long, value_name = "SECONDS")]
     retry: Option<u64>,
@@ -1928,6 +4645,10 @@ struct ServerArgs {
     #[arg(long, value_name = "COUNT")]
     history: Option<usize>,
 
+    /// Number of payload snapshots to keep for diffs [default 0]
+    #[arg(long, value_name = "COUNT")]
+    snapshot_history: Option<usize>,
+
     /// Listen on address/port for RTR
     #[arg(long = "rtr", value_name = "ADDR:PORT")]
     rtr_listen: Option<Vec<SocketAddr>>,
@@ -1956,22 +4677,46 @@ struct ServerArgs {
     #[arg(long)]
     rtr_client_metrics: bool,
 
-    /// The private key to use for RTR over TLS
+    /// Only serve payload received from this upstream RTR server
+    #[arg(long, value_name = "ADDR:PORT")]
+    rtr_upstream: Option<String>,
+
+    /// CA certificates for TLS to the upstream RTR server
     #[arg(long, value_name = "PATH")]
-    rtr_tls_key: Option<PathBuf>,
+    rtr_upstream_ca_bundle: Option<PathBuf>,
+
+    /// Pinned SHA-256 fingerprint of the upstream RTR server certificate
+    #[arg(long, value_name = "FINGERPRINT")]
+    rtr_upstream_pinned_sha256: Option<Vec<CertFingerprint>>,
+
+    /// The private key to use for RTR over TLS
+    #[arg(long, value_name = "SECRET")]
+    rtr_tls_key: Option<String>,
 
     /// The certificate to use for RTR over TLS
     #[arg(long, value_name = "PATH")]
     rtr_tls_cert: Option<PathBuf>,
 
     /// The private key to use for HTTP over TLS
-    #[arg(long, value_name = "PATH")]
-    http_tls_key: Option<PathBuf>,
+    #[arg(long, value_name = "SECRET")]
+    http_tls_key: Option<String>,
 
     /// The certificate to use for HTTP over TLS
     #[arg(long, value_name = "PATH")]
     http_tls_cert: Option<PathBuf>,
 
+    /// The token required for refreshing a repository via HTTP
+    #[arg(long, value_name = "SECRET")]
+    http_refresh_token: Option<String>,
+
+    /// The token required for enabling or disabling TALs via HTTP
+    #[arg(long, value_name = "SECRET")]
+    http_tal_token: Option<String>,
+
+    /// Append a JSON line for every administrative request to the file
+    #[arg(long, value_name = "PATH")]
+    http_audit_log: Option<PathBuf>,
+
     /// The file for keep the daemon process's PID in
     #[arg(long, value_name = "PATH")]
     pid_file: Option<PathBuf>,
@@ -2203,6 +4948,31 @@ impl ConfigFile {
         }
     }
 
+    /// Takes an unsigned 32-bit integer value from the config file.
+    ///
+    /// The value is taken from the given `key`. Returns `Ok(None)` if there
+    /// is no such key. Returns an error if the key exists but the value
+    /// isn’t an integer or if it is out of bounds.
+    fn take_u32(&mut self, key: &str) -> Result<Option<u32>, Failed> {
+        match self.take_u64(key)? {
+            Some(value) => {
+                match u32::try_from(value) {
+                    Ok(value) => Ok(Some(value)),
+                    Err(_) => {
+                        error!(
+                            "Failed in config file {}: \
+                            '{}' expected to be a positive integer \
+                            below 2^32.",
+                            self.path.display(), key
+                        );
+                        Err(Failed)
+                    }
+                }
+            }
+            None => Ok(None)
+        }
+    }
+
     /// Takes a small unsigned integer value from the config file.
     ///
     /// While the result is returned as an `usize`, it must be in the
@@ -2292,6 +5062,31 @@ impl ConfigFile {
         self.take_string(key).map(|opt| opt.map(|path| self.dir.join(path)))
     }
 
+    /// Takes a secret from the config file.
+    ///
+    /// The value is taken from the given `key`. It is expected to be a
+    /// string containing a [`Secret`]. Relative paths are interpreted
+    /// relative to the directory of the config file.
+    fn take_secret(&mut self, key: &str) -> Result<Option<Secret>, Failed> {
+        match self.take_string(key)? {
+            Some(value) => self.secret_from_str(&value, key).map(Some),
+            None => Ok(None)
+        }
+    }
+
+    /// Converts a string from the config file into a secret.
+    fn secret_from_str(
+        &self, value: &str, key: &str
+    ) -> Result<Secret, Failed> {
+        Secret::from_str_in(value, &self.dir).map_err(|err| {
+            error!(
+                "Failed in config file {}: invalid secret in '{}': {}.",
+                self.path.display(), key, err
+            );
+            Failed
+        })
+    }
+
     /// Takes a mandatory path value from the config file.
     ///
     /// This is the pretty much the same as [`take_path`] but also returns
@@ -2454,84 +5249,575 @@ impl ConfigFile {
         }
     }
 
-    /// Takes a string-to-string hashmap from the config file.
-    fn take_string_map(
+    /// Takes an array of string pairs from the config file.
+    ///
+    /// The pairs are returned in the order they appear in the file.
+    fn take_string_pairs(
+        &mut self,
+        key: &str
+    ) -> Result<Option<Vec<(String, String)>>, Failed> {
+        match self.take_value(key)? {
+            Some(toml::Value::Array(vec)) => {
+                let mut res = Vec::new();
+                for value in vec.into_iter() {
+                    let mut pair = match value {
+                        toml::Value::Array(pair) => pair.into_iter(),
+                        _ => {
+                            error!(
+                                "Failed in config file {}: \
+                                '{}' expected to be a array of string pairs.",
+                                self.path.display(),
+                                key
+                            );
+                            return Err(Failed);
+                        }
+                    };
+                    let left = match pair.next() {
+                        Some(toml::Value::String(value)) => value,
+                        _ => {
+                            error!(
+                                "Failed in config file {}: \
+                                '{}' expected to be a array of string pairs.",
+                                self.path.display(),
+                                key
+                            );
+                            return Err(Failed);
+                        }
+                    };
+                    let right = match pair.next() {
+                        Some(toml::Value::String(value)) => value,
+                        _ => {
+                            error!(
+                                "Failed in config file {}: \
+                                '{}' expected to be a array of string pairs.",
+                                self.path.display(),
+                                key
+                            );
+                            return Err(Failed);
+                        }
+                    };
+                    if pair.next().is_some() {
+                        error!(
+                            "Failed in config file {}: \
+                            '{}' expected to be a array of string pairs.",
+                            self.path.display(),
+                            key
+                        );
+                        return Err(Failed);
+                    }
+                    res.push((left.into_value(), right.into_value()));
+                }
+                Ok(Some(res))
+            }
+            Some(_) => {
+                error!(
+                    "Failed in config file {}: \
+                     '{}' expected to be a array of string pairs.",
+                    self.path.display(), key
+                );
+                Err(Failed)
+            }
+            None => Ok(None)
+        }
+    }
+
+    /// Takes a string-to-string hashmap from the config file.
+    fn take_string_map(
+        &mut self,
+        key: &str
+    ) -> Result<Option<HashMap<String, String>>, Failed> {
+        let pairs = match self.take_string_pairs(key)? {
+            Some(pairs) => pairs,
+            None => return Ok(None)
+        };
+        let mut res = HashMap::new();
+        for (left, right) in pairs {
+            if res.insert(left, right).is_some() {
+                error!(
+                    "Failed in config file {}: \
+                    'duplicate item in '{}'.",
+                    self.path.display(),
+                    key
+                );
+                return Err(Failed);
+            }
+        }
+        Ok(Some(res))
+    }
+
+    /// Takes the RRDP timeout and retry overrides from the config file.
+    ///
+    /// These are given as a table with a sub-table for each repository
+    /// keyed by either an rpkiNotify URI or a host name.
+    fn take_rrdp_request_overrides(
+        &mut self,
+        key: &str
+    ) -> Result<HashMap<String, RrdpOverride>, Failed> {
+        let mut res = HashMap::new();
+        for (name, mut file) in self.take_sub_tables(key)? {
+            if name.starts_with("https://") {
+                if let Err(err) = uri::Https::from_str(&name) {
+                    error!(
+                        "Failed in config file {}: \
+                         invalid URI '{}' in '{}': {}.",
+                        self.path.display(), name, key, err
+                    );
+                    return Err(Failed);
+                }
+            }
+            let value = RrdpOverride {
+                connect_timeout: {
+                    file.take_u64("connect-timeout")?.map(Duration::from_secs)
+                },
+                timeout: file.take_u64("timeout")?.map(Duration::from_secs),
+                retries: file.take_small_usize("retries")?,
+                retry_backoff: {
+                    file.take_u64("retry-backoff")?.map(Duration::from_secs)
+                },
+            };
+            file.check_exhausted()?;
+            res.insert(name, value);
+        }
+        Ok(res)
+    }
+
+    /// Takes the per-TAL manifest handling policies from the config file.
+    ///
+    /// These are given as a table with a sub-table for each TAL keyed by
+    /// the TAL’s name.
+    fn take_tal_policies(
+        &mut self,
+        key: &str
+    ) -> Result<HashMap<String, TalPolicy>, Failed> {
+        let mut res = HashMap::new();
+        for (name, mut file) in self.take_sub_tables(key)? {
+            let value = TalPolicy {
+                stale: file.take_from_str("stale")?,
+                missing_files: file.take_from_str("missing-files")?,
+                hash_mismatch: file.take_from_str("hash-mismatch")?,
+                resource_validation: {
+                    file.take_from_str("resource-validation")?
+                },
+            };
+            file.check_exhausted()?;
+            res.insert(name, value);
+        }
+        Ok(res)
+    }
+
+    /// Takes the local policy rules for route origins from the config file.
+    ///
+    /// These are given as a table with a sub-table for each rule keyed by
+    /// the rule’s name.
+    fn take_roa_policy(
+        &mut self,
+        key: &str
+    ) -> Result<HashMap<String, RoaPolicyRule>, Failed> {
+        let mut res = HashMap::new();
+        for (name, mut file) in self.take_sub_tables(key)? {
+            let value = RoaPolicyRule {
+                max_length_excess: {
+                    file.take_limited_u8("max-length-excess", 128)?
+                },
+                prefixes: {
+                    file.take_from_str_array("prefixes")?.unwrap_or_default()
+                },
+                asns: file.take_from_str_array("asns")?.unwrap_or_default(),
+                action: {
+                    file.take_from_str("action")?
+                        .unwrap_or(FilterPolicy::Reject)
+                },
+            };
+            file.check_exhausted()?;
+            res.insert(name, value);
+        }
+        Ok(res)
+    }
+
+    /// Takes the per-module rsync overrides from the config file.
+    ///
+    /// These are given as a table with a sub-table for each module keyed
+    /// by the module’s rsync URI.
+    fn take_rsync_overrides(
+        &mut self,
+        key: &str
+    ) -> Result<HashMap<String, RsyncOverride>, Failed> {
+        let mut res = HashMap::new();
+        for (name, mut file) in self.take_sub_tables(key)? {
+            let module = self.rsync_module(&name, key)?;
+            let mut mirrors = Vec::new();
+            for item in file.take_string_array("mirrors")?.unwrap_or_default() {
+                mirrors.push(self.rsync_module(&item, key)?);
+            }
+            let ssh = match file.take_string("ssh")? {
+                Some(value) => match SshUri::from_str(&value) {
+                    Ok(value) => Some(value),
+                    Err(err) => {
+                        error!(
+                            "Failed in config file {}: \
+                             invalid SSH URI '{}' for '{}' in '{}': {}.",
+                            self.path.display(), value, name, key, err
+                        );
+                        return Err(Failed)
+                    }
+                }
+                None => None,
+            };
+            let value = RsyncOverride {
+                timeout: file.take_u64("timeout")?.map(Duration::from_secs),
+                connect_timeout: {
+                    file.take_u64("connect-timeout")?.map(Duration::from_secs)
+                },
+                bandwidth_limit: file.take_u64("bandwidth-limit")?,
+                extra_args: {
+                    file.take_string_array("extra-args")?.unwrap_or_default()
+                },
+                mirrors,
+                ssh,
+                ssh_identity_file: file.take_path("ssh-identity-file")?,
+                ssh_known_hosts: file.take_path("ssh-known-hosts")?,
+            };
+            file.check_exhausted()?;
+            if value.ssh.is_none() && (
+                value.ssh_identity_file.is_some()
+                || value.ssh_known_hosts.is_some()
+            ) {
+                error!(
+                    "Failed in config file {}: \
+                     SSH settings without 'ssh' for '{}' in '{}'.",
+                    self.path.display(), name, key
+                );
+                return Err(Failed)
+            }
+            if res.insert(module, value).is_some() {
+                error!(
+                    "Failed in config file {}: \
+                     duplicate module '{}' in '{}'.",
+                    self.path.display(), name, key
+                );
+                return Err(Failed);
+            }
+        }
+        Ok(res)
+    }
+
+    /// Converts an rsync module URI into its canonical form.
+    ///
+    /// The URI `name` was found in the value for `key`. The trailing slash
+    /// of the module URI is optional.
+    fn rsync_module(&self, name: &str, key: &str) -> Result<String, Failed> {
+        let uri = if name.ends_with('/') {
+            uri::Rsync::from_str(name)
+        }
+        else {
+            uri::Rsync::from_string(format!("{}/", name))
+        };
+        match uri {
+            Ok(uri) if uri.path().is_empty() => {
+                Ok(uri.canonical_module().into_owned())
+            }
+            _ => {
+                error!(
+                    "Failed in config file {}: \
+                     '{}' in '{}' expected to be an rsync module URI.",
+                    self.path.display(), name, key
+                );
+                Err(Failed)
+            }
+        }
+    }
+
+    /// Takes the per-host RRDP TLS trust settings from the config file.
+    ///
+    /// These are given as a table with a sub-table for each host name.
+    fn take_rrdp_tls_overrides(
+        &mut self,
+        key: &str
+    ) -> Result<HashMap<String, RrdpTlsOverride>, Failed> {
+        let mut res = HashMap::new();
+        for (name, mut file) in self.take_sub_tables(key)? {
+            if name.contains('/') {
+                error!(
+                    "Failed in config file {}: \
+                     '{}' in '{}' expected to be a host name.",
+                    self.path.display(), name, key
+                );
+                return Err(Failed)
+            }
+            let mut pinned_certs = Vec::new();
+            for item in file.take_string_array("pinned-sha256")?
+                .unwrap_or_default()
+            {
+                match CertFingerprint::from_str(&item) {
+                    Ok(item) => pinned_certs.push(item),
+                    Err(err) => {
+                        error!(
+                            "Failed in config file {}: \
+                             invalid fingerprint '{}' for '{}' in '{}': {}.",
+                            self.path.display(), item, name, key, err
+                        );
+                        return Err(Failed)
+                    }
+                }
+            }
+            let value = RrdpTlsOverride {
+                ca_bundle: file.take_path("ca-bundle")?,
+                pinned_certs,
+            };
+            file.check_exhausted()?;
+            if value.ca_bundle.is_none() && value.pinned_certs.is_empty() {
+                error!(
+                    "Failed in config file {}: \
+                     '{}' in '{}' needs 'ca-bundle' or 'pinned-sha256'.",
+                    self.path.display(), name, key
+                );
+                return Err(Failed)
+            }
+            res.insert(name, value);
+        }
+        Ok(res)
+    }
+
+    /// Takes a table of sub-tables from the config file.
+    ///
+    /// Returns the name of each sub-table together with its content as a
+    /// config file of its own. If the key is not present, returns an empty
+    /// vec.
+    fn take_sub_tables(
+        &mut self,
+        key: &str
+    ) -> Result<Vec<(String, ConfigFile)>, Failed> {
+        let table = match self.content.remove(key) {
+            Some(toml::Item::Table(table)) => table,
+            Some(toml::Item::Value(toml::Value::InlineTable(table))) => {
+                table.into_table()
+            }
+            None => return Ok(Vec::new()),
+            Some(_) => {
+                error!(
+                    "Failed in config file {}: \
+                     '{}' expected to be a table.",
+                    self.path.display(), key
+                );
+                return Err(Failed)
+            }
+        };
+        let mut res = Vec::new();
+        for (name, item) in table {
+            let table = match item {
+                toml::Item::Table(table) => table,
+                toml::Item::Value(toml::Value::InlineTable(table)) => {
+                    table.into_table()
+                }
+                _ => {
+                    error!(
+                        "Failed in config file {}: \
+                         '{}' in '{}' expected to be a table.",
+                        self.path.display(), name, key
+                    );
+                    return Err(Failed)
+                }
+            };
+            res.push((
+                name.to_string(),
+                ConfigFile {
+                    content: table.into(),
+                    path: self.path.clone(),
+                    dir: self.dir.clone(),
+                }
+            ));
+        }
+        Ok(res)
+    }
+
+    /// Takes per-repository RRDP overrides from the config file.
+    fn take_rrdp_overrides<T>(
+        &mut self,
+        key: &str
+    ) -> Result<HashMap<uri::Https, T>, Failed>
+    where T: FromStr, T::Err: fmt::Display {
+        let pairs = match self.take_string_pairs(key)? {
+            Some(pairs) => pairs,
+            None => return Ok(HashMap::new())
+        };
+        let mut res = HashMap::new();
+        for (rpki_notify, spec) in pairs {
+            let rpki_notify = match uri::Https::from_str(&rpki_notify) {
+                Ok(uri) => uri,
+                Err(err) => {
+                    error!(
+                        "Failed in config file {}: \
+                         invalid URI '{}' in '{}': {}.",
+                        self.path.display(), rpki_notify, key, err
+                    );
+                    return Err(Failed);
+                }
+            };
+            let spec = match T::from_str(&spec) {
+                Ok(spec) => spec,
+                Err(err) => {
+                    error!(
+                        "Failed in config file {}: \
+                         invalid override for '{}' in '{}': {}.",
+                        self.path.display(), rpki_notify, key, err
+                    );
+                    return Err(Failed);
+                }
+            };
+            if res.insert(rpki_notify, spec).is_some() {
+                error!(
+                    "Failed in config file {}: \
+                    'duplicate item in '{}'.",
+                    self.path.display(),
+                    key
+                );
+                return Err(Failed);
+            }
+        }
+        Ok(res)
+    }
+
+    /// Takes the transport overrides from the config file.
+    ///
+    /// The overrides are given as an array of pairs of an rpkiNotify URI
+    /// or host name and the transport policy.
+    fn take_transport_overrides(
+        &mut self,
+        key: &str
+    ) -> Result<HashMap<String, TransportPolicy>, Failed> {
+        let pairs = match self.take_string_pairs(key)? {
+            Some(pairs) => pairs,
+            None => return Ok(HashMap::new())
+        };
+        let mut res = HashMap::new();
+        for (target, policy) in pairs {
+            let policy = match TransportPolicy::from_str(&policy) {
+                Ok(policy) => policy,
+                Err(err) => {
+                    error!(
+                        "Failed in config file {}: \
+                         invalid override for '{}' in '{}': {}.",
+                        self.path.display(), target, key, err
+                    );
+                    return Err(Failed);
+                }
+            };
+            if res.insert(target, policy).is_some() {
+                error!(
+                    "Failed in config file {}: \
+                    'duplicate item in '{}'.",
+                    self.path.display(),
+                    key
+                );
+                return Err(Failed);
+            }
+        }
+        Ok(res)
+    }
+
+    /// Takes the TAL shares from the config file.
+    ///
+    /// The shares are given as an array of pairs of a TAL name and a
+    /// percentage.
+    fn take_tal_shares(
+        &mut self,
+        key: &str
+    ) -> Result<HashMap<String, u8>, Failed> {
+        let pairs = match self.take_string_pairs(key)? {
+            Some(pairs) => pairs,
+            None => return Ok(HashMap::new())
+        };
+        let mut res = HashMap::new();
+        for (tal, share) in pairs {
+            let share = match parse_tal_share(&share) {
+                Ok(share) => share,
+                Err(err) => {
+                    error!(
+                        "Failed in config file {}: \
+                         invalid share for '{}' in '{}': {}.",
+                        self.path.display(), tal, key, err
+                    );
+                    return Err(Failed);
+                }
+            };
+            if res.insert(tal, share).is_some() {
+                error!(
+                    "Failed in config file {}: \
+                    'duplicate item in '{}'.",
+                    self.path.display(),
+                    key
+                );
+                return Err(Failed);
+            }
+        }
+        if let Err(err) = check_tal_shares(&res) {
+            error!(
+                "Failed in config file {}: invalid '{}': {}.",
+                self.path.display(), key, err
+            );
+            return Err(Failed);
+        }
+        Ok(res)
+    }
+
+    /// Takes the TAL priorities from the config file.
+    ///
+    /// The priorities are given as an array of pairs of a TAL name and an
+    /// integer given as a string.
+    fn take_tal_priorities(
+        &mut self,
+        key: &str
+    ) -> Result<HashMap<String, i32>, Failed> {
+        let pairs = match self.take_string_pairs(key)? {
+            Some(pairs) => pairs,
+            None => return Ok(HashMap::new())
+        };
+        let mut res = HashMap::new();
+        for (tal, priority) in pairs {
+            let priority = match i32::from_str(priority.trim()) {
+                Ok(priority) => priority,
+                Err(_) => {
+                    error!(
+                        "Failed in config file {}: \
+                         invalid priority for '{}' in '{}'.",
+                        self.path.display(), tal, key
+                    );
+                    return Err(Failed);
+                }
+            };
+            if res.insert(tal, priority).is_some() {
+                error!(
+                    "Failed in config file {}: \
+                    'duplicate item in '{}'.",
+                    self.path.display(),
+                    key
+                );
+                return Err(Failed);
+            }
+        }
+        Ok(res)
+    }
+
+    /// Takes a list of URI rewrite rules from the config file.
+    fn take_uri_rewrites(
         &mut self,
         key: &str
-    ) -> Result<Option<HashMap<String, String>>, Failed> {
-        match self.take_value(key)? {
-            Some(toml::Value::Array(vec)) => {
-                let mut res = HashMap::new();
-                for value in vec.into_iter() {
-                    let mut pair = match value {
-                        toml::Value::Array(pair) => pair.into_iter(),
-                        _ => {
-                            error!(
-                                "Failed in config file {}: \
-                                '{}' expected to be a array of string pairs.",
-                                self.path.display(),
-                                key
-                            );
-                            return Err(Failed);
-                        }
-                    };
-                    let left = match pair.next() {
-                        Some(toml::Value::String(value)) => value,
-                        _ => {
-                            error!(
-                                "Failed in config file {}: \
-                                '{}' expected to be a array of string pairs.",
-                                self.path.display(),
-                                key
-                            );
-                            return Err(Failed);
-                        }
-                    };
-                    let right = match pair.next() {
-                        Some(toml::Value::String(value)) => value,
-                        _ => {
-                            error!(
-                                "Failed in config file {}: \
-                                '{}' expected to be a array of string pairs.",
-                                self.path.display(),
-                                key
-                            );
-                            return Err(Failed);
-                        }
-                    };
-                    if pair.next().is_some() {
-                        error!(
-                            "Failed in config file {}: \
-                            '{}' expected to be a array of string pairs.",
-                            self.path.display(),
-                            key
-                        );
-                        return Err(Failed);
-                    }
-                    if res.insert(
-                        left.into_value(), right.into_value()
-                    ).is_some() {
-                        error!(
-                            "Failed in config file {}: \
-                            'duplicate item in '{}'.",
-                            self.path.display(),
-                            key
-                        );
-                        return Err(Failed);
-                    }
-                }
-                Ok(Some(res))
-            }
-            Some(_) => {
+    ) -> Result<Vec<(String, String)>, Failed> {
+        let pairs = match self.take_string_pairs(key)? {
+            Some(pairs) => pairs,
+            None => return Ok(Vec::new())
+        };
+        for (from, to) in &pairs {
+            if let Err(err) = UriRewrite::check_rule(from, to) {
                 error!(
                     "Failed in config file {}: \
-                     '{}' expected to be a array of string pairs.",
-                    self.path.display(), key
+                     invalid rule '{}' to '{}' in '{}': {}.",
+                    self.path.display(), from, to, key, err
                 );
-                Err(Failed)
+                return Err(Failed);
             }
-            None => Ok(None)
         }
+        Ok(pairs)
     }
 
     /// Checks whether the config file is now empty.
@@ -2565,6 +5851,27 @@ impl ConfigFile {
 
 //------------ Helpers -------------------------------------------------------
 
+/// Parses the share of the validation threads for a TAL.
+///
+/// The share is a percentage, optionally followed by a percent sign.
+fn parse_tal_share(value: &str) -> Result<u8, &'static str> {
+    let value = value.trim();
+    match u8::from_str(value.strip_suffix('%').unwrap_or(value)) {
+        Ok(value) if (1..=100).contains(&value) => Ok(value),
+        _ => Err("expected a percentage between 1 and 100")
+    }
+}
+
+/// Checks that the TAL shares don’t exceed the available threads.
+fn check_tal_shares(shares: &HashMap<String, u8>) -> Result<(), &'static str> {
+    if shares.values().map(|value| u32::from(*value)).sum::<u32>() > 100 {
+        Err("shares add up to more than 100 percent")
+    }
+    else {
+        Ok(())
+    }
+}
+
 /// Converts the syslog facility name to the facility type.
 #[cfg(unix)]
 fn facility_to_string(facility: Facility) -> String {
@@ -2674,7 +5981,7 @@ mod test {
              log-file = \"foo.log\"",
             Path::new("/test/routinator.conf")
         ).unwrap();
-        let config = Config::from_config_file(config).unwrap();
+        let config = Config::from_config_file(config, None).unwrap();
         assert_eq!(config.cache_dir.to_str().unwrap(), "/repodir");
         assert_eq!(
             config.extra_tals_dir.unwrap().to_str().unwrap(),
@@ -2717,7 +6024,7 @@ mod test {
              extra-tals-dir = \"taldir\"",
             Path::new("/test/routinator.conf")
         ).unwrap();
-        let config = Config::from_config_file(config).unwrap();
+        let config = Config::from_config_file(config, None).unwrap();
         assert_eq!(config.cache_dir.to_str().unwrap(), "/repodir");
         assert_eq!(
             config.extra_tals_dir.unwrap().to_str().unwrap(), "/test/taldir"
@@ -2751,7 +6058,7 @@ mod test {
         let in_file = ConfigFile::parse(
             &out_file, &out_path
         ).unwrap();
-        let in_config = Config::from_config_file(in_file).unwrap();
+        let in_config = Config::from_config_file(in_file, None).unwrap();
         assert_eq!(out_config, in_config);
     }
 
@@ -2761,7 +6068,32 @@ mod test {
         let config = process_basic_args(&[
             "routinator", "-r", "/repository",
             "-x", "/x1", "--exceptions", "x2", "--strict",
-            "--validation-threads", "2000",
+            "--validation-threads", "2000", "--missing-files", "warn",
+            "--hash-mismatch", "accept", "--stale-grace", "3600",
+            "--clock-skew", "300",
+            "--validation-time", "2021-02-18T13:22:06Z",
+            "--resource-validation", "strict",
+            "--max-repository-size", "1000000",
+            "--max-cache-size", "5000000000",
+            "--max-host-connections", "4", "--rsync-max-parallel", "2",
+            "--stored-max-age", "604800", "--stored-stale-age", "86400",
+            "--checkpoint-interval", "60",
+            "--max-point-objects", "10000", "--max-ca-time", "600",
+            "--signature-cache-size", "100000",
+            "--object-cache-size", "500000",
+            "--rrdp-user-agent", "Routinator/test",
+            "--rrdp-header", "X-Site: ams1",
+            "--rrdp-header", "X-Token: secret",
+            "--rrdp-header-secret", "Authorization=env:RRDP_TOKEN",
+            "--rrdp-local-dir", "lab",
+            "--fetch-log", "fetch.log",
+            "--rejection-report", "rejected.json",
+            "--mirror-dir", "mirror",
+            "--mirror-https-uri", "https://mirror.example.net/https/",
+            "--crosscheck", "--roa-overlap-report",
+            "--repository-backoff", "600", "--dead-repository-age", "3600",
+            "--rsync-include", "*.cer", "--rsync-include", "*.mft",
+            "--rsync-exclude", "*.tar.gz", "--freeze",
             "--syslog", "--syslog-facility", "auth"
         ]);
         assert_eq!(config.cache_dir, Path::new("/repository"));
@@ -2770,9 +6102,592 @@ mod test {
         );
         assert!(config.strict);
         assert_eq!(config.validation_threads, 2000);
+        assert_eq!(config.missing_files, FilterPolicy::Warn);
+        assert_eq!(config.hash_mismatch, FilterPolicy::Accept);
+        assert_eq!(config.resource_validation, ResourceValidation::Strict);
+        assert_eq!(config.stale_grace, Some(Duration::from_secs(3600)));
+        assert_eq!(config.clock_skew, Some(Duration::from_secs(300)));
+        assert_eq!(
+            config.validation_time.map(|time| time.timestamp()),
+            Some(1613654526)
+        );
+        assert_eq!(config.max_repository_size, Some(1_000_000));
+        assert_eq!(config.max_cache_size, Some(5_000_000_000));
+        assert_eq!(config.max_host_connections, Some(4));
+        assert!(config.freeze);
+        assert!(config.store_readonly);
+        assert_eq!(config.rsync_max_parallel, Some(2));
+        assert_eq!(config.rsync_include, ["*.cer", "*.mft"]);
+        assert_eq!(config.rsync_exclude, ["*.tar.gz"]);
+        assert_eq!(
+            config.stored_max_age, Some(Duration::from_secs(604800))
+        );
+        assert_eq!(
+            config.stored_stale_age, Some(Duration::from_secs(86400))
+        );
+        assert_eq!(
+            config.checkpoint_interval, Some(Duration::from_secs(60))
+        );
+        assert_eq!(config.max_point_objects, Some(10000));
+        assert_eq!(config.max_ca_time, Some(Duration::from_secs(600)));
+        assert_eq!(config.signature_cache_size, Some(100000));
+        assert_eq!(config.object_cache_size, Some(500000));
+        assert_eq!(config.rrdp_user_agent, "Routinator/test");
+        assert_eq!(config.rrdp_headers, ["X-Site: ams1", "X-Token: secret"]);
+        assert_eq!(
+            config.rrdp_header_secrets,
+            [("Authorization".into(), Secret::Env("RRDP_TOKEN".into()))]
+        );
+        assert_eq!(
+            config.rrdp_local_dir.as_deref(), Some(Path::new("/test/lab"))
+        );
+        assert_eq!(
+            config.fetch_log.as_deref(), Some(Path::new("/test/fetch.log"))
+        );
+        assert_eq!(
+            config.rejection_report.as_deref(),
+            Some(Path::new("/test/rejected.json"))
+        );
+        assert_eq!(
+            config.mirror_dir.as_deref(), Some(Path::new("/test/mirror"))
+        );
+        assert_eq!(
+            config.mirror_https_uri.as_ref().map(|uri| uri.as_str()),
+            Some("https://mirror.example.net/https/")
+        );
+        assert!(config.crosscheck);
+        assert!(config.roa_overlap_report);
+        assert_eq!(config.repository_backoff, Some(Duration::from_secs(600)));
+        assert_eq!(
+            config.repository_backoff_max, DEFAULT_REPOSITORY_BACKOFF_MAX
+        );
+        assert_eq!(config.dead_repository_age, Duration::from_secs(3600));
         assert_eq!(config.log_target, LogTarget::Syslog(Facility::LOG_AUTH));
     }
 
+    #[test]
+    fn profiles() {
+        let config = ConfigFile::parse(
+            "repository-dir = \"/repodir\"\n\
+             profile = \"large-fleet\"\n\
+             history-size = 20",
+            Path::new("/test/routinator.conf")
+        ).unwrap();
+        let config = Config::from_config_file(config, None).unwrap();
+        assert_eq!(config.profile, ConfigProfile::LargeFleet);
+        assert_eq!(
+            config.rrdp_max_delta_count,
+            ConfigProfile::LargeFleet.rrdp_max_delta_count()
+        );
+        assert_eq!(
+            config.rrdp_timeout,
+            Some(ConfigProfile::LargeFleet.rrdp_timeout())
+        );
+        assert_eq!(config.history_size, 20);
+
+        let config = ConfigFile::parse(
+            "repository-dir = \"/repodir\"\n\
+             profile = \"large-fleet\"",
+            Path::new("/test/routinator.conf")
+        ).unwrap();
+        let config = Config::from_config_file(
+            config, Some(ConfigProfile::Small)
+        ).unwrap();
+        assert_eq!(config.profile, ConfigProfile::Small);
+        assert_eq!(
+            config.history_size, ConfigProfile::Small.history_size()
+        );
+        assert!(config.validation_threads <= 2);
+
+        let config = get_default_config();
+        assert_eq!(
+            config, Config::default_with_profile(ConfigProfile::Default)
+        );
+    }
+
+    #[test]
+    fn roa_policy() {
+        let config = ConfigFile::parse(
+            "repository-dir = \"/repodir\"\n\
+             [roa-policy.long]\n\
+             max-length-excess = 8\n\
+             action = \"warn\"\n\
+             [roa-policy.bogons]\n\
+             prefixes = [\"10.0.0.0/8\", \"fc00::/7\"]\n\
+             asns = [\"AS0\"]\n",
+            Path::new("/test/routinator.conf")
+        ).unwrap();
+        let config = Config::from_config_file(config, None).unwrap();
+        assert_eq!(
+            config.roa_policy.get("long"),
+            Some(&RoaPolicyRule {
+                max_length_excess: Some(8),
+                prefixes: Vec::new(),
+                asns: Vec::new(),
+                action: FilterPolicy::Warn,
+            })
+        );
+        assert_eq!(
+            config.roa_policy.get("bogons"),
+            Some(&RoaPolicyRule {
+                max_length_excess: None,
+                prefixes: vec![
+                    Prefix::from_str("10.0.0.0/8").unwrap(),
+                    Prefix::from_str("fc00::/7").unwrap(),
+                ],
+                asns: vec![Asn::from_u32(0)],
+                action: FilterPolicy::Reject,
+            })
+        );
+
+        let file = ConfigFile::parse(
+            &format!("{}", config), Path::new("/test/routinator.conf")
+        ).unwrap();
+        assert_eq!(config, Config::from_config_file(file, None).unwrap());
+
+        assert!(Config::from_config_file(
+            ConfigFile::parse(
+                "repository-dir = \"/repodir\"\n\
+                 [roa-policy.bogons]\n\
+                 prefixes = [\"10.0.0.0/33\"]\n",
+                Path::new("/test/routinator.conf")
+            ).unwrap(),
+            None
+        ).is_err());
+    }
+
+    #[test]
+    fn tal_policies() {
+        let config = ConfigFile::parse(
+            "repository-dir = \"/repodir\"\n\
+             missing-files = \"warn\"\n\
+             [tal-policies.ripe]\n\
+             stale = \"warn\"\n\
+             hash-mismatch = \"accept\"\n\
+             resource-validation = \"strict\"\n",
+            Path::new("/test/routinator.conf")
+        ).unwrap();
+        let config = Config::from_config_file(config, None).unwrap();
+        assert_eq!(config.missing_files, FilterPolicy::Warn);
+        assert_eq!(config.hash_mismatch, FilterPolicy::Reject);
+        assert_eq!(
+            config.tal_policies.get("ripe"),
+            Some(&TalPolicy {
+                stale: Some(FilterPolicy::Warn),
+                hash_mismatch: Some(FilterPolicy::Accept),
+                resource_validation: Some(ResourceValidation::Strict),
+                .. Default::default()
+            })
+        );
+
+        let file = ConfigFile::parse(
+            &format!("{}", config), Path::new("/test/routinator.conf")
+        ).unwrap();
+        assert_eq!(config, Config::from_config_file(file, None).unwrap());
+
+        assert!(Config::from_config_file(
+            ConfigFile::parse(
+                "repository-dir = \"/repodir\"\n\
+                 [tal-policies.ripe]\n\
+                 stale = \"ignore\"\n",
+                Path::new("/test/routinator.conf")
+            ).unwrap(),
+            None
+        ).is_err());
+    }
+
+    #[test]
+    fn rrdp_request_overrides() {
+        let config = ConfigFile::parse(
+            "repository-dir = \"/repodir\"\n\
+             rrdp-retries = 1\n\
+             [rrdp-overrides.\"rrdp.example.net\"]\n\
+             timeout = 900\n\
+             retries = 3\n\
+             [rrdp-overrides.\"https://rrdp.example.com/notify.xml\"]\n\
+             connect-timeout = 20\n",
+            Path::new("/test/routinator.conf")
+        ).unwrap();
+        let config = Config::from_config_file(config, None).unwrap();
+        assert_eq!(config.rrdp_retries, 1);
+        assert_eq!(
+            config.rrdp_overrides.get("rrdp.example.net"),
+            Some(&RrdpOverride {
+                timeout: Some(Duration::from_secs(900)),
+                retries: Some(3),
+                .. Default::default()
+            })
+        );
+        assert_eq!(
+            config.rrdp_overrides.get("https://rrdp.example.com/notify.xml"),
+            Some(&RrdpOverride {
+                connect_timeout: Some(Duration::from_secs(20)),
+                .. Default::default()
+            })
+        );
+
+        let file = ConfigFile::parse(
+            &format!("{}", config), Path::new("/test/routinator.conf")
+        ).unwrap();
+        assert_eq!(config, Config::from_config_file(file, None).unwrap());
+
+        assert!(Config::from_config_file(
+            ConfigFile::parse(
+                "repository-dir = \"/repodir\"\n\
+                 [rrdp-overrides.\"rrdp.example.net\"]\n\
+                 foo = 900\n",
+                Path::new("/test/routinator.conf")
+            ).unwrap(),
+            None
+        ).is_err());
+    }
+
+    #[test]
+    fn rsync_overrides() {
+        let config = ConfigFile::parse(
+            "repository-dir = \"/repodir\"\n\
+             rsync-timeout = 300\n\
+             [rsync-overrides.\"rsync://RSYNC.example.net/repo\"]\n\
+             timeout = 1800\n\
+             connect-timeout = 60\n\
+             [rsync-overrides.\"rsync://rsync.example.com/ta/\"]\n\
+             bandwidth-limit = 0\n\
+             extra-args = [\"--ipv4\"]\n\
+             mirrors = [\"rsync://Mirror.example.com/ta\"]\n\
+             [rsync-overrides.\"rsync://rsync.example.org/private/\"]\n\
+             ssh = \"ssh://rpki@repo.example.org/srv/private\"\n\
+             ssh-identity-file = \"keys/rpki\"\n\
+             ssh-known-hosts = \"/etc/routinator/known_hosts\"\n",
+            Path::new("/test/routinator.conf")
+        ).unwrap();
+        let config = Config::from_config_file(config, None).unwrap();
+        assert_eq!(config.rsync_timeout, Some(Duration::from_secs(300)));
+        assert_eq!(
+            config.rsync_overrides.get("rsync://rsync.example.net/repo/"),
+            Some(&RsyncOverride {
+                timeout: Some(Duration::from_secs(1800)),
+                connect_timeout: Some(Duration::from_secs(60)),
+                .. Default::default()
+            })
+        );
+        assert_eq!(
+            config.rsync_overrides.get("rsync://rsync.example.com/ta/"),
+            Some(&RsyncOverride {
+                bandwidth_limit: Some(0),
+                extra_args: vec!["--ipv4".into()],
+                mirrors: vec!["rsync://mirror.example.com/ta/".into()],
+                .. Default::default()
+            })
+        );
+        assert_eq!(
+            config.rsync_overrides.get("rsync://rsync.example.org/private/"),
+            Some(&RsyncOverride {
+                ssh: Some(
+                    SshUri::from_str(
+                        "ssh://rpki@repo.example.org/srv/private/"
+                    ).unwrap()
+                ),
+                ssh_identity_file: Some("/test/keys/rpki".into()),
+                ssh_known_hosts: Some(
+                    "/etc/routinator/known_hosts".into()
+                ),
+                .. Default::default()
+            })
+        );
+
+        let file = ConfigFile::parse(
+            &format!("{}", config), Path::new("/test/routinator.conf")
+        ).unwrap();
+        assert_eq!(config, Config::from_config_file(file, None).unwrap());
+
+        assert!(Config::from_config_file(
+            ConfigFile::parse(
+                "repository-dir = \"/repodir\"\n\
+                 [rsync-overrides.\"rsync://rsync.example.net/repo/ta.cer\"]\n\
+                 timeout = 900\n",
+                Path::new("/test/routinator.conf")
+            ).unwrap(),
+            None
+        ).is_err());
+        assert!(Config::from_config_file(
+            ConfigFile::parse(
+                "repository-dir = \"/repodir\"\n\
+                 [rsync-overrides.\"rsync://rsync.example.net/repo/\"]\n\
+                 ssh-known-hosts = \"known_hosts\"\n",
+                Path::new("/test/routinator.conf")
+            ).unwrap(),
+            None
+        ).is_err());
+    }
+
+    #[test]
+    fn rrdp_tls_overrides() {
+        let pin = "E3:B0:C4:42:98:FC:1C:14:9A:FB:F4:C8:99:6F:B9:24:\
+                   27:AE:41:E4:64:9B:93:4C:A4:95:99:1B:78:52:B8:55";
+        let config = ConfigFile::parse(
+            &format!(
+                "repository-dir = \"/repodir\"\n\
+                 [rrdp-tls-overrides.\"rrdp.example.net\"]\n\
+                 ca-bundle = \"internal-ca.pem\"\n\
+                 [rrdp-tls-overrides.\"rrdp.example.com\"]\n\
+                 pinned-sha256 = [\"{}\"]\n",
+                pin
+            ),
+            Path::new("/test/routinator.conf")
+        ).unwrap();
+        let config = Config::from_config_file(config, None).unwrap();
+        assert_eq!(
+            config.rrdp_tls_overrides.get("rrdp.example.net"),
+            Some(&RrdpTlsOverride {
+                ca_bundle: Some("/test/internal-ca.pem".into()),
+                pinned_certs: Vec::new(),
+            })
+        );
+        assert_eq!(
+            config.rrdp_tls_overrides.get("rrdp.example.com"),
+            Some(&RrdpTlsOverride {
+                ca_bundle: None,
+                pinned_certs: vec![CertFingerprint::from_str(pin).unwrap()],
+            })
+        );
+
+        let file = ConfigFile::parse(
+            &format!("{}", config), Path::new("/test/routinator.conf")
+        ).unwrap();
+        assert_eq!(config, Config::from_config_file(file, None).unwrap());
+
+        for content in [
+            "[rrdp-tls-overrides.\"rrdp.example.net\"]\n",
+            "[rrdp-tls-overrides.\"rrdp.example.net\"]\n\
+             pinned-sha256 = [\"00:11\"]\n",
+            "[rrdp-tls-overrides.\"https://rrdp.example.net/\"]\n\
+             ca-bundle = \"internal-ca.pem\"\n",
+        ] {
+            assert!(Config::from_config_file(
+                ConfigFile::parse(
+                    &format!("repository-dir = \"/repodir\"\n{}", content),
+                    Path::new("/test/routinator.conf")
+                ).unwrap(),
+                None
+            ).is_err());
+        }
+    }
+
+    #[test]
+    fn rtr_upstream_tls() {
+        let pin = "E3:B0:C4:42:98:FC:1C:14:9A:FB:F4:C8:99:6F:B9:24:\
+                   27:AE:41:E4:64:9B:93:4C:A4:95:99:1B:78:52:B8:55";
+        let config = ConfigFile::parse(
+            &format!(
+                "repository-dir = \"/repodir\"\n\
+                 rtr-upstream = \"rtr.example.net:3324\"\n\
+                 rtr-upstream-ca-bundle = \"internal-ca.pem\"\n\
+                 rtr-upstream-pinned-sha256 = [\"{}\"]\n",
+                pin
+            ),
+            Path::new("/test/routinator.conf")
+        ).unwrap();
+        let config = Config::from_config_file(config, None).unwrap();
+        assert_eq!(
+            config.rtr_upstream_ca_bundle,
+            Some("/test/internal-ca.pem".into())
+        );
+        assert_eq!(
+            config.rtr_upstream_pinned_certs,
+            vec![CertFingerprint::from_str(pin).unwrap()]
+        );
+
+        let file = ConfigFile::parse(
+            &format!("{}", config), Path::new("/test/routinator.conf")
+        ).unwrap();
+        assert_eq!(config, Config::from_config_file(file, None).unwrap());
+
+        assert!(Config::from_config_file(
+            ConfigFile::parse(
+                "repository-dir = \"/repodir\"\n\
+                 rtr-upstream-pinned-sha256 = [\"00:11\"]\n",
+                Path::new("/test/routinator.conf")
+            ).unwrap(),
+            None
+        ).is_err());
+    }
+
+    #[test]
+    fn rrdp_delta_overrides() {
+        let config = process_basic_args(&[
+            "routinator", "--rrdp-delta-overflow", "partial",
+            "--rrdp-delta-override",
+            "https://rrdp.example.net/notification.xml=500:snapshot",
+            "--rrdp-delta-override",
+            "https://rrdp.example.com/notification.xml=20",
+        ]);
+        assert_eq!(config.rrdp_delta_overflow, DeltaOverflowPolicy::Partial);
+        assert_eq!(
+            config.rrdp_delta_overrides.get(
+                &uri::Https::from_str(
+                    "https://rrdp.example.net/notification.xml"
+                ).unwrap()
+            ),
+            Some(&RrdpDeltaOverride {
+                max_delta_count: 500,
+                overflow: Some(DeltaOverflowPolicy::Snapshot),
+            })
+        );
+        assert_eq!(
+            config.rrdp_delta_overrides.get(
+                &uri::Https::from_str(
+                    "https://rrdp.example.com/notification.xml"
+                ).unwrap()
+            ),
+            Some(&RrdpDeltaOverride { max_delta_count: 20, overflow: None })
+        );
+        assert!(RrdpDeltaOverride::from_str("20:skip").is_err());
+        assert!(RrdpDeltaOverride::from_str("many").is_err());
+    }
+
+    #[test]
+    fn rrdp_limit_overrides() {
+        let config = process_basic_args(&[
+            "routinator", "--rrdp-max-objects", "100000",
+            "--rrdp-max-decoded-size", "0",
+            "--rrdp-limit-override",
+            "https://rrdp.example.net/notification.xml=\
+             objects:500000,decoded-size:0",
+        ]);
+        assert_eq!(config.rrdp_max_objects, Some(100_000));
+        assert_eq!(config.rrdp_max_decoded_size, None);
+        let limits = config.rrdp_limit_overrides.get(
+            &uri::Https::from_str(
+                "https://rrdp.example.net/notification.xml"
+            ).unwrap()
+        ).unwrap();
+        assert_eq!(
+            *limits,
+            RrdpLimits {
+                max_objects: Some(500_000),
+                max_decoded_size: Some(0),
+                .. Default::default()
+            }
+        );
+        assert_eq!(
+            RrdpLimits::from_str(&limits.to_string()).unwrap(), *limits
+        );
+        assert!(RrdpLimits::from_str("objects").is_err());
+        assert!(RrdpLimits::from_str("objects:many").is_err());
+        assert!(RrdpLimits::from_str("files:10").is_err());
+    }
+
+    #[test]
+    fn transport_overrides() {
+        let config = process_basic_args(&[
+            "routinator",
+            "--transport-override",
+            "https://rrdp.example.net/notification.xml=rsync-first",
+            "--transport-override", "rrdp.example.com=rsync",
+        ]);
+        assert_eq!(
+            config.transport_overrides.get(
+                "https://rrdp.example.net/notification.xml"
+            ),
+            Some(&TransportPolicy::RsyncFirst)
+        );
+        assert_eq!(
+            config.transport_overrides.get("rrdp.example.com"),
+            Some(&TransportPolicy::Rsync)
+        );
+
+        let config = Config::from_config_file(
+            ConfigFile::parse(
+                "repository-dir = \"/repodir\"\n\
+                 transport-overrides = [\n\
+                     [\"rrdp.example.net\", \"rrdp\"],\n\
+                 ]\n",
+                Path::new("/test/routinator.conf")
+            ).unwrap(),
+            None
+        ).unwrap();
+        assert_eq!(
+            config.transport_overrides.get("rrdp.example.net"),
+            Some(&TransportPolicy::Rrdp)
+        );
+        let file = ConfigFile::parse(
+            &format!("{}", config), Path::new("/test/routinator.conf")
+        ).unwrap();
+        assert_eq!(config, Config::from_config_file(file, None).unwrap());
+
+        assert!(TransportPolicy::from_str("carrier-pigeon").is_err());
+    }
+
+    #[test]
+    fn tal_shares() {
+        let config = process_basic_args(&[
+            "routinator", "--tal-share", "ripe=50", "--tal-share", "arin=25%",
+        ]);
+        assert_eq!(config.tal_shares.get("ripe"), Some(&50));
+        assert_eq!(config.tal_shares.get("arin"), Some(&25));
+
+        let config = Config::from_config_file(
+            ConfigFile::parse(
+                "repository-dir = \"/repodir\"\n\
+                 tal-shares = [\n\
+                     [\"apnic\", \"30%\"],\n\
+                 ]\n",
+                Path::new("/test/routinator.conf")
+            ).unwrap(),
+            None
+        ).unwrap();
+        assert_eq!(config.tal_shares.get("apnic"), Some(&30));
+        let file = ConfigFile::parse(
+            &format!("{}", config), Path::new("/test/routinator.conf")
+        ).unwrap();
+        assert_eq!(config, Config::from_config_file(file, None).unwrap());
+
+        assert!(Config::from_config_file(
+            ConfigFile::parse(
+                "repository-dir = \"/repodir\"\n\
+                 tal-shares = [[\"apnic\", \"60\"], [\"ripe\", \"60\"]]\n",
+                Path::new("/test/routinator.conf")
+            ).unwrap(),
+            None
+        ).is_err());
+        assert!(parse_tal_share("0").is_err());
+        assert!(parse_tal_share("101%").is_err());
+        assert!(parse_tal_share("half").is_err());
+    }
+
+    #[test]
+    fn tal_priorities() {
+        let config = process_basic_args(&[
+            "routinator", "--tal-priority", "ripe=10",
+            "--tal-priority", "arin=-5",
+        ]);
+        assert_eq!(config.tal_priorities.get("ripe"), Some(&10));
+        assert_eq!(config.tal_priorities.get("arin"), Some(&-5));
+
+        let config = Config::from_config_file(
+            ConfigFile::parse(
+                "repository-dir = \"/repodir\"\n\
+                 tal-priorities = [[\"apnic\", \"3\"]]\n",
+                Path::new("/test/routinator.conf")
+            ).unwrap(),
+            None
+        ).unwrap();
+        assert_eq!(config.tal_priorities.get("apnic"), Some(&3));
+        let file = ConfigFile::parse(
+            &format!("{}", config), Path::new("/test/routinator.conf")
+        ).unwrap();
+        assert_eq!(config, Config::from_config_file(file, None).unwrap());
+
+        assert!(Config::from_config_file(
+            ConfigFile::parse(
+                "repository-dir = \"/repodir\"\n\
+                 tal-priorities = [[\"apnic\", \"high\"]]\n",
+                Path::new("/test/routinator.conf")
+            ).unwrap(),
+            None
+        ).is_err());
+    }
+
     #[test]
     fn verbosity() {
         let config = process_basic_args(&["routinator"]);
@@ -2791,16 +6706,26 @@ mod test {
     fn server_args() {
         let config = process_server_args(&[
             "routinator", "--refresh", "7", "--retry", "8", "--expire", "9",
-            "--history", "1000",
+            "--rrdp-heartbeat", "30", "--history", "1000",
+            "--snapshot-history", "5", "--fetch-spread", "6",
+            "--min-serial-interval", "120",
             "--rtr", "[2001:db8::4]:323",
             "--rtr", "192.0.2.4:323",
             "--http", "192.0.2.4:8080",
+            "--http-refresh-token", "env:ROUTINATOR_TOKEN",
+            "--http-tal-token", "env:ROUTINATOR_TAL_TOKEN",
             "--systemd-listen",
         ]);
         assert_eq!(config.refresh, Duration::from_secs(7));
+        assert_eq!(config.rrdp_heartbeat, Some(Duration::from_secs(30)));
+        assert_eq!(config.fetch_spread, Some(6));
         assert_eq!(config.retry, Duration::from_secs(8));
         assert_eq!(config.expire, Duration::from_secs(9));
         assert_eq!(config.history_size, 1000);
+        assert_eq!(config.snapshot_history, 5);
+        assert_eq!(
+            config.min_serial_interval, Some(Duration::from_secs(120))
+        );
         assert_eq!(
             config.rtr_listen,
             vec![
@@ -2812,6 +6737,14 @@ mod test {
             config.http_listen,
             vec![SocketAddr::from_str("192.0.2.4:8080").unwrap()]
         );
+        assert_eq!(
+            config.http_refresh_token,
+            Some(Secret::Env("ROUTINATOR_TOKEN".into()))
+        );
+        assert_eq!(
+            config.http_tal_token,
+            Some(Secret::Env("ROUTINATOR_TAL_TOKEN".into()))
+        );
         assert!(config.systemd_listen);
     }
     
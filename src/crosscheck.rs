@@ -0,0 +1,249 @@
+//! Cross-checking the collected data against the store.
+//!
+//! The store only keeps the data of a publication point that passed
+//! validation, while the collector always has whatever was last published.
+//! When objects are unexpectedly missing from the output, it helps to know
+//! where the two differ.
+//!
+//! For every publication point in the store, the cross-check compares the
+//! objects the store has accepted with the collector’s local copy. The
+//! latter consists of the collected manifest and the files listed on it.
+//! Objects present in only one of the two and objects with different
+//! content are reported as [`DivergenceMetrics`]. The collector is not
+//! updated during a cross-check.
+
+use std::collections::HashMap;
+use bytes::Bytes;
+use log::{debug, warn};
+use rpki::crypto::DigestAlgorithm;
+use rpki::repository::manifest::Manifest;
+use rpki::uri;
+use crate::collector::Collector;
+use crate::error::{Failed, Fatal};
+use crate::metrics::{DivergenceKind, DivergenceMetrics, DivergentObject};
+use crate::store::Store;
+use crate::utils::str::append_hex;
+
+
+//------------ crosscheck ----------------------------------------------------
+
+/// Compares the local copy of the collector with the store.
+///
+/// The `strict` flag is used when decoding the collected manifests.
+#[allow(clippy::mutable_key_type)]
+pub fn crosscheck(
+    store: &Store, collector: &Collector, strict: bool
+) -> Result<DivergenceMetrics, Failed> {
+    let mut res = DivergenceMetrics::default();
+    for point in store.iter_points() {
+        let mut point = point?;
+        let manifest = match point.take_manifest() {
+            Some(manifest) => manifest,
+            None => continue,
+        };
+        let repository = collector.local_repository(
+            if point.is_rrdp() { manifest.rpki_notify() } else { None }
+        )?;
+        let load = |uri: &uri::Rsync| {
+            match repository.as_ref() {
+                Some(repository) => repository.load_object(uri),
+                None => Ok(None)
+            }
+        };
+
+        let mut stored = HashMap::new();
+        stored.insert(
+            manifest.manifest_uri().clone(), manifest.manifest().clone()
+        );
+        stored.insert(manifest.crl_uri().clone(), manifest.crl().clone());
+        while let Some(object) = point.next() {
+            match object {
+                Ok(object) => {
+                    stored.insert(
+                        object.uri().clone(), object.into_content()
+                    );
+                }
+                Err(err) => {
+                    warn!(
+                        "Failed to read stored point {}: {}",
+                        point.path().display(), err
+                    );
+                    break
+                }
+            }
+        }
+
+        let mut listed = vec![manifest.manifest_uri().clone()];
+        if let Some(collected) = load(manifest.manifest_uri())? {
+            match Manifest::decode(collected, strict) {
+                Ok(collected) => {
+                    listed.extend(
+                        collected.content().iter_uris(
+                            manifest.ca_repository()
+                        ).map(|(uri, _)| uri)
+                    );
+                }
+                Err(_) => {
+                    debug!(
+                        "{}: failed to decode collected manifest.",
+                        manifest.manifest_uri()
+                    );
+                }
+            }
+        }
+
+        compare(
+            manifest.manifest_uri(), stored, listed, load, &mut res.objects
+        )?;
+        res.points += 1;
+    }
+    res.objects.sort_by(|left, right| {
+        (left.manifest_uri.as_str(), left.uri.as_str()).cmp(
+            &(right.manifest_uri.as_str(), right.uri.as_str())
+        )
+    });
+    Ok(res)
+}
+
+
+//------------ Helper Functions ----------------------------------------------
+
+/// Compares the objects of a single publication point.
+///
+/// The objects accepted by the store are given via `stored`. The URIs of
+/// the objects the collector should have are given via `listed`. The
+/// collector’s copy of an object is loaded via `load`. Any differences are
+/// appended to `target`.
+#[allow(clippy::mutable_key_type)]
+fn compare(
+    manifest_uri: &uri::Rsync,
+    mut stored: HashMap<uri::Rsync, Bytes>,
+    listed: Vec<uri::Rsync>,
+    load: impl Fn(&uri::Rsync) -> Result<Option<Bytes>, Fatal>,
+    target: &mut Vec<DivergentObject>,
+) -> Result<(), Fatal> {
+    let mut push = |
+        uri: uri::Rsync, kind: DivergenceKind,
+        collected: Option<&Bytes>, stored: Option<&Bytes>
+    | {
+        target.push(DivergentObject {
+            manifest_uri: manifest_uri.clone(),
+            uri, kind,
+            collector_hash: collected.map(|content| hash(content)),
+            store_hash: stored.map(|content| hash(content)),
+        })
+    };
+
+    for uri in listed {
+        let collected = load(&uri)?;
+        match (collected, stored.remove(&uri)) {
+            (Some(collected), Some(stored)) => {
+                if collected != stored {
+                    push(
+                        uri, DivergenceKind::HashMismatch,
+                        Some(&collected), Some(&stored)
+                    );
+                }
+            }
+            (Some(collected), None) => {
+                push(
+                    uri, DivergenceKind::CollectorOnly, Some(&collected), None
+                );
+            }
+            (None, Some(stored)) => {
+                push(uri, DivergenceKind::StoreOnly, None, Some(&stored));
+            }
+            (None, None) => { }
+        }
+    }
+
+    // What’s left in stored isn’t on the collected manifest. The collector
+    // may still have it, though.
+    for (uri, stored) in stored {
+        match load(&uri)? {
+            Some(collected) if collected == stored => { }
+            Some(collected) => {
+                push(
+                    uri, DivergenceKind::HashMismatch,
+                    Some(&collected), Some(&stored)
+                );
+            }
+            None => {
+                push(uri, DivergenceKind::StoreOnly, None, Some(&stored));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the hex-encoded SHA-256 hash of some content.
+fn hash(content: &[u8]) -> String {
+    let mut res = String::new();
+    append_hex(
+        DigestAlgorithm::sha256().digest(content).as_ref(), &mut res
+    );
+    res
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    fn uri(name: &str) -> uri::Rsync {
+        uri::Rsync::from_str(
+            &format!("rsync://example.net/repo/{}", name)
+        ).unwrap()
+    }
+
+    #[test]
+    #[allow(clippy::mutable_key_type)]
+    fn compare_point() {
+        let collected: HashMap<_, _> = [
+            ("ca.mft", "mft"), ("ca.crl", "crl"), ("a.roa", "a"),
+            ("b.roa", "b-new"), ("c.roa", "c"), ("e.roa", "e"),
+        ].into_iter().map(|(name, content)| {
+            (uri(name), Bytes::from(content))
+        }).collect();
+        let stored = [
+            ("ca.mft", "mft"), ("ca.crl", "crl"), ("b.roa", "b-old"),
+            ("d.roa", "d"), ("e.roa", "e"),
+        ].into_iter().map(|(name, content)| {
+            (uri(name), Bytes::from(content))
+        }).collect();
+        let listed = ["ca.mft", "ca.crl", "a.roa", "b.roa", "c.roa"];
+
+        let mut target = Vec::new();
+        compare(
+            &uri("ca.mft"), stored,
+            listed.into_iter().map(uri).collect(),
+            |uri| Ok(collected.get(uri).cloned()),
+            &mut target
+        ).unwrap();
+        target.sort_by(|left, right| {
+            left.uri.as_str().cmp(right.uri.as_str())
+        });
+        let res: Vec<_> = target.iter().map(|item| {
+            (item.uri.as_str(), item.kind)
+        }).collect();
+        assert_eq!(
+            res,
+            [
+                ("rsync://example.net/repo/a.roa",
+                    DivergenceKind::CollectorOnly),
+                ("rsync://example.net/repo/b.roa",
+                    DivergenceKind::HashMismatch),
+                ("rsync://example.net/repo/c.roa",
+                    DivergenceKind::CollectorOnly),
+                ("rsync://example.net/repo/d.roa",
+                    DivergenceKind::StoreOnly),
+            ]
+        );
+        assert_eq!(target[1].collector_hash, Some(hash(b"b-new")));
+        assert_eq!(target[1].store_hash, Some(hash(b"b-old")));
+        assert_eq!(target[3].collector_hash, None);
+    }
+}
@@ -0,0 +1,667 @@
+//! Rendering RPKI objects as JSON.
+//!
+//! This module allows turning the objects published in the RPKI – resource
+//! certificates, manifests, CRLs, and ROAs – into a JSON representation
+//! that includes all the information relevant to the RPKI. This makes it
+//! possible to inspect objects without having to resort to generic ASN.1
+//! tools.
+//!
+//! The central function is [`render_json`] which decodes an object of a
+//! given [`ObjectType`] and renders it. The rendered JSON is used by the
+//! `decode` command and the object endpoint of the HTTP server.
+//!
+//! Objects are only decoded, not validated. In particular, signatures are
+//! not checked.
+
+use std::{error, fmt, str};
+use std::str::FromStr;
+use bytes::Bytes;
+use chrono::{NaiveDateTime, TimeZone, Utc};
+use rpki::repository::cert::{Cert, KeyUsage, Overclaim};
+use rpki::repository::crl::Crl;
+use rpki::repository::manifest::Manifest;
+use rpki::repository::resources::{AsResources, IpResources};
+use rpki::repository::roa::Roa;
+use rpki::repository::x509::{Serial, Time};
+use rpki::crypto::PublicKeyFormat;
+use crate::utils::json::JsonBuilder;
+use crate::utils::str::append_hex;
+
+
+//------------ ObjectType ----------------------------------------------------
+
+/// The type of an RPKI object.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ObjectType {
+    /// A resource certificate.
+    Cer,
+
+    /// A certificate revocation list.
+    Crl,
+
+    /// A manifest.
+    Mft,
+
+    /// A route origin authorization.
+    Roa,
+}
+
+impl ObjectType {
+    /// All object types in the order they are tried when guessing.
+    const ALL: [ObjectType; 4] = [
+        ObjectType::Cer, ObjectType::Crl, ObjectType::Mft, ObjectType::Roa
+    ];
+
+    /// Determines the object type from the extension of a file name.
+    ///
+    /// The name can be a path or URI. Returns `None` if the extension is
+    /// missing or unknown.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let (_, ext) = name.rsplit_once('.')?;
+        Self::from_str(&ext.to_ascii_lowercase()).ok()
+    }
+
+    /// Returns the file extension for the object type.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ObjectType::Cer => "cer",
+            ObjectType::Crl => "crl",
+            ObjectType::Mft => "mft",
+            ObjectType::Roa => "roa",
+        }
+    }
+}
+
+impl FromStr for ObjectType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cer" => Ok(ObjectType::Cer),
+            "crl" => Ok(ObjectType::Crl),
+            "mft" => Ok(ObjectType::Mft),
+            "roa" => Ok(ObjectType::Roa),
+            _ => Err(format!("unknown object type '{}'", s))
+        }
+    }
+}
+
+impl fmt::Display for ObjectType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+
+//------------ render_json ---------------------------------------------------
+
+/// Decodes an object and renders it as JSON.
+///
+/// If `object_type` is `None`, all known object types are tried in turn
+/// and the first one that decodes successfully is used.
+pub fn render_json(
+    data: Bytes, object_type: Option<ObjectType>, strict: bool,
+) -> Result<String, DecodeError> {
+    match object_type {
+        Some(object_type) => render_type(data, object_type, strict),
+        None => {
+            ObjectType::ALL.iter().find_map(|object_type| {
+                render_type(data.clone(), *object_type, strict).ok()
+            }).ok_or(DecodeError { object_type: None })
+        }
+    }
+}
+
+/// Decodes an object of the given type and renders it as JSON.
+fn render_type(
+    data: Bytes, object_type: ObjectType, strict: bool,
+) -> Result<String, DecodeError> {
+    let err = DecodeError { object_type: Some(object_type) };
+    match object_type {
+        ObjectType::Cer => {
+            let cert = Cert::decode(data.clone()).map_err(|_| err)?;
+            Ok(JsonBuilder::build(|json| {
+                json.member_str("type", "certificate");
+                render_cert(json, &cert, Some(&data));
+            }))
+        }
+        ObjectType::Crl => {
+            let crl = Crl::decode(data.clone()).map_err(|_| err)?;
+            Ok(JsonBuilder::build(|json| {
+                json.member_str("type", "crl");
+                render_crl(json, &crl, &data);
+            }))
+        }
+        ObjectType::Mft => {
+            let mft = Manifest::decode(data.clone(), strict).map_err(|_| err)?;
+            Ok(JsonBuilder::build(|json| {
+                json.member_str("type", "manifest");
+                render_manifest(json, &mft, &data);
+            }))
+        }
+        ObjectType::Roa => {
+            let roa = Roa::decode(data.clone(), strict).map_err(|_| err)?;
+            Ok(JsonBuilder::build(|json| {
+                json.member_str("type", "roa");
+                render_roa(json, &roa, &data);
+            }))
+        }
+    }
+}
+
+
+//------------ Rendering the Object Types ------------------------------------
+
+fn render_cert(json: &mut JsonBuilder, cert: &Cert, data: Option<&[u8]>) {
+    let names = data.and_then(cert_names);
+    json.member_str("serialNumber", cert.serial_number());
+    render_name(json, "issuer", names.as_ref().map(|x| x.0.as_str()));
+    render_name(json, "subject", names.as_ref().map(|x| x.1.as_str()));
+    json.member_str("notBefore", time(cert.validity().not_before()));
+    json.member_str("notAfter", time(cert.validity().not_after()));
+    json.member_str(
+        "publicKeyAlgorithm",
+        match cert.subject_public_key_info().algorithm() {
+            PublicKeyFormat::Rsa => "rsa",
+            PublicKeyFormat::EcdsaP256 => "ecdsa-p256",
+        }
+    );
+    json.member_str("subjectKeyIdentifier", cert.subject_key_identifier());
+    render_opt(
+        json, "authorityKeyIdentifier", cert.authority_key_identifier()
+    );
+    match cert.basic_ca() {
+        Some(ca) => json.member_raw("basicCa", ca),
+        None => json.member_raw("basicCa", "null"),
+    }
+    json.member_str(
+        "keyUsage",
+        match cert.key_usage() {
+            KeyUsage::Ca => "ca",
+            KeyUsage::Ee => "ee",
+        }
+    );
+    json.member_raw(
+        "extendedKeyUsage", cert.extended_key_usage().is_some()
+    );
+    render_opt(json, "crlUri", cert.crl_uri());
+    render_opt(json, "caIssuer", cert.ca_issuer());
+    render_opt(json, "caRepository", cert.ca_repository());
+    render_opt(json, "rpkiManifest", cert.rpki_manifest());
+    render_opt(json, "signedObject", cert.signed_object());
+    render_opt(json, "rpkiNotify", cert.rpki_notify());
+    json.member_str(
+        "overclaim",
+        match cert.overclaim() {
+            Overclaim::Refuse => "refuse",
+            Overclaim::Trim => "trim",
+        }
+    );
+    render_ip_resources(json, "ipv4Resources", cert.v4_resources(), true);
+    render_ip_resources(json, "ipv6Resources", cert.v6_resources(), false);
+    render_as_resources(json, "asResources", cert.as_resources());
+}
+
+fn render_crl(json: &mut JsonBuilder, crl: &Crl, data: &[u8]) {
+    let content = crl_content(data);
+    render_name(
+        json, "issuer", content.as_ref().map(|x| x.issuer.as_str())
+    );
+    json.member_str("thisUpdate", time(crl.this_update()));
+    json.member_str("nextUpdate", time(crl.next_update()));
+    json.member_str(
+        "authorityKeyIdentifier", crl.authority_key_identifier()
+    );
+    json.member_str("crlNumber", crl.crl_number());
+    json.member_array("revokedCertificates", |json| {
+        if let Some(content) = content.as_ref() {
+            for (serial, date) in &content.revoked {
+                json.array_object(|json| {
+                    json.member_str("serialNumber", serial);
+                    json.member_str("revocationDate", date);
+                })
+            }
+        }
+    });
+}
+
+fn render_manifest(json: &mut JsonBuilder, mft: &Manifest, data: &[u8]) {
+    let content = mft.content();
+    json.member_str("manifestNumber", content.manifest_number());
+    json.member_str("thisUpdate", time(content.this_update()));
+    json.member_str("nextUpdate", time(content.next_update()));
+    json.member_str("fileHashAlgorithm", "sha256");
+    json.member_array("files", |json| {
+        for item in content.iter() {
+            json.array_object(|json| {
+                json.member_str(
+                    "name", String::from_utf8_lossy(item.file())
+                );
+                json.member_str("hash", hex(item.hash()));
+            })
+        }
+    });
+    json.member_object("eeCertificate", |json| {
+        render_cert(json, mft.cert(), signed_object_cert(data))
+    });
+}
+
+fn render_roa(json: &mut JsonBuilder, roa: &Roa, data: &[u8]) {
+    let content = roa.content();
+    json.member_str("asId", content.as_id());
+    json.member_array("prefixes", |json| {
+        let v4 = content.v4_addrs().iter().map(|addr| {
+            let prefix = addr.prefix();
+            (format!("{}/{}", prefix.to_v4(), prefix.addr_len()), addr)
+        });
+        let v6 = content.v6_addrs().iter().map(|addr| {
+            let prefix = addr.prefix();
+            (format!("{}/{}", prefix.to_v6(), prefix.addr_len()), addr)
+        });
+        for (prefix, addr) in v4.chain(v6) {
+            json.array_object(|json| {
+                json.member_str("prefix", prefix);
+                match addr.max_length() {
+                    Some(len) => json.member_raw("maxLength", len),
+                    None => json.member_raw("maxLength", "null"),
+                }
+            })
+        }
+    });
+    json.member_object("eeCertificate", |json| {
+        render_cert(json, roa.cert(), signed_object_cert(data))
+    });
+}
+
+
+//------------ Rendering Helpers ---------------------------------------------
+
+fn render_name(json: &mut JsonBuilder, key: &str, name: Option<&str>) {
+    render_opt(json, key, name)
+}
+
+fn render_opt(
+    json: &mut JsonBuilder, key: &str, value: Option<impl fmt::Display>
+) {
+    match value {
+        Some(value) => json.member_str(key, value),
+        None => json.member_raw(key, "null"),
+    }
+}
+
+fn render_ip_resources(
+    json: &mut JsonBuilder, key: &str, res: &IpResources, v4: bool
+) {
+    if res.is_inherited() {
+        json.member_str(key, "inherit");
+        return
+    }
+    match res.to_blocks() {
+        Ok(blocks) if res.is_present() => {
+            json.member_array(key, |json| {
+                for block in blocks.iter() {
+                    if v4 {
+                        json.array_str(block.display_v4())
+                    }
+                    else {
+                        json.array_str(block.display_v6())
+                    }
+                }
+            })
+        }
+        _ => json.member_raw(key, "null")
+    }
+}
+
+fn render_as_resources(
+    json: &mut JsonBuilder, key: &str, res: &AsResources
+) {
+    if res.is_inherited() {
+        json.member_str(key, "inherit");
+        return
+    }
+    match res.to_blocks() {
+        Ok(blocks) if res.is_present() => {
+            json.member_array(key, |json| {
+                for block in blocks.iter() {
+                    json.array_str(block)
+                }
+            })
+        }
+        _ => json.member_raw(key, "null")
+    }
+}
+
+fn time(time: Time) -> String {
+    time.to_rfc3339()
+}
+
+fn hex(data: &[u8]) -> String {
+    let mut res = String::with_capacity(data.len() * 2);
+    append_hex(data, &mut res);
+    res
+}
+
+
+//------------ DER Walking ---------------------------------------------------
+//
+// The rpki crate doesn’t give access to distinguished names or the entries
+// of a CRL, so we need to dig those out of the DER encoding ourselves. The
+// objects have already been decoded successfully at this point, so we only
+// need to deal with well-formed DER and simply bail out otherwise.
+
+/// Returns the encoded EE certificate of an encoded signed object.
+fn signed_object_cert(data: &[u8]) -> Option<&[u8]> {
+    let mut info = Der::new(data).take(TAG_SEQUENCE)?;
+    info.take(TAG_OID)?; // contentType
+    let mut signed = info.take(TAG_EXPLICIT_0)?.take(TAG_SEQUENCE)?;
+    signed.take(TAG_INTEGER)?; // version
+    signed.take(TAG_SET)?; // digestAlgorithms
+    signed.take(TAG_SEQUENCE)?; // encapContentInfo
+    let certs = signed.take(TAG_EXPLICIT_0)?; // certificates
+    let mut cert = certs;
+    cert.take(TAG_SEQUENCE)?;
+    Some(&certs.data[..certs.data.len() - cert.data.len()])
+}
+
+/// Returns the issuer and subject names of an encoded certificate.
+fn cert_names(data: &[u8]) -> Option<(String, String)> {
+    let mut cert = Der::new(data).take(TAG_SEQUENCE)?;
+    let mut tbs = cert.take(TAG_SEQUENCE)?;
+    tbs.skip_opt(TAG_EXPLICIT_0); // version
+    tbs.take(TAG_INTEGER)?; // serialNumber
+    tbs.take(TAG_SEQUENCE)?; // signature
+    let issuer = tbs.take(TAG_SEQUENCE)?.name()?;
+    tbs.take(TAG_SEQUENCE)?; // validity
+    let subject = tbs.take(TAG_SEQUENCE)?.name()?;
+    Some((issuer, subject))
+}
+
+/// The parts of a CRL not accessible via the rpki crate.
+struct CrlContent {
+    /// The issuer name.
+    issuer: String,
+
+    /// The serial numbers and revocation dates of revoked certificates.
+    revoked: Vec<(Serial, String)>,
+}
+
+/// Returns the issuer and revoked certificates of an encoded CRL.
+fn crl_content(data: &[u8]) -> Option<CrlContent> {
+    let mut crl = Der::new(data).take(TAG_SEQUENCE)?;
+    let mut tbs = crl.take(TAG_SEQUENCE)?;
+    tbs.skip_opt(TAG_INTEGER); // version
+    tbs.take(TAG_SEQUENCE)?; // signature
+    let issuer = tbs.take(TAG_SEQUENCE)?.name()?;
+    tbs.take_time()?; // thisUpdate
+    let mut revoked = Vec::new();
+    let mut list = match tbs.peek_tag() {
+        Some(tag) if tag == TAG_UTC_TIME || tag == TAG_GENERALIZED_TIME => {
+            tbs.take_time()?; // nextUpdate
+            tbs.take_opt(TAG_SEQUENCE)
+        }
+        _ => tbs.take_opt(TAG_SEQUENCE)
+    };
+    if let Some(list) = list.as_mut() {
+        while !list.is_empty() {
+            let mut entry = list.take(TAG_SEQUENCE)?;
+            let serial = entry.take(TAG_INTEGER)?.data;
+            let serial = Serial::from_slice(strip_zeros(serial)).ok()?;
+            revoked.push((serial, time(entry.take_time()?)));
+        }
+    }
+    Some(CrlContent { issuer, revoked })
+}
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OID: u8 = 0x06;
+const TAG_UTC_TIME: u8 = 0x17;
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_SET: u8 = 0x31;
+const TAG_EXPLICIT_0: u8 = 0xA0;
+
+/// A cursor over a sequence of DER-encoded values.
+#[derive(Clone, Copy)]
+struct Der<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Der<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Der { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn peek_tag(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    /// Takes the next value, returning its tag and content.
+    fn take_any(&mut self) -> Option<(u8, Der<'a>)> {
+        let (&tag, data) = self.data.split_first()?;
+        let (&first, mut data) = data.split_first()?;
+        let len = if first < 0x80 {
+            usize::from(first)
+        }
+        else {
+            let octets = usize::from(first & 0x7F);
+            if octets == 0 || octets > 4 || data.len() < octets {
+                return None
+            }
+            let (len, rest) = data.split_at(octets);
+            data = rest;
+            len.iter().fold(0usize, |res, ch| (res << 8) | usize::from(*ch))
+        };
+        if data.len() < len {
+            return None
+        }
+        let (content, rest) = data.split_at(len);
+        self.data = rest;
+        Some((tag, Der::new(content)))
+    }
+
+    /// Takes the next value which must have the given tag.
+    fn take(&mut self, tag: u8) -> Option<Der<'a>> {
+        match self.take_any()? {
+            (found, content) if found == tag => Some(content),
+            _ => None
+        }
+    }
+
+    /// Takes the next value if it has the given tag.
+    fn take_opt(&mut self, tag: u8) -> Option<Der<'a>> {
+        if self.peek_tag() == Some(tag) {
+            self.take(tag)
+        }
+        else {
+            None
+        }
+    }
+
+    /// Skips the next value if it has the given tag.
+    fn skip_opt(&mut self, tag: u8) {
+        let _ = self.take_opt(tag);
+    }
+
+    /// Takes a UTCTime or GeneralizedTime value.
+    fn take_time(&mut self) -> Option<Time> {
+        let (tag, content) = self.take_any()?;
+        let content = str::from_utf8(content.data).ok()?;
+        let content = match tag {
+            TAG_UTC_TIME => {
+                // Years 50 to 99 are 19xx, the rest is 20xx.
+                let century = if content.get(..2)? >= "50" { "19" }
+                              else { "20" };
+                format!("{}{}", century, content)
+            }
+            TAG_GENERALIZED_TIME => content.into(),
+            _ => return None
+        };
+        NaiveDateTime::parse_from_str(&content, "%Y%m%d%H%M%SZ").ok().map(
+            |dt| Time::new(Utc.from_utc_datetime(&dt))
+        )
+    }
+
+    /// Interprets the content as a distinguished name.
+    ///
+    /// Returns the name in the form familiar from LDAP, e.g.,
+    /// `CN=foo,SERIALNUMBER=bar`. Attribute types not known are given as
+    /// dotted OIDs.
+    fn name(mut self) -> Option<String> {
+        let mut res = String::new();
+        while !self.is_empty() {
+            let mut set = self.take(TAG_SET)?;
+            while !set.is_empty() {
+                let mut attr = set.take(TAG_SEQUENCE)?;
+                let oid = attr.take(TAG_OID)?;
+                let (_, value) = attr.take_any()?;
+                if !res.is_empty() {
+                    res.push(',');
+                }
+                match oid.data {
+                    [0x55, 0x04, 0x03] => res.push_str("CN"),
+                    [0x55, 0x04, 0x05] => res.push_str("SERIALNUMBER"),
+                    [0x55, 0x04, 0x06] => res.push('C'),
+                    [0x55, 0x04, 0x0A] => res.push('O'),
+                    [0x55, 0x04, 0x0B] => res.push_str("OU"),
+                    data => res.push_str(&oid_str(data)?),
+                }
+                res.push('=');
+                res.push_str(&String::from_utf8_lossy(value.data));
+            }
+        }
+        Some(res)
+    }
+}
+
+/// Returns the dotted representation of an encoded OID.
+fn oid_str(data: &[u8]) -> Option<String> {
+    let mut components = Vec::new();
+    let mut value = 0u64;
+    for &ch in data {
+        value = value.checked_mul(128)? | u64::from(ch & 0x7F);
+        if ch & 0x80 == 0 {
+            components.push(value);
+            value = 0;
+        }
+    }
+    let (&first, rest) = components.split_first()?;
+    let mut res = if first < 80 {
+        format!("{}.{}", first / 40, first % 40)
+    }
+    else {
+        format!("2.{}", first - 80)
+    };
+    for item in rest {
+        res.push_str(&format!(".{}", item));
+    }
+    Some(res)
+}
+
+/// Strips leading zero octets from an integer but keeps at least one.
+fn strip_zeros(mut data: &[u8]) -> &[u8] {
+    while data.len() > 1 && data[0] == 0 {
+        data = &data[1..]
+    }
+    data
+}
+
+
+//------------ DecodeError ---------------------------------------------------
+
+/// An object could not be decoded.
+#[derive(Clone, Copy, Debug)]
+pub struct DecodeError {
+    /// The object type we tried or `None` if we tried all of them.
+    object_type: Option<ObjectType>,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.object_type {
+            Some(object_type) => {
+                write!(f, "failed to decode object of type '{}'", object_type)
+            }
+            None => f.write_str("failed to decode object as any known type")
+        }
+    }
+}
+
+impl error::Error for DecodeError { }
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const REPO: &str = "test/self-test/repository/rpki.selftest.invalid/repo";
+
+    fn render(path: &str) -> serde_json::Value {
+        let data = std::fs::read(format!("{}/{}", REPO, path)).unwrap();
+        serde_json::from_str(
+            &render_json(
+                data.into(), ObjectType::from_name(path), false
+            ).unwrap()
+        ).unwrap()
+    }
+
+    #[test]
+    fn object_type_from_name() {
+        assert_eq!(
+            ObjectType::from_name("rsync://foo/bar/a.ROA"),
+            Some(ObjectType::Roa)
+        );
+        assert_eq!(ObjectType::from_name("foo/bar.gbr"), None);
+        assert_eq!(ObjectType::from_name("foo"), None);
+    }
+
+    #[test]
+    fn render_objects() {
+        let cert = render("ta.cer");
+        assert_eq!(cert["type"], "certificate");
+        assert_eq!(cert["keyUsage"], "ca");
+        assert_eq!(cert["issuer"], cert["subject"]);
+        assert!(cert["subject"].as_str().unwrap().starts_with("CN="));
+        assert!(cert["ipv4Resources"].is_array());
+
+        let crl = render("ta/ta.crl");
+        assert_eq!(crl["type"], "crl");
+        assert_eq!(crl["issuer"], cert["subject"]);
+        assert!(crl["revokedCertificates"].is_array());
+
+        let mft = render("ta/ta.mft");
+        assert_eq!(mft["type"], "manifest");
+        assert!(!mft["files"].as_array().unwrap().is_empty());
+        assert_eq!(mft["eeCertificate"]["keyUsage"], "ee");
+
+        let roa = render("ta/as64496.roa");
+        assert_eq!(roa["type"], "roa");
+        assert_eq!(roa["asId"], "AS64496");
+        assert!(!roa["prefixes"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn guess_object_type() {
+        let data = std::fs::read(format!("{}/ta/ta.mft", REPO)).unwrap();
+        assert!(
+            render_json(data.into(), None, false).unwrap().contains(
+                "\"type\": \"manifest\""
+            )
+        );
+        assert!(render_json(Bytes::from_static(b"foo"), None, false).is_err());
+    }
+
+    #[test]
+    fn der_oid_str() {
+        assert_eq!(
+            oid_str(&[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D]).unwrap(),
+            "1.2.840.113549"
+        );
+    }
+}
@@ -0,0 +1,702 @@
+//! Reading data dumps.
+//!
+//! The `dump` command writes the content of the store and the collector to
+//! a directory. This module allows reading such a dump back in.
+//!
+//! A dump is opened via [`Dump::open`]. The dump directory contains up to
+//! four parts:
+//!
+//! * `store` contains the data from the store. It is accessed through
+//!   [`Dump::store`],
+//! * `rrdp` contains the data of the RRDP collector and is accessed through
+//!   [`Dump::rrdp`],
+//! * `rsync` contains the data of the rsync collector and is accessed
+//!   through [`Dump::rsync`], and
+//! * `ta` contains the stored trust anchor certificates which are accessed
+//!   through [`Dump::ta_certs`].
+//!
+//! The first two contain a number of repositories each, described by a
+//! file `repositories.json`. They are represented by a [`DumpTree`] which
+//! provides access to the individual repositories as [`DumpRepository`]
+//! values. The rsync collector data is always a single rsync repository.
+//!
+//! The objects of a repository are provided as [`DumpObject`]s by iterating
+//! over [`DumpRepository::objects`]. Their rsync URI is reconstructed from
+//! their location in the dump. Objects can be selected via a
+//! [`DumpFilter`].
+
+use std::{fs, io};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use log::error;
+use rpki::uri;
+use serde::Deserialize;
+use crate::error::Failed;
+use crate::utils::fatal;
+
+
+//------------ Dump ----------------------------------------------------------
+
+/// A data dump produced by the `dump` command.
+#[derive(Clone, Debug)]
+pub struct Dump {
+    /// The base directory of the dump.
+    base: PathBuf,
+}
+
+impl Dump {
+    /// Opens the dump in the given directory.
+    ///
+    /// Fails if the directory doesn’t exist.
+    pub fn open(base: impl Into<PathBuf>) -> Result<Self, Failed> {
+        let base = base.into();
+        if !base.is_dir() {
+            error!("Dump directory {} does not exist.", base.display());
+            return Err(Failed)
+        }
+        Ok(Dump { base })
+    }
+
+    /// Returns the base directory of the dump.
+    pub fn base(&self) -> &Path {
+        &self.base
+    }
+
+    /// Returns the content of the store if present in the dump.
+    pub fn store(&self) -> Result<Option<DumpTree>, Failed> {
+        DumpTree::load(self.base.join("store"), DumpSource::Store)
+    }
+
+    /// Returns the content of the RRDP collector if present in the dump.
+    pub fn rrdp(&self) -> Result<Option<DumpTree>, Failed> {
+        DumpTree::load(self.base.join("rrdp"), DumpSource::Rrdp)
+    }
+
+    /// Returns the content of the rsync collector if present in the dump.
+    pub fn rsync(&self) -> Option<DumpRepository> {
+        let path = self.base.join("rsync");
+        if path.is_dir() {
+            Some(DumpRepository {
+                path: path.clone(),
+                root: path,
+                rrdp: None,
+            })
+        }
+        else {
+            None
+        }
+    }
+
+    /// Returns the paths of all trust anchor certificates in the dump.
+    ///
+    /// Since the certificates are stored under a hash of the URI they were
+    /// retrieved from, the URI cannot be reconstructed.
+    pub fn ta_certs(&self) -> Result<Vec<PathBuf>, Failed> {
+        let mut res = Vec::new();
+        let mut dirs = vec![self.base.join("ta")];
+        while let Some(dir) = dirs.pop() {
+            let dir = match fatal::read_existing_dir(&dir)? {
+                Some(dir) => dir,
+                None => continue,
+            };
+            for entry in dir {
+                let entry = entry?;
+                if entry.is_dir() {
+                    dirs.push(entry.into_path())
+                }
+                else if entry.is_file() {
+                    res.push(entry.into_path())
+                }
+            }
+        }
+        res.sort();
+        Ok(res)
+    }
+}
+
+
+//------------ DumpSource ----------------------------------------------------
+
+/// The part of Routinator a dump tree originates from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DumpSource {
+    /// The tree contains the content of the store.
+    Store,
+
+    /// The tree contains the content of the RRDP collector.
+    Rrdp,
+}
+
+
+//------------ DumpTree ------------------------------------------------------
+
+/// A tree of repositories described by a `repositories.json` file.
+#[derive(Clone, Debug)]
+pub struct DumpTree {
+    /// The base directory of the tree.
+    base: PathBuf,
+
+    /// Where does the tree come from?
+    source: DumpSource,
+
+    /// The repositories in the tree.
+    repositories: Vec<DumpRepository>,
+}
+
+impl DumpTree {
+    /// Loads the tree at the given path.
+    ///
+    /// Returns `Ok(None)` if the tree doesn’t exist.
+    pub fn load(
+        base: PathBuf, source: DumpSource
+    ) -> Result<Option<Self>, Failed> {
+        let path = base.join("repositories.json");
+        let json = match fatal::read_existing_file(&path)? {
+            Some(json) => json,
+            None => return Ok(None)
+        };
+        Self::from_json(base, source, &json).map(Some).map_err(|err| {
+            error!("Failed to parse {}: {}", path.display(), err);
+            Failed
+        })
+    }
+
+    /// Creates the tree from the content of `repositories.json`.
+    fn from_json(
+        base: PathBuf, source: DumpSource, json: &[u8]
+    ) -> Result<Self, String> {
+        let json: RepositoriesJson = serde_json::from_slice(
+            json
+        ).map_err(|err| err.to_string())?;
+        let mut repositories = Vec::new();
+        for item in json.repositories {
+            let path = base.join(&item.path);
+            let (root, rrdp) = match item.kind.as_str() {
+                "rsync" => (path.clone(), None),
+                "rrdp" => {
+                    let rpki_notify = match item.rpki_notify {
+                        Some(uri) => {
+                            uri::Https::from_str(&uri).map_err(|err| {
+                                format!("invalid rpkiNotify '{}': {}", uri, err)
+                            })?
+                        }
+                        None => {
+                            return Err(format!(
+                                "RRDP repository {} without rpkiNotify",
+                                item.path
+                            ))
+                        }
+                    };
+                    let updated = match item.updated {
+                        Some(updated) => {
+                            Some(
+                                DateTime::parse_from_rfc3339(
+                                    &updated
+                                ).map_err(|err| {
+                                    format!(
+                                        "invalid update time '{}': {}",
+                                        updated, err
+                                    )
+                                })?.with_timezone(&Utc)
+                            )
+                        }
+                        None => None
+                    };
+                    let root = match source {
+                        // The RRDP collector places the objects under the
+                        // rsync URI of their module.
+                        DumpSource::Rrdp => path.join("rsync").join("rsync:"),
+                        DumpSource::Store => path.clone(),
+                    };
+                    (root, Some(RrdpInfo {
+                        rpki_notify,
+                        session: item.session,
+                        serial: item.serial,
+                        updated,
+                    }))
+                }
+                kind => {
+                    return Err(format!("unknown repository type '{}'", kind))
+                }
+            };
+            repositories.push(DumpRepository { path, root, rrdp });
+        }
+        Ok(DumpTree { base, source, repositories })
+    }
+
+    /// Returns the base directory of the tree.
+    pub fn base(&self) -> &Path {
+        &self.base
+    }
+
+    /// Returns where the tree originates from.
+    pub fn source(&self) -> DumpSource {
+        self.source
+    }
+
+    /// Returns an iterator over the repositories of the tree.
+    pub fn repositories(&self) -> impl Iterator<Item = &DumpRepository> {
+        self.repositories.iter()
+    }
+
+    /// Returns the RRDP repository with the given rpkiNotify URI.
+    pub fn rrdp_repository(
+        &self, rpki_notify: &uri::Https
+    ) -> Option<&DumpRepository> {
+        self.repositories.iter().find(|repo| {
+            repo.rpki_notify() == Some(rpki_notify)
+        })
+    }
+
+    /// Returns the rsync repository of the tree if present.
+    pub fn rsync_repository(&self) -> Option<&DumpRepository> {
+        self.repositories.iter().find(|repo| repo.rrdp.is_none())
+    }
+
+    /// Returns an iterator over all objects in the tree matching `filter`.
+    pub fn objects<'a>(
+        &'a self, filter: &'a DumpFilter
+    ) -> impl Iterator<Item = Result<DumpObject, Failed>> + 'a {
+        self.repositories.iter().filter(|repo| {
+            filter.matches_repository(repo)
+        }).flat_map(move |repo| {
+            repo.objects().filter(move |item| {
+                match item {
+                    Ok(object) => filter.matches_object(object),
+                    Err(_) => true,
+                }
+            })
+        })
+    }
+}
+
+
+//------------ DumpRepository ------------------------------------------------
+
+/// A single repository within a dump.
+#[derive(Clone, Debug)]
+pub struct DumpRepository {
+    /// The directory of the repository as given in `repositories.json`.
+    path: PathBuf,
+
+    /// The directory containing the objects.
+    ///
+    /// Objects are stored under their rsync URI with the `rsync://` prefix
+    /// removed.
+    root: PathBuf,
+
+    /// Information about an RRDP repository.
+    ///
+    /// This is `None` for an rsync repository.
+    rrdp: Option<RrdpInfo>,
+}
+
+impl DumpRepository {
+    /// Returns the directory of the repository.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns whether this is an RRDP repository.
+    pub fn is_rrdp(&self) -> bool {
+        self.rrdp.is_some()
+    }
+
+    /// Returns the rpkiNotify URI of an RRDP repository.
+    pub fn rpki_notify(&self) -> Option<&uri::Https> {
+        self.rrdp.as_ref().map(|rrdp| &rrdp.rpki_notify)
+    }
+
+    /// Returns the RRDP session ID if known.
+    ///
+    /// This is only available for dumps of the RRDP collector.
+    pub fn session(&self) -> Option<&str> {
+        self.rrdp.as_ref().and_then(|rrdp| rrdp.session.as_deref())
+    }
+
+    /// Returns the RRDP serial number if known.
+    ///
+    /// This is only available for dumps of the RRDP collector.
+    pub fn serial(&self) -> Option<u64> {
+        self.rrdp.as_ref().and_then(|rrdp| rrdp.serial)
+    }
+
+    /// Returns the time of the last RRDP update if known.
+    ///
+    /// This is only available for dumps of the RRDP collector.
+    pub fn updated(&self) -> Option<DateTime<Utc>> {
+        self.rrdp.as_ref().and_then(|rrdp| rrdp.updated)
+    }
+
+    /// Returns an iterator over all the objects of the repository.
+    pub fn objects(&self) -> DumpObjects {
+        DumpObjects::new(self.root.clone())
+    }
+}
+
+
+//------------ RrdpInfo ------------------------------------------------------
+
+/// Information about an RRDP repository.
+#[derive(Clone, Debug)]
+struct RrdpInfo {
+    /// The rpkiNotify URI of the repository.
+    rpki_notify: uri::Https,
+
+    /// The session ID of the repository if known.
+    session: Option<String>,
+
+    /// The serial number of the repository if known.
+    serial: Option<u64>,
+
+    /// The time of the last update if known.
+    updated: Option<DateTime<Utc>>,
+}
+
+
+//------------ DumpObjects ---------------------------------------------------
+
+/// An iterator over the objects of a repository in a dump.
+///
+/// Objects with a path that cannot be converted into an rsync URI are
+/// skipped. If accessing the file system fails, the iterator returns an
+/// error once and then ends.
+pub struct DumpObjects {
+    /// The directory containing the objects.
+    root: PathBuf,
+
+    /// The stack of directories currently being walked.
+    dirs: Vec<(PathBuf, fs::ReadDir)>,
+
+    /// Have we started walking yet?
+    started: bool,
+}
+
+impl DumpObjects {
+    /// Creates a new iterator for objects under `root`.
+    fn new(root: PathBuf) -> Self {
+        DumpObjects { root, dirs: Vec::new(), started: false }
+    }
+
+    /// Adds a directory to the walk.
+    fn push_dir(&mut self, path: PathBuf) -> Result<(), Failed> {
+        match fs::read_dir(&path) {
+            Ok(dir) => {
+                self.dirs.push((path, dir));
+                Ok(())
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => {
+                error!(
+                    "Fatal: failed to open directory {}: {}",
+                    path.display(), err
+                );
+                Err(Failed)
+            }
+        }
+    }
+
+    /// Ends the iteration after an error.
+    fn fail(&mut self) -> Option<Result<DumpObject, Failed>> {
+        self.dirs.clear();
+        Some(Err(Failed))
+    }
+
+    /// Converts a path into the rsync URI of an object.
+    fn uri_from_path(&self, path: &Path) -> Option<uri::Rsync> {
+        let relative = path.strip_prefix(&self.root).ok()?.to_str()?;
+        let relative = relative.replace(std::path::MAIN_SEPARATOR, "/");
+        uri::Rsync::from_string(format!("rsync://{}", relative)).ok()
+    }
+}
+
+impl Iterator for DumpObjects {
+    type Item = Result<DumpObject, Failed>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.started {
+            self.started = true;
+            if self.push_dir(self.root.clone()).is_err() {
+                return self.fail()
+            }
+        }
+        loop {
+            let (dir_path, dir) = self.dirs.last_mut()?;
+            let entry = match dir.next() {
+                Some(Ok(entry)) => entry,
+                Some(Err(err)) => {
+                    error!(
+                        "Fatal: failed to read directory {}: {}",
+                        dir_path.display(), err
+                    );
+                    return self.fail()
+                }
+                None => {
+                    self.dirs.pop();
+                    continue
+                }
+            };
+            let path = entry.path();
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(err) => {
+                    error!(
+                        "Fatal: failed to read metadata for {}: {}",
+                        path.display(), err
+                    );
+                    return self.fail()
+                }
+            };
+            if metadata.is_dir() {
+                if self.push_dir(path).is_err() {
+                    return self.fail()
+                }
+            }
+            else if metadata.is_file() {
+                if let Some(uri) = self.uri_from_path(&path) {
+                    return Some(Ok(DumpObject { uri, path }))
+                }
+            }
+        }
+    }
+}
+
+
+//------------ DumpObject ----------------------------------------------------
+
+/// A single object in a dump.
+#[derive(Clone, Debug)]
+pub struct DumpObject {
+    /// The rsync URI of the object.
+    uri: uri::Rsync,
+
+    /// The path of the file containing the object.
+    path: PathBuf,
+}
+
+impl DumpObject {
+    /// Returns the rsync URI of the object.
+    pub fn uri(&self) -> &uri::Rsync {
+        &self.uri
+    }
+
+    /// Returns the path of the file containing the object.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Loads the content of the object.
+    pub fn content(&self) -> Result<Bytes, Failed> {
+        fatal::read_file(&self.path).map(Into::into)
+    }
+}
+
+
+//------------ DumpFilter ----------------------------------------------------
+
+/// A filter selecting objects from a dump tree.
+///
+/// By default, the filter matches all objects. Additional conditions can be
+/// added via its methods. An object has to match all conditions.
+#[derive(Clone, Debug, Default)]
+pub struct DumpFilter {
+    /// Only include RRDP repositories with these rpkiNotify URIs.
+    rpki_notify: Option<Vec<uri::Https>>,
+
+    /// Only include rsync or only RRDP repositories.
+    rrdp: Option<bool>,
+
+    /// Only include objects under this rsync URI.
+    base_uri: Option<uri::Rsync>,
+
+    /// Only include objects with one of these file name extensions.
+    extensions: Option<Vec<String>>,
+}
+
+impl DumpFilter {
+    /// Creates a new filter that matches everything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only includes objects of the given RRDP repository.
+    ///
+    /// If called multiple times, objects from any of the repositories are
+    /// included.
+    pub fn rpki_notify(mut self, uri: uri::Https) -> Self {
+        self.rpki_notify.get_or_insert_with(Vec::new).push(uri);
+        self
+    }
+
+    /// Only includes objects from RRDP or from rsync repositories.
+    pub fn rrdp(mut self, rrdp: bool) -> Self {
+        self.rrdp = Some(rrdp);
+        self
+    }
+
+    /// Only includes objects under the given rsync URI.
+    pub fn base_uri(mut self, uri: uri::Rsync) -> Self {
+        self.base_uri = Some(uri);
+        self
+    }
+
+    /// Only includes objects with the given file name extension.
+    ///
+    /// The extension is given without the leading dot, e.g., `"roa"`. If
+    /// called multiple times, objects with any of the extensions are
+    /// included.
+    pub fn extension(mut self, ext: impl Into<String>) -> Self {
+        self.extensions.get_or_insert_with(Vec::new).push(ext.into());
+        self
+    }
+
+    /// Returns whether objects of the repository may match the filter.
+    pub fn matches_repository(&self, repo: &DumpRepository) -> bool {
+        if let Some(rrdp) = self.rrdp {
+            if repo.is_rrdp() != rrdp {
+                return false
+            }
+        }
+        if let Some(uris) = self.rpki_notify.as_ref() {
+            match repo.rpki_notify() {
+                Some(uri) => {
+                    if !uris.contains(uri) {
+                        return false
+                    }
+                }
+                None => return false
+            }
+        }
+        true
+    }
+
+    /// Returns whether the object matches the filter.
+    ///
+    /// This only checks the conditions on the object itself, not those on
+    /// its repository.
+    pub fn matches_object(&self, object: &DumpObject) -> bool {
+        if let Some(base) = self.base_uri.as_ref() {
+            if !base.is_parent_of(object.uri()) {
+                return false
+            }
+        }
+        if let Some(extensions) = self.extensions.as_ref() {
+            let path = object.uri().path();
+            let matched = extensions.iter().any(|ext| {
+                path.len() > ext.len()
+                && path.ends_with(ext.as_str())
+                && path.as_bytes()[path.len() - ext.len() - 1] == b'.'
+            });
+            if !matched {
+                return false
+            }
+        }
+        true
+    }
+}
+
+
+//------------ RepositoriesJson ----------------------------------------------
+
+/// The content of a `repositories.json` file.
+#[derive(Deserialize)]
+struct RepositoriesJson {
+    repositories: Vec<RepositoryJson>,
+}
+
+/// A single repository in a `repositories.json` file.
+#[derive(Deserialize)]
+struct RepositoryJson {
+    path: String,
+
+    #[serde(rename = "type")]
+    kind: String,
+
+    #[serde(rename = "rpkiNotify")]
+    rpki_notify: Option<String>,
+
+    session: Option<String>,
+
+    serial: Option<u64>,
+
+    updated: Option<String>,
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn write(base: &Path, path: &str, content: &[u8]) {
+        let path = base.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn read_store_dump() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "store/repositories.json", br#"{
+            "repositories": [
+                {
+                    "path": "rrdp.example.net",
+                    "type": "rrdp",
+                    "rpkiNotify": "https://rrdp.example.net/notification.xml"
+                },
+                { "path": "rsync", "type": "rsync" }
+            ]
+        }"#);
+        write(
+            dir.path(),
+            "store/rrdp.example.net/rsync.example.net/repo/ca.mft", b"mft"
+        );
+        write(
+            dir.path(),
+            "store/rrdp.example.net/rsync.example.net/repo/ca.roa", b"roa"
+        );
+        write(
+            dir.path(),
+            "store/rsync/other.example.net/module/ca/ca.crl", b"crl"
+        );
+
+        let dump = Dump::open(dir.path()).unwrap();
+        assert!(dump.rrdp().unwrap().is_none());
+        assert!(dump.rsync().is_none());
+        let tree = dump.store().unwrap().unwrap();
+        assert_eq!(tree.source(), DumpSource::Store);
+        assert_eq!(tree.repositories().count(), 2);
+
+        let notify = uri::Https::from_str(
+            "https://rrdp.example.net/notification.xml"
+        ).unwrap();
+        let repo = tree.rrdp_repository(&notify).unwrap();
+        let mut objects = repo.objects().map(|item| {
+            item.unwrap().uri().to_string()
+        }).collect::<Vec<_>>();
+        objects.sort();
+        assert_eq!(
+            objects,
+            [
+                "rsync://rsync.example.net/repo/ca.mft",
+                "rsync://rsync.example.net/repo/ca.roa",
+            ]
+        );
+
+        let filter = DumpFilter::new().extension("roa");
+        let objects = tree.objects(&filter).map(|item| {
+            item.unwrap()
+        }).collect::<Vec<_>>();
+        assert_eq!(objects.len(), 1);
+        assert_eq!(objects[0].content().unwrap().as_ref(), b"roa");
+
+        let filter = DumpFilter::new().rrdp(false);
+        let objects = tree.objects(&filter).map(|item| {
+            item.unwrap().uri().to_string()
+        }).collect::<Vec<_>>();
+        assert_eq!(objects, ["rsync://other.example.net/module/ca/ca.crl"]);
+    }
+}
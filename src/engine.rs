@@ -20,45 +20,73 @@
 
 use std::{cmp, fmt, fs, thread};
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::File;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 use bytes::Bytes;
 use crossbeam_queue::{ArrayQueue, SegQueue};
 use log::{debug, error, info, warn};
-use rpki::crypto::keys::KeyIdentifier;
-#[allow(unused_imports)]
+use rpki::crypto::keys::{KeyIdentifier, PublicKey};
 use rpki::repository::aspa::{Aspa, AsProviderAttestation};
 use rpki::repository::cert::{Cert, KeyUsage, ResourceCert};
 use rpki::repository::crl::Crl;
-use rpki::repository::error::{InspectionError, ValidationError};
-use rpki::repository::manifest::{Manifest, ManifestContent, ManifestHash};
+use rpki::repository::error::{
+    InspectionError, ValidationError, VerificationError,
+};
+use rpki::repository::manifest::{Manifest, ManifestContent};
 use rpki::repository::roa::{Roa, RouteOriginAttestation};
 use rpki::repository::sigobj::SignedObject;
-use rpki::repository::tal::{Tal, TalInfo, TalUri};
+use rpki::repository::tal::{Tal, TalUri};
 use rpki::repository::x509::{Time, Validity};
 use rpki::uri;
-use crate::{collector, store, tals};
-use crate::config::{Config, FilterPolicy};
-use crate::collector::Collector;
+use crate::{collector, crosscheck, store, tals};
+use crate::config::{Config, FilterPolicy, ResourceValidation, TalPolicy};
+use crate::collector::{
+    Collector, Heartbeat, RefreshTarget, RrdpRepositoryInfo
+};
 use crate::error::{Failed, Fatal, RunFailed};
+use crate::mirror::Mirror;
 use crate::metrics::{
-    Metrics, PublicationMetrics, RepositoryMetrics, TalMetrics
+    CleanupPreview, DivergenceMetrics, LimitKind, LimitViolation, Metrics,
+    MissingFile, MissingFileReason, PublicationMetrics, RejectedObject,
+    RejectionReason, RepositoryMetrics, RepositoryProbe, RunId, StalePoint,
+    TalMetrics, UpdatePreview
+};
+use crate::process::{Process, RunLogGuard};
+use crate::store::{
+    Store, StoredManifest, StoredObject, StoredOutcome, StoredPoint
 };
-use crate::store::{Store, StoredManifest, StoredObject, StoredPoint};
-use crate::utils::str::str_from_ascii;
+use crate::utils::crl::RevocationList;
+use crate::utils::hash::ObjectHash;
+use crate::utils::json::JsonBuilder;
+use crate::utils::str::{append_hex, str_from_ascii};
 
 
 //------------ Configuration -------------------------------------------------
 
-/// The minimum number of manifest entries that triggers CRL serial caching.
+/// The minimum number of manifest entries that triggers CRL serial indexing.
+///
+/// Below this, the CRL is scanned for each lookup. See
+/// [`RevocationList`] for details.
 ///
 /// The value has been determined experimentally with the RPKI repository at
 /// a certain state so may or may not be a good one, really.
 const CRL_CACHE_LIMIT: usize = 50;
 
+/// The minimum time in seconds between recording confirmations of a point.
+///
+/// Confirming that stored data is still current requires a write to the
+/// store. To avoid doing this for every single publication point in every
+/// validation run, a confirmation is only recorded if the last one is older
+/// than this.
+const CONFIRM_INTERVAL: i64 = 3600;
+
+/// The maximum time a single repository probe may take.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
 
 //------------ Engine --------------------------------------------------------
 
@@ -93,28 +121,101 @@ pub struct Engine {
     /// The list of our TALs. 
     tals: Vec<Tal>,
 
+    /// The names of TALs disabled at runtime.
+    ///
+    /// These TALs are skipped during validation runs until they are
+    /// enabled again via [`switch_tal`][Self::switch_tal]. The set isn’t
+    /// persisted. Cleanup keeps their stored data until it expires since
+    /// publication points not visited are judged by their stored manifest.
+    disabled_tals: HashSet<String>,
+
     /// The collector to load updated data from.
     ///
-    /// If this is `None`, updates have been disabled.
-    collector: Option<Collector>,
+    /// If this is `None`, updates have been disabled. The collector is
+    /// shared with the heartbeat which may run on a separate thread.
+    collector: Option<Arc<Collector>>,
 
     /// The store to load stored data from.
     store: Store,
 
+    /// The local mirror to re-publish the stored data through.
+    mirror: Option<Mirror>,
+
+    /// Should we cross-check collector and store after each run?
+    crosscheck: bool,
+
     /// Should we be strict when decoding data?
     strict: bool,
 
-    /// How do we deal with stale objects?
-    stale: FilterPolicy,
+    /// How do we deal with problems with manifests and their files?
+    manifest_policy: ManifestPolicy,
+
+    /// Manifest handling policies for individual TALs.
+    ///
+    /// The keys are TAL names. TALs not present use `manifest_policy`.
+    tal_policies: HashMap<String, ManifestPolicy>,
 
     /// Number of validation threads.
     validation_threads: usize,
 
+    /// The percentage of the validation threads reserved for some TALs.
+    tal_shares: HashMap<String, u8>,
+
+    /// The priority of some TALs when sharing validation threads.
+    tal_priorities: HashMap<String, i32>,
+
     /// Should we leave the repository dirty after a valiation run.
     dirty_repository: bool,
 
+    /// The policy for objects of unknown type.
+    unknown_objects: FilterPolicy,
+
+    /// The report of rejected objects if requested.
+    rejection_report: Option<RejectionReport>,
+
+    /// The cache of successful signature verifications if enabled.
+    signature_cache: Option<SignatureCache>,
+
+    /// The cache of decoded objects if enabled.
+    object_cache: Option<ObjectCache>,
+
     /// Maximum depth of the CA chain.
     max_ca_depth: usize,
+
+    /// Maximum number of child CAs of a single CA.
+    max_ca_children: Option<usize>,
+
+    /// Maximum number of objects on the manifest of a publication point.
+    max_point_objects: Option<usize>,
+
+    /// Maximum time for processing the subtree below a CA.
+    max_ca_time: Option<Duration>,
+
+    /// Maximum age of stored data not confirmed to be current.
+    stored_max_age: Option<Duration>,
+
+    /// Age after which stored data not confirmed to be current is stale.
+    stored_stale_age: Option<Duration>,
+
+    /// The hash of the policy affecting validation outcomes.
+    ///
+    /// This is recorded with the validation outcome of stored publication
+    /// points. See [`policy_hash`][Self::policy_hash] for details.
+    policy_hash: u64,
+
+    /// The serial number of the last validation run started.
+    run_serial: AtomicU64,
+
+    /// Are we validating frozen data?
+    ///
+    /// If so, the hash of the input data is recorded for every run.
+    freeze: bool,
+
+    /// The time to validate against instead of the current time.
+    validation_time: Option<Time>,
+
+    /// The tolerated clock skew in seconds.
+    clock_skew: i64,
 }
 
 impl Engine {
@@ -136,35 +237,203 @@ impl Engine {
     /// It also takes over the provided cache and store for use during
     /// validation.
     ///
+    /// If `update` is `false` or the store is configured to be read-only,
+    /// the collector is not used and validation happens from the data in
+    /// the store only.
+    ///
     /// Loads the initial set of TALs and errors out if that fails.
     pub fn new(
         config: &Config,
         update: bool,
     ) -> Result<Self, Failed> {
-        let collector = if update {
+        let collector = if update && !config.store_readonly {
             Some(Collector::new(config)?)
         }
         else {
             None
         };
+        Self::with_collector(config, collector)
+    }
+
+    /// Creates a new engine that uses the collector in offline mode.
+    ///
+    /// The collector will not access the network but use whatever data is
+    /// present in its rsync working directory as is. This is mainly useful
+    /// for validating locally provided repository data.
+    pub fn new_offline(config: &Config) -> Result<Self, Failed> {
+        Self::with_collector(config, Some(Collector::new_offline(config)?))
+    }
+
+    /// Creates a new engine using the given collector.
+    fn with_collector(
+        config: &Config,
+        collector: Option<Collector>,
+    ) -> Result<Self, Failed> {
         let store = Store::new(config)?;
         let mut res = Engine {
             bundled_tals: tals::collect_tals(config)?,
             extra_tals_dir: config.extra_tals_dir.clone(),
             tal_labels: config.tal_labels.clone(),
             tals: Vec::new(),
-            collector,
+            disabled_tals: HashSet::new(),
+            collector: collector.map(Arc::new),
             store,
+            mirror: Mirror::new(config),
+            crosscheck: config.crosscheck,
             strict: config.strict,
-            stale: config.stale,
+            manifest_policy: ManifestPolicy::from_config(config),
+            tal_policies: {
+                let base = ManifestPolicy::from_config(config);
+                config.tal_policies.iter().map(|(name, policy)| {
+                    (name.clone(), base.with_tal_policy(policy))
+                }).collect()
+            },
             validation_threads: config.validation_threads,
+            tal_shares: config.tal_shares.clone(),
+            tal_priorities: config.tal_priorities.clone(),
             dirty_repository: config.dirty_repository,
+            unknown_objects: config.unknown_objects,
+            rejection_report: RejectionReport::new(config),
+            signature_cache: SignatureCache::new(config),
+            object_cache: ObjectCache::new(config),
             max_ca_depth: config.max_ca_depth,
+            max_ca_children: config.max_ca_children,
+            max_point_objects: config.max_point_objects,
+            max_ca_time: config.max_ca_time,
+            stored_max_age: config.stored_max_age,
+            stored_stale_age: config.stored_stale_age,
+            policy_hash: Self::policy_hash(config),
+            run_serial: AtomicU64::new(0),
+            freeze: config.freeze,
+            validation_time: config.validation_time.map(Time::new),
+            clock_skew: config.clock_skew.map(|skew| {
+                i64::try_from(skew.as_secs()).unwrap_or(i64::MAX)
+            }).unwrap_or(0),
         };
         res.reload_tals()?;
         Ok(res)
     }
 
+    /// Returns the hash of the policy affecting validation outcomes.
+    ///
+    /// The hash covers the Routinator version as well as all configuration
+    /// options that change whether the engine considers a publication point
+    /// valid. If any of these change, earlier validation outcomes can no
+    /// longer be trusted.
+    fn policy_hash(config: &Config) -> u64 {
+        let base = ManifestPolicy::from_config(config);
+        let mut tal_policies = config.tal_policies.iter().map(
+            |(name, policy)| {
+                format!(" tal({}): {}", name, base.with_tal_policy(policy))
+            }
+        ).collect::<Vec<_>>();
+        tal_policies.sort();
+        let policy = format!(
+            "{} strict={} {} unknown-objects={} max-ca-depth={} \
+             max-ca-children={} max-point-objects={} stored-max-age={} \
+             clock-skew={} validation-time={}{}",
+            env!("CARGO_PKG_VERSION"),
+            config.strict, base, config.unknown_objects,
+            config.max_ca_depth,
+            config.max_ca_children.unwrap_or(0),
+            config.max_point_objects.unwrap_or(0),
+            config.stored_max_age.map(|age| age.as_secs()).unwrap_or(0),
+            config.clock_skew.map(|skew| skew.as_secs()).unwrap_or(0),
+            config.validation_time.map(|time| {
+                time.timestamp()
+            }).unwrap_or(0),
+            tal_policies.concat(),
+        );
+        let digest = ring::digest::digest(
+            &ring::digest::SHA256, policy.as_bytes()
+        );
+        let mut res = [0u8; 8];
+        res.copy_from_slice(&digest.as_ref()[..8]);
+        u64::from_be_bytes(res)
+    }
+
+    /// Verifies a signature over `data` made with `key`.
+    ///
+    /// The actual verification is performed by `op`. If a signature cache
+    /// is enabled and a verification of the same data with the same key
+    /// has previously succeeded, `op` is skipped.
+    ///
+    /// This is only used for CRLs. The signatures of certificates and
+    /// signed objects are checked by the rpki crate as part of validating
+    /// them and can’t be separated from it.
+    fn verify_signature<E>(
+        &self, data: &[u8], key: &PublicKey,
+        op: impl FnOnce() -> Result<(), E>,
+    ) -> Result<(), E> {
+        match self.signature_cache.as_ref() {
+            Some(cache) => cache.verify(data, &key.to_info_bytes(), op),
+            None => op()
+        }
+    }
+
+    /// Decodes an object.
+    ///
+    /// The actual decoding is performed by `op`. If an object cache is
+    /// enabled and the same content has been decoded before, `op` is
+    /// skipped.
+    fn decode_object<T: CacheableObject, E>(
+        &self, content: &Bytes,
+        op: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        match self.object_cache.as_ref() {
+            Some(cache) => cache.decode(content, op),
+            None => op()
+        }
+    }
+
+    /// Returns the time to validate objects against.
+    ///
+    /// This is the time given via the `validation-time` option or the
+    /// current time if that option is missing.
+    fn now(&self) -> Time {
+        self.validation_time.unwrap_or_else(Time::now)
+    }
+
+    /// Returns whether `time` is in the future even allowing for skew.
+    ///
+    /// This is used for checking notBefore and thisUpdate times.
+    fn is_premature(&self, time: Time) -> bool {
+        time.timestamp().saturating_sub(
+            self.now().timestamp()
+        ) > self.clock_skew
+    }
+
+    /// Returns the time to validate an object valid from `not_before`.
+    ///
+    /// If the object only becomes valid in the future but within the
+    /// tolerated clock skew, returns `not_before` so the object is
+    /// accepted. Otherwise returns the regular validation time.
+    fn skewed_time(&self, not_before: Time) -> Time {
+        let now = self.now();
+        if not_before > now && !self.is_premature(not_before) {
+            not_before
+        }
+        else {
+            now
+        }
+    }
+
+    /// Returns the time to validate a signed object at if it isn’t now.
+    ///
+    /// ROAs and ASPAs are normally processed by the rpki crate which
+    /// validates them at the current time. If a validation time is
+    /// configured or the EE certificate only becomes valid within the
+    /// tolerated clock skew, they need to be validated at the time returned
+    /// instead.
+    fn signed_object_time(&self, not_before: Time) -> Option<Time> {
+        if self.validation_time.is_none() && not_before <= Time::now() {
+            None
+        }
+        else {
+            Some(self.skewed_time(not_before))
+        }
+    }
+
     /// Reloads the set of TALs.
     ///
     /// Assumes that all regular files with an extension of `tal` in the
@@ -246,11 +515,56 @@ impl Engine {
         res.sort_by(|left, right| {
             left.info().name().cmp(right.info().name())
         });
+        for name in self.tal_shares.keys() {
+            if !res.iter().any(|tal| tal.info().name() == name) {
+                warn!("Ignoring thread share for unknown TAL {}.", name);
+            }
+        }
+        for name in self.tal_priorities.keys() {
+            if !res.iter().any(|tal| tal.info().name() == name) {
+                warn!("Ignoring priority for unknown TAL {}.", name);
+            }
+        }
+        for name in self.tal_policies.keys() {
+            if !res.iter().any(|tal| tal.info().name() == name) {
+                warn!("Ignoring policy for unknown TAL {}.", name);
+            }
+        }
         self.tals = res;
 
         Ok(())
     }
 
+    /// Enables or disables a TAL for future validation runs.
+    ///
+    /// Returns whether the state of the TAL has changed. Disabling a TAL
+    /// that isn’t currently loaded is allowed so that it stays disabled if
+    /// it appears after reloading the TALs.
+    pub fn switch_tal(&mut self, switch: &TalSwitch) -> bool {
+        if !self.tals.iter().any(|tal| tal.info().name() == switch.name) {
+            warn!("TAL {} is not currently loaded.", switch.name);
+        }
+        let res = if switch.enabled {
+            self.disabled_tals.remove(&switch.name)
+        }
+        else {
+            self.disabled_tals.insert(switch.name.clone())
+        };
+        if res {
+            warn!(
+                "TAL {} {} at user request.",
+                switch.name,
+                if switch.enabled { "enabled" } else { "disabled" }
+            );
+        }
+        res
+    }
+
+    /// Returns whether the given TAL has been disabled at runtime.
+    fn is_disabled(&self, tal: &Tal) -> bool {
+        self.disabled_tals.contains(tal.info().name())
+    }
+
     /// Converts a path into a TAL label.
     ///
     /// This will be an explicitly configured TAL label if the file name
@@ -265,13 +579,27 @@ impl Engine {
         path.file_stem().unwrap().to_string_lossy().into_owned()
     }
 
+    /// Returns the manifest handling policy for a CA certificate.
+    ///
+    /// This is the policy configured for the certificate’s TAL if there is
+    /// one or the global policy otherwise.
+    fn manifest_policy(&self, cert: &CaCert) -> ManifestPolicy {
+        self.tal_policies.get(cert.cert().tal().name()).copied().unwrap_or(
+            self.manifest_policy
+        )
+    }
     /// Ignites validation processing.
     ///
     /// This spawns threads and therefore needs to be done after a
     /// possible fork.
+    ///
+    /// This needs to happen before a [`Heartbeat`] is acquired via
+    /// [`heartbeat`][Self::heartbeat].
     pub fn ignite(&mut self) -> Result<(), Failed> {
         if let Some(collector) = self.collector.as_mut() {
-            collector.ignite()?;
+            Arc::get_mut(collector).expect(
+                "igniting a shared collector"
+            ).ignite()?;
         }
         Ok(())
     }
@@ -288,25 +616,185 @@ impl Engine {
         Ok(())
     }
 
+    /// Returns a heartbeat for checking the known RRDP repositories.
+    ///
+    /// The heartbeat only fetches the notification files of the RRDP
+    /// repositories that have been seen before and can be used to decide
+    /// whether to start a validation run early. It can be moved to a
+    /// separate thread. Returns `None` if updating has been disabled.
+    pub fn heartbeat(&self) -> Option<Heartbeat> {
+        self.collector.clone().map(Heartbeat::new)
+    }
+
+    /// Probes whether the known repositories are reachable.
+    ///
+    /// This only does lightweight checks for all the repositories that
+    /// have been seen before, so it can be done frequently between
+    /// validation runs. Returns an empty list if updating has been
+    /// disabled.
+    pub fn probe(&self) -> Result<Vec<RepositoryProbe>, Fatal> {
+        match self.collector.as_ref() {
+            Some(collector) => collector.probe(PROBE_TIMEOUT),
+            None => Ok(Vec::new())
+        }
+    }
+
+    /// Determines what updating the known repositories would do.
+    ///
+    /// Only the RRDP notification files are fetched and rsync is run in
+    /// dry-run mode, so the local copy is left untouched. Repositories that
+    /// haven’t been seen before are not included. Returns an empty list if
+    /// updating has been disabled.
+    pub fn preview(&self) -> Result<Vec<UpdatePreview>, Fatal> {
+        match self.collector.as_ref() {
+            Some(collector) => collector.preview(),
+            None => Ok(Vec::new())
+        }
+    }
+
+    /// Determines what cleaning up the collector would delete.
+    ///
+    /// The repositories used by the publication points currently in the
+    /// store are retained. Nothing is actually deleted. Returns an empty
+    /// list if updating has been disabled.
+    pub fn cleanup_preview(&self) -> Result<Vec<CleanupPreview>, Failed> {
+        let collector = match self.collector.as_ref() {
+            Some(collector) => collector,
+            None => return Ok(Vec::new())
+        };
+        let mut retain = collector::Cleanup::preview();
+        self.store.retain_points(&mut retain)?;
+        collector.start().cleanup(&mut retain)?;
+        Ok(retain.into_preview())
+    }
+
+    /// Returns information about the locally known RRDP repositories.
+    ///
+    /// Returns an empty list if updating has been disabled.
+    pub fn rrdp_info(&self) -> Result<Vec<RrdpRepositoryInfo>, Fatal> {
+        match self.collector.as_ref() {
+            Some(collector) => collector.rrdp_info(),
+            None => Ok(Vec::new())
+        }
+    }
+
+    /// Compares the collector’s local copy with the store.
+    ///
+    /// Nothing is updated. Fails if updating has been disabled.
+    pub fn crosscheck(&self) -> Result<DivergenceMetrics, Failed> {
+        match self.collector.as_ref() {
+            Some(collector) => {
+                crosscheck::crosscheck(&self.store, collector, self.strict)
+            }
+            None => {
+                error!("Cannot cross-check: updating has been disabled.");
+                Err(Failed)
+            }
+        }
+    }
+
     /// Starts a validation run.
     ///
     /// During the run, `processor` will be responsible for dealing with
     /// valid objects. It must implement the [`ProcessRun`] trait.
     ///
+    /// If the store is shared with other processes and another process
+    /// has been elected writer for the run, no new data is collected and
+    /// the run uses stored data only.
+    ///
     /// The method returns a [`Run`] that drives the validation run.
     pub fn start<P: ProcessRun>(
         &self, processor: P
     ) -> Result<Run<P>, Failed> {
+        self.start_run(processor, None)
+    }
+
+    /// Starts a validation run that only updates a single repository.
+    ///
+    /// The run validates everything just like a normal run but only
+    /// collects new data for the repository given by `target`. For all
+    /// other repositories, the data in the store is used. Trust anchor
+    /// certificates are taken from the store, too, and no cleanup is done
+    /// at the end of the run.
+    pub fn start_refresh<P: ProcessRun>(
+        &self, processor: P, target: &RefreshTarget,
+    ) -> Result<Run<'_, P>, Failed> {
+        self.start_run(processor, Some(target))
+    }
+
+    /// Starts a validation run optionally restricted to one repository.
+    fn start_run<P: ProcessRun>(
+        &self, processor: P, target: Option<&RefreshTarget>,
+    ) -> Result<Run<'_, P>, Failed> {
+        let run_id = RunId::new(
+            self.run_serial.fetch_add(1, Ordering::Relaxed) + 1
+        );
+        let log_guard = Process::enter_run(run_id);
+        match target {
+            Some(target) => {
+                info!("Starting validation run refreshing {}.", target)
+            }
+            None => info!("Starting validation run."),
+        }
         info!("Using the following TALs:");
         for tal in &self.tals {
-            info!("  * {}", tal.info().name());
+            if self.is_disabled(tal) {
+                warn!("  * {} (disabled)", tal.info().name());
+            }
+            else {
+                info!("  * {}", tal.info().name());
+            }
         }
-        Ok(Run::new(
-            self,
-            self.collector.as_ref().map(Collector::start),
-            self.store.start(),
-            processor
-        ))
+        if let Some(time) = self.validation_time {
+            warn!("Validating as of {}.", time.to_rfc3339());
+        }
+        let input_hash = if self.freeze {
+            Some(self.input_hash()?)
+        }
+        else {
+            None
+        };
+        let store = self.store.start()?;
+
+        // Only collect new data if we are allowed to update the store.
+        let collector = if store.is_writer() {
+            self.collector.as_ref().map(|collector| {
+                match target {
+                    Some(target) => collector.start_refresh(target),
+                    None => collector.start(),
+                }
+            })
+        }
+        else {
+            None
+        };
+        let mut run = Run::new(
+            self, run_id, log_guard, collector, store, processor
+        );
+        run.metrics.input_hash = input_hash;
+        Ok(run)
+    }
+
+    /// Returns the hash of the input data of a validation run.
+    ///
+    /// The hash covers the name, URIs, and key of each TAL as well as the
+    /// data in the store. It is returned as a hex-encoded SHA-256 digest.
+    fn input_hash(&self) -> Result<String, Failed> {
+        let mut context = ring::digest::Context::new(&ring::digest::SHA256);
+        for tal in &self.tals {
+            context.update(tal.info().name().as_bytes());
+            context.update(&[0]);
+            for uri in tal.uris() {
+                context.update(uri.as_str().as_bytes());
+                context.update(&[0]);
+            }
+            context.update(&tal.key_info().to_info_bytes());
+        }
+        self.store.digest_content(&mut context)?;
+        let mut res = String::new();
+        append_hex(context.finish().as_ref(), &mut res);
+        info!("Validating frozen data with input hash {}.", res);
+        Ok(res)
     }
 
     /// Dumps the content of the collector and store owned by the engine.
@@ -317,6 +805,115 @@ impl Engine {
         }
         Ok(())
     }
+
+    /// Imports rsync modules from the given directory into the collector.
+    ///
+    /// Returns the number of imported modules.
+    pub fn import_rsync(&self, source: &Path) -> Result<usize, Failed> {
+        match self.collector.as_ref() {
+            Some(collector) => collector.import_rsync(source),
+            None => {
+                error!("Cannot import data: the store is read-only.");
+                Err(Failed)
+            }
+        }
+    }
+}
+
+
+//------------ TalSwitch -----------------------------------------------------
+
+/// A request to enable or disable a TAL at runtime.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TalSwitch {
+    /// The name of the TAL.
+    pub name: String,
+
+    /// Whether the TAL should be enabled or disabled.
+    pub enabled: bool,
+}
+
+impl fmt::Display for TalSwitch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f, "{} TAL {}",
+            if self.enabled { "enable" } else { "disable" },
+            self.name
+        )
+    }
+}
+
+
+//------------ ManifestPolicy ------------------------------------------------
+
+/// The policies for dealing with problems with manifests and their files.
+#[derive(Clone, Copy, Debug)]
+struct ManifestPolicy {
+    /// How do we deal with stale manifests and CRLs?
+    stale: FilterPolicy,
+
+    /// How do we deal with files missing from an updated point?
+    missing_files: FilterPolicy,
+
+    /// How do we deal with files not matching their manifest hash?
+    hash_mismatch: FilterPolicy,
+
+    /// How long are stale manifests and CRLs accepted despite `stale`?
+    stale_grace: Option<Duration>,
+
+    /// Which algorithm do we use for validating resources?
+    resource_validation: ResourceValidation,
+}
+
+impl ManifestPolicy {
+    /// Creates the global policy from the configuration.
+    fn from_config(config: &Config) -> Self {
+        ManifestPolicy {
+            stale: config.stale,
+            missing_files: config.missing_files,
+            hash_mismatch: config.hash_mismatch,
+            stale_grace: config.stale_grace,
+            resource_validation: config.resource_validation,
+        }
+    }
+
+    /// Returns the policy with the TAL specific overrides applied.
+    fn with_tal_policy(self, policy: &TalPolicy) -> Self {
+        ManifestPolicy {
+            stale: policy.stale.unwrap_or(self.stale),
+            missing_files: policy.missing_files.unwrap_or(self.missing_files),
+            hash_mismatch: policy.hash_mismatch.unwrap_or(self.hash_mismatch),
+            stale_grace: self.stale_grace,
+            resource_validation: {
+                policy.resource_validation.unwrap_or(self.resource_validation)
+            },
+        }
+    }
+
+    /// Returns whether an object became stale within the grace period.
+    ///
+    /// The object became stale at `next_update`. The current time is
+    /// given via `now`.
+    fn is_in_stale_grace(&self, next_update: Time, now: Time) -> bool {
+        self.stale_grace.is_some_and(|grace| {
+            now.timestamp().saturating_sub(
+                next_update.timestamp()
+            ) <= i64::try_from(grace.as_secs()).unwrap_or(i64::MAX)
+        })
+    }
+}
+
+impl fmt::Display for ManifestPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "stale={} missing-files={} hash-mismatch={} stale-grace={} \
+             resource-validation={}",
+            self.stale, self.missing_files, self.hash_mismatch,
+            self.stale_grace.map(|grace| grace.as_secs()).unwrap_or(0),
+            self.resource_validation,
+        )
+    }
 }
 
 
@@ -347,23 +944,35 @@ pub struct Run<'a, P> {
     /// Was a fatal error encountered during the run?
     is_fatal: AtomicBool,
 
+    /// The URIs of the CAs whose subtree took too long to process.
+    overrun_cas: Mutex<HashSet<String>>,
+
     /// The metrics collected during the run.
     metrics: Metrics,
+
+    /// Keeps the run’s identifier in log messages while the run lives.
+    _log_guard: RunLogGuard,
 }
 
 impl<'a, P> Run<'a, P> {
     /// Creates a new runner from all the parts.
     fn new(
         validation: &'a Engine,
+        run_id: RunId,
+        log_guard: RunLogGuard,
         collector: Option<collector::Run<'a>>,
         store: store::Run<'a>,
         processor: P,
     ) -> Self {
+        let mut metrics = Metrics::new();
+        metrics.run_id = Some(run_id);
         Run {
             validation, collector, store, processor,
             had_err: AtomicBool::new(false),
             is_fatal: AtomicBool::new(false),
-            metrics: Default::default()
+            overrun_cas: Default::default(),
+            metrics,
+            _log_guard: log_guard,
         }
     }
 
@@ -373,13 +982,20 @@ impl<'a, P> Run<'a, P> {
             debug!("Skipping cleanup as configured.");
             return Ok(())
         }
-
-        let mut retain = collector::Cleanup::new();
-        self.store.cleanup(&mut retain)?;
-        if let Some(collector) = self.collector.as_mut() {
-            collector.cleanup(&mut retain)?;
+        if self.collector.as_ref().is_some_and(|collector| {
+            collector.is_refresh()
+        }) {
+            debug!("Skipping cleanup for refresh run.");
+            return Ok(())
         }
-        Ok(())
+        if self.validation.validation_time.is_some() {
+            // Cleanup uses the current time and would remove data that
+            // is still valid at the validation time.
+            debug!("Skipping cleanup when validating at a given time.");
+            return Ok(())
+        }
+
+        self.store.cleanup(self.collector.as_ref())
     }
 
     /// Finishes the validation run and returns the metrics.
@@ -391,7 +1007,38 @@ impl<'a, P> Run<'a, P> {
         if let Some(collector) = self.collector {
             collector.done(&mut metrics)
         }
+        if let Some(mirror) = self.validation.mirror.as_ref() {
+            let tas: Vec<_> = self.validation.tals.iter().flat_map(|tal| {
+                tal.uris()
+            }).filter_map(|uri| {
+                self.store.load_ta(uri).ok().flatten().map(|content| {
+                    (uri.clone(), content)
+                })
+            }).collect();
+            mirror.update(&self.validation.store, &tas);
+        }
+        let writer = self.store.is_writer();
         self.store.done(&mut metrics);
+        // The caches only refer to data in the store, so only the
+        // process updating the store may update them.
+        if writer {
+            if let Some(cache) = self.validation.signature_cache.as_ref() {
+                cache.save();
+            }
+            if let Some(cache) = self.validation.object_cache.as_ref() {
+                cache.save();
+            }
+        }
+        if let Some(report) = self.validation.rejection_report.as_ref() {
+            report.write(&metrics);
+        }
+        if self.validation.crosscheck {
+            if let Some(collector) = self.validation.collector.as_ref() {
+                metrics.divergence = crosscheck::crosscheck(
+                    &self.validation.store, collector, self.validation.strict
+                ).ok();
+            }
+        }
         metrics
     }
 }
@@ -404,30 +1051,56 @@ impl<'a, P: ProcessRun> Run<'a, P> {
             return Ok(())
         }
 
-        // Initialize our task queue with all the TALs.
+        // Initialize our task queues with all the TALs.
         let metrics = RunMetrics::default();
-        let tasks = SegQueue::new();
+        let tasks = TaskQueues::new(
+            &self.validation.tals, &self.validation.tal_shares,
+            &self.validation.tal_priorities,
+            self.validation.validation_threads
+        );
         for (index, tal) in self.validation.tals.iter().enumerate() {
-            tasks.push(Task::Tal(TalTask { tal, index }));
-            self.metrics.tals.push(TalMetrics::new(tal.info().clone()));
+            let mut metrics = TalMetrics::new(tal.info().clone());
+            if self.validation.is_disabled(tal) {
+                metrics.disabled = true;
+            }
+            else {
+                tasks.push(Task::Tal(TalTask { tal, index }));
+            }
+            self.metrics.tals.push(metrics);
         }
 
+        // Keep track of when the last task of each TAL was finished.
+        let start = Instant::now();
+        let finished: Vec<_> = self.validation.tals.iter().map(|_| {
+            AtomicU64::new(0)
+        }).collect();
+
         // And off we trot.
 
         // Keep a flag to cancel everything if something goes wrong.
-        let thread_metrics = ArrayQueue::new(
-            self.validation.validation_threads
-        );
+        let thread_metrics = ArrayQueue::new(tasks.thread_count());
+        let run = &*self;
         thread::scope(|scope| {
-            for _ in 0 .. self.validation.validation_threads {
-                scope.spawn(|| {
+            for home in tasks.thread_homes() {
+                let tasks = &tasks;
+                let finished = &finished;
+                let thread_metrics = &thread_metrics;
+                let metrics = &metrics;
+                scope.spawn(move || {
                     let mut metrics = metrics.fork();
-                    while let Some(task) = tasks.pop() {
-                        if self.process_task(
-                            task, &tasks, &mut metrics,
+                    while let Some(task) = tasks.pop(home) {
+                        let tal = task.tal();
+                        if run.process_task(
+                            task, tasks, &mut metrics,
                         ).is_err() {
                             break;
                         }
+                        finished[tal].fetch_max(
+                            u64::try_from(
+                                start.elapsed().as_millis()
+                            ).unwrap_or(u64::MAX),
+                            Ordering::Relaxed
+                        );
                     }
                     thread_metrics.push(metrics).unwrap();
                 });
@@ -443,6 +1116,13 @@ impl<'a, P: ProcessRun> Run<'a, P> {
             }
         }
 
+        for (tal, finished) in self.metrics.tals.iter_mut().zip(finished) {
+            if !tal.disabled {
+                tal.duration = Some(
+                    Duration::from_millis(finished.into_inner())
+                );
+            }
+        }
         metrics.prepare_final(&mut self.metrics);
         while let Some(metrics) = thread_metrics.pop() {
             metrics.collapse(&mut self.metrics);
@@ -455,7 +1135,7 @@ impl<'a, P: ProcessRun> Run<'a, P> {
     fn process_task(
         &self,
         task: Task<P::PubPoint>,
-        tasks: &SegQueue<Task<P::PubPoint>>,
+        tasks: &TaskQueues<P::PubPoint>,
         metrics: &mut RunMetrics,
     ) -> Result<(), Failed> {
         match task {
@@ -471,11 +1151,11 @@ impl<'a, P: ProcessRun> Run<'a, P> {
     /// Processes a trust anchor.
     fn process_tal_task(
         &self, task: TalTask,
-        tasks: &SegQueue<Task<P::PubPoint>>,
+        tasks: &TaskQueues<P::PubPoint>,
         metrics: &mut RunMetrics,
     ) -> Result<(), Failed> {
         for uri in task.tal.uris() {
-            let cert = match self.load_ta(uri, task.tal.info())? {
+            let cert = match self.load_ta(uri, task.tal)? {
                 Some(cert) => cert,
                 _ => continue,
             };
@@ -486,8 +1166,11 @@ impl<'a, P: ProcessRun> Run<'a, P> {
                 );
                 continue;
             }
-            let cert = match cert.validate_ta(
-                task.tal.info().clone(), self.validation.strict
+            let time = self.validation.skewed_time(
+                cert.validity().not_before()
+            );
+            let cert = match cert.validate_ta_at(
+                task.tal.info().clone(), self.validation.strict, time
             ) {
                 Ok(cert) => CaCert::root(cert, uri.clone(), task.index),
                 Err(err) => {
@@ -510,6 +1193,7 @@ impl<'a, P: ProcessRun> Run<'a, P> {
                             cert, processor,
                             repository_index: None,
                             defer: false,
+                            postponed: false,
                         },
                         tasks, metrics,
                     )
@@ -528,34 +1212,118 @@ impl<'a, P: ProcessRun> Run<'a, P> {
     ///
     /// Attempts to download the certificate from upstream but falls back to
     /// the version in the store if available.
+    ///
+    /// In order to support key rollovers as described in RFC 8630, the
+    /// certificate with the key given in the TAL is preferred. If the
+    /// downloaded certificate has a different key than the stored one, the
+    /// certificate not matching the TAL is kept as the rollover certificate
+    /// in the store. This way, validation continues with the old key until
+    /// the TAL is updated and with the new key right after the update even
+    /// if the new certificate isn’t published yet.
     fn load_ta(
         &self,
         uri: &TalUri,
-        _info: &TalInfo,
+        tal: &Tal,
     ) -> Result<Option<Cert>, Failed> {
-        // Get the new version, store and return it if it decodes.
+        let stored = self.store.load_ta(uri)?.and_then(|bytes| {
+            Cert::decode(bytes.clone()).ok().map(|cert| (bytes, cert))
+        });
+        let stored_matches = stored.as_ref().is_some_and(|(_, cert)| {
+            cert.subject_public_key_info() == tal.key_info()
+        });
+
+        // Get the new version, store and return it if it decodes and has
+        // the right key.
+        let mut downloaded = None;
         if let Some(collector) = self.collector.as_ref() {
             if let Some(bytes) = collector.load_ta(uri) {
                 if let Ok(cert) = Cert::decode(bytes.clone()) {
-                    self.store.update_ta(uri, &bytes)?;
-                    return Ok(Some(cert))
+                    if cert.subject_public_key_info() == tal.key_info() {
+                        if let Some((stored_bytes, _)) = stored.as_ref() {
+                            if !stored_matches {
+                                info!(
+                                    "Trust anchor {}: key rollover \
+                                     detected. Now using the key from \
+                                     the TAL.",
+                                    uri
+                                );
+                                self.store.update_rollover_ta(
+                                    uri, stored_bytes
+                                )?;
+                            }
+                        }
+                        self.store.update_ta(uri, &bytes)?;
+                        return Ok(Some(cert))
+                    }
+                    if stored_matches {
+                        warn!(
+                            "Trust anchor {}: published certificate has a \
+                             key not in the TAL. Possible key rollover, \
+                             continuing with the stored certificate until \
+                             the TAL is updated.",
+                            uri
+                        );
+                        self.store.update_rollover_ta(uri, &bytes)?;
+                    }
+                    else {
+                        downloaded = Some((bytes, cert));
+                    }
                 }
             }
         }
+        if stored_matches {
+            return Ok(stored.map(|(_, cert)| cert))
+        }
 
-        // Get what we have in store.
-        self.store.load_ta(uri).map(|bytes| {
-            bytes.and_then(|bytes| Cert::decode(bytes).ok())
-        })
+        // Maybe the rollover certificate has the key of the TAL.
+        let rollover = self.store.load_rollover_ta(uri)?.and_then(|bytes| {
+            Cert::decode(bytes).ok()
+        });
+        if let Some(cert) = rollover {
+            if cert.subject_public_key_info() == tal.key_info() {
+                info!(
+                    "Trust anchor {}: using stored rollover certificate \
+                     with the key from the TAL.",
+                    uri
+                );
+                return Ok(Some(cert))
+            }
+        }
+
+        // Nothing has the right key. Store and return what we have so the
+        // caller can complain.
+        if let Some((bytes, cert)) = downloaded {
+            self.store.update_ta(uri, &bytes)?;
+            return Ok(Some(cert))
+        }
+        Ok(stored.map(|(_, cert)| cert))
     }
 
     /// Processes a CA.
     fn process_ca_task(
         &self,
-        task: CaTask<P::PubPoint>,
-        tasks: &SegQueue<Task<P::PubPoint>>,
+        mut task: CaTask<P::PubPoint>,
+        tasks: &TaskQueues<P::PubPoint>,
         metrics: &mut RunMetrics,
     ) -> Result<(), Failed> {
+        // If the CA’s rsync module would have to wait for a free rsync
+        // process, postpone the CA once and work on other tasks instead so
+        // that slow rsync servers don’t tie up all validation threads.
+        if !task.postponed && !tasks.is_empty() {
+            if let Some(collector) = self.collector.as_ref() {
+                if collector.is_busy(&task.cert) {
+                    task.postponed = true;
+                    tasks.push(Task::Ca(task));
+                    return Ok(())
+                }
+            }
+        }
+
+        if self.is_overrun(&task.cert, metrics) {
+            task.processor.cancel(&task.cert);
+            return Ok(())
+        }
+
         let more_tasks = PubPoint::new(
             self, &task.cert, task.processor, task.repository_index,
         ).and_then(|point| {
@@ -578,6 +1346,40 @@ impl<'a, P: ProcessRun> Run<'a, P> {
         Ok(())
     }
 
+    /// Checks whether processing a CA’s subtree has taken too long.
+    ///
+    /// Returns `true` if the time for processing the subtree below any
+    /// of the CA’s ancestors other than the trust anchor or the CA itself
+    /// has been exceeded and the CA should therefore be rejected. Each
+    /// offending CA is reported only once.
+    fn is_overrun(&self, cert: &CaCert, metrics: &mut RunMetrics) -> bool {
+        let max = match self.validation.max_ca_time {
+            Some(max) => max,
+            None => return false,
+        };
+        let overrun = match cert.overrun_ancestor(max) {
+            Some(overrun) => overrun,
+            None => return false,
+        };
+        let uri = overrun.uri().to_string();
+        if self.overrun_cas.lock().unwrap().insert(uri.clone()) {
+            error!(
+                "CA {}: processing its CAs took too long (limit {}s). \
+                 Rejecting all remaining CAs.",
+                uri, max.as_secs()
+            );
+            metrics.limit_violations.push(LimitViolation {
+                uri,
+                tal: cert.cert().tal().name().into(),
+                limit: LimitKind::CaTime,
+            });
+        }
+        else {
+            debug!("CA {}: rejected due to time limit.", cert.uri());
+        }
+        true
+    }
+
     /// Marks the run as failed.
     fn run_failed(&self, err: RunFailed) {
         self.had_err.store(true, Ordering::Relaxed);
@@ -604,13 +1406,46 @@ struct PubPoint<'a, P: ProcessRun> {
     /// The index of this point’s repository in the run’s metrics.
     repository_index: Option<usize>,
 
-    /// The base publication metrics for this publication point.
+    /// The manifest handling policy for this publication point.
+    policy: ManifestPolicy,
+
+    /// The base publication metrics for this publication point.
+    ///
+    /// This one only receives information about the publication point itself,
+    /// as well as the manifest and manifest CRL. Everything else will be
+    /// collected during object processing via `ValidPointManifest` so we can
+    /// drop it if the point gets cancelled.
+    metrics: PublicationMetrics,
+
+    /// The files of an updated manifest that could not be used.
+    ///
+    /// Unlike `metrics`, these are kept if we fall back to the stored
+    /// version of the point.
+    missing_files: Vec<MissingFile>,
+
+    /// The staleness report for the point if it needs one.
+    stale_point: Option<StalePoint>,
+
+    /// The limit exceeded by the point if any.
+    ///
+    /// Like `missing_files`, this is kept if we fall back to the stored
+    /// version of the point.
+    limit_violation: Option<LimitViolation>,
+
+    /// The types of the unknown objects encountered.
+    ///
+    /// Unlike `missing_files`, these are dropped if we fall back to the
+    /// stored version of the point since it will be encountering them again.
+    unknown_objects: Vec<String>,
+
+    /// The objects rejected for the rejection report.
     ///
-    /// This one only receives information about the publication point itself,
-    /// as well as the manifest and manifest CRL. Everything else will be
-    /// collected during object processing via `ValidPointManifest` so we can
-    /// drop it if the point gets cancelled.
-    metrics: PublicationMetrics,
+    /// Like `unknown_objects`, these are dropped if we fall back to the
+    /// stored version of the point.
+    rejected_objects: Vec<RejectedObject>,
+
+    /// Why the manifest of the point was rejected if we know.
+    point_rejection: Option<RejectionReason>,
 }
 
 impl<'a, P: ProcessRun> PubPoint<'a, P> {
@@ -623,7 +1458,14 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
     ) -> Result<Self, RunFailed> {
         Ok(PubPoint {
             run, cert, processor, repository_index,
+            policy: run.validation.manifest_policy(cert),
             metrics: Default::default(),
+            missing_files: Vec::new(),
+            stale_point: None,
+            limit_violation: None,
+            unknown_objects: Vec::new(),
+            rejected_objects: Vec::new(),
+            point_rejection: None,
         })
     }
 
@@ -635,20 +1477,28 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         self,
         metrics: &mut RunMetrics,
     ) -> Result<Vec<CaTask<P::PubPoint>>, RunFailed> {
-        let mut store = self.run.store.pub_point(self.cert)?;
-        if let Some(collector) = self.run.collector.as_ref() {
+        let run = self.run;
+        let mut store = run.store.pub_point(self.cert)?;
+        if let Some(collector) = run.collector.as_ref() {
             if let Some(collector) = collector.repository(self.cert)? {
                 match self.process_collected(
                     collector, &mut store, metrics
                 )? {
-                    Ok(res) => return Ok(res),
+                    Ok(res) => {
+                        run.store.retain_point(&store);
+                        return Ok(res)
+                    }
                     Err(mut this) => {
                         this.metrics = Default::default();
+                        this.unknown_objects.clear();
+                        this.rejected_objects.clear();
+                        run.store.retain_point(&store);
                         return Ok(this.process_stored(store, metrics)?)
                     }
                 }
             }
         }
+        run.store.retain_point(&store);
         Ok(self.process_stored(store, metrics)?)
     }
 
@@ -691,6 +1541,9 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
             false
         };
         if same {
+            if collector.is_current() {
+                self.confirm_stored(store)?;
+            }
             return Ok(Err(self))
         }
 
@@ -745,19 +1598,23 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         // if that happens to end up being `false` return an empty list to
         // signal that the publication point was processed successfully but
         // shouldn’t be considered further.
+        let mut stored_manifest = StoredManifest::new(
+            &collected.ee_cert,
+            &collected.content,
+            self.cert,
+            collected.manifest_bytes.clone(),
+            collected.crl_uri.clone(),
+            collected.crl_bytes.clone(),
+        );
+        if let Some(mft) = store.manifest() {
+            stored_manifest.succeed(mft);
+        }
         let mut ca_tasks = Vec::new();
         let mut items = collected.content.iter();
         let mut point_ok = true;
         let update_result = store.update(
-            StoredManifest::new(
-                &collected.ee_cert,
-                &collected.content,
-                self.cert,
-                collected.manifest_bytes.clone(),
-                collected.crl_uri.clone(),
-                collected.crl_bytes.clone(),
-            ),
-            || {
+            stored_manifest,
+            || loop {
                 let item = match items.next() {
                     Some(item) => item,
                     None => return Ok(None)
@@ -766,9 +1623,15 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
                 let file = match str_from_ascii(item.file()) {
                     Ok(file) => file,
                     Err(_) => {
+                        let file = String::from_utf8_lossy(item.file());
                         warn!("{}: illegal file name '{}'.",
-                            self.cert.rpki_manifest(),
-                            String::from_utf8_lossy(item.file())
+                            self.cert.rpki_manifest(), file
+                        );
+                        self.add_missing_file(
+                            format_args!(
+                                "{}{}", self.cert.ca_repository(), file
+                            ),
+                            MissingFileReason::IllegalName
                         );
                         return Err(store::UpdateError::Abort)
                     }
@@ -777,21 +1640,69 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
                     file.as_ref()
                 ).unwrap();
 
-                let hash = ManifestHash::new(
+                // If we don’t support the manifest’s hash algorithm, the
+                // hash can’t be verified and is treated as wrong.
+                let hash = ObjectHash::from_manifest(
                     item.hash().clone(), collected.content.file_hash_alg()
                 );
 
                 let content = match collector.load_object(&uri)? {
                     Some(content) => content,
                     None => {
-                        warn!("{}: failed to load.", uri);
-                        return Err(store::UpdateError::Abort)
+                        self.add_missing_file(
+                            &uri, MissingFileReason::NotFetched
+                        );
+                        match self.policy.missing_files {
+                            FilterPolicy::Reject => {
+                                warn!("{}: failed to load.", uri);
+                                return Err(store::UpdateError::Abort)
+                            }
+                            FilterPolicy::Warn => {
+                                warn!(
+                                    "{}: failed to load. Continuing \
+                                     without it.",
+                                    uri
+                                );
+                                continue
+                            }
+                            FilterPolicy::Accept => {
+                                info!(
+                                    "{}: failed to load. Continuing \
+                                     without it.",
+                                    uri
+                                );
+                                continue
+                            }
+                        }
                     }
                 };
 
-                if hash.verify(&content).is_err() {
-                    warn!("{}: file has wrong manifest hash.", uri);
-                    return Err(store::UpdateError::Abort)
+                if !hash.as_ref().is_some_and(|hash| hash.verify(&content)) {
+                    self.add_missing_file(
+                        &uri, MissingFileReason::HashMismatch
+                    );
+                    match self.policy.hash_mismatch {
+                        FilterPolicy::Reject => {
+                            warn!("{}: file has wrong manifest hash.", uri);
+                            return Err(store::UpdateError::Abort)
+                        }
+                        FilterPolicy::Warn => {
+                            warn!(
+                                "{}: file has wrong manifest hash. \
+                                 Continuing without it.",
+                                uri
+                            );
+                            continue
+                        }
+                        FilterPolicy::Accept => {
+                            info!(
+                                "{}: file has wrong manifest hash. \
+                                 Continuing without it.",
+                                uri
+                            );
+                            continue
+                        }
+                    }
                 }
 
                 if !self.process_object(
@@ -801,7 +1712,7 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
                     point_ok = false;
                 }
 
-                Ok(Some(StoredObject::new(uri, content, Some(hash))))
+                return Ok(Some(StoredObject::new(uri, content, hash)))
             }
         );
 
@@ -809,9 +1720,12 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
             Ok(()) => {
                 // Update was successful. We have to accept whatever result
                 // we got.
+                if let Some(mft) = store.manifest() {
+                    self.check_reissues(mft);
+                }
+                self.record_outcome(store, point_ok)?;
                 if point_ok {
-                    self.accept_point(collected, metrics);
-                    Ok(Ok(ca_tasks))
+                    Ok(Ok(self.accept_point(collected, ca_tasks, metrics)))
                 }
                 else {
                     self.reject_point(metrics);
@@ -840,8 +1754,10 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         manifest_bytes: Bytes,
         repository: &collector::Repository,
     ) -> Result<Option<ValidPointManifest>, RunFailed> {
-        let manifest = match Manifest::decode(
-            manifest_bytes.clone(), self.run.validation.strict
+        let manifest = match self.run.validation.decode_object(
+            &manifest_bytes, || Manifest::decode(
+                manifest_bytes.clone(), self.run.validation.strict
+            )
         ) {
             Ok(manifest) => manifest,
             Err(_) => {
@@ -853,8 +1769,11 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
                 return Ok(None)
             }
         };
-        let (ee_cert, content) = match manifest.validate(
-            self.cert.cert(), self.run.validation.strict
+        let time = self.run.validation.skewed_time(
+            manifest.cert().validity().not_before()
+        );
+        let (ee_cert, content) = match manifest.validate_at(
+            self.cert.cert(), self.run.validation.strict, time
         ) {
             Ok(some) => some,
             Err(err) => {
@@ -864,15 +1783,30 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
             }
         };
 
-        if content.this_update() > Time::now() {
+        if self.run.validation.is_premature(content.this_update()) {
             self.metrics.premature_manifests += 1;
             warn!("{}: premature manifest", self.cert.rpki_manifest());
             return Ok(None)
         }
 
-        if content.is_stale() {
+        if self.exceeds_point_objects(&content) {
+            self.metrics.invalid_manifests += 1;
+            return Ok(None)
+        }
+
+        let now = self.run.validation.now();
+        if content.next_update() < now {
             self.metrics.stale_manifests += 1;
-            match self.run.validation.stale {
+            match self.policy.stale {
+                FilterPolicy::Reject if self.policy.is_in_stale_grace(
+                    content.next_update(), now
+                ) => {
+                    self.metrics.grace_manifests += 1;
+                    warn!(
+                        "{}: stale manifest within grace period.",
+                        self.cert.rpki_manifest()
+                    );
+                }
                 FilterPolicy::Reject => {
                     warn!("{}: stale manifest", self.cert.rpki_manifest());
                     return Ok(None)
@@ -896,6 +1830,7 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         Ok(Some(ValidPointManifest {
             ee_cert, content, crl_uri, crl, manifest_bytes, crl_bytes,
             metrics: Default::default(),
+            limit_violations: Vec::new(),
         }))
     }
 
@@ -912,7 +1847,7 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         ee_cert: &ResourceCert,
         manifest: &ManifestContent,
         repository: &collector::Repository
-    ) -> Result<Option<(uri::Rsync, Crl, Bytes)>, RunFailed> {
+    ) -> Result<Option<(uri::Rsync, RevocationList, Bytes)>, RunFailed> {
         // Let’s first get the manifest CRL’s name relative to repo_uri. If
         // it ain’t relative at all, this is already invalid.
         let crl_uri = match ee_cert.crl_uri() {
@@ -950,8 +1885,9 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
                         return Ok(None)
                     }
                 };
-                let hash = ManifestHash::new(hash, manifest.file_hash_alg());
-                if hash.verify(&bytes).is_err() {
+                if !ObjectHash::from_manifest(
+                    hash, manifest.file_hash_alg()
+                ).is_some_and(|hash| hash.verify(&bytes)) {
                     self.metrics.invalid_crls += 1;
                     warn!("{}: file has wrong hash.", crl_uri);
                     return Ok(None)
@@ -972,7 +1908,7 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         };
 
         // Decode and validate the CRL.
-        let mut crl = match Crl::decode(crl_bytes.clone()) {
+        let crl = match Crl::decode(crl_bytes.clone()) {
             Ok(crl) => crl,
             Err(_) => {
                 self.metrics.invalid_crls += 1;
@@ -980,16 +1916,24 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
                 return Ok(None)
             }
         };
-        if let Err(err) = crl.verify_signature(
-            self.cert.cert().subject_public_key_info()
+        let key = self.cert.cert().subject_public_key_info();
+        if let Err(err) = self.run.validation.verify_signature(
+            crl_bytes.as_ref(), key, || crl.verify_signature(key)
         ) {
             self.metrics.invalid_crls += 1;
             warn!("{}: {}.", crl_uri, err);
             return Ok(None)
         }
-        if crl.is_stale() {
+        let now = self.run.validation.now();
+        if crl.next_update() < now {
             self.metrics.stale_crls += 1;
-            match self.run.validation.stale {
+            match self.policy.stale {
+                FilterPolicy::Reject if self.policy.is_in_stale_grace(
+                    crl.next_update(), now
+                ) => {
+                    self.metrics.grace_crls += 1;
+                    warn!("{}: stale CRL within grace period.", crl_uri);
+                }
                 FilterPolicy::Reject => {
                     warn!("{}: stale CRL.", crl_uri);
                     return Ok(None)
@@ -1001,10 +1945,8 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
             }
         }
 
-        // Turn on serial caching before looking for the first serial.
-        if manifest.len() > CRL_CACHE_LIMIT {
-            crl.cache_serials()
-        }
+        // Index the serials before looking for the first one if needed.
+        let crl = RevocationList::new(crl, manifest.len(), CRL_CACHE_LIMIT);
 
         // Finally: has the manifest’s cert been revoked?
         if crl.contains(ee_cert.serial_number()) {
@@ -1042,14 +1984,27 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
                     self.cert.uri(), self.cert.rpki_manifest()
                 );
                 self.metrics.missing_manifests += 1;
+                self.point_rejection = Some(RejectionReason::NotFetched);
                 self.reject_point(metrics);
                 return Ok(Vec::new())
             }
         };
 
+        if self.is_expired_stored(&manifest) {
+            self.metrics.missing_manifests += 1;
+            self.metrics.expired_stored_points += 1;
+            self.point_rejection = Some(RejectionReason::Expired);
+            self.reject_point(metrics);
+            return Ok(Vec::new())
+        }
+        let stale_stored = self.is_stale_stored(&manifest);
+        self.check_reissues(&manifest);
+
+        let last_outcome = manifest.last_outcome().copied();
         let mut manifest = match self.validate_stored_manifest(manifest) {
             Ok(manifest) => manifest,
             Err(_) => {
+                self.record_stored_outcome(&mut store, last_outcome, false)?;
                 self.reject_point(metrics);
                 return Ok(Vec::new())
             }
@@ -1070,11 +2025,9 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
                         return Err(Failed)
                     }
                     else {
-                        debug!(
-                            "Ignoring invalid stored publication point \
-                             at {}: {}",
-                            store.path().display(), err
-                        );
+                        store.quarantine(
+                            format_args!("invalid stored object: {}", err)
+                        )?;
                         self.reject_point(metrics);
                         return Ok(Vec::new())
                     }
@@ -1084,13 +2037,154 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
                 object.uri(), object.content().clone(),
                 &mut manifest, &mut ca_tasks
             )? {
+                self.record_stored_outcome(&mut store, last_outcome, false)?;
                 self.reject_point(metrics);
                 return Ok(Vec::new())
             }
         }
 
-        self.accept_point(manifest, metrics);
-        Ok(ca_tasks)
+        self.record_stored_outcome(&mut store, last_outcome, true)?;
+        if stale_stored {
+            self.metrics.stale_stored_points += 1;
+        }
+        Ok(self.accept_point(manifest, ca_tasks, metrics))
+    }
+
+    /// Records that the data of a stored point is still current.
+    ///
+    /// Only does so if the store may be updated during this run and the
+    /// last confirmation is older than [`CONFIRM_INTERVAL`]. The validation
+    /// time is used as the confirmation time.
+    fn confirm_stored(&self, store: &mut StoredPoint) -> Result<(), Failed> {
+        if !self.run.store.is_writer() {
+            return Ok(())
+        }
+        let now = self.run.validation.now();
+        let confirmed = store.manifest().and_then(|mft| mft.confirmed());
+        if let Some(confirmed) = confirmed {
+            if now.timestamp() - confirmed.timestamp() < CONFIRM_INTERVAL {
+                return Ok(())
+            }
+        }
+        store.confirm(now)
+    }
+
+    /// Checks whether the point reissues its manifest in time.
+    ///
+    /// If the manifest is past its nextUpdate time or the point is
+    /// chronically stale, prepares a report for the metrics. Points with
+    /// expired manifests are left to the regular validation.
+    fn check_reissues(&mut self, manifest: &StoredManifest) {
+        let now = self.run.validation.now();
+        if manifest.not_after() < now {
+            return
+        }
+        let timeline = manifest.timeline();
+        let overdue = manifest.next_update().map(|next_update| {
+            next_update < now
+        }).unwrap_or(false);
+        let chronic = timeline.is_chronic();
+        if !overdue && !chronic {
+            return
+        }
+        self.stale_point = Some(StalePoint {
+            manifest: self.cert.rpki_manifest().to_string(),
+            next_update: manifest.next_update(),
+            not_after: manifest.not_after(),
+            overdue,
+            chronic,
+            reissues: timeline.len(),
+            late_reissues: timeline.late(),
+            max_delay: timeline.max_delay(),
+        })
+    }
+
+    /// Returns whether stored data is too old to be used.
+    ///
+    /// This is the case if a maximum age for stored data is configured and
+    /// the data hasn’t been confirmed to be current for longer than that.
+    /// Stored data without a confirmation time is never too old.
+    fn is_expired_stored(&self, manifest: &StoredManifest) -> bool {
+        let max_age = match self.run.validation.stored_max_age {
+            Some(max_age) => max_age,
+            None => return false,
+        };
+        let confirmed = match manifest.confirmed() {
+            Some(confirmed) => confirmed,
+            None => return false,
+        };
+        if !self.exceeds_age(confirmed, max_age) {
+            return false
+        }
+        warn!(
+            "{}: stored data last confirmed at {} is too old. Not using it.",
+            self.cert.rpki_manifest(), confirmed.to_rfc3339()
+        );
+        true
+    }
+
+    /// Returns whether stored data is stale.
+    ///
+    /// This is the case if a stale age for stored data is configured and
+    /// the data hasn’t been confirmed to be current for longer than that.
+    /// Stale data is still used but counted in the metrics.
+    fn is_stale_stored(&self, manifest: &StoredManifest) -> bool {
+        let stale_age = match self.run.validation.stored_stale_age {
+            Some(stale_age) => stale_age,
+            None => return false,
+        };
+        let confirmed = match manifest.confirmed() {
+            Some(confirmed) => confirmed,
+            None => return false,
+        };
+        if !self.exceeds_age(confirmed, stale_age) {
+            return false
+        }
+        info!(
+            "{}: using stale stored data last confirmed at {}.",
+            self.cert.rpki_manifest(), confirmed.to_rfc3339()
+        );
+        true
+    }
+
+    /// Returns whether more than `age` has passed since `confirmed`.
+    ///
+    /// The age is determined relative to the validation time.
+    fn exceeds_age(&self, confirmed: Time, age: Duration) -> bool {
+        self.run.validation.now().timestamp() - confirmed.timestamp()
+            > i64::try_from(age.as_secs()).unwrap_or(i64::MAX)
+    }
+
+    /// Records the validation outcome for a stored point.
+    ///
+    /// Only does so if the store may be updated during this run.
+    fn record_outcome(
+        &self, store: &mut StoredPoint, valid: bool
+    ) -> Result<(), Failed> {
+        if !self.run.store.is_writer() {
+            return Ok(())
+        }
+        store.set_outcome(
+            StoredOutcome::now(valid, self.run.validation.policy_hash)
+        )
+    }
+
+    /// Records the validation outcome of a point validated from the store.
+    ///
+    /// Only updates the stored point if the outcome differs from the last
+    /// recorded outcome to avoid needlessly writing to the store.
+    fn record_stored_outcome(
+        &self,
+        store: &mut StoredPoint,
+        last_outcome: Option<StoredOutcome>,
+        valid: bool,
+    ) -> Result<(), Failed> {
+        if let Some(outcome) = last_outcome {
+            if outcome.matches(valid, self.run.validation.policy_hash) {
+                return Ok(())
+            }
+        }
+        self.record_outcome(store, valid)
     }
 
     /// Tries to validate a stored manifest.
@@ -1104,12 +2198,16 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         stored_manifest: StoredManifest,
     ) -> Result<ValidPointManifest, Failed> {
         // Decode and validate the manifest.
-        let manifest = match Manifest::decode(
-            stored_manifest.manifest().clone(), self.run.validation.strict
+        let manifest = match self.run.validation.decode_object(
+            stored_manifest.manifest(), || Manifest::decode(
+                stored_manifest.manifest().clone(),
+                self.run.validation.strict
+            )
         ) {
             Ok(manifest) => manifest,
             Err(_) => {
                 self.metrics.invalid_manifests += 1;
+                self.point_rejection = Some(RejectionReason::DecodeError);
                 warn!(
                     "{}: failed to decode manifest.",
                     self.cert.rpki_manifest(),
@@ -1117,22 +2215,41 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
                 return Err(Failed);
             }
         };
-        let (ee_cert, content) = match manifest.validate(
-            self.cert.cert(), self.run.validation.strict
+        let time = self.run.validation.skewed_time(
+            manifest.cert().validity().not_before()
+        );
+        let (ee_cert, content) = match manifest.validate_at(
+            self.cert.cert(), self.run.validation.strict, time
         ) {
             Ok(some) => some,
             Err(err) => {
                 warn!("{}: {}.", self.cert.rpki_manifest(), err);
                 self.metrics.invalid_manifests += 1;
+                self.point_rejection = Some(RejectionReason::Invalid);
                 return Err(Failed);
             }
         };
-        if content.is_stale() {
+        if self.exceeds_point_objects(&content) {
+            self.metrics.invalid_manifests += 1;
+            return Err(Failed);
+        }
+        let now = self.run.validation.now();
+        if content.next_update() < now {
             self.metrics.stale_manifests += 1;
-            match self.run.validation.stale {
+            match self.policy.stale {
+                FilterPolicy::Reject if self.policy.is_in_stale_grace(
+                    content.next_update(), now
+                ) => {
+                    self.metrics.grace_manifests += 1;
+                    warn!(
+                        "{}: stale manifest within grace period.",
+                        self.cert.rpki_manifest()
+                    );
+                }
                 FilterPolicy::Reject => {
                     warn!("{}: stale manifest", self.cert.rpki_manifest());
                     self.metrics.invalid_manifests += 1;
+                    self.point_rejection = Some(RejectionReason::Stale);
                     return Err(Failed);
                 }
                 FilterPolicy::Warn => {
@@ -1157,30 +2274,41 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         };
 
         // Decode and validate the CRL.
-        let mut crl = match Crl::decode(stored_manifest.crl().clone()) {
+        let crl = match Crl::decode(stored_manifest.crl().clone()) {
             Ok(crl) => crl,
             Err(_) => {
                 self.metrics.invalid_manifests += 1;
                 self.metrics.invalid_crls += 1;
+                self.point_rejection = Some(RejectionReason::DecodeError);
                 warn!("{}: failed to decode CRL.", crl_uri);
                 return Err(Failed)
             }
         };
-        if let Err(err) = crl.verify_signature(
-            self.cert.cert().subject_public_key_info()
+        let key = self.cert.cert().subject_public_key_info();
+        if let Err(err) = self.run.validation.verify_signature(
+            stored_manifest.crl().as_ref(), key, || crl.verify_signature(key)
         ) {
             warn!("{}: {}.", crl_uri, err);
             self.metrics.invalid_manifests += 1;
             self.metrics.invalid_crls += 1;
+            self.point_rejection = Some(RejectionReason::Invalid);
             return Err(Failed)
         }
-        if crl.is_stale() {
+        let now = self.run.validation.now();
+        if crl.next_update() < now {
             self.metrics.stale_crls += 1;
-            match self.run.validation.stale {
+            match self.policy.stale {
+                FilterPolicy::Reject if self.policy.is_in_stale_grace(
+                    crl.next_update(), now
+                ) => {
+                    self.metrics.grace_crls += 1;
+                    warn!("{}: stale CRL within grace period.", crl_uri);
+                }
                 FilterPolicy::Reject => {
                     warn!("{}: stale CRL.", crl_uri);
                     self.metrics.invalid_manifests += 1;
                     self.metrics.invalid_crls += 1;
+                    self.point_rejection = Some(RejectionReason::Stale);
                     return Err(Failed)
                 }
                 FilterPolicy::Warn => {
@@ -1190,10 +2318,8 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
             }
         }
 
-        // Turn on serial caching before looking for the first serial.
-        if content.len() > CRL_CACHE_LIMIT {
-            crl.cache_serials()
-        }
+        // Index the serials before looking for the first one if needed.
+        let crl = RevocationList::new(crl, content.len(), CRL_CACHE_LIMIT);
 
         // Finally: has the manifest’s cert been revoked?
         //
@@ -1205,6 +2331,7 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
                 self.cert.rpki_manifest()
             );
             self.metrics.invalid_manifests += 1;
+            self.point_rejection = Some(RejectionReason::Revoked);
             return Err(Failed)
         }
 
@@ -1215,20 +2342,43 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
             manifest_bytes: stored_manifest.manifest().clone(),
             crl_bytes: stored_manifest.crl().clone(),
             metrics: Default::default(),
+            limit_violations: Vec::new(),
         })
     }
 
     // XXX Check metrics generation and concentrate at one central point.
 
+    /// Accepts the publication point.
+    ///
+    /// Returns the tasks for the child CAs to process. If there are more
+    /// child CAs than allowed, the point itself is still accepted but none
+    /// of its children will be processed.
     fn accept_point(
         mut self,
         manifest: ValidPointManifest,
+        mut ca_tasks: Vec<CaTask<P::PubPoint>>,
         metrics: &mut RunMetrics,
-    ) {
+    ) -> Vec<CaTask<P::PubPoint>> {
         self.metrics.valid_points += 1;
         self.metrics += manifest.metrics;
+        metrics.limit_violations.extend(manifest.limit_violations);
+        if let Some(max) = self.run.validation.max_ca_children {
+            if ca_tasks.len() > max {
+                error!(
+                    "CA {}: too many child CAs ({}, limit {}).",
+                    self.cert.uri(), ca_tasks.len(), max
+                );
+                metrics.limit_violations.push(LimitViolation {
+                    uri: self.cert.uri().to_string(),
+                    tal: self.cert.cert().tal().name().into(),
+                    limit: LimitKind::CaChildren,
+                });
+                ca_tasks = Vec::new();
+            }
+        }
         self.apply_metrics(metrics);
         self.processor.commit();
+        ca_tasks
     }
 
     fn reject_point(
@@ -1236,6 +2386,10 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         metrics: &mut RunMetrics,
     ) {
         self.metrics.rejected_points += 1;
+        let reason = self.point_rejection.unwrap_or(
+            RejectionReason::PointRejected
+        );
+        self.add_rejected_object(self.cert.rpki_manifest().clone(), reason);
         self.apply_metrics(metrics);
         self.processor.cancel(self.cert);
     }
@@ -1244,6 +2398,25 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         &mut self,
         metrics: &mut RunMetrics,
     ) {
+        self.metrics.missing_files += self.missing_files.len() as u32;
+        if self.run.validation.rejection_report.is_some() {
+            let tal = self.cert.cert().tal().name();
+            metrics.rejected_objects.extend(
+                self.missing_files.iter().map(|item| RejectedObject {
+                    uri: item.uri.clone(),
+                    manifest: item.manifest.clone(),
+                    tal: tal.into(),
+                    reason: item.reason.into(),
+                })
+            );
+        }
+        metrics.missing_files.append(&mut self.missing_files);
+        metrics.rejected_objects.append(&mut self.rejected_objects);
+        metrics.stale_points.extend(self.stale_point.take());
+        metrics.limit_violations.extend(self.limit_violation.take());
+        for kind in self.unknown_objects.drain(..) {
+            *metrics.unknown_objects.entry(kind).or_default() += 1;
+        }
         let repository_index = self.repository_index.unwrap_or_else(|| {
             metrics.repository_index(self.cert)
         });
@@ -1255,6 +2428,185 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         );
     }
 
+    /// Checks whether the manifest lists more objects than allowed.
+    ///
+    /// If so, logs and records the violation.
+    fn exceeds_point_objects(&mut self, content: &ManifestContent) -> bool {
+        let max = match self.run.validation.max_point_objects {
+            Some(max) => max,
+            None => return false,
+        };
+        if content.len() <= max {
+            return false
+        }
+        error!(
+            "{}: too many objects ({}, limit {}).",
+            self.cert.rpki_manifest(), content.len(), max
+        );
+        self.limit_violation = Some(LimitViolation {
+            uri: self.cert.uri().to_string(),
+            tal: self.cert.cert().tal().name().into(),
+            limit: LimitKind::PointObjects,
+        });
+        true
+    }
+
+    /// Records a file of an updated manifest that could not be used.
+    fn add_missing_file(
+        &mut self, uri: impl fmt::Display, reason: MissingFileReason
+    ) {
+        self.missing_files.push(MissingFile {
+            manifest: self.cert.rpki_manifest().to_string(),
+            uri: uri.to_string(),
+            reason,
+        })
+    }
+
+    /// Records an object for the rejection report.
+    ///
+    /// Does nothing if no report was requested.
+    fn add_rejected_object(
+        &mut self, uri: impl fmt::Display, reason: RejectionReason
+    ) {
+        if self.run.validation.rejection_report.is_none() {
+            return
+        }
+        self.rejected_objects.push(RejectedObject {
+            uri: uri.to_string(),
+            manifest: self.cert.rpki_manifest().to_string(),
+            tal: self.cert.cert().tal().name().into(),
+            reason,
+        })
+    }
+
+    /// Returns the reason for rejecting a signed object.
+    ///
+    /// Since the reason for a failed validation isn’t available, this
+    /// decodes the object once more to look at its EE certificate. This
+    /// only happens if a rejection report was requested.
+    fn signed_object_rejection(
+        &self, content: Bytes, manifest: &ValidPointManifest,
+    ) -> RejectionReason {
+        if self.run.validation.rejection_report.is_none() {
+            return RejectionReason::Invalid
+        }
+        match SignedObject::decode(content, self.run.validation.strict) {
+            Ok(obj) => {
+                manifest.rejection_reason(
+                    obj.cert(), self.run.validation.now()
+                )
+            }
+            Err(_) => RejectionReason::DecodeError,
+        }
+    }
+
+    /// Returns the reason for rejecting a certificate.
+    ///
+    /// Only looks closer if a rejection report was requested.
+    fn cert_rejection(
+        &self, cert: &Cert, manifest: &ValidPointManifest,
+    ) -> RejectionReason {
+        if self.run.validation.rejection_report.is_none() {
+            return RejectionReason::Invalid
+        }
+        manifest.rejection_reason(cert, self.run.validation.now())
+    }
+
+    /// Returns whether a certificate claims resources not held by its issuer.
+    ///
+    /// For these certificates, the outcome of validation differs between
+    /// the original algorithm of RFC 6487 and the reconsidered algorithm of
+    /// RFC 8360. Returns an error if the resources of the certificate
+    /// cannot be determined.
+    fn overclaims(
+        cert: &Cert, issuer: &ResourceCert
+    ) -> Result<bool, VerificationError> {
+        let v4 = cert.v4_resources();
+        if !v4.is_inherited() {
+            let blocks = v4.to_blocks().map_err(|_| {
+                VerificationError::new("invalid IPv4 resources")
+            })?;
+            if !issuer.v4_resources().contains(&blocks) {
+                return Ok(true)
+            }
+        }
+        let v6 = cert.v6_resources();
+        if !v6.is_inherited() {
+            let blocks = v6.to_blocks().map_err(|_| {
+                VerificationError::new("invalid IPv6 resources")
+            })?;
+            if !issuer.v6_resources().contains(&blocks) {
+                return Ok(true)
+            }
+        }
+        let asns = cert.as_resources();
+        if !asns.is_inherited() {
+            let blocks = asns.to_blocks().map_err(|_| {
+                VerificationError::new("invalid AS resources")
+            })?;
+            if !issuer.as_resources().contains(&blocks) {
+                return Ok(true)
+            }
+        }
+        Ok(false)
+    }
+
+    /// Returns whether a certificate is rejected for its resources.
+    ///
+    /// A certificate claiming resources not held by its issuer is counted
+    /// as overclaiming regardless of the algorithm. Under the strict
+    /// algorithm, it is rejected. Otherwise, the certificate is left to
+    /// validation which trims the resources of certificates using the
+    /// certificate policy of RFC 8360 and rejects all others. A
+    /// certificate whose resources cannot be determined is always
+    /// rejected.
+    ///
+    /// The caller is responsible for counting the certificate or object as
+    /// invalid and for recording the rejection if this returns `true`.
+    fn reject_overclaim(
+        &self, uri: &uri::Rsync, cert: &Cert,
+        manifest: &mut ValidPointManifest,
+    ) -> bool {
+        match Self::overclaims(cert, self.cert.cert()) {
+            Ok(false) => return false,
+            Ok(true) => { }
+            Err(err) => {
+                warn!("{}: {}.", uri, err);
+                return true
+            }
+        }
+        manifest.metrics.overclaiming_certs += 1;
+        match self.policy.resource_validation {
+            ResourceValidation::Strict => {
+                warn!(
+                    "{}: certificate claims resources not held by \
+                     its issuer.",
+                    uri
+                );
+                true
+            }
+            ResourceValidation::Reconsidered => {
+                info!(
+                    "{}: ignoring resources not held by the issuer of \
+                     the certificate.",
+                    uri
+                );
+                false
+            }
+        }
+    }
+
+    /// Returns the type of an unknown object for use in the metrics.
+    ///
+    /// This is the lower-cased file extension or an empty string if the
+    /// file name doesn’t have one.
+    fn unknown_object_type(uri: &uri::Rsync) -> String {
+        let name = uri.as_str().rsplit('/').next().unwrap_or_default();
+        name.rsplit_once('.').map(|(_, ext)| {
+            ext.to_ascii_lowercase()
+        }).unwrap_or_default()
+    }
+
     /// Processes a single object.
     ///
     /// Returns whether processing should continue or whether the entire (!)
@@ -1290,7 +2642,24 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         }
         else {
             manifest.metrics.others += 1;
-            warn!("{}: unknown object type.", uri);
+            self.unknown_objects.push(Self::unknown_object_type(uri));
+            match self.run.validation.unknown_objects {
+                FilterPolicy::Reject => {
+                    self.add_rejected_object(
+                        uri, RejectionReason::UnknownType
+                    );
+                    warn!(
+                        "{}: unknown object type. Rejecting publication \
+                         point.",
+                        uri
+                    );
+                    return Ok(false)
+                }
+                FilterPolicy::Warn => {
+                    warn!("{}: unknown object type.", uri);
+                }
+                FilterPolicy::Accept => { }
+            }
         }
         Ok(true)
     }
@@ -1303,10 +2672,13 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         manifest: &mut ValidPointManifest,
         ca_task: &mut Vec<CaTask<P::PubPoint>>,
     ) -> Result<(), Failed> {
-        let cert = match Cert::decode(content) {
+        let cert = match self.run.validation.decode_object(
+            &content, || Cert::decode(content.clone())
+        ) {
             Ok(cert) => cert,
             Err(_) => {
                 manifest.metrics.invalid_certs += 1;
+                self.add_rejected_object(uri, RejectionReason::DecodeError);
                 warn!("{}: failed to decode certificate.", uri);
                 return Ok(())
             }
@@ -1330,21 +2702,33 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         if self.cert.check_loop(&cert).is_err() {
             warn!("{}: certificate loop detected.", uri);
             manifest.metrics.invalid_certs += 1;
+            self.add_rejected_object(uri, RejectionReason::Invalid);
+            return Ok(())
+        }
+        let reason = self.cert_rejection(&cert, manifest);
+        if self.reject_overclaim(uri, &cert, manifest) {
+            manifest.metrics.invalid_certs += 1;
+            self.add_rejected_object(uri, RejectionReason::Invalid);
             return Ok(())
         }
-        let cert = match cert.validate_ca(
-            self.cert.cert(), self.run.validation.strict
+        let time = self.run.validation.skewed_time(
+            cert.validity().not_before()
+        );
+        let cert = match cert.validate_ca_at(
+            self.cert.cert(), self.run.validation.strict, time
         ) {
             Ok(cert) => cert,
             Err(err) => {
                 warn!("{}: {}.", uri, err);
                 manifest.metrics.invalid_certs += 1;
+                self.add_rejected_object(uri, reason);
                 return Ok(())
             }
         };
         if let Err(err) = manifest.check_crl(&cert) {
             warn!("{}: {}.", uri, err);
             manifest.metrics.invalid_certs += 1;
+            self.add_rejected_object(uri, reason);
             return Ok(())
         }
 
@@ -1354,6 +2738,11 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
             Ok(cert) => cert,
             Err(_) => {
                 manifest.metrics.invalid_certs += 1;
+                manifest.limit_violations.push(LimitViolation {
+                    uri: uri.to_string(),
+                    tal: self.cert.cert().tal().name().into(),
+                    limit: LimitKind::CaDepth,
+                });
                 return Ok(())
             }
         };
@@ -1383,7 +2772,8 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         };
 
         ca_task.push(CaTask {
-            cert, processor, repository_index, defer
+            cert, processor, repository_index, defer,
+            postponed: false,
         });
         Ok(())
     }
@@ -1393,16 +2783,27 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         &mut self, uri: &uri::Rsync, cert: Cert,
         manifest: &mut ValidPointManifest,
     ) -> Result<(), Failed> {
-        if let Err(err) = cert.validate_router(
-            self.cert.cert(), self.run.validation.strict
+        let reason = self.cert_rejection(&cert, manifest);
+        if self.reject_overclaim(uri, &cert, manifest) {
+            manifest.metrics.invalid_certs += 1;
+            self.add_rejected_object(uri, RejectionReason::Invalid);
+            return Ok(())
+        }
+        let time = self.run.validation.skewed_time(
+            cert.validity().not_before()
+        );
+        if let Err(err) = cert.validate_router_at(
+            self.cert.cert(), self.run.validation.strict, time
         ) {
             warn!("{}: {}.", uri, err);
             manifest.metrics.invalid_certs += 1;
+            self.add_rejected_object(uri, reason);
             return Ok(())
         };
         if let Err(err) = manifest.check_crl(&cert) {
             warn!("{}: {}.", uri, err);
             manifest.metrics.invalid_certs += 1;
+            self.add_rejected_object(uri, reason);
             return Ok(())
         }
         manifest.metrics.valid_router_certs += 1;
@@ -1415,27 +2816,50 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         &mut self, uri: &uri::Rsync, content: Bytes,
         manifest: &mut ValidPointManifest,
     ) -> Result<(), Failed> {
-        let roa = match Roa::decode(
-            content, self.run.validation.strict
+        let roa = match self.run.validation.decode_object(
+            &content, || Roa::decode(
+                content.clone(), self.run.validation.strict
+            )
         ) {
             Ok(roa) => roa,
             Err(_) => {
                 manifest.metrics.invalid_roas += 1;
+                self.add_rejected_object(uri, RejectionReason::DecodeError);
                 warn!("{}: failed to decode ROA.", uri);
                 return Ok(())
             }
         };
-        match roa.process(
-            self.cert.cert(),
-            self.run.validation.strict,
-            |cert| manifest.check_crl(cert)
+        if self.reject_overclaim(uri, roa.cert(), manifest) {
+            manifest.metrics.invalid_roas += 1;
+            self.add_rejected_object(uri, RejectionReason::Invalid);
+            return Ok(())
+        }
+        let issuer = self.cert.cert();
+        let strict = self.run.validation.strict;
+        let res = match self.run.validation.signed_object_time(
+            roa.cert().validity().not_before()
         ) {
+            None => {
+                roa.process(issuer, strict, |cert| manifest.check_crl(cert))
+            }
+            Some(time) => {
+                validate_signed_at(content.clone(), issuer, strict, time)
+                .and_then(|cert| {
+                    manifest.check_crl(cert.as_ref())?;
+                    verify_roa(roa.content(), &cert)?;
+                    Ok((cert, roa.content().clone()))
+                })
+            }
+        };
+        match res {
             Ok((cert, route)) => {
                 manifest.metrics.valid_roas += 1;
                 self.processor.process_roa(uri, cert, route)?
             }
             Err(err) => {
                 manifest.metrics.invalid_roas += 1;
+                let reason = self.signed_object_rejection(content, manifest);
+                self.add_rejected_object(uri, reason);
                 warn!("{}: {}.", uri, err)
             }
         }
@@ -1443,35 +2867,53 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
     }
 
     /// Process an ASPA object.
-    #[allow(unused_variables)]
     fn process_aspa(
         &mut self, uri: &uri::Rsync, content: Bytes,
         manifest: &mut ValidPointManifest,
     ) -> Result<(), Failed> {
-        #[cfg(feature = "aspa")] {
-            let aspa = match Aspa::decode(
-                content, self.run.validation.strict
-            ) {
-                Ok(aspa) => aspa,
-                Err(err) => {
-                    manifest.metrics.invalid_aspas += 1;
-                    warn!("{}: failed to decode ASPA.", uri);
-                    return Ok(())
-                }
-            };
-            match aspa.process(
-                self.cert.cert(),
-                self.run.validation.strict,
-                |cert| manifest.check_crl(cert)
-            ) {
-                Ok((cert, aspa)) => {
-                    manifest.metrics.valid_aspas += 1;
-                    self.processor.process_aspa(uri, cert, aspa)?
-                }
-                Err(err) => {
-                    manifest.metrics.invalid_aspas += 1;
-                    warn!("{}: {}.", uri, err)
-                }
+        let aspa = match Aspa::decode(
+            content.clone(), self.run.validation.strict
+        ) {
+            Ok(aspa) => aspa,
+            Err(_) => {
+                manifest.metrics.invalid_aspas += 1;
+                self.add_rejected_object(uri, RejectionReason::DecodeError);
+                warn!("{}: failed to decode ASPA.", uri);
+                return Ok(())
+            }
+        };
+        if self.reject_overclaim(uri, aspa.cert(), manifest) {
+            manifest.metrics.invalid_aspas += 1;
+            self.add_rejected_object(uri, RejectionReason::Invalid);
+            return Ok(())
+        }
+        let issuer = self.cert.cert();
+        let strict = self.run.validation.strict;
+        let res = match self.run.validation.signed_object_time(
+            aspa.cert().validity().not_before()
+        ) {
+            None => {
+                aspa.process(issuer, strict, |cert| manifest.check_crl(cert))
+            }
+            Some(time) => {
+                validate_signed_at(content.clone(), issuer, strict, time)
+                .and_then(|cert| {
+                    manifest.check_crl(cert.as_ref())?;
+                    verify_aspa(aspa.content(), &cert)?;
+                    Ok((cert, aspa.content().clone()))
+                })
+            }
+        };
+        match res {
+            Ok((cert, aspa)) => {
+                manifest.metrics.valid_aspas += 1;
+                self.processor.process_aspa(uri, cert, aspa)?
+            }
+            Err(err) => {
+                manifest.metrics.invalid_aspas += 1;
+                let reason = self.signed_object_rejection(content, manifest);
+                self.add_rejected_object(uri, reason);
+                warn!("{}: {}.", uri, err)
             }
         }
         Ok(())
@@ -1483,26 +2925,39 @@ impl<'a, P: ProcessRun> PubPoint<'a, P> {
         manifest: &mut ValidPointManifest,
     ) -> Result<(), Failed> {
         let obj = match SignedObject::decode(
-            content, self.run.validation.strict
+            content.clone(), self.run.validation.strict
         ) {
             Ok(obj) => obj,
             Err(_) => {
                 manifest.metrics.invalid_gbrs += 1;
+                self.add_rejected_object(uri, RejectionReason::DecodeError);
                 warn!("{}: failed to decode GBR.", uri);
                 return Ok(())
             }
         };
-        match obj.process(
-            self.cert.cert(),
-            self.run.validation.strict,
-            |cert| manifest.check_crl(cert)
-        ) {
+        if self.reject_overclaim(uri, obj.cert(), manifest) {
+            manifest.metrics.invalid_gbrs += 1;
+            self.add_rejected_object(uri, RejectionReason::Invalid);
+            return Ok(())
+        }
+        let time = self.run.validation.skewed_time(
+            obj.cert().validity().not_before()
+        );
+        let gbr = obj.content().clone().into_bytes();
+        match obj.validate_at(
+            self.cert.cert(), self.run.validation.strict, time
+        ).and_then(|cert| {
+            manifest.check_crl(cert.as_ref())?;
+            Ok((cert, gbr))
+        }) {
             Ok((cert, content)) => {
                 manifest.metrics.valid_gbrs += 1;
                 self.processor.process_gbr(uri, cert, content)?
             }
             Err(err) => {
                 manifest.metrics.invalid_gbrs += 1;
+                let reason = self.signed_object_rejection(content, manifest);
+                self.add_rejected_object(uri, reason);
                 warn!("{}: {}.", uri, err)
             }
         }
@@ -1529,7 +2984,7 @@ struct ValidPointManifest {
     crl_uri: uri::Rsync,
 
     /// The CRL.
-    crl: Crl,
+    crl: RevocationList,
 
     /// The raw bytes of the manifest.
     manifest_bytes: Bytes,
@@ -1542,6 +2997,9 @@ struct ValidPointManifest {
     /// We keep them here separatedly, so we can just zero them out if
     /// processor decides to drop the publication point.
     metrics: PublicationMetrics,
+
+    /// The CAs rejected for exceeding limits while processing the manifest.
+    limit_violations: Vec<LimitViolation>,
 }
 
 impl ValidPointManifest {
@@ -1571,6 +3029,24 @@ impl ValidPointManifest {
         Ok(())
     }
 
+    /// Returns the reason for rejecting an object with the given certificate.
+    ///
+    /// This is used for the rejection report only. The time to validate
+    /// against is given via `now`.
+    fn rejection_reason(&self, cert: &Cert, now: Time) -> RejectionReason {
+        if self.crl.contains(cert.serial_number()) {
+            RejectionReason::Revoked
+        }
+        else if cert.validity().not_before() > now
+            || cert.validity().not_after() < now
+        {
+            RejectionReason::Expired
+        }
+        else {
+            RejectionReason::Invalid
+        }
+    }
+
     /// Reports the validity to the given processor.
     fn point_validity(&self, processor: &mut impl ProcessPubPoint) {
         processor.point_validity(
@@ -1579,7 +3055,8 @@ impl ValidPointManifest {
                 self.content.next_update(),
                 self.crl.next_update(),
             )
-        )
+        );
+        processor.point_published(self.content.this_update());
     }
 }
 
@@ -1611,6 +3088,139 @@ impl<'a, P> fmt::Debug for Task<'a, P> {
     }
 }
 
+impl<'a, P> Task<'a, P> {
+    /// Returns the index of the TAL the task belongs to.
+    fn tal(&self) -> usize {
+        match *self {
+            Task::Tal(ref inner) => inner.index,
+            Task::Ca(ref inner) => inner.cert.tal,
+        }
+    }
+}
+
+
+//------------ TaskQueues ----------------------------------------------------
+
+/// The queues of tasks waiting to be processed.
+///
+/// Normally, there is only one queue shared by all validation threads. If
+/// some TALs have been given a share of the validation threads, each of
+/// these TALs gets its own queue and the given share of the threads. The
+/// remaining TALs share the remaining threads. This way, a TAL with a large
+/// number of slow repositories can’t delay the others.
+///
+/// If the TALs sharing the remaining threads have different priorities,
+/// there is a separate queue for each priority. The threads take tasks
+/// from the queue with the highest priority first.
+///
+/// Threads take tasks from the queue they belong to. Once it is empty,
+/// they help out with the other queues rather than sitting idle.
+struct TaskQueues<'a, P> {
+    /// The queues.
+    ///
+    /// The queues of the TALs with a share come first, followed by the
+    /// queues for the TALs without a share ordered by descending priority.
+    queues: Vec<SegQueue<Task<'a, P>>>,
+
+    /// The index of the queue for each TAL.
+    tal_queues: Vec<usize>,
+
+    /// The number of threads for each queue.
+    threads: Vec<usize>,
+}
+
+impl<'a, P> TaskQueues<'a, P> {
+    /// Creates the queues for the given TALs.
+    fn new(
+        tals: &[Tal],
+        shares: &HashMap<String, u8>,
+        priorities: &HashMap<String, i32>,
+        threads: usize,
+    ) -> Self {
+        let threads = cmp::max(threads, 1);
+        let priority = |tal: &Tal| {
+            priorities.get(tal.info().name()).copied().unwrap_or(0)
+        };
+
+        // The distinct priorities of the TALs without a share, highest
+        // first.
+        let mut levels: Vec<_> = tals.iter().filter(|tal| {
+            !shares.contains_key(tal.info().name())
+        }).map(priority).collect();
+        levels.sort_unstable_by(|left, right| right.cmp(left));
+        levels.dedup();
+
+        let shared_index = tals.iter().filter(|tal| {
+            shares.contains_key(tal.info().name())
+        }).count();
+        let mut tal_queues = Vec::with_capacity(tals.len());
+        let mut queue_threads = Vec::new();
+        for tal in tals {
+            match shares.get(tal.info().name()) {
+                Some(share) => {
+                    tal_queues.push(queue_threads.len());
+                    queue_threads.push(cmp::max(
+                        threads * usize::from(*share) / 100, 1
+                    ));
+                }
+                None => {
+                    let level = priority(tal);
+                    tal_queues.push(
+                        shared_index + levels.iter().position(|item| {
+                            *item == level
+                        }).unwrap_or(0)
+                    );
+                }
+            }
+        }
+        let remaining = threads.saturating_sub(queue_threads.iter().sum());
+        queue_threads.push(
+            if levels.is_empty() { remaining }
+            else { cmp::max(remaining, 1) }
+        );
+
+        // The queues for lower priorities are served by the threads of the
+        // queue with the highest priority once it runs empty.
+        queue_threads.extend(levels.iter().skip(1).map(|_| 0));
+        TaskQueues {
+            queues: queue_threads.iter().map(|_| SegQueue::new()).collect(),
+            tal_queues,
+            threads: queue_threads,
+        }
+    }
+
+    /// Returns the total number of threads.
+    fn thread_count(&self) -> usize {
+        self.threads.iter().sum()
+    }
+
+    /// Returns an iterator over the queue index of each thread.
+    fn thread_homes(&self) -> impl Iterator<Item = usize> + '_ {
+        self.threads.iter().enumerate().flat_map(|(index, count)| {
+            (0..*count).map(move |_| index)
+        })
+    }
+
+    /// Adds a task to the queue of its TAL.
+    fn push(&self, task: Task<'a, P>) {
+        self.queues[self.tal_queues[task.tal()]].push(task)
+    }
+
+    /// Takes the next task for a thread of the given queue.
+    fn pop(&self, home: usize) -> Option<Task<'a, P>> {
+        self.queues[home].pop().or_else(|| {
+            self.queues.iter().cycle().skip(home + 1).take(
+                self.queues.len() - 1
+            ).find_map(SegQueue::pop)
+        })
+    }
+
+    /// Returns whether all queues are empty.
+    fn is_empty(&self) -> bool {
+        self.queues.iter().all(SegQueue::is_empty)
+    }
+}
+
 
 //------------ TalTask ------------------------------------------------------
 
@@ -1642,6 +3252,12 @@ struct CaTask<P> {
     /// Processing is deferred if the CA lives in a different repository than
     /// its issuing CA:
     defer: bool,
+
+    /// Has processing been postponed already?
+    ///
+    /// Processing is postponed once if the CA’s repository would have to
+    /// wait for a free rsync process.
+    postponed: bool,
 }
 
 
@@ -1678,6 +3294,9 @@ pub struct CaCert {
     /// The length of the chain of certificates from a trust anchor.
     chain_len: usize,
 
+    /// When the CA was found and processing of its subtree started.
+    started: Instant,
+
     /// The index of the TAL in the metrics.
     pub(crate) // XXX
     tal: usize,
@@ -1758,7 +3377,9 @@ impl CaCert {
             }
         };
         Ok(Arc::new(CaCert {
-            cert, uri, ca_repository, rpki_manifest, parent, chain_len, tal
+            cert, uri, ca_repository, rpki_manifest, parent, chain_len,
+            started: Instant::now(),
+            tal
         }))
     }
 
@@ -1783,6 +3404,18 @@ impl CaCert {
         }
     }
 
+    /// Returns the CA whose subtree has been processed for too long.
+    ///
+    /// Checks the CA itself and all its ancestors except for the trust
+    /// anchor and returns the one closest to the trust anchor whose
+    /// processing started more than `max` ago.
+    fn overrun_ancestor(&self, max: Duration) -> Option<&CaCert> {
+        let parent = self.parent.as_ref()?;
+        parent.overrun_ancestor(max).or_else(|| {
+            (self.started.elapsed() > max).then_some(self)
+        })
+    }
+
     /// Returns a reference to the resource certificate.
     pub fn cert(&self) -> &ResourceCert {
         &self.cert
@@ -1836,6 +3469,568 @@ impl CaCert {
 } 
 
 
+//------------ RejectionReport -----------------------------------------------
+
+/// A report of all objects rejected by a validation run.
+///
+/// At the end of each validation run, the file is replaced with a JSON
+/// object with the members `time` and `runId` describing the run and
+/// `rejectedObjects`, an array with one object for each rejected object.
+/// These objects have the members `uri`, `manifest`, `tal`, and `reason`,
+/// the latter containing the code of the reason for the rejection.
+#[derive(Debug)]
+struct RejectionReport {
+    /// The path of the report file.
+    path: PathBuf,
+}
+
+impl RejectionReport {
+    /// Creates a new value if a rejection report is configured.
+    fn new(config: &Config) -> Option<Self> {
+        config.rejection_report.as_ref().map(|path| {
+            RejectionReport { path: path.clone() }
+        })
+    }
+
+    /// Writes the report for the run described by `metrics`.
+    ///
+    /// As the report is informational only, errors are logged but otherwise
+    /// ignored.
+    fn write(&self, metrics: &Metrics) {
+        let content = JsonBuilder::build(|target| {
+            target.member_str("time", metrics.time.to_rfc3339());
+            match metrics.run_id {
+                Some(run_id) => target.member_str("runId", run_id),
+                None => target.member_raw("runId", "null"),
+            }
+            target.member_array("rejectedObjects", |target| {
+                for item in &metrics.rejected_objects {
+                    target.array_object(|target| {
+                        target.member_str("uri", &item.uri);
+                        target.member_str("manifest", &item.manifest);
+                        target.member_str("tal", &item.tal);
+                        target.member_str("reason", item.reason.code());
+                    })
+                }
+            });
+        });
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+        if let Err(err) = fs::write(&tmp_path, content).and_then(|_| {
+            fs::rename(&tmp_path, &self.path)
+        }) {
+            warn!(
+                "Failed to write rejection report {}: {}",
+                self.path.display(), err
+            );
+        }
+    }
+}
+
+
+//------------ SignatureCache ------------------------------------------------
+
+/// A cache of successful signature verifications of CRLs.
+///
+/// The cache remembers the SHA-256 digest over signed data and the encoded
+/// subject public key info of the key that successfully verified the
+/// signature. If the same data is encountered again with the same key, the
+/// costly public key operation can be skipped. Only successful
+/// verifications are cached.
+///
+/// Each entry records the generation – essentially the number of the
+/// validation run – when it was last used. When saving the cache at the end
+/// of a run, only the configured number of most recently used entries is
+/// kept.
+///
+/// The cache is kept in the file `signature-cache` in the cache directory.
+/// It starts with the current generation as an eight byte big-endian
+/// integer followed by the entries, each of them the 32 byte key followed
+/// by its generation as an eight byte big-endian integer.
+#[derive(Debug)]
+struct SignatureCache {
+    /// The path of the cache file.
+    path: PathBuf,
+
+    /// The maximum number of entries to keep.
+    max_entries: usize,
+
+    /// The current state of the cache.
+    state: Mutex<SignatureCacheState>,
+}
+
+/// The mutable state of the signature cache.
+#[derive(Debug, Default)]
+struct SignatureCacheState {
+    /// The current generation.
+    generation: u64,
+
+    /// The entries with the generation they were last used in.
+    entries: HashMap<[u8; 32], u64>,
+
+    /// Have the entries changed since loading?
+    changed: bool,
+}
+
+impl SignatureCache {
+    /// The name of the file in the cache directory holding the cache.
+    const FILE_NAME: &'static str = "signature-cache";
+
+    /// The size of an entry in the cache file.
+    const ENTRY_LEN: usize = 40;
+
+    /// Creates a new value if a signature cache is configured.
+    ///
+    /// Loads the cache from disk unless the config asks for a fresh start.
+    fn new(config: &Config) -> Option<Self> {
+        let max_entries = config.signature_cache_size?;
+        let path = config.cache_dir.join(Self::FILE_NAME);
+        let state = if config.fresh {
+            SignatureCacheState::default()
+        }
+        else {
+            Self::load(&path)
+        };
+        Some(SignatureCache {
+            path, max_entries,
+            state: Mutex::new(state),
+        })
+    }
+
+    /// Loads the cache from the file at `path`.
+    fn load(path: &Path) -> SignatureCacheState {
+        let content = match fs::read(path) {
+            Ok(content) => content,
+            Err(err) => {
+                if err.kind() != std::io::ErrorKind::NotFound {
+                    warn!(
+                        "Failed to read signature cache {}: {}",
+                        path.display(), err
+                    );
+                }
+                return SignatureCacheState::default()
+            }
+        };
+        if content.len() < 8 || (content.len() - 8) % Self::ENTRY_LEN != 0 {
+            warn!("Ignoring invalid signature cache {}.", path.display());
+            return SignatureCacheState::default()
+        }
+        let (generation, entries) = content.split_at(8);
+        SignatureCacheState {
+            generation: u64::from_be_bytes(
+                generation.try_into().expect("wrong slice length")
+            ),
+            entries: entries.chunks_exact(Self::ENTRY_LEN).map(|entry| {
+                let (key, generation) = entry.split_at(32);
+                (
+                    key.try_into().expect("wrong slice length"),
+                    u64::from_be_bytes(
+                        generation.try_into().expect("wrong slice length")
+                    )
+                )
+            }).collect(),
+            changed: false,
+        }
+    }
+
+    /// Returns the cache key for `data` signed by the key `key_info`.
+    ///
+    /// The key info is the encoded subject public key info of the key.
+    fn key(data: &[u8], key_info: &[u8]) -> [u8; 32] {
+        let mut context = ring::digest::Context::new(&ring::digest::SHA256);
+        context.update(&(data.len() as u64).to_be_bytes());
+        context.update(data);
+        context.update(key_info);
+        let mut res = [0u8; 32];
+        res.copy_from_slice(context.finish().as_ref());
+        res
+    }
+
+    /// Verifies a signature using the cache.
+    ///
+    /// If the cache has an entry for `data` and `key_info`, returns success
+    /// right away. Otherwise runs `op` and adds an entry if it succeeds.
+    fn verify<E>(
+        &self, data: &[u8], key_info: &[u8],
+        op: impl FnOnce() -> Result<(), E>,
+    ) -> Result<(), E> {
+        let key = Self::key(data, key_info);
+        {
+            let mut guard = self.state.lock().unwrap();
+            let state = &mut *guard;
+            if let Some(generation) = state.entries.get_mut(&key) {
+                if *generation != state.generation {
+                    *generation = state.generation;
+                    state.changed = true;
+                }
+                return Ok(())
+            }
+        }
+        op()?;
+        let mut state = self.state.lock().unwrap();
+        let generation = state.generation;
+        state.entries.insert(key, generation);
+        state.changed = true;
+        Ok(())
+    }
+
+    /// Writes the cache to disk and starts a new generation.
+    ///
+    /// Drops the least recently used entries beyond the configured maximum
+    /// first. As the cache is an optimization only, errors are logged but
+    /// otherwise ignored.
+    fn save(&self) {
+        let mut state = self.state.lock().unwrap();
+        if !state.changed {
+            return
+        }
+        if state.entries.len() > self.max_entries {
+            let mut entries: Vec<_> = state.entries.drain().collect();
+            entries.sort_unstable_by_key(|entry| cmp::Reverse(entry.1));
+            entries.truncate(self.max_entries);
+            state.entries = entries.into_iter().collect();
+        }
+        state.generation += 1;
+        state.changed = false;
+
+        let mut content = Vec::with_capacity(
+            8 + state.entries.len() * Self::ENTRY_LEN
+        );
+        content.extend_from_slice(&state.generation.to_be_bytes());
+        for (key, generation) in &state.entries {
+            content.extend_from_slice(key);
+            content.extend_from_slice(&generation.to_be_bytes());
+        }
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+        if let Err(err) = fs::write(&tmp_path, content).and_then(|_| {
+            fs::rename(&tmp_path, &self.path)
+        }) {
+            warn!(
+                "Failed to write signature cache {}: {}",
+                self.path.display(), err
+            );
+        }
+    }
+}
+
+
+//------------ ObjectCache ---------------------------------------------------
+
+/// A cache of decoded objects.
+///
+/// The cache keeps decoded certificates, manifests, and ROAs keyed by the
+/// SHA-256 digest of their encoded form, so unchanged objects don’t need to
+/// be decoded again in later validation runs. Only successfully decoded
+/// objects are cached.
+///
+/// The cache holds at most the configured number of entries. If it is
+/// full, adding a new entry evicts the least recently used one. For this
+/// purpose, each entry records the tick – a counter increased with every
+/// use of the cache – when it was last used.
+///
+/// The cache is kept in the file `object-cache` in the cache directory
+/// next to the store. Since the decoded objects have no representation
+/// other than their encoded form, each entry keeps the encoded object,
+/// too. The file contains these from the least to the most recently used,
+/// each of them a type byte followed by its length as a four byte
+/// big-endian integer and the encoded object. The objects are decoded
+/// again when the cache is loaded and keyed by the digest of their encoded
+/// form. Objects that fail to decode are dropped.
+#[derive(Debug)]
+struct ObjectCache {
+    /// The path of the cache file.
+    path: PathBuf,
+
+    /// The maximum number of entries to keep.
+    max_entries: usize,
+
+    /// The current state of the cache.
+    state: Mutex<ObjectCacheState>,
+}
+
+/// The mutable state of the object cache.
+#[derive(Debug, Default)]
+struct ObjectCacheState {
+    /// The tick of the most recent use of the cache.
+    tick: u64,
+
+    /// The entries with their encoded form and the tick they were last
+    /// used at.
+    entries: HashMap<[u8; 32], (CachedObject, Bytes, u64)>,
+
+    /// The keys of the entries ordered by the tick they were last used at.
+    used: BTreeMap<u64, [u8; 32]>,
+
+    /// Have the entries changed since loading?
+    changed: bool,
+}
+
+impl ObjectCacheState {
+    /// Advances and returns the tick.
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    /// Adds an entry as the most recently used one.
+    fn insert(&mut self, key: [u8; 32], object: CachedObject, data: Bytes) {
+        let tick = self.next_tick();
+        self.entries.insert(key, (object, data, tick));
+        self.used.insert(tick, key);
+    }
+}
+
+/// A decoded object kept in the object cache.
+#[derive(Clone, Debug)]
+enum CachedObject {
+    Cert(Cert),
+    Manifest(Manifest),
+    Roa(Roa),
+}
+
+impl CachedObject {
+    /// Decodes an object of the type given by `object_type`.
+    fn decode(object_type: u8, data: &Bytes, strict: bool) -> Option<Self> {
+        let data = data.clone();
+        match object_type {
+            0 => Cert::decode(data).ok().map(CachedObject::Cert),
+            1 => {
+                Manifest::decode(data, strict).ok().map(CachedObject::Manifest)
+            }
+            2 => Roa::decode(data, strict).ok().map(CachedObject::Roa),
+            _ => None
+        }
+    }
+
+    /// Returns the type byte of the object.
+    fn object_type(&self) -> u8 {
+        match self {
+            CachedObject::Cert(_) => 0,
+            CachedObject::Manifest(_) => 1,
+            CachedObject::Roa(_) => 2,
+        }
+    }
+}
+
+impl ObjectCache {
+    /// The name of the file in the cache directory holding the cache.
+    const FILE_NAME: &'static str = "object-cache";
+
+    /// Creates a new value if an object cache is configured.
+    ///
+    /// Loads the cache from disk unless the config asks for a fresh start.
+    fn new(config: &Config) -> Option<Self> {
+        let max_entries = config.object_cache_size?;
+        let path = config.cache_dir.join(Self::FILE_NAME);
+        let state = if config.fresh {
+            ObjectCacheState::default()
+        }
+        else {
+            Self::load(&path, max_entries, config.strict)
+        };
+        Some(ObjectCache {
+            path, max_entries,
+            state: Mutex::new(state),
+        })
+    }
+
+    /// Loads the cache from the file at `path`.
+    ///
+    /// Only the `max_entries` most recently used objects are kept.
+    fn load(
+        path: &Path, max_entries: usize, strict: bool
+    ) -> ObjectCacheState {
+        let mut state = ObjectCacheState::default();
+        let content = match fs::read(path) {
+            Ok(content) => Bytes::from(content),
+            Err(err) => {
+                if err.kind() != std::io::ErrorKind::NotFound {
+                    warn!(
+                        "Failed to read object cache {}: {}",
+                        path.display(), err
+                    );
+                }
+                return state
+            }
+        };
+        let mut objects = Vec::new();
+        let mut pos = 0;
+        while pos < content.len() {
+            let len = match content.get(pos + 1..pos + 5) {
+                Some(len) => {
+                    u32::from_be_bytes(
+                        len.try_into().expect("wrong slice length")
+                    ) as usize
+                }
+                None => {
+                    warn!("Ignoring invalid object cache {}.", path.display());
+                    return state
+                }
+            };
+            if content.len() - (pos + 5) < len {
+                warn!("Ignoring invalid object cache {}.", path.display());
+                return state
+            }
+            let start = pos + 5;
+            objects.push((content[pos], content.slice(start..start + len)));
+            pos = start + len;
+        }
+        let skip = objects.len().saturating_sub(max_entries);
+        for (object_type, data) in objects.into_iter().skip(skip) {
+            let mut key = [0u8; 32];
+            key.copy_from_slice(
+                ring::digest::digest(&ring::digest::SHA256, &data).as_ref()
+            );
+            if let Some(object) = CachedObject::decode(
+                object_type, &data, strict
+            ) {
+                state.insert(key, object, data);
+            }
+        }
+        state
+    }
+
+    /// Decodes an object using the cache.
+    ///
+    /// If the cache has an entry for `content`, returns a copy of it right
+    /// away. Otherwise decodes the object via `op` and adds an entry if that
+    /// succeeds, evicting the least recently used entry if the cache is
+    /// full.
+    fn decode<T: CacheableObject, E>(
+        &self, content: &Bytes,
+        op: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        let mut key = [0u8; 32];
+        key.copy_from_slice(
+            ring::digest::digest(&ring::digest::SHA256, content).as_ref()
+        );
+        {
+            let mut guard = self.state.lock().unwrap();
+            let state = &mut *guard;
+            let tick = state.next_tick();
+            if let Some((object, _, used)) = state.entries.get_mut(&key) {
+                if let Some(object) = T::from_cached(object) {
+                    state.used.remove(&*used);
+                    state.used.insert(tick, key);
+                    *used = tick;
+                    return Ok(object.clone())
+                }
+            }
+        }
+        let object = op()?;
+        if self.max_entries == 0 {
+            return Ok(object)
+        }
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+        if let Some((_, _, used)) = state.entries.remove(&key) {
+            // Another thread may have added the object in the meantime or
+            // the entry is of a different type.
+            state.used.remove(&used);
+        }
+        while state.entries.len() >= self.max_entries {
+            match state.used.pop_first() {
+                Some((_, evicted)) => {
+                    state.entries.remove(&evicted);
+                }
+                None => break
+            }
+        }
+        state.insert(key, object.clone().into_cached(), content.clone());
+        state.changed = true;
+        Ok(object)
+    }
+
+    /// Writes the cache to disk.
+    ///
+    /// As the cache is an optimization only, errors are logged but
+    /// otherwise ignored.
+    fn save(&self) {
+        let mut state = self.state.lock().unwrap();
+        if !state.changed {
+            return
+        }
+        state.changed = false;
+
+        let mut content = Vec::new();
+        for key in state.used.values() {
+            let (object, data) = match state.entries.get(key) {
+                Some((object, data, _)) => (object, data),
+                None => continue
+            };
+            content.push(object.object_type());
+            content.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            content.extend_from_slice(data);
+        }
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+        if let Err(err) = fs::write(&tmp_path, content).and_then(|_| {
+            fs::rename(&tmp_path, &self.path)
+        }) {
+            warn!(
+                "Failed to write object cache {}: {}",
+                self.path.display(), err
+            );
+        }
+    }
+}
+
+
+//------------ CacheableObject -----------------------------------------------
+
+/// A decoded object that can be kept in the object cache.
+trait CacheableObject: Clone {
+    /// Returns a reference to the object if the entry is of this type.
+    fn from_cached(object: &CachedObject) -> Option<&Self>;
+
+    /// Converts the object into a cache entry.
+    fn into_cached(self) -> CachedObject;
+}
+
+impl CacheableObject for Cert {
+    fn from_cached(object: &CachedObject) -> Option<&Self> {
+        match object {
+            CachedObject::Cert(cert) => Some(cert),
+            _ => None
+        }
+    }
+
+    fn into_cached(self) -> CachedObject {
+        CachedObject::Cert(self)
+    }
+}
+
+impl CacheableObject for Manifest {
+    fn from_cached(object: &CachedObject) -> Option<&Self> {
+        match object {
+            CachedObject::Manifest(manifest) => Some(manifest),
+            _ => None
+        }
+    }
+
+    fn into_cached(self) -> CachedObject {
+        CachedObject::Manifest(self)
+    }
+}
+
+impl CacheableObject for Roa {
+    fn from_cached(object: &CachedObject) -> Option<&Self> {
+        match object {
+            CachedObject::Roa(roa) => Some(roa),
+            _ => None
+        }
+    }
+
+    fn into_cached(self) -> CachedObject {
+        CachedObject::Roa(self)
+    }
+}
+
+
 //------------ RunMetrics ----------------------------------------------------
 
 /// The metrics collected during a engine run.
@@ -1850,6 +4045,21 @@ struct RunMetrics {
     /// The overall metrics.
     publication: PublicationMetrics,
 
+    /// The CAs rejected for exceeding limits.
+    limit_violations: Vec<LimitViolation>,
+
+    /// The files of updated manifests that could not be used.
+    missing_files: Vec<MissingFile>,
+
+    /// The publication points that don’t reissue their manifests in time.
+    stale_points: Vec<StalePoint>,
+
+    /// The number of objects of unknown type by their file extension.
+    unknown_objects: HashMap<String, u32>,
+
+    /// The objects rejected for the rejection report.
+    rejected_objects: Vec<RejectedObject>,
+
     /// The indexes of repositories in the repository metrics vec.
     ///
     /// The key is the string representation of the rpkiNotify or rsync
@@ -1864,6 +4074,11 @@ impl RunMetrics {
             tals: Default::default(),
             repositories: Default::default(),
             publication: Default::default(),
+            limit_violations: Vec::new(),
+            missing_files: Vec::new(),
+            stale_points: Vec::new(),
+            unknown_objects: HashMap::new(),
+            rejected_objects: Vec::new(),
             repository_indexes: self.repository_indexes.clone(),
         }
     }
@@ -1935,6 +4150,13 @@ impl RunMetrics {
             target.publication += metric
         }
         target.publication += self.publication;
+        target.limit_violations.extend(self.limit_violations);
+        target.missing_files.extend(self.missing_files);
+        target.stale_points.extend(self.stale_points);
+        for (kind, count) in self.unknown_objects {
+            *target.unknown_objects.entry(kind).or_default() += count;
+        }
+        target.rejected_objects.extend(self.rejected_objects);
     }
 }
 
@@ -1985,6 +4207,13 @@ pub trait ProcessPubPoint: Sized + Send + Sync {
         let _ = (manifest_ee, stale);
     }
 
+    /// Process the time the publication point was last published.
+    ///
+    /// This is the this-update time of the manifest.
+    fn point_published(&mut self, this_update: Time) {
+        let _ = this_update;
+    }
+
     /// Determines whether an object with the given URI should be processed.
     ///
     /// The object will only be processed if the method returns `Ok(true)`.
@@ -2089,6 +4318,63 @@ pub trait ProcessPubPoint: Sized + Send + Sync {
 }
 
 
+//------------ Helper Functions ----------------------------------------------
+
+/// Validates the signed object in `content` at the given time.
+///
+/// Returns the validated EE certificate. The typed signed objects of the
+/// rpki crate can only be validated at the current time and don’t give
+/// access to their inner signed object, so it is decoded again here.
+fn validate_signed_at(
+    content: Bytes, issuer: &ResourceCert, strict: bool, time: Time,
+) -> Result<ResourceCert, ValidationError> {
+    SignedObject::decode(content, strict)?.validate_at(issuer, strict, time)
+}
+
+/// Checks that the prefixes of a ROA are covered by its EE certificate.
+///
+/// This is the check the rpki crate performs when processing a ROA.
+fn verify_roa(
+    roa: &RouteOriginAttestation, cert: &ResourceCert,
+) -> Result<(), VerificationError> {
+    let covered = roa.v4_addrs().iter().all(|addr| {
+        cert.v4_resources().contains_roa(&addr)
+    }) && roa.v6_addrs().iter().all(|addr| {
+        cert.v6_resources().contains_roa(&addr)
+    });
+    if covered {
+        Ok(())
+    }
+    else {
+        Err(VerificationError::new("ROA prefix not covered by certificate"))
+    }
+}
+
+/// Checks that an ASPA is covered by its EE certificate.
+///
+/// This is the check the rpki crate performs when processing an ASPA.
+fn verify_aspa(
+    aspa: &AsProviderAttestation, cert: &ResourceCert,
+) -> Result<(), VerificationError> {
+    if !cert.as_resources().contains_asn(aspa.customer_as()) {
+        return Err(VerificationError::new(
+            "customer AS not covered by certificate"
+        ))
+    }
+    if cert.as_cert().as_resources().is_inherited() {
+        return Err(VerificationError::new(
+            "certificate contains inherited AS resources"
+        ))
+    }
+    if cert.as_cert().has_ip_resources() {
+        return Err(VerificationError::new(
+            "certificate contains IP resources"
+        ))
+    }
+    Ok(())
+}
+
+
 //============ Tests =========================================================
 
 #[cfg(test)]
@@ -2109,5 +4395,147 @@ mod test {
         let engine = Engine::new(&config, true).unwrap();
         engine.dump(&target).unwrap();
     }
+
+    #[test]
+    fn signature_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default_with_paths(
+            dir.path().into(), Default::default()
+        );
+        config.cache_dir = dir.path().into();
+        config.signature_cache_size = Some(2);
+        let key_info = b"key";
+        let ok = || Ok::<_, ()>(());
+        let err = || Err::<(), _>(());
+
+        let cache = SignatureCache::new(&config).unwrap();
+        assert_eq!(cache.verify(b"one", key_info, err), Err(()));
+        assert_eq!(cache.verify(b"one", key_info, ok), Ok(()));
+        assert_eq!(cache.verify(b"one", key_info, err), Ok(()));
+        assert_eq!(cache.verify(b"one", b"other key", err), Err(()));
+        cache.save();
+
+        // Entries survive reloading, the least recently used gets dropped.
+        let cache = SignatureCache::new(&config).unwrap();
+        assert_eq!(cache.verify(b"two", key_info, ok), Ok(()));
+        cache.save();
+        let cache = SignatureCache::new(&config).unwrap();
+        assert_eq!(cache.verify(b"three", key_info, ok), Ok(()));
+        cache.save();
+        let cache = SignatureCache::new(&config).unwrap();
+        assert_eq!(cache.verify(b"one", key_info, err), Err(()));
+        assert_eq!(cache.verify(b"two", key_info, err), Ok(()));
+        assert_eq!(cache.verify(b"three", key_info, err), Ok(()));
+    }
+
+    #[test]
+    fn object_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default_with_paths(
+            dir.path().into(), Default::default()
+        );
+        config.cache_dir = dir.path().into();
+        config.object_cache_size = Some(2);
+        let base = Path::new(env!("CARGO_MANIFEST_DIR")).join(
+            "test/self-test/repository/rpki.selftest.invalid/repo"
+        );
+        let ta = Bytes::from(fs::read(base.join("ta.cer")).unwrap());
+        let child = Bytes::from(fs::read(base.join("ta/child.cer")).unwrap());
+        let roa = Bytes::from(
+            fs::read(base.join("ta/as64496.roa")).unwrap()
+        );
+        let decode = |data: &Bytes| {
+            Cert::decode(data.clone()).map_err(|_| ())
+        };
+        let err = || Err::<Cert, _>(());
+
+        let cache = ObjectCache::new(&config).unwrap();
+        assert!(cache.decode(&ta, err).is_err());
+        assert!(cache.decode(&ta, || decode(&ta)).is_ok());
+        assert!(cache.decode(&ta, err).is_ok());
+        assert!(cache.decode(&child, || decode(&child)).is_ok());
+        assert!(cache.decode(&child, err).is_ok());
+
+        // The cache is full, so the least recently used entry is evicted.
+        assert!(cache.decode(&ta, err).is_ok());
+        assert!(cache.decode(&roa, || {
+            Roa::decode(roa.clone(), false).map_err(|_| ())
+        }).is_ok());
+        assert!(cache.decode(&roa, || Err::<Roa, _>(())).is_ok());
+        assert!(cache.decode(&ta, err).is_ok());
+        assert!(cache.decode(&child, err).is_err());
+
+        // Entries survive reloading, keeping only the most recently used.
+        cache.save();
+        let cache = ObjectCache::new(&config).unwrap();
+        assert!(cache.decode(&roa, || Err::<Roa, _>(())).is_ok());
+        assert!(cache.decode(&ta, err).is_ok());
+        assert!(cache.decode(&child, err).is_err());
+        config.object_cache_size = Some(1);
+        let cache = ObjectCache::new(&config).unwrap();
+        assert!(cache.decode(&ta, err).is_ok());
+        assert!(cache.decode(&roa, || Err::<Roa, _>(())).is_err());
+    }
+
+    #[test]
+    fn task_queues() {
+        let tals: Vec<_> = ["apnic", "arin", "ripe"].into_iter().map(|name| {
+            Tal::read_named(
+                name.into(),
+                &mut include_bytes!("../tals/ripe.tal").as_ref()
+            ).unwrap()
+        }).collect();
+
+        // Without shares, all TALs use the same queue.
+        let tasks = TaskQueues::<()>::new(
+            &tals, &HashMap::new(), &HashMap::new(), 8
+        );
+        assert_eq!(tasks.thread_count(), 8);
+        assert!(tasks.thread_homes().all(|home| home == 0));
+
+        // TALs with a share get their own queue and threads.
+        let shares = HashMap::from([
+            ("ripe".into(), 50), ("arin".into(), 1)
+        ]);
+        let tasks = TaskQueues::<()>::new(&tals, &shares, &HashMap::new(), 8);
+        assert_eq!(tasks.tal_queues, [2, 0, 1]);
+        assert_eq!(tasks.threads, [1, 4, 3]);
+        assert_eq!(
+            tasks.thread_homes().collect::<Vec<_>>(),
+            [0, 1, 1, 1, 1, 2, 2, 2]
+        );
+
+        // Threads help out once their own queue is empty.
+        for (index, tal) in tals.iter().enumerate() {
+            tasks.push(Task::Tal(TalTask { tal, index }));
+        }
+        assert_eq!(tasks.pop(1).map(|task| task.tal()), Some(2));
+        assert_eq!(tasks.pop(1).map(|task| task.tal()), Some(0));
+        assert_eq!(tasks.pop(1).map(|task| task.tal()), Some(1));
+        assert!(tasks.pop(1).is_none());
+        assert!(tasks.is_empty());
+
+        // TALs without a share are processed in order of their priority.
+        let priorities = HashMap::from([
+            ("apnic".into(), -1), ("ripe".into(), 5)
+        ]);
+        let tasks = TaskQueues::<()>::new(
+            &tals, &HashMap::new(), &priorities, 8
+        );
+        assert_eq!(tasks.tal_queues, [2, 1, 0]);
+        assert_eq!(tasks.threads, [8, 0, 0]);
+        for (index, tal) in tals.iter().enumerate() {
+            tasks.push(Task::Tal(TalTask { tal, index }));
+        }
+        assert_eq!(tasks.pop(0).map(|task| task.tal()), Some(2));
+        assert_eq!(tasks.pop(0).map(|task| task.tal()), Some(1));
+        assert_eq!(tasks.pop(0).map(|task| task.tal()), Some(0));
+        assert!(tasks.is_empty());
+
+        // Priorities only apply to TALs without a share.
+        let tasks = TaskQueues::<()>::new(&tals, &shares, &priorities, 8);
+        assert_eq!(tasks.tal_queues, [2, 0, 1]);
+        assert_eq!(tasks.threads, [1, 4, 3]);
+    }
 }
 
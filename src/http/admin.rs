@@ -0,0 +1,314 @@
+//! Common handling of the endpoints for administrative actions.
+
+use std::{fmt, fs, io, thread};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use chrono::Utc;
+use log::warn;
+use rpki::rtr::Serial;
+use tokio::sync::mpsc;
+use crate::config::Config;
+use crate::payload::PayloadDelta;
+use crate::utils::json::json_str;
+use super::request::Request;
+use super::response::{ContentType, Response, ResponseBuilder};
+
+//------------ Admin ---------------------------------------------------------
+
+/// The state shared by an administrative endpoint.
+///
+/// Administrative endpoints require a token presented as a bearer token
+/// and pass the requested action on to the validation via a channel. All
+/// requests are recorded in the audit log if one is configured.
+pub struct Admin<T> {
+    /// The token a request needs to present.
+    ///
+    /// If this is `None`, the endpoint is disabled.
+    token: Option<String>,
+
+    /// The name of the token recorded as the principal in the audit log.
+    principal: &'static str,
+
+    /// The sender for passing requested actions to the validation.
+    sender: mpsc::UnboundedSender<T>,
+
+    /// The audit log if one is configured.
+    audit: Option<Arc<AuditLog>>,
+}
+
+impl<T: fmt::Display> Admin<T> {
+    pub fn new(
+        token: Option<String>,
+        principal: &'static str,
+        sender: mpsc::UnboundedSender<T>,
+        audit: Option<Arc<AuditLog>>,
+    ) -> Self {
+        Self { token, principal, sender, audit }
+    }
+
+    /// Checks that the request is authorized.
+    ///
+    /// Returns the error response if it isn’t.
+    pub fn authorize(&self, req: &Request) -> Result<(), Response> {
+        let token = match self.token.as_ref() {
+            Some(token) => token,
+            None => {
+                self.record(req, false, None, "forbidden");
+                return Err(Response::forbidden())
+            }
+        };
+        if !req.has_bearer_token(token) {
+            self.record(req, false, None, "unauthorized");
+            return Err(Response::unauthorized())
+        }
+        Ok(())
+    }
+
+    /// Passes an action on to the validation.
+    ///
+    /// Returns a response with `body` if that succeeded or a response with
+    /// `unavailable` if the validation doesn’t accept actions anymore.
+    pub fn send(
+        &self, req: &Request, action: T,
+        body: String, unavailable: &'static str,
+    ) -> Response {
+        let target = action.to_string();
+        if self.sender.send(action).is_err() {
+            self.record(req, true, Some(&target), "unavailable");
+            return ResponseBuilder::service_unavailable()
+                .content_type(ContentType::TEXT)
+                .body(unavailable)
+        }
+        self.record(req, true, Some(&target), "accepted");
+        ResponseBuilder::accepted()
+            .content_type(ContentType::TEXT)
+            .body(body)
+    }
+
+    /// Records a request in the audit log.
+    ///
+    /// The principal is only recorded if the request was `authorized`.
+    fn record(
+        &self, req: &Request, authorized: bool,
+        action: Option<&str>, outcome: &str,
+    ) {
+        if let Some(audit) = self.audit.as_ref() {
+            audit.write(
+                req, authorized.then_some(self.principal), action, outcome
+            )
+        }
+    }
+}
+
+
+//------------ AuditLog ------------------------------------------------------
+
+/// A log of all requests to administrative endpoints.
+///
+/// For every request, one line is appended to the log file. Each line is a
+/// JSON object with the following members:
+///
+/// * `time`: the time the entry was created,
+/// * `principal`: the name of the token presented with the request or
+///   `null` if the request was refused before it was authorized,
+/// * `peer`: the address and port of the client,
+/// * `path`: the path of the endpoint,
+/// * `action`: a description of the requested action or `null` if the
+///   request was refused before it was parsed, and
+/// * `outcome`: one of `"accepted"`, `"unavailable"`, `"unauthorized"`, or
+///   `"forbidden"`.
+///
+/// Rejections due to malformed query parameters are not recorded.
+///
+/// In addition, after the first validation run completed following one or
+/// more accepted requests, a line with the `action` `"validation"` and the
+/// `outcome` `"completed"` is appended. Its additional members `serial`,
+/// `announced`, and `withdrawn` give the new serial and the number of
+/// payload items announced and withdrawn by the run. The server also
+/// records a line with the `principal` `"config"` when it starts with the
+/// `freeze` option. The members that don’t apply to these lines are
+/// `null`.
+///
+/// The lines are appended by a separate thread so that writing to the file
+/// never blocks the HTTP server.
+#[derive(Debug)]
+pub struct AuditLog {
+    /// The path of the log file.
+    path: PathBuf,
+
+    /// The sender for passing lines on to the writer thread.
+    writer: mpsc::UnboundedSender<String>,
+
+    /// Has a request been accepted since the last validation run?
+    pending: AtomicBool,
+}
+
+impl AuditLog {
+    /// Creates a new value if an audit log is configured.
+    ///
+    /// This starts the thread appending lines to the file.
+    pub fn new(config: &Config) -> Option<Arc<Self>> {
+        let path = config.http_audit_log.clone()?;
+        let (writer, mut lines) = mpsc::unbounded_channel::<String>();
+        let thread_path = path.clone();
+        thread::spawn(move || {
+            while let Some(line) = lines.blocking_recv() {
+                Self::append(&thread_path, &line)
+            }
+        });
+        Some(Arc::new(AuditLog {
+            path, writer,
+            pending: AtomicBool::new(false),
+        }))
+    }
+
+    /// Records that the server starts validating frozen data.
+    ///
+    /// Freezing can only be requested via the config, so the line has the
+    /// `"config"` principal.
+    pub fn write_freeze(&self) {
+        self.write_entry(
+            Some("config"), None, None, Some("validate frozen data"),
+            "accepted", None
+        );
+    }
+
+    /// Records the outcome of a validation run.
+    ///
+    /// The `delta` contains the changes since the previous version. A line
+    /// is only written if a request has been accepted since the last time
+    /// this method was called.
+    pub fn write_run(&self, serial: Serial, delta: Option<&PayloadDelta>) {
+        if !self.pending.swap(false, Ordering::Relaxed) {
+            return
+        }
+        let (announced, withdrawn) = match delta {
+            Some(delta) => (delta.announce_len(), delta.withdraw_len()),
+            None => (0, 0)
+        };
+        self.write_entry(
+            None, None, None, Some("validation"), "completed",
+            Some(format!(
+                ",\"serial\":{},\"announced\":{},\"withdrawn\":{}",
+                serial, announced, withdrawn
+            ))
+        );
+    }
+
+    /// Records a request.
+    fn write(
+        &self, req: &Request, principal: Option<&str>,
+        action: Option<&str>, outcome: &str,
+    ) {
+        if outcome == "accepted" {
+            self.pending.store(true, Ordering::Relaxed);
+        }
+        self.write_entry(
+            principal, Some(&req.peer().to_string()),
+            Some(req.uri().path()), action, outcome, None
+        );
+    }
+
+    /// Passes a line on to the writer thread.
+    ///
+    /// The `extra` members are appended to the JSON object and must start
+    /// with a comma.
+    fn write_entry(
+        &self,
+        principal: Option<&str>, peer: Option<&str>, path: Option<&str>,
+        action: Option<&str>, outcome: &str, extra: Option<String>,
+    ) {
+        let line = format!(
+            "{{\"time\":\"{}\",\"principal\":{},\"peer\":{},\
+             \"path\":{},\"action\":{},\"outcome\":\"{}\"{}}}\n",
+            Utc::now().to_rfc3339(),
+            Self::json_opt(principal), Self::json_opt(peer),
+            Self::json_opt(path), Self::json_opt(action),
+            outcome, extra.unwrap_or_default(),
+        );
+        if self.writer.send(line).is_err() {
+            warn!(
+                "Failed to write audit log {}: writer has stopped.",
+                self.path.display()
+            );
+        }
+    }
+
+    /// Returns an optional string as a JSON value.
+    fn json_opt(value: Option<&str>) -> String {
+        match value {
+            Some(value) => format!("\"{}\"", json_str(value)),
+            None => String::from("null"),
+        }
+    }
+
+    /// Appends a line to the log file at `path`.
+    ///
+    /// Errors are logged but otherwise ignored so that the administrative
+    /// actions remain available.
+    fn append(path: &Path, line: &str) {
+        if let Err(err) = fs::OpenOptions::new().create(true).append(
+            true
+        ).open(path).and_then(|mut file| {
+            file.write_all(line.as_bytes())
+        }) {
+            warn!("Failed to write audit log {}: {}", path.display(), err);
+        }
+    }
+
+    /// Handles a request to the endpoint returning the audit log.
+    ///
+    /// The endpoint `/api/v1/audit` returns all entries of the log as a
+    /// JSON array. The file is read on a blocking thread.
+    pub async fn handle_get_or_head(
+        this: Option<&Arc<Self>>, req: &Request
+    ) -> Option<Response> {
+        if req.uri().path() != "/api/v1/audit" {
+            return None
+        }
+        let this = match this {
+            Some(this) => this,
+            None => return Some(Response::not_found()),
+        };
+        if req.is_head() {
+            return Some(
+                ResponseBuilder::ok().content_type(ContentType::JSON).empty()
+            )
+        }
+        let path = this.path.clone();
+        let content = match tokio::task::spawn_blocking(move || {
+            fs::read_to_string(path)
+        }).await {
+            Ok(Ok(content)) => content,
+            Ok(Err(err)) if err.kind() == io::ErrorKind::NotFound => {
+                String::new()
+            }
+            Ok(Err(err)) => {
+                warn!(
+                    "Failed to read audit log {}: {}",
+                    this.path.display(), err
+                );
+                return Some(Response::internal_server_error())
+            }
+            Err(_) => return Some(Response::internal_server_error()),
+        };
+        let mut body = String::from("[");
+        let mut first = true;
+        for line in content.lines().filter(|line| !line.is_empty()) {
+            if first {
+                first = false;
+            }
+            else {
+                body.push(',');
+            }
+            body.push_str("\n  ");
+            body.push_str(line);
+        }
+        body.push_str("\n]\n");
+        Some(
+            ResponseBuilder::ok().content_type(ContentType::JSON).body(body)
+        )
+    }
+}
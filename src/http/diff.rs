@@ -0,0 +1,134 @@
+//! Handling of endpoints for the snapshot history.
+
+use std::str::FromStr;
+use rpki::rtr::Serial;
+use rpki::rtr::payload::RouteOrigin;
+use crate::payload::SharedHistory;
+use crate::utils::date::format_iso_date;
+use crate::utils::json::JsonBuilder;
+use super::request::Request;
+use super::response::{ContentType, Response, ResponseBuilder};
+
+
+//------------ handle_get_or_head --------------------------------------------
+
+pub fn handle_get_or_head(
+    req: &Request,
+    history: &SharedHistory,
+) -> Option<Response> {
+    match req.uri().path() {
+        "/api/v1/snapshots" => Some(handle_snapshots(req, history)),
+        "/api/v1/diff" => Some(handle_diff(req, history)),
+        _ => None
+    }
+}
+
+fn handle_snapshots(req: &Request, history: &SharedHistory) -> Response {
+    let history = history.read();
+    if !history.is_active() {
+        return Response::initial_validation()
+    }
+
+    if req.is_head() {
+        return ResponseBuilder::ok().content_type(ContentType::JSON).empty()
+    }
+
+    let res = JsonBuilder::build(|target| {
+        target.member_raw("session", history.session());
+        target.member_raw("serial", history.serial());
+        target.member_array("snapshots", |target| {
+            for (serial, snapshot) in history.snapshots() {
+                target.array_object(|target| {
+                    target.member_raw("serial", serial);
+                    target.member_str(
+                        "created", format_iso_date(snapshot.created())
+                    );
+                    target.member_raw(
+                        "routeOrigins", snapshot.origin_refs().count()
+                    );
+                })
+            }
+        })
+    });
+    ResponseBuilder::ok().content_type(ContentType::JSON).body(res)
+}
+
+fn handle_diff(req: &Request, history: &SharedHistory) -> Response {
+    let history = history.read();
+    if !history.is_active() {
+        return Response::initial_validation()
+    }
+
+    let mut from = None;
+    let mut to = None;
+    let query = req.uri().query().unwrap_or("");
+    for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+        let target = if key == "from" {
+            &mut from
+        }
+        else if key == "to" {
+            &mut to
+        }
+        else {
+            return Response::bad_request()
+        };
+        if target.is_some() {
+            return Response::bad_request()
+        }
+        match Serial::from_str(&value) {
+            Ok(serial) => *target = Some(serial),
+            Err(_) => return Response::bad_request()
+        }
+    }
+    let from = match from {
+        Some(from) => from,
+        None => return Response::bad_request()
+    };
+    let to = to.unwrap_or_else(|| history.serial());
+
+    let diff = match history.diff(from, to) {
+        Some(diff) => diff,
+        None => return Response::not_found()
+    };
+
+    if req.is_head() {
+        return ResponseBuilder::ok().content_type(ContentType::JSON).empty()
+    }
+
+    let res = JsonBuilder::build(|target| {
+        target.member_raw("session", history.session());
+        target.member_raw("from", diff.from());
+        target.member_raw("to", diff.to());
+        target.member_array("announced", |target| {
+            for origin in diff.announced() {
+                target.array_object(|target| origin_json(origin, target))
+            }
+        });
+        target.member_array("withdrawn", |target| {
+            for origin in diff.withdrawn() {
+                target.array_object(|target| origin_json(origin, target))
+            }
+        });
+        target.member_array("asns", |target| {
+            for (asn, changes) in diff.asn_changes() {
+                target.array_object(|target| {
+                    target.member_str("asn", asn);
+                    target.member_raw("announced", changes.announced);
+                    target.member_raw("withdrawn", changes.withdrawn);
+                })
+            }
+        });
+    });
+    ResponseBuilder::ok().content_type(ContentType::JSON).body(res)
+}
+
+fn origin_json(origin: &RouteOrigin, target: &mut JsonBuilder) {
+    target.member_str("asn", origin.asn);
+    target.member_str(
+        "prefix",
+        format_args!(
+            "{}/{}", origin.prefix.addr(), origin.prefix.prefix_len()
+        )
+    );
+    target.member_raw("maxLength", origin.prefix.resolved_max_len());
+}
@@ -6,7 +6,11 @@ use crate::config::Config;
 use crate::metrics::{HttpServerMetrics, SharedRtrServerMetrics};
 use crate::payload::SharedHistory;
 use crate::process::LogOutput;
-use super::{delta, log, metrics, payload, status, validity};
+use super::{
+    delta, diff, gbr, log, metrics, object, payload, refresh, status, tal,
+    unsafe_vrps, validity
+};
+use super::admin::AuditLog;
 use super::request::Request;
 use super::response::Response;
 
@@ -15,6 +19,10 @@ use super::response::Response;
 pub struct State {
     payload: payload::State,
     log: log::State,
+    object: object::State,
+    refresh: refresh::State,
+    tal: tal::State,
+    audit: Option<Arc<AuditLog>>,
     history: SharedHistory,
     metrics: Arc<HttpServerMetrics>,
     rtr_metrics: SharedRtrServerMetrics,
@@ -22,16 +30,24 @@ pub struct State {
 }
 
 impl State {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         config: &Config,
         history: SharedHistory,
         rtr_metrics: SharedRtrServerMetrics,
         log: Option<Arc<LogOutput>>,
         notify: NotifySender,
+        refresh: refresh::State,
+        tal: tal::State,
+        audit: Option<Arc<AuditLog>>,
     ) -> Self {
         Self {
             payload: payload::State::new(config),
             log: log::State::new(log),
+            object: object::State::new(config),
+            refresh,
+            tal,
+            audit,
             history,
             metrics: Arc::new(HttpServerMetrics::default()),
             rtr_metrics,
@@ -45,6 +61,15 @@ impl State {
 
     pub async fn handle_request(&self, req: Request) -> Response {
         self.metrics.inc_requests();
+        if req.is_post() {
+            if let Some(response) = self.refresh.handle_post(&req) {
+                return response
+            }
+            return match self.tal.handle_post(&req) {
+                Some(response) => response,
+                None => Response::method_not_allowed(),
+            }
+        }
         if !req.is_get_or_head() {
             return Response::method_not_allowed()
         }
@@ -81,6 +106,29 @@ impl State {
             &req, &self.history) {
             return response
         }
+        if let Some(response) = gbr::handle_get_or_head(
+            &req, &self.history
+        ) {
+            return response
+        }
+        if let Some(response) = diff::handle_get_or_head(
+            &req, &self.history
+        ) {
+            return response
+        }
+        if let Some(response) = unsafe_vrps::handle_get_or_head(
+            &req, &self.history
+        ) {
+            return response
+        }
+        if let Some(response) = AuditLog::handle_get_or_head(
+            self.audit.as_ref(), &req
+        ).await {
+            return response
+        }
+        if let Some(response) = self.object.handle_get_or_head(&req).await {
+            return response
+        }
 
         #[cfg(feature = "ui")]
         if let Some(response) = super::ui::handle_get_or_head(&req) {
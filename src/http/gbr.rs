@@ -0,0 +1,64 @@
+//! Handling of the Ghostbuster Records endpoint.
+
+use crate::payload::SharedHistory;
+use crate::utils::json::JsonBuilder;
+use super::request::Request;
+use super::response::{ContentType, Response, ResponseBuilder};
+
+
+//------------ handle_get_or_head --------------------------------------------
+
+pub fn handle_get_or_head(
+    req: &Request,
+    history: &SharedHistory,
+) -> Option<Response> {
+    if req.uri().path() != "/api/v1/gbrs" {
+        return None
+    }
+
+    let mut ca = None;
+    let mut tal = None;
+    let query = req.uri().query().unwrap_or("");
+    for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+        if key == "ca" {
+            ca = Some(value)
+        }
+        else if key == "tal" {
+            tal = Some(value)
+        }
+        else {
+            return Some(Response::bad_request())
+        }
+    }
+
+    let snapshot = match history.read().current() {
+        Some(snapshot) => snapshot,
+        None => return Some(Response::initial_validation())
+    };
+
+    if req.is_head() {
+        return Some(
+            ResponseBuilder::ok().content_type(ContentType::JSON).empty()
+        )
+    }
+
+    let res = JsonBuilder::build(|target| {
+        target.member_array("gbrs", |target| {
+            for item in snapshot.gbrs() {
+                if let Some(ca) = ca.as_deref() {
+                    let item_ca = item.ca.as_ref().map(|uri| uri.as_str());
+                    if item_ca != Some(ca) {
+                        continue
+                    }
+                }
+                if let Some(tal) = tal.as_ref() {
+                    if item.tal != *tal {
+                        continue
+                    }
+                }
+                target.array_object(|target| item.json(target))
+            }
+        })
+    });
+    Some(ResponseBuilder::ok().content_type(ContentType::JSON).body(res))
+}
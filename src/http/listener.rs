@@ -14,29 +14,52 @@ use log::error;
 use rpki::rtr::server::NotifySender;
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 use tokio::net::TcpListener;
+use tokio::sync::mpsc;
 use tokio_rustls::TlsAcceptor;
+use crate::collector::RefreshTarget;
 use crate::config::Config;
+use crate::engine::TalSwitch;
 use crate::error::ExitError;
 use crate::metrics::{HttpServerMetrics, SharedRtrServerMetrics};
 use crate::payload::SharedHistory;
 use crate::process::LogOutput;
 use crate::utils::{net, tls};
 use crate::utils::tls::MaybeTlsTcpStream;
+use super::admin::AuditLog;
 use super::dispatch::State;
+use super::request::Request;
+use super::{refresh, tal};
 
 
 //------------ http_listener -------------------------------------------------
 
 /// Returns a future for all HTTP server listeners.
+#[allow(clippy::too_many_arguments)]
 pub fn http_listener(
     origins: SharedHistory,
     rtr_metrics: SharedRtrServerMetrics,
     log: Option<Arc<LogOutput>>,
     config: &Config,
     notify: NotifySender,
+    refresh: mpsc::UnboundedSender<RefreshTarget>,
+    tals: mpsc::UnboundedSender<TalSwitch>,
+    audit: Option<Arc<AuditLog>>,
 ) -> Result<impl Future<Output = ()>, ExitError> {
+    let refresh_token = match config.http_refresh_token.as_ref() {
+        Some(secret) => Some(secret.load_string("HTTP refresh token")?),
+        None => None,
+    };
+    let tal_token = match config.http_tal_token.as_ref() {
+        Some(secret) => Some(secret.load_string("HTTP TAL token")?),
+        None => None,
+    };
     let state = Arc::new(
-        State::new(config, origins, rtr_metrics, log, notify)
+        State::new(
+            config, origins, rtr_metrics, log, notify,
+            refresh::State::new(refresh_token, refresh, audit.clone()),
+            tal::State::new(tal_token, tals, audit.clone()),
+            audit,
+        )
     );
 
     // Binding needs to have happened before dropping privileges
@@ -59,8 +82,8 @@ pub fn http_listener(
 fn create_tls_config(
     config: &Config
 ) -> Result<Arc<tls::ServerConfig>, ExitError> {
-    let key_path = match config.http_tls_key.as_ref() {
-        Some(path) => path.as_ref(),
+    let key = match config.http_tls_key.as_ref() {
+        Some(key) => key,
         None => {
             error!("Missing http-tls-key option for HTTP TLS server.");
             return Err(ExitError::Generic)
@@ -73,7 +96,7 @@ fn create_tls_config(
             return Err(ExitError::Generic)
         }
     };
-    tls::create_server_config("HTTP", key_path, cert_path).map(Arc::new)
+    tls::create_server_config("HTTP", key, cert_path).map(Arc::new)
 }
 
 async fn _http_listener(
@@ -119,7 +142,7 @@ async fn single_http_listener(
         metrics: state.metrics().clone(),
     };
     loop {
-        let stream = match listener.accept().await {
+        let (stream, peer) = match listener.accept().await {
             Ok(some) => some,
             Err(err) => {
                 error!("Fatal error in HTTP server {}: {}", addr, err);
@@ -135,7 +158,9 @@ async fn single_http_listener(
                 service_fn(move |req| {
                     let state = service_state.clone();
                     async move {
-                        state.handle_request(req.into()).await.into_hyper()
+                        state.handle_request(
+                            Request::new(req, peer)
+                        ).await.into_hyper()
                     }
                 })
             ).await;
@@ -153,13 +178,18 @@ struct HttpAccept {
 }
 
 impl HttpAccept {
-    async fn accept(&self) -> Result<HttpStream, io::Error> {
-        let (sock, _) = self.sock.accept().await?;
+    async fn accept(
+        &self
+    ) -> Result<(HttpStream, SocketAddr), io::Error> {
+        let (sock, peer) = self.sock.accept().await?;
         self.metrics.inc_conn_open();
-        Ok(HttpStream {
-            sock: MaybeTlsTcpStream::new(sock, self.tls.as_ref()),
-            metrics: self.metrics.clone()
-        })
+        Ok((
+            HttpStream {
+                sock: MaybeTlsTcpStream::new(sock, self.tls.as_ref()),
+                metrics: self.metrics.clone()
+            },
+            peer
+        ))
     }
 }
 
@@ -1,13 +1,16 @@
 //! Handling of the metrics endpoint.
 
 use std::{cmp, fmt};
+use std::collections::BTreeMap;
 use std::fmt::Write;
 use chrono::Utc;
+use crate::collector::RrdpLimit;
 use crate::config::FilterPolicy;
 use crate::metrics::{
-    HttpServerMetrics, Metrics, PayloadMetrics, PublicationMetrics,
-    RrdpRepositoryMetrics, RsyncModuleMetrics, SharedRtrServerMetrics,
-    VrpMetrics
+    CacheMetrics, DivergenceKind, DivergenceMetrics, FailingRepository, FailureReason, HttpServerMetrics, LimitKind,
+    LimitViolation, Metrics, MissingFile, MissingFileReason, PayloadMetrics, ProbeMetrics, PropagationMetrics,
+    PublicationMetrics, RrdpRepositoryMetrics, RsyncModuleMetrics,
+    SharedRtrServerMetrics, StalePoint, TalMetrics, VrpMetrics
 };
 use crate::payload::SharedHistory;
 use super::request::Request;
@@ -39,13 +42,18 @@ async fn handle_metrics(
     http: &HttpServerMetrics,
     rtr: &SharedRtrServerMetrics,
 ) -> Response {
-    let (metrics, serial, start, done, duration, unsafe_vrps) = {
+    let (
+        metrics, probes, propagation, serial, start, done, duration,
+        unsafe_vrps
+    ) = {
         let history = history.read();
         (
             match history.metrics() {
                 Some(metrics) => metrics,
                 None => return Response::initial_validation(),
             },
+            history.probes(),
+            history.propagation(),
             history.serial(),
             history.last_update_start(),
             history.last_update_done(),
@@ -106,11 +114,18 @@ async fn handle_metrics(
         serial
     );
 
+    // Propagation delay of announced payload.
+    propagation_metrics(&mut target, &propagation);
+
     // Per-TA metrics.
     pub_point_metrics(
         &mut target, Group::Ta,
         metrics.tals.iter().map(|m| (m.tal.name(), &m.publication))
     );
+    stored_point_metrics(
+        &mut target, Group::Ta,
+        metrics.tals.iter().map(|m| (m.tal.name(), &m.publication))
+    );
     object_metrics(
         &mut target, Group::Ta,
         metrics.tals.iter().map(|m| (m.tal.name(), &m.publication))
@@ -123,12 +138,18 @@ async fn handle_metrics(
         &mut target, Group::Ta, unsafe_vrps,
         metrics.tals.iter().map(|m| (m.tal.name(), &m.payload))
     );
+    tal_duration_metrics(&mut target, &metrics.tals);
+    tal_disabled_metrics(&mut target, &metrics.tals);
 
     // Per-repository metrics.
     pub_point_metrics(
         &mut target, Group::Repository,
         metrics.repositories.iter().map(|m| (m.uri.as_ref(), &m.publication))
     );
+    stored_point_metrics(
+        &mut target, Group::Repository,
+        metrics.repositories.iter().map(|m| (m.uri.as_ref(), &m.publication))
+    );
     object_metrics(
         &mut target, Group::Repository,
         metrics.repositories.iter().map(|m| (m.uri.as_ref(), &m.publication))
@@ -152,9 +173,53 @@ async fn handle_metrics(
         metrics.local.vrps().contributed
     );
 
+    // Redundant VRPs.
+    target.single(
+        Metric::new(
+            "vrps_duplicate_roas",
+            "VRPs published by more than one ROA",
+            MetricType::Gauge
+        ),
+        metrics.overlaps.duplicate
+    );
+    target.single(
+        Metric::new(
+            "vrps_shadowed",
+            "VRPs covered by another VRP for the same AS",
+            MetricType::Gauge
+        ),
+        metrics.overlaps.shadowed
+    );
+
+    // Delegation limit violations.
+    limit_metrics(&mut target, &metrics.limit_violations);
+
+    // Files missing from updated manifests.
+    missing_file_metrics(&mut target, &metrics.missing_files);
+
+    // Objects of unknown type.
+    unknown_object_metrics(&mut target, &metrics.unknown_objects);
+
+    // Publication points not reissuing their manifests in time.
+    stale_point_metrics(&mut target, &metrics.stale_points);
+
+    // Repositories failing to update.
+    failing_repository_metrics(&mut target, &metrics.failing_repositories);
+
+    // Size of the collected data.
+    if let Some(cache) = metrics.cache.as_ref() {
+        cache_metrics(&mut target, cache);
+    }
+
+    // Differences between collector and store.
+    if let Some(divergence) = metrics.divergence.as_ref() {
+        divergence_metrics(&mut target, divergence);
+    }
+
     // Collector metrics.
     rrdp_metrics(&mut target, &metrics.rrdp);
     rsync_metrics(&mut target, &metrics.rsync);
+    probe_metrics(&mut target, &probes);
 
     // Server metrics.
     rtr_metrics(&mut target, rtr).await;
@@ -166,6 +231,242 @@ async fn handle_metrics(
     target.into_response()
 }
 
+fn tal_duration_metrics(target: &mut Target, tals: &[TalMetrics]) {
+    let metric = Metric::new(
+        "ta_duration",
+        "time until all data of the trust anchor was processed in seconds",
+        MetricType::Gauge
+    );
+    target.header(metric);
+    for tal in tals {
+        if let Some(value) = tal.duration {
+            target.multi(metric).label("name", tal.name()).value(
+                format_args!(
+                    "{}.{:03}",
+                    value.as_secs(),
+                    value.subsec_millis(),
+                )
+            )
+        }
+    }
+}
+
+fn tal_disabled_metrics(target: &mut Target, tals: &[TalMetrics]) {
+    let metric = Metric::new(
+        "ta_disabled",
+        "whether the trust anchor was disabled at runtime and skipped",
+        MetricType::Gauge
+    );
+    target.header(metric);
+    for tal in tals {
+        target.multi(metric).label("name", tal.name()).value(
+            u8::from(tal.disabled)
+        )
+    }
+}
+
+fn limit_metrics(target: &mut Target, violations: &[LimitViolation]) {
+    let metric = Metric::new(
+        "limit_violations",
+        "CAs rejected for exceeding delegation limits",
+        MetricType::Gauge
+    );
+    target.header(metric);
+    for limit in LimitKind::ALL {
+        target.multi(metric).label("limit", limit.code()).value(
+            violations.iter().filter(|item| item.limit == limit).count()
+        );
+    }
+}
+
+fn missing_file_metrics(target: &mut Target, files: &[MissingFile]) {
+    let metric = Metric::new(
+        "missing_files",
+        "files listed on updated manifests that could not be used",
+        MetricType::Gauge
+    );
+    target.header(metric);
+    for reason in MissingFileReason::ALL {
+        target.multi(metric).label("reason", reason.code()).value(
+            files.iter().filter(|item| item.reason == reason).count()
+        );
+    }
+}
+
+fn unknown_object_metrics(
+    target: &mut Target, objects: &BTreeMap<String, u32>
+) {
+    let metric = Metric::new(
+        "unknown_objects",
+        "objects of unknown type listed on manifests by file extension",
+        MetricType::Gauge
+    );
+    target.header(metric);
+    for (kind, count) in objects {
+        target.multi(metric).label("type", kind).value(count);
+    }
+}
+
+/// The number of worst stale points reported individually.
+const WORST_STALE_POINTS: usize = 10;
+
+fn stale_point_metrics(target: &mut Target, points: &[StalePoint]) {
+    let metric = Metric::new(
+        "stale_publication_points",
+        "publication points not reissuing their manifests in time",
+        MetricType::Gauge
+    );
+    target.header(metric);
+    target.multi(metric).label("state", "overdue").value(
+        points.iter().filter(|item| item.overdue).count()
+    );
+    target.multi(metric).label("state", "chronic").value(
+        points.iter().filter(|item| item.chronic).count()
+    );
+
+    let metric = Metric::new(
+        "stale_publication_point_late_reissues",
+        "recent late manifest reissues of the worst stale points",
+        MetricType::Gauge
+    );
+    target.header(metric);
+    for item in points.iter().take(WORST_STALE_POINTS) {
+        target.multi(metric).label("manifest", &item.manifest).value(
+            item.late_reissues
+        );
+    }
+
+    let metric = Metric::new(
+        "stale_publication_point_max_delay",
+        "largest recent manifest reissue delay of the worst stale points \
+         in seconds",
+        MetricType::Gauge
+    );
+    target.header(metric);
+    for item in points.iter().take(WORST_STALE_POINTS) {
+        target.multi(metric).label("manifest", &item.manifest).value(
+            item.max_delay
+        );
+    }
+}
+
+fn failing_repository_metrics(
+    target: &mut Target, repositories: &[FailingRepository]
+) {
+    let metric = Metric::new(
+        "failing_repositories",
+        "repositories whose last update failed",
+        MetricType::Gauge
+    );
+    target.header(metric);
+    for reason in FailureReason::ALL {
+        target.multi(metric).label("reason", reason.code()).value(
+            repositories.iter().filter(|item| item.reason == reason).count()
+        );
+    }
+
+    let repositories: Vec<_> = repositories.iter().filter(|item| {
+        item.dead
+    }).collect();
+
+    let metric = Metric::new(
+        "dead_repositories",
+        "repositories failing to update for a long time",
+        MetricType::Gauge
+    );
+    target.header(metric);
+    target.single(metric, repositories.len());
+
+    let metric = Metric::new(
+        "dead_repository_failures",
+        "number of consecutive failed updates of dead repositories",
+        MetricType::Gauge
+    );
+    target.header(metric);
+    for item in &repositories {
+        target.multi(metric).label("uri", &item.uri).value(item.failures);
+    }
+
+    let metric = Metric::new(
+        "dead_repository_failing_seconds",
+        "seconds since the first failed update of dead repositories",
+        MetricType::Gauge
+    );
+    target.header(metric);
+    let now = Utc::now();
+    for item in &repositories {
+        target.multi(metric).label("uri", &item.uri).value(
+            now.signed_duration_since(item.since).num_seconds()
+        );
+    }
+}
+
+fn cache_metrics(target: &mut Target, cache: &CacheMetrics) {
+    let metric = Metric::new(
+        "cache_size",
+        "on-disk size of the collected data in bytes",
+        MetricType::Gauge
+    );
+    target.header(metric);
+    target.multi(metric).label("type", "rrdp").value(cache.size(true));
+    target.multi(metric).label("type", "rsync").value(cache.size(false));
+
+    let metric = Metric::new(
+        "cache_repositories",
+        "number of repositories present in the collected data",
+        MetricType::Gauge
+    );
+    target.header(metric);
+    target.multi(metric).label("type", "rrdp").value(cache.count(true));
+    target.multi(metric).label("type", "rsync").value(cache.count(false));
+
+    let metric = Metric::new(
+        "cache_evicted_repositories",
+        "repositories removed to stay within the cache size limit",
+        MetricType::Gauge
+    );
+    target.header(metric);
+    target.single(metric, cache.evicted.len());
+
+    let metric = Metric::new(
+        "cache_evicted_bytes",
+        "size of the repositories removed to stay within the size limit",
+        MetricType::Gauge
+    );
+    target.header(metric);
+    target.single(metric, cache.evicted_size());
+}
+
+fn divergence_metrics(target: &mut Target, divergence: &DivergenceMetrics) {
+    let metric = Metric::new(
+        "crosscheck_points",
+        "publication points compared between collector and store",
+        MetricType::Gauge
+    );
+    target.header(metric);
+    target.single(metric, divergence.points);
+
+    let metric = Metric::new(
+        "crosscheck_divergent_points",
+        "publication points that differ between collector and store",
+        MetricType::Gauge
+    );
+    target.header(metric);
+    target.single(metric, divergence.divergent_points());
+
+    let metric = Metric::new(
+        "crosscheck_divergent_objects",
+        "objects that differ between collector and store",
+        MetricType::Gauge
+    );
+    target.header(metric);
+    for kind in DivergenceKind::ALL {
+        target.multi(metric).label("kind", kind.code()).value(
+            divergence.count(kind)
+        );
+    }
+}
+
 fn pub_point_metrics<'a>(
     target: &mut Target, group: Group,
     metrics: impl Iterator<Item = (&'a str, &'a PublicationMetrics)>
@@ -186,6 +487,26 @@ fn pub_point_metrics<'a>(
     }
 }
 
+fn stored_point_metrics<'a>(
+    target: &mut Target, group: Group,
+    metrics: impl Iterator<Item = (&'a str, &'a PublicationMetrics)>
+) {
+    let metric = Metric::with_prefix(
+        group.prefix(), "stored_publication_points_total",
+        ("publication points using old stored data per ", group.help()),
+        MetricType::Gauge
+    );
+    target.header(metric);
+    for (name, metrics) in metrics {
+        target.multi(metric).label(group.label(), name)
+            .label("state", "stale")
+            .value(metrics.stale_stored_points);
+        target.multi(metric).label(group.label(), name)
+            .label("state", "expired")
+            .value(metrics.expired_stored_points);
+    }
+}
+
 fn object_metrics<'a>(
     target: &mut Target, group: Group,
     metrics: impl Iterator<Item = (&'a str, &'a PublicationMetrics)>
@@ -213,10 +534,18 @@ fn object_metrics<'a>(
             .label("type", "manifest")
             .label("state", "stale")
             .value(metrics.stale_manifests);
+        target.multi(metric).label(group.label(), name)
+            .label("type", "manifest")
+            .label("state", "grace")
+            .value(metrics.grace_manifests);
         target.multi(metric).label(group.label(), name)
             .label("type", "manifest")
             .label("state", "missing")
             .value(metrics.missing_manifests);
+        target.multi(metric).label(group.label(), name)
+            .label("type", "file")
+            .label("state", "missing")
+            .value(metrics.missing_files);
 
         target.multi(metric).label(group.label(), name)
             .label("type", "crl")
@@ -230,6 +559,10 @@ fn object_metrics<'a>(
             .label("type", "crl")
             .label("state", "stale")
             .value(metrics.stale_crls);
+        target.multi(metric).label(group.label(), name)
+            .label("type", "crl")
+            .label("state", "grace")
+            .value(metrics.grace_crls);
         target.multi(metric).label(group.label(), name)
             .label("type", "crl")
             .label("state", "stray")
@@ -247,6 +580,10 @@ fn object_metrics<'a>(
             .label("type", "cert")
             .label("state", "invalid")
             .value(metrics.invalid_certs);
+        target.multi(metric).label(group.label(), name)
+            .label("type", "cert")
+            .label("state", "overclaiming")
+            .value(metrics.overclaiming_certs);
 
         target.multi(metric).label(group.label(), name)
             .label("type", "roa")
@@ -257,16 +594,14 @@ fn object_metrics<'a>(
             .label("state", "invalid")
             .value(metrics.invalid_roas);
 
-        #[cfg(feature = "aspa")] {
-            target.multi(metric).label(group.label(), name)
-                .label("type", "aspa")
-                .label("state", "valid")
-                .value(metrics.valid_aspas);
-            target.multi(metric).label(group.label(), name)
-                .label("type", "aspa")
-                .label("state", "invalid")
-                .value(metrics.invalid_aspas);
-        }
+        target.multi(metric).label(group.label(), name)
+            .label("type", "aspa")
+            .label("state", "valid")
+            .value(metrics.valid_aspas);
+        target.multi(metric).label(group.label(), name)
+            .label("type", "aspa")
+            .label("state", "invalid")
+            .value(metrics.invalid_aspas);
 
         target.multi(metric).label(group.label(), name)
             .label("type", "gbr")
@@ -306,6 +641,24 @@ fn vrp_metrics<'a>(
         ("number of VRPs filtered out by local exceptions per ", group.help()),
         MetricType::Gauge
     );
+    let policy_filtered_metric = Metric::with_prefix(
+        group.prefix(), "policy_filtered_vrps_total",
+        ("number of VRPs filtered out by the ROA policy per ", group.help()),
+        MetricType::Gauge
+    );
+    let policy_flagged_metric = Metric::with_prefix(
+        group.prefix(), "policy_flagged_vrps_total",
+        ("number of VRPs flagged by the ROA policy per ", group.help()),
+        MetricType::Gauge
+    );
+    let length_filtered_metric = Metric::with_prefix(
+        group.prefix(), "length_filtered_vrps_total",
+        (
+            "number of VRPs filtered out for their prefix length per ",
+            group.help()
+        ),
+        MetricType::Gauge
+    );
     let duplicate_metric = Metric::with_prefix(
         group.prefix(), "duplicate_vrps_total",
         ("number of duplicate VRPs per ", group.help()),
@@ -322,6 +675,9 @@ fn vrp_metrics<'a>(
         target.header(unsafe_metric);
     }
     target.header(filtered_metric);
+    target.header(policy_filtered_metric);
+    target.header(policy_flagged_metric);
+    target.header(length_filtered_metric);
     target.header(duplicate_metric);
     target.header(contributed_metric);
     for (name, metrics) in metrics {
@@ -333,6 +689,12 @@ fn vrp_metrics<'a>(
         }
         target.multi(filtered_metric).label(group.label(), name)
             .value(metrics.locally_filtered);
+        target.multi(policy_filtered_metric).label(group.label(), name)
+            .value(metrics.policy_filtered);
+        target.multi(policy_flagged_metric).label(group.label(), name)
+            .value(metrics.policy_flagged);
+        target.multi(length_filtered_metric).label(group.label(), name)
+            .value(metrics.length_filtered);
         target.multi(duplicate_metric).label(group.label(), name)
             .value(metrics.duplicate);
         target.multi(contributed_metric).label(group.label(), name)
@@ -419,21 +781,34 @@ fn payload_metrics<'a>(
                 .value(metrics.contributed);
         }
 
-        #[cfg(feature = "aspa")] {
-            target.multi(valid_metric)
-                .label(group.label(), name)
-                .label("type", "aspas")
-                .value(metrics.aspas.valid);
-            target.multi(duplicate_metric)
-                .label(group.label(), name)
-                .label("type", "aspas")
-                .value(metrics.aspas.duplicate);
-            target.multi(contributed_metric)
-                .label(group.label(), name)
-                .label("type", "aspas")
-                .value(metrics.aspas.contributed);
-        }
+        target.multi(valid_metric)
+            .label(group.label(), name)
+            .label("type", "aspas")
+            .value(metrics.aspas.valid);
+        target.multi(duplicate_metric)
+            .label(group.label(), name)
+            .label("type", "aspas")
+            .value(metrics.aspas.duplicate);
+        target.multi(contributed_metric)
+            .label(group.label(), name)
+            .label("type", "aspas")
+            .value(metrics.aspas.contributed);
+    }
+}
+
+fn propagation_metrics(target: &mut Target, metrics: &PropagationMetrics) {
+    let metric = Metric::new(
+        "propagation_delay_seconds",
+        "time from publication to inclusion in an RTR serial",
+        MetricType::Histogram
+    );
+    target.header(metric);
+    for (bound, count) in metrics.cumulative() {
+        metric.bucket(target, bound, count);
     }
+    metric.bucket(target, "+Inf", metrics.count());
+    metric.suffixed(target, "sum", metrics.sum());
+    metric.suffixed(target, "count", metrics.count());
 }
 
 fn rrdp_metrics(target: &mut Target, metrics: &[RrdpRepositoryMetrics]) {
@@ -455,6 +830,36 @@ fn rrdp_metrics(target: &mut Target, metrics: &[RrdpRepositoryMetrics]) {
         MetricType::Gauge
     );
     target.header(payload_status);
+    let head_skipped = Metric::new(
+        "rrdp_head_skipped",
+        "whether the RRDP update was skipped after a HEAD request",
+        MetricType::Gauge
+    );
+    target.header(head_skipped);
+    let requests = Metric::new(
+        "rrdp_requests",
+        "number of HTTP GET requests sent for the RRDP update",
+        MetricType::Gauge
+    );
+    target.header(requests);
+    let bytes = Metric::new(
+        "rrdp_bytes",
+        "number of bytes received for the RRDP update",
+        MetricType::Gauge
+    );
+    target.header(bytes);
+    let deltas = Metric::new(
+        "rrdp_deltas",
+        "number of deltas applied during the RRDP update",
+        MetricType::Gauge
+    );
+    target.header(deltas);
+    let rrdp_family = Metric::new(
+        "rrdp_address_family",
+        "IP version used for getting the RRDP notification file",
+        MetricType::Gauge
+    );
+    target.header(rrdp_family);
     let duration = Metric::new(
         "rrdp_duration",
         "duration of RRDP update in seconds",
@@ -478,6 +883,23 @@ fn rrdp_metrics(target: &mut Target, metrics: &[RrdpRepositoryMetrics]) {
         target.multi(payload_status).label("uri", &rrdp.notify_uri).value(
             rrdp.payload_status.map(|status| status.into_i16()).unwrap_or(0)
         );
+        target.multi(head_skipped).label("uri", &rrdp.notify_uri).value(
+            u8::from(rrdp.head_skipped)
+        );
+        target.multi(requests).label("uri", &rrdp.notify_uri).value(
+            rrdp.requests
+        );
+        target.multi(bytes).label("uri", &rrdp.notify_uri).value(
+            rrdp.bytes
+        );
+        target.multi(deltas).label("uri", &rrdp.notify_uri).value(
+            rrdp.deltas
+        );
+        if let Some(family) = rrdp.address_family {
+            target.multi(rrdp_family).label("uri", &rrdp.notify_uri).value(
+                family.version()
+            );
+        }
         if let Ok(value) = rrdp.duration {
             target.multi(duration).label("uri", &rrdp.notify_uri).value(
                 format_args!(
@@ -491,6 +913,20 @@ fn rrdp_metrics(target: &mut Target, metrics: &[RrdpRepositoryMetrics]) {
             target.multi(serial).label("uri", &rrdp.notify_uri).value(value)
         }
     }
+
+    let limit_violations = Metric::new(
+        "rrdp_limit_violations",
+        "RRDP repositories exceeding content limits during the update",
+        MetricType::Gauge
+    );
+    target.header(limit_violations);
+    for limit in RrdpLimit::ALL {
+        target.multi(limit_violations).label("limit", limit.code()).value(
+            metrics.iter().filter(|item| {
+                item.limit_violation == Some(limit)
+            }).count()
+        );
+    }
 }
 
 fn rsync_metrics(target: &mut Target, metrics: &[RsyncModuleMetrics]) {
@@ -504,6 +940,48 @@ fn rsync_metrics(target: &mut Target, metrics: &[RsyncModuleMetrics]) {
         MetricType::Gauge
     );
     target.header(duration);
+    let points = Metric::new(
+        "rsync_points",
+        "number of publication points that used the rsync module",
+        MetricType::Gauge
+    );
+    target.header(points);
+    let family = Metric::new(
+        "rsync_address_family",
+        "IP version used for connecting to the rsync server",
+        MetricType::Gauge
+    );
+    target.header(family);
+    let files = Metric::new(
+        "rsync_files_transferred",
+        "number of files transferred by rsync",
+        MetricType::Gauge
+    );
+    target.header(files);
+    let received = Metric::new(
+        "rsync_bytes_received",
+        "number of bytes received by rsync",
+        MetricType::Gauge
+    );
+    target.header(received);
+    let sent = Metric::new(
+        "rsync_bytes_sent",
+        "number of bytes sent by rsync",
+        MetricType::Gauge
+    );
+    target.header(sent);
+    let speedup = Metric::new(
+        "rsync_speedup",
+        "total size of the module divided by the bytes transferred by rsync",
+        MetricType::Gauge
+    );
+    target.header(speedup);
+    let mirror = Metric::new(
+        "rsync_mirror",
+        "whether the rsync module was updated from a mirror",
+        MetricType::Gauge
+    );
+    target.header(mirror);
 
     for rsync in metrics {
         target.multi(status).label("uri", &rsync.module).value(
@@ -521,6 +999,89 @@ fn rsync_metrics(target: &mut Target, metrics: &[RsyncModuleMetrics]) {
                 )
             );
         }
+        target.multi(points).label("uri", &rsync.module).value(
+            rsync.points
+        );
+        if let Some(value) = rsync.address_family {
+            target.multi(family).label("uri", &rsync.module).value(
+                value.version()
+            );
+        }
+        if let Some(stats) = rsync.stats {
+            target.multi(files).label("uri", &rsync.module).value(
+                stats.files_transferred
+            );
+            target.multi(received).label("uri", &rsync.module).value(
+                stats.bytes_received
+            );
+            target.multi(sent).label("uri", &rsync.module).value(
+                stats.bytes_sent
+            );
+            target.multi(speedup).label("uri", &rsync.module).value(
+                format_args!("{:.2}", stats.speedup)
+            );
+        }
+        if let Some(uri) = rsync.mirror.as_ref() {
+            target.multi(mirror).label("uri", &rsync.module).label(
+                "mirror", uri
+            ).value(1);
+        }
+    }
+}
+
+fn probe_metrics(target: &mut Target, metrics: &ProbeMetrics) {
+    if metrics.is_empty() {
+        return
+    }
+    let up = Metric::new(
+        "repository_probe_up",
+        "whether the repository was reachable during the last probe",
+        MetricType::Gauge
+    );
+    target.header(up);
+    let duration = Metric::new(
+        "repository_probe_duration",
+        "duration of the last repository probe in seconds",
+        MetricType::Gauge
+    );
+    target.header(duration);
+    let last = Metric::new(
+        "repository_probe_last",
+        "seconds since the last repository probe",
+        MetricType::Gauge
+    );
+    target.header(last);
+    let successes = Metric::new(
+        "repository_probe_successes",
+        "number of successful repository probes",
+        MetricType::Counter
+    );
+    target.header(successes);
+    let failures = Metric::new(
+        "repository_probe_failures",
+        "number of failed repository probes",
+        MetricType::Counter
+    );
+    target.header(failures);
+
+    let now = Utc::now();
+    for item in metrics.iter() {
+        let uri = &item.last.uri;
+        target.multi(up).label("uri", uri).value(
+            u8::from(item.last.reachable)
+        );
+        target.multi(duration).label("uri", uri).value(
+            format_args!(
+                "{}.{:03}",
+                item.last.duration.as_secs(),
+                item.last.duration.subsec_millis(),
+            )
+        );
+        target.multi(last).label("uri", uri).value(
+            now.signed_duration_since(item.last.time).num_seconds()
+        );
+        target.multi(successes).label("uri", uri).value(item.successes);
+        target.multi(failures).label("uri", uri).value(item.failures);
     }
 }
 
@@ -910,6 +1471,24 @@ impl Metric {
     fn multi(self, target: &mut Target) -> LabelValue {
         LabelValue::new(self, target)
     }
+
+    fn bucket(
+        self, target: &mut Target, bound: impl fmt::Display, count: u64
+    ) {
+        writeln!(&mut target.buf,
+            "routinator{}_{}_bucket{{le=\"{}\"}} {}",
+            self.prefix, self.name, bound, count
+        ).expect("writing to string");
+    }
+
+    fn suffixed(
+        self, target: &mut Target, suffix: &str, value: impl fmt::Display
+    ) {
+        writeln!(&mut target.buf,
+            "routinator{}_{}_{} {}",
+            self.prefix, self.name, suffix, value
+        ).expect("writing to string");
+    }
 }
 
 
@@ -955,8 +1534,8 @@ impl<'a> LabelValue<'a> {
 enum MetricType {
     Counter,
     Gauge,
-    /* Not currently used:
     Histogram,
+    /* Not currently used:
     Summary,
     */
 }
@@ -967,8 +1546,8 @@ impl fmt::Display for MetricType {
             match *self {
                 MetricType::Counter => "counter",
                 MetricType::Gauge => "gauge",
-                /*
                 MetricType::Histogram => "histogram",
+                /*
                 MetricType::Summary => "summary",
                 */
             }
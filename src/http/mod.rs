@@ -1,10 +1,12 @@
 //! The HTTP server.
 //!
 //! The module provides all functionality exposed by the HTTP server to
-//! those interested. The only public item, [`http_listener`] creates all
+//! those interested. The main public item, [`http_listener`] creates all
 //! necessary networking services based on the current configuration and
-//! returns a future that drives the server.
+//! returns a future that drives the server. In addition, [`AuditLog`]
+//! records requests to the administrative endpoints.
 
+pub use self::admin::AuditLog;
 pub use self::listener::http_listener;
 pub use self::response::ContentType;
 
@@ -19,11 +21,18 @@ mod request;
 mod response;
 
 // Finally, these modules actually handle requests.
+mod admin;
 mod delta;
+mod diff;
+mod gbr;
 mod log;
 mod metrics;
+mod object;
 mod payload;
+mod refresh;
 mod status;
+mod tal;
 mod ui;
+mod unsafe_vrps;
 mod validity;
 
@@ -0,0 +1,136 @@
+//! Handles endpoints related to stored RPKI objects.
+
+use std::str::FromStr;
+use rpki::uri;
+use crate::config::Config;
+use crate::decode::{ObjectType, render_json};
+use crate::store::Store;
+use super::request::Request;
+use super::response::{ContentType, Response, ResponseBuilder};
+
+
+//------------ State ---------------------------------------------------------
+
+pub struct State {
+    /// The store to read objects from.
+    ///
+    /// This is `None` if we don’t have a store, i.e., when we are only
+    /// relaying data from an RTR server.
+    store: Option<Store>,
+
+    /// Should objects be decoded strictly when rendering them as JSON?
+    strict: bool,
+}
+
+impl State {
+    pub fn new(config: &Config) -> Self {
+        State {
+            store: if config.rtr_upstream.is_some() {
+                None
+            }
+            else {
+                Some(Store::reader(config))
+            },
+            strict: config.strict,
+        }
+    }
+
+    pub async fn handle_get_or_head(
+        &self,
+        req: &Request,
+    ) -> Option<Response> {
+        if req.uri().path() != "/api/v1/object" {
+            return None
+        }
+        let store = match self.store.as_ref() {
+            Some(store) => store.clone(),
+            None => return Some(Response::not_found()),
+        };
+        let (uri, json) = match object_query(req.uri().query()) {
+            Some(query) => query,
+            None => return Some(Response::bad_request()),
+        };
+        let content_type = if json {
+            ContentType::JSON
+        }
+        else {
+            content_type(&uri)
+        };
+        let strict = self.strict;
+        let content = tokio::task::spawn_blocking(move || {
+            let content = match store.load_object(&uri) {
+                Ok(Some(content)) => content,
+                Ok(None) => return Ok(None),
+                Err(_) => return Err(()),
+            };
+            if json {
+                render_json(
+                    content, ObjectType::from_name(uri.path()), strict
+                ).map(|json| Some(json.into())).map_err(|_| ())
+            }
+            else {
+                Ok(Some(content))
+            }
+        }).await;
+        let content = match content {
+            Ok(Ok(Some(content))) => content,
+            Ok(Ok(None)) => return Some(Response::not_found()),
+            _ => return Some(Response::internal_server_error()),
+        };
+        let res = ResponseBuilder::ok().content_type(content_type);
+        if req.is_head() {
+            Some(res.empty())
+        }
+        else {
+            Some(res.body(content))
+        }
+    }
+}
+
+
+//------------ Helper Functions ----------------------------------------------
+
+/// Extracts the object URI and whether to render JSON from the query.
+fn object_query(query: Option<&str>) -> Option<(uri::Rsync, bool)> {
+    let mut uri = None;
+    let mut json = false;
+    for (key, value) in form_urlencoded::parse(query?.as_bytes()) {
+        if key == "uri" && uri.is_none() {
+            uri = Some(uri::Rsync::from_str(&value).ok()?);
+        }
+        else if key == "format" {
+            json = match value.as_ref() {
+                "der" => false,
+                "json" => true,
+                _ => return None
+            }
+        }
+        else {
+            return None
+        }
+    }
+    Some((uri?, json))
+}
+
+/// Returns the content type for an object based on its file extension.
+fn content_type(uri: &uri::Rsync) -> ContentType {
+    let path = uri.path();
+    if path.ends_with(".roa") {
+        ContentType::external(b"application/rpki-roa")
+    }
+    else if path.ends_with(".mft") {
+        ContentType::external(b"application/rpki-manifest")
+    }
+    else if path.ends_with(".gbr") {
+        ContentType::external(b"application/rpki-ghostbusters")
+    }
+    else if path.ends_with(".crl") {
+        ContentType::external(b"application/pkix-crl")
+    }
+    else if path.ends_with(".cer") {
+        ContentType::external(b"application/pkix-cert")
+    }
+    else {
+        ContentType::external(b"application/octet-stream")
+    }
+}
@@ -12,12 +12,14 @@ use super::response::{Response, ResponseBuilder};
 
 pub struct State {
     output: Output,
+    enable_aspa: bool,
 }
 
 impl State {
     pub fn new(config: &Config) -> Self {
         Self {
             output: Output::from_config(config),
+            enable_aspa: config.enable_aspa,
         }
     }
 
@@ -27,14 +29,22 @@ impl State {
         history: &SharedHistory,
     ) -> Option<Response> {
         let path = req.uri().path();
+        let mut output = self.output.clone();
         let format = if path == "/api/v1/origins/" {
             OutputFormat::Json
         }
+        else if path == "/api/v1/aspas/" {
+            if !self.enable_aspa {
+                return None
+            }
+            output.no_route_origins();
+            output.no_router_keys();
+            OutputFormat::Json
+        }
         else {
             OutputFormat::from_path(req.uri().path())?
         };
 
-        let mut output = self.output.clone();
         if output.update_from_query(req.uri().query()).is_err() {
             return Some(Response::bad_request())
         };
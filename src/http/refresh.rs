@@ -0,0 +1,65 @@
+//! Handles the endpoint for refreshing a single repository.
+
+use std::sync::Arc;
+use log::info;
+use tokio::sync::mpsc;
+use crate::collector::RefreshTarget;
+use super::admin::{Admin, AuditLog};
+use super::request::Request;
+use super::response::Response;
+
+//------------ State ---------------------------------------------------------
+
+pub struct State {
+    /// The token and the sender for passing on requested refreshes.
+    ///
+    /// If there is no token, refreshing is disabled.
+    admin: Admin<RefreshTarget>,
+}
+
+impl State {
+    pub fn new(
+        token: Option<String>,
+        sender: mpsc::UnboundedSender<RefreshTarget>,
+        audit: Option<Arc<AuditLog>>,
+    ) -> Self {
+        Self {
+            admin: Admin::new(token, "refresh-token", sender, audit)
+        }
+    }
+
+    pub fn handle_post(&self, req: &Request) -> Option<Response> {
+        if req.uri().path() != "/api/v1/refresh" {
+            return None
+        }
+        if let Err(response) = self.admin.authorize(req) {
+            return Some(response)
+        }
+
+        let mut target = None;
+        for (key, value) in form_urlencoded::parse(
+            req.uri().query().unwrap_or("").as_bytes()
+        ) {
+            if key == "repository" && target.is_none() {
+                target = match value.parse::<RefreshTarget>() {
+                    Ok(value) => Some(value),
+                    Err(_) => return Some(Response::bad_request()),
+                };
+            }
+            else {
+                return Some(Response::bad_request())
+            }
+        }
+        let target = match target {
+            Some(target) => target,
+            None => return Some(Response::bad_request()),
+        };
+
+        info!("Refresh of {} requested by {} via HTTP.", target, req.peer());
+        let body = format!("Refreshing {}.", target);
+        Some(self.admin.send(
+            req, target, body, "Refreshing is not available."
+        ))
+    }
+}
+
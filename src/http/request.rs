@@ -1,22 +1,39 @@
 //! Request handling.
 
+use std::net::SocketAddr;
 use hyper::{Method, Uri};
-use hyper::header::HeaderMap;
+use hyper::header::{AUTHORIZATION, HeaderMap};
 
 
 //------------ Request -------------------------------------------------------
 
 pub struct Request {
     hyper: hyper::Request<hyper::body::Incoming>,
+
+    /// The address of the peer that sent the request.
+    peer: SocketAddr,
 }
 
 impl Request {
+    /// Creates a new request from a Hyper request and the peer address.
+    pub fn new(
+        hyper: hyper::Request<hyper::body::Incoming>,
+        peer: SocketAddr,
+    ) -> Self {
+        Self { hyper, peer }
+    }
+
     /// Returns whether the method is GET or HEAD.
     pub fn is_get_or_head(&self) -> bool {
         self.hyper.method() == Method::GET
             || self.hyper.method() == Method::HEAD
     }
 
+    /// Returns whether the method is POST.
+    pub fn is_post(&self) -> bool {
+        self.hyper.method() == Method::POST
+    }
+
     /// Returns whether the method is HEAD.
     pub fn is_head(&self) -> bool {
         self.hyper.method() == Method::HEAD
@@ -27,18 +44,31 @@ impl Request {
         self.hyper.uri()
     }
 
+    /// Returns the address of the peer that sent the request.
+    pub fn peer(&self) -> SocketAddr {
+        self.peer
+    }
+
     /// Returns the headers.
     pub fn headers(&self) -> &HeaderMap {
         self.hyper.headers()
     }
-}
-
-
-//--- From
 
-impl From<hyper::Request<hyper::body::Incoming>> for Request {
-    fn from(hyper: hyper::Request<hyper::body::Incoming>) -> Self {
-        Self { hyper }
+    /// Returns whether the request carries the given bearer token.
+    ///
+    /// The comparison takes the same time no matter where the presented
+    /// token differs.
+    pub fn has_bearer_token(&self, token: &str) -> bool {
+        let presented = match self.headers().get(AUTHORIZATION).and_then(
+            |value| value.to_str().ok()?.strip_prefix("Bearer ")
+        ) {
+            Some(presented) => presented,
+            None => return false,
+        };
+        presented.len() == token.len()
+            && presented.bytes().zip(token.bytes()).fold(0, |acc, (l, r)| {
+                acc | (l ^ r)
+            }) == 0
     }
 }
 
@@ -43,6 +43,13 @@ impl Response {
             .body("Not Found")
     }
 
+    /// Returns an Internal Server Error response.
+    pub fn internal_server_error() -> Self {
+        ResponseBuilder::internal_server_error()
+            .content_type(ContentType::TEXT)
+            .body("Internal Server Error")
+    }
+
     /// Returns a Not Modified response.
     pub fn not_modified(etag: &str, done: DateTime<Utc>) -> Self {
         ResponseBuilder::not_modified().etag(etag).last_modified(done).empty()
@@ -55,6 +62,21 @@ impl Response {
             .body("Method not allowed.")
     }
 
+    /// Returns an Unauthorized response asking for a bearer token.
+    pub fn unauthorized() -> Self {
+        ResponseBuilder::new(StatusCode::UNAUTHORIZED)
+            .www_authenticate("Bearer")
+            .content_type(ContentType::TEXT)
+            .body("Unauthorized")
+    }
+
+    /// Returns a Forbidden response.
+    pub fn forbidden() -> Self {
+        ResponseBuilder::new(StatusCode::FORBIDDEN)
+            .content_type(ContentType::TEXT)
+            .body("Forbidden")
+    }
+
     /// Returns a Moved Permanently response pointing to the given location.
     #[allow(dead_code)]
     pub fn moved_permanently(location: &str) -> Self {
@@ -132,6 +154,11 @@ impl ResponseBuilder {
         Self::new(StatusCode::OK)
     }
 
+    /// Creates a new builder for a 202 Accepted response.
+    pub fn accepted() -> Self {
+        Self::new(StatusCode::ACCEPTED)
+    }
+
     /// Creates a new builder for a Service Unavailable response.
     pub fn service_unavailable() -> Self {
         Self::new(StatusCode::SERVICE_UNAVAILABLE)
@@ -147,6 +174,11 @@ impl ResponseBuilder {
         Self::new(StatusCode::NOT_FOUND)
     }
 
+    /// Creates a new builder for an Internal Server Error response.
+    pub fn internal_server_error() -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
     /// Creates a new builder for a Not Modified response.
     pub fn not_modified() -> Self {
         Self::new(StatusCode::NOT_MODIFIED)
@@ -186,6 +218,13 @@ impl ResponseBuilder {
         }
     }
 
+    /// Adds the WWW-Authenticate header.
+    pub fn www_authenticate(self, challenge: &str) -> Self {
+        ResponseBuilder {
+            builder: self.builder.header("WWW-Authenticate", challenge)
+        }
+    }
+
     /// Adds the Location header.
     #[allow(dead_code)]
     pub fn location(self, location: &str) -> Self {
@@ -1,8 +1,10 @@
 //! Handling of endpoints related to the status.
 
 use std::cmp;
+use std::str::FromStr;
 use chrono::{DateTime, Duration, Utc};
 use clap::{crate_name, crate_version};
+use rpki::rtr::payload::RouteOrigin;
 use crate::metrics::{
     HttpServerMetrics, PayloadMetrics, PublicationMetrics,
     RtrClientMetrics, SharedRtrServerMetrics, VrpMetrics,
@@ -28,6 +30,20 @@ pub async fn handle_get_or_head(
         "/api/v1/status" => {
             Some(handle_api_status(head, history, http, rtr).await)
         },
+        "/api/v1/stale-points" => {
+            Some(handle_api_stale_points(head, history))
+        }
+        "/api/v1/roa-overlaps" => {
+            Some(handle_api_roa_overlaps(head, history))
+        }
+        "/api/v1/dead-repositories" => {
+            Some(handle_api_dead_repositories(head, history))
+        }
+        "/api/v1/repository-health" => {
+            Some(handle_api_repository_health(
+                head, history, req.uri().query()
+            ))
+        }
         "/version" => Some(handle_version(head)),
         _ => None
     }
@@ -80,6 +96,17 @@ async fn handle_status(
     // serial
     writeln!(res, "serial: {}", serial);
 
+    // run-id
+    match metrics.run_id {
+        Some(run_id) => writeln!(res, "run-id: {}", run_id),
+        None => writeln!(res, "run-id: -"),
+    }
+
+    // input-hash
+    if let Some(hash) = metrics.input_hash.as_ref() {
+        writeln!(res, "input-hash: {}", hash);
+    }
+
     // last-update-start-at and -ago
     writeln!(res, "last-update-start-at:  {}", now - start);
     writeln!(res, "last-update-start-ago: {}", start);
@@ -187,22 +214,46 @@ async fn handle_status(
     }
     writeln!(res);
 
+    // duration-per-tal
+    write!(res, "duration-per-tal: ");
+    for tal in &metrics.tals {
+        match tal.duration {
+            Some(duration) => {
+                write!(
+                    res, "{}={:.3} ", tal.name(), duration.as_secs_f32()
+                );
+            }
+            None => write!(res, "{}=- ", tal.name()),
+        }
+    }
+    writeln!(res);
+
     // stale-count
     writeln!(
         res, "stale-count: {}", metrics.publication.stale_objects()
     );
 
+    // rsync-version and rsync-options
+    if let Some(capabilities) = metrics.rsync_capabilities.as_ref() {
+        writeln!(
+            res, "rsync-version: {}",
+            capabilities.version.as_deref().unwrap_or("-")
+        );
+        writeln!(res, "rsync-options: {}", capabilities.options.join(" "));
+    }
+
     // rsync_status
     writeln!(res, "rsync-durations:");
     for metrics in &metrics.rsync {
         write!(
             res,
-            "   {}: status={}",
+            "   {}: status={}, points={}",
             metrics.module,
             match metrics.status {
                 Ok(status) => status.code().unwrap_or(-1),
                 Err(_) => -1
-            }
+            },
+            metrics.points,
         );
         if let Ok(duration) = metrics.duration {
             writeln!(
@@ -222,13 +273,15 @@ async fn handle_status(
     for metrics in &metrics.rrdp {
         write!(
             res,
-            "   {}: status={}, notification-status={}, payload-status={}",
+            "   {}: status={}, notification-status={}, payload-status={}, \
+             requests={}, bytes={}, deltas={}",
             metrics.notify_uri,
             metrics.status().into_i16(),
             metrics.notify_status.into_i16(),
             metrics.payload_status.map(|status| {
                 status.into_i16()
             }).unwrap_or(0),
+            metrics.requests, metrics.bytes, metrics.deltas,
         );
         if let Ok(duration) = metrics.duration {
             write!(
@@ -356,6 +409,13 @@ async fn handle_api_status(
             concat!(crate_name!(), "/", crate_version!())
         );
         target.member_raw("serial", serial);
+        match metrics.run_id {
+            Some(run_id) => target.member_str("runId", run_id),
+            None => target.member_raw("runId", "null"),
+        }
+        if let Some(hash) = metrics.input_hash.as_ref() {
+            target.member_str("inputHash", hash);
+        }
         target.member_str("now", now.format("%+"));
         target.member_str("lastUpdateStart", start.format("%+"));
         if let Some(done) = done {
@@ -382,6 +442,15 @@ async fn handle_api_status(
                     json_publication_metrics(
                         target, &tal.publication
                     );
+                    target.member_raw("disabled", tal.disabled);
+                    if let Some(duration) = tal.duration {
+                        target.member_raw("duration",
+                            format_args!("{:.3}", duration.as_secs_f32())
+                        );
+                    }
+                    else {
+                        target.member_raw("duration", "null");
+                    }
                 });
             }
         });
@@ -411,6 +480,46 @@ async fn handle_api_status(
             metrics.local.vrps().contributed
         );
 
+        target.member_array("limitViolations", |target| {
+            for item in &metrics.limit_violations {
+                target.array_object(|target| {
+                    target.member_str("uri", &item.uri);
+                    target.member_str("tal", &item.tal);
+                    target.member_str("limit", item.limit.code());
+                })
+            }
+        });
+
+        target.member_array("missingFiles", |target| {
+            for item in &metrics.missing_files {
+                target.array_object(|target| {
+                    target.member_str("manifest", &item.manifest);
+                    target.member_str("uri", &item.uri);
+                    target.member_str("reason", item.reason.code());
+                })
+            }
+        });
+
+        target.member_object("unknownObjects", |target| {
+            for (kind, count) in &metrics.unknown_objects {
+                target.member_raw(kind, count);
+            }
+        });
+
+        if let Some(capabilities) = metrics.rsync_capabilities.as_ref() {
+            target.member_object("rsyncCapabilities", |target| {
+                match capabilities.version.as_ref() {
+                    Some(version) => target.member_str("version", version),
+                    None => target.member_raw("version", "null"),
+                }
+                target.member_array("options", |target| {
+                    for option in &capabilities.options {
+                        target.array_str(option);
+                    }
+                });
+            });
+        }
+
         target.member_object("rsync", |target| {
             for metrics in &metrics.rsync {
                 target.member_object(&metrics.module, |target| {
@@ -428,6 +537,41 @@ async fn handle_api_status(
                         }
                         Err(_) => target.member_raw("duration", "null")
                     }
+                    target.member_raw("points", metrics.points);
+                    match metrics.address_family {
+                        Some(family) => {
+                            target.member_str("addressFamily", family)
+                        }
+                        None => target.member_raw("addressFamily", "null")
+                    }
+                    match metrics.stats {
+                        Some(stats) => {
+                            target.member_object("stats", |target| {
+                                target.member_raw(
+                                    "filesTransferred",
+                                    stats.files_transferred
+                                );
+                                target.member_raw(
+                                    "totalSize", stats.total_size
+                                );
+                                target.member_raw(
+                                    "bytesSent", stats.bytes_sent
+                                );
+                                target.member_raw(
+                                    "bytesReceived", stats.bytes_received
+                                );
+                                target.member_raw(
+                                    "speedup",
+                                    format_args!("{:.2}", stats.speedup)
+                                );
+                            })
+                        }
+                        None => target.member_raw("stats", "null")
+                    }
+                    match metrics.mirror.as_ref() {
+                        Some(mirror) => target.member_str("mirror", mirror),
+                        None => target.member_raw("mirror", "null")
+                    }
                 })
             }
         });
@@ -449,6 +593,19 @@ async fn handle_api_status(
                             status.into_i16()
                         }).unwrap_or(0)
                     );
+                    target.member_raw(
+                        "headSkipped",
+                        metrics.head_skipped
+                    );
+                    target.member_raw("requests", metrics.requests);
+                    target.member_raw("bytes", metrics.bytes);
+                    target.member_raw("deltas", metrics.deltas);
+                    match metrics.address_family {
+                        Some(family) => {
+                            target.member_str("addressFamily", family)
+                        }
+                        None => target.member_raw("addressFamily", "null")
+                    }
                     match metrics.duration {
                         Ok(duration) => {
                             target.member_raw("duration",
@@ -457,7 +614,9 @@ async fn handle_api_status(
                         }
                         Err(_) => target.member_raw("duration", "null")
                     }
-                    if !metrics.status().is_not_modified() {
+                    if !metrics.status().is_not_modified()
+                        && !metrics.head_skipped
+                    {
                         match metrics.serial {
                             Some(serial) => {
                                 target.member_raw("serial", serial);
@@ -481,10 +640,37 @@ async fn handle_api_status(
                             target.member_raw("snapshot_reason", "null");
                         }
                     }
+                    match metrics.limit_violation {
+                        Some(limit) => {
+                            target.member_str("limitViolation", limit.code())
+                        }
+                        None => target.member_raw("limitViolation", "null")
+                    }
                 })
             }
         });
 
+        match metrics.cache.as_ref() {
+            Some(cache) => {
+                target.member_object("cache", |target| {
+                    target.member_raw("rrdpSize", cache.size(true));
+                    target.member_raw("rsyncSize", cache.size(false));
+                    target.member_raw("evictedSize", cache.evicted_size());
+                    target.member_object("repositories", |target| {
+                        for item in &cache.repositories {
+                            target.member_raw(&item.uri, item.size);
+                        }
+                    });
+                    target.member_array("evicted", |target| {
+                        for item in &cache.evicted {
+                            target.array_str(&item.uri);
+                        }
+                    });
+                })
+            }
+            None => target.member_raw("cache", "null")
+        }
+
         target.member_object("rtr", |target| {
             target.member_raw(
                 "currentConnections",
@@ -574,19 +760,216 @@ async fn handle_api_status(
     ResponseBuilder::ok().content_type(ContentType::JSON).body(res)
 }
 
+
+//------------ handle_api_stale_points ---------------------------------------
+
+fn handle_api_stale_points(
+    head: bool,
+    history: &SharedHistory,
+) -> Response {
+    let metrics = match history.read().metrics() {
+        Some(metrics) => metrics,
+        None => return Response::initial_validation()
+    };
+
+    if head {
+        return ResponseBuilder::ok().content_type(ContentType::JSON).empty();
+    }
+
+    let res = JsonBuilder::build(|target| {
+        target.member_raw(
+            "chronic",
+            metrics.stale_points.iter().filter(|item| item.chronic).count()
+        );
+        target.member_raw(
+            "overdue",
+            metrics.stale_points.iter().filter(|item| item.overdue).count()
+        );
+        target.member_array("points", |target| {
+            for item in &metrics.stale_points {
+                target.array_object(|target| {
+                    target.member_str("manifest", &item.manifest);
+                    match item.next_update {
+                        Some(time) => {
+                            target.member_str(
+                                "nextUpdate", time.format("%+")
+                            )
+                        }
+                        None => target.member_raw("nextUpdate", "null"),
+                    }
+                    target.member_str("notAfter", item.not_after.format("%+"));
+                    target.member_raw("overdue", item.overdue);
+                    target.member_raw("chronic", item.chronic);
+                    target.member_raw("reissues", item.reissues);
+                    target.member_raw("lateReissues", item.late_reissues);
+                    target.member_raw("maxDelay", item.max_delay);
+                })
+            }
+        });
+    });
+
+    ResponseBuilder::ok().content_type(ContentType::JSON).body(res)
+}
+
+
+//------------ handle_api_roa_overlaps ---------------------------------------
+
+fn handle_api_roa_overlaps(
+    head: bool,
+    history: &SharedHistory,
+) -> Response {
+    let metrics = match history.read().metrics() {
+        Some(metrics) => metrics,
+        None => return Response::initial_validation()
+    };
+
+    if head {
+        return ResponseBuilder::ok().content_type(ContentType::JSON).empty();
+    }
+
+    let res = JsonBuilder::build(|target| {
+        target.member_raw("duplicate", metrics.overlaps.duplicate);
+        target.member_raw("shadowed", metrics.overlaps.shadowed);
+        target.member_array("origins", |target| {
+            for item in &metrics.overlaps.report {
+                target.array_object(|target| {
+                    origin_json(&item.origin, target);
+                    target.member_array("roas", |target| {
+                        for uri in &item.roas {
+                            target.array_str(uri);
+                        }
+                    });
+                    match item.shadowed_by.as_ref() {
+                        Some(origin) => {
+                            target.member_object("shadowedBy", |target| {
+                                origin_json(origin, target)
+                            })
+                        }
+                        None => target.member_raw("shadowedBy", "null"),
+                    }
+                })
+            }
+        });
+    });
+
+    ResponseBuilder::ok().content_type(ContentType::JSON).body(res)
+}
+
+fn origin_json(origin: &RouteOrigin, target: &mut JsonBuilder) {
+    target.member_str("asn", origin.asn);
+    target.member_str(
+        "prefix",
+        format_args!(
+            "{}/{}", origin.prefix.addr(), origin.prefix.prefix_len()
+        )
+    );
+    target.member_raw("maxLength", origin.prefix.resolved_max_len());
+}
+
+
+//------------ handle_api_dead_repositories ----------------------------------
+
+fn handle_api_dead_repositories(
+    head: bool,
+    history: &SharedHistory,
+) -> Response {
+    let metrics = match history.read().metrics() {
+        Some(metrics) => metrics,
+        None => return Response::initial_validation()
+    };
+
+    if head {
+        return ResponseBuilder::ok().content_type(ContentType::JSON).empty();
+    }
+
+    let res = JsonBuilder::build(|target| {
+        target.member_array("repositories", |target| {
+            for item in metrics.dead_repositories() {
+                target.array_object(|target| {
+                    target.member_str("uri", &item.uri);
+                    target.member_str("failingSince", item.since.format("%+"));
+                    target.member_raw("failures", item.failures);
+                })
+            }
+        });
+    });
+
+    ResponseBuilder::ok().content_type(ContentType::JSON).body(res)
+}
+
+
+//------------ handle_api_repository_health ----------------------------------
+
+fn handle_api_repository_health(
+    head: bool,
+    history: &SharedHistory,
+    query: Option<&str>,
+) -> Response {
+    let mut runs = 1;
+    for (key, value) in form_urlencoded::parse(query.unwrap_or("").as_bytes()) {
+        if key == "runs" {
+            runs = match u32::from_str(&value) {
+                Ok(runs) => runs,
+                Err(_) => return Response::bad_request(),
+            };
+        }
+        else {
+            return Response::bad_request()
+        }
+    }
+
+    let metrics = match history.read().metrics() {
+        Some(metrics) => metrics,
+        None => return Response::initial_validation()
+    };
+
+    if head {
+        return ResponseBuilder::ok().content_type(ContentType::JSON).empty();
+    }
+
+    let res = JsonBuilder::build(|target| {
+        target.member_raw("runs", runs);
+        target.member_array("repositories", |target| {
+            for item in &metrics.failing_repositories {
+                if item.failures < runs {
+                    continue
+                }
+                target.array_object(|target| {
+                    target.member_str("uri", &item.uri);
+                    target.member_str("failingSince", item.since.format("%+"));
+                    target.member_raw("failures", item.failures);
+                    target.member_str("reason", item.reason.code());
+                    target.member_raw("dead", item.dead);
+                })
+            }
+        });
+    });
+
+    ResponseBuilder::ok().content_type(ContentType::JSON).body(res)
+}
+
 fn json_publication_metrics(
     target: &mut JsonBuilder, metrics: &PublicationMetrics
 ) {
     target.member_raw("validPublicationPoints", metrics.valid_points);
     target.member_raw("rejectedPublicationPoints", metrics.rejected_points);
+    target.member_raw(
+        "staleStoredPublicationPoints", metrics.stale_stored_points
+    );
+    target.member_raw(
+        "expiredStoredPublicationPoints", metrics.expired_stored_points
+    );
     target.member_raw("validManifests", metrics.valid_manifests);
     target.member_raw("invalidManifests", metrics.invalid_manifests);
     target.member_raw("prematureManifests", metrics.premature_manifests);
     target.member_raw("staleManifests", metrics.stale_manifests);
+    target.member_raw("graceManifests", metrics.grace_manifests);
     target.member_raw("missingManifests", metrics.missing_manifests);
+    target.member_raw("missingFiles", metrics.missing_files);
     target.member_raw("validCRLs", metrics.valid_crls);
     target.member_raw("invalidCRLs", metrics.invalid_crls);
     target.member_raw("staleCRLs", metrics.stale_crls);
+    target.member_raw("graceCRLs", metrics.grace_crls);
     target.member_raw("strayCRLs", metrics.stray_crls);
     target.member_raw("validCACerts", metrics.valid_ca_certs);
 
@@ -595,13 +978,12 @@ fn json_publication_metrics(
 
     target.member_raw("validRouterCerts", metrics.valid_router_certs);
     target.member_raw("invalidCerts", metrics.invalid_certs);
+    target.member_raw("overclaimingCerts", metrics.overclaiming_certs);
     target.member_raw("validROAs", metrics.valid_roas);
     target.member_raw("invalidROAs", metrics.invalid_roas);
     target.member_raw("validGBRs", metrics.valid_gbrs);
-    #[cfg(feature = "aspa")] {
-        target.member_raw("invalidASPAs", metrics.invalid_aspas);
-        target.member_raw("validASPAs", metrics.valid_aspas);
-    }
+    target.member_raw("invalidASPAs", metrics.invalid_aspas);
+    target.member_raw("validASPAs", metrics.valid_aspas);
     target.member_raw("invalidGBRs", metrics.invalid_gbrs);
     target.member_raw("otherObjects", metrics.others);
 }
@@ -630,11 +1012,9 @@ fn json_payload_metrics(
         target.member_object("routerKeys", |target| {
             json_vrps_metrics(target, &payload.router_keys, false)
         });
-        #[cfg(feature = "aspa")] {
-            target.member_object("aspas", |target| {
-                json_vrps_metrics(target, &payload.aspas, false)
-            });
-        }
+        target.member_object("aspas", |target| {
+            json_vrps_metrics(target, &payload.aspas, false)
+        });
     });
 }
 
@@ -649,6 +1029,9 @@ fn json_vrps_metrics(
         target.member_raw("unsafe", vrps.marked_unsafe);
     }
     target.member_raw("locallyFiltered", vrps.locally_filtered);
+    target.member_raw("policyFiltered", vrps.policy_filtered);
+    target.member_raw("policyFlagged", vrps.policy_flagged);
+    target.member_raw("lengthFiltered", vrps.length_filtered);
     target.member_raw("duplicate", vrps.duplicate);
     target.member_raw("final", vrps.contributed);
 }
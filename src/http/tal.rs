@@ -0,0 +1,66 @@
+//! Handles the endpoints for enabling and disabling TALs at runtime.
+
+use std::sync::Arc;
+use log::info;
+use tokio::sync::mpsc;
+use crate::engine::TalSwitch;
+use super::admin::{Admin, AuditLog};
+use super::request::Request;
+use super::response::Response;
+
+//------------ State ---------------------------------------------------------
+
+pub struct State {
+    /// The token and the sender for passing on requested changes.
+    ///
+    /// If there is no token, switching TALs is disabled.
+    admin: Admin<TalSwitch>,
+}
+
+impl State {
+    pub fn new(
+        token: Option<String>,
+        sender: mpsc::UnboundedSender<TalSwitch>,
+        audit: Option<Arc<AuditLog>>,
+    ) -> Self {
+        Self {
+            admin: Admin::new(token, "tal-token", sender, audit)
+        }
+    }
+
+    pub fn handle_post(&self, req: &Request) -> Option<Response> {
+        let enabled = match req.uri().path() {
+            "/api/v1/tals/enable" => true,
+            "/api/v1/tals/disable" => false,
+            _ => return None
+        };
+        if let Err(response) = self.admin.authorize(req) {
+            return Some(response)
+        }
+
+        let mut name = None;
+        for (key, value) in form_urlencoded::parse(
+            req.uri().query().unwrap_or("").as_bytes()
+        ) {
+            if key == "tal" && name.is_none() && !value.is_empty() {
+                name = Some(value.into_owned());
+            }
+            else {
+                return Some(Response::bad_request())
+            }
+        }
+        let switch = match name {
+            Some(name) => TalSwitch { name, enabled },
+            None => return Some(Response::bad_request()),
+        };
+
+        info!("Request to {} from {} via HTTP.", switch, req.peer());
+        let body = format!(
+            "TAL {} will be {} from the next validation run.",
+            switch.name, if enabled { "enabled" } else { "disabled" }
+        );
+        Some(self.admin.send(
+            req, switch, body, "Switching TALs is not available."
+        ))
+    }
+}
@@ -0,0 +1,75 @@
+//! Handling of the unsafe VRPs endpoint.
+
+use crate::config::FilterPolicy;
+use crate::payload::SharedHistory;
+use crate::utils::json::JsonBuilder;
+use super::request::Request;
+use super::response::{ContentType, Response, ResponseBuilder};
+
+
+//------------ handle_get_or_head --------------------------------------------
+
+pub fn handle_get_or_head(
+    req: &Request,
+    history: &SharedHistory,
+) -> Option<Response> {
+    if req.uri().path() != "/api/v1/unsafe-vrps" {
+        return None
+    }
+
+    let mut tal = None;
+    let query = req.uri().query().unwrap_or("");
+    for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+        if key == "tal" {
+            tal = Some(value)
+        }
+        else {
+            return Some(Response::bad_request())
+        }
+    }
+
+    let (snapshot, policy) = {
+        let history = history.read();
+        match history.current() {
+            Some(snapshot) => (snapshot, history.unsafe_vrps()),
+            None => return Some(Response::initial_validation())
+        }
+    };
+
+    if req.is_head() {
+        return Some(
+            ResponseBuilder::ok().content_type(ContentType::JSON).empty()
+        )
+    }
+
+    let res = JsonBuilder::build(|target| {
+        target.member_str("policy", policy);
+        target.member_raw("included", policy != FilterPolicy::Reject);
+        target.member_array("roas", |target| {
+            for (origin, info) in snapshot.unsafe_origin_refs() {
+                if let Some(tal) = tal.as_ref() {
+                    if info.tal_name() != Some(tal.as_ref()) {
+                        continue
+                    }
+                }
+                target.array_object(|target| {
+                    target.member_str("asn", origin.asn);
+                    target.member_str(
+                        "prefix",
+                        format_args!(
+                            "{}/{}",
+                            origin.prefix.addr(), origin.prefix.prefix_len()
+                        )
+                    );
+                    target.member_raw(
+                        "maxLength", origin.prefix.resolved_max_len()
+                    );
+                    target.member_str(
+                        "ta", info.tal_name().unwrap_or("N/A")
+                    );
+                })
+            }
+        })
+    });
+    Some(ResponseBuilder::ok().content_type(ContentType::JSON).body(res))
+}
@@ -42,7 +42,8 @@
 //! * [validity] can be used to perform route origin validation.
 //!
 //! Apart from these, there are a few more modules that support these core
-//! parts in their work.
+//! parts in their work. Among them, [decode] renders RPKI objects in a form
+//! suitable for inspection by humans.
 //!
 #![allow(renamed_and_removed_lints)]
 #![allow(clippy::unknown_clippy_lints)]
@@ -55,15 +56,21 @@ pub use reqwest;
 
 pub mod collector;
 pub mod config;
+pub mod crosscheck;
+pub mod decode;
+pub mod dump;
 pub mod engine;
 pub mod error;
 pub mod http;
 pub mod metrics;
+pub mod mirror;
+pub mod offline;
 pub mod operation;
 pub mod output;
 pub mod payload;
 pub mod process;
 pub mod rtr;
+pub mod selftest;
 pub mod rta;
 pub mod slurm;
 pub mod store;
@@ -5,7 +5,8 @@
 //! [`Metrics`] that collects all metrics gathered during the run. Additional
 //! types contain the metrics related to specific processed entities.
 
-use std::{cmp, io, ops, process, slice};
+use std::{cmp, fmt, io, ops, process, slice};
+use std::collections::BTreeMap;
 use std::iter::Peekable;
 use std::net::IpAddr;
 use std::sync::{Arc};
@@ -14,10 +15,13 @@ use std::time::{Duration, SystemTimeError};
 use chrono::{DateTime, TimeZone, Utc};
 use rpki::uri;
 use rpki::repository::tal::TalInfo;
+use rpki::repository::x509::Time;
+use rpki::rtr::payload::RouteOrigin;
 use rpki::rtr::state::Serial;
 use tokio::sync::Mutex;
 use uuid::Uuid;
-use crate::collector::{HttpStatus, SnapshotReason};
+use crate::collector::{HttpStatus, RrdpLimit, SnapshotReason};
+use crate::utils::net::AddressFamily;
 
 
 //------------ Metrics -------------------------------------------------------
@@ -28,6 +32,11 @@ pub struct Metrics {
     /// Time when these metrics have been collected.
     pub time: DateTime<Utc>,
 
+    /// The identifier of the validation run.
+    ///
+    /// This is `None` if the metrics weren’t collected by a validation run.
+    pub run_id: Option<RunId>,
+
     /// Rsync metrics.
     pub rsync: Vec<RsyncModuleMetrics>,
 
@@ -48,6 +57,52 @@ pub struct Metrics {
 
     /// Overall payload metrics.
     pub payload: PayloadMetrics,
+
+    /// Metrics on redundant route origins.
+    pub overlaps: OverlapMetrics,
+
+    /// The CAs rejected for exceeding limits of the delegation structure.
+    pub limit_violations: Vec<LimitViolation>,
+
+    /// The files listed on updated manifests that could not be used.
+    pub missing_files: Vec<MissingFile>,
+
+    /// The publication points with stale or chronically late manifests.
+    ///
+    /// After finalizing, these are ordered worst offenders first.
+    pub stale_points: Vec<StalePoint>,
+
+    /// The number of objects of unknown type by their file extension.
+    pub unknown_objects: BTreeMap<String, u32>,
+
+    /// The objects rejected during the run.
+    ///
+    /// This is only collected if a rejection report has been requested.
+    pub rejected_objects: Vec<RejectedObject>,
+
+    /// The repositories whose recent updates have failed.
+    pub failing_repositories: Vec<FailingRepository>,
+
+    /// The size of the data collected via RRDP and rsync.
+    ///
+    /// This is `None` if the collector wasn’t cleaned up during the run.
+    pub cache: Option<CacheMetrics>,
+
+    /// The differences between the collector and the store.
+    ///
+    /// This is `None` unless cross-checking was enabled.
+    pub divergence: Option<DivergenceMetrics>,
+
+    /// The hash of the input data of the validation run.
+    ///
+    /// This is only present when validating frozen data. It is the
+    /// hex-encoded SHA-256 digest over the TALs and the stored data.
+    pub input_hash: Option<String>,
+
+    /// The capabilities of the rsync binary.
+    ///
+    /// This is `None` if rsync isn’t used.
+    pub rsync_capabilities: Option<RsyncCapabilities>,
 }
 
 impl Metrics {
@@ -55,6 +110,7 @@ impl Metrics {
     pub fn new() -> Self {
         Metrics {
             time: Utc::now(),
+            run_id: None,
             rsync: Vec::new(),
             rrdp: Vec::new(),
             tals: Vec::new(),
@@ -62,6 +118,17 @@ impl Metrics {
             publication: Default::default(),
             local: Default::default(),
             payload: Default::default(),
+            overlaps: Default::default(),
+            limit_violations: Vec::new(),
+            missing_files: Vec::new(),
+            stale_points: Vec::new(),
+            unknown_objects: BTreeMap::new(),
+            rejected_objects: Vec::new(),
+            failing_repositories: Vec::new(),
+            cache: None,
+            divergence: None,
+            input_hash: None,
+            rsync_capabilities: None,
         }
     }
 
@@ -75,6 +142,7 @@ impl Metrics {
         }
         self.local.finalize();
         self.payload.finalize();
+        self.stale_points.sort_by(|left, right| right.severity_cmp(left));
     }
 
     /// Returns the time the metrics were created as a Unix timestamp.
@@ -93,6 +161,13 @@ impl Metrics {
         }
         true
     }
+
+    /// Returns an iterator over the repositories considered dead.
+    pub fn dead_repositories(
+        &self
+    ) -> impl Iterator<Item = &FailingRepository> + '_ {
+        self.failing_repositories.iter().filter(|item| item.dead)
+    }
 }
 
 impl Default for Metrics {
@@ -108,6 +183,42 @@ impl AsRef<Self> for Metrics {
 }
 
 
+//------------ RunId ---------------------------------------------------------
+
+/// The identifier of a validation run.
+///
+/// The identifier consists of a random UUID that is unique for each run and
+/// a serial number that is incremented with each run performed by the same
+/// process. It is displayed as the UUID followed by a slash and the serial
+/// number.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct RunId {
+    /// The random UUID of the run.
+    pub uuid: Uuid,
+
+    /// The serial number of the run within the process.
+    pub serial: u64,
+}
+
+impl RunId {
+    /// Creates a new run identifier for the given serial number.
+    pub fn new(serial: u64) -> Self {
+        RunId {
+            uuid: uuid::Builder::from_random_bytes(
+                rand::random()
+            ).into_uuid(),
+            serial
+        }
+    }
+}
+
+impl fmt::Display for RunId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.uuid, self.serial)
+    }
+}
+
+
 //------------ RrdpRepositoryMetrics -----------------------------------------
 
 /// Metrics collected while updating an RRDP repository.
@@ -128,6 +239,12 @@ pub struct RrdpRepositoryMetrics {
     /// Was there a reason to fall back to using a snapshot?
     pub snapshot_reason: Option<SnapshotReason>,
 
+    /// The content limit exceeded during the update if any.
+    ///
+    /// If a delta exceeded a limit and the following snapshot did, too,
+    /// this is the limit exceeded by the snapshot.
+    pub limit_violation: Option<RrdpLimit>,
+
     /// The status of requesting the last payload file.
     ///
     /// If multiple payload files had to be requested, for instance because
@@ -138,6 +255,28 @@ pub struct RrdpRepositoryMetrics {
     /// repository was up-to-date.
     pub payload_status: Option<HttpStatus>,
 
+    /// Was the update skipped because a HEAD request showed no change?
+    ///
+    /// In this case, `notify_status` is the status of the HEAD request.
+    pub head_skipped: bool,
+
+    /// The number of HTTP GET requests sent for the update.
+    pub requests: usize,
+
+    /// The number of bytes received for the update.
+    ///
+    /// This is the size of the notification, snapshot, and delta files
+    /// after a possible transfer compression has been removed.
+    pub bytes: u64,
+
+    /// The number of deltas applied during the update.
+    pub deltas: usize,
+
+    /// The address family used for getting the notification file.
+    ///
+    /// This is `None` if no response was received.
+    pub address_family: Option<AddressFamily>,
+
     /// The duration of the last update.
     pub duration: Result<Duration, SystemTimeError>,
 }
@@ -150,7 +289,13 @@ impl RrdpRepositoryMetrics {
             session: None,
             serial: None,
             snapshot_reason: None,
+            limit_violation: None,
             payload_status: None,
+            head_skipped: false,
+            requests: 0,
+            bytes: 0,
+            deltas: 0,
+            address_family: None,
             duration: Ok(Duration::from_secs(0))
         }
     }
@@ -168,6 +313,47 @@ impl RrdpRepositoryMetrics {
             self.notify_status
         }
     }
+
+    /// Returns the reason for a failed update.
+    pub fn failure_reason(&self) -> FailureReason {
+        if self.limit_violation.is_some() {
+            return FailureReason::InvalidData
+        }
+        match self.status() {
+            HttpStatus::Error => FailureReason::Connection,
+            HttpStatus::Response(code)
+                if !code.is_success() && !self.status().is_not_modified()
+            => {
+                FailureReason::HttpStatus
+            }
+            _ => FailureReason::InvalidData,
+        }
+    }
+}
+
+
+//------------ RsyncCapabilities ---------------------------------------------
+
+/// The capabilities of the rsync binary in use.
+///
+/// These are determined once when the rsync collector is created by
+/// asking the binary for its version and help text.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RsyncCapabilities {
+    /// The version reported by rsync if it could be determined.
+    pub version: Option<String>,
+
+    /// The options of interest supported by the binary.
+    pub options: Vec<&'static str>,
+}
+
+impl RsyncCapabilities {
+    /// Returns whether the binary supports the given long option.
+    ///
+    /// The option is given including the leading dashes.
+    pub fn supports(&self, option: &str) -> bool {
+        self.options.contains(&option)
+    }
 }
 
 
@@ -179,6 +365,332 @@ pub struct RsyncModuleMetrics {
     pub module: uri::Rsync,
     pub status: Result<process::ExitStatus, io::Error>,
     pub duration: Result<Duration, SystemTimeError>,
+
+    /// The number of publication points that used the module.
+    ///
+    /// The module is only updated once per validation run, so all but the
+    /// first of these reused the result of that update.
+    pub points: usize,
+
+    /// The address family used to connect to the server.
+    ///
+    /// This is `None` if no connection could be established.
+    pub address_family: Option<AddressFamily>,
+
+    /// The transfer statistics reported by rsync.
+    ///
+    /// This is `None` if rsync didn’t report any.
+    pub stats: Option<RsyncStats>,
+
+    /// The URI of the mirror the module was updated from.
+    ///
+    /// This is `None` if the module was updated from its own URI or if the
+    /// update failed.
+    pub mirror: Option<String>,
+}
+
+impl RsyncModuleMetrics {
+    /// Returns the reason for a failed update.
+    pub fn failure_reason(&self) -> FailureReason {
+        match self.status {
+            Err(ref err) if err.kind() == io::ErrorKind::TimedOut => {
+                FailureReason::Timeout
+            }
+            _ if self.address_family.is_none() => FailureReason::Connection,
+            _ => FailureReason::Rsync,
+        }
+    }
+}
+
+
+//------------ RsyncStats ----------------------------------------------------
+
+/// The transfer statistics of an rsync run.
+///
+/// These are taken from the output of rsync’s `--stats` option. Values
+/// missing from the output are left at zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RsyncStats {
+    /// The number of files transferred.
+    pub files_transferred: u64,
+
+    /// The total size of all files in the module in bytes.
+    pub total_size: u64,
+
+    /// The total size of the files transferred in bytes.
+    pub transferred_size: u64,
+
+    /// The number of bytes sent to the server.
+    pub bytes_sent: u64,
+
+    /// The number of bytes received from the server.
+    pub bytes_received: u64,
+
+    /// The speedup reported by rsync.
+    ///
+    /// This is the total size divided by the bytes sent and received, i.e.,
+    /// how much the transfer saved compared to downloading everything.
+    pub speedup: f64,
+}
+
+
+//------------ RepositoryProbe -----------------------------------------------
+
+/// The result of probing whether a repository is reachable.
+///
+/// Probes happen between validation runs and are much more lightweight
+/// than an update: for RRDP repositories, a HEAD request for the
+/// notification file is sent; for rsync, a connection to the server is
+/// established and immediately closed again.
+#[derive(Clone, Debug)]
+pub struct RepositoryProbe {
+    /// The URI identifying the repository.
+    ///
+    /// For RRDP, this is the rpkiNotify URI. For rsync, this is the URI
+    /// of the server without any module.
+    pub uri: String,
+
+    /// The time the probe was started.
+    pub time: DateTime<Utc>,
+
+    /// The time it took to complete the probe.
+    pub duration: Duration,
+
+    /// Was the repository reachable?
+    pub reachable: bool,
+}
+
+
+//------------ UpdatePreview -------------------------------------------------
+
+/// What updating a locally known repository would do.
+///
+/// Previews are determined without actually updating the repository: for
+/// RRDP repositories, only the notification file is fetched; for rsync,
+/// rsync is run in dry-run mode.
+#[derive(Clone, Debug)]
+pub struct UpdatePreview {
+    /// The URI identifying the repository.
+    ///
+    /// For RRDP, this is the rpkiNotify URI. For rsync, this is the URI
+    /// of the module.
+    pub uri: String,
+
+    /// The change an update would bring.
+    pub change: PreviewChange,
+}
+
+/// The change an update of a repository would bring.
+#[derive(Clone, Copy, Debug)]
+pub enum PreviewChange {
+    /// The repository has not changed.
+    Unchanged,
+
+    /// Deltas would be applied to reach the given serial number.
+    Deltas { count: usize, serial: u64 },
+
+    /// A snapshot with the given serial number would be loaded.
+    Snapshot { reason: SnapshotReason, serial: u64 },
+
+    /// Rsync would transfer the given number of files and bytes.
+    Files { count: u64, size: u64 },
+
+    /// Determining the change failed.
+    Failed,
+}
+
+impl PreviewChange {
+    /// Returns a short code for the kind of change.
+    pub fn code(self) -> &'static str {
+        match self {
+            PreviewChange::Unchanged => "unchanged",
+            PreviewChange::Deltas { .. } => "deltas",
+            PreviewChange::Snapshot { .. } => "snapshot",
+            PreviewChange::Files { .. } => "files",
+            PreviewChange::Failed => "failed",
+        }
+    }
+}
+
+
+//------------ CleanupPreview ------------------------------------------------
+
+/// Data that cleaning up the collector would delete.
+///
+/// Previews are determined without deleting anything. They list all the
+/// RRDP repositories and rsync modules that would be removed as well as
+/// anything else found in the collector’s directories that doesn’t belong
+/// there.
+#[derive(Clone, Debug)]
+pub struct CleanupPreview {
+    /// The URI identifying the repository.
+    ///
+    /// For RRDP, this is the rpkiNotify URI. For rsync, this is the URI
+    /// of the module. For stray data, this is the path on disk.
+    pub uri: String,
+
+    /// Whether this is RRDP data rather than rsync data.
+    pub rrdp: bool,
+
+    /// The on-disk size in bytes.
+    pub size: u64,
+
+    /// The reason the data would be deleted.
+    pub reason: CleanupReason,
+}
+
+/// The reason cleanup would delete data.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CleanupReason {
+    /// No current publication point uses the repository.
+    Unused,
+
+    /// The data doesn’t belong into the collector’s directory.
+    Stray,
+
+    /// The repository would be evicted to stay within the cache size limit.
+    CacheLimit,
+}
+
+impl CleanupReason {
+    /// Returns a string code for the reason.
+    pub fn code(self) -> &'static str {
+        match self {
+            CleanupReason::Unused => "unused",
+            CleanupReason::Stray => "stray",
+            CleanupReason::CacheLimit => "cache-limit",
+        }
+    }
+}
+
+
+//------------ ProbeMetrics --------------------------------------------------
+
+/// The accumulated results of all repository probes.
+#[derive(Clone, Debug, Default)]
+pub struct ProbeMetrics {
+    /// The availability of the probed repositories by their URI.
+    repositories: BTreeMap<String, RepositoryAvailability>,
+}
+
+impl ProbeMetrics {
+    /// Adds the results of a round of probes.
+    pub fn update(&mut self, probes: Vec<RepositoryProbe>) {
+        for probe in probes {
+            let item = self.repositories.entry(
+                probe.uri.clone()
+            ).or_insert_with(|| {
+                RepositoryAvailability {
+                    last: probe.clone(), successes: 0, failures: 0,
+                }
+            });
+            if probe.reachable {
+                item.successes += 1;
+            }
+            else {
+                item.failures += 1;
+            }
+            item.last = probe;
+        }
+    }
+
+    /// Returns whether there are no probe results yet.
+    pub fn is_empty(&self) -> bool {
+        self.repositories.is_empty()
+    }
+
+    /// Returns an iterator over the availability of all repositories.
+    pub fn iter(&self) -> impl Iterator<Item = &RepositoryAvailability> {
+        self.repositories.values()
+    }
+}
+
+
+//------------ RepositoryAvailability ----------------------------------------
+
+/// The availability of a single repository as determined by probes.
+#[derive(Clone, Debug)]
+pub struct RepositoryAvailability {
+    /// The result of the last probe.
+    pub last: RepositoryProbe,
+
+    /// The number of successful probes.
+    pub successes: u64,
+
+    /// The number of failed probes.
+    pub failures: u64,
+}
+
+
+//------------ PropagationMetrics --------------------------------------------
+
+/// The distribution of propagation delays of newly announced payload.
+///
+/// The propagation delay of a payload item is the time between its
+/// publication, i.e., the this-update time of the latest manifest along
+/// its validation chain, and the publication of the first RTR serial that
+/// includes it. The delays are accumulated over all serials since
+/// Routinator was started.
+#[derive(Clone, Debug, Default)]
+pub struct PropagationMetrics {
+    /// The number of items per bucket.
+    ///
+    /// The item at index `i` contains the number of items with a delay of
+    /// at most `Self::BOUNDS[i]` seconds but more than the previous bound.
+    /// The last item contains the items exceeding all bounds.
+    buckets: [u64; Self::BOUNDS.len() + 1],
+
+    /// The sum of all delays in seconds.
+    sum: u64,
+
+    /// The number of items.
+    count: u64,
+}
+
+impl PropagationMetrics {
+    /// The upper bounds of the buckets in seconds.
+    pub const BOUNDS: [u64; 9] = [
+        60, 300, 900, 1800, 3600, 7200, 14400, 43200, 86400
+    ];
+
+    /// Adds an item published at the given time and announced at `now`.
+    ///
+    /// Publication times in the future are counted as no delay.
+    pub fn add(&mut self, published: Time, now: Time) {
+        let delay = u64::try_from(
+            now.timestamp().saturating_sub(published.timestamp())
+        ).unwrap_or(0);
+        let idx = Self::BOUNDS.iter().position(|bound| delay <= *bound)
+            .unwrap_or(Self::BOUNDS.len());
+        self.buckets[idx] += 1;
+        self.sum = self.sum.saturating_add(delay);
+        self.count += 1;
+    }
+
+    /// Returns the cumulative number of items for each bucket.
+    ///
+    /// Returns pairs of the upper bound of the bucket in seconds and the
+    /// number of items with a delay up to and including this bound. The
+    /// bucket for items exceeding all bounds is not included. Its count
+    /// is available via [`count`][Self::count].
+    pub fn cumulative(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        Self::BOUNDS.iter().zip(self.buckets.iter()).scan(
+            0, |acc, (bound, count)| {
+                *acc += *count;
+                Some((*bound, *acc))
+            }
+        )
+    }
+
+    /// Returns the sum of all delays in seconds.
+    pub fn sum(&self) -> u64 {
+        self.sum
+    }
+
+    /// Returns the number of items.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
 }
 
 
@@ -195,6 +707,15 @@ pub struct TalMetrics {
 
     /// The VRP metrics.
     pub payload: PayloadMetrics,
+
+    /// The time it took to collect and validate the TAL’s data.
+    ///
+    /// This is the time from the start of the validation run until the
+    /// last task for the TAL was finished.
+    pub duration: Option<Duration>,
+
+    /// Was the TAL disabled at runtime and skipped during validation?
+    pub disabled: bool,
 }
 
 impl TalMetrics {
@@ -203,6 +724,8 @@ impl TalMetrics {
             tal,
             publication: Default::default(),
             payload: Default::default(),
+            duration: None,
+            disabled: false,
         }
     }
 
@@ -257,6 +780,20 @@ pub struct PublicationMetrics {
     /// The number of rejected publication points.
     pub rejected_points: u32,
 
+    /// The number of valid publication points using stale stored data.
+    ///
+    /// These points could not be refreshed and their stored data has not
+    /// been confirmed to be current for longer than the stale age. They
+    /// are included in `valid_points`.
+    pub stale_stored_points: u32,
+
+    /// The number of publication points whose stored data was too old.
+    ///
+    /// These points could not be refreshed and their stored data has not
+    /// been confirmed to be current for longer than the maximum age. They
+    /// are included in `rejected_points`.
+    pub expired_stored_points: u32,
+
     /// The number of valid manifests.
     pub valid_manifests: u32,
 
@@ -269,9 +806,19 @@ pub struct PublicationMetrics {
     /// The number of stale manifests.
     pub stale_manifests: u32,
 
+    /// The number of stale manifests accepted during the grace period.
+    ///
+    /// These are included in `stale_manifests`.
+    pub grace_manifests: u32,
+
     /// The number of missing manifests.
     pub missing_manifests: u32,
 
+    /// The number of files listed on updated manifests that were unusable.
+    ///
+    /// See [`MissingFile`] for details.
+    pub missing_files: u32,
+
     /// The number of valid CRLs.
     pub valid_crls: u32,
 
@@ -281,6 +828,11 @@ pub struct PublicationMetrics {
     /// The number of stale CRLs.
     pub stale_crls: u32,
 
+    /// The number of stale CRLs accepted during the grace period.
+    ///
+    /// These are included in `stale_crls`.
+    pub grace_crls: u32,
+
     /// The number of stray CRLs.
     ///
     /// Stray CRLs are CRL objects appearing in publication points that are
@@ -297,6 +849,14 @@ pub struct PublicationMetrics {
     /// The number of invalid certificates.
     pub invalid_certs: u32,
 
+    /// The number of certificates claiming resources not held by their issuer.
+    ///
+    /// These are CA, router, and EE certificates that are valid with their
+    /// resources trimmed under the reconsidered algorithm but invalid
+    /// under the strict algorithm. They are counted regardless of the
+    /// algorithm used.
+    pub overclaiming_certs: u32,
+
     /// The number of valid ROAs.
     pub valid_roas: u32,
 
@@ -339,20 +899,26 @@ impl<'a> ops::AddAssign<&'a Self> for PublicationMetrics {
     fn add_assign(&mut self, other: &'a Self) {
         self.valid_points += other.valid_points;
         self.rejected_points += other.rejected_points;
+        self.stale_stored_points += other.stale_stored_points;
+        self.expired_stored_points += other.expired_stored_points;
 
         self.valid_manifests += other.valid_manifests;
         self.invalid_manifests += other.invalid_manifests;
         self.premature_manifests += other.premature_manifests;
         self.stale_manifests += other.stale_manifests;
+        self.grace_manifests += other.grace_manifests;
         self.missing_manifests += other.missing_manifests;
+        self.missing_files += other.missing_files;
         self.valid_crls += other.valid_crls;
         self.invalid_crls += other.invalid_crls;
         self.stale_crls += other.stale_crls;
+        self.grace_crls += other.grace_crls;
         self.stray_crls += other.stray_crls;
 
         self.valid_ca_certs += other.valid_ca_certs;
         self.valid_router_certs += other.valid_router_certs;
         self.invalid_certs += other.invalid_certs;
+        self.overclaiming_certs += other.overclaiming_certs;
         self.valid_roas += other.valid_roas;
         self.invalid_roas += other.invalid_roas;
         self.valid_gbrs += other.valid_gbrs;
@@ -370,6 +936,501 @@ impl ops::AddAssign for PublicationMetrics {
 }
 
 
+//------------ LimitViolation ------------------------------------------------
+
+/// A CA rejected for exceeding a limit of the delegation structure.
+#[derive(Clone, Debug)]
+pub struct LimitViolation {
+    /// The URI of the offending CA certificate.
+    pub uri: String,
+
+    /// The name of the TAL the CA was found under.
+    pub tal: String,
+
+    /// The limit that was exceeded.
+    pub limit: LimitKind,
+}
+
+/// The kind of delegation limit exceeded by a CA.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LimitKind {
+    /// The CA is too far away from the trust anchor.
+    ///
+    /// The CA itself has been rejected.
+    CaDepth,
+
+    /// The CA has too many child CAs.
+    ///
+    /// The CA itself has been accepted but none of its children.
+    CaChildren,
+
+    /// The CA’s publication point has too many objects.
+    ///
+    /// The CA itself has been rejected.
+    PointObjects,
+
+    /// Processing the CAs below the CA took too long.
+    ///
+    /// All CAs below the CA not processed yet have been rejected.
+    CaTime,
+}
+
+impl LimitKind {
+    /// All limits.
+    pub const ALL: [Self; 4] = [
+        LimitKind::CaDepth, LimitKind::CaChildren,
+        LimitKind::PointObjects, LimitKind::CaTime,
+    ];
+
+    /// Returns a short code for the limit.
+    pub fn code(self) -> &'static str {
+        match self {
+            LimitKind::CaDepth => "ca-depth",
+            LimitKind::CaChildren => "ca-children",
+            LimitKind::PointObjects => "point-objects",
+            LimitKind::CaTime => "ca-time",
+        }
+    }
+}
+
+
+//------------ MissingFile ---------------------------------------------------
+
+/// A file listed on an updated manifest that could not be used.
+#[derive(Clone, Debug)]
+pub struct MissingFile {
+    /// The URI of the manifest.
+    pub manifest: String,
+
+    /// The URI of the file.
+    pub uri: String,
+
+    /// Why the file could not be used.
+    pub reason: MissingFileReason,
+}
+
+/// The reason a file listed on a manifest could not be used.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MissingFileReason {
+    /// The collector did not provide the file.
+    NotFetched,
+
+    /// The file’s content does not match the hash given on the manifest.
+    HashMismatch,
+
+    /// The file name on the manifest is not acceptable.
+    IllegalName,
+}
+
+impl MissingFileReason {
+    /// All reasons.
+    pub const ALL: [Self; 3] = [
+        Self::NotFetched, Self::HashMismatch, Self::IllegalName
+    ];
+
+    /// Returns a short code for the reason.
+    pub fn code(self) -> &'static str {
+        match self {
+            MissingFileReason::NotFetched => "not-fetched",
+            MissingFileReason::HashMismatch => "hash-mismatch",
+            MissingFileReason::IllegalName => "illegal-name",
+        }
+    }
+}
+
+
+//------------ RejectedObject ------------------------------------------------
+
+/// An object rejected during a validation run.
+#[derive(Clone, Debug)]
+pub struct RejectedObject {
+    /// The URI of the object.
+    pub uri: String,
+
+    /// The URI of the manifest of the publication point of the object.
+    pub manifest: String,
+
+    /// The name of the TAL the object belongs to.
+    pub tal: String,
+
+    /// Why the object was rejected.
+    pub reason: RejectionReason,
+}
+
+/// The reason an object was rejected.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RejectionReason {
+    /// The object could not be decoded.
+    DecodeError,
+
+    /// The object failed validation, e.g., because of a bad signature.
+    Invalid,
+
+    /// The certificate of the object has expired or isn’t valid yet.
+    Expired,
+
+    /// The certificate of the object has been revoked.
+    Revoked,
+
+    /// The manifest or its CRL is stale.
+    Stale,
+
+    /// The object is of an unknown type.
+    UnknownType,
+
+    /// The collector did not provide the object.
+    NotFetched,
+
+    /// The object’s content does not match the hash given on the manifest.
+    HashMismatch,
+
+    /// The file name on the manifest is not acceptable.
+    IllegalName,
+
+    /// The publication point of the manifest was rejected.
+    PointRejected,
+}
+
+impl RejectionReason {
+    /// Returns a short code for the reason.
+    pub fn code(self) -> &'static str {
+        match self {
+            RejectionReason::DecodeError => "decode-error",
+            RejectionReason::Invalid => "invalid",
+            RejectionReason::Expired => "expired",
+            RejectionReason::Revoked => "revoked",
+            RejectionReason::Stale => "stale",
+            RejectionReason::UnknownType => "unknown-type",
+            RejectionReason::NotFetched => "not-fetched",
+            RejectionReason::HashMismatch => "hash-mismatch",
+            RejectionReason::IllegalName => "illegal-name",
+            RejectionReason::PointRejected => "point-rejected",
+        }
+    }
+}
+
+impl From<MissingFileReason> for RejectionReason {
+    fn from(reason: MissingFileReason) -> Self {
+        match reason {
+            MissingFileReason::NotFetched => RejectionReason::NotFetched,
+            MissingFileReason::HashMismatch => RejectionReason::HashMismatch,
+            MissingFileReason::IllegalName => RejectionReason::IllegalName,
+        }
+    }
+}
+
+
+//------------ FailingRepository ---------------------------------------------
+
+/// A repository whose recent updates have failed.
+///
+/// A repository is considered dead if every update attempt has failed for
+/// at least the time given via the `dead_repository_age` configuration
+/// option.
+#[derive(Clone, Debug)]
+pub struct FailingRepository {
+    /// The URI identifying the repository.
+    ///
+    /// For RRDP, this is the rpkiNotify URI. For rsync, this is the URI
+    /// of the module.
+    pub uri: String,
+
+    /// The time of the first of the consecutive failed updates.
+    pub since: DateTime<Utc>,
+
+    /// The number of consecutive failed updates.
+    pub failures: u32,
+
+    /// The reason the last update failed.
+    pub reason: FailureReason,
+
+    /// Whether the repository is considered dead.
+    pub dead: bool,
+}
+
+/// The reason an update of a repository failed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FailureReason {
+    /// The server could not be reached or didn’t respond.
+    Connection,
+
+    /// The server responded with an HTTP error status.
+    HttpStatus,
+
+    /// The update took too long.
+    Timeout,
+
+    /// The rsync process failed.
+    Rsync,
+
+    /// The data provided by the server was not acceptable.
+    InvalidData,
+
+    /// The reason is not known.
+    ///
+    /// This is used for failures recorded by earlier versions.
+    Unknown,
+}
+
+impl FailureReason {
+    /// All reasons.
+    pub const ALL: [Self; 6] = [
+        Self::Connection, Self::HttpStatus, Self::Timeout, Self::Rsync,
+        Self::InvalidData, Self::Unknown,
+    ];
+
+    /// Returns a short code for the reason.
+    pub fn code(self) -> &'static str {
+        match self {
+            FailureReason::Connection => "connection",
+            FailureReason::HttpStatus => "http-status",
+            FailureReason::Timeout => "timeout",
+            FailureReason::Rsync => "rsync-error",
+            FailureReason::InvalidData => "invalid-data",
+            FailureReason::Unknown => "unknown",
+        }
+    }
+
+    /// Returns the reason for a short code.
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|reason| reason.code() == code)
+    }
+}
+
+
+//------------ CacheMetrics --------------------------------------------------
+
+/// The on-disk size of the data collected via RRDP and rsync.
+#[derive(Clone, Debug, Default)]
+pub struct CacheMetrics {
+    /// The repositories present after the validation run.
+    pub repositories: Vec<CachedRepository>,
+
+    /// The repositories removed because the size limit was exceeded.
+    pub evicted: Vec<CachedRepository>,
+}
+
+impl CacheMetrics {
+    /// Returns the total size of the RRDP repositories or rsync modules.
+    pub fn size(&self, rrdp: bool) -> u64 {
+        self.repositories.iter().filter(|item| item.rrdp == rrdp).map(|item| {
+            item.size
+        }).sum()
+    }
+
+    /// Returns the number of RRDP repositories or rsync modules.
+    pub fn count(&self, rrdp: bool) -> usize {
+        self.repositories.iter().filter(|item| item.rrdp == rrdp).count()
+    }
+
+    /// Returns the total size of the evicted repositories.
+    pub fn evicted_size(&self) -> u64 {
+        self.evicted.iter().map(|item| item.size).sum()
+    }
+}
+
+/// A repository present in the collector’s data.
+#[derive(Clone, Debug)]
+pub struct CachedRepository {
+    /// The URI identifying the repository.
+    ///
+    /// For RRDP, this is the rpkiNotify URI. For rsync, this is the URI
+    /// of the module.
+    pub uri: String,
+
+    /// Whether this is an RRDP repository rather than an rsync module.
+    pub rrdp: bool,
+
+    /// The on-disk size of the repository in bytes.
+    pub size: u64,
+}
+
+
+//------------ DivergenceMetrics ---------------------------------------------
+
+/// The differences between the collector’s data and the store.
+///
+/// For every stored publication point, the objects the store has accepted
+/// are compared with the collector’s local copy of the publication point.
+/// The latter consists of the collected manifest and the files listed on
+/// it.
+#[derive(Clone, Debug, Default)]
+pub struct DivergenceMetrics {
+    /// The number of publication points that were compared.
+    pub points: usize,
+
+    /// The objects that differ between collector and store.
+    ///
+    /// The list is ordered by manifest URI and then by object URI.
+    pub objects: Vec<DivergentObject>,
+}
+
+impl DivergenceMetrics {
+    /// Returns the number of differing objects of the given kind.
+    pub fn count(&self, kind: DivergenceKind) -> usize {
+        self.objects.iter().filter(|item| item.kind == kind).count()
+    }
+
+    /// Returns the number of publication points with differing objects.
+    pub fn divergent_points(&self) -> usize {
+        let mut res = 0;
+        let mut last = None;
+        for item in &self.objects {
+            if last != Some(&item.manifest_uri) {
+                res += 1;
+                last = Some(&item.manifest_uri);
+            }
+        }
+        res
+    }
+}
+
+/// An object that differs between the collector and the store.
+#[derive(Clone, Debug)]
+pub struct DivergentObject {
+    /// The manifest URI of the publication point of the object.
+    pub manifest_uri: uri::Rsync,
+
+    /// The URI of the object.
+    pub uri: uri::Rsync,
+
+    /// How the object differs.
+    pub kind: DivergenceKind,
+
+    /// The hex-encoded SHA-256 hash of the collector’s copy if present.
+    pub collector_hash: Option<String>,
+
+    /// The hex-encoded SHA-256 hash of the stored copy if present.
+    pub store_hash: Option<String>,
+}
+
+/// How an object differs between the collector and the store.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DivergenceKind {
+    /// The object is only present in the collector.
+    CollectorOnly,
+
+    /// The object is only present in the store.
+    StoreOnly,
+
+    /// Collector and store have different content for the object.
+    HashMismatch,
+}
+
+impl DivergenceKind {
+    /// All kinds in the order they are reported in.
+    pub const ALL: [Self; 3] = [
+        DivergenceKind::CollectorOnly, DivergenceKind::StoreOnly,
+        DivergenceKind::HashMismatch,
+    ];
+
+    /// Returns a string code for the kind.
+    pub fn code(self) -> &'static str {
+        match self {
+            DivergenceKind::CollectorOnly => "collector-only",
+            DivergenceKind::StoreOnly => "store-only",
+            DivergenceKind::HashMismatch => "hash-mismatch",
+        }
+    }
+}
+
+
+//------------ StalePoint ----------------------------------------------------
+
+/// A publication point that doesn’t reissue its manifest in time.
+///
+/// A point is reported if its current manifest is past its nextUpdate time
+/// or if it is chronically stale, i.e., it regularly reissues its manifest
+/// only after the previous one has become stale. Points with expired
+/// manifests are not reported.
+#[derive(Clone, Debug)]
+pub struct StalePoint {
+    /// The URI of the manifest.
+    pub manifest: String,
+
+    /// The nextUpdate time of the current manifest if known.
+    pub next_update: Option<Time>,
+
+    /// The expiry time of the current manifest.
+    pub not_after: Time,
+
+    /// Is the current manifest past its nextUpdate time?
+    pub overdue: bool,
+
+    /// Does the point regularly reissue its manifest late?
+    pub chronic: bool,
+
+    /// The number of recently observed reissues.
+    pub reissues: usize,
+
+    /// The number of those reissues that were late.
+    pub late_reissues: usize,
+
+    /// The largest delay of any of those reissues in seconds.
+    pub max_delay: i64,
+}
+
+impl StalePoint {
+    /// Compares how bad two points are.
+    ///
+    /// Chronically stale points are worse than others. Among them, more
+    /// late reissues, then a larger delay, then being overdue make a point
+    /// worse.
+    pub fn severity_cmp(&self, other: &Self) -> cmp::Ordering {
+        self.chronic.cmp(&other.chronic)
+        .then(self.late_reissues.cmp(&other.late_reissues))
+        .then(self.max_delay.cmp(&other.max_delay))
+        .then(self.overdue.cmp(&other.overdue))
+    }
+}
+
+
+//------------ OverlapMetrics ------------------------------------------------
+
+/// Metrics on redundant route origins.
+///
+/// A route origin is redundant if it is published by more than one ROA or
+/// if it is shadowed, i.e., another route origin for the same AS covers its
+/// prefix with a maximum length at least as large. Only published route
+/// origins are considered, local exceptions are ignored.
+#[derive(Clone, Debug, Default)]
+pub struct OverlapMetrics {
+    /// The number of route origins published by more than one ROA.
+    pub duplicate: u32,
+
+    /// The number of shadowed route origins.
+    pub shadowed: u32,
+
+    /// The redundant route origins.
+    ///
+    /// This is only collected if an overlap report has been requested.
+    pub report: Vec<RedundantOrigin>,
+}
+
+
+//------------ RedundantOrigin -----------------------------------------------
+
+/// A redundant route origin.
+#[derive(Clone, Debug)]
+pub struct RedundantOrigin {
+    /// The route origin.
+    pub origin: RouteOrigin,
+
+    /// The URIs of the ROAs publishing the route origin.
+    pub roas: Vec<String>,
+
+    /// The route origin covering this one if it is shadowed.
+    pub shadowed_by: Option<RouteOrigin>,
+}
+
+impl RedundantOrigin {
+    /// Returns whether the route origin is published by more than one ROA.
+    pub fn is_duplicate(&self) -> bool {
+        self.roas.len() > 1
+    }
+}
+
+
 //------------ PayloadMetrics ------------------------------------------------
 
 /// Metrics regarding the generated payload set.
@@ -459,6 +1520,15 @@ pub struct VrpMetrics {
     /// The number of VRPs filtered due to local exceptions.
     pub locally_filtered: u32,
 
+    /// The number of VRPs filtered due to the local ROA policy.
+    pub policy_filtered: u32,
+
+    /// The number of VRPs flagged but kept due to the local ROA policy.
+    pub policy_flagged: u32,
+
+    /// The number of VRPs filtered for exceeding the prefix length limit.
+    pub length_filtered: u32,
+
     /// The number of duplicate VRPs.
     ///
     /// This number is only calculated after local filtering. If duplicates
@@ -488,6 +1558,9 @@ impl<'a> ops::AddAssign<&'a Self> for VrpMetrics {
         self.valid += other.valid;
         self.marked_unsafe += other.marked_unsafe;
         self.locally_filtered += other.locally_filtered;
+        self.policy_filtered += other.policy_filtered;
+        self.policy_flagged += other.policy_flagged;
+        self.length_filtered += other.length_filtered;
         self.duplicate += other.duplicate;
         self.contributed += other.contributed;
     }
@@ -1075,6 +2148,24 @@ mod test {
     use super::*;
     use std::str::FromStr;
 
+    #[test]
+    fn propagation_metrics() {
+        let now = Time::now();
+        let mut metrics = PropagationMetrics::default();
+        metrics.add(now, now);
+        metrics.add(now + chrono::Duration::try_seconds(10).unwrap(), now);
+        metrics.add(now - chrono::Duration::try_seconds(600).unwrap(), now);
+        metrics.add(now - chrono::Duration::try_days(2).unwrap(), now);
+        assert_eq!(metrics.count(), 4);
+        assert_eq!(metrics.sum(), 600 + 2 * 86400);
+        let buckets: Vec<_> = metrics.cumulative().collect();
+        assert_eq!(buckets.len(), PropagationMetrics::BOUNDS.len());
+        assert_eq!(buckets[0], (60, 2));
+        assert_eq!(buckets[1], (300, 2));
+        assert_eq!(buckets[2], (900, 3));
+        assert_eq!(buckets.last(), Some(&(86400, 3)));
+    }
+
     #[test]
     fn insert_rtr_metrics() {
         let addr1 = IpAddr::from_str("10.0.0.1").unwrap();
@@ -0,0 +1,698 @@
+//! Re-publishing the validated data as a local mirror.
+//!
+//! After each validation run, the content of the store can be written to a
+//! directory from where it can be served to other relying party software,
+//! turning Routinator into a caching fan-out point. The mirror directory
+//! contains two sub-directories:
+//!
+//! * `rsync` contains all stored objects at the path given by their rsync
+//!   URI, i.e., `rsync/<authority>/<module>/<path>`. It can be served
+//!   by an rsync daemon as a module.
+//! * `https` contains an RRDP repository for each RRDP repository present
+//!   in the store. The notification file is at the path given by the
+//!   original rpkiNotify URI, i.e., `https/<authority>/<path>`. The
+//!   snapshot is kept next to it in a directory named after the session
+//!   and serial number. Trust anchor certificates referred to by HTTPS URIs
+//!   are placed into this directory, too. It can be served by any web
+//!   server.
+//!
+//! Downstream relying party software can then use URI rewriting to access
+//! the mirror instead of the original repositories.
+//!
+//! The RRDP repositories only ever contain a snapshot. Whenever the content
+//! of a repository changes, its serial number is increased. Each process
+//! uses a new session.
+
+use std::{fs, io};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use bytes::Bytes;
+use log::{debug, error, info};
+use ring::digest;
+use rpki::repository::tal::TalUri;
+use rpki::rrdp::{Hash, NotificationFile, UriAndHash};
+use rpki::uri;
+use rpki::util::base64;
+use uuid::Uuid;
+use crate::config::Config;
+use crate::store::Store;
+use crate::utils::sync::Mutex;
+
+
+//------------ Mirror --------------------------------------------------------
+
+/// A local mirror of the validated data.
+#[derive(Debug)]
+pub struct Mirror {
+    /// The base directory of the mirror.
+    dir: PathBuf,
+
+    /// The URI under which the `https` directory is served.
+    ///
+    /// This always ends in a slash. If it is `None`, RRDP repositories are
+    /// not published.
+    https_uri: Option<String>,
+
+    /// The RRDP session of this process.
+    session: Uuid,
+
+    /// The published RRDP repositories.
+    ///
+    /// This is `None` before the mirror was written for the first time.
+    published: Mutex<Option<HashMap<uri::Https, Published>>>,
+}
+
+impl Mirror {
+    /// Creates a new mirror if one is configured.
+    pub fn new(config: &Config) -> Option<Self> {
+        let dir = config.mirror_dir.clone()?;
+        Some(Mirror {
+            dir,
+            https_uri: config.mirror_https_uri.as_ref().map(|uri| {
+                let mut uri = uri.to_string();
+                if !uri.ends_with('/') {
+                    uri.push('/')
+                }
+                uri
+            }),
+            session: uuid::Builder::from_random_bytes(
+                rand::random()
+            ).into_uuid(),
+            published: Mutex::new(None),
+        })
+    }
+
+    /// Updates the mirror with the content of the store.
+    ///
+    /// The trust anchor certificates are given via `tas`.
+    ///
+    /// As the mirror is only a service to others, errors are logged but
+    /// otherwise ignored.
+    pub fn update(&self, store: &Store, tas: &[(TalUri, Bytes)]) {
+        let mut published = self.published.lock();
+        if let Err(err) = self.update_inner(store, tas, &mut published) {
+            error!(
+                "Failed to update mirror in {}: {}",
+                self.dir.display(), err
+            );
+        }
+    }
+
+    /// Updates the mirror, returning any error.
+    fn update_inner(
+        &self,
+        store: &Store,
+        tas: &[(TalUri, Bytes)],
+        published: &mut Option<HashMap<uri::Https, Published>>,
+    ) -> Result<(), io::Error> {
+        let rsync_dir = self.dir.join("rsync");
+        let https_dir = self.dir.join("https");
+        let tmp_dir = self.dir.join("tmp");
+
+        // Start from scratch when the mirror is written for the first time
+        // so we don’t keep anything from an earlier process.
+        let published = match published {
+            Some(published) => published,
+            None => {
+                for dir in [&rsync_dir, &https_dir, &tmp_dir] {
+                    remove_dir_all(dir)?;
+                }
+                published.insert(HashMap::new())
+            }
+        };
+        fs::create_dir_all(&rsync_dir)?;
+        fs::create_dir_all(&https_dir)?;
+        fs::create_dir_all(&tmp_dir)?;
+
+        let mut rsync_paths = HashSet::new();
+        let mut snapshots = HashMap::new();
+
+        for (uri, content) in tas {
+            match uri {
+                TalUri::Rsync(uri) => {
+                    write_rsync_object(
+                        &rsync_dir, uri, content, &mut rsync_paths
+                    )?;
+                }
+                TalUri::Https(uri) => {
+                    if let Some(path) = https_path(&https_dir, uri) {
+                        write_if_changed(&path, content)?;
+                    }
+                }
+            }
+        }
+
+        for point in store.iter_points() {
+            let mut point = point.map_err(|_| {
+                io::Error::new(io::ErrorKind::Other, "failed to read store")
+            })?;
+            let manifest = match point.take_manifest() {
+                Some(manifest) => manifest,
+                None => continue,
+            };
+            let mut snapshot = match (
+                self.https_uri.is_some(), manifest.rpki_notify()
+            ) {
+                (true, Some(notify)) => {
+                    if !snapshots.contains_key(notify) {
+                        let path = tmp_dir.join(
+                            format!("{}.xml", snapshots.len())
+                        );
+                        snapshots.insert(
+                            notify.clone(), SnapshotBody::create(path)?
+                        );
+                    }
+                    snapshots.get_mut(notify)
+                }
+                _ => None
+            };
+            let mut add = |uri: &uri::Rsync, content: &Bytes| {
+                write_rsync_object(
+                    &rsync_dir, uri, content, &mut rsync_paths
+                )?;
+                if let Some(snapshot) = snapshot.as_mut() {
+                    snapshot.publish(uri, content)?;
+                }
+                Ok::<_, io::Error>(())
+            };
+            add(manifest.manifest_uri(), manifest.manifest())?;
+            add(manifest.crl_uri(), manifest.crl())?;
+            while let Some(object) = point.next() {
+                let object = object.map_err(|err| {
+                    io::Error::new(
+                        io::ErrorKind::Other,
+                        format!(
+                            "failed to read stored point {}: {}",
+                            point.path().display(), err
+                        )
+                    )
+                })?;
+                add(object.uri(), object.content())?;
+            }
+        }
+
+        prune_dir(&rsync_dir, &rsync_paths)?;
+
+        let mut changed = 0;
+        if let Some(https_uri) = self.https_uri.as_ref() {
+            let removed: Vec<_> = published.keys().filter(|notify| {
+                !snapshots.contains_key(*notify)
+            }).cloned().collect();
+            for notify in removed {
+                self.remove_repository(&https_dir, &notify)?;
+                published.remove(&notify);
+            }
+            for (notify, snapshot) in snapshots {
+                if self.publish_snapshot(
+                    &https_dir, https_uri, &notify, snapshot, published
+                )? {
+                    changed += 1;
+                }
+            }
+        }
+        remove_dir_all(&tmp_dir)?;
+
+        info!(
+            "Updated mirror in {}: {} RRDP repositories changed.",
+            self.dir.display(), changed
+        );
+        Ok(())
+    }
+
+    /// Publishes the snapshot of an RRDP repository if it has changed.
+    ///
+    /// Returns whether a new snapshot was published.
+    fn publish_snapshot(
+        &self,
+        https_dir: &Path,
+        https_uri: &str,
+        notify: &uri::Https,
+        snapshot: SnapshotBody,
+        published: &mut HashMap<uri::Https, Published>,
+    ) -> Result<bool, io::Error> {
+        let (tmp_path, digest) = snapshot.finish()?;
+        let notify_path = match https_path(https_dir, notify) {
+            Some(path) => path,
+            None => {
+                debug!("Mirror: skipping RRDP repository {}.", notify);
+                fs::remove_file(&tmp_path)?;
+                return Ok(false)
+            }
+        };
+        let serial = match published.get(notify) {
+            Some(item) if item.digest == digest => {
+                fs::remove_file(&tmp_path)?;
+                return Ok(false)
+            }
+            Some(item) => item.serial + 1,
+            None => 1,
+        };
+        let base_dir = match notify_path.parent() {
+            Some(dir) => dir,
+            None => return Ok(false)
+        };
+
+        // Write the snapshot.
+        let snapshot_rel = format!(
+            "{}/{}/snapshot.xml", self.session, serial
+        );
+        let snapshot_path = base_dir.join(&snapshot_rel);
+        if let Some(dir) = snapshot_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut hasher = HashingWriter::new(
+            BufWriter::new(File::create(&snapshot_path)?)
+        );
+        writeln!(
+            hasher,
+            "<snapshot xmlns=\"http://www.ripe.net/rpki/rrdp\" \
+             version=\"1\" session_id=\"{}\" serial=\"{}\">",
+            self.session, serial
+        )?;
+        io::copy(&mut File::open(&tmp_path)?, &mut hasher)?;
+        hasher.write_all(b"</snapshot>\n")?;
+        let hash = hasher.finish()?;
+        fs::remove_file(&tmp_path)?;
+
+        // Write the notification file.
+        let snapshot_uri = {
+            let base = notify.as_str().trim_start_matches("https://");
+            let base = match base.rfind('/') {
+                Some(pos) => &base[..pos + 1],
+                None => base,
+            };
+            format!("{}{}{}", https_uri, base, snapshot_rel)
+        };
+        let snapshot_uri = uri::Https::from_string(
+            snapshot_uri
+        ).map_err(|err| {
+            io::Error::new(io::ErrorKind::Other, err.to_string())
+        })?;
+        let mut notification = Vec::new();
+        NotificationFile::new(
+            self.session, serial, UriAndHash::new(snapshot_uri, hash),
+            Vec::new()
+        ).write_xml(&mut notification)?;
+        let mut tmp_notify = notify_path.clone().into_os_string();
+        tmp_notify.push(".tmp");
+        fs::write(&tmp_notify, notification)?;
+        fs::rename(&tmp_notify, &notify_path)?;
+
+        // Remove outdated snapshots but keep the previous one for clients
+        // that are just fetching it.
+        self.remove_old_snapshots(base_dir, serial)?;
+
+        published.insert(notify.clone(), Published { serial, digest });
+        Ok(true)
+    }
+
+    /// Removes an RRDP repository that is not present anymore.
+    fn remove_repository(
+        &self, https_dir: &Path, notify: &uri::Https
+    ) -> Result<(), io::Error> {
+        let path = match https_path(https_dir, notify) {
+            Some(path) => path,
+            None => return Ok(())
+        };
+        match fs::remove_file(&path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                return Err(err)
+            }
+            _ => { }
+        }
+        if let Some(dir) = path.parent() {
+            remove_dir_all(&dir.join(self.session.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// Removes snapshots older than the previous serial.
+    fn remove_old_snapshots(
+        &self, base_dir: &Path, serial: u64
+    ) -> Result<(), io::Error> {
+        for entry in fs::read_dir(base_dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let name = match name.to_str() {
+                Some(name) => name,
+                None => continue,
+            };
+            let session = match Uuid::parse_str(name) {
+                Ok(session) => session,
+                Err(_) => continue,
+            };
+            if session != self.session {
+                remove_dir_all(&entry.path())?;
+                continue
+            }
+            for entry in fs::read_dir(entry.path())? {
+                let entry = entry?;
+                let old = entry.file_name().to_str().and_then(|name| {
+                    name.parse::<u64>().ok()
+                });
+                if old.is_some_and(|old| old + 1 < serial) {
+                    remove_dir_all(&entry.path())?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+
+//------------ Published -----------------------------------------------------
+
+/// Information about a published RRDP repository.
+#[derive(Clone, Copy, Debug)]
+struct Published {
+    /// The current serial number.
+    serial: u64,
+
+    /// The SHA-256 digest of the publish elements of the snapshot.
+    digest: [u8; 32],
+}
+
+
+//------------ SnapshotBody --------------------------------------------------
+
+/// The publish elements of a snapshot being assembled.
+///
+/// Since the publication points of a repository may be spread out in the
+/// store, the elements are collected in a temporary file first.
+struct SnapshotBody {
+    /// The path of the temporary file.
+    path: PathBuf,
+
+    /// The temporary file.
+    file: HashingWriter<BufWriter<File>>,
+}
+
+impl SnapshotBody {
+    /// Creates a new, empty body in a temporary file.
+    fn create(path: PathBuf) -> Result<Self, io::Error> {
+        let file = HashingWriter::new(BufWriter::new(File::create(&path)?));
+        Ok(SnapshotBody { path, file })
+    }
+
+    /// Adds a publish element for an object.
+    fn publish(
+        &mut self, uri: &uri::Rsync, content: &[u8]
+    ) -> Result<(), io::Error> {
+        writeln!(
+            self.file, "  <publish uri=\"{}\">{}</publish>",
+            xml_attr(uri.as_str()), base64::Xml.encode(content)
+        )
+    }
+
+    /// Finishes the body.
+    ///
+    /// Returns the path of the temporary file and the digest of its content.
+    fn finish(self) -> Result<(PathBuf, [u8; 32]), io::Error> {
+        let digest = self.file.finish()?;
+        Ok((self.path, digest.into()))
+    }
+}
+
+
+//------------ HashingWriter -------------------------------------------------
+
+/// A writer that calculates the SHA-256 digest of everything written.
+struct HashingWriter<W> {
+    /// The underlying writer.
+    writer: W,
+
+    /// The digest context.
+    context: digest::Context,
+}
+
+impl<W: Write> HashingWriter<W> {
+    /// Creates a new hashing writer.
+    fn new(writer: W) -> Self {
+        HashingWriter {
+            writer,
+            context: digest::Context::new(&digest::SHA256),
+        }
+    }
+
+    /// Flushes the writer and returns the digest.
+    fn finish(mut self) -> Result<Hash, io::Error> {
+        self.writer.flush()?;
+        Hash::try_from(self.context.finish()).map_err(|_| {
+            io::Error::new(io::ErrorKind::Other, "invalid digest")
+        })
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+        let len = self.writer.write(buf)?;
+        self.context.update(&buf[..len]);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> Result<(), io::Error> {
+        self.writer.flush()
+    }
+}
+
+
+//------------ Helper Functions ----------------------------------------------
+
+/// Returns the path for a URI below a base directory.
+///
+/// The path is formed by the components in `prefix`, such as the
+/// authority, followed by those of `path`. Returns `None` if any of these
+/// components is empty, would lead outside of the directory, or contains a
+/// path separator.
+fn uri_path(base: &Path, prefix: &[&str], path: &str) -> Option<PathBuf> {
+    let mut res = base.to_path_buf();
+    for component in prefix.iter().copied().chain(path.split('/')) {
+        match component {
+            "" | "." | ".." => return None,
+            component if component.contains(['/', '\\']) => return None,
+            component => res.push(component),
+        }
+    }
+    Some(res)
+}
+
+/// Returns the path of an HTTPS URI in the `https` directory.
+fn https_path(base: &Path, uri: &uri::Https) -> Option<PathBuf> {
+    let path = uri.as_str().strip_prefix("https://")?;
+    let (authority, path) = path.split_once('/')?;
+    uri_path(base, &[authority], path)
+}
+
+/// Writes an object into the `rsync` directory.
+///
+/// The path of the object is added to `paths`.
+fn write_rsync_object(
+    base: &Path,
+    uri: &uri::Rsync,
+    content: &[u8],
+    paths: &mut HashSet<PathBuf>,
+) -> Result<(), io::Error> {
+    let path = match uri_path(
+        base,
+        &[uri.canonical_authority().as_ref(), uri.module_name()],
+        uri.path()
+    ) {
+        Some(path) => path,
+        None => {
+            debug!("Mirror: skipping object {}.", uri);
+            return Ok(())
+        }
+    };
+    write_if_changed(&path, content)?;
+    paths.insert(path);
+    Ok(())
+}
+
+/// Writes a file unless it already has the given content.
+///
+/// This avoids changing the modification time of unchanged files which
+/// would make rsync transfer them again.
+fn write_if_changed(path: &Path, content: &[u8]) -> Result<(), io::Error> {
+    if let Ok(mut file) = File::open(path) {
+        if file.metadata()?.len() == content.len() as u64 {
+            let mut existing = Vec::with_capacity(content.len());
+            file.read_to_end(&mut existing)?;
+            if existing == content {
+                return Ok(())
+            }
+        }
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, content)
+}
+
+/// Removes all files not in `keep` from a directory tree.
+///
+/// Directories that end up empty are removed, too. Returns whether the
+/// directory is empty.
+fn prune_dir(dir: &Path, keep: &HashSet<PathBuf>) -> Result<bool, io::Error> {
+    let mut empty = true;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            if prune_dir(&path, keep)? {
+                fs::remove_dir(&path)?;
+            }
+            else {
+                empty = false;
+            }
+        }
+        else if keep.contains(&path) {
+            empty = false;
+        }
+        else {
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(empty)
+}
+
+/// Removes a directory tree if it exists.
+fn remove_dir_all(path: &Path) -> Result<(), io::Error> {
+    match fs::remove_dir_all(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(())
+    }
+}
+
+/// Escapes a string for use in an XML attribute.
+fn xml_attr(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            ch => res.push(ch),
+        }
+    }
+    res
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn paths() {
+        let base = Path::new("/mirror/https");
+        assert_eq!(
+            https_path(
+                base,
+                &uri::Https::from_str(
+                    "https://rrdp.example.net/rrdp/notification.xml"
+                ).unwrap()
+            ),
+            Some(PathBuf::from(
+                "/mirror/https/rrdp.example.net/rrdp/notification.xml"
+            ))
+        );
+        assert_eq!(uri_path(base, &["example.net"], "a/../b"), None);
+        assert_eq!(uri_path(base, &["example.net"], "a//b"), None);
+        assert_eq!(uri_path(base, &["..", "repo"], "a"), None);
+        assert_eq!(uri_path(base, &["example.net", "."], "a"), None);
+        assert_eq!(uri_path(base, &["example.net", "a\\b"], "c"), None);
+        assert_eq!(
+            uri_path(base, &["example.net", "repo"], "a/b.roa"),
+            Some(PathBuf::from("/mirror/https/example.net/repo/a/b.roa"))
+        );
+        assert_eq!(
+            xml_attr("rsync://a/b&\"c\""), "rsync://a/b&amp;&quot;c&quot;"
+        );
+    }
+
+    #[test]
+    fn publish_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default_with_paths(
+            Default::default(), Default::default()
+        );
+        config.mirror_dir = Some(dir.path().into());
+        config.mirror_https_uri = Some(
+            uri::Https::from_str("https://mirror.example.net/https").unwrap()
+        );
+        let mirror = Mirror::new(&config).unwrap();
+        let https_dir = dir.path().join("https");
+        let notify = uri::Https::from_str(
+            "https://rrdp.example.net/rrdp/notification.xml"
+        ).unwrap();
+        let object = uri::Rsync::from_str(
+            "rsync://example.net/repo/a.roa"
+        ).unwrap();
+        let mut published = HashMap::new();
+
+        let publish = |published: &mut _| {
+            let mut body = SnapshotBody::create(
+                dir.path().join("body.xml")
+            ).unwrap();
+            body.publish(&object, b"object").unwrap();
+            mirror.publish_snapshot(
+                &https_dir, mirror.https_uri.as_ref().unwrap(), &notify,
+                body, published
+            ).unwrap()
+        };
+
+        // The first snapshot is published, an unchanged one is not.
+        assert!(publish(&mut published));
+        assert!(!publish(&mut published));
+
+        let notification = NotificationFile::parse(io::BufReader::new(
+            File::open(
+                https_dir.join("rrdp.example.net/rrdp/notification.xml")
+            ).unwrap()
+        )).unwrap();
+        assert_eq!(notification.session_id(), mirror.session);
+        assert_eq!(notification.serial(), 1);
+        assert_eq!(
+            notification.snapshot().uri().as_str(),
+            format!(
+                "https://mirror.example.net/https/rrdp.example.net/rrdp/\
+                 {}/1/snapshot.xml",
+                mirror.session
+            )
+        );
+        let snapshot_path = https_dir.join(format!(
+            "rrdp.example.net/rrdp/{}/1/snapshot.xml", mirror.session
+        ));
+        assert!(
+            notification.snapshot().hash().matches(
+                &fs::read(&snapshot_path).unwrap()
+            )
+        );
+        let snapshot = rpki::rrdp::Snapshot::parse(io::BufReader::new(
+            File::open(&snapshot_path).unwrap()
+        )).unwrap();
+        assert_eq!(snapshot.elements().len(), 1);
+        assert_eq!(snapshot.elements()[0].uri(), &object);
+        assert_eq!(snapshot.elements()[0].data().as_ref(), b"object");
+    }
+
+    #[test]
+    fn prune() {
+        let dir = tempfile::tempdir().unwrap();
+        let keep_path = dir.path().join("a/b/keep");
+        write_if_changed(&keep_path, b"keep").unwrap();
+        write_if_changed(&dir.path().join("a/b/drop"), b"drop").unwrap();
+        write_if_changed(&dir.path().join("c/drop"), b"drop").unwrap();
+        let keep = HashSet::from([keep_path.clone()]);
+        assert!(!prune_dir(dir.path(), &keep).unwrap());
+        assert!(keep_path.exists());
+        assert!(!dir.path().join("a/b/drop").exists());
+        assert!(!dir.path().join("c").exists());
+    }
+}
@@ -0,0 +1,154 @@
+//! Validation of a local directory.
+//!
+//! This module allows validating repository data that has not been
+//! published yet, e.g., to check a repository before it goes live. The
+//! data is provided in a directory that contains the objects under their
+//! rsync URI without the `rsync://` prefix, i.e., a directory for each
+//! rsync server containing a directory for each module. This is also the
+//! layout of the `rsync` directory of a dump produced by the `dump`
+//! command, so the directory of such a dump can be used, too.
+//!
+//! Much like the self-test, validation happens in a temporary directory
+//! using the collector in offline mode. The network is never accessed and
+//! the configured cache is not touched.
+
+use std::path::{Path, PathBuf};
+use log::{error, info};
+use tempfile::TempDir;
+use crate::config::Config;
+use crate::engine::Engine;
+use crate::error::Failed;
+use crate::metrics::Metrics;
+use crate::payload::{PayloadSnapshot, ValidationReport};
+use crate::slurm::LocalExceptions;
+use crate::utils::fatal;
+
+
+//------------ OfflineValidation ---------------------------------------------
+
+/// Validation of a local directory against a single TAL.
+///
+/// The validation is prepared via [`new`][Self::new] which creates a
+/// temporary directory and a suitable configuration. It is then performed
+/// via [`run`][Self::run]. The temporary directory is removed when the
+/// value is dropped.
+pub struct OfflineValidation {
+    /// The temporary directory holding the cache and the TAL.
+    base: TempDir,
+
+    /// The configuration to use for validation.
+    config: Config,
+
+    /// The directory containing the data keyed by rsync URI.
+    source: PathBuf,
+
+    /// The TAL file to validate against.
+    tal: PathBuf,
+}
+
+impl OfflineValidation {
+    /// Prepares validation of the directory `source` using the TAL `tal`.
+    ///
+    /// The configuration is derived from `config`. All paths, the trust
+    /// anchors, and the local exceptions are replaced, but the remaining
+    /// configuration is kept so that the same policy as for regular runs
+    /// applies.
+    pub fn new(
+        config: &Config, source: &Path, tal: &Path,
+    ) -> Result<Self, Failed> {
+        if !source.is_dir() {
+            error!("{} is not a directory.", source.display());
+            return Err(Failed)
+        }
+        let base = match tempfile::tempdir() {
+            Ok(base) => base,
+            Err(err) => {
+                error!("Failed to create temporary directory: {}", err);
+                return Err(Failed)
+            }
+        };
+
+        // A dump keeps the rsync data in its own sub-directory.
+        let dump_dir = source.join("rsync");
+        let source = if dump_dir.is_dir() { dump_dir } else { source.into() };
+
+        let mut config = config.clone();
+        config.cache_dir = base.path().join("repository");
+        config.no_rir_tals = true;
+        config.bundled_tals = Vec::new();
+        config.extra_tals_dir = Some(base.path().join("tals"));
+        config.tal_labels = Default::default();
+        config.exceptions = Vec::new();
+        config.fresh = false;
+        config.store_readonly = false;
+        Ok(OfflineValidation { base, config, source, tal: tal.into() })
+    }
+
+    /// Performs the validation.
+    ///
+    /// Returns the resulting payload and the metrics of the validation run.
+    /// Rejected objects are listed in the metrics and, if configured, in
+    /// the rejection report.
+    pub fn run(&self) -> Result<(PayloadSnapshot, Metrics), Failed> {
+        self.install_tal()?;
+        let mut engine = Engine::new_offline(&self.config)?;
+        let count = engine.import_rsync(&self.source)?;
+        info!(
+            "Validating {} rsync modules from {}.",
+            count, self.source.display()
+        );
+        engine.ignite()?;
+        let (report, mut metrics) = ValidationReport::process(
+            &engine, &self.config
+        ).map_err(|_| Failed)?;
+        let snapshot = report.into_snapshot(
+            &LocalExceptions::empty(), &mut metrics
+        );
+        Ok((snapshot, metrics))
+    }
+
+    /// Places the TAL into the temporary TAL directory.
+    ///
+    /// The TAL keeps its file name so that it is named as usual in output.
+    fn install_tal(&self) -> Result<(), Failed> {
+        let name = match self.tal.file_stem() {
+            Some(name) => name,
+            None => {
+                error!("Invalid TAL path {}.", self.tal.display());
+                return Err(Failed)
+            }
+        };
+        let tal_dir = self.base.path().join("tals");
+        fatal::create_dir_all(&tal_dir)?;
+        let mut file_name = name.to_owned();
+        file_name.push(".tal");
+        fatal::write_file(
+            &tal_dir.join(file_name), &fatal::read_file(&self.tal)?
+        )
+    }
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn validate_self_test_repository() {
+        let _ = crate::process::Process::init(); // May be inited already.
+        let config = Config::default_with_paths(
+            Default::default(), Default::default()
+        );
+        let base = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("test/self-test");
+        let (snapshot, metrics) = OfflineValidation::new(
+            &config, &base.join("repository"), &base.join("self-test.tal")
+        ).unwrap().run().unwrap();
+        assert!(metrics.rejected_objects.is_empty());
+        assert_eq!(metrics.tals.len(), 1);
+        assert_eq!(metrics.tals[0].name(), "self-test");
+        assert_eq!(snapshot.origin_refs().count(), 4);
+    }
+}
@@ -11,7 +11,8 @@
 // consisitency.
 #![allow(clippy::unnecessary_wraps)]
 
-use std::{fs, io, thread};
+use std::{cmp, fmt, fs, io, thread};
+use std::future::Future;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::process::Command;
@@ -20,26 +21,43 @@ use std::sync::mpsc;
 use std::sync::Arc;
 use std::sync::mpsc::RecvTimeoutError;
 use std::time::{Duration, Instant};
-#[cfg(feature = "rta")] use bytes::Bytes;
+use bytes::Bytes;
+use chrono::{DateTime, SecondsFormat, Utc};
 use clap::{Arg, Args, ArgAction, ArgMatches, FromArgMatches, Parser};
 use log::{error, info, warn};
 use rpki::resources::{Asn, Prefix};
 #[cfg(feature = "rta")] use rpki::repository::rta::Rta;
+use rpki::rtr::Serial;
 use rpki::rtr::server::NotifySender;
+use rpki::uri;
 use tempfile::NamedTempFile;
-use tokio::sync::oneshot;
+use tokio::sync::{mpsc as async_mpsc, oneshot};
 #[cfg(feature = "rta")] use crate::rta;
-use crate::{output, validity};
+use crate::{offline, output, validity};
+use crate::collector::{
+    Backoff, Heartbeat, RefreshTarget, RrdpRepositoryInfo
+};
 use crate::config::Config;
+use crate::decode::{ObjectType, render_json};
 use crate::error::{ExitError, Failed, RunFailed};
-use crate::http::http_listener;
-use crate::metrics::{SharedRtrServerMetrics};
+use crate::http::{AuditLog, http_listener};
+use crate::metrics::{
+    CleanupPreview, DivergenceMetrics, FailingRepository, Metrics,
+    PreviewChange,
+    SharedRtrServerMetrics, UpdatePreview,
+};
 use crate::output::{Output, OutputFormat};
-use crate::payload::{PayloadSnapshot, SharedHistory, ValidationReport};
+use crate::payload::{
+    GhostbusterRecord, PayloadSnapshot, SharedHistory, ValidationReport
+};
 use crate::process::Process;
-use crate::engine::Engine;
-use crate::rtr::{rtr_listener};
+use crate::engine::{Engine, TalSwitch};
+use crate::rtr::{rtr_listener, rtr_upstream};
+use crate::selftest;
 use crate::slurm::LocalExceptions;
+use crate::store::Store;
+use crate::utils::json::JsonBuilder;
+use crate::utils::tls;
 
 #[cfg(unix)] use tokio::signal::unix::{Signal, SignalKind, signal};
 #[cfg(not(unix))] use futures::future::pending;
@@ -68,8 +86,19 @@ pub enum Operation {
     #[cfg(feature = "rta")]
     ValidateDocument(ValidateDocument),
     Update(Update),
+    Refresh(Refresh),
     PrintConfig(PrintConfig),
     Dump(Dump),
+    Import(Import),
+    WhereIs(WhereIs),
+    Pin(Pin),
+    RrdpState(RrdpState),
+    RepoHealth(RepoHealth),
+    Gbr(Gbr),
+    Crosscheck(Crosscheck),
+    Decode(Decode),
+    SelfTest(SelfTest),
+    ValidateDir(ValidateDir),
     Man(Man),
 }
 
@@ -91,8 +120,19 @@ impl Operation {
         let app = ValidateDocument::config_args(app);
 
         let app = Update::config_args(app);
+        let app = Refresh::config_args(app);
         let app = PrintConfig::config_args(app);
         let app = Dump::config_args(app);
+        let app = Import::config_args(app);
+        let app = WhereIs::config_args(app);
+        let app = Pin::config_args(app);
+        let app = RrdpState::config_args(app);
+        let app = RepoHealth::config_args(app);
+        let app = Gbr::config_args(app);
+        let app = Crosscheck::config_args(app);
+        let app = Decode::config_args(app);
+        let app = SelfTest::config_args(app);
+        let app = ValidateDir::config_args(app);
         Man::config_args(app)
     }
 
@@ -123,6 +163,9 @@ impl Operation {
             Some(("update", matches)) => {
                 Operation::Update(Update::from_arg_matches(matches)?)
             }
+            Some(("refresh", matches)) => {
+                Operation::Refresh(Refresh::from_arg_matches(matches)?)
+            }
             Some(("config", matches)) => {
                 Operation::PrintConfig(
                     PrintConfig::from_arg_matches(matches, cur_dir, config)?
@@ -131,6 +174,38 @@ impl Operation {
             Some(("dump", matches)) => {
                 Operation::Dump( Dump::from_arg_matches(matches, cur_dir)?)
             }
+            Some(("import", matches)) => {
+                Operation::Import(Import::from_arg_matches(matches, cur_dir)?)
+            }
+            Some(("whereis", matches)) => {
+                Operation::WhereIs(WhereIs::from_arg_matches(matches)?)
+            }
+            Some(("pin", matches)) => {
+                Operation::Pin(Pin::from_arg_matches(matches)?)
+            }
+            Some(("rrdp-state", matches)) => {
+                Operation::RrdpState(RrdpState::from_arg_matches(matches)?)
+            }
+            Some(("repo-health", matches)) => {
+                Operation::RepoHealth(RepoHealth::from_arg_matches(matches)?)
+            }
+            Some(("gbr", matches)) => {
+                Operation::Gbr(Gbr::from_arg_matches(matches)?)
+            }
+            Some(("crosscheck", matches)) => {
+                Operation::Crosscheck(Crosscheck::from_arg_matches(matches)?)
+            }
+            Some(("decode", matches)) => {
+                Operation::Decode(Decode::from_arg_matches(matches)?)
+            }
+            Some(("self-test", matches)) => {
+                Operation::SelfTest(SelfTest::from_arg_matches(matches)?)
+            }
+            Some(("validate-dir", matches)) => {
+                Operation::ValidateDir(
+                    ValidateDir::from_arg_matches(matches, cur_dir)?
+                )
+            }
             Some(("man", matches)) => {
                 Operation::Man(Man::from_arg_matches(matches)?)
             }
@@ -165,8 +240,19 @@ impl Operation {
             #[cfg(feature = "rta")]
             Operation::ValidateDocument(cmd) => cmd.run(process),
             Operation::Update(cmd) => cmd.run(process),
+            Operation::Refresh(cmd) => cmd.run(process),
             Operation::PrintConfig(cmd) => cmd.run(process),
             Operation::Dump(cmd) => cmd.run(process),
+            Operation::Import(cmd) => cmd.run(process),
+            Operation::WhereIs(cmd) => cmd.run(process),
+            Operation::Pin(cmd) => cmd.run(process),
+            Operation::RrdpState(cmd) => cmd.run(process),
+            Operation::RepoHealth(cmd) => cmd.run(process),
+            Operation::Gbr(cmd) => cmd.run(process),
+            Operation::Crosscheck(cmd) => cmd.run(process),
+            Operation::Decode(cmd) => cmd.run(process),
+            Operation::SelfTest(cmd) => cmd.run(process),
+            Operation::ValidateDir(cmd) => cmd.run(process),
             Operation::Man(cmd) => cmd.run(process),
         }
     }
@@ -228,18 +314,35 @@ impl Server {
         );
 
         let history = SharedHistory::from_config(process.config());
+        let audit = AuditLog::new(process.config());
         let mut notify = NotifySender::new();
+        let (refresh_tx, mut refresh_rx) = async_mpsc::unbounded_channel();
+        let (tal_tx, mut tal_rx) = async_mpsc::unbounded_channel();
+        let (heartbeat_tx, mut heartbeat_rx)
+            = async_mpsc::unbounded_channel();
         let rtr = rtr_listener(
             history.clone(), rtr_metrics.clone(), process.config(),
             notify.clone(), process.get_listen_fd()?
         )?;
         let http = http_listener(
             history.clone(), rtr_metrics, log.clone(), process.config(),
-            notify.clone(),
+            notify.clone(), refresh_tx, tal_tx, audit.clone(),
         )?;
 
         process.drop_privileges()?;
 
+        if let Some(upstream) = process.config().rtr_upstream.clone() {
+            return Self::serve_only(
+                process, upstream, history, notify, rtr, http
+            )
+        }
+
+        if process.config().freeze {
+            if let Some(audit) = audit.as_ref() {
+                audit.write_freeze();
+            }
+        }
+
         let mut validation = Engine::new(process.config(), true)?;
         let runtime = process.runtime()?;
         let mut rtr = runtime.spawn(rtr);
@@ -249,9 +352,18 @@ impl Server {
 
         validation.ignite()?;
 
+        let heartbeat = process.config().rrdp_heartbeat;
+        let heartbeat_req = heartbeat.and_then(|_| {
+            validation.heartbeat()
+        }).map(|heartbeat| Self::spawn_heartbeat(heartbeat, heartbeat_tx));
+
         let join = thread::spawn(move || {
             let mut can_retry = true;
+            let probe = process.config().repository_probe;
+            let mut next_probe = probe.map(|probe| Instant::now() + probe);
+            let mut run = 0u64;
             let err = loop {
+                run = run.wrapping_add(1);
                 if let Some(log) = log.as_ref() {
                     log.start();
                 }
@@ -259,12 +371,23 @@ impl Server {
                     process.config(), true
                 ) {
                     Ok(exceptions) => {
+                        let serial = history.read().serial();
                         match Self::process_once(
                             process.config(), &validation, &history,
-                            &mut notify, exceptions,
+                            &mut notify, exceptions, None,
                         ) {
                             Ok(()) => {
-                                history.read().refresh_wait()
+                                Self::audit_run(
+                                    audit.as_ref(), &history, serial
+                                );
+                                let wait = history.read().refresh_wait();
+                                match process.config().fetch_spread {
+                                    Some(spread) => cmp::min(
+                                        wait,
+                                        process.config().refresh / spread
+                                    ),
+                                    None => wait
+                                }
                             }
                             Err(err) => {
                                 if err.should_retry() {
@@ -309,12 +432,24 @@ impl Server {
                 // log rotation, we need to loop here. But then we need
                 // to recalculate timeout.
                 let deadline = Instant::now() + timeout;
+                let mut next_heartbeat = heartbeat.map(|heartbeat| {
+                    Instant::now() + heartbeat
+                });
 
                 let end = loop {
-                    let timeout = deadline.saturating_duration_since(
-                        Instant::now()
-                    );
-                    match sig_rx.recv_timeout(timeout) {
+                    let mut wakeup = deadline;
+                    if let Some(next) = next_heartbeat {
+                        wakeup = cmp::min(wakeup, next);
+                    }
+                    if let Some(next) = next_probe {
+                        wakeup = cmp::min(wakeup, next);
+                    }
+                    if let Some(next) = history.read().pending_deadline() {
+                        wakeup = cmp::min(wakeup, next);
+                    }
+                    match sig_rx.recv_timeout(
+                        wakeup.saturating_duration_since(Instant::now())
+                    ) {
                         Ok(UserSignal::ReloadTals) => {
                             match validation.reload_tals() {
                                 Ok(_) => {
@@ -335,8 +470,95 @@ impl Server {
                                 break Some(Err(Failed));
                             }
                         }
+                        Ok(UserSignal::SwitchTal(switch)) => {
+                            if validation.switch_tal(&switch) {
+                                break None;
+                            }
+                        }
+                        Ok(UserSignal::Heartbeat(heartbeat_run, uri)) => {
+                            // Ignore results of checks started before the
+                            // last validation run.
+                            if heartbeat_run == run {
+                                info!(
+                                    "RRDP {}: new serial, starting \
+                                     validation early.",
+                                    uri
+                                );
+                                break None;
+                            }
+                        }
+                        Ok(UserSignal::Refresh(target)) => {
+                            let exceptions = match LocalExceptions::load(
+                                process.config(), true
+                            ) {
+                                Ok(exceptions) => exceptions,
+                                Err(_) => {
+                                    error!(
+                                        "Failed to load exceptions. \
+                                         Skipping refresh of {}.",
+                                        target
+                                    );
+                                    continue;
+                                }
+                            };
+                            let serial = history.read().serial();
+                            match Self::process_once(
+                                process.config(), &validation, &history,
+                                &mut notify, exceptions, Some(&target),
+                            ) {
+                                Ok(()) => {
+                                    Self::audit_run(
+                                        audit.as_ref(), &history, serial
+                                    );
+                                }
+                                Err(err) if err.should_retry() => {
+                                    error!(
+                                        "Refreshing {} failed.", target
+                                    );
+                                }
+                                Err(_) => break Some(Err(Failed)),
+                            }
+                        }
                         Err(RecvTimeoutError::Timeout) => {
-                            break None;
+                            if history.publish_pending() {
+                                info!("Sending out notifications.");
+                                notify.notify();
+                            }
+                            let now = Instant::now();
+                            if now >= deadline {
+                                break None;
+                            }
+                            if next_probe.map(|next| now >= next)
+                                .unwrap_or(false)
+                            {
+                                match validation.probe() {
+                                    Ok(probes) => {
+                                        history.update_probes(probes)
+                                    }
+                                    Err(_) => {
+                                        error!(
+                                            "Fatal: Probing repositories \
+                                             failed, shutting down."
+                                        );
+                                        break Some(Err(Failed));
+                                    }
+                                }
+                                next_probe = probe.map(|probe| {
+                                    Instant::now() + probe
+                                });
+                            }
+                            if next_heartbeat.map(|next| now >= next)
+                                .unwrap_or(false)
+                            {
+                                next_heartbeat = heartbeat.map(|heartbeat| {
+                                    Instant::now() + heartbeat
+                                });
+                                // If the last check is still running, we
+                                // simply skip this one.
+                                if let Some(req) = heartbeat_req.as_ref() {
+                                    let _ = req.try_send(run);
+                                }
+                            }
                         }
                         Err(RecvTimeoutError::Disconnected) => {
                             break Some(Ok(()));
@@ -360,6 +582,25 @@ impl Server {
                             break Err(Failed);
                         }
                     }
+                    Some(target) = refresh_rx.recv() => {
+                        if sig_tx.send(UserSignal::Refresh(target)).is_err() {
+                            break Err(Failed);
+                        }
+                    }
+                    Some(switch) = tal_rx.recv() => {
+                        if sig_tx.send(
+                            UserSignal::SwitchTal(switch)
+                        ).is_err() {
+                            break Err(Failed);
+                        }
+                    }
+                    Some((run, uri)) = heartbeat_rx.recv() => {
+                        if sig_tx.send(
+                            UserSignal::Heartbeat(run, uri)
+                        ).is_err() {
+                            break Err(Failed);
+                        }
+                    }
                     res = &mut err_rx => {
                         match res {
                             Ok(res) => break res,
@@ -382,16 +623,107 @@ impl Server {
         res.map_err(Into::into)
     }
 
+    /// Spawns a thread running heartbeats on request.
+    ///
+    /// Returns the sender for requesting a heartbeat. Each request carries
+    /// the number of the current validation run which is sent back to
+    /// `tx` together with the rpkiNotify URI of a changed repository. At
+    /// most one request is queued while a heartbeat is running. The thread
+    /// ends when the returned sender is dropped.
+    fn spawn_heartbeat(
+        heartbeat: Heartbeat,
+        tx: async_mpsc::UnboundedSender<(u64, uri::Https)>,
+    ) -> mpsc::SyncSender<u64> {
+        let (req_tx, req_rx) = mpsc::sync_channel(1);
+        thread::spawn(move || {
+            while let Ok(run) = req_rx.recv() {
+                if let Some(uri) = heartbeat.check() {
+                    if tx.send((run, uri)).is_err() {
+                        break
+                    }
+                }
+            }
+        });
+        req_tx
+    }
+
+    /// Runs the server in serving-only mode.
+    ///
+    /// Instead of performing validation, the payload is received from the
+    /// upstream RTR server at `upstream`.
+    fn serve_only(
+        process: Process,
+        upstream: String,
+        history: SharedHistory,
+        notify: NotifySender,
+        rtr: impl Future<Output = ()> + Send + 'static,
+        http: impl Future<Output = ()> + Send + 'static,
+    ) -> Result<(), ExitError> {
+        info!("Serving payload from upstream RTR server {}.", upstream);
+        let config = process.config();
+        let tls = if
+            config.rtr_upstream_ca_bundle.is_some()
+            || !config.rtr_upstream_pinned_certs.is_empty()
+        {
+            Some(Arc::new(tls::create_client_config(
+                "RTR upstream",
+                config.rtr_upstream_ca_bundle.as_deref(),
+                &config.rtr_upstream_pinned_certs,
+            )?))
+        }
+        else {
+            None
+        };
+        let runtime = process.runtime()?;
+        let mut rtr = runtime.spawn(rtr);
+        let mut http = runtime.spawn(http);
+        let mut upstream = runtime.spawn(
+            rtr_upstream(upstream, tls, history, notify)
+        );
+        let res: Result<(), Failed> = runtime.block_on(async move {
+            let mut signal = SignalListener::new()?;
+            loop {
+                tokio::select! {
+                    sig = signal.next() => {
+                        if let UserSignal::RotateLog = sig {
+                            process.rotate_log()?
+                        }
+                    }
+                    _ = &mut upstream => break Err(Failed),
+                    _ = &mut rtr => break Err(Failed),
+                    _ = &mut http => break Err(Failed),
+                }
+            }
+        });
+        res.map_err(Into::into)
+    }
+
+    /// Performs a validation run and updates the history.
+    ///
+    /// If `target` is given, only this repository is updated.
     fn process_once(
         config: &Config,
         engine: &Engine,
         history: &SharedHistory,
         notify: &mut NotifySender,
         exceptions: LocalExceptions,
+        target: Option<&RefreshTarget>,
     ) -> Result<(), RunFailed> {
-        info!("Starting a validation run.");
         history.mark_update_start();
-        let (report, metrics) = ValidationReport::process(engine, config)?;
+        let (report, metrics) = match target {
+            Some(target) => {
+                info!("Starting a validation run refreshing {}.", target);
+                let res = ValidationReport::process_refresh(
+                    engine, config, target
+                )?;
+                let _ = Refresh::check(target, &res.1);
+                res
+            }
+            None => {
+                info!("Starting a validation run.");
+                ValidationReport::process(engine, config)?
+            }
+        };
         let must_notify = history.update(
             report, &exceptions, metrics,
         );
@@ -415,6 +747,21 @@ impl Server {
         history.mark_update_done();
         Ok(())
     }
+
+    /// Records a finished validation run in the audit log.
+    ///
+    /// The `serial` is the serial of the history before the run.
+    fn audit_run(
+        audit: Option<&Arc<AuditLog>>, history: &SharedHistory,
+        serial: Serial,
+    ) {
+        if let Some(audit) = audit {
+            let history = history.read();
+            audit.write_run(
+                history.serial(), history.delta_since(serial).as_deref()
+            );
+        }
+    }
 }
 
 
@@ -428,6 +775,12 @@ pub struct Vrps {
     /// Otherwise we just dump it to stdout.
     path: Option<PathBuf>,
 
+    /// Write a separate file for each TAL.
+    ///
+    /// If this is `true`, `path` is some path containing the placeholder
+    /// [`TAL_PLACEHOLDER`][Self::TAL_PLACEHOLDER].
+    partition_by_tal: bool,
+
     /// The desired output format.
     format: OutputFormat,
 
@@ -455,6 +808,14 @@ struct VrpsArgs {
     )]
     format: String,
 
+    /// The template file for the 'template' output format
+    #[arg(long, value_name = "PATH")]
+    template: Option<PathBuf>,
+
+    /// Write a separate file per TAL, replacing {tal} in the output path
+    #[arg(long)]
+    partition_by_tal: bool,
+
     /// Only include records for the given prefix
     #[arg(
         short = 'p',
@@ -497,6 +858,9 @@ struct VrpsArgs {
 }
 
 impl Vrps {
+    /// The placeholder for the TAL name in a partitioned output path.
+    const TAL_PLACEHOLDER: &'static str = "{tal}";
+
     /// Adds the command configuration to a clap app.
     pub fn config_args<'a: 'b, 'b>(app: clap::Command) -> clap::Command {
         app.subcommand(
@@ -514,11 +878,32 @@ impl Vrps {
     ) -> Result<Self, Failed> {
         let args = VrpsArgs::from_arg_matches(matches).unwrap();
 
-        let format = match OutputFormat::from_str(&args.format) {
-            Ok(format) => format,
-            Err(_) => {
-                error!("Unknown output format '{}'", args.format);
-                return Err(Failed)
+        let format = if args.format == "template" {
+            match args.template {
+                Some(ref path) => OutputFormat::from_template(path)?,
+                None => {
+                    error!(
+                        "The 'template' output format requires \
+                         the --template option."
+                    );
+                    return Err(Failed)
+                }
+            }
+        }
+        else if args.template.is_some() {
+            error!(
+                "The --template option requires the 'template' \
+                 output format."
+            );
+            return Err(Failed)
+        }
+        else {
+            match OutputFormat::from_str(&args.format) {
+                Ok(format) => format,
+                Err(_) => {
+                    error!("Unknown output format '{}'", args.format);
+                    return Err(Failed)
+                }
             }
         };
 
@@ -529,6 +914,22 @@ impl Vrps {
             Some(args.output)
         };
 
+        if args.partition_by_tal {
+            let has_placeholder = path.as_ref().and_then(|path| {
+                path.to_str()
+            }).map(|path| {
+                path.contains(Self::TAL_PLACEHOLDER)
+            }).unwrap_or(false);
+            if !has_placeholder {
+                error!(
+                    "The --partition-by-tal option requires an output \
+                     path containing '{}'.",
+                     Self::TAL_PLACEHOLDER
+                );
+                return Err(Failed)
+            }
+        }
+
         let mut output = Output::new();
 
         if args.select_prefix.is_some() || args.select_asn.is_some() {
@@ -559,6 +960,7 @@ impl Vrps {
 
         Ok(Vrps {
             path,
+            partition_by_tal: args.partition_by_tal,
             format,
             output,
             noupdate: args.noupdate,
@@ -609,17 +1011,17 @@ impl Vrps {
         let metrics = Arc::new(metrics);
         let res = match self.path {
             Some(ref path) => {
-                let mut file = match fs::File::create(path) {
-                    Ok(file) => file,
-                    Err(err) => {
-                        error!(
-                            "Failed to open output file '{}': {}",
-                            path.display(), err
-                        );
-                        return Err(Failed.into())
-                    }
-                };
-                self.output.write(vrps, metrics, self.format, &mut file)
+                if self.partition_by_tal {
+                    Self::write_partitioned(
+                        path, self.output, vrps, metrics, self.format
+                    )?;
+                }
+                else {
+                    Self::write_file(
+                        path, self.output, vrps, metrics, self.format
+                    )?;
+                }
+                Ok(())
             }
             None => {
                 let out = io::stdout();
@@ -629,10 +1031,7 @@ impl Vrps {
         };
         if let Err(err) = res {
             // Surpress an error message for broken pipe on stdout.
-            if 
-                self.path.is_some() ||
-                err.kind() != io::ErrorKind::BrokenPipe
-            {
+            if err.kind() != io::ErrorKind::BrokenPipe {
                 error!(
                     "Failed to output result: {}",
                     err
@@ -648,6 +1047,55 @@ impl Vrps {
         }
     }
 
+    /// Writes the output into a file.
+    fn write_file(
+        path: &Path,
+        output: Output,
+        vrps: Arc<PayloadSnapshot>,
+        metrics: Arc<Metrics>,
+        format: OutputFormat,
+    ) -> Result<(), Failed> {
+        let mut file = match fs::File::create(path) {
+            Ok(file) => file,
+            Err(err) => {
+                error!(
+                    "Failed to open output file '{}': {}",
+                    path.display(), err
+                );
+                return Err(Failed)
+            }
+        };
+        output.write(vrps, metrics, format, &mut file).map_err(|err| {
+            error!("Failed to output result: {}", err);
+            Failed
+        })
+    }
+
+    /// Writes a separate output file for each TAL.
+    ///
+    /// The path of each file is derived from `template` by replacing the
+    /// TAL placeholder with the name of the TAL.
+    fn write_partitioned(
+        template: &Path,
+        output: Output,
+        vrps: Arc<PayloadSnapshot>,
+        metrics: Arc<Metrics>,
+        format: OutputFormat,
+    ) -> Result<(), Failed> {
+        // We have checked that the template is valid Unicode already.
+        let template = template.to_string_lossy();
+        for tal in &metrics.tals {
+            let path = PathBuf::from(
+                template.replace(Self::TAL_PLACEHOLDER, tal.tal.name())
+            );
+            let mut output = output.clone();
+            output.set_tal(tal.tal.name());
+            Self::write_file(
+                &path, output, vrps.clone(), metrics.clone(), format
+            )?;
+        }
+        Ok(())
+    }
 }
 
 
@@ -1064,6 +1512,18 @@ pub struct Update {
     /// Return an error on incomplete update.
     #[arg(short, long)]
     complete: bool,
+
+    /// Only show what updating the known repositories would do
+    #[arg(long, conflicts_with = "complete")]
+    dry_run: bool,
+
+    /// Print the result of a dry run as JSON
+    #[arg(long, requires = "dry_run")]
+    json: bool,
+
+    /// Show what cleaning up would delete instead of updating
+    #[arg(long, requires = "dry_run")]
+    cleanup: bool,
 }
 
 impl Update {
@@ -1089,10 +1549,35 @@ impl Update {
     /// also does validation in order to discover new points.
     ///
     /// Which turns out is just a shortcut for `vrps` with no output.
+    ///
+    /// For a dry run, only the repositories known from earlier runs are
+    /// checked and the expected changes are printed to stdout. If cleanup
+    /// is requested, the data that cleaning up the collector would delete
+    /// is printed instead.
     fn run(self, process: Process) -> Result<(), ExitError> {
         let mut engine = Engine::new(process.config(), true)?;
         engine.ignite()?;
         process.switch_logging(false, false)?;
+        if self.cleanup {
+            let preview = engine.cleanup_preview()?;
+            if self.json {
+                println!("{}", Self::cleanup_json(&preview));
+            }
+            else {
+                Self::cleanup_table(&preview);
+            }
+            return Ok(())
+        }
+        if self.dry_run {
+            let preview = engine.preview()?;
+            if self.json {
+                println!("{}", Self::json(&preview));
+            }
+            else {
+                Self::table(&preview);
+            }
+            return Ok(())
+        }
         let (_, metrics) = ValidationReport::process(
             &engine, process.config(),
         )?;
@@ -1103,6 +1588,185 @@ impl Update {
            Ok(())
         }
     }
+
+    /// Returns the details of a previewed change for the table.
+    fn details(change: PreviewChange) -> String {
+        match change {
+            PreviewChange::Deltas { count, serial } => {
+                format!("{} deltas to serial {}", count, serial)
+            }
+            PreviewChange::Snapshot { reason, serial } => {
+                format!("serial {}, {}", serial, reason.code())
+            }
+            PreviewChange::Files { count, size } => {
+                format!("{} files, {} bytes", count, size)
+            }
+            PreviewChange::Unchanged | PreviewChange::Failed => "".into(),
+        }
+    }
+
+    /// Prints the result of a dry run as a table.
+    fn table(preview: &[UpdatePreview]) {
+        let rows: Vec<_> = preview.iter().map(|item| {
+            [
+                item.uri.clone(),
+                item.change.code().into(),
+                Self::details(item.change),
+            ]
+        }).collect();
+        Self::print_table(["uri", "change", "details"], &rows);
+    }
+
+    /// Prints the result of a cleanup dry run as a table.
+    fn cleanup_table(preview: &[CleanupPreview]) {
+        let rows: Vec<_> = preview.iter().map(|item| {
+            [
+                item.uri.clone(),
+                if item.rrdp { "rrdp" } else { "rsync" }.into(),
+                item.size.to_string(),
+                item.reason.code().into(),
+            ]
+        }).collect();
+        Self::print_table(["uri", "type", "size", "reason"], &rows);
+    }
+
+    /// Prints a table with aligned columns.
+    fn print_table<const N: usize>(header: [&str; N], rows: &[[String; N]]) {
+        let mut widths = header.map(str::len);
+        for row in rows {
+            for (width, value) in widths.iter_mut().zip(row) {
+                *width = cmp::max(*width, value.len())
+            }
+        }
+        let print_row = |row: &mut dyn Iterator<Item = &str>| {
+            let line = row.zip(widths).map(|(value, width)| {
+                format!("{:<width$}", value, width = width)
+            }).collect::<Vec<_>>().join("  ");
+            println!("{}", line.trim_end());
+        };
+        print_row(&mut header.into_iter());
+        for row in rows {
+            print_row(&mut row.iter().map(String::as_str));
+        }
+    }
+
+    /// Returns the result of a dry run as JSON.
+    fn json(preview: &[UpdatePreview]) -> String {
+        JsonBuilder::build(|json| {
+            json.member_array("repositories", |json| {
+                for item in preview {
+                    json.array_object(|json| {
+                        json.member_str("uri", &item.uri);
+                        json.member_str("change", item.change.code());
+                        match item.change {
+                            PreviewChange::Deltas { count, serial } => {
+                                json.member_raw("deltas", count);
+                                json.member_raw("serial", serial);
+                            }
+                            PreviewChange::Snapshot { reason, serial } => {
+                                json.member_str("reason", reason.code());
+                                json.member_raw("serial", serial);
+                            }
+                            PreviewChange::Files { count, size } => {
+                                json.member_raw("files", count);
+                                json.member_raw("size", size);
+                            }
+                            PreviewChange::Unchanged
+                            | PreviewChange::Failed => { }
+                        }
+                    })
+                }
+            })
+        })
+    }
+
+    /// Returns the result of a cleanup dry run as JSON.
+    fn cleanup_json(preview: &[CleanupPreview]) -> String {
+        JsonBuilder::build(|json| {
+            json.member_array("removed", |json| {
+                for item in preview {
+                    json.array_object(|json| {
+                        json.member_str("uri", &item.uri);
+                        json.member_str(
+                            "type", if item.rrdp { "rrdp" } else { "rsync" }
+                        );
+                        json.member_raw("size", item.size);
+                        json.member_str("reason", item.reason.code());
+                    })
+                }
+            })
+        })
+    }
+}
+
+
+//------------ Refresh -------------------------------------------------------
+
+/// Update a single repository.
+///
+/// This does a validation run that only fetches new data for the given
+/// repository and uses the stored data for all others.
+#[derive(Clone, Debug, Parser)]
+pub struct Refresh {
+    /// The rpkiNotify URI or an rsync URI of the repository
+    #[arg(long, value_name = "URI")]
+    repository: RefreshTarget,
+}
+
+impl Refresh {
+    /// Adds the command configuration to a clap app.
+    pub fn config_args<'a: 'b, 'b>(app: clap::Command) -> clap::Command {
+        app.subcommand(
+            Refresh::augment_args(
+                clap::Command::new("refresh")
+                    .about("Updates a single repository")
+                    .after_help(AFTER_HELP)
+            )
+        )
+    }
+
+    /// Creates a command from clap matches.
+    pub fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Failed> {
+        Ok(<Refresh as FromArgMatches>::from_arg_matches(matches).unwrap())
+    }
+
+    /// Refreshes the repository.
+    fn run(self, process: Process) -> Result<(), ExitError> {
+        let mut engine = Engine::new(process.config(), true)?;
+        engine.ignite()?;
+        process.switch_logging(false, false)?;
+        let (_, metrics) = ValidationReport::process_refresh(
+            &engine, process.config(), &self.repository
+        )?;
+        Self::check(&self.repository, &metrics)?;
+        Ok(())
+    }
+
+    /// Checks the metrics of a refresh run whether the update succeeded.
+    ///
+    /// Logs the outcome.
+    fn check(target: &RefreshTarget, metrics: &Metrics) -> Result<(), Failed> {
+        let mut used = metrics.rrdp.iter().map(|item| {
+            item.notify_uri.as_str().to_string()
+        }).chain(metrics.rsync.iter().map(|item| {
+            item.module.canonical_module().into_owned()
+        })).peekable();
+        if used.peek().is_none() {
+            error!("Repository {} is not used by any CA.", target);
+            return Err(Failed)
+        }
+        let failed = used.filter(|uri| {
+            metrics.failing_repositories.iter().any(|item| item.uri == *uri)
+        }).collect::<Vec<_>>();
+        if !failed.is_empty() {
+            error!(
+                "Refreshing {} failed for {}.", target, failed.join(", ")
+            );
+            return Err(Failed)
+        }
+        info!("Refreshed {}.", target);
+        Ok(())
+    }
 }
 
 
@@ -1184,6 +1848,864 @@ impl Dump {
 }
 
 
+//------------ Import --------------------------------------------------------
+
+/// Imports the cache of another relying party software.
+#[derive(Clone, Debug, Parser)]
+pub struct Import {
+    /// The relying party software the cache is from (fort, rpki-client)
+    #[arg(long, value_name = "SOFTWARE")]
+    from: ImportSource,
+
+    /// Path to the cache directory
+    #[arg(value_name = "PATH")]
+    dir: PathBuf,
+}
+
+impl Import {
+    /// Adds the command configuration to a clap app.
+    pub fn config_args<'a: 'b, 'b>(app: clap::Command) -> clap::Command {
+        app.subcommand(
+            Import::augment_args(
+                clap::Command::new("import")
+                    .about("Imports the cache of another validator")
+                    .after_help(AFTER_HELP)
+            )
+        )
+    }
+
+    /// Creates a command from clap matches.
+    pub fn from_arg_matches(
+        matches: &ArgMatches,
+        cur_dir: &Path,
+    ) -> Result<Self, Failed> {
+        let mut res =
+            <Import as FromArgMatches>::from_arg_matches(matches).unwrap();
+        res.dir = cur_dir.join(res.dir);
+        Ok(res)
+    }
+
+    /// Imports the repository data into the rsync collector.
+    fn run(self, process: Process) -> Result<(), ExitError> {
+        let engine = Engine::new(process.config(), true)?;
+        process.switch_logging(false, false)?;
+        let count = engine.import_rsync(&self.from.rsync_dir(&self.dir))?;
+        info!("Imported {} rsync modules from {}.", count, self.from);
+        Ok(())
+    }
+}
+
+
+//------------ ImportSource --------------------------------------------------
+
+/// The relying party software whose cache is imported.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ImportSource {
+    /// FORT Validator.
+    ///
+    /// Its local repository keeps rsync data in the `rsync` sub-directory
+    /// using the rsync URI without the scheme as the path.
+    Fort,
+
+    /// rpki-client.
+    ///
+    /// Its cache directory keeps the repository data of both rsync and
+    /// RRDP using the rsync URI without the scheme as the path. Its own
+    /// state lives in entries starting with a dot.
+    RpkiClient,
+}
+
+impl ImportSource {
+    /// Returns the directory containing the data keyed by rsync URI.
+    fn rsync_dir(self, dir: &Path) -> PathBuf {
+        match self {
+            ImportSource::Fort => dir.join("rsync"),
+            ImportSource::RpkiClient => dir.into(),
+        }
+    }
+
+    /// Returns the name of the software.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ImportSource::Fort => "fort",
+            ImportSource::RpkiClient => "rpki-client",
+        }
+    }
+}
+
+impl FromStr for ImportSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fort" => Ok(ImportSource::Fort),
+            "rpki-client" => Ok(ImportSource::RpkiClient),
+            _ => Err(format!("unknown validator '{}'", s))
+        }
+    }
+}
+
+impl fmt::Display for ImportSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+
+//------------ WhereIs -------------------------------------------------------
+
+/// Shows where in the store an object is kept.
+#[derive(Clone, Debug, Parser)]
+pub struct WhereIs {
+    /// The rsync URI of the object
+    #[arg(value_name = "URI")]
+    uri: uri::Rsync,
+}
+
+impl WhereIs {
+    /// Adds the command configuration to a clap app.
+    pub fn config_args<'a: 'b, 'b>(app: clap::Command) -> clap::Command {
+        app.subcommand(
+            WhereIs::augment_args(
+                clap::Command::new("whereis")
+                    .about("Shows the publication point an object is \
+                            stored in")
+                    .after_help(AFTER_HELP)
+            )
+        )
+    }
+
+    /// Creates a command from clap matches.
+    pub fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Failed> {
+        Ok(<WhereIs as FromArgMatches>::from_arg_matches(matches).unwrap())
+    }
+
+    /// Looks up the object and prints its location to stdout.
+    fn run(self, process: Process) -> Result<(), ExitError> {
+        let store = Store::new(process.config())?;
+        process.switch_logging(false, false)?;
+        let location = match store.whereis(&self.uri)? {
+            Some(location) => location,
+            None => {
+                error!("{} is not in the store.", self.uri);
+                return Err(ExitError::Generic)
+            }
+        };
+        println!("Object: {}", self.uri);
+        println!("Manifest: {}", location.manifest_uri());
+        println!("CA repository: {}", location.ca_repository());
+        match location.rpki_notify() {
+            Some(uri) => println!("RRDP notification: {}", uri),
+            None => println!("RRDP notification: none"),
+        }
+        println!("Stored in: {}", location.path().display());
+        Ok(())
+    }
+}
+
+
+//------------ Pin -----------------------------------------------------------
+
+/// Pins publication points in the store.
+#[derive(Clone, Debug, Parser)]
+pub struct Pin {
+    /// Remove the pin instead of adding it
+    #[arg(long, requires = "uri")]
+    remove: bool,
+
+    /// The manifest URI of the publication point [default: list all]
+    #[arg(value_name = "URI")]
+    uri: Option<uri::Rsync>,
+}
+
+impl Pin {
+    /// Adds the command configuration to a clap app.
+    pub fn config_args<'a: 'b, 'b>(app: clap::Command) -> clap::Command {
+        app.subcommand(
+            Pin::augment_args(
+                clap::Command::new("pin")
+                    .about("Keeps publication points in the store even \
+                            when expired")
+                    .after_help(AFTER_HELP)
+            )
+        )
+    }
+
+    /// Creates a command from clap matches.
+    pub fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Failed> {
+        Ok(<Pin as FromArgMatches>::from_arg_matches(matches).unwrap())
+    }
+
+    /// Adds or removes a pin or lists all pinned points.
+    fn run(self, process: Process) -> Result<(), ExitError> {
+        let store = Store::new(process.config())?;
+        process.switch_logging(false, false)?;
+        let uri = match self.uri {
+            Some(uri) => uri,
+            None => {
+                for uri in store.pinned()? {
+                    println!("{}: {}", uri, store.pin_status(&uri)?);
+                }
+                return Ok(())
+            }
+        };
+        if self.remove {
+            if !store.unpin(&uri)? {
+                warn!("{} was not pinned.", uri);
+            }
+        }
+        else if !store.pin(&uri)? {
+            warn!("{} is already pinned.", uri);
+        }
+        Ok(())
+    }
+}
+
+
+//------------ RrdpState -----------------------------------------------------
+
+/// Shows the state of the locally known RRDP repositories.
+#[derive(Clone, Debug, Parser)]
+pub struct RrdpState {
+    /// Print the state as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+impl RrdpState {
+    /// Adds the command configuration to a clap app.
+    pub fn config_args<'a: 'b, 'b>(app: clap::Command) -> clap::Command {
+        app.subcommand(
+            RrdpState::augment_args(
+                clap::Command::new("rrdp-state")
+                    .about("Shows the state of the local RRDP repositories")
+                    .after_help(AFTER_HELP)
+            )
+        )
+    }
+
+    /// Creates a command from clap matches.
+    pub fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Failed> {
+        Ok(<RrdpState as FromArgMatches>::from_arg_matches(matches).unwrap())
+    }
+
+    /// Prints the state of all repositories to stdout.
+    fn run(self, process: Process) -> Result<(), ExitError> {
+        let engine = Engine::new(process.config(), true)?;
+        process.switch_logging(false, false)?;
+        let info = engine.rrdp_info()?;
+        if self.json {
+            println!("{}", Self::json(&info));
+        }
+        else {
+            Self::table(&info);
+        }
+        Ok(())
+    }
+
+    /// Formats an optional time for output.
+    fn time(time: Option<DateTime<Utc>>) -> Option<String> {
+        time.map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true))
+    }
+
+    /// Prints the state as a table.
+    fn table(info: &[RrdpRepositoryInfo]) {
+        let rows: Vec<_> = info.iter().map(|item| {
+            [
+                item.rpki_notify.to_string(),
+                item.session.to_string(),
+                item.serial.to_string(),
+                Self::time(item.updated).unwrap_or_else(|| "-".into()),
+                Self::time(item.failed).unwrap_or_else(|| "-".into()),
+                if item.fallback { "yes" } else { "no" }.into(),
+                item.size.to_string(),
+            ]
+        }).collect();
+        let header = [
+            "rpkiNotify", "session", "serial", "last success",
+            "last failure", "fallback", "size",
+        ];
+        let mut widths = header.map(str::len);
+        for row in &rows {
+            for (width, value) in widths.iter_mut().zip(row) {
+                *width = cmp::max(*width, value.len())
+            }
+        }
+        let print_row = |row: &mut dyn Iterator<Item = &str>| {
+            let line = row.zip(widths).map(|(value, width)| {
+                format!("{:<width$}", value, width = width)
+            }).collect::<Vec<_>>().join("  ");
+            println!("{}", line.trim_end());
+        };
+        print_row(&mut header.into_iter());
+        for row in &rows {
+            print_row(&mut row.iter().map(String::as_str));
+        }
+    }
+
+    /// Returns the state as JSON.
+    fn json(info: &[RrdpRepositoryInfo]) -> String {
+        JsonBuilder::build(|json| {
+            json.member_array("repositories", |json| {
+                for item in info {
+                    json.array_object(|json| {
+                        json.member_str("rpkiNotify", &item.rpki_notify);
+                        json.member_str("session", item.session);
+                        json.member_raw("serial", item.serial);
+                        for (key, value) in [
+                            ("lastSuccess", item.updated),
+                            ("lastFailure", item.failed),
+                            ("bestBefore", item.best_before),
+                        ] {
+                            match Self::time(value) {
+                                Some(value) => json.member_str(key, value),
+                                None => json.member_raw(key, "null"),
+                            }
+                        }
+                        json.member_raw("fallback", item.fallback);
+                        json.member_raw("size", item.size);
+                    })
+                }
+            })
+        })
+    }
+}
+
+
+//------------ RepoHealth ----------------------------------------------------
+
+/// Shows the repositories that failed to update.
+#[derive(Clone, Debug, Parser)]
+pub struct RepoHealth {
+    /// Only show repositories that failed at least this many times in a row
+    #[arg(long, value_name = "COUNT", default_value_t = 1)]
+    runs: u32,
+
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+impl RepoHealth {
+    /// Adds the command configuration to a clap app.
+    pub fn config_args<'a: 'b, 'b>(app: clap::Command) -> clap::Command {
+        app.subcommand(
+            RepoHealth::augment_args(
+                clap::Command::new("repo-health")
+                    .about("Shows the repositories that failed to update")
+                    .after_help(AFTER_HELP)
+            )
+        )
+    }
+
+    /// Creates a command from clap matches.
+    pub fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Failed> {
+        Ok(<RepoHealth as FromArgMatches>::from_arg_matches(matches).unwrap())
+    }
+
+    /// Prints the failing repositories to stdout.
+    fn run(self, process: Process) -> Result<(), ExitError> {
+        process.switch_logging(false, false)?;
+        let mut repositories = Backoff::new(process.config()).failing();
+        repositories.retain(|item| item.failures >= self.runs);
+        if self.json {
+            println!("{}", Self::json(&repositories));
+        }
+        else {
+            Self::table(&repositories);
+        }
+        Ok(())
+    }
+
+    /// Formats a time for output.
+    fn time(time: DateTime<Utc>) -> String {
+        time.to_rfc3339_opts(SecondsFormat::Secs, true)
+    }
+
+    /// Prints the repositories as a table.
+    fn table(repositories: &[FailingRepository]) {
+        let rows: Vec<_> = repositories.iter().map(|item| {
+            [
+                item.uri.clone(),
+                item.failures.to_string(),
+                Self::time(item.since),
+                item.reason.code().into(),
+                if item.dead { "yes" } else { "no" }.into(),
+            ]
+        }).collect();
+        let header = ["uri", "failures", "failing since", "reason", "dead"];
+        let mut widths = header.map(str::len);
+        for row in &rows {
+            for (width, value) in widths.iter_mut().zip(row) {
+                *width = cmp::max(*width, value.len())
+            }
+        }
+        let print_row = |row: &mut dyn Iterator<Item = &str>| {
+            let line = row.zip(widths).map(|(value, width)| {
+                format!("{:<width$}", value, width = width)
+            }).collect::<Vec<_>>().join("  ");
+            println!("{}", line.trim_end());
+        };
+        print_row(&mut header.into_iter());
+        for row in &rows {
+            print_row(&mut row.iter().map(String::as_str));
+        }
+    }
+
+    /// Returns the repositories as JSON.
+    fn json(repositories: &[FailingRepository]) -> String {
+        JsonBuilder::build(|json| {
+            json.member_array("repositories", |json| {
+                for item in repositories {
+                    json.array_object(|json| {
+                        json.member_str("uri", &item.uri);
+                        json.member_str("failingSince", Self::time(item.since));
+                        json.member_raw("failures", item.failures);
+                        json.member_str("reason", item.reason.code());
+                        json.member_raw("dead", item.dead);
+                    })
+                }
+            })
+        })
+    }
+}
+
+
+//------------ Gbr -----------------------------------------------------------
+
+/// Lists the contact information from Ghostbuster Records.
+#[derive(Clone, Debug, Parser)]
+pub struct Gbr {
+    /// Only list records published by the CA with this certificate URI
+    #[arg(long, value_name = "URI")]
+    ca: Option<uri::Rsync>,
+
+    /// Only list records published under this TAL
+    #[arg(long, value_name = "NAME")]
+    tal: Option<String>,
+
+    /// Print the records as JSON
+    #[arg(long)]
+    json: bool,
+
+    /// Don't update the local cache
+    #[arg(short, long)]
+    noupdate: bool,
+}
+
+impl Gbr {
+    /// Adds the command configuration to a clap app.
+    pub fn config_args<'a: 'b, 'b>(app: clap::Command) -> clap::Command {
+        app.subcommand(
+            Gbr::augment_args(
+                clap::Command::new("gbr")
+                    .about("Lists the contacts from Ghostbuster Records")
+                    .after_help(AFTER_HELP)
+            )
+        )
+    }
+
+    /// Creates a command from clap matches.
+    pub fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Failed> {
+        Ok(<Gbr as FromArgMatches>::from_arg_matches(matches).unwrap())
+    }
+
+    /// Validates the repository and prints the records to stdout.
+    fn run(self, process: Process) -> Result<(), ExitError> {
+        let mut engine = Engine::new(process.config(), !self.noupdate)?;
+        engine.ignite()?;
+        process.switch_logging(false, false)?;
+        let (report, mut metrics) = ValidationReport::process(
+            &engine, process.config(),
+        )?;
+        let snapshot = report.into_snapshot(
+            &LocalExceptions::load(process.config(), false)?,
+            &mut metrics,
+        );
+        let gbrs: Vec<_> = snapshot.gbrs().iter().filter(|item| {
+            self.ca.as_ref().map(|ca| item.ca.as_ref() == Some(ca))
+                .unwrap_or(true)
+            && self.tal.as_ref().map(|tal| item.tal == *tal)
+                .unwrap_or(true)
+        }).collect();
+        if self.json {
+            println!("{}", Self::json(&gbrs));
+        }
+        else {
+            Self::text(&gbrs);
+        }
+        Ok(())
+    }
+
+    /// Prints the records as text.
+    fn text(gbrs: &[&GhostbusterRecord]) {
+        for (index, item) in gbrs.iter().enumerate() {
+            if index > 0 {
+                println!();
+            }
+            println!("GBR:     {}", item.uri);
+            if let Some(ca) = item.ca.as_ref() {
+                println!("CA:      {}", ca);
+            }
+            println!("TAL:     {}", item.tal);
+            if let Some(name) = item.vcard.full_name.as_ref() {
+                println!("Name:    {}", name);
+            }
+            if let Some(org) = item.vcard.org.as_ref() {
+                println!("Org:     {}", org);
+            }
+            for address in &item.vcard.addresses {
+                println!("Address: {}", address);
+            }
+            for phone in &item.vcard.phones {
+                println!("Phone:   {}", phone);
+            }
+            for email in &item.vcard.emails {
+                println!("Email:   {}", email);
+            }
+        }
+    }
+
+    /// Returns the records as JSON.
+    fn json(gbrs: &[&GhostbusterRecord]) -> String {
+        JsonBuilder::build(|json| {
+            json.member_array("gbrs", |json| {
+                for item in gbrs {
+                    json.array_object(|json| item.json(json))
+                }
+            })
+        })
+    }
+}
+
+
+//------------ Crosscheck ----------------------------------------------------
+
+/// Compares the collector’s local copy with the store.
+#[derive(Clone, Debug, Parser)]
+pub struct Crosscheck {
+    /// Print the differences as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+impl Crosscheck {
+    /// Adds the command configuration to a clap app.
+    pub fn config_args<'a: 'b, 'b>(app: clap::Command) -> clap::Command {
+        app.subcommand(
+            Crosscheck::augment_args(
+                clap::Command::new("crosscheck")
+                    .about("Shows differences between collected and stored \
+                            data")
+                    .after_help(AFTER_HELP)
+            )
+        )
+    }
+
+    /// Creates a command from clap matches.
+    pub fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Failed> {
+        Ok(<Crosscheck as FromArgMatches>::from_arg_matches(matches).unwrap())
+    }
+
+    /// Prints the differences to stdout.
+    fn run(self, process: Process) -> Result<(), ExitError> {
+        let engine = Engine::new(process.config(), true)?;
+        process.switch_logging(false, false)?;
+        let divergence = engine.crosscheck()?;
+        if self.json {
+            println!("{}", Self::json(&divergence));
+        }
+        else {
+            Self::table(&divergence);
+        }
+        Ok(())
+    }
+
+    /// Prints the differences as a table.
+    fn table(divergence: &DivergenceMetrics) {
+        let rows: Vec<_> = divergence.objects.iter().map(|item| {
+            [
+                item.uri.to_string(),
+                item.kind.code().into(),
+                item.manifest_uri.to_string(),
+            ]
+        }).collect();
+        let header = ["uri", "difference", "manifest"];
+        let mut widths = header.map(str::len);
+        for row in &rows {
+            for (width, value) in widths.iter_mut().zip(row) {
+                *width = cmp::max(*width, value.len())
+            }
+        }
+        let print_row = |row: &mut dyn Iterator<Item = &str>| {
+            let line = row.zip(widths).map(|(value, width)| {
+                format!("{:<width$}", value, width = width)
+            }).collect::<Vec<_>>().join("  ");
+            println!("{}", line.trim_end());
+        };
+        print_row(&mut header.into_iter());
+        for row in &rows {
+            print_row(&mut row.iter().map(String::as_str));
+        }
+        println!(
+            "\n{} of {} publication points differ.",
+            divergence.divergent_points(), divergence.points
+        );
+    }
+
+    /// Returns the differences as JSON.
+    fn json(divergence: &DivergenceMetrics) -> String {
+        JsonBuilder::build(|json| {
+            json.member_raw("points", divergence.points);
+            json.member_raw("divergentPoints", divergence.divergent_points());
+            json.member_array("objects", |json| {
+                for item in &divergence.objects {
+                    json.array_object(|json| {
+                        json.member_str("uri", &item.uri);
+                        json.member_str("manifest", &item.manifest_uri);
+                        json.member_str("difference", item.kind.code());
+                        for (key, value) in [
+                            ("collectorHash", item.collector_hash.as_ref()),
+                            ("storeHash", item.store_hash.as_ref()),
+                        ] {
+                            match value {
+                                Some(value) => json.member_str(key, value),
+                                None => json.member_raw(key, "null"),
+                            }
+                        }
+                    })
+                }
+            })
+        })
+    }
+}
+
+
+//------------ Decode --------------------------------------------------------
+
+/// Prints the content of an RPKI object as JSON.
+#[derive(Clone, Debug, Parser)]
+pub struct Decode {
+    /// The type of the object [default: from file extension]
+    #[arg(short = 't', long = "type", value_name = "TYPE")]
+    object_type: Option<ObjectType>,
+
+    /// Path to the object
+    #[arg(value_name = "PATH")]
+    path: PathBuf,
+}
+
+impl Decode {
+    /// Adds the command configuration to a clap app.
+    pub fn config_args<'a: 'b, 'b>(app: clap::Command) -> clap::Command {
+        app.subcommand(
+            Decode::augment_args(
+                clap::Command::new("decode")
+                    .about("Prints the content of an RPKI object as JSON")
+                    .after_help(AFTER_HELP)
+            )
+        )
+    }
+
+    /// Creates a command from clap matches.
+    pub fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Failed> {
+        Ok(<Decode as FromArgMatches>::from_arg_matches(matches).unwrap())
+    }
+
+    /// Decodes the object and prints it to stdout.
+    fn run(self, process: Process) -> Result<(), ExitError> {
+        process.switch_logging(false, false)?;
+        let data = match fs::read(&self.path) {
+            Ok(data) => Bytes::from(data),
+            Err(err) => {
+                error!(
+                    "Failed to read object '{}': {}",
+                    self.path.display(), err
+                );
+                return Err(ExitError::Generic)
+            }
+        };
+        let object_type = self.object_type.or_else(|| {
+            ObjectType::from_name(&self.path.to_string_lossy())
+        });
+        match render_json(data, object_type, process.config().strict) {
+            Ok(json) => {
+                println!("{}", json);
+                Ok(())
+            }
+            Err(err) => {
+                error!(
+                    "Failed to decode object '{}': {}",
+                    self.path.display(), err
+                );
+                Err(ExitError::Invalid)
+            }
+        }
+    }
+}
+
+
+//------------ SelfTest ------------------------------------------------------
+
+/// Validates the bundled fixture repository.
+#[derive(Clone, Debug, Parser)]
+pub struct SelfTest;
+
+impl SelfTest {
+    /// Adds the command configuration to a clap app.
+    pub fn config_args<'a: 'b, 'b>(app: clap::Command) -> clap::Command {
+        app.subcommand(
+            SelfTest::augment_args(
+                clap::Command::new("self-test")
+                    .about("Validates a bundled test repository and exits")
+                    .after_help(AFTER_HELP)
+            )
+        )
+    }
+
+    /// Creates a command from clap matches.
+    pub fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Failed> {
+        Ok(<SelfTest as FromArgMatches>::from_arg_matches(matches).unwrap())
+    }
+
+    /// Runs the self-test.
+    ///
+    /// Runs the full validation pipeline against the fixture repository
+    /// bundled with Routinator and compares the result with the expected
+    /// VRPs. Neither the configured cache nor the network are touched.
+    fn run(self, process: Process) -> Result<(), ExitError> {
+        let test = selftest::SelfTest::new(process.config())?;
+        process.switch_logging(false, false)?;
+        match test.run() {
+            Ok(()) => {
+                println!("Self-test passed.");
+                Ok(())
+            }
+            Err(_) => {
+                println!("Self-test failed.");
+                Err(ExitError::Generic)
+            }
+        }
+    }
+}
+
+
+//------------ ValidateDir ---------------------------------------------------
+
+/// Validates repository data in a local directory.
+#[derive(Clone, Debug, Parser)]
+pub struct ValidateDir {
+    /// The TAL to validate against
+    #[arg(long, value_name = "PATH")]
+    tal: PathBuf,
+
+    /// The destination of the output list
+    #[arg(short, long, value_name = "PATH", default_value = "-")]
+    output: PathBuf,
+
+    /// The format of the output list
+    #[arg(
+        short, long, value_name = "FORMAT",
+        default_value = OutputFormat::DEFAULT_VALUE,
+    )]
+    format: String,
+
+    /// Path to the directory with the repository data
+    #[arg(value_name = "PATH")]
+    dir: PathBuf,
+}
+
+impl ValidateDir {
+    /// Adds the command configuration to a clap app.
+    pub fn config_args<'a: 'b, 'b>(app: clap::Command) -> clap::Command {
+        app.subcommand(
+            ValidateDir::augment_args(
+                clap::Command::new("validate-dir")
+                    .about("Validates repository data in a local directory")
+                    .after_help(AFTER_HELP)
+            )
+        )
+    }
+
+    /// Creates a command from clap matches.
+    pub fn from_arg_matches(
+        matches: &ArgMatches,
+        cur_dir: &Path,
+    ) -> Result<Self, Failed> {
+        let mut res =
+            <ValidateDir as FromArgMatches>::from_arg_matches(matches)
+            .unwrap();
+        res.tal = cur_dir.join(res.tal);
+        res.dir = cur_dir.join(res.dir);
+        if res.output != Path::new("-") {
+            res.output = cur_dir.join(res.output);
+        }
+        Ok(res)
+    }
+
+    /// Validates the directory and outputs the resulting payload.
+    ///
+    /// Every rejected object is logged. If there are any or if the trust
+    /// anchor itself isn’t valid, exits with the status for invalid
+    /// objects after producing the output.
+    fn run(self, process: Process) -> Result<(), ExitError> {
+        let format = match OutputFormat::from_str(&self.format) {
+            Ok(format) => format,
+            Err(_) => {
+                error!("Unknown output format '{}'", self.format);
+                return Err(ExitError::Generic)
+            }
+        };
+        let mut output = Output::new();
+        output.update_from_config(process.config());
+        let validation = offline::OfflineValidation::new(
+            process.config(), &self.dir, &self.tal
+        )?;
+        process.switch_logging(false, false)?;
+        let (snapshot, metrics) = validation.run()?;
+
+        for item in &metrics.rejected_objects {
+            warn!("Rejected {}: {}.", item.uri, item.reason.code());
+        }
+        let valid = metrics.publication.valid_points > 0;
+        if !valid {
+            error!("No valid trust anchor found for {}.", self.tal.display());
+        }
+        let rejected = metrics.rejected_objects.len();
+
+        let snapshot = Arc::new(snapshot);
+        let metrics = Arc::new(metrics);
+        if self.output == Path::new("-") {
+            let out = io::stdout();
+            let mut out = out.lock();
+            if let Err(err) = output.write(
+                snapshot, metrics, format, &mut out
+            ) {
+                if err.kind() != io::ErrorKind::BrokenPipe {
+                    error!("Failed to output result: {}", err);
+                }
+                return Err(ExitError::Generic)
+            }
+        }
+        else {
+            Vrps::write_file(
+                &self.output, output, snapshot, metrics, format
+            )?;
+        }
+
+        if !valid || rejected > 0 {
+            if rejected > 0 {
+                error!("Validation rejected {} objects.", rejected);
+            }
+            Err(ExitError::Invalid)
+        }
+        else {
+            Ok(())
+        }
+    }
+}
+
+
 //------------ Man -----------------------------------------------------------
 
 /// Show the manual page.
@@ -1312,6 +2834,9 @@ impl Man {
 enum UserSignal {
     ReloadTals,
     RotateLog,
+    Refresh(RefreshTarget),
+    SwitchTal(TalSwitch),
+    Heartbeat(u64, uri::Https),
 }
 
 /// Wait for the next validation run or a user telling us to quit or reload.
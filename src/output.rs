@@ -1,6 +1,16 @@
 //! Output of validated RPKI payload.
-
-use std::{error, fmt, io};
+//!
+//! Besides the built-in output formats, additional formats can be provided
+//! by implementing the [`CustomFormat`] trait and registering it under a
+//! name via [`OutputFormat::register`]. The name can then be used with the
+//! `vrps` command and as a path on the HTTP server just like the names of
+//! the built-in formats.
+//!
+//! In addition, [`OutputFormat::from_template`] creates a format from a
+//! simple text template without having to write any code.
+
+use std::{error, fmt, fs, io};
+use std::path::Path;
 use std::str::FromStr;
 use std::sync::Arc;
 use bytes::Bytes;
@@ -21,6 +31,7 @@ use crate::payload::{
 use crate::metrics::Metrics;
 use crate::utils::date::format_iso_date;
 use crate::utils::json::json_str;
+use crate::utils::sync::RwLock;
 
 
 //------------ OutputFormat --------------------------------------------------
@@ -53,6 +64,12 @@ pub enum OutputFormat {
     /// JSON format with extended information.
     ExtendedJson,
 
+    /// CSV format listing BGPsec router keys only.
+    ///
+    /// Each line contains the ASN, the subject key identifier, the
+    /// router public key, and the trust anchor.
+    RouterKeys,
+
     /// JSON format using the SLURM scheme.
     Slurm,
 
@@ -85,6 +102,9 @@ pub enum OutputFormat {
     ///
     /// Seriously: no output.
     None,
+
+    /// A registered custom format.
+    Custom(&'static dyn CustomFormat),
 }
 
 impl OutputFormat {
@@ -95,6 +115,7 @@ impl OutputFormat {
         ("csvext", OutputFormat::ExtendedCsv),
         ("json", OutputFormat::Json),
         ("jsonext", OutputFormat::ExtendedJson),
+        ("routerkeys", OutputFormat::RouterKeys),
         ("slurm", OutputFormat::Slurm),
         ("openbgpd", OutputFormat::Openbgpd),
         ("bird1", OutputFormat::Bird1),
@@ -108,7 +129,51 @@ impl OutputFormat {
     pub const DEFAULT_VALUE: &'static str = "csv";
 }
 
+/// The registered custom output formats and their names.
+static CUSTOM_FORMATS: RwLock<Vec<(&'static str, OutputFormat)>>
+    = RwLock::new(Vec::new());
+
 impl OutputFormat {
+    /// Registers a custom output format under the given name.
+    ///
+    /// Once registered, the format is available everywhere a built-in
+    /// format can be used. Since the format stays registered for the
+    /// lifetime of the process, both the name and the format need to be
+    /// static. Formats created at run time can be leaked via `Box::leak`.
+    ///
+    /// Fails if there already is a format by that name.
+    pub fn register(
+        name: &'static str,
+        format: &'static dyn CustomFormat,
+    ) -> Result<(), Failed> {
+        let mut custom = CUSTOM_FORMATS.write();
+        if Self::VALUES.iter().chain(custom.iter()).any(|item| {
+            item.0 == name
+        }) {
+            error!("Output format '{}' already exists.", name);
+            return Err(Failed)
+        }
+        custom.push((name, OutputFormat::Custom(format)));
+        Ok(())
+    }
+
+    /// Creates an output format from the template file at `path`.
+    ///
+    /// See [`Template`] for the syntax of the template. As with custom
+    /// formats, the template stays around for the lifetime of the process.
+    pub fn from_template(path: &Path) -> Result<Self, Failed> {
+        Ok(OutputFormat::Custom(Box::leak(Box::new(Template::load(path)?))))
+    }
+
+    /// Returns an iterator over all output formats and their names.
+    ///
+    /// This includes all custom formats registered at the time of calling.
+    pub fn iter() -> impl Iterator<Item = (&'static str, Self)> {
+        Self::VALUES.iter().copied().chain(
+            CUSTOM_FORMATS.read().clone()
+        )
+    }
+
     /// Returns the output format for a given request path.
     pub fn from_path(path: &str) -> Option<Self> {
         if !path.starts_with('/') {
@@ -124,6 +189,11 @@ impl OutputFormat {
                 return Some(res)
             }
         }
+        for &(name, res) in CUSTOM_FORMATS.read().iter() {
+            if name == value {
+                return Some(res)
+            }
+        }
         None
     }
 
@@ -131,11 +201,12 @@ impl OutputFormat {
     pub fn content_type(self) -> ContentType {
         match self {
             OutputFormat::Csv | OutputFormat::CompatCsv |
-            OutputFormat::ExtendedCsv
+            OutputFormat::ExtendedCsv | OutputFormat::RouterKeys
                 => ContentType::CSV,
             OutputFormat::Json | OutputFormat::ExtendedJson |
             OutputFormat::Slurm
                 => ContentType::JSON,
+            OutputFormat::Custom(format) => format.content_type(),
             _ => ContentType::TEXT,
         }
     }
@@ -147,6 +218,7 @@ impl OutputFormat {
             OutputFormat::ExtendedCsv => Box::new(ExtendedCsv),
             OutputFormat::Json => Box::new(Json),
             OutputFormat::ExtendedJson => Box::new(ExtendedJson),
+            OutputFormat::RouterKeys => Box::new(RouterKeys),
             OutputFormat::Slurm => Box::new(Slurm),
             OutputFormat::Openbgpd => Box::new(Openbgpd),
             OutputFormat::Bird1 => Box::new(Bird1),
@@ -154,6 +226,7 @@ impl OutputFormat {
             OutputFormat::Rpsl => Box::new(Rpsl),
             OutputFormat::Summary => Box::new(Summary),
             OutputFormat::None => Box::new(NoOutput),
+            OutputFormat::Custom(format) => Box::new(Custom(format)),
         }
     }
 }
@@ -173,6 +246,153 @@ impl FromStr for OutputFormat {
 }
 
 
+//------------ CustomFormat --------------------------------------------------
+
+/// An output format provided from outside of Routinator.
+///
+/// The methods are called while producing output in the order they are
+/// listed here. For each kind of payload, the `before_` method is called
+/// first, followed by one call of the item method for each item separated
+/// by calls to the delimiter method, and finally the `after_` method. Only
+/// [`origin`][Self::origin] needs to be provided. All other methods do
+/// nothing by default.
+///
+/// Use [`OutputFormat::register`] to make the format available.
+pub trait CustomFormat: Send + Sync {
+    /// Returns the content type of the output for use with HTTP.
+    fn content_type(&self) -> ContentType {
+        ContentType::TEXT
+    }
+
+    /// Writes the start of the output.
+    fn header(
+        &self,
+        snapshot: &PayloadSnapshot,
+        metrics: &Metrics,
+        target: &mut dyn io::Write,
+    ) -> Result<(), io::Error> {
+        let _ = (snapshot, metrics, target);
+        Ok(())
+    }
+
+    /// Writes what comes before the first route origin.
+    fn before_origins(
+        &self, target: &mut dyn io::Write
+    ) -> Result<(), io::Error> {
+        let _ = target;
+        Ok(())
+    }
+
+    /// Writes a single route origin.
+    fn origin(
+        &self,
+        origin: RouteOrigin,
+        info: &PayloadInfo,
+        target: &mut dyn io::Write,
+    ) -> Result<(), io::Error>;
+
+    /// Writes what goes between two route origins.
+    fn origin_delimiter(
+        &self, target: &mut dyn io::Write
+    ) -> Result<(), io::Error> {
+        let _ = target;
+        Ok(())
+    }
+
+    /// Writes what comes after the last route origin.
+    fn after_origins(
+        &self, target: &mut dyn io::Write
+    ) -> Result<(), io::Error> {
+        let _ = target;
+        Ok(())
+    }
+
+    /// Writes what comes before the first router key.
+    fn before_router_keys(
+        &self, target: &mut dyn io::Write
+    ) -> Result<(), io::Error> {
+        let _ = target;
+        Ok(())
+    }
+
+    /// Writes a single router key.
+    fn router_key(
+        &self,
+        key: &RouterKey,
+        info: &PayloadInfo,
+        target: &mut dyn io::Write,
+    ) -> Result<(), io::Error> {
+        let _ = (key, info, target);
+        Ok(())
+    }
+
+    /// Writes what goes between two router keys.
+    fn router_key_delimiter(
+        &self, target: &mut dyn io::Write
+    ) -> Result<(), io::Error> {
+        let _ = target;
+        Ok(())
+    }
+
+    /// Writes what comes after the last router key.
+    fn after_router_keys(
+        &self, target: &mut dyn io::Write
+    ) -> Result<(), io::Error> {
+        let _ = target;
+        Ok(())
+    }
+
+    /// Writes what comes before the first ASPA.
+    fn before_aspas(
+        &self, target: &mut dyn io::Write
+    ) -> Result<(), io::Error> {
+        let _ = target;
+        Ok(())
+    }
+
+    /// Writes a single ASPA.
+    fn aspa(
+        &self,
+        aspa: &Aspa,
+        info: &PayloadInfo,
+        target: &mut dyn io::Write,
+    ) -> Result<(), io::Error> {
+        let _ = (aspa, info, target);
+        Ok(())
+    }
+
+    /// Writes what goes between two ASPAs.
+    fn aspa_delimiter(
+        &self, target: &mut dyn io::Write
+    ) -> Result<(), io::Error> {
+        let _ = target;
+        Ok(())
+    }
+
+    /// Writes what comes after the last ASPA.
+    fn after_aspas(
+        &self, target: &mut dyn io::Write
+    ) -> Result<(), io::Error> {
+        let _ = target;
+        Ok(())
+    }
+
+    /// Writes the end of the output.
+    fn footer(
+        &self, metrics: &Metrics, target: &mut dyn io::Write
+    ) -> Result<(), io::Error> {
+        let _ = (metrics, target);
+        Ok(())
+    }
+}
+
+impl fmt::Debug for dyn CustomFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("CustomFormat")
+    }
+}
+
+
 //------------ Selection -----------------------------------------------------
 
 /// A set of rules defining which payload to include in output.
@@ -308,6 +528,12 @@ pub struct Output {
 
     /// Should we include ASPA data?
     aspas: bool,
+
+    /// Limiting data to that derived from the TAL of the given name.
+    ///
+    /// If this is `None`, data from all TALs and local exceptions is
+    /// included.
+    tal: Option<String>,
 }
 
 impl Output {
@@ -317,7 +543,8 @@ impl Output {
             selection: None,
             route_origins: true,
             router_keys: true,
-            aspas: true
+            aspas: true,
+            tal: None,
         }
     }
 
@@ -413,6 +640,11 @@ impl Output {
         self.aspas = false
     }
 
+    /// Limits the output to data derived from the TAL with the given name.
+    pub fn set_tal(&mut self, name: impl Into<String>) {
+        self.tal = Some(name.into())
+    }
+
     /// Outputs the payload snapshot to the target in the given format.
     pub fn write<W: io::Write>(
         self,
@@ -456,6 +688,17 @@ impl Output {
             None => true
         }
     }
+
+    fn include_info(&self, info: &PayloadInfo) -> bool {
+        match self.tal.as_ref() {
+            Some(tal) => {
+                info.iter().any(|item| {
+                    item.tal_name() == Some(tal.as_str())
+                })
+            }
+            None => true
+        }
+    }
 }
 
 impl Default for Output {
@@ -522,7 +765,10 @@ impl<Target: io::Write> OutputStream<Target> {
                             break
                         }
                     };
-                    if !self.output.include_origin(origin) {
+                    if
+                        !self.output.include_origin(origin)
+                        || !self.output.include_info(info)
+                    {
                         continue
                     }
                     if *first {
@@ -545,7 +791,10 @@ impl<Target: io::Write> OutputStream<Target> {
                             break
                         }
                     };
-                    if !self.output.include_router_key(key) {
+                    if
+                        !self.output.include_router_key(key)
+                        || !self.output.include_info(info)
+                    {
                         continue
                     }
                     if *first {
@@ -568,7 +817,10 @@ impl<Target: io::Write> OutputStream<Target> {
                             break
                         }
                     };
-                    if !self.output.include_aspa(aspa) {
+                    if
+                        !self.output.include_aspa(aspa)
+                        || !self.output.include_info(info)
+                    {
                         continue
                     }
                     if *first {
@@ -873,6 +1125,36 @@ impl<W: io::Write> Formatter<W> for ExtendedCsv {
 }
 
 
+//------------ RouterKeys ----------------------------------------------------
+
+struct RouterKeys;
+
+impl<W: io::Write> Formatter<W> for RouterKeys {
+    fn header(
+        &self, _snapshot: &PayloadSnapshot, _metrics: &Metrics, target: &mut W
+    ) -> Result<(), io::Error> {
+        writeln!(target, "ASN,SKI,Router Public Key,Trust Anchor")
+    }
+
+    fn origin(
+        &self, _origin: RouteOrigin, _info: &PayloadInfo, _target: &mut W
+    ) -> Result<(), io::Error> {
+        Ok(())
+    }
+
+    fn router_key(
+        &self, key: &RouterKey, info: &PayloadInfo, target: &mut W
+    ) -> Result<(), io::Error> {
+        writeln!(target, "{},{},{},{}",
+            key.asn,
+            key.key_identifier,
+            key.key_info,
+            info.tal_name().unwrap_or("N/A"),
+        )
+    }
+}
+
+
 //------------ Json ----------------------------------------------------------
 
 struct Json;
@@ -881,15 +1163,21 @@ impl<W: io::Write> Formatter<W> for Json {
     fn header(
         &self, _snapshot: &PayloadSnapshot, metrics: &Metrics, target: &mut W
     ) -> Result<(), io::Error> {
-        writeln!(target,
+        write!(target,
             "{{\
             \n  \"metadata\": {{\
             \n    \"generated\": {},\
-            \n    \"generatedTime\": \"{}\"\
-            \n  }}",
+            \n    \"generatedTime\": \"{}\"",
             metrics.time.timestamp(),
             format_iso_date(metrics.time)
-        )
+        )?;
+        if let Some(run_id) = metrics.run_id {
+            write!(target, ",\n    \"runId\": \"{}\"", run_id)?;
+        }
+        if let Some(hash) = metrics.input_hash.as_ref() {
+            write!(target, ",\n    \"inputHash\": \"{}\"", hash)?;
+        }
+        writeln!(target, "\n  }}")
     }
 
     fn before_origins(
@@ -1066,11 +1354,17 @@ impl<W: io::Write> Formatter<W> for ExtendedJson {
             "{{\
             \n  \"metadata\": {{\
             \n    \"generated\": {},\
-            \n    \"generatedTime\": \"{}\"\
-            \n  }}",
+            \n    \"generatedTime\": \"{}\"",
             metrics.time.timestamp(),
             format_iso_date(metrics.time)
-        )
+        )?;
+        if let Some(run_id) = metrics.run_id {
+            write!(target, ",\n    \"runId\": \"{}\"", run_id)?;
+        }
+        if let Some(hash) = metrics.input_hash.as_ref() {
+            write!(target, ",\n    \"inputHash\": \"{}\"", hash)?;
+        }
+        write!(target, "\n  }}")
     }
 
     fn before_origins(
@@ -1478,3 +1772,331 @@ impl<W: io::Write> Formatter<W> for NoOutput {
 }
 
 
+//------------ Template ------------------------------------------------------
+
+/// An output format defined by a text template.
+///
+/// The template is text with fields enclosed in double braces that are
+/// replaced with the values of a VRP. The fields `asn` (the AS number in
+/// the form `AS64496`), `asn-number` (only the number), `prefix` (in slash
+/// notation), `maxlen`, and `tal` are available.
+///
+/// The part of the template between `{{#vrps}}` and `{{/vrps}}` is repeated
+/// for each VRP while the text before and after is printed once at the
+/// beginning and end of the output, respectively. Fields can only be used
+/// within this part. A line break directly following either of the two
+/// markers is dropped. If the template does not contain the markers, the
+/// whole template is repeated for each VRP.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Template {
+    /// The text printed before the first VRP.
+    header: String,
+
+    /// The parts printed for each VRP.
+    item: Vec<TemplatePart>,
+
+    /// The text printed after the last VRP.
+    footer: String,
+}
+
+/// A part of the repeated section of a template.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum TemplatePart {
+    /// Literal text.
+    Text(String),
+
+    /// The AS number including the `AS` prefix.
+    Asn,
+
+    /// The AS number as just the number.
+    AsnNumber,
+
+    /// The prefix in slash notation.
+    Prefix,
+
+    /// The max length.
+    MaxLen,
+
+    /// The name of the TAL.
+    Tal,
+}
+
+impl Template {
+    /// The marker starting the repeated section.
+    const START: &'static str = "{{#vrps}}";
+
+    /// The marker ending the repeated section.
+    const END: &'static str = "{{/vrps}}";
+
+    /// Loads a template from the file at `path`.
+    pub fn load(path: &Path) -> Result<Self, Failed> {
+        let data = fs::read_to_string(path).map_err(|err| {
+            error!(
+                "Failed to read template file {}: {}", path.display(), err
+            );
+            Failed
+        })?;
+        Self::parse(&data).map_err(|err| {
+            error!("Invalid template file {}: {}", path.display(), err);
+            Failed
+        })
+    }
+
+    /// Parses a template from a string.
+    fn parse(data: &str) -> Result<Self, String> {
+        let (header, item, footer) = match data.split_once(Self::START) {
+            Some((header, rest)) => {
+                let (item, footer) = match rest.split_once(Self::END) {
+                    Some(some) => some,
+                    None => {
+                        return Err(format!("missing {}", Self::END))
+                    }
+                };
+                (
+                    header,
+                    Self::strip_line_break(item),
+                    Self::strip_line_break(footer)
+                )
+            }
+            None => {
+                if data.contains(Self::END) {
+                    return Err(format!("missing {}", Self::START))
+                }
+                ("", data, "")
+            }
+        };
+        if header.contains("{{") || footer.contains("{{") {
+            return Err(format!(
+                "fields are only allowed between {} and {}",
+                Self::START, Self::END
+            ))
+        }
+        Ok(Template {
+            header: header.into(),
+            item: Self::parse_item(item)?,
+            footer: footer.into(),
+        })
+    }
+
+    /// Removes a line break at the start of `s`.
+    fn strip_line_break(s: &str) -> &str {
+        s.strip_prefix("\r\n").or_else(|| {
+            s.strip_prefix('\n')
+        }).unwrap_or(s)
+    }
+
+    /// Parses the repeated section of a template.
+    fn parse_item(mut data: &str) -> Result<Vec<TemplatePart>, String> {
+        let mut res = Vec::new();
+        while let Some((text, rest)) = data.split_once("{{") {
+            if !text.is_empty() {
+                res.push(TemplatePart::Text(text.into()));
+            }
+            let (name, rest) = match rest.split_once("}}") {
+                Some(some) => some,
+                None => return Err("unterminated field".into())
+            };
+            res.push(match name.trim() {
+                "asn" => TemplatePart::Asn,
+                "asn-number" => TemplatePart::AsnNumber,
+                "prefix" => TemplatePart::Prefix,
+                "maxlen" => TemplatePart::MaxLen,
+                "tal" => TemplatePart::Tal,
+                name => return Err(format!("unknown field '{}'", name)),
+            });
+            data = rest;
+        }
+        if !data.is_empty() {
+            res.push(TemplatePart::Text(data.into()));
+        }
+        Ok(res)
+    }
+}
+
+impl CustomFormat for Template {
+    fn header(
+        &self,
+        _snapshot: &PayloadSnapshot,
+        _metrics: &Metrics,
+        target: &mut dyn io::Write,
+    ) -> Result<(), io::Error> {
+        target.write_all(self.header.as_bytes())
+    }
+
+    fn origin(
+        &self,
+        origin: RouteOrigin,
+        info: &PayloadInfo,
+        target: &mut dyn io::Write,
+    ) -> Result<(), io::Error> {
+        for part in &self.item {
+            match part {
+                TemplatePart::Text(text) => {
+                    target.write_all(text.as_bytes())?
+                }
+                TemplatePart::Asn => write!(target, "{}", origin.asn)?,
+                TemplatePart::AsnNumber => {
+                    write!(target, "{}", origin.asn.into_u32())?
+                }
+                TemplatePart::Prefix => {
+                    write!(target, "{}/{}",
+                        origin.prefix.addr(), origin.prefix.prefix_len()
+                    )?
+                }
+                TemplatePart::MaxLen => {
+                    write!(target, "{}", origin.prefix.resolved_max_len())?
+                }
+                TemplatePart::Tal => {
+                    target.write_all(
+                        info.tal_name().unwrap_or("N/A").as_bytes()
+                    )?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn footer(
+        &self, _metrics: &Metrics, target: &mut dyn io::Write
+    ) -> Result<(), io::Error> {
+        target.write_all(self.footer.as_bytes())
+    }
+}
+
+
+//------------ Custom --------------------------------------------------------
+
+/// A formatter wrapping a registered custom format.
+struct Custom(&'static dyn CustomFormat);
+
+impl<W: io::Write> Formatter<W> for Custom {
+    fn header(
+        &self, snapshot: &PayloadSnapshot, metrics: &Metrics, target: &mut W
+    ) -> Result<(), io::Error> {
+        self.0.header(snapshot, metrics, target)
+    }
+
+    fn before_origins(&self, target: &mut W) -> Result<(), io::Error> {
+        self.0.before_origins(target)
+    }
+
+    fn origin(
+        &self, origin: RouteOrigin, info: &PayloadInfo, target: &mut W
+    ) -> Result<(), io::Error> {
+        self.0.origin(origin, info, target)
+    }
+
+    fn origin_delimiter(&self, target: &mut W) -> Result<(), io::Error> {
+        self.0.origin_delimiter(target)
+    }
+
+    fn after_origins(&self, target: &mut W) -> Result<(), io::Error> {
+        self.0.after_origins(target)
+    }
+
+    fn before_router_keys(&self, target: &mut W) -> Result<(), io::Error> {
+        self.0.before_router_keys(target)
+    }
+
+    fn router_key(
+        &self, key: &RouterKey, info: &PayloadInfo, target: &mut W
+    ) -> Result<(), io::Error> {
+        self.0.router_key(key, info, target)
+    }
+
+    fn router_key_delimiter(&self, target: &mut W) -> Result<(), io::Error> {
+        self.0.router_key_delimiter(target)
+    }
+
+    fn after_router_keys(&self, target: &mut W) -> Result<(), io::Error> {
+        self.0.after_router_keys(target)
+    }
+
+    fn before_aspas(&self, target: &mut W) -> Result<(), io::Error> {
+        self.0.before_aspas(target)
+    }
+
+    fn aspa(
+        &self, aspa: &Aspa, info: &PayloadInfo, target: &mut W
+    ) -> Result<(), io::Error> {
+        self.0.aspa(aspa, info, target)
+    }
+
+    fn aspa_delimiter(&self, target: &mut W) -> Result<(), io::Error> {
+        self.0.aspa_delimiter(target)
+    }
+
+    fn after_aspas(&self, target: &mut W) -> Result<(), io::Error> {
+        self.0.after_aspas(target)
+    }
+
+    fn footer(
+        &self, metrics: &Metrics, target: &mut W
+    ) -> Result<(), io::Error> {
+        self.0.footer(metrics, target)
+    }
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_template() {
+        use self::TemplatePart::*;
+
+        assert_eq!(
+            Template::parse("{{prefix}} {{ maxlen }} {{asn-number}}\n"),
+            Ok(Template {
+                header: String::new(),
+                item: vec![
+                    Prefix, Text(" ".into()), MaxLen, Text(" ".into()),
+                    AsnNumber, Text("\n".into()),
+                ],
+                footer: String::new(),
+            })
+        );
+        assert_eq!(
+            Template::parse(
+                "roa-set {\n{{#vrps}}\n  {{asn}} {{tal}}\n{{/vrps}}\n}\n"
+            ),
+            Ok(Template {
+                header: "roa-set {\n".into(),
+                item: vec![
+                    Text("  ".into()), Asn, Text(" ".into()), Tal,
+                    Text("\n".into()),
+                ],
+                footer: "}\n".into(),
+            })
+        );
+        assert!(Template::parse("{{#vrps}}{{asn}}").is_err());
+        assert!(Template::parse("{{asn}}{{/vrps}}").is_err());
+        assert!(Template::parse("{{asn}").is_err());
+        assert!(Template::parse("{{as}}").is_err());
+        assert!(Template::parse("{{asn}}{{#vrps}}{{/vrps}}").is_err());
+    }
+
+    #[test]
+    fn router_keys() {
+        use rpki::crypto::keys::KeyIdentifier;
+        use crate::slurm::ExceptionInfo;
+
+        let key = RouterKey::new(
+            KeyIdentifier::try_from([0x11; 20].as_ref()).unwrap(),
+            Asn::from_u32(65000),
+            Bytes::from_static(b"key").try_into().unwrap(),
+        );
+        let info = PayloadInfo::from(Arc::new(ExceptionInfo::default()));
+        let mut target = Vec::new();
+        let format = OutputFormat::from_str("routerkeys").unwrap();
+        let formatter = format.formatter::<Vec<u8>>();
+        formatter.router_key(&key, &info, &mut target).unwrap();
+        assert_eq!(
+            String::from_utf8(target).unwrap(),
+            "AS65000,1111111111111111111111111111111111111111,a2V5,N/A\n"
+        );
+    }
+}
@@ -0,0 +1,174 @@
+//! Differences between arbitrary payload snapshots.
+//!
+//! This is a private module. Its public types are re-exported by the parent.
+
+use std::collections::BTreeMap;
+use rpki::resources::Asn;
+use rpki::rtr::{Action, Serial};
+use rpki::rtr::payload::RouteOrigin;
+use super::delta::PayloadDelta;
+use super::snapshot::PayloadSnapshot;
+
+
+//------------ SnapshotDiff --------------------------------------------------
+
+/// The difference in route origins between two payload snapshots.
+///
+/// Unlike [`PayloadDelta`] which always leads from one version to the next,
+/// a diff can be produced between any two snapshots kept in the history.
+/// It is intended for inspecting and monitoring changes rather than for
+/// serving clients.
+#[derive(Clone, Debug)]
+pub struct SnapshotDiff {
+    /// The serial number of the older snapshot.
+    from: Serial,
+
+    /// The serial number of the newer snapshot.
+    to: Serial,
+
+    /// The route origins present in the newer but not the older snapshot.
+    announced: Vec<RouteOrigin>,
+
+    /// The route origins present in the older but not the newer snapshot.
+    withdrawn: Vec<RouteOrigin>,
+}
+
+impl SnapshotDiff {
+    /// Constructs the diff between two snapshots.
+    pub fn construct(
+        from: Serial, old: &PayloadSnapshot,
+        to: Serial, new: &PayloadSnapshot,
+    ) -> Self {
+        let mut res = SnapshotDiff {
+            from, to,
+            announced: Vec::new(),
+            withdrawn: Vec::new(),
+        };
+        if let Some(delta) = PayloadDelta::construct(old, new, from) {
+            for (origin, action) in delta.origin_actions() {
+                match action {
+                    Action::Announce => res.announced.push(origin),
+                    Action::Withdraw => res.withdrawn.push(origin),
+                }
+            }
+        }
+        res
+    }
+
+    /// Returns the serial number of the older snapshot.
+    pub fn from(&self) -> Serial {
+        self.from
+    }
+
+    /// Returns the serial number of the newer snapshot.
+    pub fn to(&self) -> Serial {
+        self.to
+    }
+
+    /// Returns the route origins added between the two snapshots.
+    pub fn announced(&self) -> &[RouteOrigin] {
+        &self.announced
+    }
+
+    /// Returns the route origins removed between the two snapshots.
+    pub fn withdrawn(&self) -> &[RouteOrigin] {
+        &self.withdrawn
+    }
+
+    /// Returns whether the route origins of the two snapshots are the same.
+    pub fn is_empty(&self) -> bool {
+        self.announced.is_empty() && self.withdrawn.is_empty()
+    }
+
+    /// Returns the changes per origin AS.
+    ///
+    /// Only ASNs with changes are included.
+    pub fn asn_changes(&self) -> BTreeMap<Asn, AsnChanges> {
+        let mut res = BTreeMap::<_, AsnChanges>::new();
+        for origin in &self.announced {
+            res.entry(origin.asn).or_default().announced += 1;
+        }
+        for origin in &self.withdrawn {
+            res.entry(origin.asn).or_default().withdrawn += 1;
+        }
+        res
+    }
+}
+
+
+//------------ AsnChanges ----------------------------------------------------
+
+/// The number of changed route origins for a single origin AS.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct AsnChanges {
+    /// The number of added route origins.
+    pub announced: usize,
+
+    /// The number of removed route origins.
+    pub withdrawn: usize,
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use rpki::resources::{MaxLenPrefix, Prefix};
+    use rpki::rtr::payload::Payload;
+    use crate::slurm::ExceptionInfo;
+    use super::super::info::PayloadInfo;
+    use super::*;
+
+    fn snapshot(origins: &[(&str, u32)]) -> PayloadSnapshot {
+        let payload: Vec<_> = origins.iter().map(|(prefix, asn)| {
+            Payload::Origin(RouteOrigin::new(
+                MaxLenPrefix::new(Prefix::from_str(prefix).unwrap(), None)
+                    .unwrap(),
+                Asn::from_u32(*asn)
+            ))
+        }).collect();
+        PayloadSnapshot::from_payload(
+            payload.iter(),
+            &PayloadInfo::from(Arc::new(
+                ExceptionInfo { path: None, comment: None }
+            ))
+        )
+    }
+
+    #[test]
+    fn construct() {
+        let old = snapshot(&[
+            ("192.0.2.0/24", 64496), ("198.51.100.0/24", 64496),
+            ("203.0.113.0/24", 64497),
+        ]);
+        let new = snapshot(&[
+            ("192.0.2.0/24", 64496), ("203.0.113.0/24", 64498),
+            ("2001:db8::/32", 64498),
+        ]);
+        let diff = SnapshotDiff::construct(1.into(), &old, 4.into(), &new);
+        assert_eq!(diff.from(), Serial::from(1));
+        assert_eq!(diff.to(), Serial::from(4));
+        assert_eq!(diff.announced().len(), 2);
+        assert_eq!(diff.withdrawn().len(), 2);
+
+        let changes = diff.asn_changes();
+        assert_eq!(
+            changes.get(&Asn::from_u32(64496)),
+            Some(&AsnChanges { announced: 0, withdrawn: 1 })
+        );
+        assert_eq!(
+            changes.get(&Asn::from_u32(64497)),
+            Some(&AsnChanges { announced: 0, withdrawn: 1 })
+        );
+        assert_eq!(
+            changes.get(&Asn::from_u32(64498)),
+            Some(&AsnChanges { announced: 2, withdrawn: 0 })
+        );
+
+        assert!(SnapshotDiff::construct(
+            1.into(), &old, 1.into(), &old
+        ).is_empty());
+    }
+}
@@ -0,0 +1,244 @@
+//! Ghostbuster Records.
+//!
+//! This is a private module. Its public types are re-exported by the parent.
+//!
+//! Ghostbuster Records as defined in RFC 6493 provide contact information
+//! for the operator of a CA in the form of a vCard. The records are
+//! collected during validation and kept with the payload snapshot so that
+//! operators chasing a broken publication point can find out whom to talk
+//! to.
+
+use std::str;
+use rpki::uri;
+use rpki::repository::cert::ResourceCert;
+use crate::utils::json::JsonBuilder;
+
+
+//------------ GhostbusterRecord ---------------------------------------------
+
+/// The content of a valid Ghostbuster Record.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct GhostbusterRecord {
+    /// The rsync URI of the record.
+    pub uri: uri::Rsync,
+
+    /// The rsync URI of the certificate of the CA publishing the record.
+    pub ca: Option<uri::Rsync>,
+
+    /// The name of the TAL the record was published under.
+    pub tal: String,
+
+    /// The contact information contained in the record.
+    pub vcard: VCard,
+}
+
+impl GhostbusterRecord {
+    /// Creates a new record from the content of a valid GBR object.
+    ///
+    /// Returns `None` if the content isn’t a vCard.
+    pub(super) fn new(
+        uri: &uri::Rsync, cert: &ResourceCert, content: &[u8],
+    ) -> Option<Self> {
+        Some(GhostbusterRecord {
+            uri: uri.clone(),
+            ca: cert.ca_issuer().cloned(),
+            tal: cert.tal().name().into(),
+            vcard: VCard::parse(content)?,
+        })
+    }
+
+    /// Writes the record as the members of a JSON object.
+    pub fn json(&self, target: &mut JsonBuilder) {
+        target.member_str("uri", &self.uri);
+        match self.ca.as_ref() {
+            Some(ca) => target.member_str("ca", ca),
+            None => target.member_raw("ca", "null"),
+        }
+        target.member_str("tal", &self.tal);
+        match self.vcard.full_name.as_ref() {
+            Some(name) => target.member_str("fullName", name),
+            None => target.member_raw("fullName", "null"),
+        }
+        match self.vcard.org.as_ref() {
+            Some(org) => target.member_str("org", org),
+            None => target.member_raw("org", "null"),
+        }
+        for (key, values) in [
+            ("addresses", &self.vcard.addresses),
+            ("phones", &self.vcard.phones),
+            ("emails", &self.vcard.emails),
+        ] {
+            target.member_array(key, |target| {
+                for value in values {
+                    target.array_str(value)
+                }
+            })
+        }
+    }
+}
+
+
+//------------ VCard ---------------------------------------------------------
+
+/// The contact information from a vCard.
+///
+/// This only contains the properties allowed by RFC 6493. Structured
+/// values such as addresses are flattened into a single line.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct VCard {
+    /// The formatted name of the contact.
+    pub full_name: Option<String>,
+
+    /// The organization of the contact.
+    pub org: Option<String>,
+
+    /// The postal addresses.
+    pub addresses: Vec<String>,
+
+    /// The telephone numbers.
+    pub phones: Vec<String>,
+
+    /// The email addresses.
+    pub emails: Vec<String>,
+}
+
+impl VCard {
+    /// Parses a vCard.
+    ///
+    /// The parser is rather lenient and ignores all properties it doesn’t
+    /// know as well as all property parameters. Returns `None` if the
+    /// content isn’t UTF-8 or doesn’t start with `BEGIN:VCARD`.
+    pub fn parse(content: &[u8]) -> Option<Self> {
+        let content = str::from_utf8(content).ok()?;
+
+        // Unfold lines: a line break followed by white space continues the
+        // previous line.
+        let mut lines = Vec::<String>::new();
+        for line in content.lines() {
+            match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+                (Some(cont), Some(last)) => last.push_str(cont),
+                _ => lines.push(line.into()),
+            }
+        }
+
+        let mut lines = lines.iter().filter(|line| !line.trim().is_empty());
+        if !lines.next()?.trim().eq_ignore_ascii_case("BEGIN:VCARD") {
+            return None
+        }
+        let mut res = VCard::default();
+        for line in lines {
+            let (name, value) = match line.split_once(':') {
+                Some(some) => some,
+                None => continue,
+            };
+            // Strip parameters and the group.
+            let name = name.split(';').next().unwrap_or_default();
+            let name = name.rsplit('.').next().unwrap_or_default();
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("FN") {
+                res.full_name = Some(Self::unescape(value));
+            }
+            else if name.eq_ignore_ascii_case("ORG") {
+                res.org = Some(Self::join(value, " "));
+            }
+            else if name.eq_ignore_ascii_case("ADR") {
+                res.addresses.push(Self::join(value, ", "));
+            }
+            else if name.eq_ignore_ascii_case("TEL") {
+                res.phones.push(Self::unescape(value));
+            }
+            else if name.eq_ignore_ascii_case("EMAIL") {
+                res.emails.push(Self::unescape(value));
+            }
+            else if name.eq_ignore_ascii_case("END") {
+                break
+            }
+        }
+        Some(res)
+    }
+
+    /// Joins the non-empty components of a structured value.
+    fn join(value: &str, sep: &str) -> String {
+        let mut res = Vec::new();
+        let mut component = String::new();
+        let mut chars = value.chars();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\\' => {
+                    if let Some(ch) = chars.next() {
+                        component.push(Self::unescape_char(ch))
+                    }
+                }
+                ';' => {
+                    res.push(std::mem::take(&mut component));
+                }
+                _ => component.push(ch)
+            }
+        }
+        res.push(component);
+        res.retain(|item| !item.trim().is_empty());
+        res.join(sep)
+    }
+
+    /// Removes the escaping from a text value.
+    fn unescape(value: &str) -> String {
+        let mut res = String::with_capacity(value.len());
+        let mut chars = value.chars();
+        while let Some(ch) = chars.next() {
+            if ch == '\\' {
+                if let Some(ch) = chars.next() {
+                    res.push(Self::unescape_char(ch))
+                }
+            }
+            else {
+                res.push(ch)
+            }
+        }
+        res
+    }
+
+    /// Returns the character for an escaped character.
+    fn unescape_char(ch: char) -> char {
+        match ch {
+            'n' | 'N' => '\n',
+            _ => ch
+        }
+    }
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_vcard() {
+        let vcard = VCard::parse(
+            b"BEGIN:VCARD\r\n\
+              VERSION:4.0\r\n\
+              FN:Job Ghostbuster\r\n\
+              ORG:Example\\, Inc.;RPKI\r\n\
+              ADR;TYPE=WORK:;;Main Street 1;Amsterdam;;1000 AA;\r\n \
+               Netherlands\r\n\
+              TEL;TYPE=VOICE:tel:+31-20-555-0000\r\n\
+              item1.EMAIL:rpki@example.net\r\n\
+              EMAIL:noc@example.net\r\n\
+              END:VCARD\r\n"
+        ).unwrap();
+        assert_eq!(vcard.full_name.as_deref(), Some("Job Ghostbuster"));
+        assert_eq!(vcard.org.as_deref(), Some("Example, Inc. RPKI"));
+        assert_eq!(
+            vcard.addresses,
+            ["Main Street 1, Amsterdam, 1000 AA, Netherlands"]
+        );
+        assert_eq!(vcard.phones, ["tel:+31-20-555-0000"]);
+        assert_eq!(vcard.emails, ["rpki@example.net", "noc@example.net"]);
+
+        assert_eq!(VCard::parse(b"FN:Job Ghostbuster\r\n"), None);
+        assert_eq!(VCard::parse(b"BEGIN:VCARD\r\nFN:\xff\r\n"), None);
+    }
+}
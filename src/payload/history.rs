@@ -6,15 +6,19 @@
 use std::{cmp, ops};
 use std::collections::VecDeque;
 use std::sync::{Arc, RwLock};
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 use chrono::{DateTime, Utc};
 use log::info;
-use rpki::rtr::{Serial, State, Timing};
+use rpki::repository::x509::Time;
+use rpki::rtr::{Action, Serial, State, Timing};
 use rpki::rtr::server::PayloadSource;
 use crate::config::{Config, FilterPolicy};
-use crate::metrics::Metrics;
+use crate::metrics::{
+    Metrics, ProbeMetrics, PropagationMetrics, RepositoryProbe
+};
 use crate::slurm::LocalExceptions;
 use super::delta::{DeltaArcIter, PayloadDelta};
+use super::diff::SnapshotDiff;
 use super::snapshot::{PayloadSnapshot, SnapshotArcIter};
 use super::validation::ValidationReport;
 
@@ -52,6 +56,11 @@ impl SharedHistory {
     /// exceptions. If this snapshot differs from the current one, adds a
     /// new version to the history.
     ///
+    /// If a minimum interval between serials has been configured and the
+    /// last serial was published less than that interval ago, the snapshot
+    /// is held back instead, replacing any snapshot held back earlier. It
+    /// will be published via [`publish_pending`][Self::publish_pending].
+    ///
     /// The method returns whether it has indeed added a new version.
     pub fn update(
         &self,
@@ -62,7 +71,69 @@ impl SharedHistory {
         let snapshot = report.into_snapshot(
             exceptions, &mut metrics,
         );
+        {
+            let mut history = self.write();
+            if let Some(deadline) = history.serial_deadline() {
+                if deadline > Instant::now() {
+                    info!(
+                        "Holding back new payload for {} seconds.",
+                        deadline.saturating_duration_since(
+                            Instant::now()
+                        ).as_secs()
+                    );
+                    history.metrics = Some(metrics.into());
+                    history.pending = Some(snapshot);
+                    return false
+                }
+            }
+            history.pending = None;
+        }
+        self.update_snapshot(snapshot, metrics)
+    }
+
+    /// Publishes a held back snapshot if its time has come.
+    ///
+    /// The method returns whether it has added a new version.
+    pub fn publish_pending(&self) -> bool {
+        let snapshot = {
+            let mut history = self.write();
+            if history.pending.is_none() {
+                return false
+            }
+            if let Some(deadline) = history.serial_deadline() {
+                if deadline > Instant::now() {
+                    return false
+                }
+            }
+            match history.pending.take() {
+                Some(snapshot) => snapshot,
+                None => return false,
+            }
+        };
+        info!("Publishing held back payload.");
+        self.publish_snapshot(snapshot)
+    }
+
+    /// Updates the history with a new snapshot.
+    ///
+    /// If the snapshot differs from the current one, adds a new version to
+    /// the history. The metrics are replaced with `metrics`.
+    ///
+    /// The method returns whether it has indeed added a new version.
+    pub fn update_snapshot(
+        &self,
+        snapshot: PayloadSnapshot,
+        metrics: Metrics,
+    ) -> bool {
+        self.write().metrics = Some(metrics.into());
+        self.publish_snapshot(snapshot)
+    }
 
+    /// Publishes a new snapshot.
+    ///
+    /// If the snapshot differs from the current one, adds a new version to
+    /// the history and returns `true`.
+    fn publish_snapshot(&self, snapshot: PayloadSnapshot) -> bool {
         let (current, serial) = {
             let read = self.read();
             (read.current(), read.serial())
@@ -73,7 +144,6 @@ impl SharedHistory {
         });
 
         let mut history = self.write();
-        history.metrics = Some(metrics.into());
         let res = if let Some(delta) = delta {
             // Data has changed.
             info!(
@@ -81,6 +151,17 @@ impl SharedHistory {
                 delta.announce_len(),
                 delta.withdraw_len(),
             );
+            let now = Time::now();
+            let propagation = Arc::make_mut(&mut history.propagation);
+            for (payload, action) in delta.actions() {
+                if matches!(action, Action::Announce) {
+                    if let Some(published) = snapshot.info(payload).and_then(
+                        |info| info.published()
+                    ) {
+                        propagation.add(published, now)
+                    }
+                }
+            }
             history.push_delta(delta);
             true
         }
@@ -92,12 +173,23 @@ impl SharedHistory {
             // Nothing has changed.
             false
         };
+        if res {
+            history.last_serial = Some(Instant::now());
+        }
         // Update the snapshot. The refresh time and object information may
         // have changed.
-        history.current = Some(snapshot.into());
+        let snapshot = Arc::new(snapshot);
+        history.push_snapshot(snapshot.clone(), res);
+        history.current = Some(snapshot);
         res
     }
 
+    /// Adds the results of a round of repository probes.
+    pub fn update_probes(&self, probes: Vec<RepositoryProbe>) {
+        let mut history = self.write();
+        Arc::make_mut(&mut history.probes).update(probes);
+    }
+
     /// Marks the beginning of an update cycle.
     pub fn mark_update_start(&self) {
         self.write().last_update_start = Utc::now();
@@ -113,9 +205,9 @@ impl SharedHistory {
                 .to_std().unwrap_or_else(|_| Duration::from_secs(0))
         );
         locked.next_update_start = SystemTime::now() + locked.refresh;
-        if let Some(refresh) = locked.current.as_ref().and_then(|c|
-            c.refresh()
-        ) {
+        if let Some(refresh) = locked.current.as_ref().filter(|_| {
+            locked.payload_refresh
+        }).and_then(|c| c.refresh()) {
             let refresh = SystemTime::from(refresh);
             if refresh < locked.next_update_start {
                 locked.next_update_start = refresh;
@@ -205,15 +297,36 @@ pub struct PayloadHistory {
     /// The current metrics.
     metrics: Option<Arc<Metrics>>,
 
+    /// The accumulated results of repository probes.
+    probes: Arc<ProbeMetrics>,
+
+    /// The accumulated propagation delays of announced payload.
+    propagation: Arc<PropagationMetrics>,
+
     /// The session ID.
     session: u64,
 
     /// The number of diffs to keep.
     keep: usize,
 
+    /// A queue of recent snapshots with their serial numbers.
+    ///
+    /// The newest snapshot is at the front of the queue. It is the same as
+    /// the current snapshot.
+    snapshots: VecDeque<(Serial, Arc<PayloadSnapshot>)>,
+
+    /// The number of snapshots to keep.
+    snapshot_keep: usize,
+
     /// The time to wait between updates,
     refresh: Duration,
 
+    /// Should the refresh time of the payload shorten the wait?
+    ///
+    /// This is not the case when validating at a given time since the
+    /// refresh time of the payload may then be in the past.
+    payload_refresh: bool,
+
     /// How to deal with unsafe VRPs.
     unsafe_vrps: FilterPolicy,
 
@@ -239,6 +352,15 @@ pub struct PayloadHistory {
 
     /// Default RTR timing.
     timing: Timing,
+
+    /// The minimum time between publishing two new serials.
+    min_serial_interval: Option<Duration>,
+
+    /// The instant the last new serial was published.
+    last_serial: Option<Instant>,
+
+    /// A snapshot held back because of the minimum serial interval.
+    pending: Option<PayloadSnapshot>,
 }
 
 impl PayloadHistory {
@@ -248,13 +370,18 @@ impl PayloadHistory {
             current: None,
             deltas: VecDeque::with_capacity(config.history_size),
             metrics: None,
+            probes: Default::default(),
+            propagation: Default::default(),
             session: {
                 SystemTime::now()
                     .duration_since(SystemTime::UNIX_EPOCH).unwrap()
                     .as_secs()
             },
             keep: config.history_size,
+            snapshots: VecDeque::with_capacity(config.snapshot_history),
+            snapshot_keep: config.snapshot_history,
             refresh: config.refresh,
+            payload_refresh: config.validation_time.is_none(),
             unsafe_vrps: config.unsafe_vrps,
             last_update_start: Utc::now(),
             last_update_done: None,
@@ -266,6 +393,9 @@ impl PayloadHistory {
                 retry: config.retry.as_secs() as u32,
                 expire: config.expire.as_secs() as u32,
             },
+            min_serial_interval: config.min_serial_interval,
+            last_serial: None,
+            pending: None,
         }
     }
 
@@ -277,6 +407,29 @@ impl PayloadHistory {
         self.deltas.push_front(Arc::new(delta))
     }
 
+    /// Pushes a new snapshot to the history.
+    ///
+    /// If `new_version` is `false`, the snapshot replaces the newest
+    /// snapshot since its serial number hasn’t changed.
+    fn push_snapshot(
+        &mut self, snapshot: Arc<PayloadSnapshot>, new_version: bool
+    ) {
+        if self.snapshot_keep == 0 {
+            return
+        }
+        let serial = self.serial();
+        if !new_version {
+            if let Some(front) = self.snapshots.front_mut() {
+                *front = (serial, snapshot);
+                return
+            }
+        }
+        if self.snapshots.len() == self.snapshot_keep {
+            let _ = self.snapshots.pop_back();
+        }
+        self.snapshots.push_front((serial, snapshot))
+    }
+
     /// Returns whether the history is already active.
     ///
     /// The history becomes active once the first validation has finished.
@@ -291,6 +444,22 @@ impl PayloadHistory {
         self.current.clone()
     }
 
+    /// Returns the earliest instant the next serial may be published.
+    ///
+    /// Returns `None` if there is no restriction.
+    fn serial_deadline(&self) -> Option<Instant> {
+        Some(self.last_serial? + self.min_serial_interval?)
+    }
+
+    /// Returns when a held back snapshot is due to be published.
+    ///
+    /// Returns `None` if no snapshot is currently held back.
+    pub fn pending_deadline(&self) -> Option<Instant> {
+        self.pending.as_ref().map(|_| {
+            self.serial_deadline().unwrap_or_else(Instant::now)
+        })
+    }
+
     /// Returns the duration until the next refresh should start.
     pub fn refresh_wait(&self) -> Duration {
         self.next_update_start
@@ -373,6 +542,37 @@ impl PayloadHistory {
         Some(res)
     }
 
+    /// Returns the serial numbers and snapshots kept in the history.
+    ///
+    /// The snapshots are returned newest first. Unless snapshots are kept
+    /// via the `snapshot-history` option, this is empty.
+    pub fn snapshots(
+        &self
+    ) -> impl Iterator<Item = (Serial, &Arc<PayloadSnapshot>)> + '_ {
+        self.snapshots.iter().map(|(serial, snapshot)| (*serial, snapshot))
+    }
+
+    /// Returns the snapshot with the given serial number.
+    ///
+    /// Returns `None` if the snapshot isn’t kept in the history.
+    pub fn snapshot(&self, serial: Serial) -> Option<Arc<PayloadSnapshot>> {
+        if serial == self.serial() {
+            return self.current()
+        }
+        self.snapshots.iter().find(|item| item.0 == serial).map(|item| {
+            item.1.clone()
+        })
+    }
+
+    /// Returns the diff between the snapshots with the given serials.
+    ///
+    /// Returns `None` if either snapshot isn’t kept in the history.
+    pub fn diff(&self, from: Serial, to: Serial) -> Option<SnapshotDiff> {
+        let old = self.snapshot(from)?;
+        let new = self.snapshot(to)?;
+        Some(SnapshotDiff::construct(from, &old, to, &new))
+    }
+
     /// Returns the serial number of the current data set.
     pub fn serial(&self) -> Serial {
         self.deltas.front().map(|delta| {
@@ -402,6 +602,16 @@ impl PayloadHistory {
         self.metrics.clone()
     }
 
+    /// Returns the accumulated results of repository probes.
+    pub fn probes(&self) -> Arc<ProbeMetrics> {
+        self.probes.clone()
+    }
+
+    /// Returns the accumulated propagation delays.
+    pub fn propagation(&self) -> Arc<PropagationMetrics> {
+        self.propagation.clone()
+    }
+
     /// Returns the time the last update was started.
     pub fn last_update_start(&self) -> DateTime<Utc> {
         self.last_update_start
@@ -432,3 +642,53 @@ impl PayloadHistory {
     }
 }
 
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use rpki::resources::{Asn, MaxLenPrefix, Prefix};
+    use rpki::rtr::payload::{Payload, RouteOrigin};
+    use crate::slurm::ExceptionInfo;
+    use super::super::info::PayloadInfo;
+    use super::*;
+
+    fn snapshot(origins: &[(&str, u32)]) -> Arc<PayloadSnapshot> {
+        let payload: Vec<_> = origins.iter().map(|(prefix, asn)| {
+            Payload::Origin(RouteOrigin::new(
+                MaxLenPrefix::new(Prefix::from_str(prefix).unwrap(), None)
+                    .unwrap(),
+                Asn::from_u32(*asn)
+            ))
+        }).collect();
+        Arc::new(PayloadSnapshot::from_payload(
+            payload.iter(),
+            &PayloadInfo::from(Arc::new(
+                ExceptionInfo { path: None, comment: None }
+            ))
+        ))
+    }
+
+    #[test]
+    fn diff() {
+        let mut config = Config::default_with_paths(
+            Default::default(), Default::default()
+        );
+        config.snapshot_history = 2;
+        let mut history = PayloadHistory::from_config(&config);
+        history.snapshots.push_front((
+            1.into(), snapshot(&[("192.0.2.0/24", 64496)])
+        ));
+        history.snapshots.push_front((
+            2.into(),
+            snapshot(&[("192.0.2.0/24", 64496), ("2001:db8::/32", 64497)])
+        ));
+
+        let diff = history.diff(1.into(), 2.into()).unwrap();
+        assert_eq!(diff.announced().len(), 1);
+        assert!(diff.withdrawn().is_empty());
+        assert!(history.diff(2.into(), 2.into()).unwrap().is_empty());
+        assert!(history.diff(1.into(), 3.into()).is_none());
+    }
+}
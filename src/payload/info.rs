@@ -2,6 +2,7 @@
 //!
 //! This is a private module. Its public types are re-exported by the parent.
 
+use std::cmp;
 use std::sync::Arc;
 use rpki::uri;
 use rpki::repository::cert::{Cert, ResourceCert};
@@ -78,6 +79,24 @@ impl PayloadInfo {
             Err(ref info) => Some(info),
         }
     }
+
+    /// Returns when the payload was first published.
+    ///
+    /// This is the earliest publication time of all the published objects
+    /// the payload was derived from. Returns `None` if the payload was
+    /// also added by a local exception or if a publication time is
+    /// unknown.
+    pub fn published(&self) -> Option<Time> {
+        let mut res: Option<Time> = None;
+        for item in self {
+            let published = item.publish_info()?.published?;
+            res = Some(match res {
+                Some(res) => cmp::min(res, published),
+                None => published,
+            });
+        }
+        res
+    }
 }
 
 
@@ -146,6 +165,13 @@ pub struct PublishInfo {
 
     /// When will the object’s publication point become stale?
     pub point_stale: Time,
+
+    /// When was the object’s validation chain last published?
+    ///
+    /// This is the latest this-update time of the manifests of the
+    /// object’s publication point and those of all its parent CAs. It is
+    /// `None` if there was no manifest which can’t really happen.
+    pub published: Option<Time>,
 }
 
 impl PublishInfo {
@@ -154,6 +180,7 @@ impl PublishInfo {
         cert: &ResourceCert,
         ca_validity: Validity,
         point_stale: Time,
+        published: Option<Time>,
     ) -> Self {
         PublishInfo {
             tal: cert.tal().clone(),
@@ -163,6 +190,7 @@ impl PublishInfo {
             roa_validity: cert.validity(),
             chain_validity: cert.validity().trim(ca_validity),
             point_stale,
+            published,
         }
     }
 
@@ -172,6 +200,7 @@ impl PublishInfo {
         tal: Arc<TalInfo>,
         ca_validity: Validity,
         point_stale: Time,
+        published: Option<Time>,
     ) -> Self {
         PublishInfo {
             tal,
@@ -179,6 +208,7 @@ impl PublishInfo {
             roa_validity: cert.validity(),
             chain_validity: cert.validity().trim(ca_validity),
             point_stale,
+            published,
         }
     }
 
@@ -5,9 +5,13 @@
 //! A full such data set is called a [`PayloadSnapshot`] with differences
 //! between consecutive such snapshots available as [`PayloadDelta`]. A
 //! collection of the two plus additional information is the
-//! [`PayloadHistory`] or, wrapped in an arc, [`SharedHistory`].
+//! [`PayloadHistory`] or, wrapped in an arc, [`SharedHistory`]. The history
+//! can also keep a number of older snapshots so that a [`SnapshotDiff`]
+//! between any two of them can be produced.
 
 pub use self::delta::{DeltaArcIter, PayloadDelta};
+pub use self::diff::{AsnChanges, SnapshotDiff};
+pub use self::gbr::{GhostbusterRecord, VCard};
 pub use self::history::{PayloadHistory, SharedHistory};
 pub use self::info::PayloadInfo;
 pub use self::snapshot::{
@@ -17,8 +21,11 @@ pub use self::snapshot::{
 pub use self::validation::ValidationReport;
 
 mod delta;
+mod diff;
+mod gbr;
 mod history;
 mod info;
+mod policy;
 mod validation;
 mod snapshot;
 
@@ -0,0 +1,152 @@
+//! Local policy for route origins.
+//!
+//! This is a private module. Its types are used by the validation report
+//! only.
+
+use rpki::rtr::payload::RouteOrigin;
+use crate::config::{Config, FilterPolicy, RoaPolicyRule};
+
+
+//------------ RoaPolicy -----------------------------------------------------
+
+/// The local policy applied to the route origins of valid ROAs.
+///
+/// The policy consists of the rules given via the `roa-policy` config file
+/// setting. It is applied when assembling the payload snapshot and can
+/// drop or flag route origins that are valid but undesirable locally.
+#[derive(Clone, Debug, Default)]
+pub struct RoaPolicy {
+    /// The rules with their names, ordered by name.
+    rules: Vec<(String, RoaPolicyRule)>,
+}
+
+impl RoaPolicy {
+    /// Creates the policy from the config.
+    pub fn new(config: &Config) -> Self {
+        let mut rules: Vec<_> = config.roa_policy.iter().filter(|(_, rule)| {
+            rule.action != FilterPolicy::Accept
+        }).map(|(name, rule)| (name.clone(), rule.clone())).collect();
+        rules.sort_by(|left, right| left.0.cmp(&right.0));
+        RoaPolicy { rules }
+    }
+
+    /// Checks a route origin against the policy.
+    ///
+    /// Returns the name of the first matching rule and its action. Rules
+    /// rejecting the route origin take precedence over those only warning
+    /// about it. Returns `None` if no rule matches.
+    pub fn check(&self, origin: RouteOrigin) -> Option<(&str, FilterPolicy)> {
+        let mut res = None;
+        for (name, rule) in &self.rules {
+            if !Self::matches(rule, origin) {
+                continue
+            }
+            if rule.action == FilterPolicy::Reject {
+                return Some((name.as_str(), FilterPolicy::Reject))
+            }
+            if res.is_none() {
+                res = Some((name.as_str(), rule.action))
+            }
+        }
+        res
+    }
+
+    /// Returns whether the route origin matches all conditions of a rule.
+    fn matches(rule: &RoaPolicyRule, origin: RouteOrigin) -> bool {
+        if rule.max_length_excess.is_none()
+            && rule.prefixes.is_empty()
+            && rule.asns.is_empty()
+        {
+            return false
+        }
+        if let Some(excess) = rule.max_length_excess {
+            let prefix_len = origin.prefix.prefix_len();
+            let max_len = origin.prefix.resolved_max_len();
+            if max_len.saturating_sub(prefix_len) <= excess {
+                return false
+            }
+        }
+        if !rule.prefixes.is_empty() {
+            let prefix = origin.prefix.prefix();
+            if !rule.prefixes.iter().any(|item| {
+                item.covers(prefix) || prefix.covers(*item)
+            }) {
+                return false
+            }
+        }
+        if !rule.asns.is_empty() && !rule.asns.contains(&origin.asn) {
+            return false
+        }
+        true
+    }
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use rpki::resources::{Asn, MaxLenPrefix, Prefix};
+    use super::*;
+
+    fn origin(prefix: &str, max_len: u8, asn: u32) -> RouteOrigin {
+        RouteOrigin::new(
+            MaxLenPrefix::new(
+                Prefix::from_str(prefix).unwrap(), Some(max_len)
+            ).unwrap(),
+            Asn::from_u32(asn)
+        )
+    }
+
+    fn rule(
+        max_length_excess: Option<u8>, prefixes: &[&str], asns: &[u32],
+        action: FilterPolicy,
+    ) -> RoaPolicyRule {
+        RoaPolicyRule {
+            max_length_excess,
+            prefixes: prefixes.iter().map(|prefix| {
+                Prefix::from_str(prefix).unwrap()
+            }).collect(),
+            asns: asns.iter().copied().map(Asn::from_u32).collect(),
+            action,
+        }
+    }
+
+    #[test]
+    fn check() {
+        let mut config = Config::default_with_paths(
+            Default::default(), Default::default()
+        );
+        config.roa_policy.insert(
+            "long".into(), rule(Some(8), &[], &[], FilterPolicy::Warn)
+        );
+        config.roa_policy.insert(
+            "bogons".into(),
+            rule(None, &["10.0.0.0/8"], &[], FilterPolicy::Reject)
+        );
+        config.roa_policy.insert(
+            "as0".into(), rule(None, &[], &[0], FilterPolicy::Accept)
+        );
+        let policy = RoaPolicy::new(&config);
+
+        assert_eq!(policy.check(origin("192.0.2.0/24", 24, 64496)), None);
+        assert_eq!(
+            policy.check(origin("198.51.0.0/16", 24, 64496)),
+            None
+        );
+        assert_eq!(
+            policy.check(origin("198.51.0.0/16", 25, 64496)),
+            Some(("long", FilterPolicy::Warn))
+        );
+        assert_eq!(
+            policy.check(origin("10.1.0.0/16", 16, 64496)),
+            Some(("bogons", FilterPolicy::Reject))
+        );
+        assert_eq!(
+            policy.check(origin("0.0.0.0/0", 32, 64496)),
+            Some(("bogons", FilterPolicy::Reject))
+        );
+        assert_eq!(policy.check(origin("192.0.2.0/24", 24, 0)), None);
+    }
+}
@@ -4,13 +4,15 @@
 
 #![allow(dead_code)]
 
+use std::iter;
 use std::sync::Arc;
 use chrono::{DateTime, Utc};
 use rpki::repository::x509::Time;
 use rpki::rtr::payload::{
-    Aspa, PayloadRef, PayloadType, RouteOrigin, RouterKey
+    Aspa, Payload, PayloadRef, PayloadType, RouteOrigin, RouterKey
 };
 use rpki::rtr::server::PayloadSet;
+use super::gbr::GhostbusterRecord;
 use super::info::PayloadInfo;
 
 
@@ -23,12 +25,21 @@ pub struct PayloadSnapshot {
     /// The route origins.
     origins: PayloadCollection<RouteOrigin>,
 
+    /// The route origins marked as unsafe.
+    ///
+    /// These are kept separately whether or not they are also part of
+    /// `origins`.
+    unsafe_origins: PayloadCollection<RouteOrigin>,
+
     /// The router keys,
     router_keys: PayloadCollection<RouterKey>,
 
     /// The AS providers,
     aspas: PayloadCollection<Aspa>,
 
+    /// The Ghostbuster Records, ordered by CA and URI.
+    gbrs: Vec<GhostbusterRecord>,
+
     /// The time when this snapshot was created.
     created: DateTime<Utc>,
 
@@ -43,8 +54,10 @@ impl Default for PayloadSnapshot {
     fn default() -> Self {
         PayloadSnapshot {
             origins: Default::default(),
+            unsafe_origins: Default::default(),
             router_keys: Default::default(),
             aspas: Default::default(),
+            gbrs: Vec::new(),
             created: Utc::now(),
             refresh: None
         }
@@ -55,19 +68,50 @@ impl PayloadSnapshot {
     /// Creates a new snapshot from its parts.
     pub(super) fn new(
         origins: impl Iterator<Item = (RouteOrigin, PayloadInfo)>,
+        unsafe_origins: impl Iterator<Item = (RouteOrigin, PayloadInfo)>,
         router_keys: impl Iterator<Item = (RouterKey, PayloadInfo)>,
         aspas: impl Iterator<Item = (Aspa, PayloadInfo)>,
+        gbrs: Vec<GhostbusterRecord>,
         refresh: Option<Time>
     ) -> Self {
         Self {
             origins: PayloadCollection::from_iter(origins),
+            unsafe_origins: PayloadCollection::from_iter(unsafe_origins),
             router_keys: PayloadCollection::from_iter(router_keys),
             aspas: PayloadCollection::from_iter(aspas),
+            gbrs,
             created: Utc::now(),
             refresh,
         }
     }
 
+    /// Creates a new snapshot from payload sharing the same information.
+    pub fn from_payload<'a>(
+        payload: impl Iterator<Item = &'a Payload>,
+        info: &PayloadInfo,
+    ) -> Self {
+        let mut origins = Vec::new();
+        let mut router_keys = Vec::new();
+        let mut aspas = Vec::new();
+        for item in payload {
+            match item {
+                Payload::Origin(origin) => {
+                    origins.push((*origin, info.clone()))
+                }
+                Payload::RouterKey(key) => {
+                    router_keys.push((key.clone(), info.clone()))
+                }
+                Payload::Aspa(aspa) => {
+                    aspas.push((aspa.clone(), info.clone()))
+                }
+            }
+        }
+        Self::new(
+            origins.into_iter(), iter::empty(), router_keys.into_iter(),
+            aspas.into_iter(), Vec::new(), None
+        )
+    }
+
     /// Returns when this snapshot was created.
     pub fn created(&self) -> DateTime<Utc> {
         self.created
@@ -98,6 +142,17 @@ impl PayloadSnapshot {
         self.origins.iter()
     }
 
+    /// Returns an iterator over the route origins marked as unsafe.
+    ///
+    /// These are only collected if the unsafe VRP policy is not `accept`.
+    /// Depending on the policy, they may or may not also be included in
+    /// the route origins of the snapshot.
+    pub fn unsafe_origin_refs(
+        &self
+    ) -> impl Iterator<Item = (&RouteOrigin, &PayloadInfo)> + '_ {
+        self.unsafe_origins.iter()
+    }
+
     /// Returns an iterator over the route origins.
     pub fn origins(
         &self
@@ -140,6 +195,24 @@ impl PayloadSnapshot {
         self.aspas.iter_payload()
     }
 
+    /// Returns the Ghostbuster Records.
+    ///
+    /// The records are ordered by the URI of their CA certificate.
+    pub fn gbrs(&self) -> &[GhostbusterRecord] {
+        &self.gbrs
+    }
+
+    /// Returns the information for the given payload item.
+    ///
+    /// Returns `None` if the item is not part of the snapshot.
+    pub fn info(&self, payload: PayloadRef) -> Option<&PayloadInfo> {
+        match payload {
+            PayloadRef::Origin(origin) => self.origins.find(&origin),
+            PayloadRef::RouterKey(key) => self.router_keys.find(key),
+            PayloadRef::Aspa(aspa) => self.aspas.find(aspa),
+        }
+    }
+
     /// Returns an iterator over the payload of a shared snapshot.
     pub fn arc_iter(self: Arc<Self>) -> SnapshotArcIter {
         SnapshotArcIter::new(self)
@@ -205,6 +278,14 @@ impl<P> PayloadCollection<P> {
         self.vec.get(idx).map(|item| (&item.0, &item.1))
     }
 
+    /// Returns the information for the given item.
+    pub fn find(&self, item: &P) -> Option<&PayloadInfo>
+    where P: Ord {
+        self.vec.binary_search_by(|probe| probe.0.cmp(item)).ok().map(|idx| {
+            &self.vec[idx].1
+        })
+    }
+
     /// Returns an iterator over the payload.
     pub fn iter(&self) -> impl Iterator<Item = (&P, &PayloadInfo)> {
         self.vec.iter().map(|item| (&item.0, &item.1))
@@ -17,6 +17,7 @@ use std::cmp;
 use std::collections::hash_map;
 use std::collections::HashMap;
 use std::sync::Arc;
+use bytes::Bytes;
 use crossbeam_queue::SegQueue;
 use log::{info, warn};
 use rpki::uri;
@@ -32,12 +33,17 @@ use rpki::repository::x509::{Time, Validity};
 use rpki::resources::{Asn, Prefix, SmallAsnSet};
 use rpki::rtr::payload::{Aspa, RouteOrigin, RouterKey};
 use rpki::rtr::pdu::{ProviderAsns, RouterKeyInfo};
+use crate::collector::RefreshTarget;
 use crate::config::{Config, FilterPolicy};
 use crate::engine::{CaCert, Engine, ProcessPubPoint, ProcessRun};
 use crate::error::{Failed, RunFailed};
-use crate::metrics::{Metrics, PayloadMetrics, VrpMetrics};
+use crate::metrics::{
+    Metrics, PayloadMetrics, RedundantOrigin, VrpMetrics
+};
 use crate::slurm::LocalExceptions;
+use super::gbr::GhostbusterRecord;
 use super::info::{PayloadInfo, PublishInfo};
+use super::policy::RoaPolicy;
 use super::snapshot::PayloadSnapshot;
 
 
@@ -75,6 +81,12 @@ pub struct ValidationReport {
 
     /// How are we dealing with unsafe VRPs?
     unsafe_vrps: FilterPolicy,
+
+    /// Should we list redundant route origins individually?
+    overlap_report: bool,
+
+    /// The local policy for route origins.
+    roa_policy: RoaPolicy,
 }
 
 impl ValidationReport {
@@ -89,6 +101,8 @@ impl ValidationReport {
             limit_v4_len: config.limit_v4_len,
             limit_v6_len: config.limit_v6_len,
             unsafe_vrps: config.unsafe_vrps,
+            overlap_report: config.roa_overlap_report,
+            roa_policy: RoaPolicy::new(config),
         }
     }
 
@@ -104,6 +118,21 @@ impl ValidationReport {
         Ok((report, metrics))
     }
 
+    /// Creates a new validation report by running a refresh run.
+    ///
+    /// Only the repository given by `target` is updated. See
+    /// [`Engine::start_refresh`] for details.
+    pub fn process_refresh(
+        engine: &Engine, config: &Config, target: &RefreshTarget,
+    ) -> Result<(Self, Metrics), RunFailed> {
+        let report = Self::new(config);
+        let mut run = engine.start_refresh(&report, target)?;
+        run.process()?;
+        run.cleanup()?;
+        let metrics = run.done();
+        Ok((report, metrics))
+    }
+
     /// Converts the report into a payload snapshot.
     pub fn into_snapshot(
         self,
@@ -112,7 +141,7 @@ impl ValidationReport {
     ) -> PayloadSnapshot {
         let mut builder = SnapshotBuilder::new(
             self.rejected.finalize(), self.unsafe_vrps,
-            exceptions,
+            self.overlap_report, &self.roa_policy, exceptions,
         );
         while let Some(point) = self.pub_points.pop() {
             builder.process_pub_point(point, metrics)
@@ -135,6 +164,7 @@ impl<'a> ProcessRun for &'a ValidationReport {
                 pub_point: PubPoint::new_ta(cert, tal_index),
                 validity: cert.cert().validity(),
                 point_stale: cert.cert().validity().not_after(),
+                published: None,
             }
         ))
     }
@@ -161,6 +191,9 @@ pub struct PubPointProcessor<'a> {
 
     /// When will this publication point become stale.
     point_stale: Time,
+
+    /// When was the validation chain of this point last published.
+    published: Option<Time>,
 }
 
 impl<'a> ProcessPubPoint for PubPointProcessor<'a> {
@@ -177,6 +210,10 @@ impl<'a> ProcessPubPoint for PubPointProcessor<'a> {
         self.point_stale = cmp::min(self.point_stale, stale);
     }
 
+    fn point_published(&mut self, this_update: Time) {
+        self.published = cmp::max(self.published, Some(this_update));
+    }
+
     fn want(&self, _uri: &uri::Rsync) -> Result<bool, Failed> {
         // While we actually only care for some types, we want everything
         // processed for statistics.
@@ -194,6 +231,7 @@ impl<'a> ProcessPubPoint for PubPointProcessor<'a> {
                 point_stale: cmp::min(
                     self.point_stale, cert.cert().validity().not_after()
                 ),
+                published: self.published,
             }
         ))
     }
@@ -213,8 +251,10 @@ impl<'a> ProcessPubPoint for PubPointProcessor<'a> {
             );
             return Ok(())
         }
+        // Only the ASNs held by the issuer are valid. The certificate may
+        // claim more if they are ignored under the reconsidered algorithm.
         let asns = match cert.as_resources().to_blocks() {
-            Ok(blocks) => blocks,
+            Ok(blocks) => blocks.intersection(ca_cert.cert().as_resources()),
             Err(_) => {
                 warn!(
                     "{}: router certificate contains invalid AS resources.",
@@ -245,7 +285,7 @@ impl<'a> ProcessPubPoint for PubPointProcessor<'a> {
             asns, id, key,
             Arc::new(PublishInfo::router_cert(
                 &cert, uri, ca_cert.cert().tal().clone(),
-                self.validity, self.point_stale,
+                self.validity, self.point_stale, self.published,
             )),
         );
         Ok(())
@@ -260,7 +300,7 @@ impl<'a> ProcessPubPoint for PubPointProcessor<'a> {
         if self.pub_point.add_roa(
             route,
             Arc::new(PublishInfo::signed_object(
-                &cert, self.validity, self.point_stale
+                &cert, self.validity, self.point_stale, self.published
             )),
             self.report.limit_v4_len, self.report.limit_v6_len,
         ) {
@@ -282,12 +322,25 @@ impl<'a> ProcessPubPoint for PubPointProcessor<'a> {
         self.pub_point.add_aspa(
             aspa,
             Arc::new(PublishInfo::signed_object(
-                &cert, self.validity, self.point_stale
+                &cert, self.validity, self.point_stale, self.published
             ))
         );
         Ok(())
     }
 
+    fn process_gbr(
+        &mut self,
+        uri: &uri::Rsync,
+        cert: ResourceCert,
+        content: Bytes
+    ) -> Result<(), Failed> {
+        match GhostbusterRecord::new(uri, &cert, content.as_ref()) {
+            Some(gbr) => self.pub_point.gbrs.push(gbr),
+            None => warn!("{}: GBR does not contain a vCard.", uri),
+        }
+        Ok(())
+    }
+
     fn restart(&mut self) -> Result<(), Failed> {
         self.pub_point.restart();
         Ok(())
@@ -337,6 +390,15 @@ pub struct PubPoint {
     /// The list of valid ASPA payload.
     aspas: Vec<PubAspa>,
 
+    /// The list of valid Ghostbuster Records.
+    gbrs: Vec<GhostbusterRecord>,
+
+    /// The number of IPv4 route origins dropped for their prefix length.
+    length_filtered_v4: u32,
+
+    /// The number of IPv6 route origins dropped for their prefix length.
+    length_filtered_v6: u32,
+
     /// The time when the publication point needs to be refreshed.
     refresh: Time,
 
@@ -359,6 +421,9 @@ impl PubPoint {
             origins: Vec::new(),
             router_keys: Vec::new(),
             aspas: Vec::new(),
+            gbrs: Vec::new(),
+            length_filtered_v4: 0,
+            length_filtered_v6: 0,
             refresh,
             orig_refresh: refresh,
             tal_index,
@@ -386,6 +451,9 @@ impl PubPoint {
         self.origins.is_empty()
         && self.router_keys.is_empty()
         && self.aspas.is_empty()
+        && self.gbrs.is_empty()
+        && self.length_filtered_v4 == 0
+        && self.length_filtered_v6 == 0
     }
 
     /// Updates the refresh time to be no later than the given time.
@@ -398,10 +466,17 @@ impl PubPoint {
         self.origins.clear();
         self.router_keys.clear();
         self.aspas.clear();
+        self.gbrs.clear();
+        self.length_filtered_v4 = 0;
+        self.length_filtered_v6 = 0;
         self.refresh = self.orig_refresh;
     }
 
     /// Adds the content of a ROA to the payload.
+    ///
+    /// Route origins with prefixes longer than the limit for their address
+    /// family are dropped but counted. Returns whether any route origin was
+    /// added.
     fn add_roa(
         &mut self,
         roa: RouteOriginAttestation,
@@ -411,14 +486,16 @@ impl PubPoint {
     ) -> bool {
         let mut any = false;
         for origin in roa.iter_origins() {
-            let limit = if origin.prefix.prefix().is_v4() {
-                limit_v4_len
-            }
-            else {
-                limit_v6_len
-            };
+            let v4 = origin.prefix.prefix().is_v4();
+            let limit = if v4 { limit_v4_len } else { limit_v6_len };
             if let Some(limit) = limit {
                 if origin.prefix.prefix().len() > limit {
+                    if v4 {
+                        self.length_filtered_v4 += 1;
+                    }
+                    else {
+                        self.length_filtered_v6 += 1;
+                    }
                     continue;
                 }
             }
@@ -590,6 +667,9 @@ struct SnapshotBuilder<'a> {
     /// The collected route origins.
     origins: HashMap<RouteOrigin, PayloadInfo>,
 
+    /// The route origins marked as unsafe.
+    unsafe_origins: HashMap<RouteOrigin, PayloadInfo>,
+
     /// The collected router keys.
     router_keys: HashMap<RouterKey, PayloadInfo>,
 
@@ -598,6 +678,9 @@ struct SnapshotBuilder<'a> {
     /// The key is the customer ASN.
     aspas: HashMap<Asn, (SmallAsnSet, PayloadInfo)>,
 
+    /// The collected Ghostbuster Records.
+    gbrs: Vec<GhostbusterRecord>,
+
     /// The list of rejected resources.
     rejected: RejectedResources,
 
@@ -607,6 +690,12 @@ struct SnapshotBuilder<'a> {
     /// Are unsafe VRPs present?
     unsafe_vrps_present: bool,
 
+    /// Should we list redundant route origins individually?
+    overlap_report: bool,
+
+    /// The local policy for route origins.
+    roa_policy: &'a RoaPolicy,
+
     /// The time when this snapshot needs to be refreshed at the latest.
     refresh: Option<Time>,
 
@@ -617,16 +706,21 @@ impl<'a> SnapshotBuilder<'a> {
     fn new(
         rejected: RejectedResources,
         unsafe_vrps: FilterPolicy,
+        overlap_report: bool,
+        roa_policy: &'a RoaPolicy,
         exceptions: &'a LocalExceptions,
-        
     ) -> Self {
         Self {
             origins: Default::default(),
+            unsafe_origins: Default::default(),
             router_keys: Default::default(),
             aspas: Default::default(),
+            gbrs: Vec::new(),
             rejected,
             unsafe_vrps,
             unsafe_vrps_present: false,
+            overlap_report,
+            roa_policy,
             refresh: None,
             exceptions,
         }
@@ -640,6 +734,14 @@ impl<'a> SnapshotBuilder<'a> {
             metrics, point.tal_index, point.repository_index,
         );
         self.update_refresh(point.refresh);
+        metrics.update_origin(true, |m| {
+            m.valid += point.length_filtered_v4;
+            m.length_filtered += point.length_filtered_v4;
+        });
+        metrics.update_origin(false, |m| {
+            m.valid += point.length_filtered_v6;
+            m.length_filtered += point.length_filtered_v6;
+        });
         point.origins.into_iter().for_each(|item| {
             self.process_origin(item, &mut metrics)
         });
@@ -649,6 +751,7 @@ impl<'a> SnapshotBuilder<'a> {
         point.aspas.into_iter().for_each(|item| {
             self.process_aspa(item, &mut metrics)
         });
+        self.gbrs.extend(point.gbrs);
     }
 
     /// Updates the refresh time.
@@ -668,6 +771,16 @@ impl<'a> SnapshotBuilder<'a> {
         // Is the prefix in the rejected resources?
         if !self.rejected.keep_prefix(origin.origin.prefix.prefix()) {
             self.unsafe_vrps_present = true;
+            if self.unsafe_vrps.log() {
+                match self.unsafe_origins.entry(origin.origin) {
+                    hash_map::Entry::Vacant(entry) => {
+                        entry.insert(origin.info.clone().into());
+                    }
+                    hash_map::Entry::Occupied(mut entry) => {
+                        entry.get_mut().add_published(origin.info.clone());
+                    }
+                }
+            }
             match self.unsafe_vrps {
                 FilterPolicy::Accept => {
                     // Don’t count, don’t warn ...
@@ -698,6 +811,33 @@ impl<'a> SnapshotBuilder<'a> {
             }
         }
 
+        // Does the origin match the local policy?
+        match self.roa_policy.check(origin.origin) {
+            Some((rule, FilterPolicy::Reject)) => {
+                metrics.update_origin(v4, |m| m.policy_filtered += 1);
+                info!(
+                    "Filtering VRP ({}/{}-{}, {}) due to ROA policy \
+                     rule '{}'",
+                    origin.origin.prefix.addr(),
+                    origin.origin.prefix.prefix_len(),
+                    origin.origin.prefix.resolved_max_len(),
+                    origin.origin.asn, rule
+                );
+                return
+            }
+            Some((rule, _)) => {
+                metrics.update_origin(v4, |m| m.policy_flagged += 1);
+                warn!(
+                    "VRP ({}/{}-{}, {}) matches ROA policy rule '{}'",
+                    origin.origin.prefix.addr(),
+                    origin.origin.prefix.prefix_len(),
+                    origin.origin.prefix.resolved_max_len(),
+                    origin.origin.asn, rule
+                );
+            }
+            None => { }
+        }
+
         // Is the origin to be filtered locally?
         if self.exceptions.drop_origin(origin.origin) {
             metrics.update_origin(v4, |m| m.locally_filtered += 1);
@@ -778,11 +918,69 @@ impl<'a> SnapshotBuilder<'a> {
             );
         }
 
+        self.find_overlaps(metrics);
         self.insert_assertions(metrics);
         metrics.finalize();
         self.into_snapshot()
     }
 
+    /// Determines duplicate and shadowed route origins.
+    ///
+    /// Sorted by AS, address, and prefix length, each route origin follows
+    /// all route origins of the same AS that cover its prefix. We keep
+    /// these on a stack, each together with the route origin with the
+    /// largest maximum length among them so far. A route origin is shadowed
+    /// if this maximum length is at least its own.
+    fn find_overlaps(&self, metrics: &mut Metrics) {
+        let mut origins: Vec<_> = self.origins.iter().collect();
+        origins.sort_unstable_by_key(|(origin, _)| (
+            origin.asn,
+            origin.prefix.addr(),
+            origin.prefix.prefix_len(),
+            cmp::Reverse(origin.prefix.resolved_max_len()),
+        ));
+
+        let mut stack: Vec<(&RouteOrigin, &RouteOrigin)> = Vec::new();
+        for (origin, info) in origins {
+            while let Some((top, _)) = stack.last() {
+                if top.asn == origin.asn
+                    && top.prefix.prefix().covers(origin.prefix.prefix())
+                {
+                    break
+                }
+                stack.pop();
+            }
+            let max_len = origin.prefix.resolved_max_len();
+            let shadowed_by = stack.last().map(|item| item.1).filter(|top| {
+                top.prefix.resolved_max_len() >= max_len
+            });
+            stack.push((origin, shadowed_by.unwrap_or(origin)));
+
+            let mut roas: Vec<_> = info.iter().filter_map(|item| {
+                item.uri()
+            }).collect();
+            roas.sort_unstable_by(|left, right| {
+                left.as_str().cmp(right.as_str())
+            });
+            roas.dedup();
+            let duplicate = roas.len() > 1;
+
+            if duplicate {
+                metrics.overlaps.duplicate += 1;
+            }
+            if shadowed_by.is_some() {
+                metrics.overlaps.shadowed += 1;
+            }
+            if self.overlap_report && (duplicate || shadowed_by.is_some()) {
+                metrics.overlaps.report.push(RedundantOrigin {
+                    origin: *origin,
+                    roas: roas.iter().map(|uri| uri.to_string()).collect(),
+                    shadowed_by: shadowed_by.copied(),
+                })
+            }
+        }
+    }
+
     fn insert_assertions(&mut self, metrics: &mut Metrics) {
         for (origin, info) in self.exceptions.origin_assertions() {
             match self.origins.entry(origin) {
@@ -829,9 +1027,17 @@ impl<'a> SnapshotBuilder<'a> {
         // XXX ASPA assertions.
     }
 
-    fn into_snapshot(self) -> PayloadSnapshot {
+    fn into_snapshot(mut self) -> PayloadSnapshot {
+        self.gbrs.sort_by(|left, right| {
+            (
+                left.ca.as_ref().map(uri::Rsync::as_str), left.uri.as_str()
+            ).cmp(&(
+                right.ca.as_ref().map(uri::Rsync::as_str), right.uri.as_str()
+            ))
+        });
         PayloadSnapshot::new(
             self.origins.into_iter(),
+            self.unsafe_origins.into_iter(),
             self.router_keys.into_iter(),
             self.aspas.into_iter().filter_map(
                 |(customer, (providers, info))| {
@@ -850,6 +1056,7 @@ impl<'a> SnapshotBuilder<'a> {
                     }
                 }
             ),
+            self.gbrs,
             self.refresh,
         )
     }
@@ -1,6 +1,6 @@
 //! Managing the process Routinator runs in.
 
-use std::{fs, io, mem, process};
+use std::{fmt, fs, io, mem, process};
 use std::future::Future;
 use std::io::Write;
 use std::net::TcpListener;
@@ -13,6 +13,7 @@ use log::{error, LevelFilter};
 use tokio::runtime::Runtime;
 use crate::config::{Config, LogTarget};
 use crate::error::Failed;
+use crate::metrics::RunId;
 use crate::utils::date::{format_iso_date, format_local_iso_date};
 use crate::utils::fmt::WriteOrPanic;
 use crate::utils::sync::{Mutex, RwLock};
@@ -76,6 +77,15 @@ impl Process {
         Ok(())
     }
 
+    /// Sets the identifier of the current validation run.
+    ///
+    /// The identifier is included in all log messages until the returned
+    /// guard is dropped.
+    pub fn enter_run(run_id: RunId) -> RunLogGuard {
+        *CURRENT_RUN_ID.write() = Some(run_id);
+        RunLogGuard(())
+    }
+
     /// Switches logging to the configured target.
     ///
     /// Once the configuration has been successfully loaded, logging should
@@ -304,26 +314,33 @@ impl Logger {
             return;
         }
 
+        let message = LogMessage {
+            run_id: *CURRENT_RUN_ID.read(),
+            args: record.args(),
+        };
+
         if let Some(output) = self.output.as_ref() {
-            writeln!(output.lock(), "{}", record.args());
+            writeln!(output.lock(), "{}", message);
         }
 
-        if let Err(err) = self.try_log(record) {
+        if let Err(err) = self.try_log(record.level(), &message) {
             self.log_failure(err);
         }
     }
 
     /// Tries logging a message and returns an error if there is one.
-    fn try_log(&self, record: &log::Record) -> Result<(), io::Error> {
+    fn try_log(
+        &self, level: log::Level, message: &LogMessage
+    ) -> Result<(), io::Error> {
         match self.target.lock().deref_mut() {
             #[cfg(unix)]
-            LogBackend::Syslog(ref mut logger) => logger.log(record),
+            LogBackend::Syslog(ref mut logger) => logger.log(level, message),
             LogBackend::File { ref mut file, .. } => {
                 writeln!(
                     file, "[{}] [{}] {}",
                     format_local_iso_date(chrono::Local::now()),
-                    record.level(),
-                    record.args()
+                    level,
+                    message
                 )
             }
             LogBackend::Stderr{ ref mut stderr, timestamp } => {
@@ -334,7 +351,7 @@ impl Logger {
                     );
                 }
                 let _ = writeln!(
-                    stderr, "[{}] {}", record.level(), record.args()
+                    stderr, "[{}] {}", level, message
                 );
                 Ok(())
             }
@@ -438,6 +455,49 @@ impl Logger {
 }
 
 
+//------------ LogMessage ----------------------------------------------------
+
+/// The identifier of the validation run currently in progress.
+static CURRENT_RUN_ID: RwLock<Option<RunId>> = RwLock::new(None);
+
+/// A log message prepared for output.
+///
+/// If a validation run is in progress, the message is prefixed with the
+/// identifier of the run.
+struct LogMessage<'a> {
+    /// The identifier of the current validation run, if any.
+    run_id: Option<RunId>,
+
+    /// The actual message.
+    args: &'a fmt::Arguments<'a>,
+}
+
+impl<'a> fmt::Display for LogMessage<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.run_id {
+            Some(run_id) => write!(f, "[run {}] {}", run_id, self.args),
+            None => self.args.fmt(f),
+        }
+    }
+}
+
+
+//------------ RunLogGuard ---------------------------------------------------
+
+/// Including a validation run’s identifier in log messages.
+///
+/// A value is returned by [`Process::enter_run`]. The identifier will be
+/// removed from log messages again when it is dropped.
+#[derive(Debug)]
+pub struct RunLogGuard(());
+
+impl Drop for RunLogGuard {
+    fn drop(&mut self) {
+        *CURRENT_RUN_ID.write() = None;
+    }
+}
+
+
 //------------ SyslogLogger --------------------------------------------------
 
 /// A syslog logger.
@@ -480,15 +540,17 @@ impl SyslogLogger {
     }
 
     /// Tries logging.
-    fn log(&mut self, record: &log::Record) -> Result<(), io::Error> {
-        match record.level() {
-            log::Level::Error => self.0.err(record.args()),
-            log::Level::Warn => self.0.warning(record.args()),
-            log::Level::Info => self.0.info(record.args()),
-            log::Level::Debug => self.0.debug(record.args()),
+    fn log(
+        &mut self, level: log::Level, message: &LogMessage
+    ) -> Result<(), io::Error> {
+        match level {
+            log::Level::Error => self.0.err(message),
+            log::Level::Warn => self.0.warning(message),
+            log::Level::Info => self.0.info(message),
+            log::Level::Debug => self.0.debug(message),
             log::Level::Trace => {
                 // Syslog doesn’t have trace, use debug instead.
-                self.0.debug(record.args())
+                self.0.debug(message)
             }
         }.map_err(|err| {
             match err.0 {
@@ -1,6 +1,7 @@
 /// Support for the RPKI-to-Router Protocol.
 
 use std::io;
+use std::collections::HashSet;
 use std::future::Future;
 use std::net::{SocketAddr, TcpListener as StdListener};
 use std::pin::Pin;
@@ -9,16 +10,20 @@ use std::task::{Context, Poll};
 use std::time::Duration;
 use futures::{pin_mut, Stream};
 use futures::future::{pending, select_all};
-use log::error;
+use log::{error, info, warn};
+use rpki::rtr::client::{Client, PayloadError, PayloadTarget};
+use rpki::rtr::payload::{Action, Payload, Timing};
 use rpki::rtr::server::{NotifySender, Server, Socket};
 use rpki::rtr::state::State;
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 use tokio::net::{TcpListener, TcpStream};
-use tokio_rustls::TlsAcceptor;
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+use tokio_rustls::rustls::pki_types::ServerName;
 use crate::config::Config;
 use crate::error::ExitError;
-use crate::metrics::{SharedRtrServerMetrics, RtrClientMetrics};
-use crate::payload::SharedHistory;
+use crate::metrics::{Metrics, SharedRtrServerMetrics, RtrClientMetrics};
+use crate::payload::{PayloadInfo, PayloadSnapshot, SharedHistory};
+use crate::slurm::ExceptionInfo;
 use crate::utils::{net, tls};
 use crate::utils::tls::MaybeTlsTcpStream;
 
@@ -60,8 +65,8 @@ pub fn rtr_listener(
 fn create_tls_config(
     config: &Config
 ) -> Result<Arc<tls::ServerConfig>, ExitError> {
-    let key_path = match config.rtr_tls_key.as_ref() {
-        Some(path) => path.as_ref(),
+    let key = match config.rtr_tls_key.as_ref() {
+        Some(key) => key,
         None => {
             error!("Missing rtr-tls-key option for RTR TLS server.");
             return Err(ExitError::Generic)
@@ -74,7 +79,7 @@ fn create_tls_config(
             return Err(ExitError::Generic)
         }
     };
-    tls::create_server_config("RTR", key_path, cert_path).map(Arc::new)
+    tls::create_server_config("RTR", key, cert_path).map(Arc::new)
 }
 
 async fn _rtr_listener(
@@ -128,6 +133,199 @@ async fn single_rtr_listener(
 }
 
 
+//------------ rtr_upstream --------------------------------------------------
+
+/// The time to wait before reconnecting to the upstream server.
+const UPSTREAM_RETRY: Duration = Duration::from_secs(10);
+
+/// Returns a future receiving payload from an upstream RTR server.
+///
+/// The future connects to the RTR server at `addr`, which normally is
+/// another Routinator instance performing the actual validation, and
+/// updates `history` with all payload received, sending out notifications
+/// via `sender` whenever the payload changes. If `tls` is present, the
+/// connection uses TLS with the given client config. If the connection
+/// fails or is closed, it reconnects after a short while. The future only
+/// returns if the server name in `addr` cannot be used for TLS.
+pub async fn rtr_upstream(
+    addr: String,
+    tls: Option<Arc<tls::ClientConfig>>,
+    history: SharedHistory,
+    sender: NotifySender,
+) {
+    let tls = match tls {
+        Some(config) => {
+            match upstream_server_name(&addr) {
+                Some(name) => Some((TlsConnector::from(config), name)),
+                None => {
+                    error!(
+                        "Invalid server name in upstream RTR server {}.",
+                        addr
+                    );
+                    return
+                }
+            }
+        }
+        None => None
+    };
+    let mut target = UpstreamTarget::new(&addr, history, sender);
+    let mut state = None;
+    loop {
+        match TcpStream::connect(&addr).await {
+            Ok(sock) => {
+                match tls {
+                    Some((ref connector, ref name)) => {
+                        match connector.connect(name.clone(), sock).await {
+                            Ok(sock) => {
+                                (target, state) = run_upstream(
+                                    &addr, sock, target, state
+                                ).await;
+                            }
+                            Err(err) => {
+                                warn!(
+                                    "TLS handshake with upstream RTR \
+                                     server {} failed: {}",
+                                    addr, err
+                                );
+                            }
+                        }
+                    }
+                    None => {
+                        (target, state) = run_upstream(
+                            &addr, sock, target, state
+                        ).await;
+                    }
+                }
+            }
+            Err(err) => {
+                warn!(
+                    "Failed to connect to upstream RTR server {}: {}",
+                    addr, err
+                );
+            }
+        }
+        tokio::time::sleep(UPSTREAM_RETRY).await;
+    }
+}
+
+/// Runs an RTR client on an established upstream connection.
+///
+/// Returns the target and client state for use with the next connection.
+async fn run_upstream<Sock: AsyncRead + AsyncWrite + Unpin>(
+    addr: &str,
+    sock: Sock,
+    target: UpstreamTarget,
+    state: Option<State>,
+) -> (UpstreamTarget, Option<State>) {
+    info!("Connected to upstream RTR server {}.", addr);
+    let mut client = Client::new(sock, target, state);
+    match client.run().await {
+        Ok(()) => {
+            warn!(
+                "Upstream RTR server {} closed the connection.",
+                addr
+            );
+        }
+        Err(err) => {
+            warn!(
+                "Connection to upstream RTR server {} failed: {}",
+                addr, err
+            );
+        }
+    }
+    let state = client.state();
+    (client.into_target(), state)
+}
+
+/// Returns the TLS server name for an upstream address.
+///
+/// The address is expected in the form `host:port` with IPv6 addresses
+/// enclosed in square brackets.
+fn upstream_server_name(addr: &str) -> Option<ServerName<'static>> {
+    let (host, _) = addr.rsplit_once(':')?;
+    let host = host.strip_prefix('[').and_then(|host| {
+        host.strip_suffix(']')
+    }).unwrap_or(host);
+    ServerName::try_from(host.to_owned()).ok()
+}
+
+
+//------------ UpstreamTarget ------------------------------------------------
+
+/// The target for payload received from an upstream RTR server.
+struct UpstreamTarget {
+    /// The current set of payload.
+    payload: HashSet<Payload>,
+
+    /// Whether the update currently in progress is a reset.
+    reset: bool,
+
+    /// The information attached to all received payload.
+    info: PayloadInfo,
+
+    /// The history to update.
+    history: SharedHistory,
+
+    /// The sender for notifying our own RTR clients.
+    sender: NotifySender,
+}
+
+impl UpstreamTarget {
+    fn new(addr: &str, history: SharedHistory, sender: NotifySender) -> Self {
+        UpstreamTarget {
+            payload: HashSet::new(),
+            reset: false,
+            info: PayloadInfo::from(Arc::new(ExceptionInfo {
+                path: None,
+                comment: Some(format!("received from upstream {}", addr)),
+            })),
+            history,
+            sender,
+        }
+    }
+}
+
+impl PayloadTarget for UpstreamTarget {
+    type Update = Vec<(Action, Payload)>;
+
+    fn start(&mut self, reset: bool) -> Self::Update {
+        self.reset = reset;
+        Vec::new()
+    }
+
+    fn apply(
+        &mut self, update: Self::Update, _timing: Timing
+    ) -> Result<(), PayloadError> {
+        if self.reset {
+            self.payload.clear();
+        }
+        for (action, payload) in update {
+            match action {
+                Action::Announce => {
+                    self.payload.insert(payload);
+                }
+                Action::Withdraw => {
+                    self.payload.remove(&payload);
+                }
+            }
+        }
+        self.history.mark_update_start();
+        let snapshot = PayloadSnapshot::from_payload(
+            self.payload.iter(), &self.info
+        );
+        if self.history.update_snapshot(snapshot, Metrics::new()) {
+            info!(
+                "Received new payload from upstream, new serial is {}.",
+                self.history.read().serial()
+            );
+            self.sender.notify();
+        }
+        self.history.mark_update_done();
+        Ok(())
+    }
+}
+
+
 //------------ RtrListener --------------------------------------------------
 
 /// A wrapper around an TCP listener that produces RTR streams.
@@ -0,0 +1,256 @@
+//! A self-test using bundled fixture data.
+//!
+//! The self-test runs the complete validation pipeline against a tiny RPKI
+//! repository that is bundled with Routinator. It allows checking whether a
+//! build of Routinator works correctly on a given platform without having
+//! to access the real RPKI.
+//!
+//! The bundled repository consists of a trust anchor with one child CA and
+//! a handful of ROAs. All objects are valid until the end of 2099. The
+//! source data lives in `test/self-test` in the source tree.
+//!
+//! The test is performed in a temporary directory in two passes. The first
+//! pass uses the collector in offline mode, i.e., the bundled data is placed
+//! into the rsync collector’s working directory and used as is. The second
+//! pass uses the store only and thus checks that the data has been correctly
+//! stored by the first pass. After each pass, the resulting payload is
+//! compared with the expected VRPs and output in all the output formats.
+
+use std::sync::Arc;
+use log::{error, info};
+use tempfile::TempDir;
+use crate::config::Config;
+use crate::engine::Engine;
+use crate::error::Failed;
+use crate::output::{Output, OutputFormat};
+use crate::payload::ValidationReport;
+use crate::slurm::LocalExceptions;
+use crate::utils::fatal;
+
+
+//------------ Fixture Data --------------------------------------------------
+
+/// The name of the TAL file for the bundled trust anchor.
+///
+/// This is also the name of the trust anchor used in output.
+const TAL_NAME: &str = "self-test";
+
+/// The content of the TAL file for the bundled trust anchor.
+const TAL: &[u8] = include_bytes!("../test/self-test/self-test.tal");
+
+/// The bundled repository data.
+///
+/// The first element of each pair is the path of the object within the
+/// rsync collector’s working directory, the second its content.
+const REPOSITORY: &[(&str, &[u8])] = &[
+    (
+        "rpki.selftest.invalid/repo/ta.cer",
+        include_bytes!(
+            "../test/self-test/repository/rpki.selftest.invalid/repo/ta.cer"
+        )
+    ),
+    (
+        "rpki.selftest.invalid/repo/ta/ta.mft",
+        include_bytes!(
+            "../test/self-test/repository/rpki.selftest.invalid/repo/\
+             ta/ta.mft"
+        )
+    ),
+    (
+        "rpki.selftest.invalid/repo/ta/ta.crl",
+        include_bytes!(
+            "../test/self-test/repository/rpki.selftest.invalid/repo/\
+             ta/ta.crl"
+        )
+    ),
+    (
+        "rpki.selftest.invalid/repo/ta/child.cer",
+        include_bytes!(
+            "../test/self-test/repository/rpki.selftest.invalid/repo/\
+             ta/child.cer"
+        )
+    ),
+    (
+        "rpki.selftest.invalid/repo/ta/as64496.roa",
+        include_bytes!(
+            "../test/self-test/repository/rpki.selftest.invalid/repo/\
+             ta/as64496.roa"
+        )
+    ),
+    (
+        "rpki.selftest.invalid/repo/ta/as64497.roa",
+        include_bytes!(
+            "../test/self-test/repository/rpki.selftest.invalid/repo/\
+             ta/as64497.roa"
+        )
+    ),
+    (
+        "rpki.selftest.invalid/repo/child/child.mft",
+        include_bytes!(
+            "../test/self-test/repository/rpki.selftest.invalid/repo/\
+             child/child.mft"
+        )
+    ),
+    (
+        "rpki.selftest.invalid/repo/child/child.crl",
+        include_bytes!(
+            "../test/self-test/repository/rpki.selftest.invalid/repo/\
+             child/child.crl"
+        )
+    ),
+    (
+        "rpki.selftest.invalid/repo/child/as64498.roa",
+        include_bytes!(
+            "../test/self-test/repository/rpki.selftest.invalid/repo/\
+             child/as64498.roa"
+        )
+    ),
+];
+
+/// The expected VRPs in CSV output format.
+const EXPECTED_CSV: &str = include_str!("../test/self-test/expected.csv");
+
+
+//------------ SelfTest ------------------------------------------------------
+
+/// A self-test run.
+///
+/// The self-test is prepared via [`new`][Self::new] which creates a
+/// temporary directory and a suitable configuration. It is then performed
+/// via [`run`][Self::run]. The temporary directory is removed when the
+/// value is dropped.
+pub struct SelfTest {
+    /// The temporary directory holding all the data.
+    base: TempDir,
+
+    /// The configuration to use for the test.
+    config: Config,
+}
+
+impl SelfTest {
+    /// Prepares a new self-test.
+    ///
+    /// The configuration for the test is derived from `config`. All paths
+    /// and the trust anchors are replaced, as is anything that affects the
+    /// output, but the remaining configuration is kept. This way, the test
+    /// is performed with the same settings as regular runs.
+    pub fn new(config: &Config) -> Result<Self, Failed> {
+        let base = match tempfile::tempdir() {
+            Ok(base) => base,
+            Err(err) => {
+                error!("Failed to create temporary directory: {}", err);
+                return Err(Failed)
+            }
+        };
+        let mut config = config.clone();
+        config.cache_dir = base.path().join("repository");
+        config.no_rir_tals = true;
+        config.bundled_tals = Vec::new();
+        config.extra_tals_dir = Some(base.path().join("tals"));
+        config.tal_labels = Default::default();
+        config.exceptions = Vec::new();
+        config.fresh = false;
+        config.store_readonly = false;
+        Ok(SelfTest { base, config })
+    }
+
+    /// Performs the self-test.
+    ///
+    /// Returns an error if the test fails. The reason for the failure will
+    /// have been logged.
+    pub fn run(&self) -> Result<(), Failed> {
+        self.install_fixtures()?;
+
+        info!("Self-test: validating with the collector in offline mode.");
+        let mut engine = Engine::new_offline(&self.config)?;
+        engine.ignite()?;
+        self.check_pass(&engine, "collector")?;
+
+        info!("Self-test: validating from the store.");
+        let mut engine = Engine::new(&self.config, false)?;
+        engine.ignite()?;
+        self.check_pass(&engine, "store")?;
+
+        Ok(())
+    }
+
+    /// Places the fixture data into the temporary directory.
+    fn install_fixtures(&self) -> Result<(), Failed> {
+        let tal_dir = self.base.path().join("tals");
+        fatal::create_dir_all(&tal_dir)?;
+        fatal::write_file(&tal_dir.join(format!("{}.tal", TAL_NAME)), TAL)?;
+
+        let rsync_dir = self.config.cache_dir.join("rsync");
+        for (path, content) in REPOSITORY {
+            let path = rsync_dir.join(path);
+            if let Some(dir) = path.parent() {
+                fatal::create_dir_all(dir)?;
+            }
+            fatal::write_file(&path, content)?;
+        }
+        Ok(())
+    }
+
+    /// Performs one validation pass and checks the result.
+    fn check_pass(&self, engine: &Engine, pass: &str) -> Result<(), Failed> {
+        let (report, mut metrics) = match ValidationReport::process(
+            engine, &self.config
+        ) {
+            Ok(res) => res,
+            Err(_) => {
+                error!("Self-test failed: validation run using {} failed.",
+                    pass
+                );
+                return Err(Failed)
+            }
+        };
+        let snapshot = Arc::new(
+            report.into_snapshot(&LocalExceptions::empty(), &mut metrics)
+        );
+        let metrics = Arc::new(metrics);
+
+        for (name, format) in OutputFormat::iter() {
+            let mut target = Vec::new();
+            if let Err(err) = Output::new().write(
+                snapshot.clone(), metrics.clone(), format, &mut target
+            ) {
+                error!(
+                    "Self-test failed: producing {} output using {} \
+                     failed: {}",
+                    name, pass, err
+                );
+                return Err(Failed)
+            }
+            if !matches!(format, OutputFormat::Csv) {
+                continue
+            }
+            if target != EXPECTED_CSV.as_bytes() {
+                error!(
+                    "Self-test failed: unexpected VRPs using {}.\n\
+                     Expected:\n{}\nReceived:\n{}",
+                    pass, EXPECTED_CSV, String::from_utf8_lossy(&target)
+                );
+                return Err(Failed)
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bundled_self_test() {
+        let _ = crate::process::Process::init(); // May be inited already.
+        let config = Config::default_with_paths(
+            Default::default(), Default::default()
+        );
+        SelfTest::new(&config).unwrap().run().unwrap();
+    }
+}
@@ -14,6 +14,12 @@
 //! and [`pub_point`][Run::pub_point], respectively. These are represented
 //! by the [`Repository`] and [`StoredPoint`] types.
 //!
+//! All publication points currently stored can be iterated over using
+//! [`Store::iter_points`]. This is intended for tooling that wants to
+//! analyze the stored data without knowing about its layout on disk. The
+//! publication point an individual object is stored in can be found via
+//! [`Store::whereis`].
+//!
 //! # Error Handling
 //!
 //! Pretty much all methods and functions provided by this module can return
@@ -57,21 +63,87 @@
 //! level is the authority (i.e., hostname), and the third is the SHA-256
 //! hash of the full URI with an extension of `.cer` added.
 //!
-//! Finally, the `tmp` directory is used to build the publication point files
+//! During a key rollover of a trust anchor, the certificate with the key
+//! not currently in use is kept under `ta/rollover` using the same
+//! structure. This way, both certificates are available until the
+//! rollover is complete and the old certificate expires.
+//!
+//! The `tmp` directory is used to build the publication point files
 //! in so they can be constructed without yet knowing whether the update is
 //! actually complete and correct. File names here are named using eight
 //! random hex-digits.
+//!
+//! Publication points can be pinned by listing their manifest URI in the
+//! file `pinned` directly in the store directory, one URI per line. Cleanup
+//! keeps pinned points even if their manifest has expired. This is intended
+//! for preserving data for later investigation.
+//!
+//! Finally, stored publication point files that turn out to be corrupt are
+//! moved to the `quarantine` directory rather than failing the validation
+//! run. They are placed at the same relative path they had in the store
+//! and are accompanied by a file with an additional `.reason` extension
+//! that contains the time and reason for the move. Cleanup deletes
+//! quarantined files once their reason file is older than 30 days. The
+//! publication point itself is treated as if it had never been stored.
+//!
+//! The `index` directory maps object URIs to the publication point they
+//! are stored in. For each CA repository (the rsync directory a publication
+//! point publishes into), there is a directory derived from its URI the
+//! same way as for trust anchor certificates. It contains one file per
+//! publication point publishing into this directory, named after the
+//! point’s manifest URI, that contains the path of the point’s file
+//! relative to the store directory. These files are written by the writer
+//! whenever it opens or updates a point and are removed during cleanup
+//! once the point they refer to is gone.
+//!
+//! # Read-only Mode
+//!
+//! If the `store_readonly` configuration option is set, the store will never
+//! modify any data on disk. The base directory is not created if it is
+//! missing, cleanup is skipped, and any attempt at updating a trust anchor
+//! certificate or a publication point results in an error.
+//!
+//! # Shared Stores
+//!
+//! If the `store_shared` configuration option is set, the store can be
+//! shared between several Routinator processes using the same repository
+//! directory. Access is coordinated through advisory file locks on files
+//! placed directly in the store directory.
+//!
+//! At the start of each validation run, the processes hold an election for
+//! the writer of the run: whoever manages to acquire an exclusive lock on
+//! the file `writer.lock` becomes the writer and holds the lock until the
+//! end of the run. Only the writer collects new data and updates the store.
+//! All other processes perform their run as readers using only the data
+//! already present in the store. Since the election is repeated for every
+//! run, another process takes over if the writer goes away.
+//!
+//! Updates to publication points are atomic as the new file is built in
+//! the `tmp` directory and then moved into place. Readers are therefore
+//! only affected by data being deleted during cleanup. To prevent this,
+//! they hold a shared lock on the file `readers.lock` for the duration of
+//! their run. The writer only performs cleanup if it can acquire an
+//! exclusive lock on this file without waiting and skips it otherwise.
+//!
+//! Finally, the file `generation` contains a counter that is incremented by
+//! the writer at the end of every run. Readers can use it to determine
+//! whether the data in the store has changed since their last run.
 
-use std::{fs, io};
+use std::{fmt, fs, io, thread};
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
 use std::fs::File;
-use std::io::{Seek, SeekFrom, Write};
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
 use bytes::Bytes;
+use chrono::Utc;
 use log::{debug, error, info, warn};
 use rand::random;
-use rpki::crypto::digest::DigestAlgorithm;
+use ring::digest;
 use rpki::repository::cert::{Cert, ResourceCert};
-use rpki::repository::manifest::{ManifestContent, ManifestHash};
+use rpki::repository::manifest::ManifestContent;
 use rpki::repository::tal::TalUri;
 use rpki::repository::x509::{Serial, Time};
 use rpki::uri;
@@ -81,9 +153,12 @@ use crate::engine::CaCert;
 use crate::error::{Failed, Fatal, RunFailed};
 use crate::metrics::Metrics;
 use crate::utils::fatal;
+use crate::utils::flock::FileLock;
 use crate::utils::binio::{Compose, Parse, ParseError};
 use crate::utils::dump::DumpRegistry;
+use crate::utils::hash::{HashAlgorithm, ObjectHash};
 use crate::utils::json::JsonBuilder;
+use crate::utils::sync::Mutex;
 use crate::utils::uri::UriExt;
 
 
@@ -108,6 +183,12 @@ use crate::utils::uri::UriExt;
 pub struct Store {
     /// The base path for the store.
     path: PathBuf,
+
+    /// Is the store read-only?
+    readonly: bool,
+
+    /// Is the store shared with other processes?
+    shared: bool,
 }
 
 impl Store {
@@ -116,6 +197,17 @@ impl Store {
         // We are using "stored" since store was foolishly used in 0.9.0 for
         // the database.
         let path = config.cache_dir.join("stored");
+        if config.store_readonly {
+            if !path.is_dir() {
+                error!(
+                    "Store directory {} does not exist. Cannot use it \
+                     in read-only mode.",
+                    path.display()
+                );
+                return Err(Failed)
+            }
+            return Ok(path)
+        }
         if let Err(err) = fs::create_dir_all(&path) {
             error!(
                 "Failed to create store directory {}: {}",
@@ -142,9 +234,96 @@ impl Store {
     pub fn new(config: &Config) -> Result<Self, Failed> {
         Ok(Store {
             path: Self::create_base_dir(config)?,
+            readonly: config.store_readonly,
+            shared: config.store_shared,
         })
     }
 
+    /// Creates a read-only view of the store.
+    ///
+    /// Unlike [`new`][Self::new], this does not touch the file system at
+    /// all, so the store directory may not exist (yet).
+    pub fn reader(config: &Config) -> Self {
+        Store {
+            path: config.cache_dir.join("stored"),
+            readonly: true,
+            shared: config.store_shared,
+        }
+    }
+
+    /// Returns whether the store is read-only.
+    pub fn is_readonly(&self) -> bool {
+        self.readonly
+    }
+
+    /// Checks that the store can be written to.
+    ///
+    /// The argument `writer` states whether the current run is allowed to
+    /// update the store. If it isn’t, logs an error mentioning `what` and
+    /// returns an error.
+    fn check_writable(
+        &self, writer: bool, what: impl fmt::Display
+    ) -> Result<(), Failed> {
+        if writer {
+            Ok(())
+        }
+        else if self.readonly {
+            error!(
+                "Fatal: attempted to update {} in read-only store.", what
+            );
+            Err(Failed)
+        }
+        else {
+            error!(
+                "Fatal: attempted to update {} in shared store without \
+                 being its writer.",
+                what
+            );
+            Err(Failed)
+        }
+    }
+
+    /// The name of the directory where corrupt files are moved to.
+    const QUARANTINE_BASE: &'static str = "quarantine";
+
+    /// The time quarantined files are kept before they are deleted.
+    const QUARANTINE_MAX_AGE: Duration = Duration::from_secs(30 * 86400);
+
+    /// Moves a corrupt file to the quarantine directory.
+    ///
+    /// The file is placed at the same path relative to the quarantine
+    /// directory as it had relative to the store. A file with the same name
+    /// and an added extension of `.reason` is created next to it that
+    /// contains the current time and the reason given in `reason`.
+    fn quarantine(
+        &self, path: &Path, reason: impl fmt::Display
+    ) -> Result<(), Failed> {
+        let relative = match path.strip_prefix(&self.path) {
+            Ok(relative) => relative,
+            Err(_) => {
+                error!(
+                    "Fatal: cannot quarantine {} outside of the store.",
+                    path.display()
+                );
+                return Err(Failed)
+            }
+        };
+        let target = self.path.join(Self::QUARANTINE_BASE).join(relative);
+        fatal::create_parent_all(&target)?;
+        fatal::rename(path, &target)?;
+        let mut reason_path = target.clone().into_os_string();
+        reason_path.push(".reason");
+        fatal::write_file(
+            Path::new(&reason_path),
+            format!("{}\n{}\n", Utc::now().to_rfc3339(), reason).as_bytes()
+        )?;
+        warn!(
+            "Moved corrupt stored file {} to {}: {}",
+            path.display(), target.display(), reason
+        );
+        Ok(())
+    }
+
     /// Sanitizes the stored data.
     ///
     /// Currently doesn’t do anything.
@@ -153,8 +332,145 @@ impl Store {
     }
 
     /// Start a validation run with the store.
-    pub fn start(&self) -> Run {
-        Run::new(self)
+    ///
+    /// If the store is shared, this holds the writer election for the run
+    /// and acquires the necessary locks. The returned run will only allow
+    /// updates if this process has been elected writer. Use
+    /// [`Run::is_writer`] to find out.
+    pub fn start(&self) -> Result<Run, Failed> {
+        if !self.shared || self.readonly {
+            return Ok(Run::new(self, None))
+        }
+        let readers = self.lock(FileLock::shared(&self.readers_lock_path()))?;
+        let writer = self.lock(
+            FileLock::try_exclusive(&self.writer_lock_path())
+        )?;
+        let generation = self.generation()?;
+        let shared = match writer {
+            Some(writer) => {
+                debug!(
+                    "Elected writer for shared store at generation {}.",
+                    generation
+                );
+                SharedRun { _lock: writer, writer: true, generation }
+            }
+            None => {
+                info!(
+                    "Shared store is updated by another process. \
+                     Validating from stored data at generation {}.",
+                    generation
+                );
+                SharedRun { _lock: readers, writer: false, generation }
+            }
+        };
+        Ok(Run::new(self, Some(shared)))
+    }
+
+    /// Converts the result of acquiring a lock.
+    fn lock<T>(&self, res: Result<T, io::Error>) -> Result<T, Failed> {
+        res.map_err(|err| {
+            error!(
+                "Fatal: failed to lock shared store {}: {}",
+                self.path.display(), err
+            );
+            Failed
+        })
+    }
+
+    /// Returns the current generation of the store.
+    ///
+    /// The generation is a counter incremented every time a writer has
+    /// finished a validation run on a shared store. It is zero if the
+    /// store is not shared or has never been updated.
+    pub fn generation(&self) -> Result<u64, Failed> {
+        let path = self.path.join(Self::GENERATION_FILE);
+        let content = match fatal::read_existing_file(&path)? {
+            Some(content) => content,
+            None => return Ok(0)
+        };
+        match std::str::from_utf8(&content).ok().and_then(|content| {
+            content.trim().parse().ok()
+        }) {
+            Some(generation) => Ok(generation),
+            None => {
+                warn!(
+                    "Ignoring invalid store generation file {}.",
+                    path.display()
+                );
+                Ok(0)
+            }
+        }
+    }
+
+    /// Increments the generation of the store.
+    fn bump_generation(&self) -> Result<u64, Failed> {
+        let generation = self.generation()?.wrapping_add(1);
+        let (tmp_path, mut tmp_file) = self.tmp_file()?;
+        if let Err(err) = write!(tmp_file, "{}", generation) {
+            error!(
+                "Fatal: failed to write temporary file {}: {}",
+                tmp_path.display(), err
+            );
+            return Err(Failed)
+        }
+        drop(tmp_file);
+        fatal::rename(&tmp_path, &self.path.join(Self::GENERATION_FILE))?;
+        Ok(generation)
+    }
+
+    /// The name of the file holding the store generation.
+    const GENERATION_FILE: &'static str = "generation";
+
+    /// Returns the path of the lock file used for writer election.
+    fn writer_lock_path(&self) -> PathBuf {
+        self.path.join("writer.lock")
+    }
+
+    /// Returns the path of the lock file held by readers.
+    fn readers_lock_path(&self) -> PathBuf {
+        self.path.join("readers.lock")
+    }
+
+    /// Adds the content of the store to a digest.
+    ///
+    /// This covers the relative paths and the content of all files under
+    /// `ta`, `rrdp`, and `rsync`, i.e., all the data that validation is
+    /// based upon. The files are processed in the order of their paths, so
+    /// the digest only depends on the stored data itself.
+    pub fn digest_content(
+        &self, context: &mut digest::Context
+    ) -> Result<(), Failed> {
+        for base in ["ta", Self::RRDP_BASE, "rsync"] {
+            self.digest_dir(Path::new(base), context)?;
+        }
+        Ok(())
+    }
+
+    /// Adds the content of a directory relative to the store to a digest.
+    fn digest_dir(
+        &self, relative: &Path, context: &mut digest::Context
+    ) -> Result<(), Failed> {
+        let path = self.path.join(relative);
+        let dir = match fatal::read_existing_dir(&path)? {
+            Some(dir) => dir,
+            None => return Ok(())
+        };
+        let mut entries = dir.collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by(|left, right| left.file_name().cmp(right.file_name()));
+        for entry in entries {
+            let path = relative.join(entry.file_name());
+            if entry.is_dir() {
+                self.digest_dir(&path, context)?;
+            }
+            else if entry.is_file() {
+                let content = fatal::read_file(entry.path())?;
+                context.update(path.to_string_lossy().as_bytes());
+                context.update(&[0]);
+                context.update(&(content.len() as u64).to_be_bytes());
+                context.update(&content);
+            }
+        }
+        Ok(())
     }
 
     /// Dumps the content of the store.
@@ -164,8 +480,9 @@ impl Store {
         debug!("Dumping store content to {}", dir.display());
         fatal::remove_dir_all(&dir)?;
         let mut repos = DumpRegistry::new(dir);
-        self.dump_tree(&self.rsync_repository_path(), &mut repos)?;
-        self.dump_tree(&self.rrdp_repository_base(), &mut repos)?;
+        for point in self.iter_points() {
+            self.dump_point(point?, &mut repos)?;
+        }
         self.dump_repository_json(repos)?;
         debug!("Store dump complete.");
         Ok(())
@@ -185,56 +502,18 @@ impl Store {
         Ok(())
     }
 
-    /// Dumps all the stored points found in the tree under `path`.
+    /// Dumps all data for a single stored publication point.
     ///
     /// The point’s repository and rsync URI is determined from the stored
-    /// points themselves. The target path is being determined from `repos`.
-    fn dump_tree(
-        &self,
-        path: &Path,
-        repos: &mut DumpRegistry,
-    ) -> Result<(), Failed> {
-        let dir = match fatal::read_existing_dir(path)? {
-            Some(dir) => dir,
-            None => return Ok(())
-        };
-        for entry in dir {
-            let entry = entry?;
-            if entry.is_dir() {
-                self.dump_tree(entry.path(), repos)?;
-            }
-            else if entry.is_file() {
-                self.dump_point(entry.path(), repos)?;
-            }
-        }
-        Ok(())
-    }
-
-    /// Dumps all data for a single stored publication point.
+    /// point itself. The target path is being determined from `repos`.
     fn dump_point(
         &self,
-        path: &Path,
+        mut point: StoredPoint,
         repos: &mut DumpRegistry,
     ) -> Result<(), Failed> {
-        let mut file = match File::open(path) {
-            Ok(file) => file,
-            Err(err) => {
-                error!(
-                    "Fatal: failed to open file {}: {}",
-                    path.display(), err
-                );
-                return Err(Failed)
-            }
-        };
-        let manifest = match StoredManifest::read(&mut file) {
-            Ok(some) => some,
-            Err(err) => {
-                error!(
-                    "Skipping {}: failed to read file: {}",
-                    path.display(), err
-                );
-                return Ok(())
-            }
+        let manifest = match point.take_manifest() {
+            Some(manifest) => manifest,
+            None => return Ok(())
         };
 
         let repo_dir = repos.get_repo_path(manifest.rpki_notify.as_ref());
@@ -244,14 +523,13 @@ impl Store {
         )?;
         self.dump_object(&repo_dir, &manifest.crl_uri, &manifest.crl)?;
 
-        loop {
-            let object = match StoredObject::read(&mut file) {
-                Ok(Some(object)) => object,
-                Ok(None) => break,
+        for object in &mut point {
+            let object = match object {
+                Ok(object) => object,
                 Err(err) => {
                     warn!(
                         "Partially skipping {}: failed to read file: {}",
-                        path.display(), err
+                        point.path().display(), err
                     );
                     return Ok(())
                 }
@@ -332,6 +610,225 @@ impl Store {
         )
     }
 
+    /// Registers the repositories of the stored points to be retained.
+    ///
+    /// This applies the same rules as cleanup does for points not seen
+    /// during a validation run: the repository of every point with a
+    /// current manifest is retained. It allows determining what cleaning up
+    /// the collector would delete without performing a validation run.
+    pub fn retain_points(
+        &self, retain: &mut collector::Cleanup
+    ) -> Result<(), Failed> {
+        let pinned = self.pinned()?.into_iter().collect();
+        for point in self.iter_points() {
+            if let Some(manifest) = point?.manifest() {
+                RetainedPoint::from_manifest(manifest).register(
+                    &pinned, retain
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns an iterator over all stored publication points.
+    ///
+    /// The iterator walks the store’s directory tree and returns each
+    /// publication point with a readable stored manifest as a
+    /// [`StoredPoint`]. The manifest is available via
+    /// [`StoredPoint::manifest`] and the point itself can be iterated over
+    /// to get its objects. Files that do not contain a valid stored point
+    /// are skipped.
+    ///
+    /// If accessing the file system fails, the iterator returns an error
+    /// once and then ends.
+    pub fn iter_points(&self) -> StoredPoints<'_> {
+        StoredPoints::new(self)
+    }
+
+    /// Looks up the publication point an object is stored in.
+    ///
+    /// The object is identified by its rsync URI. It can be any object
+    /// of a publication point, including its manifest and CRL. The index
+    /// of the store is used to find the candidate points, so this only
+    /// needs to look at the points publishing into the object’s directory.
+    ///
+    /// Returns `Ok(None)` if the object is not currently stored.
+    pub fn whereis(
+        &self, uri: &uri::Rsync
+    ) -> Result<Option<StoredLocation>, Failed> {
+        Ok(self.find_object(uri)?.map(|(location, _)| location))
+    }
+
+    /// Loads the content of the stored object with the given URI.
+    ///
+    /// The object can be any object of a publication point, including its
+    /// manifest and CRL. Returns `Ok(None)` if the object is not currently
+    /// stored.
+    pub fn load_object(
+        &self, uri: &uri::Rsync
+    ) -> Result<Option<Bytes>, Failed> {
+        Ok(self.find_object(uri)?.map(|(_, content)| content))
+    }
+
+    /// Finds an object via the index.
+    ///
+    /// Returns the location and content of the object if it is stored.
+    fn find_object(
+        &self, uri: &uri::Rsync
+    ) -> Result<Option<(StoredLocation, Bytes)>, Failed> {
+        let ca_repository = match uri.parent() {
+            Some(ca_repository) => ca_repository,
+            None => return Ok(None)
+        };
+        let dir_path = self.path.join(
+            ca_repository.unique_path(Self::INDEX_BASE, "")
+        );
+        let dir = match fatal::read_existing_dir(&dir_path)? {
+            Some(dir) => dir,
+            None => return Ok(None)
+        };
+        for authority in dir {
+            let authority = authority?;
+            if !authority.is_dir() {
+                continue
+            }
+            for entry in fatal::read_dir(authority.path())? {
+                let entry = entry?;
+                if !entry.is_file() {
+                    continue
+                }
+                let path = match self.read_index_entry(entry.path())? {
+                    Some(path) => path,
+                    None => continue
+                };
+                let is_rrdp = path.starts_with(self.rrdp_repository_base());
+                let mut point = StoredPoint::open(
+                    self, path, is_rrdp, false
+                )?;
+                let manifest = match point.take_manifest() {
+                    Some(manifest) => manifest,
+                    None => continue
+                };
+                if manifest.ca_repository != ca_repository {
+                    continue
+                }
+                let content = if manifest.manifest_uri == *uri {
+                    Some(manifest.manifest.clone())
+                }
+                else if manifest.crl_uri == *uri {
+                    Some(manifest.crl.clone())
+                }
+                else {
+                    point.find_map(|object| match object {
+                        Ok(object) if object.uri() == uri => {
+                            Some(object.into_content())
+                        }
+                        _ => None
+                    })
+                };
+                if let Some(content) = content {
+                    return Ok(Some((
+                        StoredLocation {
+                            not_after: manifest.not_after,
+                            manifest_uri: manifest.manifest_uri,
+                            ca_repository: manifest.ca_repository,
+                            rpki_notify: manifest.rpki_notify,
+                            path: point.path,
+                        },
+                        content
+                    )))
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// The name of the file listing the pinned publication points.
+    const PINNED_FILE: &'static str = "pinned";
+
+    /// Returns the manifest URIs of all pinned publication points.
+    ///
+    /// Lines in the pin file that aren’t valid rsync URIs are ignored.
+    pub fn pinned(&self) -> Result<Vec<uri::Rsync>, Failed> {
+        let path = self.path.join(Self::PINNED_FILE);
+        let content = match fatal::read_existing_file(&path)? {
+            Some(content) => content,
+            None => return Ok(Vec::new())
+        };
+        Ok(String::from_utf8_lossy(&content).lines().filter_map(|line| {
+            let line = line.trim();
+            if line.is_empty() {
+                return None
+            }
+            match uri::Rsync::from_str(line) {
+                Ok(uri) => Some(uri),
+                Err(_) => {
+                    warn!(
+                        "Ignoring invalid URI '{}' in {}.",
+                        line, path.display()
+                    );
+                    None
+                }
+            }
+        }).collect())
+    }
+
+    /// Pins the publication point with the given manifest URI.
+    ///
+    /// Returns whether the point wasn’t pinned before.
+    pub fn pin(&self, uri: &uri::Rsync) -> Result<bool, Failed> {
+        self.check_writable(!self.readonly, "pinned publication points")?;
+        let mut pinned = self.pinned()?;
+        if pinned.contains(uri) {
+            return Ok(false)
+        }
+        pinned.push(uri.clone());
+        self.write_pinned(&pinned)?;
+        Ok(true)
+    }
+
+    /// Removes the pin of the publication point with the given manifest URI.
+    ///
+    /// Returns whether the point was pinned.
+    pub fn unpin(&self, uri: &uri::Rsync) -> Result<bool, Failed> {
+        self.check_writable(!self.readonly, "pinned publication points")?;
+        let mut pinned = self.pinned()?;
+        let len = pinned.len();
+        pinned.retain(|item| item != uri);
+        if pinned.len() == len {
+            return Ok(false)
+        }
+        self.write_pinned(&pinned)?;
+        Ok(true)
+    }
+
+    /// Returns the status of a pinned publication point.
+    pub fn pin_status(&self, uri: &uri::Rsync) -> Result<PinStatus, Failed> {
+        Ok(match self.whereis(uri)? {
+            Some(location) if location.not_after() > Time::now() => {
+                PinStatus::Current
+            }
+            Some(_) => PinStatus::Expired,
+            None => PinStatus::Missing,
+        })
+    }
+
+    /// Replaces the pin file with the given list of manifest URIs.
+    fn write_pinned(&self, pinned: &[uri::Rsync]) -> Result<(), Failed> {
+        let (tmp_path, mut tmp_file) = self.tmp_file()?;
+        for uri in pinned {
+            if let Err(err) = writeln!(tmp_file, "{}", uri) {
+                error!(
+                    "Fatal: failed to write to file {}: {}",
+                    tmp_path.display(), err
+                );
+                return Err(Failed)
+            }
+        }
+        drop(tmp_file);
+        fatal::rename(&tmp_path, &self.path.join(Self::PINNED_FILE))
+    }
+
     /// Returns the path to use for the trust anchor at the given URI.
     fn ta_path(&self, uri: &TalUri) -> PathBuf {
         match *uri {
@@ -348,6 +845,22 @@ impl Store {
         }
     }
 
+    /// Returns the path for the rollover trust anchor at the given URI.
+    fn rollover_ta_path(&self, uri: &TalUri) -> PathBuf {
+        match *uri {
+            TalUri::Rsync(ref uri) => {
+                self.path.join(
+                    uri.unique_path("ta/rollover/rsync", ".cer")
+                )
+            }
+            TalUri::Https(ref uri) => {
+                self.path.join(
+                    uri.unique_path("ta/rollover/https", ".cer")
+                )
+            }
+        }
+    }
+
     /// The name of the directory where all the RRDP repositories go.
     const RRDP_BASE: &'static str = "rrdp";
 
@@ -366,6 +879,73 @@ impl Store {
         self.path.join("rsync")
     }
 
+    /// The name of the directory where the object index goes.
+    const INDEX_BASE: &'static str = "index";
+
+    /// Returns the path of the index entry for a stored manifest.
+    fn index_path(&self, manifest: &StoredManifest) -> PathBuf {
+        self.path.join(
+            manifest.ca_repository.unique_path(Self::INDEX_BASE, "")
+        ).join(
+            manifest.manifest_uri.unique_path("", "")
+        )
+    }
+
+    /// Makes sure the index contains an entry for a publication point.
+    ///
+    /// The point is described by its stored manifest and the path of its
+    /// file. The entry is only written if it is missing or refers to a
+    /// different path.
+    fn index_point(
+        &self, manifest: &StoredManifest, point_path: &Path
+    ) -> Result<(), Failed> {
+        let relative = match point_path.strip_prefix(&self.path).ok().and_then(
+            |path| path.to_str()
+        ) {
+            Some(relative) => relative,
+            None => return Ok(())
+        };
+        let path = self.index_path(manifest);
+        if let Some(content) = fatal::read_existing_file(&path)? {
+            if content == relative.as_bytes() {
+                return Ok(())
+            }
+        }
+        let (tmp_path, mut tmp_file) = self.tmp_file()?;
+        if let Err(err) = tmp_file.write_all(relative.as_bytes()) {
+            error!(
+                "Fatal: failed to write to file {}: {}",
+                tmp_path.display(), err
+            );
+            return Err(Failed)
+        }
+        drop(tmp_file);
+        fatal::create_parent_all(&path)?;
+        fatal::rename(&tmp_path, &path)
+    }
+
+    /// Reads an index entry and returns the path of the point it refers to.
+    ///
+    /// Returns `Ok(None)` if the entry is garbage.
+    fn read_index_entry(
+        &self, path: &Path
+    ) -> Result<Option<PathBuf>, Failed> {
+        let content = match fatal::read_existing_file(path)? {
+            Some(content) => content,
+            None => return Ok(None)
+        };
+        let relative = match String::from_utf8(content) {
+            Ok(relative) => PathBuf::from(relative),
+            Err(_) => return Ok(None)
+        };
+        if relative.as_os_str().is_empty() || !relative.components().all(
+            |item| matches!(item, std::path::Component::Normal(_))
+        ) {
+            return Ok(None)
+        }
+        Ok(Some(self.path.join(relative)))
+    }
+
     /// The name of the directory where the temporary files go.
     const TMP_BASE: &'static str = "tmp";
 
@@ -427,23 +1007,61 @@ impl Store {
 pub struct Run<'a> {
     /// A reference to the underlying store.
     store: &'a Store,
+
+    /// Information about the run if the store is shared.
+    shared: Option<SharedRun>,
+
+    /// The publication points seen during the run.
+    ///
+    /// This is used during cleanup to decide whether to retain a point
+    /// without having to read its file again.
+    retained: Mutex<HashMap<PathBuf, RetainedPoint>>,
 }
 
 impl<'a> Run<'a> {
     /// Creates a new runner from a store.
     fn new(
         store: &'a Store,
+        shared: Option<SharedRun>,
     ) -> Self {
-        Run { store }
+        Run { store, shared, retained: Default::default() }
     }
 
-    /// Finishes the validation run.
+    /// Returns whether the run is allowed to update the store.
     ///
-    /// Updates the `metrics` with the store run’s metrics.
+    /// This is `false` if the store is read-only or if the store is shared
+    /// and another process was elected writer for this run.
+    pub fn is_writer(&self) -> bool {
+        match self.shared {
+            Some(ref shared) => shared.writer,
+            None => !self.store.readonly
+        }
+    }
+
+    /// Returns the generation of the store at the start of the run.
+    ///
+    /// This is always zero if the store is not shared.
+    pub fn generation(&self) -> u64 {
+        self.shared.as_ref().map(|shared| shared.generation).unwrap_or(0)
+    }
+
+    /// Finishes the validation run.
+    ///
+    /// Updates the `metrics` with the store run’s metrics.
+    ///
+    /// If the store is shared and this run was its writer, the store’s
+    /// generation is incremented.
     ///
     /// If you are not interested in the metrics, you can simple drop the
     /// value, instead.
     pub fn done(self, _metrics: &mut Metrics) {
+        if self.shared.as_ref().map(|shared| shared.writer).unwrap_or(false) {
+            // Errors have been logged already and the next writer will
+            // simply try again.
+            if let Ok(generation) = self.store.bump_generation() {
+                debug!("Shared store now at generation {}.", generation);
+            }
+        }
     }
 
     /// Loads a stored trust anchor certificate.
@@ -457,6 +1075,9 @@ impl<'a> Run<'a> {
     pub fn update_ta(
         &self, uri: &TalUri, content: &[u8]
     ) -> Result<(), Failed> {
+        self.check_writable(
+            format_args!("trust anchor certificate {}", uri)
+        )?;
         let path = self.store.ta_path(uri);
         if let Some(dir) = path.parent() {
             fatal::create_dir_all(dir)?;
@@ -464,6 +1085,39 @@ impl<'a> Run<'a> {
         fatal::write_file(&path, content)
     }
 
+    /// Loads the stored rollover trust anchor certificate.
+    ///
+    /// This is the certificate with the key not currently in use during a
+    /// key rollover of the trust anchor.
+    pub fn load_rollover_ta(
+        &self, uri: &TalUri
+    ) -> Result<Option<Bytes>, Failed> {
+        fatal::read_existing_file(
+            &self.store.rollover_ta_path(uri)
+        ).map(|maybe| {
+            maybe.map(Into::into)
+        })
+    }
+
+    /// Updates or inserts the rollover trust anchor certificate.
+    pub fn update_rollover_ta(
+        &self, uri: &TalUri, content: &[u8]
+    ) -> Result<(), Failed> {
+        self.check_writable(
+            format_args!("rollover trust anchor certificate {}", uri)
+        )?;
+        let path = self.store.rollover_ta_path(uri);
+        if let Some(dir) = path.parent() {
+            fatal::create_dir_all(dir)?;
+        }
+        fatal::write_file(&path, content)
+    }
+
+    /// Checks that the run is allowed to update the store.
+    fn check_writable(&self, what: impl fmt::Display) -> Result<(), Failed> {
+        self.store.check_writable(self.is_writer(), what)
+    }
+
     /// Accesses the repository for the provided RPKI CA.
     ///
     /// If the CA’s rpkiNotify URI is present, the RRDP repository identified
@@ -481,7 +1135,7 @@ impl<'a> Run<'a> {
         else {
             (self.store.rsync_repository_path(), false)
         };
-        Repository::new(self.store, path, rrdp)
+        Repository::new(self.store, path, rrdp, self.is_writer())
     }
 
     /// Accesses the publication point for the provided RPKI CA.
@@ -500,23 +1154,100 @@ impl<'a> Run<'a> {
         self.repository(ca_cert).get_point(ca_cert.rpki_manifest())
     }
 
-    /// Cleans up the store.
+    /// Records a publication point seen during the run.
+    ///
+    /// The information kept for the point is used by
+    /// [`cleanup`][Self::cleanup] in place of the point’s file. Points
+    /// without a stored manifest are ignored.
+    pub fn retain_point(&self, point: &StoredPoint) {
+        if let Some(manifest) = point.manifest() {
+            self.retained.lock().insert(
+                point.path().into(),
+                RetainedPoint::from_manifest(manifest),
+            );
+        }
+    }
+
+    /// Cleans up the store and the collector.
     ///
-    /// All publication points that have an expired manifest will be removed.
-    /// RRDP repositories that have no more publication points are removed,
-    /// too.
+    /// All publication points that have an expired manifest will be removed
+    /// unless they have been pinned. RRDP repositories that have no more
+    /// publication points are removed, too.
     ///
-    /// All RRDP repositories and rsync modules retained are registered with
-    /// `collector` for retaining in the collector as well.
+    /// All RRDP repositories and rsync modules retained are registered for
+    /// retaining in the collector as well. Publication points seen during
+    /// the run are taken from the information recorded via
+    /// [`retain_point`][Self::retain_point], all others are read from disk.
+    /// Once the set of retained repositories is known, the collector is
+    /// cleaned up concurrently with the remainder of the store.
+    ///
+    /// If the store is read-only or if the store is shared and this run is
+    /// not its writer, the store is left untouched. If the store is shared
+    /// and other processes are currently reading from it, nothing happens
+    /// either.
     pub fn cleanup(
         &self,
-        collector: &mut collector::Cleanup,
+        collector: Option<&collector::Run>,
     ) -> Result<(), Failed> {
+        let mut retain = collector::Cleanup::new();
+        if !self.is_writer() {
+            debug!("Skipping store cleanup as we are not the writer.");
+            if let Some(collector) = collector {
+                collector.cleanup(&mut retain)?;
+            }
+            return Ok(())
+        }
+        let _readers = if self.shared.is_some() {
+            match self.store.lock(
+                FileLock::try_exclusive(&self.store.readers_lock_path())
+            )? {
+                Some(lock) => Some(lock),
+                None => {
+                    info!(
+                        "Skipping store cleanup as other processes are \
+                         reading from the store."
+                    );
+                    return Ok(())
+                }
+            }
+        }
+        else {
+            None
+        };
         self.cleanup_ta()?;
-        self.cleanup_points(&self.store.rrdp_repository_base(), collector)?;
-        self.cleanup_points(&self.store.rsync_repository_path(), collector)?;
-        self.cleanup_tmp()?;
-        Ok(())
+        {
+            let retained = self.retained.lock();
+            let pinned = self.store.pinned()?.into_iter().collect();
+            self.cleanup_points(
+                &self.store.rrdp_repository_base(), &retained, &pinned,
+                &mut retain
+            )?;
+            self.cleanup_points(
+                &self.store.rsync_repository_path(), &retained, &pinned,
+                &mut retain
+            )?;
+        }
+        thread::scope(|scope| {
+            let collector = collector.map(|collector| {
+                scope.spawn(move || collector.cleanup(&mut retain))
+            });
+            let res = self.cleanup_index().and_then(|_| {
+                self.cleanup_tmp()
+            }).and_then(|_| {
+                self.cleanup_quarantine()
+            });
+            if let Some(collector) = collector {
+                match collector.join() {
+                    Ok(Ok(())) => { }
+                    Ok(Err(err)) => return Err(err),
+                    Err(_) => {
+                        error!("Collector cleanup thread has panicked.");
+                        return Err(Failed)
+                    }
+                }
+            }
+            res
+        })
     }
 
     /// Cleans up the trust anchors.
@@ -540,34 +1271,164 @@ impl<'a> Run<'a> {
     /// Deletes all publication points with an expired manifest as well as
     /// any obviously garbage files. The RRDP repository of any publication
     /// point that is retained is registered to be retained by the collector.
+    ///
+    /// Points included in `retained` are judged based on that information
+    /// alone. All other points are read from disk. Points whose manifest
+    /// URI is included in `pinned` are kept even if expired.
     fn cleanup_points(
         &self,
         base: &Path,
+        retained: &HashMap<PathBuf, RetainedPoint>,
+        pinned: &HashSet<uri::Rsync>,
         retain: &mut collector::Cleanup,
     ) -> Result<(), Failed> {
         cleanup_dir_tree(base, |path| {
+            if let Some(point) = retained.get(path) {
+                return Ok(point.register(pinned, retain))
+            }
             if let Ok(stored) = StoredManifest::read(
                 &mut fatal::open_file(path)?
             ) {
-                if stored.retain() {
-                    if let Some(uri) = stored.rpki_notify.as_ref() {
-                        retain.add_rrdp_repository(uri)
-                    }
-                    else {
-                        retain.add_rsync_module(&stored.manifest_uri)
-                    }
-                    return Ok(true)
-                }
+                return Ok(
+                    RetainedPoint::from_manifest(&stored).register(
+                        pinned, retain
+                    )
+                )
             }
             Ok(false)
         })
     }
 
+    /// Cleans up the object index.
+    ///
+    /// Deletes all entries that refer to publication points that are gone.
+    /// This needs to run after the publication points have been cleaned up.
+    fn cleanup_index(&self) -> Result<(), Failed> {
+        cleanup_dir_tree(
+            &self.store.path.join(Store::INDEX_BASE),
+            |path| {
+                Ok(match self.store.read_index_entry(path)? {
+                    Some(point) => point.is_file(),
+                    None => false
+                })
+            }
+        )
+    }
+
     fn cleanup_tmp(&self) -> Result<(), Failed> {
         cleanup_dir_tree(&self.store.path.join("tmp"), |_path| {
             Ok(false)
         })
     }
+
+    /// Cleans up the quarantine directory.
+    ///
+    /// A quarantined file and its reason file are deleted once the reason
+    /// file is older than [`Store::QUARANTINE_MAX_AGE`]. Files without a
+    /// reason file are deleted right away.
+    fn cleanup_quarantine(&self) -> Result<(), Failed> {
+        let now = SystemTime::now();
+        cleanup_dir_tree(
+            &self.store.path.join(Store::QUARANTINE_BASE),
+            |path| {
+                let reason_path = if path.extension() == Some(
+                    OsStr::new("reason")
+                ) {
+                    path.to_path_buf()
+                }
+                else {
+                    let mut reason_path = path.as_os_str().to_owned();
+                    reason_path.push(".reason");
+                    reason_path.into()
+                };
+                Ok(
+                    fs::metadata(reason_path).and_then(|meta| {
+                        meta.modified()
+                    }).is_ok_and(|modified| {
+                        now.duration_since(modified).map_or(true, |age| {
+                            age < Store::QUARANTINE_MAX_AGE
+                        })
+                    })
+                )
+            }
+        )
+    }
+}
+
+
+//------------ RetainedPoint -------------------------------------------------
+
+/// The information necessary to decide whether to retain a point.
+#[derive(Clone, Debug)]
+struct RetainedPoint {
+    /// The expire time of the EE certificate of the manifest.
+    not_after: Time,
+
+    /// The rpkiNotify URI of the issuing CA certificate.
+    rpki_notify: Option<uri::Https>,
+
+    /// The manifest’s rsync URI.
+    manifest_uri: uri::Rsync,
+}
+
+impl RetainedPoint {
+    /// Creates the information from a stored manifest.
+    fn from_manifest(manifest: &StoredManifest) -> Self {
+        RetainedPoint {
+            not_after: manifest.not_after,
+            rpki_notify: manifest.rpki_notify.clone(),
+            manifest_uri: manifest.manifest_uri.clone(),
+        }
+    }
+
+    /// Returns whether the point should be retained.
+    ///
+    /// If so, registers the point’s repository with `retain`. Expired
+    /// points are retained only if they are included in `pinned`. Their
+    /// repository isn’t registered since the collector’s data is of no
+    /// further use for them.
+    fn register(
+        &self,
+        pinned: &HashSet<uri::Rsync>,
+        retain: &mut collector::Cleanup
+    ) -> bool {
+        if self.not_after <= Time::now() {
+            if pinned.contains(&self.manifest_uri) {
+                debug!(
+                    "Retaining pinned, expired publication point {}.",
+                    self.manifest_uri
+                );
+                return true
+            }
+            return false
+        }
+        if let Some(uri) = self.rpki_notify.as_ref() {
+            retain.add_rrdp_repository(uri)
+        }
+        else {
+            retain.add_rsync_module(&self.manifest_uri)
+        }
+        true
+    }
+}
+
+
+//------------ SharedRun -----------------------------------------------------
+
+/// Information about a validation run on a shared store.
+#[derive(Debug)]
+struct SharedRun {
+    /// The lock held for the duration of the run.
+    ///
+    /// This is the writer lock if the run is the writer or a shared lock
+    /// on the readers lock otherwise.
+    _lock: FileLock,
+
+    /// Was the run elected writer?
+    writer: bool,
+
+    /// The generation of the store at the start of the run.
+    generation: u64,
 }
 
 
@@ -592,12 +1453,17 @@ pub struct Repository<'a> {
 
     /// Are we using an rrdp tree?
     is_rrdp: bool,
+
+    /// Are we allowed to update the repository?
+    writer: bool,
 }
 
 impl<'a> Repository<'a> {
     /// Creates a repository object on a store using the given tree names.
-    fn new(store: &'a Store, path: PathBuf, is_rrdp: bool) -> Self {
-        Repository { store, path, is_rrdp }
+    fn new(
+        store: &'a Store, path: PathBuf, is_rrdp: bool, writer: bool
+    ) -> Self {
+        Repository { store, path, is_rrdp, writer }
     }
 
     /// Returns whether this is an RRDP repository.
@@ -616,7 +1482,8 @@ impl<'a> Repository<'a> {
         &self, manifest_uri: &uri::Rsync
     ) -> Result<StoredPoint<'a>, Failed> {
         StoredPoint::open(
-            self.store, self.point_path(manifest_uri), self.is_rrdp
+            self.store, self.point_path(manifest_uri), self.is_rrdp,
+            self.writer
         )
     }
 
@@ -660,6 +1527,9 @@ pub struct StoredPoint<'a> {
 
     /// Is this a publication point with in an RRDP repository?
     is_rrdp: bool,
+
+    /// Are we allowed to update the point?
+    writer: bool,
 }
 
 impl<'a> StoredPoint<'a> {
@@ -667,10 +1537,14 @@ impl<'a> StoredPoint<'a> {
     ///
     /// If there is a file at the given path, it is opened. Otherwise, er,
     /// well, it is not.
+    ///
+    /// If the file exists but its stored manifest is corrupt, the file is
+    /// quarantined if `writer` is `true` and ignored otherwise.
     fn open(
         store: &'a Store,
         path: PathBuf,
         is_rrdp: bool,
+        writer: bool,
     ) -> Result<Self, Failed> {
         let mut file = match File::open(&path) {
             Ok(file) => file,
@@ -679,7 +1553,7 @@ impl<'a> StoredPoint<'a> {
                     store, path,
                     file: None,
                     manifest: None,
-                    is_rrdp
+                    is_rrdp, writer,
                 })
             }
             Err(err) => {
@@ -701,6 +1575,18 @@ impl<'a> StoredPoint<'a> {
                     );
                     return Err(Failed)
                 }
+                else if writer {
+                    drop(file);
+                    store.quarantine(
+                        &path, format_args!("invalid stored manifest: {}", err)
+                    )?;
+                    return Ok(StoredPoint {
+                        store, path,
+                        file: None,
+                        manifest: None,
+                        is_rrdp, writer,
+                    })
+                }
                 else {
                     info!(
                         "Ignoring invalid stored publication point at {}: {}",
@@ -711,6 +1597,12 @@ impl<'a> StoredPoint<'a> {
             }
         };
 
+        if writer {
+            if let Some(manifest) = manifest.as_ref() {
+                store.index_point(manifest, &path)?;
+            }
+        }
+
         Ok(StoredPoint {
             store, path,
             file: if manifest.is_some() {
@@ -720,10 +1612,35 @@ impl<'a> StoredPoint<'a> {
                 None
             },
             manifest,
-            is_rrdp
+            is_rrdp, writer,
         })
     }
 
+    /// Quarantines the stored point because it is corrupt.
+    ///
+    /// If we are allowed to update the store, the file of the stored point
+    /// is moved to the quarantine directory with `reason` given as the
+    /// reason. Otherwise, only a message is logged. In both cases, the
+    /// point is afterwards treated as if there was nothing stored.
+    pub fn quarantine(
+        &mut self, reason: impl fmt::Display
+    ) -> Result<(), Failed> {
+        self.manifest = None;
+        if self.file.take().is_none() {
+            return Ok(())
+        }
+        if self.writer {
+            self.store.quarantine(&self.path, reason)
+        }
+        else {
+            info!(
+                "Ignoring invalid stored publication point at {}: {}",
+                self.path.display(), reason
+            );
+            Ok(())
+        }
+    }
+
     /// Returns a reference to the path of the file.
     pub fn path(&self) -> &Path {
         &self.path
@@ -760,11 +1677,18 @@ impl<'a> StoredPoint<'a> {
     /// returned. Otherwise, `self` represents the new point. It is
     /// positioned at the first object, i.e., if it is iterated over, the
     /// first object will be returned next.
+    ///
+    /// If the store may not be updated during the current run, an error is
+    /// logged and a fatal error is returned.
     pub fn update(
         &mut self,
         manifest: StoredManifest,
         mut objects: impl FnMut() -> Result<Option<StoredObject>, UpdateError>
     ) -> Result<(), UpdateError> {
+        self.store.check_writable(
+            self.writer,
+            format_args!("publication point {}", self.path.display())
+        )?;
         let (tmp_path, mut tmp_file) = self.store.tmp_file()?;
 
         if let Err(err) = manifest.write(&mut tmp_file) {
@@ -816,6 +1740,7 @@ impl<'a> StoredPoint<'a> {
             fatal::create_dir_all(path)?;
         }
         fatal::rename(&tmp_path, &self.path)?;
+        self.store.index_point(&manifest, &self.path)?;
         let mut file = fatal::open_file(&self.path)?;
         if let Err(err) = file.seek(SeekFrom::Start(tmp_object_start)) {
             error!(
@@ -830,6 +1755,128 @@ impl<'a> StoredPoint<'a> {
 
         Ok(())
     }
+
+    /// Records the outcome of the validation of the stored point.
+    ///
+    /// The outcome is written to a new copy of the stored point. If there
+    /// currently is no stored point or it has been stored in an older format
+    /// without room for the outcome, nothing happens.
+    ///
+    /// If the store may not be updated during the current run, an error is
+    /// logged and returned.
+    pub fn set_outcome(
+        &mut self, outcome: StoredOutcome
+    ) -> Result<(), Failed> {
+        let mut data = Vec::new();
+        StoredOutcome::compose(Some(&outcome), &mut data).map_err(|err| {
+            error!(
+                "Fatal: failed to encode validation outcome for {}: {}",
+                self.path.display(), err
+            );
+            Failed
+        })?;
+        if self.patch(StoredManifest::OUTCOME_POS, &data)? {
+            if let Some(manifest) = self.manifest.as_mut() {
+                manifest.last_outcome = Some(outcome);
+            }
+        }
+        Ok(())
+    }
+
+    /// Records that the data of the stored point is current.
+    ///
+    /// The time given in `now` is written to a new copy of the stored point
+    /// as the confirmation time. If there currently is no stored point or it
+    /// has been stored in an older format without room for the time,
+    /// nothing happens.
+    ///
+    /// If the store may not be updated during the current run, an error is
+    /// logged and returned.
+    pub fn confirm(&mut self, now: Time) -> Result<(), Failed> {
+        let mut data = Vec::new();
+        StoredManifest::compose_confirmed(
+            Some(now), &mut data
+        ).map_err(|err| {
+            error!(
+                "Fatal: failed to encode confirmation time for {}: {}",
+                self.path.display(), err
+            );
+            Failed
+        })?;
+        if self.patch(StoredManifest::CONFIRMED_POS, &data)? {
+            if let Some(manifest) = self.manifest.as_mut() {
+                manifest.confirmed = Some(now);
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces part of the stored manifest.
+    ///
+    /// Writes a copy of the file with `data` at position `pos` to the
+    /// `tmp` directory and then moves it into place, so that readers
+    /// always see either the old or the new version. Returns whether the
+    /// data was written. This doesn’t happen if there is no stored point
+    /// or it is stored in an older format.
+    fn patch(&mut self, pos: u64, data: &[u8]) -> Result<bool, Failed> {
+        let position = match self.file.as_mut() {
+            Some(file) => file.stream_position().map_err(|err| {
+                error!(
+                    "Fatal: failed to get position in file {}: {}",
+                    self.path.display(), err
+                );
+                Failed
+            })?,
+            None => return Ok(false)
+        };
+        self.store.check_writable(
+            self.writer,
+            format_args!("publication point {}", self.path.display())
+        )?;
+        let (tmp_path, mut tmp_file) = self.store.tmp_file()?;
+        let res = File::open(&self.path).and_then(|mut file| {
+            let mut version = [0u8];
+            file.read_exact(&mut version)?;
+            // Version 3 has the same layout at the start of the data.
+            if version[0] != 3 && version[0] != StoredManifest::VERSION {
+                return Ok(false)
+            }
+            tmp_file.write_all(&version)?;
+            io::copy(&mut (&mut file).take(pos - 1), &mut tmp_file)?;
+            tmp_file.write_all(data)?;
+            file.seek(SeekFrom::Current(data.len() as i64))?;
+            io::copy(&mut file, &mut tmp_file)?;
+            Ok(true)
+        });
+        drop(tmp_file);
+        match res {
+            Ok(true) => { }
+            Ok(false) => {
+                fatal::remove_file(&tmp_path)?;
+                return Ok(false)
+            }
+            Err(err) => {
+                error!(
+                    "Fatal: failed to write to file {}: {}",
+                    tmp_path.display(), err
+                );
+                return Err(Failed)
+            }
+        }
+
+        drop(self.file.take());
+        fatal::rename(&tmp_path, &self.path)?;
+        let mut file = fatal::open_file(&self.path)?;
+        if let Err(err) = file.seek(SeekFrom::Start(position)) {
+            error!(
+                "Fatal: failed to position file {}: {}",
+                self.path.display(), err
+            );
+            return Err(Failed)
+        }
+        self.file = Some(file);
+        Ok(true)
+    }
 }
 
 impl<'a> Iterator for StoredPoint<'a> {
@@ -841,6 +1888,128 @@ impl<'a> Iterator for StoredPoint<'a> {
 }
 
 
+//------------ StoredPoints --------------------------------------------------
+
+/// An iterator over all the publication points in a store.
+///
+/// A value of this type is returned by [`Store::iter_points`].
+pub struct StoredPoints<'a> {
+    /// The store we are iterating over.
+    store: &'a Store,
+
+    /// The base directories still to be walked.
+    ///
+    /// The boolean states whether points under the directory are in an
+    /// RRDP repository.
+    bases: Vec<(PathBuf, bool)>,
+
+    /// The stack of directories currently being walked.
+    dirs: Vec<(PathBuf, fs::ReadDir)>,
+
+    /// Are the points currently being walked in an RRDP repository?
+    is_rrdp: bool,
+}
+
+impl<'a> StoredPoints<'a> {
+    /// Creates a new iterator for the given store.
+    fn new(store: &'a Store) -> Self {
+        StoredPoints {
+            store,
+            bases: vec![
+                (store.rrdp_repository_base(), true),
+                (store.rsync_repository_path(), false),
+            ],
+            dirs: Vec::new(),
+            is_rrdp: false,
+        }
+    }
+
+    /// Ends the iteration after an error.
+    fn fail(&mut self) -> Option<Result<StoredPoint<'a>, Failed>> {
+        self.bases.clear();
+        self.dirs.clear();
+        Some(Err(Failed))
+    }
+}
+
+impl<'a> Iterator for StoredPoints<'a> {
+    type Item = Result<StoredPoint<'a>, Failed>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (dir_path, dir) = match self.dirs.last_mut() {
+                Some(dir) => dir,
+                None => {
+                    let (path, is_rrdp) = self.bases.pop()?;
+                    self.is_rrdp = is_rrdp;
+                    match fs::read_dir(&path) {
+                        Ok(dir) => self.dirs.push((path, dir)),
+                        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                        Err(err) => {
+                            error!(
+                                "Fatal: failed to open directory {}: {}",
+                                path.display(), err
+                            );
+                            return self.fail()
+                        }
+                    }
+                    continue
+                }
+            };
+            let entry = match dir.next() {
+                Some(Ok(entry)) => entry,
+                Some(Err(err)) => {
+                    error!(
+                        "Fatal: failed to read directory {}: {}",
+                        dir_path.display(), err
+                    );
+                    return self.fail()
+                }
+                None => {
+                    self.dirs.pop();
+                    continue
+                }
+            };
+            let path = entry.path();
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(err) => {
+                    error!(
+                        "Fatal: failed to read metadata for {}: {}",
+                        path.display(), err
+                    );
+                    return self.fail()
+                }
+            };
+            if metadata.is_dir() {
+                match fs::read_dir(&path) {
+                    Ok(dir) => self.dirs.push((path, dir)),
+                    Err(err) => {
+                        error!(
+                            "Fatal: failed to open directory {}: {}",
+                            path.display(), err
+                        );
+                        return self.fail()
+                    }
+                }
+            }
+            else if metadata.is_file() {
+                match StoredPoint::open(
+                    self.store, path, self.is_rrdp, false
+                ) {
+                    Ok(point) => {
+                        if point.manifest().is_some() {
+                            return Some(Ok(point))
+                        }
+                    }
+                    Err(_) => return self.fail()
+                }
+            }
+        }
+    }
+}
+
+
 //------------ StoredManifest ------------------------------------------------
 
 /// The content of a manifest placed in the store.
@@ -868,8 +2037,27 @@ impl<'a> Iterator for StoredPoint<'a> {
 ///   [`crl`][Self::crl] method. There must always be exactly one CRL used by
 ///   a publication point. As it needs to be available for validation, we
 ///   might as well store it together with the manifest.
+/// * The outcome of the last validation of the publication point via the
+///   [`last_outcome`][Self::last_outcome] method. This allows deciding
+///   whether an earlier validation result can be trusted. It is placed at
+///   a fixed position at the start of the file so it can be replaced
+///   without decoding the rest of the file.
+/// * The time the data was last confirmed to be current via the
+///   [`confirmed`][Self::confirmed] method. This allows withholding data
+///   of publication points whose repository has become unavailable. It,
+///   too, is placed at a fixed position so it can be replaced.
+/// * The nextUpdate time of the manifest and a [`ReissueTimeline`] via the
+///   [`next_update`][Self::next_update] and [`timeline`][Self::timeline]
+///   methods. Together, they allow judging whether the publication point
+///   reissues its manifest in time.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct StoredManifest {
+    /// The outcome of the last validation of the publication point.
+    last_outcome: Option<StoredOutcome>,
+
+    /// The time the data was last confirmed to be current.
+    confirmed: Option<Time>,
+
     /// The expire time of the EE certificate of the manifest.
     not_after: Time,
 
@@ -896,18 +2084,39 @@ pub struct StoredManifest {
 
     /// The raw content of the CRL.
     crl: Bytes,
+
+    /// The nextUpdate time of the manifest.
+    ///
+    /// This is `None` if the manifest was stored by an older version.
+    next_update: Option<Time>,
+
+    /// The recent history of reissues of the manifest.
+    timeline: ReissueTimeline,
 }
 
 impl StoredManifest {
     /// The version of the type.
     ///
-    /// It was 0 before 0.14.0.
-    const VERSION: u8 = 1;
+    /// It was 0 before 0.14.0, 1 before the last validation outcome was
+    /// added, 2 before the confirmation time was added, and 3 before the
+    /// nextUpdate time and reissue timeline were added. Versions 1 to 3
+    /// can still be read.
+    const VERSION: u8 = 4;
+
+    /// The position of the last validation outcome in the encoded data.
+    const OUTCOME_POS: u64 = 1;
+
+    /// The position of the confirmation time in the encoded data.
+    const CONFIRMED_POS: u64 = 18;
 
     /// Creates a new stored manifest.
     ///
     /// The new value is created from the components of the stored manifest.
-    /// See the methods with the same name for their meaning.
+    /// See the methods with the same name for their meaning. The last
+    /// validation outcome is left empty. Since the data has just been
+    /// collected, it is considered confirmed now. The reissue timeline
+    /// starts out empty. Use [`succeed`][Self::succeed] to continue the
+    /// timeline of a previously stored manifest.
     pub fn new(
         ee_cert: &ResourceCert,
         manifest: &ManifestContent,
@@ -917,6 +2126,8 @@ impl StoredManifest {
         crl: Bytes,
     ) -> Self {
         StoredManifest {
+            last_outcome: None,
+            confirmed: Some(Time::now()),
             not_after: ee_cert.validity().not_after(),
             manifest_number: manifest.manifest_number(),
             this_update: manifest.this_update(),
@@ -925,7 +2136,28 @@ impl StoredManifest {
             manifest_uri: ca_cert.rpki_manifest().clone(),
             manifest: manifest_bytes,
             crl_uri,
-            crl
+            crl,
+            next_update: Some(manifest.next_update()),
+            timeline: ReissueTimeline::default(),
+        }
+    }
+
+    /// Makes the manifest the successor of a previously stored manifest.
+    ///
+    /// Takes over the reissue timeline of `previous` and adds the reissue
+    /// represented by this manifest. If the nextUpdate time of `previous`
+    /// isn’t known, the timeline is taken over unchanged.
+    pub fn succeed(&mut self, previous: &StoredManifest) {
+        self.timeline = previous.timeline.clone();
+        if let Some(next_update) = previous.next_update {
+            self.timeline.push(Reissue {
+                declared:
+                    next_update.timestamp()
+                    - previous.this_update.timestamp(),
+                actual:
+                    self.this_update.timestamp()
+                    - previous.this_update.timestamp(),
+            });
         }
     }
 
@@ -933,12 +2165,23 @@ impl StoredManifest {
     pub fn read(reader: &mut impl io::Read) -> Result<Self, ParseError> {
         // Version number.
         let version = u8::parse(reader)?;
-        if version != Self::VERSION {
-            return Err(ParseError::format(
-                    format!("unexpected version {}", version)
-            ))
-        }
-        Ok(StoredManifest {
+        let (last_outcome, confirmed) = match version {
+            1 => (None, None),
+            2 => (StoredOutcome::parse(reader)?, None),
+            3 | Self::VERSION => {
+                (
+                    StoredOutcome::parse(reader)?,
+                    Self::parse_confirmed(reader)?
+                )
+            }
+            _ => {
+                return Err(ParseError::format(
+                        format!("unexpected version {}", version)
+                ))
+            }
+        };
+        let mut res = StoredManifest {
+            last_outcome, confirmed,
             not_after: Parse::parse(reader)?,
             manifest_number: Parse::parse(reader)?,
             this_update: Parse::parse(reader)?,
@@ -948,7 +2191,14 @@ impl StoredManifest {
             manifest: Parse::parse(reader)?,
             crl_uri: Parse::parse(reader)?,
             crl: Parse::parse(reader)?,
-        })
+            next_update: None,
+            timeline: ReissueTimeline::default(),
+        };
+        if version == Self::VERSION {
+            res.next_update = Parse::parse(reader)?;
+            res.timeline = Parse::parse(reader)?;
+        }
+        Ok(res)
     }
 
     /// Appends the stored manifest to a writer.
@@ -957,6 +2207,8 @@ impl StoredManifest {
     ) -> Result<(), io::Error> {
         Self::VERSION.compose(writer)?;
 
+        StoredOutcome::compose(self.last_outcome.as_ref(), writer)?;
+        Self::compose_confirmed(self.confirmed, writer)?;
         self.not_after.compose(writer)?;
         self.manifest_number.compose(writer)?;
         self.this_update.compose(writer)?;
@@ -966,16 +2218,49 @@ impl StoredManifest {
         self.manifest.compose(writer)?;
         self.crl_uri.compose(writer)?;
         self.crl.compose(writer)?;
+        self.next_update.compose(writer)?;
+        self.timeline.compose(writer)?;
 
         Ok(())
     }
 
-    /// Returns whether we should retain the stored manifest.
-    fn retain(&self) -> bool {
-        self.not_after > Time::now()
-    }
-}
-
+    /// Reads an optional confirmation time from an IO reader.
+    fn parse_confirmed(
+        reader: &mut impl io::Read
+    ) -> Result<Option<Time>, ParseError> {
+        let status = u8::parse(reader)?;
+        let time = Time::parse(reader)?;
+        match status {
+            0 => Ok(None),
+            1 => Ok(Some(time)),
+            _ => {
+                Err(ParseError::format(
+                    format!("invalid confirmation status {}", status)
+                ))
+            }
+        }
+    }
+
+    /// Appends an optional confirmation time to a writer.
+    ///
+    /// The time is always encoded with the same length so it can be
+    /// replaced at a fixed position.
+    fn compose_confirmed(
+        time: Option<Time>, writer: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        match time {
+            Some(time) => {
+                1u8.compose(writer)?;
+                time.compose(writer)
+            }
+            None => {
+                0u8.compose(writer)?;
+                0i64.compose(writer)
+            }
+        }
+    }
+}
+
 impl StoredManifest {
     /// Returns the expire time of the manifest.
     ///
@@ -995,6 +2280,14 @@ impl StoredManifest {
         self.this_update
     }
 
+    /// Returns the rpkiNotify URI of the issuing CA certificate if present.
+    ///
+    /// If this is `Some(_)`, the publication point is part of the RRDP
+    /// repository with this URI.
+    pub fn rpki_notify(&self) -> Option<&uri::Https> {
+        self.rpki_notify.as_ref()
+    }
+
     /// Returns the rsync URI of the directory containing the objects.
     ///
     /// As the manifest only lists relative file names, this URI is necessary
@@ -1007,11 +2300,21 @@ impl StoredManifest {
         &self.ca_repository
     }
 
+    /// Returns the rsync URI of the manifest.
+    pub fn manifest_uri(&self) -> &uri::Rsync {
+        &self.manifest_uri
+    }
+
     /// Returns the bytes of the manifest.
     pub fn manifest(&self) -> &Bytes {
         &self.manifest
     }
 
+    /// Returns the rsync URI of the publication point’s CRL.
+    pub fn crl_uri(&self) -> &uri::Rsync {
+        &self.crl_uri
+    }
+
     /// Returns the bytes of the publication point’s CRL.
     ///
     /// This CRL should be the CRL referenced via the CRL distribution
@@ -1020,6 +2323,353 @@ impl StoredManifest {
     pub fn crl(&self) -> &Bytes {
         &self.crl
     }
+
+    /// Returns the outcome of the last validation if available.
+    ///
+    /// This is not available for publication points that have been stored
+    /// by an older version of Routinator or that have not finished
+    /// validation yet.
+    pub fn last_outcome(&self) -> Option<&StoredOutcome> {
+        self.last_outcome.as_ref()
+    }
+
+    /// Returns the time the data was last confirmed to be current.
+    ///
+    /// This is the time the data was last collected or the collector last
+    /// successfully updated the repository and found the data unchanged.
+    /// The time is not available for publication points that have been
+    /// stored by an older version of Routinator.
+    pub fn confirmed(&self) -> Option<Time> {
+        self.confirmed
+    }
+
+    /// Returns the nextUpdate time of the manifest if available.
+    ///
+    /// The time is not available for publication points that have been
+    /// stored by an older version of Routinator.
+    pub fn next_update(&self) -> Option<Time> {
+        self.next_update
+    }
+
+    /// Returns the recent history of reissues of the manifest.
+    pub fn timeline(&self) -> &ReissueTimeline {
+        &self.timeline
+    }
+}
+
+
+//------------ ReissueTimeline -----------------------------------------------
+
+/// The recent history of manifest reissues of a publication point.
+///
+/// Each time a new manifest replaces a stored one, a [`Reissue`] is
+/// recorded comparing the time that actually passed between the two
+/// manifests’ thisUpdate times with the interval declared by the earlier
+/// manifest via its nextUpdate time. Only the last
+/// [`MAX_LEN`][Self::MAX_LEN] reissues are kept.
+///
+/// A publication point that regularly reissues its manifest late is
+/// considered _chronically stale._ This allows spotting such points before
+/// their manifests actually expire.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct ReissueTimeline {
+    /// The recorded reissues, oldest first.
+    reissues: Vec<Reissue>,
+}
+
+impl ReissueTimeline {
+    /// The maximum number of reissues kept.
+    pub const MAX_LEN: usize = 8;
+
+    /// The minimum number of reissues needed to judge a point.
+    const MIN_CHRONIC: usize = 3;
+
+    /// Adds a reissue, dropping the oldest one if necessary.
+    pub fn push(&mut self, reissue: Reissue) {
+        if self.reissues.len() >= Self::MAX_LEN {
+            self.reissues.remove(0);
+        }
+        self.reissues.push(reissue);
+    }
+
+    /// Returns the number of recorded reissues.
+    pub fn len(&self) -> usize {
+        self.reissues.len()
+    }
+
+    /// Returns whether there are no recorded reissues.
+    pub fn is_empty(&self) -> bool {
+        self.reissues.is_empty()
+    }
+
+    /// Returns an iterator over the recorded reissues, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = Reissue> + '_ {
+        self.reissues.iter().copied()
+    }
+
+    /// Returns the number of recorded reissues that were late.
+    pub fn late(&self) -> usize {
+        self.iter().filter(|item| item.is_late()).count()
+    }
+
+    /// Returns the largest delay of any recorded reissue in seconds.
+    ///
+    /// Returns zero if no reissue was late.
+    pub fn max_delay(&self) -> i64 {
+        self.iter().map(Reissue::delay).max().unwrap_or(0).max(0)
+    }
+
+    /// Returns whether the point is chronically stale.
+    ///
+    /// This is the case if enough reissues have been recorded and at least
+    /// half of them were late.
+    pub fn is_chronic(&self) -> bool {
+        self.len() >= Self::MIN_CHRONIC && self.late() * 2 >= self.len()
+    }
+}
+
+impl<W: io::Write> Compose<W> for ReissueTimeline {
+    fn compose(&self, target: &mut W) -> Result<(), io::Error> {
+        // We never keep more than MAX_LEN items, so this always fits.
+        (self.reissues.len() as u8).compose(target)?;
+        for item in &self.reissues {
+            item.declared.compose(target)?;
+            item.actual.compose(target)?;
+        }
+        Ok(())
+    }
+}
+
+impl<R: io::Read> Parse<R> for ReissueTimeline {
+    fn parse(source: &mut R) -> Result<Self, ParseError> {
+        let len = usize::from(u8::parse(source)?);
+        if len > Self::MAX_LEN {
+            return Err(ParseError::format(
+                format!("excessive reissue timeline length {}", len)
+            ))
+        }
+        let mut reissues = Vec::with_capacity(len);
+        for _ in 0..len {
+            reissues.push(Reissue {
+                declared: i64::parse(source)?,
+                actual: i64::parse(source)?,
+            })
+        }
+        Ok(ReissueTimeline { reissues })
+    }
+}
+
+
+//------------ Reissue -------------------------------------------------------
+
+/// A single reissue of a manifest.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Reissue {
+    /// The update interval declared by the earlier manifest in seconds.
+    ///
+    /// This is the difference between its nextUpdate and thisUpdate times.
+    pub declared: i64,
+
+    /// The time actually passed until the reissue in seconds.
+    ///
+    /// This is the difference between the thisUpdate times of the two
+    /// manifests.
+    pub actual: i64,
+}
+
+impl Reissue {
+    /// Returns how many seconds the reissue was late.
+    ///
+    /// The value is negative if the manifest was reissued early.
+    pub fn delay(self) -> i64 {
+        self.actual - self.declared
+    }
+
+    /// Returns whether the manifest was reissued after the earlier one
+    /// became stale.
+    pub fn is_late(self) -> bool {
+        self.delay() > 0
+    }
+}
+
+
+//------------ StoredOutcome -------------------------------------------------
+
+/// The outcome of the last validation of a stored publication point.
+///
+/// Besides whether the publication point was found to be valid and when
+/// that happened, this contains the hash of the engine policy that was in
+/// effect at the time. If the policy or the Routinator version changes, this
+/// hash changes, too, and the outcome should not be trusted anymore.
+///
+/// The outcome is always encoded with the same length, even if it is not
+/// present, so that it can be replaced at a fixed position.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct StoredOutcome {
+    /// The time the publication point was validated.
+    validated: Time,
+
+    /// Was the publication point found to be valid?
+    valid: bool,
+
+    /// The hash of the engine policy used for validation.
+    policy: u64,
+}
+
+impl StoredOutcome {
+    /// Creates a new outcome for a validation happening now.
+    pub fn now(valid: bool, policy: u64) -> Self {
+        StoredOutcome { validated: Time::now(), valid, policy }
+    }
+
+    /// Returns the time the publication point was validated.
+    pub fn validated(&self) -> Time {
+        self.validated
+    }
+
+    /// Returns whether the publication point was found to be valid.
+    pub fn valid(&self) -> bool {
+        self.valid
+    }
+
+    /// Returns the hash of the engine policy used for validation.
+    pub fn policy(&self) -> u64 {
+        self.policy
+    }
+
+    /// Returns whether the outcome was reached with the given policy.
+    ///
+    /// The time of validation is ignored.
+    pub fn matches(&self, valid: bool, policy: u64) -> bool {
+        self.valid == valid && self.policy == policy
+    }
+
+    /// Reads an optional outcome from an IO reader.
+    fn parse(
+        reader: &mut impl io::Read
+    ) -> Result<Option<Self>, ParseError> {
+        let status = u8::parse(reader)?;
+        let validated = Time::parse(reader)?;
+        let policy = u64::parse(reader)?;
+        let valid = match status {
+            0 => return Ok(None),
+            1 => true,
+            2 => false,
+            _ => {
+                return Err(ParseError::format(
+                    format!("invalid validation outcome {}", status)
+                ))
+            }
+        };
+        Ok(Some(StoredOutcome { validated, valid, policy }))
+    }
+
+    /// Appends an optional outcome to a writer.
+    fn compose(
+        this: Option<&Self>, writer: &mut impl io::Write
+    ) -> Result<(), io::Error> {
+        match this {
+            Some(this) => {
+                (if this.valid { 1u8 } else { 2u8 }).compose(writer)?;
+                this.validated.compose(writer)?;
+                this.policy.compose(writer)?;
+            }
+            None => {
+                0u8.compose(writer)?;
+                0i64.compose(writer)?;
+                0u64.compose(writer)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+
+//------------ StoredLocation ------------------------------------------------
+
+/// The location of an object in the store.
+///
+/// A value of this type is returned by [`Store::whereis`].
+#[derive(Clone, Debug)]
+pub struct StoredLocation {
+    /// The expire time of the EE certificate of the manifest.
+    not_after: Time,
+
+    /// The manifest URI of the publication point.
+    manifest_uri: uri::Rsync,
+
+    /// The CA repository URI of the publication point.
+    ca_repository: uri::Rsync,
+
+    /// The rpkiNotify URI of the repository, if it uses RRDP.
+    rpki_notify: Option<uri::Https>,
+
+    /// The path to the file of the publication point.
+    path: PathBuf,
+}
+
+impl StoredLocation {
+    /// Returns the manifest URI of the publication point.
+    pub fn manifest_uri(&self) -> &uri::Rsync {
+        &self.manifest_uri
+    }
+
+    /// Returns the CA repository URI of the publication point.
+    pub fn ca_repository(&self) -> &uri::Rsync {
+        &self.ca_repository
+    }
+
+    /// Returns the rpkiNotify URI of the repository if there is one.
+    pub fn rpki_notify(&self) -> Option<&uri::Https> {
+        self.rpki_notify.as_ref()
+    }
+
+    /// Returns the path to the file of the publication point.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the time the manifest of the publication point expires.
+    pub fn not_after(&self) -> Time {
+        self.not_after
+    }
+}
+
+
+//------------ PinStatus -----------------------------------------------------
+
+/// The status of a pinned publication point.
+///
+/// A value of this type is returned by [`Store::pin_status`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PinStatus {
+    /// The point is stored and its manifest is still valid.
+    Current,
+
+    /// The point is stored but its manifest has expired.
+    ///
+    /// Without the pin, the point would have been removed by cleanup.
+    Expired,
+
+    /// The point is not currently stored.
+    Missing,
+}
+
+impl PinStatus {
+    /// Returns a description of the status.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PinStatus::Current => "pinned",
+            PinStatus::Expired => "pinned, expired",
+            PinStatus::Missing => "pinned, not stored",
+        }
+    }
+}
+
+impl fmt::Display for PinStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 
@@ -1039,7 +2689,7 @@ pub struct StoredObject {
     uri: uri::Rsync,
 
     /// The manifest hash of the object if available.
-    hash: Option<ManifestHash>,
+    hash: Option<ObjectHash>,
 
     /// The content of the object.
     content: Bytes,
@@ -1050,7 +2700,7 @@ impl StoredObject {
     pub fn new(
         uri: uri::Rsync,
         content: Bytes,
-        hash: Option<ManifestHash>,
+        hash: Option<ObjectHash>,
     ) -> Self {
         StoredObject { uri, hash, content }
     }
@@ -1080,16 +2730,18 @@ impl StoredObject {
         let uri = uri::Rsync::parse(reader)?;
         let hash = match u8::parse(reader)? {
             0 => None,
-            1 => {
-                let algorithm = DigestAlgorithm::sha256();
+            hash_type => {
+                let algorithm = match HashAlgorithm::from_type(hash_type) {
+                    Some(algorithm) => algorithm,
+                    None => {
+                        return Err(ParseError::format(
+                            format!("unsupported hash type {}", hash_type)
+                        ));
+                    }
+                };
                 let mut value = vec![0u8; algorithm.digest_len()];
                 reader.read_exact(&mut value)?;
-                Some(ManifestHash::new(value.into(), algorithm))
-            }
-            hash_type => {
-                return Err(ParseError::format(
-                    format!("unsupported hash type {}", hash_type)
-                ));
+                ObjectHash::new(algorithm, value.into())
             }
         };
         let content = Bytes::parse(reader)?;
@@ -1108,17 +2760,16 @@ impl StoredObject {
 
         // Hash.
         //
-        // One octet hash type: 0 .. None, 1 .. SHA-256
+        // One octet hash type: 0 .. None, 1 .. SHA-256, 2 .. SHA-512
         // As many octets as the hash type requires.
         //
-        // Unknown digest algorithms (there is non yet, but there may be) are
-        // encoded as if the field was None.
+        // See `HashAlgorithm::to_type` for the types.
         match self.hash.as_ref() {
-            Some(hash) if hash.algorithm().is_sha256() => {
-                1u8.compose(writer)?;
+            Some(hash) => {
+                hash.algorithm().to_type().compose(writer)?;
                 writer.write_all(hash.as_slice())?;
             }
-            _ => {
+            None => {
                 0u8.compose(writer)?;
             }
         }
@@ -1133,6 +2784,11 @@ impl StoredObject {
         &self.uri
     }
 
+    /// Returns the manifest hash of the object if available.
+    pub fn hash(&self) -> Option<&ObjectHash> {
+        self.hash.as_ref()
+    }
+
     /// Returns the stored object’s content.
     pub fn content(&self) -> &Bytes {
         &self.content
@@ -1249,6 +2905,12 @@ mod test {
     #[test]
     fn write_read_stored_manifest() {
         let mut orig = StoredManifest {
+            last_outcome: Some(StoredOutcome {
+                validated: Time::utc(2021, 2, 18, 13, 22, 6),
+                valid: false,
+                policy: 0x1234_5678_9abc_def0,
+            }),
+            confirmed: Some(Time::utc(2021, 2, 19, 8, 12, 45)),
             not_after: Time::utc(2021, 2, 18, 13, 22, 6),
             manifest_number: Serial::from(12u64),
             this_update: Time::utc(2020, 1, 20, 16, 47, 6),
@@ -1265,7 +2927,14 @@ mod test {
             crl_uri: uri::Rsync::from_str(
                 "rsync://foo.bar/bla/blubb"
             ).unwrap(),
-            crl: Bytes::from(b"blablubb".as_ref())
+            crl: Bytes::from(b"blablubb".as_ref()),
+            next_update: Some(Time::utc(2020, 1, 21, 16, 47, 6)),
+            timeline: ReissueTimeline {
+                reissues: vec![
+                    Reissue { declared: 86400, actual: 3600 },
+                    Reissue { declared: 86400, actual: 90000 },
+                ]
+            },
         };
         let mut written = Vec::new();
         orig.write(&mut written).unwrap();
@@ -1277,21 +2946,520 @@ mod test {
         orig.write(&mut written).unwrap();
         let decoded = StoredManifest::read(&mut written.as_slice()).unwrap();
         assert_eq!(orig, decoded);
+
+        orig.last_outcome = None;
+        let mut written = Vec::new();
+        orig.write(&mut written).unwrap();
+        let decoded = StoredManifest::read(&mut written.as_slice()).unwrap();
+        assert_eq!(orig, decoded);
+
+        // Version 3 is the same minus nextUpdate and the timeline.
+        orig.next_update = None;
+        orig.timeline = ReissueTimeline::default();
+        let mut v4 = Vec::new();
+        orig.write(&mut v4).unwrap();
+        let mut v3 = vec![3u8];
+        v3.extend_from_slice(&v4[1..v4.len() - 2]);
+        let decoded = StoredManifest::read(&mut v3.as_slice()).unwrap();
+        assert_eq!(orig, decoded);
+
+        // Version 2 is the same minus the confirmation time.
+        orig.confirmed = None;
+        let mut v4 = Vec::new();
+        orig.write(&mut v4).unwrap();
+        let v3 = &v4[..v4.len() - 2];
+        let mut written = vec![2u8];
+        written.extend_from_slice(&v3[1..18]);
+        written.extend_from_slice(&v3[27..]);
+        let decoded = StoredManifest::read(&mut written.as_slice()).unwrap();
+        assert_eq!(orig, decoded);
+
+        // Version 1 is the same minus the outcome, too.
+        let mut written = vec![1u8];
+        written.extend_from_slice(&v3[27..]);
+        let decoded = StoredManifest::read(&mut written.as_slice()).unwrap();
+        assert_eq!(orig, decoded);
+    }
+
+    #[test]
+    fn reissue_timeline() {
+        let on_time = Reissue { declared: 86400, actual: 3600 };
+        let late = Reissue { declared: 86400, actual: 90000 };
+
+        let mut timeline = ReissueTimeline::default();
+        timeline.push(late);
+        timeline.push(late);
+        assert!(!timeline.is_chronic());
+        timeline.push(on_time);
+        assert!(timeline.is_chronic());
+        assert_eq!(timeline.late(), 2);
+        assert_eq!(timeline.max_delay(), 3600);
+
+        for _ in 0..ReissueTimeline::MAX_LEN {
+            timeline.push(on_time);
+        }
+        assert_eq!(timeline.len(), ReissueTimeline::MAX_LEN);
+        assert_eq!(timeline.late(), 0);
+        assert_eq!(timeline.max_delay(), 0);
+        assert!(!timeline.is_chronic());
     }
 
     #[test]
     fn write_read_stored_object() {
-        let orig = StoredObject::new(
-            uri::Rsync::from_str("rsync://foo.bar/bla/blubb").unwrap(),
-            Bytes::from(b"foobar".as_ref()),
-            None
+        let uri = uri::Rsync::from_str("rsync://foo.bar/bla/blubb").unwrap();
+        let content = Bytes::from(b"foobar".as_ref());
+        let mut hashes = vec![None];
+        for algorithm in [HashAlgorithm::Sha256, HashAlgorithm::Sha512] {
+            hashes.push(ObjectHash::new(
+                algorithm,
+                Bytes::copy_from_slice(algorithm.digest(&content).as_ref())
+            ));
+        }
+        for hash in hashes {
+            let orig = StoredObject::new(uri.clone(), content.clone(), hash);
+            let mut written = Vec::new();
+            orig.write(&mut written).unwrap();
+            let decoded = StoredObject::read(
+                &mut written.as_slice()
+            ).unwrap().unwrap();
+            assert_eq!(orig, decoded);
+        }
+    }
+
+    #[test]
+    fn readonly_store() {
+        let _ = crate::process::Process::init(); // May be inited already.
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default_with_paths(
+            Default::default(), dir.path().into()
         );
-        let mut written = Vec::new();
-        orig.write(&mut written).unwrap();
-        let decoded = StoredObject::read(
-            &mut written.as_slice()
-        ).unwrap().unwrap();
-        assert_eq!(orig, decoded);
+        config.store_readonly = true;
+        assert!(Store::new(&config).is_err());
+        assert!(!dir.path().join("stored").exists());
+
+        fs::create_dir_all(dir.path().join("stored")).unwrap();
+        let store = Store::new(&config).unwrap();
+        assert!(store.is_readonly());
+        let ta = TalUri::from_str("rsync://foo.bar/bla/ta.cer").unwrap();
+        assert!(store.start().unwrap().update_ta(&ta, b"foobar").is_err());
+        assert!(!store.ta_path(&ta).exists());
+    }
+
+    #[test]
+    fn rollover_ta() {
+        let _ = crate::process::Process::init(); // May be inited already.
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::default_with_paths(
+            Default::default(), dir.path().into()
+        );
+        let store = Store::new(&config).unwrap();
+        let ta = TalUri::from_str("rsync://foo.bar/bla/ta.cer").unwrap();
+        let run = store.start().unwrap();
+        run.update_ta(&ta, b"new").unwrap();
+        assert!(run.load_rollover_ta(&ta).unwrap().is_none());
+        run.update_rollover_ta(&ta, b"old").unwrap();
+        assert_eq!(run.load_ta(&ta).unwrap().unwrap().as_ref(), b"new");
+        assert_eq!(
+            run.load_rollover_ta(&ta).unwrap().unwrap().as_ref(), b"old"
+        );
+        assert_ne!(store.ta_path(&ta), store.rollover_ta_path(&ta));
+    }
+
+    #[test]
+    fn digest_content() {
+        let _ = crate::process::Process::init(); // May be inited already.
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::default_with_paths(
+            Default::default(), dir.path().into()
+        );
+        let store = Store::new(&config).unwrap();
+        let digest = |store: &Store| {
+            let mut context = digest::Context::new(&digest::SHA256);
+            store.digest_content(&mut context).unwrap();
+            context.finish().as_ref().to_vec()
+        };
+        let empty = digest(&store);
+
+        let point = dir.path().join("stored/rsync/foo.bar/bla/point.mft");
+        fs::create_dir_all(point.parent().unwrap()).unwrap();
+        fs::write(&point, b"foobar").unwrap();
+        let first = digest(&store);
+        assert_ne!(empty, first);
+
+        // Temporary files don’t count.
+        fs::create_dir_all(dir.path().join("stored/tmp")).unwrap();
+        fs::write(dir.path().join("stored/tmp/abcd1234"), b"x").unwrap();
+        assert_eq!(first, digest(&store));
+
+        fs::write(&point, b"foobaz").unwrap();
+        assert_ne!(first, digest(&store));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn shared_store() {
+        let _ = crate::process::Process::init(); // May be inited already.
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default_with_paths(
+            Default::default(), dir.path().into()
+        );
+        config.store_shared = true;
+        let one = Store::new(&config).unwrap();
+        let two = Store::new(&config).unwrap();
+        let ta = TalUri::from_str("rsync://foo.bar/bla/ta.cer").unwrap();
+
+        let writer = one.start().unwrap();
+        let reader = two.start().unwrap();
+        assert!(writer.is_writer());
+        assert!(!reader.is_writer());
+        assert_eq!(writer.generation(), 0);
+        assert!(reader.update_ta(&ta, b"foobar").is_err());
+        writer.update_ta(&ta, b"foobar").unwrap();
+
+        // Cleanup is skipped while the reader is active, so the invalid
+        // trust anchor certificate has to stay.
+        writer.cleanup(None).unwrap();
+        assert!(one.ta_path(&ta).exists());
+
+        writer.done(&mut Default::default());
+        assert_eq!(one.generation().unwrap(), 1);
+        drop(reader);
+
+        // Now the other store wins the election.
+        let writer = two.start().unwrap();
+        let reader = one.start().unwrap();
+        assert!(writer.is_writer());
+        assert!(!reader.is_writer());
+        assert_eq!(reader.generation(), 1);
+        drop(reader);
+        writer.cleanup(None).unwrap();
+        assert!(!one.ta_path(&ta).exists());
+    }
+
+    #[test]
+    fn quarantine_corrupt_point() {
+        let _ = crate::process::Process::init(); // May be inited already.
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::default_with_paths(
+            Default::default(), dir.path().into()
+        );
+        let store = Store::new(&config).unwrap();
+        let path = store.rsync_repository_path().join("foo.bar/bla/mft");
+        fatal::create_parent_all(&path).unwrap();
+        fs::write(&path, b"\x02garbage").unwrap();
+
+        // Readers leave the file alone.
+        let point = StoredPoint::open(
+            &store, path.clone(), false, false
+        ).unwrap();
+        assert!(point.manifest().is_none());
+        assert!(path.exists());
+
+        // Writers move it out of the way.
+        let point = StoredPoint::open(
+            &store, path.clone(), false, true
+        ).unwrap();
+        assert!(point.manifest().is_none());
+        assert!(!path.exists());
+        let target = store.path.join("quarantine").join(
+            path.strip_prefix(&store.path).unwrap()
+        );
+        assert_eq!(fs::read(&target).unwrap(), b"\x02garbage");
+        let mut reason = target.clone().into_os_string();
+        reason.push(".reason");
+        assert!(
+            fs::read_to_string(&reason).unwrap().contains("invalid stored")
+        );
+
+        // Cleanup keeps recent files but removes those without a reason.
+        let stray = store.path.join("quarantine").join("stray");
+        fs::write(&stray, b"stray").unwrap();
+        store.start().unwrap().cleanup(None).unwrap();
+        assert!(target.exists());
+        assert!(Path::new(&reason).exists());
+        assert!(!stray.exists());
+    }
+
+    #[test]
+    fn whereis_object() {
+        let _ = crate::process::Process::init(); // May be inited already.
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::default_with_paths(
+            Default::default(), dir.path().into()
+        );
+        let store = Store::new(&config).unwrap();
+        let manifest = StoredManifest {
+            last_outcome: None,
+            confirmed: None,
+            not_after: Time::now() + chrono::Duration::days(1),
+            manifest_number: Serial::from(1u64),
+            this_update: Time::now(),
+            rpki_notify: None,
+            ca_repository: uri::Rsync::from_str(
+                "rsync://foo.bar/bla/ca/"
+            ).unwrap(),
+            manifest_uri: uri::Rsync::from_str(
+                "rsync://foo.bar/bla/ca/ca.mft"
+            ).unwrap(),
+            manifest: Bytes::from(b"foobar".as_ref()),
+            crl_uri: uri::Rsync::from_str(
+                "rsync://foo.bar/bla/ca/ca.crl"
+            ).unwrap(),
+            crl: Bytes::from(b"blablubb".as_ref()),
+            next_update: None,
+            timeline: ReissueTimeline::default(),
+        };
+        let roa = uri::Rsync::from_str("rsync://foo.bar/bla/ca/1.roa").unwrap();
+        let path = store.rsync_repository_path().join(
+            "foo.bar/bla/ca/ca.mft"
+        );
+        let mut point = StoredPoint::open(
+            &store, path.clone(), false, true
+        ).unwrap();
+        let mut objects = vec![
+            StoredObject::new(roa.clone(), Bytes::from_static(b"roa"), None)
+        ];
+        point.update(manifest, || Ok(objects.pop())).unwrap();
+        drop(point);
+
+        let location = store.whereis(&roa).unwrap().unwrap();
+        assert_eq!(
+            location.manifest_uri().as_str(), "rsync://foo.bar/bla/ca/ca.mft"
+        );
+        assert_eq!(location.path(), path);
+        assert!(
+            store.whereis(
+                &uri::Rsync::from_str("rsync://foo.bar/bla/ca/ca.crl").unwrap()
+            ).unwrap().is_some()
+        );
+        assert!(
+            store.whereis(
+                &uri::Rsync::from_str("rsync://foo.bar/bla/ca/2.roa").unwrap()
+            ).unwrap().is_none()
+        );
+        assert_eq!(
+            store.load_object(&roa).unwrap().unwrap().as_ref(), b"roa"
+        );
+        assert_eq!(
+            Store::reader(&config).load_object(
+                &uri::Rsync::from_str("rsync://foo.bar/bla/ca/ca.crl").unwrap()
+            ).unwrap().unwrap().as_ref(),
+            b"blablubb"
+        );
+
+        // Once the point is gone, cleanup removes the index entry.
+        fs::remove_file(&path).unwrap();
+        store.start().unwrap().cleanup(None).unwrap();
+        assert!(store.whereis(&roa).unwrap().is_none());
+    }
+
+    #[test]
+    fn set_outcome_and_confirm() {
+        let _ = crate::process::Process::init(); // May be inited already.
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::default_with_paths(
+            Default::default(), dir.path().into()
+        );
+        let store = Store::new(&config).unwrap();
+        let manifest = StoredManifest {
+            last_outcome: None,
+            confirmed: None,
+            not_after: Time::now() + chrono::Duration::days(1),
+            manifest_number: Serial::from(1u64),
+            this_update: Time::now(),
+            rpki_notify: None,
+            ca_repository: uri::Rsync::from_str(
+                "rsync://foo.bar/bla/ca/"
+            ).unwrap(),
+            manifest_uri: uri::Rsync::from_str(
+                "rsync://foo.bar/bla/ca/ca.mft"
+            ).unwrap(),
+            manifest: Bytes::from(b"foobar".as_ref()),
+            crl_uri: uri::Rsync::from_str(
+                "rsync://foo.bar/bla/ca/ca.crl"
+            ).unwrap(),
+            crl: Bytes::from(b"blablubb".as_ref()),
+            next_update: None,
+            timeline: ReissueTimeline::default(),
+        };
+        let path = store.rsync_repository_path().join(
+            "foo.bar/bla/ca/ca.mft"
+        );
+        let mut point = StoredPoint::open(
+            &store, path.clone(), false, true
+        ).unwrap();
+        let mut objects = vec![
+            StoredObject::new(
+                uri::Rsync::from_str("rsync://foo.bar/bla/ca/2.roa").unwrap(),
+                Bytes::from_static(b"roa2"), None
+            ),
+            StoredObject::new(
+                uri::Rsync::from_str("rsync://foo.bar/bla/ca/1.roa").unwrap(),
+                Bytes::from_static(b"roa1"), None
+            ),
+        ];
+        point.update(manifest, || Ok(objects.pop())).unwrap();
+
+        // Replacing the file keeps the position of the reader.
+        assert_eq!(point.next().unwrap().unwrap().content().as_ref(), b"roa1");
+        let outcome = StoredOutcome::now(true, 12);
+        let now = Time::now();
+        point.set_outcome(outcome).unwrap();
+        point.confirm(now).unwrap();
+        assert_eq!(point.next().unwrap().unwrap().content().as_ref(), b"roa2");
+        assert!(point.next().is_none());
+        drop(point);
+
+        let mut point = StoredPoint::open(
+            &store, path.clone(), false, false
+        ).unwrap();
+        let manifest = point.manifest().unwrap();
+        let last_outcome = manifest.last_outcome().unwrap();
+        assert!(last_outcome.matches(true, 12));
+        assert_eq!(
+            last_outcome.validated().timestamp(),
+            outcome.validated().timestamp()
+        );
+        assert_eq!(
+            manifest.confirmed().unwrap().timestamp(), now.timestamp()
+        );
+        assert_eq!(point.next().unwrap().unwrap().content().as_ref(), b"roa1");
+        assert!(
+            fs::read_dir(store.path.join("tmp")).unwrap().next().is_none()
+        );
+    }
+
+    #[test]
+    fn cleanup_retained_points() {
+        let _ = crate::process::Process::init(); // May be inited already.
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::default_with_paths(
+            Default::default(), dir.path().into()
+        );
+        let store = Store::new(&config).unwrap();
+        let manifest = |name: &str, not_after| StoredManifest {
+            last_outcome: None,
+            confirmed: None,
+            not_after,
+            manifest_number: Serial::from(1u64),
+            this_update: Time::now(),
+            rpki_notify: None,
+            ca_repository: uri::Rsync::from_str(
+                "rsync://foo.bar/bla/ca/"
+            ).unwrap(),
+            manifest_uri: uri::Rsync::from_str(
+                &format!("rsync://foo.bar/bla/ca/{}", name)
+            ).unwrap(),
+            manifest: Bytes::from(b"foobar".as_ref()),
+            crl_uri: uri::Rsync::from_str(
+                "rsync://foo.bar/bla/ca/ca.crl"
+            ).unwrap(),
+            crl: Bytes::from(b"blablubb".as_ref()),
+            next_update: None,
+            timeline: ReissueTimeline::default(),
+        };
+        let seen = store.rsync_repository_path().join(
+            "foo.bar/bla/ca/seen.mft"
+        );
+        let unseen = store.rsync_repository_path().join(
+            "foo.bar/bla/ca/unseen.mft"
+        );
+        let expired = store.rsync_repository_path().join(
+            "foo.bar/bla/ca/expired.mft"
+        );
+        let run = store.start().unwrap();
+        for (path, name, not_after) in [
+            (&seen, "seen.mft", Time::now() + chrono::Duration::days(1)),
+            (&unseen, "unseen.mft", Time::now() + chrono::Duration::days(1)),
+            (&expired, "expired.mft", Time::now() - chrono::Duration::days(1)),
+        ] {
+            let mut point = StoredPoint::open(
+                &store, path.clone(), false, true
+            ).unwrap();
+            point.update(manifest(name, not_after), || Ok(None)).unwrap();
+            if name == "seen.mft" {
+                run.retain_point(&point);
+            }
+        }
+
+        // The retained point is kept based on the recorded information
+        // even if its file has become unreadable in the meantime.
+        fs::write(&seen, b"\x02garbage").unwrap();
+        run.cleanup(None).unwrap();
+        assert!(seen.exists());
+        assert!(unseen.exists());
+        assert!(!expired.exists());
+    }
+
+    #[test]
+    fn pinned_points() {
+        let _ = crate::process::Process::init(); // May be inited already.
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::default_with_paths(
+            Default::default(), dir.path().into()
+        );
+        let store = Store::new(&config).unwrap();
+        let pinned_uri = uri::Rsync::from_str(
+            "rsync://foo.bar/bla/ca/pinned.mft"
+        ).unwrap();
+        let expired_uri = uri::Rsync::from_str(
+            "rsync://foo.bar/bla/ca/expired.mft"
+        ).unwrap();
+        let manifest = |uri: &uri::Rsync| StoredManifest {
+            last_outcome: None,
+            confirmed: None,
+            not_after: Time::now() - chrono::Duration::days(1),
+            manifest_number: Serial::from(1u64),
+            this_update: Time::now(),
+            rpki_notify: None,
+            ca_repository: uri::Rsync::from_str(
+                "rsync://foo.bar/bla/ca/"
+            ).unwrap(),
+            manifest_uri: uri.clone(),
+            manifest: Bytes::from(b"foobar".as_ref()),
+            crl_uri: uri::Rsync::from_str(
+                "rsync://foo.bar/bla/ca/ca.crl"
+            ).unwrap(),
+            crl: Bytes::from(b"blablubb".as_ref()),
+            next_update: None,
+            timeline: ReissueTimeline::default(),
+        };
+        let pinned = store.rsync_repository_path().join(
+            "foo.bar/bla/ca/pinned.mft"
+        );
+        let expired = store.rsync_repository_path().join(
+            "foo.bar/bla/ca/expired.mft"
+        );
+        for (path, uri) in [(&pinned, &pinned_uri), (&expired, &expired_uri)] {
+            let mut point = StoredPoint::open(
+                &store, path.clone(), false, true
+            ).unwrap();
+            point.update(manifest(uri), || Ok(None)).unwrap();
+        }
+
+        assert!(store.pinned().unwrap().is_empty());
+        assert!(store.pin(&pinned_uri).unwrap());
+        assert!(!store.pin(&pinned_uri).unwrap());
+        assert!(store.pin(&expired_uri).unwrap());
+        assert!(store.unpin(&expired_uri).unwrap());
+        assert!(!store.unpin(&expired_uri).unwrap());
+        assert_eq!(store.pinned().unwrap(), vec![pinned_uri.clone()]);
+        assert!(Store::reader(&config).pin(&expired_uri).is_err());
+
+        store.start().unwrap().cleanup(None).unwrap();
+        assert!(pinned.exists());
+        assert!(!expired.exists());
+        assert_eq!(
+            store.pin_status(&pinned_uri).unwrap(), PinStatus::Expired
+        );
+        assert_eq!(
+            store.pin_status(&expired_uri).unwrap(), PinStatus::Missing
+        );
+
+        // Once unpinned, the point goes away with the next cleanup.
+        store.unpin(&pinned_uri).unwrap();
+        store.start().unwrap().cleanup(None).unwrap();
+        assert!(!pinned.exists());
     }
 }
 
@@ -131,6 +131,11 @@ impl<Meta> Archive<Meta> {
         })
     }
 
+    /// Returns the current size of the archive file in bytes.
+    pub fn size(&self) -> u64 {
+        self.file.size
+    }
+
     /// Verifies the consistency of an archive.
     ///
     /// The method traverses the entire archive and makes sure that the
@@ -350,6 +350,27 @@ impl<R: io::Read> Parse<R> for Time {
 }
 
 
+//------------ Option<Time> --------------------------------------------------
+//
+// Encoded like Option<i64> with the Unix timestamp as the value.
+
+impl<W: io::Write> Compose<W> for Option<Time> {
+    fn compose(&self, target: &mut W) -> Result<(), io::Error> {
+        self.map(|time| time.timestamp()).compose(target)
+    }
+}
+
+impl<R: io::Read> Parse<R> for Option<Time> {
+    fn parse(source: &mut R) -> Result<Self, ParseError> {
+        Option::<i64>::parse(source)?.map(|ts| {
+            Utc.timestamp_opt(ts, 0).single().map(Into::into).ok_or_else(|| {
+                ParseError::format("invalid timestamp")
+            })
+        }).transpose()
+    }
+}
+
+
 //------------ HashMap<K, V> -------------------------------------------------
 //
 // Encoded as the number of items as a u64 followed by pairs of key and value.
@@ -0,0 +1,125 @@
+//! Revocation checks against CRLs.
+//!
+//! The CRL type of the rpki crate keeps the list of revoked certificates in
+//! its encoded form and scans it for every lookup unless asked to cache the
+//! serial numbers in a hash set. Some CAs publish CRLs with hundreds of
+//! thousands of entries, for which such a set takes up many times the size
+//! of the CRL itself, and each validation thread may hold a few of them.
+//!
+//! [`RevocationList`] instead either scans the encoded list for every
+//! lookup or, if many lookups are expected, keeps a sorted index of the
+//! serial numbers that is looked up via binary search. The index takes
+//! little more than the size of the serial numbers and is shared between
+//! clones.
+
+use std::sync::Arc;
+use bcder::Mode;
+use bcder::encode::Values;
+use rpki::repository::crl::Crl;
+use rpki::repository::x509::{Serial, Time};
+
+
+//------------ RevocationList ------------------------------------------------
+
+/// A CRL prepared for checking whether certificates have been revoked.
+#[derive(Clone, Debug)]
+pub struct RevocationList {
+    /// The CRL.
+    crl: Crl,
+
+    /// The sorted serial numbers of the revoked certificates.
+    ///
+    /// If this is `None`, the CRL is scanned for each lookup.
+    index: Option<Arc<[Serial]>>,
+}
+
+impl RevocationList {
+    /// Creates a new revocation list from a CRL.
+    ///
+    /// The `lookups` argument gives the expected number of lookups. If it
+    /// is larger than `index_limit`, the serial numbers are indexed.
+    /// Otherwise each lookup scans the CRL.
+    pub fn new(crl: Crl, lookups: usize, index_limit: usize) -> Self {
+        let index = if lookups > index_limit {
+            Self::index(&crl)
+        }
+        else {
+            None
+        };
+        RevocationList { crl, index }
+    }
+
+    /// Creates the sorted index of the revoked serial numbers.
+    ///
+    /// The rpki crate doesn’t provide access to the serial numbers of the
+    /// CRL entries, so the list of revoked certificates is encoded again
+    /// and its entries are decoded here. Returns `None` if that fails,
+    /// which shouldn’t happen as the list has been decoded before. Lookups
+    /// then scan the CRL.
+    fn index(crl: &Crl) -> Option<Arc<[Serial]>> {
+        let revoked = crl.revoked_certs().encode_ref().to_captured(Mode::Der);
+        let mut serials = Vec::new();
+        Mode::Der.decode(revoked.as_slice(), |cons| {
+            cons.take_sequence(|cons| {
+                while let Some(serial) = cons.take_opt_sequence(|cons| {
+                    let serial = Serial::take_from(cons)?;
+                    cons.skip_all()?;
+                    Ok(serial)
+                })? {
+                    serials.push(serial)
+                }
+                Ok(())
+            })
+        }).ok()?;
+        serials.sort_unstable();
+        serials.dedup();
+        Some(serials.into())
+    }
+
+    /// Returns whether the certificate with the given serial was revoked.
+    pub fn contains(&self, serial: Serial) -> bool {
+        match self.index.as_ref() {
+            Some(index) => index.binary_search(&serial).is_ok(),
+            None => self.crl.contains(serial),
+        }
+    }
+
+    /// Returns the time the next CRL will be issued.
+    pub fn next_update(&self) -> Time {
+        self.crl.next_update()
+    }
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn indexed_and_linear_lookups_agree() {
+        let crl = Crl::decode(
+            include_bytes!("../../test/crl/ca1.crl").as_ref()
+        ).unwrap();
+        let linear = RevocationList::new(crl.clone(), 0, 1);
+        let indexed = RevocationList::new(crl.clone(), 2, 1);
+        assert!(linear.index.is_none());
+        assert_eq!(indexed.index.as_ref().unwrap().len(), 163);
+
+        for serial in indexed.index.as_ref().unwrap().iter() {
+            assert!(linear.contains(*serial));
+            assert!(indexed.contains(*serial));
+        }
+        for serial in [0u64, 0xEF80FC, 0x057E0F49, u64::MAX] {
+            let serial = Serial::from(serial);
+            assert!(!linear.contains(serial));
+            assert!(!indexed.contains(serial));
+        }
+        for serial in [0xEF80FDu64, 0x01038472, 0x057E0F48] {
+            let serial = Serial::from(serial);
+            assert!(linear.contains(serial));
+            assert!(indexed.contains(serial));
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! Advisory file locks.
+//!
+//! This module provides a thin wrapper around the advisory file locks
+//! provided by the operating system. These are used to coordinate access
+//! to the store between several Routinator processes.
+//!
+//! Locks are only available on Unix systems. On all other systems, trying
+//! to acquire a lock will always fail with an error of kind
+//! `io::ErrorKind::Unsupported`.
+
+use std::{fs, io};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+
+//------------ FileLock ------------------------------------------------------
+
+/// An advisory lock on a file.
+///
+/// The lock file is created if it doesn’t exist yet. The lock is held for
+/// as long as the value is alive and released when it is dropped.
+#[derive(Debug)]
+pub struct FileLock {
+    /// The path of the lock file.
+    path: PathBuf,
+
+    /// The open lock file.
+    file: File,
+}
+
+impl FileLock {
+    /// Opens the lock file at the given path without locking it.
+    fn open(path: &Path) -> Result<Self, io::Error> {
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        Ok(FileLock { path: path.into(), file })
+    }
+
+    /// Acquires a shared lock, waiting for it if necessary.
+    pub fn shared(path: &Path) -> Result<Self, io::Error> {
+        let res = Self::open(path)?;
+        lock(&res.file, LockKind::Shared)?;
+        Ok(res)
+    }
+
+    /// Tries to acquire an exclusive lock without waiting.
+    ///
+    /// Returns `Ok(None)` if the lock is currently held by someone else.
+    pub fn try_exclusive(path: &Path) -> Result<Option<Self>, io::Error> {
+        let res = Self::open(path)?;
+        match lock(&res.file, LockKind::TryExclusive) {
+            Ok(()) => Ok(Some(res)),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns the path of the lock file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+
+//------------ Helpers -------------------------------------------------------
+
+/// The kind of lock to acquire.
+#[derive(Clone, Copy, Debug)]
+enum LockKind {
+    /// A shared lock, waiting if necessary.
+    Shared,
+
+    /// An exclusive lock, failing if not immediately available.
+    TryExclusive,
+}
+
+#[cfg(unix)]
+fn lock(file: &File, kind: LockKind) -> Result<(), io::Error> {
+    use std::os::unix::io::AsRawFd;
+    use nix::fcntl::{flock, FlockArg};
+
+    let arg = match kind {
+        LockKind::Shared => FlockArg::LockShared,
+        LockKind::TryExclusive => FlockArg::LockExclusiveNonblock,
+    };
+    flock(file.as_raw_fd(), arg).map_err(io::Error::from)
+}
+
+#[cfg(not(unix))]
+fn lock(_file: &File, _kind: LockKind) -> Result<(), io::Error> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "file locking is not supported on this system"
+    ))
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exclusive_and_shared() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lock");
+
+        let excl = FileLock::try_exclusive(&path).unwrap().unwrap();
+        assert!(FileLock::try_exclusive(&path).unwrap().is_none());
+        drop(excl);
+
+        let shared = FileLock::shared(&path).unwrap();
+        assert!(FileLock::try_exclusive(&path).unwrap().is_none());
+        drop(shared);
+        assert!(FileLock::try_exclusive(&path).unwrap().is_some());
+    }
+}
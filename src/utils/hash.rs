@@ -0,0 +1,176 @@
+//! Hashes of repository objects.
+//!
+//! Manifests list the hash of each file of a publication point using the
+//! digest algorithm given by the manifest. This module provides our own
+//! representation of these hashes independently of the algorithms
+//! currently supported by the rpki crate so that the engine and the store
+//! don’t need to hard-code SHA-256 and additional algorithms only need to be
+//! added here.
+
+use std::fmt;
+use bytes::Bytes;
+use ring::digest;
+use rpki::crypto::digest::DigestAlgorithm;
+
+
+//------------ HashAlgorithm -------------------------------------------------
+
+/// A digest algorithm used for object hashes.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum HashAlgorithm {
+    /// SHA-256 as required by RFC 7935.
+    Sha256,
+
+    /// SHA-512.
+    Sha512,
+}
+
+impl HashAlgorithm {
+    /// Returns the algorithm for a digest algorithm of the rpki crate.
+    ///
+    /// Returns `None` if the algorithm isn’t supported.
+    pub fn from_digest_algorithm(algorithm: DigestAlgorithm) -> Option<Self> {
+        if algorithm.is_sha256() {
+            Some(HashAlgorithm::Sha256)
+        }
+        else {
+            None
+        }
+    }
+
+    /// Returns the algorithm for the given hash type of the store format.
+    ///
+    /// Returns `None` if the type is unknown. Type 0 is used for a missing
+    /// hash and thus never returns an algorithm.
+    pub fn from_type(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(HashAlgorithm::Sha256),
+            2 => Some(HashAlgorithm::Sha512),
+            _ => None
+        }
+    }
+
+    /// Returns the hash type used in the store format.
+    pub fn to_type(self) -> u8 {
+        match self {
+            HashAlgorithm::Sha256 => 1,
+            HashAlgorithm::Sha512 => 2,
+        }
+    }
+
+    /// Returns the length of a digest in octets.
+    pub fn digest_len(self) -> usize {
+        self.ring_algorithm().output_len()
+    }
+
+    /// Returns the digest of the given data.
+    pub fn digest(self, data: &[u8]) -> digest::Digest {
+        digest::digest(self.ring_algorithm(), data)
+    }
+
+    /// Returns the ring algorithm for this algorithm.
+    fn ring_algorithm(self) -> &'static digest::Algorithm {
+        match self {
+            HashAlgorithm::Sha256 => &digest::SHA256,
+            HashAlgorithm::Sha512 => &digest::SHA512,
+        }
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            HashAlgorithm::Sha256 => "SHA-256",
+            HashAlgorithm::Sha512 => "SHA-512",
+        })
+    }
+}
+
+
+//------------ ObjectHash ----------------------------------------------------
+
+/// The hash of an object as listed on a manifest.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ObjectHash {
+    /// The digest algorithm of the hash.
+    algorithm: HashAlgorithm,
+
+    /// The hash value.
+    value: Bytes,
+}
+
+impl ObjectHash {
+    /// Creates a new hash from the algorithm and value.
+    ///
+    /// Returns `None` if the value has the wrong length for the algorithm.
+    pub fn new(algorithm: HashAlgorithm, value: Bytes) -> Option<Self> {
+        (value.len() == algorithm.digest_len()).then_some(
+            ObjectHash { algorithm, value }
+        )
+    }
+
+    /// Creates a new hash from a manifest entry.
+    ///
+    /// The `algorithm` is the file hash algorithm of the manifest. Returns
+    /// `None` if the algorithm isn’t supported or the value has the wrong
+    /// length.
+    pub fn from_manifest(
+        value: Bytes, algorithm: DigestAlgorithm
+    ) -> Option<Self> {
+        Self::new(HashAlgorithm::from_digest_algorithm(algorithm)?, value)
+    }
+
+    /// Returns the digest algorithm of the hash.
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
+    }
+
+    /// Returns the hash value.
+    pub fn as_slice(&self) -> &[u8] {
+        self.value.as_ref()
+    }
+
+    /// Returns whether the hash matches the given content.
+    pub fn verify(&self, content: &[u8]) -> bool {
+        self.algorithm.digest(content).as_ref() == self.value.as_ref()
+    }
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn types() {
+        for algorithm in [HashAlgorithm::Sha256, HashAlgorithm::Sha512] {
+            assert_eq!(
+                HashAlgorithm::from_type(algorithm.to_type()),
+                Some(algorithm)
+            );
+        }
+        assert_eq!(HashAlgorithm::from_type(0), None);
+        assert_eq!(
+            HashAlgorithm::from_digest_algorithm(DigestAlgorithm::sha256()),
+            Some(HashAlgorithm::Sha256)
+        );
+    }
+
+    #[test]
+    fn verify() {
+        for algorithm in [HashAlgorithm::Sha256, HashAlgorithm::Sha512] {
+            let hash = ObjectHash::new(
+                algorithm,
+                Bytes::copy_from_slice(algorithm.digest(b"foo").as_ref())
+            ).unwrap();
+            assert!(hash.verify(b"foo"));
+            assert!(!hash.verify(b"bar"));
+        }
+        assert_eq!(
+            ObjectHash::new(HashAlgorithm::Sha512, Bytes::from(vec![0; 32])),
+            None
+        );
+    }
+}
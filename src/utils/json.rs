@@ -8,7 +8,6 @@ use crate::utils::fmt::WriteOrPanic;
 
 /// A helper type for building a JSON-encoded string on the fly.
 ///
-/// Note that the builder only supports strings without control characters.
 pub struct JsonBuilder<'a> {
     target: &'a mut String,
     indent: usize,
@@ -152,10 +151,18 @@ pub fn json_str(val: impl fmt::Display) -> impl fmt::Display {
 
     impl<'a, 'f> fmt::Write for WriteJsonStr<'a, 'f> {
         fn write_str(&mut self, mut s: &str) -> fmt::Result {
-            while let Some(idx) = s.find(|ch| ch == '"' || ch == '\\') {
+            while let Some(idx) = s.find(|ch: char| {
+                ch == '"' || ch == '\\' || ch.is_ascii_control()
+            }) {
                 self.0.write_str(&s[..idx])?;
-                self.0.write_str("\\")?;
-                write!(self.0, "{}", char::from(s.as_bytes()[idx]))?;
+                let ch = s.as_bytes()[idx];
+                if ch.is_ascii_control() {
+                    write!(self.0, "\\u{:04x}", ch)?;
+                }
+                else {
+                    self.0.write_str("\\")?;
+                    write!(self.0, "{}", char::from(ch))?;
+                }
                 s = &s[idx + 1..];
             }
             self.0.write_str(s)
@@ -204,6 +211,10 @@ mod test {
             format!("{}", json_str("foo\\")).as_str(),
             "foo\\\\"
         );
+        assert_eq!(
+            format!("{}", json_str("f\no\u{7f}")).as_str(),
+            "f\\u000ao\\u007f"
+        );
     }
 }
 
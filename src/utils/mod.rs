@@ -2,13 +2,18 @@
 
 pub mod archive;
 pub mod binio;
+pub mod crl;
 pub mod date;
 pub mod dump;
 pub mod fatal;
+pub mod flock;
 pub mod fmt;
+pub mod hash;
 pub mod json;
 pub mod net;
+pub mod ratelimit;
 pub mod str;
+pub mod secret;
 pub mod sync;
 pub mod tls;
 pub mod uri;
@@ -1,8 +1,15 @@
 //! Utility functions related to networking.
 
-use std::net::{SocketAddr, TcpListener as StdListener};
+use std::{fmt, io, thread};
+use std::collections::HashMap;
+use std::net::{
+    SocketAddr, TcpListener as StdListener, TcpStream, ToSocketAddrs
+};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
 use log::error;
 use crate::error::ExitError;
+use crate::utils::sync::Mutex;
 
 
 pub fn bind(addr: &SocketAddr) -> Result<StdListener, ExitError> {
@@ -20,3 +27,235 @@ pub fn bind(addr: &SocketAddr) -> Result<StdListener, ExitError> {
     Ok(listener)
 }
 
+
+//------------ connect -------------------------------------------------------
+
+/// The delay before starting the next connection attempt.
+///
+/// This is the ‘Connection Attempt Delay’ recommended by RFC 8305.
+const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Establishes a TCP connection to one of the given addresses.
+///
+/// Follows the approach of RFC 8305: The addresses are reordered so that
+/// address families alternate, starting with the family of the first
+/// address. Connection attempts are started one after another, each
+/// [`CONNECTION_ATTEMPT_DELAY`] after the previous one or as soon as the
+/// previous one failed. The first connection to be established is returned
+/// and all others are dropped. Each attempt may take at most `timeout`.
+///
+/// If none of the attempts succeeds, returns the error of the last failed
+/// attempt.
+pub fn connect(
+    addrs: &[SocketAddr], timeout: Duration,
+) -> Result<TcpStream, io::Error> {
+    let addrs = interleave_families(addrs);
+    let (tx, rx) = mpsc::channel();
+    let mut next = addrs.iter();
+    let mut pending = 0usize;
+    let mut last_err = None;
+    loop {
+        let started = match next.next() {
+            Some(addr) => {
+                let addr = *addr;
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    // If the receiver is gone, someone else won the race.
+                    let _ = tx.send(
+                        TcpStream::connect_timeout(&addr, timeout)
+                    );
+                });
+                pending += 1;
+                true
+            }
+            None => false,
+        };
+        if pending == 0 {
+            break
+        }
+        let res = if started {
+            match rx.recv_timeout(CONNECTION_ATTEMPT_DELAY) {
+                Ok(res) => res,
+                Err(_) => continue,
+            }
+        }
+        else {
+            match rx.recv() {
+                Ok(res) => res,
+                Err(_) => break,
+            }
+        };
+        pending -= 1;
+        match res {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_err = Some(err),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, "no addresses to connect to")
+    }))
+}
+
+/// Reorders addresses so that address families alternate.
+///
+/// The family of the first address is kept first. Within a family, the
+/// original order is preserved.
+fn interleave_families(addrs: &[SocketAddr]) -> Vec<SocketAddr> {
+    let first = match addrs.first() {
+        Some(addr) => AddressFamily::of(addr),
+        None => return Vec::new(),
+    };
+    let (mut preferred, mut other) = (Vec::new(), Vec::new());
+    for addr in addrs {
+        if AddressFamily::of(addr) == first {
+            preferred.push(*addr)
+        }
+        else {
+            other.push(*addr)
+        }
+    }
+    let mut res = Vec::with_capacity(addrs.len());
+    let mut preferred = preferred.into_iter();
+    let mut other = other.into_iter();
+    loop {
+        match (preferred.next(), other.next()) {
+            (None, None) => break,
+            (left, right) => {
+                res.extend(left);
+                res.extend(right);
+            }
+        }
+    }
+    res
+}
+
+
+//------------ AddressFamily -------------------------------------------------
+
+/// The address family used for a connection.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum AddressFamily {
+    /// IP version 4.
+    Ipv4,
+
+    /// IP version 6.
+    Ipv6,
+}
+
+impl AddressFamily {
+    /// Returns the address family of a socket address.
+    pub fn of(addr: &SocketAddr) -> Self {
+        match addr {
+            SocketAddr::V4(_) => AddressFamily::Ipv4,
+            SocketAddr::V6(_) => AddressFamily::Ipv6,
+        }
+    }
+
+    /// Returns the IP version number of the family.
+    pub fn version(self) -> u8 {
+        match self {
+            AddressFamily::Ipv4 => 4,
+            AddressFamily::Ipv6 => 6,
+        }
+    }
+
+    /// Returns a string representation of the family.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AddressFamily::Ipv4 => "ipv4",
+            AddressFamily::Ipv6 => "ipv6",
+        }
+    }
+}
+
+impl fmt::Display for AddressFamily {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+
+//------------ DnsCache ------------------------------------------------------
+
+/// A short-lived cache of resolved host names.
+///
+/// Results of resolving a host name are kept for a fixed time to live so
+/// that repeatedly connecting to the same server doesn’t require repeated
+/// lookups. Failed lookups are not cached.
+#[derive(Debug)]
+pub struct DnsCache {
+    /// The time to live for cached entries.
+    ttl: Duration,
+
+    /// The cached entries by host name and port.
+    entries: Mutex<HashMap<(String, u16), CachedAddrs>>,
+}
+
+/// The addresses for a host name and the time they were resolved.
+type CachedAddrs = (Instant, Vec<SocketAddr>);
+
+impl DnsCache {
+    /// Creates a new, empty cache using the given time to live.
+    pub fn new(ttl: Duration) -> Self {
+        DnsCache {
+            ttl,
+            entries: Default::default(),
+        }
+    }
+
+    /// Resolves a host name and port into a list of socket addresses.
+    ///
+    /// The host name may also be an IPv4 address or an IPv6 address
+    /// optionally enclosed in square brackets.
+    pub fn resolve(
+        &self, host: &str, port: u16
+    ) -> Result<Vec<SocketAddr>, io::Error> {
+        let host = host.strip_prefix('[').and_then(|host| {
+            host.strip_suffix(']')
+        }).unwrap_or(host);
+        let key = (host.to_ascii_lowercase(), port);
+        if let Some((time, addrs)) = self.entries.lock().get(&key) {
+            if time.elapsed() < self.ttl {
+                return Ok(addrs.clone())
+            }
+        }
+        let addrs: Vec<_> = (host, port).to_socket_addrs()?.collect();
+        self.entries.lock().insert(key, (Instant::now(), addrs.clone()));
+        Ok(addrs)
+    }
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn interleave() {
+        let addrs: Vec<SocketAddr> = [
+            "[2001:db8::1]:873", "[2001:db8::2]:873", "[2001:db8::3]:873",
+            "192.0.2.1:873", "192.0.2.2:873",
+        ].iter().map(|addr| addr.parse().unwrap()).collect();
+        assert_eq!(
+            interleave_families(&addrs),
+            [addrs[0], addrs[3], addrs[1], addrs[4], addrs[2]]
+        );
+        assert!(interleave_families(&[]).is_empty());
+    }
+
+    #[test]
+    fn connect_skips_unreachable() {
+        let listener = StdListener::bind("127.0.0.1:0").unwrap();
+        let good = listener.local_addr().unwrap();
+        let bad = {
+            let listener = StdListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap()
+        };
+        let stream = connect(&[bad, good], Duration::from_secs(5)).unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), good);
+        assert!(connect(&[bad], Duration::from_secs(5)).is_err());
+        assert!(connect(&[], Duration::from_secs(5)).is_err());
+    }
+}
@@ -0,0 +1,167 @@
+//! Limiting the bandwidth of transfers.
+//!
+//! This module provides a simple token bucket in [`TokenBucket`] that can
+//! be shared between all transfers that should be limited together. A
+//! [`RateLimit`] combines several buckets so that a transfer can be subject
+//! to both a global limit and a more specific one. Finally, [`LimitedRead`]
+//! applies a rate limit to a reader.
+
+use std::{io, thread};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::utils::sync::Mutex;
+
+
+//------------ TokenBucket ---------------------------------------------------
+
+/// A token bucket limiting the number of bytes per second.
+///
+/// The bucket holds up to one second worth of tokens. Taking more tokens
+/// than available puts the bucket into debt and makes the taker wait until
+/// the debt is paid off. This keeps the average rate at the limit even for
+/// large reads.
+#[derive(Debug)]
+pub struct TokenBucket {
+    /// The rate in bytes per second.
+    rate: u64,
+
+    /// The current state of the bucket.
+    state: Mutex<BucketState>,
+}
+
+/// The mutable state of a token bucket.
+#[derive(Debug)]
+struct BucketState {
+    /// The number of tokens currently available.
+    ///
+    /// This becomes negative if the bucket is in debt.
+    tokens: f64,
+
+    /// The time the tokens were last updated.
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// Creates a new, full bucket for the given rate in bytes per second.
+    ///
+    /// # Panics
+    ///
+    /// The function panics if `rate` is zero.
+    pub fn new(rate: u64) -> Self {
+        assert!(rate > 0);
+        TokenBucket {
+            rate,
+            state: Mutex::new(BucketState {
+                tokens: rate as f64,
+                updated: Instant::now(),
+            })
+        }
+    }
+
+    /// Returns the rate of the bucket in bytes per second.
+    pub fn rate(&self) -> u64 {
+        self.rate
+    }
+
+    /// Takes the given number of tokens from the bucket.
+    ///
+    /// Returns the time the caller needs to wait before it is allowed to
+    /// continue.
+    fn take(&self, amount: usize) -> Duration {
+        let rate = self.rate as f64;
+        let mut state = self.state.lock();
+        let now = Instant::now();
+        let elapsed = now.duration_since(state.updated).as_secs_f64();
+        state.tokens = (state.tokens + elapsed * rate).min(rate);
+        state.tokens -= amount as f64;
+        state.updated = now;
+        if state.tokens < 0. {
+            Duration::from_secs_f64(-state.tokens / rate)
+        }
+        else {
+            Duration::ZERO
+        }
+    }
+}
+
+
+//------------ RateLimit -----------------------------------------------------
+
+/// A set of token buckets a transfer is subject to.
+///
+/// The default value does not limit at all.
+#[derive(Clone, Debug, Default)]
+pub struct RateLimit {
+    /// The buckets to take tokens from.
+    buckets: Vec<Arc<TokenBucket>>,
+}
+
+impl RateLimit {
+    /// Adds a bucket to the limit.
+    pub fn push(&mut self, bucket: Arc<TokenBucket>) {
+        self.buckets.push(bucket)
+    }
+
+    /// Returns whether the limit actually limits anything.
+    pub fn is_unlimited(&self) -> bool {
+        self.buckets.is_empty()
+    }
+
+    /// Accounts for a transfer of the given number of bytes.
+    ///
+    /// Blocks the current thread until the transfer is allowed under all
+    /// buckets.
+    pub fn take(&self, amount: usize) {
+        let wait = self.buckets.iter().map(|bucket| {
+            bucket.take(amount)
+        }).max().unwrap_or_default();
+        if !wait.is_zero() {
+            thread::sleep(wait)
+        }
+    }
+}
+
+
+//------------ LimitedRead ---------------------------------------------------
+
+/// A reader that limits the rate at which data is read.
+#[derive(Debug)]
+pub struct LimitedRead<R> {
+    /// The wrapped reader.
+    reader: R,
+
+    /// The rate limit to apply.
+    limit: RateLimit,
+}
+
+impl<R> LimitedRead<R> {
+    /// Creates a new reader wrapping `reader` and applying `limit`.
+    pub fn new(reader: R, limit: RateLimit) -> Self {
+        LimitedRead { reader, limit }
+    }
+}
+
+impl<R: io::Read> io::Read for LimitedRead<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        let res = self.reader.read(buf)?;
+        self.limit.take(res);
+        Ok(res)
+    }
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bucket_debt() {
+        let bucket = TokenBucket::new(1000);
+        assert!(bucket.take(1000).is_zero());
+        let wait = bucket.take(500);
+        assert!(wait > Duration::from_millis(400));
+        assert!(wait <= Duration::from_millis(500));
+    }
+}
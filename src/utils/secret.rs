@@ -0,0 +1,186 @@
+//! Access to secrets kept outside of the configuration.
+//!
+//! Credentials such as TLS keys or access tokens shouldn’t have to be
+//! included in the config file in plain text. Instead, the config refers
+//! to them via a [`Secret`] which describes where the actual value can be
+//! found: in a file, in an environment variable, or in the output of an
+//! external command.
+
+use std::{env, fmt, fs, io};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use log::error;
+use crate::error::Failed;
+
+
+//------------ Secret --------------------------------------------------------
+
+/// A reference to a secret value.
+///
+/// A secret is given as a string starting with a prefix that selects the
+/// source of the secret:
+///
+/// * `file:` followed by a path reads the secret from the file,
+/// * `env:` followed by a name reads the secret from the environment
+///   variable of that name, and
+/// * `command:` followed by a command line runs the command and uses its
+///   output as the secret. The command line is split at white space. It is
+///   not passed to a shell.
+///
+/// A string without any of these prefixes is taken as the path of a file.
+/// This keeps options that used to take a path working.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Secret {
+    /// The secret is the content of a file.
+    File(PathBuf),
+
+    /// The secret is the value of an environment variable.
+    Env(String),
+
+    /// The secret is the output of a command.
+    Command(String),
+}
+
+impl Secret {
+    /// Creates a secret from its string representation.
+    ///
+    /// Relative paths of file secrets are resolved against `base`.
+    pub fn from_str_in(s: &str, base: &Path) -> Result<Self, &'static str> {
+        if let Some(name) = s.strip_prefix("env:") {
+            if name.is_empty() {
+                return Err("empty environment variable name")
+            }
+            Ok(Secret::Env(name.into()))
+        }
+        else if let Some(command) = s.strip_prefix("command:") {
+            if command.trim().is_empty() {
+                return Err("empty command")
+            }
+            Ok(Secret::Command(command.into()))
+        }
+        else {
+            let path = s.strip_prefix("file:").unwrap_or(s);
+            if path.is_empty() {
+                return Err("empty path")
+            }
+            Ok(Secret::File(base.join(path)))
+        }
+    }
+
+    /// Loads the value of the secret.
+    ///
+    /// The secret is used for `what`, which is used in error messages.
+    /// Errors are logged.
+    pub fn load(&self, what: &str) -> Result<Vec<u8>, Failed> {
+        self.load_raw().map_err(|err| {
+            error!("Failed to load {} from {}: {}", what, self, err);
+            Failed
+        })
+    }
+
+    /// Loads the value of the secret as a string.
+    ///
+    /// A trailing line feed is removed since files and command output
+    /// often end in one.
+    pub fn load_string(&self, what: &str) -> Result<String, Failed> {
+        let mut res = String::from_utf8(self.load(what)?).map_err(|_| {
+            error!("Failed to load {} from {}: not valid UTF-8", what, self);
+            Failed
+        })?;
+        if res.ends_with('\n') {
+            res.pop();
+            if res.ends_with('\r') {
+                res.pop();
+            }
+        }
+        Ok(res)
+    }
+
+    /// Loads the value of the secret without logging errors.
+    fn load_raw(&self) -> Result<Vec<u8>, io::Error> {
+        match self {
+            Secret::File(path) => fs::read(path),
+            Secret::Env(name) => {
+                env::var(name).map(String::into_bytes).map_err(|err| {
+                    io::Error::new(io::ErrorKind::NotFound, err)
+                })
+            }
+            Secret::Command(command) => {
+                let mut args = command.split_whitespace();
+                let program = args.next().ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "empty command")
+                })?;
+                let output = Command::new(program).args(args).output()?;
+                if !output.status.success() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        format!("command failed with {}", output.status)
+                    ))
+                }
+                Ok(output.stdout)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Secret::File(path) => write!(f, "file:{}", path.display()),
+            Secret::Env(name) => write!(f, "env:{}", name),
+            Secret::Command(command) => write!(f, "command:{}", command),
+        }
+    }
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_str_in() {
+        let base = Path::new("/etc/routinator");
+        assert_eq!(
+            Secret::from_str_in("key.pem", base).unwrap(),
+            Secret::File("/etc/routinator/key.pem".into())
+        );
+        assert_eq!(
+            Secret::from_str_in("file:/tmp/key.pem", base).unwrap(),
+            Secret::File("/tmp/key.pem".into())
+        );
+        assert_eq!(
+            Secret::from_str_in("env:TOKEN", base).unwrap(),
+            Secret::Env("TOKEN".into())
+        );
+        assert_eq!(
+            Secret::from_str_in("command:pass show rpki", base).unwrap(),
+            Secret::Command("pass show rpki".into())
+        );
+        assert!(Secret::from_str_in("env:", base).is_err());
+        assert!(Secret::from_str_in("command: ", base).is_err());
+        assert!(Secret::from_str_in("", base).is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secret");
+        fs::write(&path, "foo\n").unwrap();
+        assert_eq!(
+            Secret::File(path).load_string("test").unwrap(), "foo"
+        );
+        assert_eq!(
+            Secret::Command("echo bar".into()).load_string("test").unwrap(),
+            "bar"
+        );
+        assert!(Secret::Command("false".into()).load("test").is_err());
+        assert!(
+            Secret::Env("ROUTINATOR_TEST_NO_SUCH_VAR".into())
+                .load("test").is_err()
+        );
+    }
+}
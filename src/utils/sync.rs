@@ -1,6 +1,7 @@
 //! Utilities for concurrency.
 
-use std::sync::{Mutex as StdMutex, RwLock as StdRwLock};
+use std::collections::HashMap;
+use std::sync::{Condvar, Mutex as StdMutex, RwLock as StdRwLock};
 
 pub use std::sync::{MutexGuard, RwLockReadGuard, RwLockWriteGuard};
 
@@ -13,7 +14,7 @@ pub struct RwLock<T: ?Sized>(StdRwLock<T>);
 
 impl<T> RwLock<T> {
     /// Creates a new read/write lock in unlocked state.
-    pub fn new(t: T) -> Self {
+    pub const fn new(t: T) -> Self {
         RwLock(StdRwLock::new(t))
     }
 }
@@ -87,3 +88,241 @@ impl<T: ?Sized> Mutex<T> {
     }
 }
 
+
+//------------ HostLimit -----------------------------------------------------
+
+/// Limits the number of concurrent operations targeting the same host.
+///
+/// Each host may have at most a fixed number of operations running at any
+/// time. Further attempts to acquire a permit for the host block until one
+/// of the running operations has finished. Host names are compared ignoring
+/// ASCII case.
+#[derive(Debug)]
+pub struct HostLimit {
+    /// The maximum number of concurrent operations per host.
+    limit: usize,
+
+    /// The number of currently running operations per host.
+    running: Mutex<HashMap<String, usize>>,
+
+    /// Signalled whenever a permit is released.
+    released: Condvar,
+}
+
+impl HostLimit {
+    /// Creates a new limit allowing `limit` operations per host.
+    ///
+    /// # Panics
+    ///
+    /// The function panics if `limit` is zero.
+    pub fn new(limit: usize) -> Self {
+        assert!(limit > 0);
+        HostLimit {
+            limit,
+            running: Default::default(),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Returns the maximum number of concurrent operations per host.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Acquires a permit for an operation targeting the given host.
+    ///
+    /// Blocks until fewer than the limit of operations are running for the
+    /// host. The permit is released when the returned value is dropped.
+    pub fn acquire(&self, host: &str) -> HostPermit<'_> {
+        let host = host.to_ascii_lowercase();
+        let mut running = self.running.lock();
+        while running.get(&host).copied().unwrap_or(0) >= self.limit {
+            running = self.released.wait(running).expect(
+                "acquiring a poisoned mutex"
+            );
+        }
+        *running.entry(host.clone()).or_default() += 1;
+        HostPermit { limit: self, host }
+    }
+}
+
+
+//------------ HostPermit ----------------------------------------------------
+
+/// A permit to run an operation targeting a host.
+///
+/// A value of this type is returned by [`HostLimit::acquire`]. The permit
+/// is released when the value is dropped.
+#[derive(Debug)]
+pub struct HostPermit<'a> {
+    /// The limit the permit was acquired from.
+    limit: &'a HostLimit,
+
+    /// The normalized host name.
+    host: String,
+}
+
+impl<'a> Drop for HostPermit<'a> {
+    fn drop(&mut self) {
+        let mut running = self.limit.running.lock();
+        if let Some(count) = running.get_mut(&self.host) {
+            *count -= 1;
+            if *count == 0 {
+                running.remove(&self.host);
+            }
+        }
+        drop(running);
+        self.limit.released.notify_all();
+    }
+}
+
+
+//------------ Semaphore -----------------------------------------------------
+
+/// Limits the number of concurrent operations of some kind.
+///
+/// At most a fixed number of operations may run at any time. Further
+/// attempts to acquire a permit block until one of the running operations
+/// has finished.
+#[derive(Debug)]
+pub struct Semaphore {
+    /// The maximum number of concurrent operations.
+    limit: usize,
+
+    /// The number of currently running operations.
+    running: Mutex<usize>,
+
+    /// Signalled whenever a permit is released.
+    released: Condvar,
+}
+
+impl Semaphore {
+    /// Creates a new semaphore allowing `limit` concurrent operations.
+    ///
+    /// # Panics
+    ///
+    /// The function panics if `limit` is zero.
+    pub fn new(limit: usize) -> Self {
+        assert!(limit > 0);
+        Semaphore {
+            limit,
+            running: Mutex::new(0),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Returns whether all permits are currently taken.
+    ///
+    /// Since other threads may release or acquire permits at any time, the
+    /// result is only a hint.
+    pub fn is_exhausted(&self) -> bool {
+        *self.running.lock() >= self.limit
+    }
+
+    /// Acquires a permit.
+    ///
+    /// Blocks until fewer than the limit of operations are running. The
+    /// permit is released when the returned value is dropped.
+    pub fn acquire(&self) -> SemaphorePermit<'_> {
+        let mut running = self.running.lock();
+        while *running >= self.limit {
+            running = self.released.wait(running).expect(
+                "acquiring a poisoned mutex"
+            );
+        }
+        *running += 1;
+        SemaphorePermit { semaphore: self }
+    }
+}
+
+
+//------------ SemaphorePermit -----------------------------------------------
+
+/// A permit to run an operation limited by a semaphore.
+///
+/// A value of this type is returned by [`Semaphore::acquire`]. The permit
+/// is released when the value is dropped.
+#[derive(Debug)]
+pub struct SemaphorePermit<'a> {
+    /// The semaphore the permit was acquired from.
+    semaphore: &'a Semaphore,
+}
+
+impl<'a> Drop for SemaphorePermit<'a> {
+    fn drop(&mut self) {
+        *self.semaphore.running.lock() -= 1;
+        self.semaphore.released.notify_one();
+    }
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn host_limit() {
+        let limit = Arc::new(HostLimit::new(2));
+        let current = Arc::new(AtomicUsize::new(0));
+        let max = Arc::new(AtomicUsize::new(0));
+        let threads: Vec<_> = (0..8).map(|i| {
+            let limit = limit.clone();
+            let current = current.clone();
+            let max = max.clone();
+            thread::spawn(move || {
+                let host = if i % 2 == 0 { "rpki.example" }
+                           else { "RPKI.example" };
+                let _permit = limit.acquire(host);
+                let now = current.fetch_add(1, Ordering::SeqCst) + 1;
+                max.fetch_max(now, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(10));
+                current.fetch_sub(1, Ordering::SeqCst);
+            })
+        }).collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert!(max.load(Ordering::SeqCst) <= 2);
+        assert!(limit.running.lock().is_empty());
+
+        // A different host isn’t affected.
+        let _one = limit.acquire("one.example");
+        let _two = limit.acquire("one.example");
+        let _other = limit.acquire("other.example");
+    }
+
+    #[test]
+    fn semaphore() {
+        let semaphore = Arc::new(Semaphore::new(3));
+        let current = Arc::new(AtomicUsize::new(0));
+        let max = Arc::new(AtomicUsize::new(0));
+        let threads: Vec<_> = (0..8).map(|_| {
+            let semaphore = semaphore.clone();
+            let current = current.clone();
+            let max = max.clone();
+            thread::spawn(move || {
+                let _permit = semaphore.acquire();
+                let now = current.fetch_add(1, Ordering::SeqCst) + 1;
+                max.fetch_max(now, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(10));
+                current.fetch_sub(1, Ordering::SeqCst);
+            })
+        }).collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert!(max.load(Ordering::SeqCst) <= 3);
+        assert!(!semaphore.is_exhausted());
+
+        let permits: Vec<_> = (0..3).map(|_| semaphore.acquire()).collect();
+        assert!(semaphore.is_exhausted());
+        drop(permits);
+        assert!(!semaphore.is_exhausted());
+    }
+}
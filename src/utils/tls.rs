@@ -1,43 +1,133 @@
 //! Utility functions for dealing with TLS.
 
-use std::io;
+use std::{fmt, io};
 use std::fs::File;
 use std::path::Path;
 use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use log::error;
 use futures::{pin_mut, ready, TryFuture};
 use futures::future::Either;
 use pin_project_lite::pin_project;
+use ring::digest;
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 use tokio::net::TcpStream;
 use tokio_rustls::{Accept, TlsAcceptor};
-use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::{
+    CertificateError, DigitallySignedStruct, Error as TlsError, RootCertStore,
+    SignatureScheme,
+};
+use tokio_rustls::rustls::client::WebPkiServerVerifier;
+use tokio_rustls::rustls::client::danger::{
+    HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+};
+use tokio_rustls::rustls::crypto::{
+    verify_tls12_signature, verify_tls13_signature, WebPkiSupportedAlgorithms,
+};
+use tokio_rustls::rustls::pki_types::{
+    CertificateDer, PrivateKeyDer, ServerName, UnixTime,
+};
 use tokio_rustls::server::TlsStream;
 use crate::error::ExitError;
+use crate::utils::secret::Secret;
 
-pub use tokio_rustls::rustls::ServerConfig;
+pub use tokio_rustls::rustls::{ClientConfig, ServerConfig};
 
 
 //------------ create_server_config -----------------------------------------
 
 /// Creates the TLS server config.
 ///
-/// The service this config is for should be given through `service`. This is
-/// used for logging.
+/// The private key is loaded from the secret `key`. The service this config
+/// is for should be given through `service`. This is used for logging.
 pub fn create_server_config(
-    service: &str, key_path: &Path, cert_path: &Path
+    service: &str, key: &Secret, cert_path: &Path
 ) -> Result<ServerConfig, ExitError> {
 
     ServerConfig::builder()
         .with_no_client_auth()
-        .with_single_cert(read_certs(cert_path)?, read_key(key_path)?)
+        .with_single_cert(read_certs(cert_path)?, read_key(key)?)
         .map_err(|err| {
             error!("Failed to create {} TLS server config: {}", service, err);
             ExitError::Generic
         })
 }
 
+
+//------------ create_client_config -----------------------------------------
+
+/// Creates a TLS client config with its own trust settings.
+///
+/// The config does not use the system trust store. Instead, if `ca_bundle`
+/// is given, server certificates are verified against the CA certificates
+/// in that PEM file. If `pins` is not empty, the server’s end-entity
+/// certificate must additionally have one of the given fingerprints. If
+/// only pins are given, a matching certificate is accepted without
+/// verifying its chain or server name.
+///
+/// The service this config is for should be given through `service`. This
+/// is used for logging.
+pub fn create_client_config(
+    service: &str, ca_bundle: Option<&Path>, pins: &[CertFingerprint],
+) -> Result<ClientConfig, ExitError> {
+    let verifier = match ca_bundle {
+        Some(path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in read_certs(path)? {
+                roots.add(cert).map_err(|err| {
+                    error!(
+                        "Invalid CA certificate in '{}': {}",
+                        path.display(), err
+                    );
+                    ExitError::Generic
+                })?;
+            }
+            let verifier = WebPkiServerVerifier::builder(
+                roots.into()
+            ).build().map_err(|err| {
+                error!(
+                    "Failed to create {} TLS client config: {}", service, err
+                );
+                ExitError::Generic
+            })?;
+            if pins.is_empty() {
+                return Ok(
+                    ClientConfig::builder()
+                        .with_webpki_verifier(verifier)
+                        .with_no_client_auth()
+                )
+            }
+            Some(verifier)
+        }
+        None => {
+            if pins.is_empty() {
+                error!(
+                    "Failed to create {} TLS client config: \
+                     neither CA bundle nor pinned certificates given.",
+                    service
+                );
+                return Err(ExitError::Generic)
+            }
+            None
+        }
+    };
+    Ok(
+        ClientConfig::builder()
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(PinningVerifier {
+                verifier,
+                pins: pins.into(),
+                algorithms: {
+                    tokio_rustls::rustls::crypto::ring::default_provider()
+                        .signature_verification_algorithms
+                },
+            }))
+            .with_no_client_auth()
+    )
+}
+
 /// Reads the certificates from the given PEM file.
 fn read_certs(
     cert_path: &Path
@@ -61,25 +151,18 @@ fn read_certs(
     })
 }
 
-/// Reads the first private key from the given PEM file.
+/// Reads the private key from the given PEM encoded secret.
 ///
 /// The key may be a PKCS#1 RSA private key, a PKCS#8 private key, or a
 /// SEC1 encoded EC private key. All other PEM items are ignored.
 ///
-/// Errors out if opening or reading the file fails or if there isn’t exactly
-/// one private key in the file.
-fn read_key(key_path: &Path) -> Result<PrivateKeyDer<'static>, ExitError> {
+/// Errors out if loading the secret fails or if there isn’t exactly one
+/// private key in it.
+fn read_key(secret: &Secret) -> Result<PrivateKeyDer<'static>, ExitError> {
     use rustls_pemfile::Item::*;
 
-    let mut key_file = io::BufReader::new(
-        File::open(key_path).map_err(|err| {
-            error!(
-                "Failed to open TLS key file '{}': {}.",
-                key_path.display(), err
-            );
-            ExitError::Generic
-        })?
-    );
+    let data = secret.load("TLS key").map_err(|_| ExitError::Generic)?;
+    let mut key_file = data.as_slice();
 
     let mut key = None;
 
@@ -88,8 +171,8 @@ fn read_key(key_path: &Path) -> Result<PrivateKeyDer<'static>, ExitError> {
     {
         let item = item.map_err(|err| {
             error!(
-                "Failed to read TLS key file '{}': {}.",
-                key_path.display(), err
+                "Failed to read TLS key '{}': {}.",
+                secret, err
             );
             ExitError::Generic
         })?;
@@ -102,8 +185,8 @@ fn read_key(key_path: &Path) -> Result<PrivateKeyDer<'static>, ExitError> {
         };
         if key.is_some() {
             error!(
-                "TLS key file '{}' contains multiple keys.",
-                key_path.display()
+                "TLS key '{}' contains multiple keys.",
+                secret
             );
             return Err(ExitError::Generic)
         }
@@ -114,8 +197,8 @@ fn read_key(key_path: &Path) -> Result<PrivateKeyDer<'static>, ExitError> {
         Some(key) => Ok(key),
         None => {
              error!(
-                "TLS key file '{}' does not contain any usable keys.",
-                key_path.display()
+                "TLS key '{}' does not contain any usable keys.",
+                secret
             );
             Err(ExitError::Generic)
        }
@@ -123,6 +206,123 @@ fn read_key(key_path: &Path) -> Result<PrivateKeyDer<'static>, ExitError> {
 }
 
 
+//------------ PinningVerifier -----------------------------------------------
+
+/// A server certificate verifier checking certificate fingerprints.
+#[derive(Debug)]
+struct PinningVerifier {
+    /// A verifier for the certificate chain if a CA bundle was given.
+    verifier: Option<Arc<WebPkiServerVerifier>>,
+
+    /// The acceptable fingerprints of the end-entity certificate.
+    pins: Vec<CertFingerprint>,
+
+    /// The algorithms for verifying handshake signatures.
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl ServerCertVerifier for PinningVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, TlsError> {
+        if let Some(verifier) = self.verifier.as_ref() {
+            verifier.verify_server_cert(
+                end_entity, intermediates, server_name, ocsp_response, now
+            )?;
+        }
+        if !self.pins.contains(&CertFingerprint::of(end_entity)) {
+            return Err(TlsError::InvalidCertificate(
+                CertificateError::ApplicationVerificationFailure
+            ))
+        }
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, TlsError> {
+        verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, TlsError> {
+        verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
+
+
+//------------ CertFingerprint -----------------------------------------------
+
+/// The SHA-256 fingerprint of a DER encoded certificate.
+///
+/// The textual representation is the fingerprint in hexadecimal digits
+/// with the octets separated by colons, as printed by
+/// `openssl x509 -fingerprint -sha256`. When parsing, the colons are
+/// optional and the case of the digits is ignored.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct CertFingerprint([u8; 32]);
+
+impl CertFingerprint {
+    /// Returns the fingerprint of the given DER encoded certificate.
+    pub fn of(cert: &[u8]) -> Self {
+        let mut res = [0u8; 32];
+        res.copy_from_slice(
+            digest::digest(&digest::SHA256, cert).as_ref()
+        );
+        CertFingerprint(res)
+    }
+}
+
+impl FromStr for CertFingerprint {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut digits = s.chars().filter(|ch| *ch != ':').map(|ch| {
+            ch.to_digit(16).ok_or("invalid hex digit in fingerprint")
+        });
+        let mut res = [0u8; 32];
+        for item in &mut res {
+            match (digits.next(), digits.next()) {
+                (Some(high), Some(low)) => *item = (high? << 4 | low?) as u8,
+                _ => return Err("fingerprint too short"),
+            }
+        }
+        if digits.next().is_some() {
+            return Err("fingerprint too long")
+        }
+        Ok(CertFingerprint(res))
+    }
+}
+
+impl fmt::Display for CertFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, item) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(":")?;
+            }
+            write!(f, "{:02X}", item)?;
+        }
+        Ok(())
+    }
+}
+
+
 //------------ TlsTcpStream --------------------------------------------------
 
 pin_project! {
@@ -337,3 +537,29 @@ impl AsyncWrite for MaybeTlsTcpStream {
     }
 }
 
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cert_fingerprint() {
+        let s = "E3:B0:C4:42:98:FC:1C:14:9A:FB:F4:C8:99:6F:B9:24:\
+                 27:AE:41:E4:64:9B:93:4C:A4:95:99:1B:78:52:B8:55";
+        let fingerprint = CertFingerprint::of(b"");
+        assert_eq!(fingerprint.to_string(), s);
+        assert_eq!(CertFingerprint::from_str(s), Ok(fingerprint));
+        assert_eq!(
+            CertFingerprint::from_str(
+                &s.replace(':', "").to_ascii_lowercase()
+            ),
+            Ok(fingerprint)
+        );
+        assert!(CertFingerprint::from_str(&s[3..]).is_err());
+        assert!(CertFingerprint::from_str(&format!("{}:00", s)).is_err());
+        assert!(CertFingerprint::from_str(&s.replace('E', "G")).is_err());
+    }
+}
@@ -1,6 +1,7 @@
-//! Utilities for handling rsync and HTTPS URIs.
+//! Utilities for handling rsync, HTTPS, and SSH URIs.
 
 use std::borrow::Cow;
+use std::fmt;
 use std::net::IpAddr;
 use std::path::PathBuf;
 use std::str::FromStr;
@@ -109,3 +110,251 @@ impl UriExt for uri::Rsync {
     }
 }
 
+
+
+//------------ UriRewrite ----------------------------------------------------
+
+/// A set of rules for rewriting URIs before accessing them.
+///
+/// Each rule consists of a prefix and its replacement. When rewriting a
+/// URI, the first rule with a matching prefix is used and the prefix is
+/// replaced. If no rule matches, the URI is used unchanged.
+///
+/// This is only used when actually accessing a remote server. The URI
+/// identifying the accessed data remains unchanged.
+#[derive(Clone, Debug, Default)]
+pub struct UriRewrite {
+    /// The rules as pairs of prefix and replacement.
+    rules: Vec<(String, String)>,
+}
+
+impl UriRewrite {
+    /// Creates a new rewriter from a list of rules.
+    ///
+    /// The rules are not checked. Use [`check_rule`][Self::check_rule]
+    /// for that.
+    pub fn new(rules: &[(String, String)]) -> Self {
+        UriRewrite { rules: rules.into() }
+    }
+
+    /// Checks that a rule is acceptable.
+    ///
+    /// Both the prefix and the replacement must start with the same
+    /// scheme, either `rsync://` or `https://`, followed by a non-empty
+    /// authority.
+    pub fn check_rule(from: &str, to: &str) -> Result<(), &'static str> {
+        let scheme = if from.starts_with("rsync://") {
+            "rsync://"
+        }
+        else if from.starts_with("https://") {
+            "https://"
+        }
+        else {
+            return Err("prefix must start with rsync:// or https://")
+        };
+        if !to.starts_with(scheme) {
+            return Err("prefix and replacement must use the same scheme")
+        }
+        if from.len() == scheme.len() || to.len() == scheme.len() {
+            return Err("prefix and replacement must contain a host name")
+        }
+        Ok(())
+    }
+
+    /// Returns whether there are no rules.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Rewrites a URI given as a string.
+    pub fn rewrite<'a>(&self, uri: &'a str) -> Cow<'a, str> {
+        for (from, to) in &self.rules {
+            if let Some(tail) = uri.strip_prefix(from.as_str()) {
+                let mut res = String::with_capacity(to.len() + tail.len());
+                res.push_str(to);
+                res.push_str(tail);
+                return Cow::Owned(res)
+            }
+        }
+        Cow::Borrowed(uri)
+    }
+}
+
+
+//------------ SshUri --------------------------------------------------------
+
+/// The location of an rsync module accessible via SSH.
+///
+/// The location is given as a URI of the form
+/// `ssh://[user@]host[:port]/path/`. The path is the absolute path of the
+/// module’s directory on the host.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SshUri {
+    /// The user to log in as, if given.
+    user: Option<String>,
+
+    /// The host name or bracketed IPv6 address.
+    host: String,
+
+    /// The port, if given.
+    port: Option<u16>,
+
+    /// The path of the directory, starting and ending with a slash.
+    path: String,
+}
+
+impl SshUri {
+    /// The default SSH port.
+    const DEFAULT_PORT: u16 = 22;
+
+    /// Returns the host.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// Returns the port.
+    pub fn port(&self) -> u16 {
+        self.port.unwrap_or(Self::DEFAULT_PORT)
+    }
+
+    /// Returns whether the URI contains an explicit port.
+    pub fn has_port(&self) -> bool {
+        self.port.is_some()
+    }
+
+    /// Returns the source for an rsync command using a remote shell.
+    ///
+    /// This is of the form `[user@]host:path`.
+    pub fn rsync_source(&self) -> String {
+        match self.user.as_ref() {
+            Some(user) => format!("{}@{}:{}", user, self.host, self.path),
+            None => format!("{}:{}", self.host, self.path),
+        }
+    }
+}
+
+
+//--- FromStr
+
+impl FromStr for SshUri {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.strip_prefix("ssh://").ok_or("expected ssh:// URI")?;
+        let (authority, path) = match s.split_once('/') {
+            Some((authority, path)) => (authority, path),
+            None => (s, ""),
+        };
+        let (user, host) = match authority.rsplit_once('@') {
+            Some((user, host)) if !user.is_empty() => (Some(user.into()), host),
+            Some(_) => return Err("empty user name"),
+            None => (None, authority),
+        };
+        let (host, port) = match host.rsplit_once(':') {
+            Some((host, port)) if !port.contains(']') => {
+                (host, Some(u16::from_str(port).map_err(|_| "invalid port")?))
+            }
+            _ => (host, None)
+        };
+        if host.is_empty() {
+            return Err("missing host name")
+        }
+        let mut res = SshUri {
+            user, host: host.into(), port, path: format!("/{}", path)
+        };
+        if !res.path.ends_with('/') {
+            res.path.push('/')
+        }
+        Ok(res)
+    }
+}
+
+
+//--- Display
+
+impl fmt::Display for SshUri {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ssh://")?;
+        if let Some(user) = self.user.as_ref() {
+            write!(f, "{}@", user)?;
+        }
+        f.write_str(&self.host)?;
+        if let Some(port) = self.port {
+            write!(f, ":{}", port)?;
+        }
+        f.write_str(&self.path)
+    }
+}
+
+
+//============ Tests =========================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn uri_rewrite() {
+        let rewrite = UriRewrite::new(&[
+            (
+                "rsync://rpki.example.net/repo/".into(),
+                "rsync://mirror.lab/example/".into()
+            ),
+            (
+                "https://rrdp.example.net/".into(),
+                "https://mirror.lab/rrdp/".into()
+            ),
+            (
+                "https://rrdp.example.net/special/".into(),
+                "https://never.lab/".into()
+            ),
+        ]);
+        assert_eq!(
+            rewrite.rewrite("rsync://rpki.example.net/repo/ta.cer"),
+            "rsync://mirror.lab/example/ta.cer"
+        );
+        assert_eq!(
+            rewrite.rewrite("rsync://rpki.example.net/other/"),
+            "rsync://rpki.example.net/other/"
+        );
+        assert_eq!(
+            rewrite.rewrite("https://rrdp.example.net/special/notify.xml"),
+            "https://mirror.lab/rrdp/special/notify.xml"
+        );
+
+        assert!(UriRewrite::check_rule(
+            "rsync://rpki.example.net/", "rsync://mirror.lab/"
+        ).is_ok());
+        assert!(UriRewrite::check_rule(
+            "rsync://rpki.example.net/", "https://mirror.lab/"
+        ).is_err());
+        assert!(UriRewrite::check_rule(
+            "ftp://rpki.example.net/", "ftp://mirror.lab/"
+        ).is_err());
+        assert!(UriRewrite::check_rule("https://", "https://lab/").is_err());
+    }
+
+    #[test]
+    fn ssh_uri() {
+        let uri = SshUri::from_str(
+            "ssh://rpki@repo.example.net:2222/srv/rpki/repo"
+        ).unwrap();
+        assert_eq!(uri.host(), "repo.example.net");
+        assert_eq!(uri.port(), 2222);
+        assert_eq!(uri.rsync_source(), "rpki@repo.example.net:/srv/rpki/repo/");
+        assert_eq!(
+            uri.to_string(), "ssh://rpki@repo.example.net:2222/srv/rpki/repo/"
+        );
+
+        let uri = SshUri::from_str("ssh://[2001:db8::1]/repo/").unwrap();
+        assert_eq!(uri.host(), "[2001:db8::1]");
+        assert_eq!(uri.port(), 22);
+        assert!(!uri.has_port());
+        assert_eq!(uri.rsync_source(), "[2001:db8::1]:/repo/");
+
+        assert!(SshUri::from_str("rsync://repo.example.net/repo/").is_err());
+        assert!(SshUri::from_str("ssh:///repo/").is_err());
+        assert!(SshUri::from_str("ssh://@host/repo/").is_err());
+        assert!(SshUri::from_str("ssh://host:x/repo/").is_err());
+    }
+}